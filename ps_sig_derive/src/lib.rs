@@ -0,0 +1,148 @@
+// `#[derive(Credential)]` for structs of attributes, implementing `ps_sig::credential_struct::CredentialStruct`.
+// Each named field is a message, at the index of its position in the struct. A field is encoded with
+// `ps_sig::credential_struct::ToMessage` unless it has `#[encoding(..)]`: `unsigned` and `signed` for the integer
+// messages of `ps_sig::integer_message`, `date` for `TypedMessage::Date` and `text` for `TypedMessage::Text` of any
+// `ToString`. `#[non_redactable]` fields are disclosed in every presentation and the `#[link_secret]` field, if
+// any, is never disclosed and must be the first field, the index of the link secret of blind issuance.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Meta, NestedMeta};
+
+#[proc_macro_derive(Credential, attributes(non_redactable, link_secret, encoding))]
+pub fn derive_credential(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+enum Encoding {
+    Default,
+    Unsigned,
+    Signed,
+    Date,
+    Text,
+}
+
+struct FieldAttrs {
+    non_redactable: bool,
+    link_secret: bool,
+    encoding: Encoding,
+}
+
+fn field_attrs(field: &syn::Field) -> Result<FieldAttrs, Error> {
+    let mut attrs = FieldAttrs {
+        non_redactable: false,
+        link_secret: false,
+        encoding: Encoding::Default,
+    };
+    for attr in &field.attrs {
+        if attr.path.is_ident("non_redactable") {
+            attrs.non_redactable = true;
+        } else if attr.path.is_ident("link_secret") {
+            attrs.link_secret = true;
+        } else if attr.path.is_ident("encoding") {
+            let nested = match attr.parse_meta()? {
+                Meta::List(list) if list.nested.len() == 1 => list.nested,
+                meta => {
+                    return Err(Error::new_spanned(
+                        meta,
+                        "expected #[encoding(unsigned | signed | date | text)]",
+                    ))
+                }
+            };
+            attrs.encoding = match &nested[0] {
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("unsigned") => Encoding::Unsigned,
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("signed") => Encoding::Signed,
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("date") => Encoding::Date,
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("text") => Encoding::Text,
+                other => {
+                    return Err(Error::new_spanned(
+                        other,
+                        "unknown encoding, expected unsigned, signed, date or text",
+                    ))
+                }
+            };
+        }
+    }
+    if attrs.link_secret && (attrs.non_redactable || !matches!(attrs.encoding, Encoding::Default)) {
+        return Err(Error::new_spanned(
+            field,
+            "a link secret is never disclosed and has no encoding",
+        ));
+    }
+    Ok(attrs)
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    input,
+                    "Credential can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                input,
+                "Credential can only be derived for structs",
+            ))
+        }
+    };
+    let mut specs = vec![];
+    let mut messages = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let attrs = field_attrs(field)?;
+        if attrs.link_secret && i != 0 {
+            return Err(Error::new_spanned(
+                field,
+                "the link secret must be the first field",
+            ));
+        }
+        let ident = field.ident.as_ref().expect("Named fields have idents");
+        let name = ident.to_string();
+        let non_redactable = attrs.non_redactable;
+        let link_secret = attrs.link_secret;
+        specs.push(quote! {
+            ::ps_sig::credential_struct::FieldSpec {
+                name: #name,
+                non_redactable: #non_redactable,
+                link_secret: #link_secret,
+            }
+        });
+        messages.push(match attrs.encoding {
+            Encoding::Default => {
+                quote! { ::ps_sig::credential_struct::ToMessage::to_message(&self.#ident) }
+            }
+            Encoding::Unsigned => {
+                quote! { ::ps_sig::integer_message::encode_unsigned(self.#ident) }
+            }
+            Encoding::Signed => quote! { ::ps_sig::integer_message::encode_signed(self.#ident) },
+            Encoding::Date => {
+                quote! { ::ps_sig::typed_message::TypedMessage::Date(self.#ident).encode() }
+            }
+            Encoding::Text => quote! {
+                ::ps_sig::typed_message::TypedMessage::Text(::std::string::ToString::to_string(&self.#ident)).encode()
+            },
+        });
+    }
+    let ty = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::ps_sig::credential_struct::CredentialStruct for #ty #ty_generics #where_clause {
+            const FIELDS: &'static [::ps_sig::credential_struct::FieldSpec] = &[#(#specs),*];
+
+            fn to_messages(&self) -> ::std::vec::Vec<::ps_sig::curve::FieldElement> {
+                ::std::vec![#(#messages),*]
+            }
+        }
+    })
+}
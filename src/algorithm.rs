@@ -0,0 +1,371 @@
+// Identifiers of the signature algorithms of this crate, for archives that verify artifacts long after they were
+// created. An id names the scheme, the curve, the group of signatures and the hash to the field and the groups,
+// e.g. "PS-2016-BLS12381-G2-SHAKE256", so a change of any of them gets a new id. An `Artifact` carries the id of
+// its algorithm in its serialization along with the key, signature and messages. `AlgorithmRegistry` maps ids to
+// the routines verifying them: a later version of the crate, or of an application, keeps the routines of older
+// variants registered under their ids next to the new ones, and artifacts with an unknown id fail with
+// `PSError::UnknownAlgorithm` rather than being verified with the wrong routine.
+// The groups are chosen at compile time, so the ids of the other group of signatures are defined but not verified
+// by the registry of a build.
+
+use crate::curve::{FieldElement, FieldElement_SIZE};
+use crate::errors::PSError;
+use crate::keys::{DeserializeLimits, PKrss, Params, Verkey};
+use crate::rss_signature::{RSSignature, RedactedSignature};
+use crate::signature::Signature;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AlgorithmId(pub &'static str);
+
+/// `Signature` of the 2016 scheme with signatures in G1
+pub const PS2016_G1: AlgorithmId = AlgorithmId("PS-2016-BLS12381-G1-SHAKE256");
+/// `Signature` of the 2016 scheme with signatures in G2
+pub const PS2016_G2: AlgorithmId = AlgorithmId("PS-2016-BLS12381-G2-SHAKE256");
+/// `RSSignature` over all messages with signatures in G1
+pub const RSS2020_G1: AlgorithmId = AlgorithmId("PS-RSS-2020-BLS12381-G1-SHAKE256");
+/// `RSSignature` over all messages with signatures in G2
+pub const RSS2020_G2: AlgorithmId = AlgorithmId("PS-RSS-2020-BLS12381-G2-SHAKE256");
+/// `RedactedSignature` with signatures in G1
+pub const RSS2020_REDACTED_G1: AlgorithmId =
+    AlgorithmId("PS-RSS-2020-REDACTED-BLS12381-G1-SHAKE256");
+/// `RedactedSignature` with signatures in G2
+pub const RSS2020_REDACTED_G2: AlgorithmId =
+    AlgorithmId("PS-RSS-2020-REDACTED-BLS12381-G2-SHAKE256");
+
+/// Ids of the algorithms of this build
+#[cfg(feature = "SignatureG2")]
+pub const PS2016: AlgorithmId = PS2016_G2;
+#[cfg(feature = "SignatureG2")]
+pub const RSS2020: AlgorithmId = RSS2020_G2;
+#[cfg(feature = "SignatureG2")]
+pub const RSS2020_REDACTED: AlgorithmId = RSS2020_REDACTED_G2;
+#[cfg(feature = "SignatureG1")]
+pub const PS2016: AlgorithmId = PS2016_G1;
+#[cfg(feature = "SignatureG1")]
+pub const RSS2020: AlgorithmId = RSS2020_G1;
+#[cfg(feature = "SignatureG1")]
+pub const RSS2020_REDACTED: AlgorithmId = RSS2020_REDACTED_G1;
+
+/// Signature with what is needed to verify it, tagged with the id of its algorithm
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub algorithm: String,
+    /// `to_bytes` of the `Verkey` or `PKrss`
+    pub public_key: Vec<u8>,
+    /// Label of the params of the verkey, empty for keys of the redactable signature scheme
+    pub params_label: Vec<u8>,
+    /// `to_bytes` of the signature
+    pub signature: Vec<u8>,
+    /// Signed messages by index, only the disclosed ones for a `RedactedSignature`
+    pub messages: BTreeMap<usize, FieldElement>,
+}
+
+/// Routine verifying artifacts of an algorithm
+pub type VerifyRoutine = fn(&Artifact) -> Result<bool, PSError>;
+
+#[derive(Clone, Debug)]
+pub struct AlgorithmRegistry {
+    routines: HashMap<String, VerifyRoutine>,
+}
+
+impl Artifact {
+    /// Artifact of `PS2016` for a signature on `messages` with a verkey of the params of `params_label`
+    pub fn signature(
+        sig: &Signature,
+        messages: &[FieldElement],
+        vk: &Verkey,
+        params_label: &[u8],
+    ) -> Self {
+        Self {
+            algorithm: PS2016.0.to_string(),
+            public_key: vk.to_bytes(),
+            params_label: params_label.to_vec(),
+            signature: sig.to_bytes(),
+            messages: messages.iter().cloned().enumerate().collect(),
+        }
+    }
+
+    /// Artifact of `RSS2020` for a signature on all `messages`
+    pub fn rss_signature(sig: &RSSignature, messages: &[FieldElement], pk: &PKrss) -> Self {
+        Self {
+            algorithm: RSS2020.0.to_string(),
+            public_key: pk.to_bytes(),
+            params_label: vec![],
+            signature: sig.to_bytes(),
+            messages: messages.iter().cloned().enumerate().collect(),
+        }
+    }
+
+    /// Artifact of `RSS2020_REDACTED` for a derived signature with its disclosed messages
+    pub fn redacted_signature(
+        sig: &RedactedSignature,
+        disclosed_msgs: &HashMap<usize, FieldElement>,
+        pk: &PKrss,
+    ) -> Self {
+        Self {
+            algorithm: RSS2020_REDACTED.0.to_string(),
+            public_key: pk.to_bytes(),
+            params_label: vec![],
+            signature: sig.to_bytes(),
+            messages: disclosed_msgs
+                .iter()
+                .map(|(i, m)| (*i, m.clone()))
+                .collect(),
+        }
+    }
+
+    /// Encoding as length of the id as 1 byte || id || public key, params label and signature each preceded by its
+    /// length as 8 bytes big endian || count of messages as 8 bytes big endian || (index as 8 bytes big endian ||
+    /// message) for each message in increasing order of index
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.algorithm.len() as u8];
+        bytes.extend_from_slice(self.algorithm.as_bytes());
+        for b in &[&self.public_key, &self.params_label, &self.signature] {
+            bytes.extend_from_slice(&(b.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(b);
+        }
+        bytes.extend_from_slice(&(self.messages.len() as u64).to_be_bytes());
+        for (i, m) in &self.messages {
+            bytes.extend_from_slice(&(*i as u64).to_be_bytes());
+            bytes.append(&mut m.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_bytes_with_limits(bytes, &DeserializeLimits::default())
+    }
+
+    pub fn from_bytes_with_limits(
+        bytes: &[u8],
+        limits: &DeserializeLimits,
+    ) -> Result<Self, PSError> {
+        limits.check_bytes(bytes)?;
+        let (id_len, rest) = split(bytes, 1)?;
+        let (id, mut rest) = split(rest, id_len[0] as usize)?;
+        let algorithm =
+            String::from_utf8(id.to_vec()).map_err(|e| PSError::DeserializationError {
+                msg: format!("{}", e),
+            })?;
+        let mut fields = Vec::with_capacity(3);
+        for _ in 0..3 {
+            let (len, r) = split_u64(rest)?;
+            let (field, r) = split(r, len)?;
+            fields.push(field.to_vec());
+            rest = r;
+        }
+        let (count, mut rest) = split_u64(rest)?;
+        limits.check_messages(count)?;
+        let mut messages = BTreeMap::new();
+        for _ in 0..count {
+            let (i, r) = split_u64(rest)?;
+            let (m, r) = split(r, FieldElement_SIZE)?;
+            let m = FieldElement::from_bytes(m).map_err(|e| PSError::DeserializationError {
+                msg: format!("{}", e),
+            })?;
+            messages.insert(i, m);
+            rest = r;
+        }
+        if !rest.is_empty() || messages.len() != count {
+            return Err(PSError::DeserializationError {
+                msg: String::from("Trailing bytes or repeated index in artifact"),
+            });
+        }
+        let signature = fields.pop().unwrap();
+        let params_label = fields.pop().unwrap();
+        let public_key = fields.pop().unwrap();
+        Ok(Self {
+            algorithm,
+            public_key,
+            params_label,
+            signature,
+            messages,
+        })
+    }
+}
+
+fn split(bytes: &[u8], len: usize) -> Result<(&[u8], &[u8]), PSError> {
+    if bytes.len() < len {
+        return Err(PSError::DeserializationError {
+            msg: format!("Artifact needs {} more bytes but has {}", len, bytes.len()),
+        });
+    }
+    Ok(bytes.split_at(len))
+}
+
+fn split_u64(bytes: &[u8]) -> Result<(usize, &[u8]), PSError> {
+    let (b, rest) = split(bytes, 8)?;
+    let mut be = [0; 8];
+    be.copy_from_slice(b);
+    let v = usize::try_from(u64::from_be_bytes(be)).map_err(|_| PSError::DeserializationError {
+        msg: String::from("Length does not fit in usize"),
+    })?;
+    Ok((v, rest))
+}
+
+/// Messages at indices 0..n, fails if an index is missing
+fn all_messages(artifact: &Artifact) -> Result<Vec<FieldElement>, PSError> {
+    if let Some((i, _)) = artifact
+        .messages
+        .iter()
+        .enumerate()
+        .find(|(j, (i, _))| *j != **i)
+    {
+        return Err(PSError::InvalidIndex {
+            idx: i,
+            valid: String::from("messages at every index"),
+        });
+    }
+    Ok(artifact.messages.values().cloned().collect())
+}
+
+fn verify_ps2016(artifact: &Artifact) -> Result<bool, PSError> {
+    let vk = Verkey::from_bytes(&artifact.public_key)?;
+    let sig = Signature::from_bytes(&artifact.signature)?;
+    sig.verify(
+        all_messages(artifact)?,
+        &vk,
+        &Params::new(&artifact.params_label),
+    )
+}
+
+fn verify_rss2020(artifact: &Artifact) -> Result<bool, PSError> {
+    let pk = PKrss::from_bytes(&artifact.public_key)?;
+    let sig = RSSignature::from_bytes(&artifact.signature)?;
+    sig.verify(&all_messages(artifact)?, &pk)
+}
+
+fn verify_rss2020_redacted(artifact: &Artifact) -> Result<bool, PSError> {
+    let pk = PKrss::from_bytes(&artifact.public_key)?;
+    let sig = RedactedSignature::from_bytes(&artifact.signature)?;
+    let disclosed_msgs = artifact
+        .messages
+        .iter()
+        .map(|(i, m)| (*i, m.clone()))
+        .collect();
+    sig.verify(&disclosed_msgs, &pk)
+}
+
+impl Default for AlgorithmRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlgorithmRegistry {
+    /// Registry with the algorithms of this build
+    pub fn new() -> Self {
+        let mut routines: HashMap<String, VerifyRoutine> = HashMap::new();
+        routines.insert(PS2016.0.to_string(), verify_ps2016);
+        routines.insert(RSS2020.0.to_string(), verify_rss2020);
+        routines.insert(RSS2020_REDACTED.0.to_string(), verify_rss2020_redacted);
+        Self { routines }
+    }
+
+    /// Registry without any algorithm
+    pub fn empty() -> Self {
+        Self {
+            routines: HashMap::new(),
+        }
+    }
+
+    /// Register `routine` for artifacts of `id`, e.g. of an older variant. Fails if `id` is already registered.
+    pub fn register(&mut self, id: &str, routine: VerifyRoutine) -> Result<(), PSError> {
+        if self.supports(id) {
+            return Err(PSError::GeneralError {
+                msg: format!("Algorithm {} is already registered", id),
+            });
+        }
+        self.routines.insert(id.to_string(), routine);
+        Ok(())
+    }
+
+    pub fn supports(&self, id: &str) -> bool {
+        self.routines.contains_key(id)
+    }
+
+    /// Ids of the registered algorithms in increasing order
+    pub fn algorithms(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self.routines.keys().map(|id| id.as_str()).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Verify `artifact` with the routine of its algorithm. Fails with `PSError::UnknownAlgorithm` if none is
+    /// registered.
+    pub fn verify(&self, artifact: &Artifact) -> Result<bool, PSError> {
+        match self.routines.get(&artifact.algorithm) {
+            Some(routine) => routine(artifact),
+            None => Err(PSError::UnknownAlgorithm {
+                id: artifact.algorithm.clone(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::{keygen, rsskeygen};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_algorithm_registry() {
+        let registry = AlgorithmRegistry::new();
+        let label = "test".as_bytes();
+        let params = Params::new(label);
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(3).into();
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        let artifact = Artifact::signature(&sig, &msgs, &vk, label);
+        let bytes = artifact.to_bytes();
+        assert_eq!(&bytes[1..1 + PS2016.0.len()], PS2016.0.as_bytes());
+        let decoded = Artifact::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, artifact);
+        assert!(registry.verify(&decoded).unwrap());
+        assert!(Artifact::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let (sk, pk) = rsskeygen(4, &params).unwrap();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(4).into();
+        let sig = RSSignature::new(&msgs, &sk, &params).unwrap();
+        let artifact = Artifact::rss_signature(&sig, &msgs, &pk);
+        assert!(registry
+            .verify(&Artifact::from_bytes(&artifact.to_bytes()).unwrap())
+            .unwrap());
+        let disclosed: HashSet<usize> = vec![1, 3].into_iter().collect();
+        let derived = sig.derive(&msgs, &disclosed, &pk).unwrap();
+        let disclosed_msgs = disclosed.iter().map(|i| (*i, msgs[*i].clone())).collect();
+        let redacted = Artifact::redacted_signature(&derived, &disclosed_msgs, &pk);
+        assert!(registry.verify(&redacted).unwrap());
+
+        // A signature over all messages needs all of them
+        let mut missing = artifact.clone();
+        missing.messages.remove(&0);
+        assert!(registry.verify(&missing).is_err());
+        let mut tampered = redacted.clone();
+        tampered.messages.insert(1, FieldElement::random());
+        assert!(!registry.verify(&tampered).unwrap());
+
+        // Routines are chosen by id
+        let mut wrong_id = redacted.clone();
+        wrong_id.algorithm = RSS2020.0.to_string();
+        assert!(registry.verify(&wrong_id).is_err());
+        let mut old = redacted.clone();
+        old.algorithm = String::from("PS-RSS-2019-DRAFT");
+        match registry.verify(&old) {
+            Err(PSError::UnknownAlgorithm { id }) => assert_eq!(id, "PS-RSS-2019-DRAFT"),
+            r => panic!("Expected UnknownAlgorithm but got {:?}", r),
+        }
+        let mut archive = AlgorithmRegistry::new();
+        archive
+            .register("PS-RSS-2019-DRAFT", verify_rss2020_redacted)
+            .unwrap();
+        assert!(archive.verify(&old).unwrap());
+        assert!(archive.register(PS2016.0, verify_ps2016).is_err());
+        assert_eq!(archive.algorithms().len(), 4);
+        assert!(AlgorithmRegistry::empty().verify(&redacted).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+// Anonymous tokens, like Privacy Pass, with signatures of the 2016 scheme on a single message. The message is a
+// secret of the user which the issuer blind-signs as a link secret, see `link_secret`, so the issuer never sees it.
+// To redeem a token in a scope, e.g. a service or an epoch, the user sends a proof of knowledge of the signature
+// with the secret hidden and the tag H(scope)^secret with a proof that its exponent is the signed secret. The
+// secret shares the blinding of the proof of knowledge of signature, so the response for it proves both.
+// Redemptions of different tokens and of one token in different scopes are unlinkable. A token redeemed twice in a
+// scope gives the same tag and the verifier rejects it with `PSError::TokenAlreadySpent`, so a user can redeem a
+// token once per scope: with scopes per epoch, a user with k tokens is limited to k redemptions per epoch. Tags are
+// checked to be in the prime order group, else adding a point of small order to the tag would give other tags of the
+// same token that verify for some blindings.
+//
+// Issuance:
+//   1. The user calls `PendingToken::new` with a nonce from the issuer and sends the `LinkSecretCommitment`.
+//   2. The issuer replies with `TokenIssuer::issue`.
+//   3. The user gets the `Token` with `PendingToken::finish`.
+
+use crate::blind_signature::BlindingKey;
+use crate::challenge_domain::ANON_TOKEN;
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{keygen, Params, Sigkey, Verkey};
+use crate::link_secret::{LinkSecret, LinkSecretCommitment};
+use crate::pok_sig::PoKOfSignatureProof;
+use crate::scalar::Blinding;
+use crate::signature::{check_subgroup, Signature};
+use crate::VerkeyGroup;
+use std::collections::{HashMap, HashSet};
+
+const SCOPE_DOMAIN: &[u8] = b"PS token scope : ";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TokenIssuer {
+    sigkey: Sigkey,
+    blinding_key: BlindingKey,
+    verkey: Verkey,
+}
+
+/// Secret of a requested token and the blinding of its commitment, kept by the user until the issuer replies
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingToken {
+    secret: LinkSecret,
+    blinding: Blinding,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Token {
+    secret: LinkSecret,
+    signature: Signature,
+}
+
+/// Sent by the user to redeem a token in `scope`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Redemption {
+    pub scope: Vec<u8>,
+    /// H(scope)^secret
+    pub tag: VerkeyGroup,
+    /// H(scope)^blinding of the secret
+    pub tag_commitment: VerkeyGroup,
+    pub proof: PoKOfSignatureProof,
+}
+
+/// Tags of the redeemed tokens of each scope
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TokenVerifier {
+    spent: HashMap<Vec<u8>, HashSet<Vec<u8>>>,
+}
+
+fn scope_base(scope: &[u8]) -> VerkeyGroup {
+    VerkeyGroup::from_msg_hash(&[SCOPE_DOMAIN, scope].concat())
+}
+
+fn redemption_challenge(
+    bytes_for_challenge: Vec<u8>,
+    tag: &VerkeyGroup,
+    tag_commitment: &VerkeyGroup,
+    scope: &[u8],
+    nonce: &[u8],
+) -> FieldElement {
+    let mut bytes = ANON_TOKEN.tag();
+    bytes.extend_from_slice(&(scope.len() as u64).to_be_bytes());
+    bytes.extend_from_slice(scope);
+    bytes.extend(bytes_for_challenge);
+    bytes.append(&mut tag.to_bytes());
+    bytes.append(&mut tag_commitment.to_bytes());
+    bytes.extend_from_slice(nonce);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl TokenIssuer {
+    pub fn new(params: &Params) -> Result<Self, PSError> {
+        let (sigkey, verkey) = keygen(1, params)?;
+        let blinding_key = BlindingKey::new(&sigkey, params);
+        Ok(Self {
+            sigkey,
+            blinding_key,
+            verkey,
+        })
+    }
+
+    /// Verkey to verify redemptions with
+    pub fn verkey(&self) -> &Verkey {
+        &self.verkey
+    }
+
+    /// Key for users to request tokens with
+    pub fn blinding_key(&self) -> &BlindingKey {
+        &self.blinding_key
+    }
+
+    /// Blind signature for the request of a user, `nonce` is the one given to the user for the request
+    pub fn issue(
+        &self,
+        request: &LinkSecretCommitment,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<Signature, PSError> {
+        request.sign::<FieldElement>(&[], &self.sigkey, &self.blinding_key, params, nonce)
+    }
+}
+
+impl PendingToken {
+    /// New token secret and the request to send to the issuer
+    pub fn new(
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> (Self, LinkSecretCommitment) {
+        let secret = LinkSecret::new();
+        let (request, blinding) = secret.commit(blinding_key, params, nonce);
+        (Self { secret, blinding }, request)
+    }
+
+    /// Unblind the signature of the issuer, fails if it is not a signature on the secret
+    pub fn finish(self, sig: &Signature, vk: &Verkey, params: &Params) -> Result<Token, PSError> {
+        let signature =
+            self.secret
+                .unblind::<_, FieldElement>(sig, &self.blinding, &[], vk, params)?;
+        Ok(Token {
+            secret: self.secret,
+            signature,
+        })
+    }
+}
+
+impl Token {
+    /// Redeem the token in `scope` for a verifier who sent `nonce`
+    pub fn redeem(
+        &self,
+        scope: &[u8],
+        vk: &Verkey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<Redemption, PSError> {
+        let blinding = Blinding::random();
+        let pok = self.secret.init_pok::<FieldElement>(
+            &self.signature,
+            vk,
+            params,
+            &[],
+            Some(std::slice::from_ref(&blinding)),
+            HashSet::new(),
+        )?;
+        let base = scope_base(scope);
+        let tag = &base * self.secret.secret();
+        let tag_commitment = &base * blinding.as_field_element();
+        let challenge = redemption_challenge(pok.to_bytes(), &tag, &tag_commitment, scope, nonce);
+        Ok(Redemption {
+            scope: scope.to_vec(),
+            tag,
+            tag_commitment,
+            proof: pok.gen_proof(&challenge)?,
+        })
+    }
+}
+
+impl Redemption {
+    /// Verify the proof of knowledge of a token signed by `vk` and that `tag` is for its secret. Does not check
+    /// for double spending, see `TokenVerifier`. Fails with `PSError::InvalidGroupElement` if `tag` or
+    /// `tag_commitment` is outside the prime order group.
+    pub fn verify(&self, vk: &Verkey, params: &Params, nonce: &[u8]) -> Result<bool, PSError> {
+        check_subgroup(&[&self.tag, &self.tag_commitment])?;
+        if self.tag.is_identity() {
+            return Ok(false);
+        }
+        let challenge = redemption_challenge(
+            self.proof
+                .get_bytes_for_challenge(HashSet::new(), vk, params),
+            &self.tag,
+            &self.tag_commitment,
+            &self.scope,
+            nonce,
+        );
+        if !self
+            .proof
+            .verify_with_link_secret(vk, params, HashMap::new(), &challenge)?
+        {
+            return Ok(false);
+        }
+        // H(scope)^response * tag^challenge == tag_commitment
+        let response = self.proof.get_resp_for_link_secret()?;
+        Ok(
+            scope_base(&self.scope).binary_scalar_mul(&self.tag, &response, &challenge)
+                == self.tag_commitment,
+        )
+    }
+}
+
+impl TokenVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept a redemption in `scope` with `nonce`. Returns false if it does not verify and fails with
+    /// `PSError::TokenAlreadySpent` if a redemption of the same token in `scope` was accepted before.
+    pub fn accept(
+        &mut self,
+        redemption: &Redemption,
+        scope: &[u8],
+        vk: &Verkey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        if redemption.scope != scope || !redemption.verify(vk, params, nonce)? {
+            return Ok(false);
+        }
+        let tags = self.spent.entry(scope.to_vec()).or_default();
+        if !tags.insert(redemption.tag.to_bytes()) {
+            return Err(PSError::TokenAlreadySpent);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(issuer: &TokenIssuer, params: &Params) -> Token {
+        let nonce = FieldElement::random().to_bytes();
+        let (pending, request) = PendingToken::new(issuer.blinding_key(), params, &nonce);
+        let sig = issuer.issue(&request, params, &nonce).unwrap();
+        pending.finish(&sig, issuer.verkey(), params).unwrap()
+    }
+
+    #[test]
+    fn test_anonymous_tokens() {
+        let params = Params::new("test".as_bytes());
+        let issuer = TokenIssuer::new(&params).unwrap();
+        let vk = issuer.verkey();
+        let token_1 = token(&issuer, &params);
+        let token_2 = token(&issuer, &params);
+        let mut verifier = TokenVerifier::new();
+        let (epoch_1, epoch_2) = (b"epoch 1".to_vec(), b"epoch 2".to_vec());
+
+        let nonce = FieldElement::random().to_bytes();
+        let r_1 = token_1.redeem(&epoch_1, vk, &params, &nonce).unwrap();
+        assert!(verifier
+            .accept(&r_1, &epoch_1, vk, &params, &nonce)
+            .unwrap());
+        let r_2 = token_2.redeem(&epoch_1, vk, &params, &nonce).unwrap();
+        assert!(verifier
+            .accept(&r_2, &epoch_1, vk, &params, &nonce)
+            .unwrap());
+        assert_ne!(r_1.tag, r_2.tag);
+
+        // Double spending in the same scope
+        let nonce_1 = FieldElement::random().to_bytes();
+        let r_3 = token_1.redeem(&epoch_1, vk, &params, &nonce_1).unwrap();
+        assert_eq!(r_3.tag, r_1.tag);
+        match verifier.accept(&r_3, &epoch_1, vk, &params, &nonce_1) {
+            Err(PSError::TokenAlreadySpent) => (),
+            r => panic!("Expected TokenAlreadySpent but got {:?}", r),
+        }
+        // but not in another scope where the tag differs
+        let r_4 = token_1.redeem(&epoch_2, vk, &params, &nonce_1).unwrap();
+        assert_ne!(r_4.tag, r_1.tag);
+        assert!(verifier
+            .accept(&r_4, &epoch_2, vk, &params, &nonce_1)
+            .unwrap());
+
+        // Redemption for another scope, nonce or with a tag not of the secret
+        let r_5 = token_2.redeem(&epoch_2, vk, &params, &nonce).unwrap();
+        assert!(!verifier
+            .accept(&r_5, &epoch_1, vk, &params, &nonce)
+            .unwrap());
+        assert!(!r_5.verify(vk, &params, &nonce_1).unwrap());
+        let mut wrong_tag = r_5.clone();
+        wrong_tag.tag = r_4.tag.clone();
+        assert!(!wrong_tag.verify(vk, &params, &nonce).unwrap());
+        assert!(verifier
+            .accept(&r_5, &epoch_2, vk, &params, &nonce)
+            .unwrap());
+
+        // Token of another issuer
+        let other = TokenIssuer::new(&params).unwrap();
+        let r_6 = token(&other, &params)
+            .redeem(&epoch_2, vk, &params, &nonce)
+            .unwrap();
+        assert!(!r_6.verify(vk, &params, &nonce).unwrap());
+    }
+
+    // The point of small order is of G1
+    #[cfg(feature = "SignatureG2")]
+    #[test]
+    fn test_tag_outside_group_rejected() {
+        let params = Params::new("test".as_bytes());
+        let issuer = TokenIssuer::new(&params).unwrap();
+        let vk = issuer.verkey();
+        let nonce = FieldElement::random().to_bytes();
+        let r = token(&issuer, &params)
+            .redeem(b"epoch", vk, &params, &nonce)
+            .unwrap();
+
+        // Point on the curve (x = 5) but outside the prime order subgroup of G1
+        let mut small_order = vec![0u8; crate::curve::GroupG1_SIZE];
+        small_order[0] = 2;
+        small_order[crate::curve::MODBYTES] = 5;
+        let small_order = VerkeyGroup::from_bytes(&small_order).unwrap();
+        let mut shifted_tag = r.clone();
+        shifted_tag.tag = &r.tag + &small_order;
+        let mut shifted_commitment = r.clone();
+        shifted_commitment.tag_commitment = &r.tag_commitment + &small_order;
+        for s in &[shifted_tag, shifted_commitment] {
+            match TokenVerifier::new().accept(s, b"epoch", vk, &params, &nonce) {
+                Err(PSError::InvalidGroupElement) => (),
+                r => panic!("Expected InvalidGroupElement but got {:?}", r),
+            }
+        }
+
+        let mut identity = r;
+        identity.tag = VerkeyGroup::identity();
+        assert!(!identity.verify(vk, &params, &nonce).unwrap());
+    }
+}
@@ -0,0 +1,313 @@
+// Conversion of field and group elements to the types of arkworks' BLS12-381 and back, behind the feature
+// `ark-bridge`, so that credentials of this crate can be used in SNARK circuits built with arkworks, e.g. to prove
+// possession of a signature. Both are the same curve with the same generators, elements are converted through their
+// bytes: a `FieldElement` is 48 bytes big endian of a value below the order, an `Fr` 32, and G1 and G2 are the
+// uncompressed affine coordinates, x || y after the prefix 0x04 in amcl_wrapper and without flags in arkworks. For G2
+// amcl_wrapper has the real part of each coordinate first and arkworks the imaginary one. Elements from arkworks are
+// read as elements from outside with the subgroup checks of `group_elem_from_bytes`, and elements of amcl_wrapper are
+// validated by arkworks, so that no conversion yields a point outside the prime order group. The identity, which has
+// no affine coordinates, is converted on its own. Signatures, verkeys and params convert element by element to
+// `ArkSignature`, `ArkVerkey` and `ArkParams`, in the groups of arkworks of the build's groups of signatures and
+// verkeys. Verkeys from arkworks are checked with `Verkey::sanity_check` and params with the generators of their label
+// hash, as when deserializing.
+
+use crate::curve::{FieldElement, FieldElement_SIZE, GroupElement, G1, G2, MODBYTES};
+use crate::errors::PSError;
+use crate::keys::{Params, ParamsFields, Verkey};
+use crate::signature::{group_elem_from_bytes, Signature};
+use crate::{SignatureGroup, VerkeyGroup};
+use ark_bls12_381::{Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::convert::TryFrom;
+
+/// Group of arkworks of `SignatureGroup`
+#[cfg(feature = "SignatureG2")]
+pub type ArkSignatureGroup = G2Affine;
+/// Group of arkworks of `VerkeyGroup`
+#[cfg(feature = "SignatureG2")]
+pub type ArkVerkeyGroup = G1Affine;
+/// Group of arkworks of `SignatureGroup`
+#[cfg(feature = "SignatureG1")]
+pub type ArkSignatureGroup = G1Affine;
+/// Group of arkworks of `VerkeyGroup`
+#[cfg(feature = "SignatureG1")]
+pub type ArkVerkeyGroup = G2Affine;
+
+/// `Signature` in arkworks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArkSignature {
+    pub sigma_1: ArkSignatureGroup,
+    pub sigma_2: ArkSignatureGroup,
+}
+
+/// `Verkey` in arkworks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArkVerkey {
+    pub X_tilde: ArkVerkeyGroup,
+    pub Y_tilde: Vec<ArkVerkeyGroup>,
+    pub params_label_hash: Fr,
+}
+
+/// `Params` in arkworks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArkParams {
+    pub g: ArkSignatureGroup,
+    pub g_tilde: ArkVerkeyGroup,
+    pub label_hash: Fr,
+}
+
+/// Value of arkworks of the same element
+pub trait ArkConvert: Sized {
+    type Ark;
+
+    fn to_ark(&self) -> Result<Self::Ark, PSError>;
+
+    fn from_ark(ark: &Self::Ark) -> Result<Self, PSError>;
+}
+
+impl ArkConvert for FieldElement {
+    type Ark = Fr;
+
+    fn to_ark(&self) -> Result<Fr, PSError> {
+        // Below the order, so not reduced
+        Ok(Fr::from_be_bytes_mod_order(&self.to_bytes()))
+    }
+
+    fn from_ark(ark: &Fr) -> Result<Self, PSError> {
+        let value = ark.into_bigint().to_bytes_be();
+        let mut bytes = vec![0u8; FieldElement_SIZE - value.len()];
+        bytes.extend_from_slice(&value);
+        FieldElement::from_bytes(&bytes).map_err(|e| PSError::DeserializationError {
+            msg: format!("{}", e),
+        })
+    }
+}
+
+impl ArkConvert for G1 {
+    type Ark = G1Affine;
+
+    fn to_ark(&self) -> Result<G1Affine, PSError> {
+        if self.is_identity() {
+            return Ok(G1Affine::zero());
+        }
+        // Without the prefix 0x04
+        ark_from_bytes(&self.to_bytes()[1..])
+    }
+
+    fn from_ark(ark: &G1Affine) -> Result<Self, PSError> {
+        if ark.is_zero() {
+            return Ok(G1::identity());
+        }
+        let mut bytes = vec![0x04];
+        bytes.append(&mut ark_to_bytes(ark)?);
+        group_elem_from_bytes(&bytes)
+    }
+}
+
+impl ArkConvert for G2 {
+    type Ark = G2Affine;
+
+    fn to_ark(&self) -> Result<G2Affine, PSError> {
+        if self.is_identity() {
+            return Ok(G2Affine::zero());
+        }
+        ark_from_bytes(&swap_fp2_halves(&self.to_bytes()))
+    }
+
+    fn from_ark(ark: &G2Affine) -> Result<Self, PSError> {
+        if ark.is_zero() {
+            return Ok(G2::identity());
+        }
+        group_elem_from_bytes(&swap_fp2_halves(&ark_to_bytes(ark)?))
+    }
+}
+
+impl ArkConvert for Signature {
+    type Ark = ArkSignature;
+
+    fn to_ark(&self) -> Result<ArkSignature, PSError> {
+        Ok(ArkSignature {
+            sigma_1: self.sigma_1.to_ark()?,
+            sigma_2: self.sigma_2.to_ark()?,
+        })
+    }
+
+    fn from_ark(ark: &ArkSignature) -> Result<Self, PSError> {
+        Ok(Self {
+            sigma_1: SignatureGroup::from_ark(&ark.sigma_1)?,
+            sigma_2: SignatureGroup::from_ark(&ark.sigma_2)?,
+        })
+    }
+}
+
+impl ArkConvert for Verkey {
+    type Ark = ArkVerkey;
+
+    fn to_ark(&self) -> Result<ArkVerkey, PSError> {
+        Ok(ArkVerkey {
+            X_tilde: self.X_tilde.to_ark()?,
+            Y_tilde: self
+                .Y_tilde
+                .iter()
+                .map(|y| y.to_ark())
+                .collect::<Result<_, _>>()?,
+            params_label_hash: self.params_label_hash.to_ark()?,
+        })
+    }
+
+    /// Fails like `Verkey::sanity_check`
+    fn from_ark(ark: &ArkVerkey) -> Result<Self, PSError> {
+        let vk = Self {
+            X_tilde: VerkeyGroup::from_ark(&ark.X_tilde)?,
+            Y_tilde: ark
+                .Y_tilde
+                .iter()
+                .map(VerkeyGroup::from_ark)
+                .collect::<Result<_, _>>()?,
+            params_label_hash: FieldElement::from_ark(&ark.params_label_hash)?,
+        };
+        vk.sanity_check()?;
+        Ok(vk)
+    }
+}
+
+impl ArkConvert for Params {
+    type Ark = ArkParams;
+
+    fn to_ark(&self) -> Result<ArkParams, PSError> {
+        Ok(ArkParams {
+            g: self.g.to_ark()?,
+            g_tilde: self.g_tilde.to_ark()?,
+            label_hash: self.label_hash.to_ark()?,
+        })
+    }
+
+    /// Fails with `PSError::DeserializationError` if the generators are not those of the label hash
+    fn from_ark(ark: &ArkParams) -> Result<Self, PSError> {
+        Params::try_from(ParamsFields {
+            g: SignatureGroup::from_ark(&ark.g)?,
+            g_tilde: VerkeyGroup::from_ark(&ark.g_tilde)?,
+            label_hash: FieldElement::from_ark(&ark.label_hash)?,
+        })
+    }
+}
+
+/// x and y of G2 with the 2 halves of each swapped, from real part first to imaginary part first and back
+fn swap_fp2_halves(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .chunks(2 * MODBYTES)
+        .flat_map(|c| [&c[MODBYTES..], &c[..MODBYTES]].concat())
+        .collect()
+}
+
+fn ark_from_bytes<A: CanonicalDeserialize>(bytes: &[u8]) -> Result<A, PSError> {
+    A::deserialize_uncompressed(bytes).map_err(|_| PSError::InvalidGroupElement)
+}
+
+fn ark_to_bytes<A: CanonicalSerialize>(ark: &A) -> Result<Vec<u8>, PSError> {
+    let mut bytes = vec![];
+    ark.serialize_uncompressed(&mut bytes)
+        .map_err(|e| PSError::GeneralError {
+            msg: format!("Serialization of arkworks element failed: {}", e),
+        })?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use ark_ec::CurveGroup;
+
+    #[test]
+    fn test_field_element_round_trip() {
+        for x in &[
+            FieldElement::zero(),
+            FieldElement::one(),
+            FieldElement::random(),
+            -FieldElement::one(),
+        ] {
+            let ark = x.to_ark().unwrap();
+            assert_eq!(&FieldElement::from_ark(&ark).unwrap(), x);
+        }
+        assert_eq!(FieldElement::one().to_ark().unwrap(), Fr::from(1u64));
+        assert_eq!((-FieldElement::one()).to_ark().unwrap(), -Fr::from(1u64));
+    }
+
+    #[test]
+    fn test_generators() {
+        // Both have the standard generators of BLS12-381
+        assert_eq!(G1::generator().to_ark().unwrap(), G1Affine::generator());
+        assert_eq!(G2::generator().to_ark().unwrap(), G2Affine::generator());
+    }
+
+    #[test]
+    fn test_g1_round_trip() {
+        let x = FieldElement::random();
+        let p = G1::from_msg_hash(b"p");
+        let p_x = &p * &x;
+        assert_eq!(G1::from_ark(&p.to_ark().unwrap()).unwrap(), p);
+        assert_eq!(
+            (p.to_ark().unwrap() * x.to_ark().unwrap()).into_affine(),
+            p_x.to_ark().unwrap()
+        );
+        assert_eq!(G1::identity().to_ark().unwrap(), G1Affine::zero());
+        assert_eq!(G1::from_ark(&G1Affine::zero()).unwrap(), G1::identity());
+    }
+
+    #[test]
+    fn test_g2_round_trip() {
+        let x = FieldElement::random();
+        let p = G2::from_msg_hash(b"p");
+        let p_x = &p * &x;
+        assert_eq!(G2::from_ark(&p.to_ark().unwrap()).unwrap(), p);
+        assert_eq!(
+            (p.to_ark().unwrap() * x.to_ark().unwrap()).into_affine(),
+            p_x.to_ark().unwrap()
+        );
+        assert_eq!(G2::identity().to_ark().unwrap(), G2Affine::zero());
+        assert_eq!(G2::from_ark(&G2Affine::zero()).unwrap(), G2::identity());
+    }
+
+    #[test]
+    fn test_signature_keys_and_params_round_trip() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let msgs: Vec<FieldElement> = (0..3).map(|_| FieldElement::random()).collect();
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+
+        let params_1 = Params::from_ark(&params.to_ark().unwrap()).unwrap();
+        assert_eq!(params_1.g, params.g);
+        assert_eq!(params_1.g_tilde, params.g_tilde);
+        assert_eq!(params_1.label_hash, params.label_hash);
+        let vk_1 = Verkey::from_ark(&vk.to_ark().unwrap()).unwrap();
+        assert_eq!(vk_1.to_bytes(), vk.to_bytes());
+        let sig_1 = Signature::from_ark(&sig.to_ark().unwrap()).unwrap();
+        assert_eq!(sig_1.to_bytes(), sig.to_bytes());
+        assert!(sig_1.verify(msgs, &vk_1, &params_1).unwrap());
+
+        // Elements are converted one by one
+        assert_eq!(sig.to_ark().unwrap().sigma_1, sig.sigma_1.to_ark().unwrap());
+        assert_eq!(vk.to_ark().unwrap().Y_tilde.len(), 3);
+    }
+
+    #[test]
+    fn test_invalid_keys_and_params_from_ark() {
+        let params = Params::new("test".as_bytes());
+        let (_, vk) = keygen(3, &params).unwrap();
+        let mut ark_vk = vk.to_ark().unwrap();
+        ark_vk.Y_tilde[2] = ark_vk.Y_tilde[0];
+        match Verkey::from_ark(&ark_vk) {
+            Err(PSError::WeakKey { .. }) => (),
+            r => panic!("Expected WeakKey but got {:?}", r),
+        }
+        let mut ark_params = params.to_ark().unwrap();
+        ark_params.label_hash = Fr::from(1u64);
+        match Params::from_ark(&ark_params) {
+            Err(PSError::DeserializationError { .. }) => (),
+            r => panic!("Expected DeserializationError but got {:?}", r),
+        }
+    }
+}
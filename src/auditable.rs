@@ -0,0 +1,124 @@
+// Signing with auditable randomness. With `Signature::new` the signer picks the randomness u of sigma_1 = g^u freely
+// so it could embed a covert channel in it, e.g. leak bits of its signing key to someone observing signatures.
+// In this mode the randomness is derived as u = H(k || messages) from a nonce key k which the signer commits to with
+// g^k before signing. sigma_1 is the commitment to the randomness of each signature. To audit, the signer hands the
+// nonce key to the auditor, who checks it against the commitment and recomputes sigma_1 of every signature, so the
+// signer had no choice over the randomness once it committed. A revealed nonce key should not be used again since
+// anyone holding it can predict sigma_1 of future signatures.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey};
+use crate::signature::Signature;
+use crate::SignatureGroup;
+
+const NONCE_DOMAIN: &[u8] = b"PS signing nonce : ";
+
+/// Secret from which the randomness of the signatures is derived
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NonceKey {
+    key: FieldElement,
+}
+
+/// Commitment g^k to the nonce key k, published before signing
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NonceKeyCommitment {
+    pub commitment: SignatureGroup,
+}
+
+impl Default for NonceKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NonceKey {
+    pub fn new() -> Self {
+        Self {
+            key: FieldElement::random(),
+        }
+    }
+
+    pub fn commitment(&self, params: &Params) -> NonceKeyCommitment {
+        NonceKeyCommitment {
+            commitment: &params.g * &self.key,
+        }
+    }
+
+    /// Randomness u = H(k || messages) of the signature over `messages`
+    pub fn nonce(&self, messages: &[FieldElement]) -> FieldElement {
+        let mut bytes = NONCE_DOMAIN.to_vec();
+        bytes.append(&mut self.key.to_bytes());
+        for m in messages {
+            bytes.append(&mut m.to_bytes());
+        }
+        FieldElement::from_msg_hash(&bytes)
+    }
+
+    /// Sign like `Signature::new` but with the randomness derived from the nonce key. Signatures over the same
+    /// messages with the same keys are equal.
+    pub fn sign(
+        &self,
+        messages: &[FieldElement],
+        sigkey: &Sigkey,
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        Signature::check_sigkey_and_messages_compat(messages, sigkey)?;
+        let u = self.nonce(messages);
+        let (sigma_1, sigma_2) = Signature::sign_with_sigma_1_generated_from_given_exp(
+            messages, sigkey, &u, 0, &params.g,
+        )?;
+        Ok(Signature { sigma_1, sigma_2 })
+    }
+}
+
+impl NonceKeyCommitment {
+    /// Check that `nonce_key` is the committed one and that the randomness of `sig` was derived from it.
+    /// Does not verify the signature itself.
+    pub fn audit(
+        &self,
+        nonce_key: &NonceKey,
+        sig: &Signature,
+        messages: &[FieldElement],
+        params: &Params,
+    ) -> bool {
+        if nonce_key.commitment(params) != *self {
+            return false;
+        }
+        sig.sigma_1 == &params.g * &nonce_key.nonce(messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_auditable_signature() {
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let nonce_key = NonceKey::new();
+        let commitment = nonce_key.commitment(&params);
+
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = nonce_key.sign(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(sig.verify(msgs.clone().into(), &vk, &params).unwrap());
+        assert!(commitment.audit(&nonce_key, &sig, msgs.as_slice(), &params));
+
+        // Signature with randomness of the signer's choosing fails the audit
+        let sig_1 = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(!commitment.audit(&nonce_key, &sig_1, msgs.as_slice(), &params));
+
+        // Nonce key other than the committed one
+        let other_key = NonceKey::new();
+        let sig_2 = other_key.sign(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(!commitment.audit(&other_key, &sig_2, msgs.as_slice(), &params));
+
+        // Audit is for the given messages
+        let msgs_1 = FieldElementVector::random(count_msgs);
+        assert!(!commitment.audit(&nonce_key, &sig, msgs_1.as_slice(), &params));
+    }
+}
@@ -0,0 +1,381 @@
+// Scheme defined in section 6.1 of the 2016 paper
+// `BlindIssuanceRequest` is a partially blind issuance on top of it: the holder commits to the first messages, which
+// the signer never sees, and lists the public messages, e.g. the expiry or the type of the credential, which the
+// signer sees and can refuse. The proof of knowledge of the opening of the commitment has the public messages and the
+// nonce of the signer in its challenge, so they cannot be changed without the holder, and the signer signs them as the
+// known messages of `BlindSignature::new`.
+
+use crate::challenge_domain::BLIND_ISSUANCE;
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::pok_sig::{ProofSignatureGroup, ProverCommittingSignatureGroup};
+use crate::scalar::{Blinding, BlindingScalar, MessageScalar};
+use crate::signature::Signature;
+use crate::SignatureGroup;
+
+/// The public key described in the paper is split into `BlindingKey` and `Verkey`. Only `Verkey` is
+/// needed by the verifier. `BlindingKey` is used by the user to request a blind signature.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlindingKey {
+    pub X: SignatureGroup,
+    pub Y: Vec<SignatureGroup>,
+}
+
+impl BlindingKey {
+    pub fn new(sig_key: &Sigkey, params: &Params) -> Self {
+        let X = &params.g * &sig_key.x;
+        let Y = sig_key.y.iter().map(|y| &params.g * y).collect();
+        Self { X, Y }
+    }
+
+    pub fn msg_count(&self) -> usize {
+        self.Y.len()
+    }
+}
+
+pub struct BlindSignature {}
+
+impl BlindSignature {
+    /// 1 or more messages are captured in a commitment `commitment`. The remaining known messages are in `messages`.
+    /// The committed messages are expected to be the first messages, i.e. if the commitment is to `k` messages
+    /// then these are messages at indices `0..k` and `messages` are at indices `k..n`.
+    /// This is a blind signature.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<M: MessageScalar>(
+        commitment: &SignatureGroup,
+        messages: &[M],
+        sig_key: &Sigkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        let messages = &M::field_elements(messages);
+        // There should be commitment to at least one message
+        Self::check_blinding_key_and_messages_compat(messages, blinding_key)?;
+
+        let u = FieldElement::random();
+        let offset = blinding_key.msg_count() - messages.len();
+        let (sigma_1, mut sigma_2) = Signature::sign_with_sigma_1_generated_from_given_exp(
+            messages, sig_key, &u, offset, &params.g,
+        )?;
+        crate::profiling::record_scalar_muls(1);
+        sigma_2 += commitment * &u;
+        Ok(Signature { sigma_1, sigma_2 })
+    }
+
+    /// Once signature on committed attributes (blind signature) is received, the signature needs to be unblinded.
+    /// Takes the blinding used in the commitment.
+    pub fn unblind<B: BlindingScalar>(sig: &Signature, blinding: &B) -> Signature {
+        let sigma_1 = sig.sigma_1.clone();
+        let sigma_1_t = &sigma_1 * blinding.as_blinding();
+        let sigma_2 = &sig.sigma_2 - sigma_1_t;
+        Signature { sigma_1, sigma_2 }
+    }
+
+    pub fn check_blinding_key_and_messages_compat(
+        messages: &[FieldElement],
+        blinding_key: &BlindingKey,
+    ) -> Result<(), PSError> {
+        if messages.len() >= blinding_key.msg_count() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: blinding_key.msg_count(),
+                given: messages.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Request of a signature on hidden messages, at indices 0..k, followed by public messages
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlindIssuanceRequest {
+    /// g^blinding * Y_0^m_0 * ... * Y_{k-1}^m_{k-1} for the hidden messages m_0..m_{k-1}
+    pub commitment: SignatureGroup,
+    /// Proof of knowledge of the opening of `commitment`
+    pub proof: ProofSignatureGroup,
+    /// Messages at indices k..n, seen by the signer
+    pub public_messages: Vec<FieldElement>,
+}
+
+/// Kept by the holder until the signer replies
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingBlindIssuance {
+    blinding: Blinding,
+    messages: Vec<FieldElement>,
+}
+
+fn issuance_challenge(
+    committed_bytes: Vec<u8>,
+    commitment: &SignatureGroup,
+    public_messages: &[FieldElement],
+    nonce: &[u8],
+) -> FieldElement {
+    let mut bytes = BLIND_ISSUANCE.tag();
+    bytes.extend(committed_bytes);
+    bytes.append(&mut commitment.to_bytes());
+    bytes.extend_from_slice(&(public_messages.len() as u64).to_be_bytes());
+    for m in public_messages {
+        bytes.append(&mut m.to_bytes());
+    }
+    bytes.extend_from_slice(nonce);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// Y_0..Y_{k-1} and g
+fn commitment_bases(
+    blinding_key: &BlindingKey,
+    hidden_count: usize,
+    params: &Params,
+) -> Vec<SignatureGroup> {
+    let mut bases = blinding_key.Y[..hidden_count].to_vec();
+    bases.push(params.g.clone());
+    bases
+}
+
+impl BlindIssuanceRequest {
+    /// Request of a signature on `hidden` only, the signer sees no message
+    pub fn new<M: MessageScalar>(
+        hidden: &[M],
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<(Self, PendingBlindIssuance), PSError> {
+        Self::with_public_messages(hidden, &[] as &[FieldElement], blinding_key, params, nonce)
+    }
+
+    /// Request of a signature on `hidden` followed by `public`. `nonce` is chosen by the signer to prevent replay of
+    /// the request. Fails unless there is at least 1 hidden message and the messages are as many as of the key.
+    pub fn with_public_messages<M: MessageScalar, P: MessageScalar>(
+        hidden: &[M],
+        public: &[P],
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<(Self, PendingBlindIssuance), PSError> {
+        let hidden = &M::field_elements(hidden);
+        let public = &P::field_elements(public);
+        BlindSignature::check_blinding_key_and_messages_compat(public, blinding_key)?;
+        if hidden.len() + public.len() != blinding_key.msg_count() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: blinding_key.msg_count(),
+                given: hidden.len() + public.len(),
+            });
+        }
+        let blinding = Blinding::random();
+        let bases = commitment_bases(blinding_key, hidden.len(), params);
+        let mut secrets = hidden.to_vec();
+        secrets.push(blinding.as_field_element().clone());
+        let mut commitment = SignatureGroup::identity();
+        let mut committing = ProverCommittingSignatureGroup::new();
+        for (base, secret) in bases.iter().zip(secrets.iter()) {
+            commitment += base * secret;
+            committing.commit(base, None);
+        }
+        let committed = committing.finish();
+        let challenge = issuance_challenge(committed.to_bytes(), &commitment, public, nonce);
+        let request = Self {
+            commitment,
+            proof: committed.gen_proof(&challenge, &secrets)?,
+            public_messages: public.to_vec(),
+        };
+        let mut messages = hidden.to_vec();
+        messages.extend_from_slice(public);
+        Ok((request, PendingBlindIssuance { blinding, messages }))
+    }
+
+    /// Count of hidden messages for a key of `blinding_key`
+    pub fn hidden_count(&self, blinding_key: &BlindingKey) -> usize {
+        blinding_key
+            .msg_count()
+            .saturating_sub(self.public_messages.len())
+    }
+
+    /// Verify the proof of knowledge of the opening of the commitment for `nonce`
+    pub fn verify(
+        &self,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        BlindSignature::check_blinding_key_and_messages_compat(
+            &self.public_messages,
+            blinding_key,
+        )?;
+        let bases = commitment_bases(blinding_key, self.hidden_count(blinding_key), params);
+        if self.proof.responses.len() != bases.len() {
+            return Ok(false);
+        }
+        // Same bytes as of the `ProverCommittedSignatureGroup` of the holder, bases || commitment
+        let mut committed_bytes = vec![];
+        for b in &bases {
+            committed_bytes.append(&mut b.to_bytes());
+        }
+        committed_bytes.append(&mut self.proof.commitment.to_bytes());
+        let challenge = issuance_challenge(
+            committed_bytes,
+            &self.commitment,
+            &self.public_messages,
+            nonce,
+        );
+        self.proof.verify(&bases, &self.commitment, &challenge)
+    }
+
+    /// Verify the request and sign the hidden messages and the public messages. The signer checks the public
+    /// messages are ones it agrees to sign before.
+    pub fn sign(
+        &self,
+        sig_key: &Sigkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<Signature, PSError> {
+        if !self.verify(blinding_key, params, nonce)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Blind issuance request does not verify"),
+            });
+        }
+        BlindSignature::new(
+            &self.commitment,
+            &self.public_messages,
+            sig_key,
+            blinding_key,
+            params,
+        )
+    }
+}
+
+impl PendingBlindIssuance {
+    /// Unblind the signature of the signer and return it with the messages. Fails if it is not a signature on the
+    /// hidden messages followed by the public messages.
+    pub fn finish(
+        self,
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<(Signature, Vec<FieldElement>), PSError> {
+        let sig = BlindSignature::unblind(sig, &self.blinding);
+        if !sig.verify(self.messages.clone(), vk, params)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signature is not on the requested messages"),
+            });
+        }
+        Ok((sig, self.messages))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_signature_blinded_messages() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let blinding_key = BlindingKey::new(&sk, &params);
+        for count_blinded_msgs in 1..count_msgs {
+            let msgs = (0..count_msgs)
+                .map(|_| FieldElement::random())
+                .collect::<Vec<FieldElement>>();
+            let blinding = FieldElement::random();
+
+            // commitment = Y[0]^m[0] * Y[1]^m[1] * ... * g^blinding
+            let mut comm = &params.g * &blinding;
+            for (y, m) in blinding_key
+                .Y
+                .iter()
+                .zip(msgs.iter())
+                .take(count_blinded_msgs)
+            {
+                comm += y * m;
+            }
+
+            let sig_blinded = BlindSignature::new(
+                &comm,
+                &msgs[count_blinded_msgs..count_msgs],
+                &sk,
+                &blinding_key,
+                &params,
+            )
+            .unwrap();
+            let sig_unblinded = BlindSignature::unblind(&sig_blinded, &blinding);
+            assert!(sig_unblinded.verify(msgs, &vk, &params).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_blind_signature_needs_a_committed_message() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, _) = keygen(count_msgs, &params).unwrap();
+        let blinding_key = BlindingKey::new(&sk, &params);
+        let msgs = (0..count_msgs)
+            .map(|_| FieldElement::random())
+            .collect::<Vec<FieldElement>>();
+        let comm = &params.g * &FieldElement::random();
+        assert!(BlindSignature::new(&comm, &msgs, &sk, &blinding_key, &params).is_err());
+    }
+
+    #[test]
+    fn test_partially_blind_issuance() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let blinding_key = BlindingKey::new(&sk, &params);
+        let nonce = FieldElement::random().to_bytes();
+        let hidden: Vec<FieldElement> = (0..2).map(|_| FieldElement::random()).collect();
+        // e.g. the expiry and the type of the credential
+        let public: Vec<FieldElement> = (0..2).map(|_| FieldElement::random()).collect();
+
+        let (request, pending) = BlindIssuanceRequest::with_public_messages(
+            &hidden,
+            &public,
+            &blinding_key,
+            &params,
+            &nonce,
+        )
+        .unwrap();
+        assert!(request.verify(&blinding_key, &params, &nonce).unwrap());
+        assert_eq!(request.hidden_count(&blinding_key), 2);
+        let sig = request.sign(&sk, &blinding_key, &params, &nonce).unwrap();
+        let (sig, msgs) = pending.finish(&sig, &vk, &params).unwrap();
+        assert_eq!(msgs[..2], hidden[..]);
+        assert_eq!(msgs[2..], public[..]);
+        assert!(sig.verify(msgs, &vk, &params).unwrap());
+
+        // The public messages are bound to the request
+        let mut changed = request.clone();
+        changed.public_messages[0] = FieldElement::random();
+        assert!(!changed.verify(&blinding_key, &params, &nonce).unwrap());
+        assert!(changed.sign(&sk, &blinding_key, &params, &nonce).is_err());
+        assert!(!request
+            .verify(&blinding_key, &params, &FieldElement::random().to_bytes())
+            .unwrap());
+
+        // Fully blind
+        let hidden: Vec<FieldElement> = (0..count_msgs).map(|_| FieldElement::random()).collect();
+        let (request, pending) =
+            BlindIssuanceRequest::new(&hidden, &blinding_key, &params, &nonce).unwrap();
+        let sig = request.sign(&sk, &blinding_key, &params, &nonce).unwrap();
+        assert_eq!(pending.finish(&sig, &vk, &params).unwrap().1, hidden);
+
+        // No hidden message or a wrong count of messages
+        assert!(BlindIssuanceRequest::with_public_messages(
+            &[] as &[FieldElement],
+            &public,
+            &blinding_key,
+            &params,
+            &nonce
+        )
+        .is_err());
+        assert!(BlindIssuanceRequest::with_public_messages(
+            &hidden[..1],
+            &public,
+            &blinding_key,
+            &params,
+            &nonce
+        )
+        .is_err());
+    }
+}
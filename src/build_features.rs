@@ -0,0 +1,169 @@
+// Compile-time configuration of the crate, for tooling checking that an issuer and its verifiers run compatible
+// builds. `features()` has the curve, the groups of signatures and verkeys, the hash to the field and the groups,
+// whether the standard library and rayon are used and the optional cargo features enabled. Keys of a build with the
+// other group of signatures have elements of other sizes and fail to deserialize, or worse would be read as
+// elements of the wrong group where the sizes happen to match, so `KeyWithBuild` serializes a key along with the
+// features of the build that made it and `into_key` fails closed with `PSError::BuildMismatch` unless the build
+// reading it is compatible: the same curve, groups and hash. Optional features like `profiling` do not change
+// keys, signatures or proofs and do not need to match.
+
+use crate::errors::PSError;
+
+/// Configuration of a build of the crate, as `features()`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildFeatures {
+    /// Version of the crate
+    pub version: String,
+    pub curve: String,
+    /// Group of the signatures, "G1" or "G2"
+    pub signature_group: String,
+    /// Group of the verkeys, the other one
+    pub verkey_group: String,
+    /// Hash to the field and the groups
+    pub hash: String,
+    /// Whether the standard library is used, always for now
+    pub std: bool,
+    /// Whether computations run in parallel with rayon, like the keys of `rsskeygen`
+    pub parallel: bool,
+    /// Optional cargo features enabled, in increasing order
+    pub optional: Vec<String>,
+}
+
+/// Key with the configuration of the build that made it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyWithBuild<K> {
+    pub build: BuildFeatures,
+    pub key: K,
+}
+
+#[cfg(feature = "SignatureG2")]
+const GROUPS: (&str, &str) = ("G2", "G1");
+#[cfg(feature = "SignatureG1")]
+const GROUPS: (&str, &str) = ("G1", "G2");
+
+/// Configuration of this build
+pub fn features() -> BuildFeatures {
+    let optional = [
+        ("ark-bridge", cfg!(feature = "ark-bridge")),
+        ("data-integrity", cfg!(feature = "data-integrity")),
+        ("derive", cfg!(feature = "derive")),
+        ("encrypted-keys", cfg!(feature = "encrypted-keys")),
+        ("human-serde", cfg!(feature = "human-serde")),
+        ("io-errors", cfg!(feature = "io-errors")),
+        ("json-document", cfg!(feature = "json-document")),
+        ("legacy-keys", cfg!(feature = "legacy-keys")),
+        ("perf-tests", cfg!(feature = "perf-tests")),
+        ("profiling", cfg!(feature = "profiling")),
+        ("sealed-credentials", cfg!(feature = "sealed-credentials")),
+        ("server", cfg!(feature = "server")),
+        ("signature-traits", cfg!(feature = "signature-traits")),
+        ("test-utils", cfg!(feature = "test-utils")),
+        ("ursa-keys", cfg!(feature = "ursa-keys")),
+    ];
+    BuildFeatures {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        curve: String::from("BLS12-381"),
+        signature_group: GROUPS.0.to_string(),
+        verkey_group: GROUPS.1.to_string(),
+        hash: String::from("SHAKE256"),
+        std: true,
+        parallel: true,
+        optional: optional
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| name.to_string())
+            .collect(),
+    }
+}
+
+impl BuildFeatures {
+    /// Fails with `PSError::BuildMismatch` for the first of the curve, the groups and the hash that differs from
+    /// the one of `other`
+    pub fn check_compatible(&self, other: &BuildFeatures) -> Result<(), PSError> {
+        let fields = [
+            ("curve", &self.curve, &other.curve),
+            (
+                "signature_group",
+                &self.signature_group,
+                &other.signature_group,
+            ),
+            ("verkey_group", &self.verkey_group, &other.verkey_group),
+            ("hash", &self.hash, &other.hash),
+        ];
+        for (field, expected, found) in fields.iter() {
+            if expected != found {
+                return Err(PSError::BuildMismatch {
+                    field: field.to_string(),
+                    expected: expected.to_string(),
+                    found: found.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K> KeyWithBuild<K> {
+    /// `key` with the features of this build
+    pub fn new(key: K) -> Self {
+        Self {
+            build: features(),
+            key,
+        }
+    }
+
+    /// The key, failing with `PSError::BuildMismatch` if it was made by a build incompatible with this one
+    pub fn into_key(self) -> Result<K, PSError> {
+        features().check_compatible(&self.build)?;
+        Ok(self.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, Params};
+
+    #[test]
+    fn test_build_features() {
+        let build = features();
+        assert_eq!(build.curve, "BLS12-381");
+        assert_ne!(build.signature_group, build.verkey_group);
+        #[cfg(feature = "SignatureG2")]
+        assert_eq!(build.signature_group, "G2");
+        assert_eq!(build.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            build.optional.contains(&String::from("profiling")),
+            cfg!(feature = "profiling")
+        );
+
+        let params = Params::new("test".as_bytes());
+        let (_, vk) = keygen(2, &params).unwrap();
+        let key = KeyWithBuild::new(vk.clone());
+        assert_eq!(key.build, build);
+        assert_eq!(key.clone().into_key().unwrap(), vk);
+
+        // Optional features do not matter, the groups do
+        let mut other = key.clone();
+        other.build.optional = vec![String::from("server")];
+        other.build.version = String::from("0.1.0");
+        assert!(other.into_key().is_ok());
+        let mut other = key;
+        std::mem::swap(
+            &mut other.build.signature_group,
+            &mut other.build.verkey_group,
+        );
+        match other.into_key() {
+            Err(PSError::BuildMismatch {
+                field,
+                expected,
+                found,
+            }) => {
+                assert_eq!(field, "signature_group");
+                assert_eq!(expected, build.signature_group);
+                assert_eq!(found, build.verkey_group);
+            }
+            r => panic!("Expected BuildMismatch but got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Domain separation of Fiat-Shamir challenges. Every transcript hashed for a challenge in this crate starts with
+// the tag of its proof type and version from `REGISTRY`, so that the transcript of one proof type can never be
+// the transcript of another, including of proof types added later. Composite proofs such as
+// `PoKOfSignatureWithCommitments` start with their own tag followed by the transcripts of their parts, each with
+// its tag. A tag is `TAG_PREFIX` || length of the proof type as 1 byte || proof type || version as 2 bytes big
+// endian, so that no tag is a prefix of another one.
+// Downstream composite proofs create the domain of their proof type with `ChallengeDomain::custom`, which rejects
+// the proof types of the registry, and start their transcripts with its tag.
+
+use crate::errors::PSError;
+
+pub const TAG_PREFIX: &[u8] = b"PS challenge : ";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChallengeDomain {
+    pub proof_type: &'static str,
+    pub version: u16,
+}
+
+/// `PoKOfSignature` and `PoKOfSignatureProof`
+pub const POK_SIG: ChallengeDomain = ChallengeDomain::new("pok_sig", 1);
+/// `PoKOfSignatureWithCommitments` and `PoKOfSignatureWithCommitmentsProof`
+pub const POK_SIG_WITH_COMMITMENTS: ChallengeDomain =
+    ChallengeDomain::new("pok_sig_with_commitments", 1);
+/// `IssuerRingPoK` and `IssuerRingProof`
+pub const ISSUER_RING: ChallengeDomain = ChallengeDomain::new("issuer_ring", 1);
+/// Proof of knowledge of the opening of a vector commitment, `gen_challenge` of the types of `impl_PoK_VC`
+pub const POK_VC: ChallengeDomain = ChallengeDomain::new("pok_vc", 1);
+/// `LinkSecretCommitment`
+pub const LINK_SECRET: ChallengeDomain = ChallengeDomain::new("link_secret", 1);
+/// `HolderSignature`
+pub const HOLDER_SIGNATURE: ChallengeDomain = ChallengeDomain::new("holder_signature", 1);
+/// Challenges of the disclosed messages of `RedactedSignature`
+pub const RSS_DERIVE: ChallengeDomain = ChallengeDomain::new("rss_derive", 1);
+/// `anon_token::Redemption`
+pub const ANON_TOKEN: ChallengeDomain = ChallengeDomain::new("anon_token", 1);
+/// Proof of possession of `keygen_transcript::KeygenTranscript`
+pub const KEYGEN_TRANSCRIPT: ChallengeDomain = ChallengeDomain::new("keygen_transcript", 1);
+/// `linking_proof::LinkingProof`
+pub const LINKING_PROOF: ChallengeDomain = ChallengeDomain::new("linking_proof", 1);
+/// `predicate_proof::PredicatePresentation`
+pub const PREDICATE_PRESENTATION: ChallengeDomain =
+    ChallengeDomain::new("predicate_presentation", 1);
+/// `k_show::Show`
+pub const K_SHOW: ChallengeDomain = ChallengeDomain::new("k_show", 1);
+/// `credential_update::UpdateRequest`
+pub const CREDENTIAL_UPDATE: ChallengeDomain = ChallengeDomain::new("credential_update", 1);
+/// `text_equality::TextEqualityProof`
+pub const TEXT_EQUALITY: ChallengeDomain = ChallengeDomain::new("text_equality", 1);
+/// `signing_domain::ProofOfPossession`
+pub const KEY_POSSESSION: ChallengeDomain = ChallengeDomain::new("key_possession", 1);
+/// `blind_signature::BlindIssuanceRequest`
+pub const BLIND_ISSUANCE: ChallengeDomain = ChallengeDomain::new("blind_issuance", 1);
+/// `rss_upgrade::UpgradeLink`
+pub const RSS_UPGRADE: ChallengeDomain = ChallengeDomain::new("rss_upgrade", 1);
+/// `quick::SingleDisclosure`
+pub const QUICK_DISCLOSURE: ChallengeDomain = ChallengeDomain::new("quick_disclosure", 1);
+
+/// Domains of the proof types of this crate
+pub const REGISTRY: &[ChallengeDomain] = &[
+    POK_SIG,
+    POK_SIG_WITH_COMMITMENTS,
+    ISSUER_RING,
+    POK_VC,
+    LINK_SECRET,
+    HOLDER_SIGNATURE,
+    RSS_DERIVE,
+    ANON_TOKEN,
+    KEYGEN_TRANSCRIPT,
+    LINKING_PROOF,
+    PREDICATE_PRESENTATION,
+    K_SHOW,
+    CREDENTIAL_UPDATE,
+    TEXT_EQUALITY,
+    KEY_POSSESSION,
+    BLIND_ISSUANCE,
+    RSS_UPGRADE,
+    QUICK_DISCLOSURE,
+];
+
+impl ChallengeDomain {
+    pub const fn new(proof_type: &'static str, version: u16) -> Self {
+        Self {
+            proof_type,
+            version,
+        }
+    }
+
+    /// Domain of a proof type outside this crate. Fails if `proof_type` is one of the registry, whatever the
+    /// version, or is longer than 255 bytes.
+    pub fn custom(proof_type: &'static str, version: u16) -> Result<Self, PSError> {
+        if proof_type.len() > u8::MAX as usize {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "Proof type should be at most {} bytes but is {}",
+                    u8::MAX,
+                    proof_type.len()
+                ),
+            });
+        }
+        if Self::registered(proof_type).is_some() {
+            return Err(PSError::ReservedChallengeDomain {
+                proof_type: String::from(proof_type),
+            });
+        }
+        Ok(Self::new(proof_type, version))
+    }
+
+    /// Domain of the registry for `proof_type`
+    pub fn registered(proof_type: &str) -> Option<Self> {
+        REGISTRY
+            .iter()
+            .find(|d| d.proof_type == proof_type)
+            .copied()
+    }
+
+    /// Bytes a transcript starts with
+    pub fn tag(&self) -> Vec<u8> {
+        let mut bytes = TAG_PREFIX.to_vec();
+        bytes.push(self.proof_type.len() as u8);
+        bytes.extend_from_slice(self.proof_type.as_bytes());
+        bytes.extend_from_slice(&self.version.to_be_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::{keygen, Params};
+    use crate::pok_sig::PoKOfSignature;
+    use crate::signature::Signature;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_challenge_domains() {
+        let tags: HashSet<Vec<u8>> = REGISTRY.iter().map(|d| d.tag()).collect();
+        assert_eq!(tags.len(), REGISTRY.len());
+        for d in REGISTRY {
+            assert!(d.proof_type.len() <= u8::MAX as usize);
+            for other in REGISTRY {
+                if d != other {
+                    assert!(!other.tag().starts_with(&d.tag()));
+                }
+            }
+        }
+        assert_ne!(
+            ChallengeDomain::new("pok_sig", 2).tag(),
+            ChallengeDomain::new("pok_sig", 1).tag()
+        );
+
+        match ChallengeDomain::custom("issuer_ring", 2) {
+            Err(PSError::ReservedChallengeDomain { .. }) => (),
+            r => panic!("Expected ReservedChallengeDomain but got {:?}", r),
+        }
+        let custom = ChallengeDomain::custom("composite", 1).unwrap();
+        assert!(!tags.contains(&custom.tag()));
+        assert_eq!(ChallengeDomain::registered("pok_sig"), Some(POK_SIG));
+        assert_eq!(ChallengeDomain::registered("composite"), None);
+
+        // Transcripts start with the tag of their proof type
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let msgs = FieldElementVector::random(3);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let pok =
+            PoKOfSignature::init(&sig, &vk, &params, msgs.into(), None, HashSet::new()).unwrap();
+        assert!(pok.to_bytes().starts_with(&POK_SIG.tag()));
+    }
+}
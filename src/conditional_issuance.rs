@@ -0,0 +1,182 @@
+// Pre-signatures for conditional issuance: a pre-signature becomes a signature of the 2016 scheme once the holder
+// learns a secret t, e.g. the preimage of a payment the issuer is owed. The secret is locked by (T, T_tilde) =
+// (g^t, g_tilde^t). A pre-signature is (sigma_1, sigma_2 - sigma_1^t) with sigma_1 = g^u, which the signer computes
+// as sigma_1^t = T^u without knowing t.
+//   - `pre_sign`: the signer creates the pre-signature for the lock.
+//   - `PreSignature::verify`: e(sigma_1, X_tilde * Y_tilde_1^m_1 * ... * T_tilde^-1) == e(pre_sigma_2, g_tilde), so the
+//     holder knows that the secret of the lock completes it.
+//   - `PreSignature::adapt`: with t, sigma_2 = pre_sigma_2 + sigma_1^t.
+//   - `PreSignature::extract`: from a signature adapted from the pre-signature, the witness sigma_1^t, checked
+//     against T_tilde.
+// This is not an adaptor signature for atomic swaps. Signatures have no scalars, so a signer shown the adapted
+// signature learns sigma_1^t and never t: issuance cannot be made to reveal the secret to the signer. The exchange
+// is only conditional on the holder learning t by other means. Completing a pre-signature without t means computing
+// sigma_1^t from g^u and g^t, the computational Diffie-Hellman problem. The holder can randomize the adapted
+// signature, after which nothing can be extracted from it.
+
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::signature::Signature;
+use crate::{ate_2_pairing, SignatureGroup, VerkeyGroup};
+
+/// (g^t, g_tilde^t) for the secret t that unlocks a pre-signature
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssuanceLock {
+    pub T: SignatureGroup,
+    pub T_tilde: VerkeyGroup,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreSignature {
+    pub sigma_1: SignatureGroup,
+    /// sigma_2 - sigma_1^t
+    pub pre_sigma_2: SignatureGroup,
+}
+
+impl IssuanceLock {
+    pub fn new(secret: &FieldElement, params: &Params) -> Self {
+        Self {
+            T: &params.g * secret,
+            T_tilde: &params.g_tilde * secret,
+        }
+    }
+
+    /// Whether T and T_tilde have the same exponent, e(T, g_tilde) == e(g, T_tilde)
+    pub fn is_valid(&self, params: &Params) -> bool {
+        !self.T.is_identity()
+            && ate_2_pairing(&self.T, &params.g_tilde, &(-&params.g), &self.T_tilde).is_one()
+    }
+}
+
+/// Pre-signature on `messages` for `lock`. Fails if the lock is not valid, as a pre-signature for an
+/// invalid lock could not be adapted.
+pub fn pre_sign(
+    messages: &[FieldElement],
+    sigkey: &Sigkey,
+    lock: &IssuanceLock,
+    params: &Params,
+) -> Result<PreSignature, PSError> {
+    Signature::check_sigkey_and_messages_compat(messages, sigkey)?;
+    if !lock.is_valid(params) {
+        return Err(PSError::GeneralError {
+            msg: String::from("Invalid issuance lock"),
+        });
+    }
+    let u = FieldElement::random();
+    let exp = Signature::sigkey_exponent(messages, sigkey, 0)?;
+    let sigma_1 = &params.g * &u;
+    // sigma_1^exp - T^u = sigma_1^exp - sigma_1^t
+    let pre_sigma_2 = SignatureGroup::binary_scalar_mul(&sigma_1, &lock.T, &exp, &(-&u));
+    Ok(PreSignature {
+        sigma_1,
+        pre_sigma_2,
+    })
+}
+
+impl PreSignature {
+    /// Whether the secret of `lock` completes it to a signature on `messages`
+    pub fn verify(
+        &self,
+        messages: Vec<FieldElement>,
+        vk: &Verkey,
+        lock: &IssuanceLock,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        Signature::check_verkey_and_messages_compat(messages.as_slice(), vk)?;
+        vk.check_params(params)?;
+        if self.sigma_1.is_identity() || !lock.is_valid(params) {
+            return Ok(false);
+        }
+        let J = Signature::X_tilde_Y_m(messages, vk) - &lock.T_tilde;
+        Ok(ate_2_pairing(&self.sigma_1, &J, &(-&self.pre_sigma_2), &params.g_tilde).is_one())
+    }
+
+    /// Signature with the secret of the lock
+    pub fn adapt(&self, secret: &FieldElement) -> Signature {
+        self.complete(&(&self.sigma_1 * secret))
+    }
+
+    /// Witness sigma_1^t from `sig` adapted from this pre-signature. Fails if `sig` is not adapted from it with the
+    /// secret of `lock`.
+    pub fn extract(
+        &self,
+        sig: &Signature,
+        lock: &IssuanceLock,
+        params: &Params,
+    ) -> Result<SignatureGroup, PSError> {
+        let witness = &sig.sigma_2 - &self.pre_sigma_2;
+        // e(witness, g_tilde) == e(sigma_1, T_tilde)
+        if sig.sigma_1 != self.sigma_1
+            || !ate_2_pairing(&witness, &params.g_tilde, &(-&self.sigma_1), &lock.T_tilde).is_one()
+        {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signature is not adapted from the pre-signature"),
+            });
+        }
+        Ok(witness)
+    }
+
+    /// Signature with the witness sigma_1^t, as returned by `extract`
+    pub fn complete(&self, witness: &SignatureGroup) -> Signature {
+        Signature {
+            sigma_1: self.sigma_1.clone(),
+            sigma_2: &self.pre_sigma_2 + witness,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_pre_sign_adapt_extract() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params).unwrap();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(4).into();
+        let secret = FieldElement::random();
+        let lock = IssuanceLock::new(&secret, &params);
+        assert!(lock.is_valid(&params));
+
+        let pre_sig = pre_sign(&msgs, &sk, &lock, &params).unwrap();
+        assert!(pre_sig.verify(msgs.clone(), &vk, &lock, &params).unwrap());
+        // Not a signature before adapting
+        let not_adapted = Signature {
+            sigma_1: pre_sig.sigma_1.clone(),
+            sigma_2: pre_sig.pre_sigma_2.clone(),
+        };
+        assert!(!not_adapted.verify(msgs.clone(), &vk, &params).unwrap());
+        let other_lock = IssuanceLock::new(&FieldElement::random(), &params);
+        assert!(!pre_sig
+            .verify(msgs.clone(), &vk, &other_lock, &params)
+            .unwrap());
+        assert!(!pre_sig
+            .verify(FieldElementVector::random(4).into(), &vk, &lock, &params)
+            .unwrap());
+
+        let sig = pre_sig.adapt(&secret);
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+        assert!(!pre_sig
+            .adapt(&FieldElement::random())
+            .verify(msgs.clone(), &vk, &params)
+            .unwrap());
+
+        let witness = pre_sig.extract(&sig, &lock, &params).unwrap();
+        assert_eq!(witness, &pre_sig.sigma_1 * &secret);
+        assert_eq!(pre_sig.complete(&witness).to_bytes(), sig.to_bytes());
+        assert!(pre_sig.extract(&sig, &other_lock, &params).is_err());
+        let unrelated = Signature::new(&msgs, &sk, &params).unwrap();
+        assert!(pre_sig.extract(&unrelated, &lock, &params).is_err());
+
+        // Statement whose elements have different exponents
+        let invalid = IssuanceLock {
+            T: lock.T.clone(),
+            T_tilde: other_lock.T_tilde.clone(),
+        };
+        assert!(!invalid.is_valid(&params));
+        assert!(pre_sign(&msgs, &sk, &invalid, &params).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+// Signing and verifying many signatures with the same key without heap allocations per call. `SignContext` and
+// `VerifyContext` are created once per key and keep tables of multiples of g and of each Y_tilde_i, see
+// `FixedBaseTable`, so that signing and the multi-scalar multiplication of verification are additions of table
+// entries selected by the windows of the scalars, which are read from bytes on the stack. The scalars of signing are
+// secret, so `sign` reads every entry of each window in constant time and adds the selected one, while verification
+// only looks up the entry of each window of its public scalars. The remaining operations,
+// the pairings and arithmetic of field and group elements, work on the stack as well, so `sign` and `verify`
+// allocate nothing for a fixed count of messages, see `test_no_allocations`.
+// The tables cost memory, 64 * 15 group elements per base, and creating them about as much as 4 scalar
+// multiplications per base, which is recovered after a few calls. `with_hints` picks the tables for the expected
+// count of calls with `perf_hints::PerfHints` instead.
+// `VerifierSession` keeps a `VerifyContext` per issuer for the proofs of knowledge of signatures verified over a
+// connection. The verkey of an issuer is checked and its tables created at its first proof, later proofs only look
+// them up. amcl_wrapper has no pairings with precomputed arguments, so the pairing with J * X_tilde is computed for
+// each proof, but J * X_tilde * Y_tilde_i^m_i of the revealed messages and the recomputed commitment of the hidden
+// ones are additions of table entries.
+
+use crate::curve::{scalar_mul, FieldElement, FieldElement_SIZE, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::perf_hints::PerfHints;
+use crate::pok_sig::{FixedBaseTable, PoKOfSignatureProof};
+use crate::signature::Signature;
+use crate::{ate_2_pairing, SignatureGroup, VerkeyGroup};
+use rand::{thread_rng, RngCore};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignContext {
+    sigkey: Sigkey,
+    g_table: FixedBaseTable<SignatureGroup>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VerifyContext {
+    X_tilde: VerkeyGroup,
+    g_tilde: VerkeyGroup,
+    Y_tilde_tables: Vec<FixedBaseTable<VerkeyGroup>>,
+}
+
+/// Verifier state of a connection, the contexts of the issuers whose proofs were verified, by the bytes of their verkey
+#[derive(Clone, Debug)]
+pub struct VerifierSession {
+    params: Params,
+    g_tilde_table: FixedBaseTable<VerkeyGroup>,
+    issuers: HashMap<Vec<u8>, VerifyContext>,
+}
+
+/// Random non-zero field element from `FieldElement_SIZE` random bytes reduced modulo the curve order, whose bias is
+/// negligible as the bytes have over 128 bits more than the order. Unlike `FieldElement::random`, it does not allocate.
+fn random_non_zero() -> FieldElement {
+    let mut bytes = [0u8; FieldElement_SIZE];
+    loop {
+        thread_rng().fill_bytes(&mut bytes);
+        let r = FieldElement::from_bytes(&bytes).unwrap();
+        if !r.is_zero() {
+            return r;
+        }
+    }
+}
+
+impl SignContext {
+    pub fn new(sigkey: &Sigkey, params: &Params) -> Self {
+        Self {
+            sigkey: sigkey.clone(),
+            g_table: FixedBaseTable::new(&params.g),
+        }
+    }
+
+    /// Context with the table of g chosen by `hints`, e.g. none for a context signing a single message
+    pub fn with_hints(sigkey: &Sigkey, params: &Params, hints: &PerfHints) -> Self {
+        Self {
+            sigkey: sigkey.clone(),
+            g_table: FixedBaseTable::with_window(&params.g, hints.window_size()),
+        }
+    }
+
+    /// Same as `Signature::new` with the key and params given to `new`
+    pub fn sign(&self, messages: &[FieldElement]) -> Result<Signature, PSError> {
+        Signature::check_sigkey_and_messages_compat(messages, &self.sigkey)?;
+        // sigma_1 = g^u, sigma_2 = g^{u * (x + y_1*m_1 + y_2*m_2 + ...)}
+        let u = random_non_zero();
+        let exp = Signature::sigkey_exponent(messages, &self.sigkey, 0)? * &u;
+        let mut sigma_1 = SignatureGroup::identity();
+        self.g_table.mul_add_const_time(&u, &mut sigma_1);
+        let mut sigma_2 = SignatureGroup::identity();
+        self.g_table.mul_add_const_time(&exp, &mut sigma_2);
+        Ok(Signature { sigma_1, sigma_2 })
+    }
+}
+
+impl VerifyContext {
+    /// Fails with `PSError::ParamsMismatch` if `vk` was not generated with `params`
+    pub fn new(vk: &Verkey, params: &Params) -> Result<Self, PSError> {
+        vk.check_params(params)?;
+        Ok(Self {
+            X_tilde: vk.X_tilde.clone(),
+            g_tilde: params.generator_tilde(),
+            Y_tilde_tables: vk.Y_tilde.iter().map(FixedBaseTable::new).collect(),
+        })
+    }
+
+    /// Same as `new` with the tables chosen by `hints`
+    pub fn with_hints(vk: &Verkey, params: &Params, hints: &PerfHints) -> Result<Self, PSError> {
+        vk.check_params(params)?;
+        let window = hints.window_size();
+        Ok(Self {
+            X_tilde: vk.X_tilde.clone(),
+            g_tilde: params.generator_tilde(),
+            Y_tilde_tables: vk
+                .Y_tilde
+                .iter()
+                .map(|b| FixedBaseTable::with_window(b, window))
+                .collect(),
+        })
+    }
+
+    pub fn supported_messages(&self) -> usize {
+        self.Y_tilde_tables.len()
+    }
+
+    /// Same as `Signature::verify` with the verkey and params given to `new`
+    pub fn verify(&self, sig: &Signature, messages: &[FieldElement]) -> Result<bool, PSError> {
+        if messages.len() != self.supported_messages() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: self.supported_messages(),
+                given: messages.len(),
+            });
+        }
+        if sig.sigma_1.is_identity() || sig.sigma_2.is_identity() {
+            return Ok(false);
+        }
+        // e(sigma_1, X_tilde * Y_tilde_1^m_1 * Y_tilde_2^m_2 *...) * e(-sigma_2, g_tilde) == 1
+        let mut J = self.X_tilde.clone();
+        for (table, m) in self.Y_tilde_tables.iter().zip(messages) {
+            table.mul_add(m, &mut J);
+        }
+        Ok(ate_2_pairing(&sig.sigma_1, &J, &(-&sig.sigma_2), &self.g_tilde).is_one())
+    }
+}
+
+impl VerifierSession {
+    pub fn new(params: &Params) -> Self {
+        Self {
+            params: params.clone(),
+            g_tilde_table: FixedBaseTable::new(&params.generator_tilde()),
+            issuers: HashMap::new(),
+        }
+    }
+
+    /// Count of issuers whose verkey was checked and context created
+    pub fn issuer_count(&self) -> usize {
+        self.issuers.len()
+    }
+
+    /// Same as `PoKOfSignatureProof::verify` with the params given to `new`. The first proof of an issuer fails with
+    /// `PSError::ParamsMismatch` if `vk` was not generated with these params and with the error of
+    /// `Verkey::sanity_check` if it is weak, and creates its context.
+    pub fn verify_proof(
+        &mut self,
+        proof: &PoKOfSignatureProof,
+        vk: &Verkey,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        let key = vk.to_bytes();
+        if !self.issuers.contains_key(&key) {
+            vk.sanity_check()?;
+            let context = VerifyContext::new(vk, &self.params)?;
+            self.issuers.insert(key.clone(), context);
+        }
+        let context = &self.issuers[&key];
+        PoKOfSignatureProof::validate_revealed_msgs(vk, revealed_msgs)?;
+        let responses = proof.proof_vc.responses.as_slice();
+        let bases = context.supported_messages() - revealed_msgs.len() + 1;
+        if responses.len() != bases {
+            return Err(PSError::UnequalNoOfBasesExponents {
+                bases,
+                exponents: responses.len(),
+            });
+        }
+        if proof.sig.sigma_1.is_identity() || proof.sig.sigma_2.is_identity() {
+            return Ok(false);
+        }
+
+        // g_tilde^r_0 * Y_tilde_i^r_i for each hidden message * J^challenge and J * X_tilde * Y_tilde_i^m_i for
+        // each revealed message m_i
+        let mut recomputed = scalar_mul(&proof.J, challenge);
+        self.g_tilde_table.mul_add(&responses[0], &mut recomputed);
+        let mut J = &proof.J + &context.X_tilde;
+        let mut hidden_responses = responses[1..].iter();
+        for (i, table) in context.Y_tilde_tables.iter().enumerate() {
+            match revealed_msgs.get(&i) {
+                Some(m) => table.mul_add(m, &mut J),
+                None => table.mul_add(hidden_responses.next().unwrap(), &mut recomputed),
+            }
+        }
+        if recomputed != proof.proof_vc.commitment {
+            return Ok(false);
+        }
+        Ok(ate_2_pairing(
+            &proof.sig.sigma_1,
+            &J,
+            &(-&proof.sig.sigma_2),
+            &context.g_tilde,
+        )
+        .is_one())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+    use crate::pok_sig::PoKOfSignature;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// Counts the allocations of each thread so that tests running in parallel do not disturb the count
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations_of<T, F: FnOnce() -> T>(f: F) -> (T, usize) {
+        let before = ALLOCATIONS.with(|a| a.get());
+        let result = f();
+        (result, ALLOCATIONS.with(|a| a.get()) - before)
+    }
+
+    #[test]
+    fn test_no_allocations() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sign_ctx = SignContext::new(&sk, &params);
+        let verify_ctx = VerifyContext::new(&vk, &params).unwrap();
+
+        // The first call initializes the random number generator of the thread
+        sign_ctx.sign(msgs.as_slice()).unwrap();
+        let (sig, allocations) = allocations_of(|| sign_ctx.sign(msgs.as_slice()).unwrap());
+        assert_eq!(allocations, 0);
+        let (valid, allocations) =
+            allocations_of(|| verify_ctx.verify(&sig, msgs.as_slice()).unwrap());
+        assert!(valid);
+        assert_eq!(allocations, 0);
+
+        // Agrees with `Signature`
+        assert!(sig.verify(msgs.clone().into(), &vk, &params).unwrap());
+        let other = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(verify_ctx.verify(&other, msgs.as_slice()).unwrap());
+        let other_msgs = FieldElementVector::random(count_msgs);
+        assert!(!verify_ctx.verify(&sig, other_msgs.as_slice()).unwrap());
+        assert!(verify_ctx
+            .verify(&sig, &msgs.as_slice()[..count_msgs - 1])
+            .is_err());
+        let identity = Signature {
+            sigma_1: SignatureGroup::identity(),
+            sigma_2: SignatureGroup::identity(),
+        };
+        assert!(!verify_ctx.verify(&identity, msgs.as_slice()).unwrap());
+        match VerifyContext::new(&vk, &Params::new("other".as_bytes())) {
+            Err(PSError::ParamsMismatch) => (),
+            r => panic!("Expected ParamsMismatch but got {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_verifier_session() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let issuers: Vec<(Sigkey, Verkey)> = (0..2)
+            .map(|_| keygen(count_msgs, &params).unwrap())
+            .collect();
+        let mut session = VerifierSession::new(&params);
+        for (sk, vk) in &issuers {
+            for revealed_indices in &[vec![], vec![1, 3], vec![0, 1, 2, 3, 4]] {
+                let msgs = FieldElementVector::random(count_msgs);
+                let sig = Signature::new(msgs.as_slice(), sk, &params).unwrap();
+                let revealed = revealed_indices.iter().cloned().collect();
+                let pok =
+                    PoKOfSignature::init(&sig, vk, &params, msgs.clone().into(), None, revealed)
+                        .unwrap();
+                let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+                let proof = pok.gen_proof(&challenge).unwrap();
+                let mut revealed_msgs: HashMap<usize, FieldElement> = revealed_indices
+                    .iter()
+                    .map(|i| (*i, msgs[*i].clone()))
+                    .collect();
+                assert!(proof
+                    .verify(vk, &params, revealed_msgs.clone(), &challenge)
+                    .unwrap());
+                assert!(session
+                    .verify_proof(&proof, vk, &revealed_msgs, &challenge)
+                    .unwrap());
+                assert!(!session
+                    .verify_proof(&proof, vk, &revealed_msgs, &FieldElement::random())
+                    .unwrap());
+                // Not a proof of the other issuer
+                let other_vk = &issuers[0].1;
+                if other_vk != vk {
+                    assert!(!session
+                        .verify_proof(&proof, other_vk, &revealed_msgs, &challenge)
+                        .unwrap());
+                }
+                if let Some(i) = revealed_indices.first() {
+                    revealed_msgs.insert(*i, FieldElement::random());
+                    assert!(!session
+                        .verify_proof(&proof, vk, &revealed_msgs, &challenge)
+                        .unwrap());
+                    revealed_msgs.remove(i);
+                    assert!(session
+                        .verify_proof(&proof, vk, &revealed_msgs, &challenge)
+                        .is_err());
+                }
+            }
+        }
+        // A context per issuer, created at its first proof
+        assert_eq!(session.issuer_count(), 2);
+
+        let (_, other_params_vk) = keygen(count_msgs, &Params::new("other".as_bytes())).unwrap();
+        let (sk, vk) = &issuers[0];
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), sk, &params).unwrap();
+        let pok =
+            PoKOfSignature::init(&sig, vk, &params, msgs.into(), None, Default::default()).unwrap();
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&challenge).unwrap();
+        match session.verify_proof(&proof, &other_params_vk, &HashMap::new(), &challenge) {
+            Err(PSError::ParamsMismatch) => (),
+            r => panic!("Expected ParamsMismatch but got {:?}", r),
+        }
+        assert_eq!(session.issuer_count(), 2);
+    }
+}
@@ -0,0 +1,264 @@
+// Credentials as Rust structs. `CredentialStruct` maps the fields of a struct to the messages of a credential, in
+// the order of the fields, and has typed methods to sign, present and verify presentations with fields named rather
+// than indexed. With the feature `derive`, `#[derive(Credential)]` implements it, see the crate `ps_sig_derive` for
+// the attributes `#[non_redactable]`, `#[link_secret]` and `#[encoding(..)]`.
+// Non-redactable fields are disclosed by `present` whether asked or not and `verify_presentation` rejects a
+// presentation hiding one, the link secret is never disclosed and `verify_presentation` rejects a presentation
+// revealing it. Presentations are fresh, built with `PresentationBuilder` and verified with
+// `verify_fresh_presentation`.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::link_secret::LinkSecret;
+use crate::presentation::{
+    verify_fresh_presentation, FreshnessChallenge, Presentation, PresentationPolicy,
+    VerifiedPresentation,
+};
+use crate::presentation_builder::PresentationBuilder;
+use crate::signature::Signature;
+use crate::typed_message::TypedMessage;
+
+#[cfg(feature = "derive")]
+pub use ps_sig_derive::Credential;
+
+/// Message of a field without `#[encoding(..)]`
+pub trait ToMessage {
+    fn to_message(&self) -> FieldElement;
+}
+
+impl ToMessage for u64 {
+    fn to_message(&self) -> FieldElement {
+        TypedMessage::Int(*self).encode()
+    }
+}
+
+impl ToMessage for bool {
+    fn to_message(&self) -> FieldElement {
+        TypedMessage::Bool(*self).encode()
+    }
+}
+
+impl ToMessage for String {
+    fn to_message(&self) -> FieldElement {
+        TypedMessage::Text(self.clone()).encode()
+    }
+}
+
+impl ToMessage for FieldElement {
+    fn to_message(&self) -> FieldElement {
+        self.clone()
+    }
+}
+
+impl ToMessage for LinkSecret {
+    fn to_message(&self) -> FieldElement {
+        self.secret().clone()
+    }
+}
+
+/// Field of a credential struct, at the index of its position in `CredentialStruct::FIELDS`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldSpec {
+    pub name: &'static str,
+    pub non_redactable: bool,
+    pub link_secret: bool,
+}
+
+pub trait CredentialStruct {
+    const FIELDS: &'static [FieldSpec];
+
+    /// Messages of the fields in the order of `FIELDS`
+    fn to_messages(&self) -> Vec<FieldElement>;
+
+    /// Index of the message of field `name`, failing with `PSError::UnknownField` for no such field
+    fn index_of(name: &str) -> Result<usize, PSError> {
+        Self::FIELDS
+            .iter()
+            .position(|f| f.name == name)
+            .ok_or_else(|| PSError::UnknownField {
+                name: name.to_string(),
+            })
+    }
+
+    fn sign(&self, sigkey: &Sigkey, params: &Params) -> Result<Signature, PSError> {
+        Signature::new(&self.to_messages(), sigkey, params)
+    }
+
+    fn verify(&self, sig: &Signature, vk: &Verkey, params: &Params) -> Result<bool, PSError> {
+        sig.verify(self.to_messages(), vk, params)
+    }
+
+    /// Fresh presentation of `sig` disclosing the fields `disclosed` and the non-redactable fields. Fails with
+    /// `PSError::LinkSecretRevealed` if `disclosed` has the link secret.
+    fn present(
+        &self,
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        disclosed: &[&str],
+        freshness: FreshnessChallenge,
+    ) -> Result<Presentation, PSError> {
+        let mut indices = vec![];
+        for name in disclosed {
+            let idx = Self::index_of(name)?;
+            if Self::FIELDS[idx].link_secret {
+                return Err(PSError::LinkSecretRevealed { idx });
+            }
+            indices.push(idx);
+        }
+        indices.extend(
+            Self::FIELDS
+                .iter()
+                .enumerate()
+                .filter(|(_, f)| f.non_redactable)
+                .map(|(i, _)| i),
+        );
+        PresentationBuilder::new(sig, self.to_messages(), vk, params)?
+            .disclose(&indices)?
+            .bind_context(freshness)?
+            .finish()
+    }
+
+    /// Verify a presentation of `present` like `verify_fresh_presentation`. Fails with
+    /// `PSError::NonRedactableMessageHidden` if a non-redactable field is not disclosed and with
+    /// `PSError::LinkSecretRevealed` if the link secret is.
+    fn verify_presentation(
+        presentation: &Presentation,
+        vk: &Verkey,
+        params: &Params,
+        freshness: &FreshnessChallenge,
+        now: u64,
+        max_clock_skew: u64,
+    ) -> Result<VerifiedPresentation, PSError> {
+        for (idx, field) in Self::FIELDS.iter().enumerate() {
+            let revealed = presentation.revealed_msgs.contains_key(&idx);
+            if field.non_redactable && !revealed {
+                return Err(PSError::NonRedactableMessageHidden { idx });
+            }
+            if field.link_secret && revealed {
+                return Err(PSError::LinkSecretRevealed { idx });
+            }
+        }
+        verify_fresh_presentation(
+            presentation,
+            vk,
+            params,
+            freshness,
+            &PresentationPolicy::transferable(),
+            now,
+            max_clock_skew,
+        )
+    }
+
+    /// Message of field `name` disclosed in `verified`, `None` if it is not disclosed
+    fn disclosed_field<'a>(
+        verified: &'a VerifiedPresentation,
+        name: &str,
+    ) -> Result<Option<&'a FieldElement>, PSError> {
+        Ok(verified.disclosed.get(&Self::index_of(name)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    /// As derived for `struct Employee { #[link_secret] link_secret: LinkSecret, name: String,
+    /// #[non_redactable] employer: String, #[encoding(unsigned)] salary: u64 }`
+    struct Employee {
+        link_secret: LinkSecret,
+        name: String,
+        employer: String,
+        salary: u64,
+    }
+
+    impl CredentialStruct for Employee {
+        const FIELDS: &'static [FieldSpec] = &[
+            FieldSpec {
+                name: "link_secret",
+                non_redactable: false,
+                link_secret: true,
+            },
+            FieldSpec {
+                name: "name",
+                non_redactable: false,
+                link_secret: false,
+            },
+            FieldSpec {
+                name: "employer",
+                non_redactable: true,
+                link_secret: false,
+            },
+            FieldSpec {
+                name: "salary",
+                non_redactable: false,
+                link_secret: false,
+            },
+        ];
+
+        fn to_messages(&self) -> Vec<FieldElement> {
+            vec![
+                self.link_secret.to_message(),
+                self.name.to_message(),
+                self.employer.to_message(),
+                crate::integer_message::encode_unsigned(self.salary),
+            ]
+        }
+    }
+
+    #[test]
+    fn test_credential_struct() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(Employee::FIELDS.len(), &params).unwrap();
+        let employee = Employee {
+            link_secret: LinkSecret::new(),
+            name: String::from("Alice"),
+            employer: String::from("ACME"),
+            salary: 50_000,
+        };
+        let sig = employee.sign(&sk, &params).unwrap();
+        assert!(employee.verify(&sig, &vk, &params).unwrap());
+
+        let now = 1_600_000_000;
+        let freshness = FreshnessChallenge::new(now);
+        let presentation = employee
+            .present(&sig, &vk, &params, &["salary"], freshness.clone())
+            .unwrap();
+        let verified =
+            Employee::verify_presentation(&presentation, &vk, &params, &freshness, now, 0).unwrap();
+        assert_eq!(
+            Employee::disclosed_field(&verified, "employer").unwrap(),
+            Some(&TypedMessage::Text(String::from("ACME")).encode())
+        );
+        assert_eq!(
+            Employee::disclosed_field(&verified, "salary").unwrap(),
+            Some(&crate::integer_message::encode_unsigned(50_000))
+        );
+        assert_eq!(Employee::disclosed_field(&verified, "name").unwrap(), None);
+        assert!(Employee::disclosed_field(&verified, "age").is_err());
+
+        match employee.present(&sig, &vk, &params, &["link_secret"], freshness.clone()) {
+            Err(PSError::LinkSecretRevealed { idx: 0 }) => (),
+            r => panic!("Expected LinkSecretRevealed but got {:?}", r.map(|_| ())),
+        }
+        match employee.present(&sig, &vk, &params, &["age"], freshness.clone()) {
+            Err(PSError::UnknownField { .. }) => (),
+            r => panic!("Expected UnknownField but got {:?}", r.map(|_| ())),
+        }
+        // A presentation hiding the non-redactable employer
+        let hiding = PresentationBuilder::new(&sig, employee.to_messages(), &vk, &params)
+            .unwrap()
+            .disclose(&[3])
+            .unwrap()
+            .bind_context(freshness.clone())
+            .unwrap()
+            .finish()
+            .unwrap();
+        match Employee::verify_presentation(&hiding, &vk, &params, &freshness, now, 0) {
+            Err(PSError::NonRedactableMessageHidden { idx: 2 }) => (),
+            r => panic!("Expected NonRedactableMessageHidden but got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,410 @@
+// Update of some messages of a credential of the 2016 scheme, e.g. an address change, without issuing it again
+// from scratch. The first `hidden_count` messages of the credential, such as a link secret, are never seen by the
+// issuer and carry over to the new credential; the others are known to the issuer when signing.
+//   1. The holder calls `UpdateRequest::new` with its credential and the indices to update. The request has a
+//      blind signature commitment C = g^blinding * Y_0^m_0 * ... * Y_{k-1}^m_{k-1} to the hidden messages, with
+//      a proof of knowledge of its opening, and a proof of knowledge of the credential revealing the messages that
+//      stay the same and are known to the issuer. Both proofs share the challenge and the blindings of the hidden
+//      messages, so equal responses prove that C commits to the hidden messages of the credential.
+//   2. The issuer calls `UpdateRequest::sign` with the new values of the updated messages, which verifies the
+//      request and blind-signs C with the revealed and the new messages, see `BlindSignature`.
+//   3. The holder gets the new credential with `PendingUpdate::finish`.
+// The old values of the updated messages stay hidden. The nonce from the issuer prevents replaying a request.
+
+use crate::blind_signature::{BlindSignature, BlindingKey};
+use crate::challenge_domain::CREDENTIAL_UPDATE;
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::pok_sig::{
+    PoKOfSignature, PoKOfSignatureProof, ProofSignatureGroup, ProverCommittingSignatureGroup,
+};
+use crate::scalar::Blinding;
+use crate::signature::Signature;
+use crate::SignatureGroup;
+use std::collections::{HashMap, HashSet};
+
+/// Sent by the holder to the issuer to update a credential
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UpdateRequest {
+    /// g^blinding * Y_0^m_0 * ... * Y_{k-1}^m_{k-1} for the hidden messages m_0..m_{k-1}
+    pub commitment: SignatureGroup,
+    /// Proof of knowledge of the opening of `commitment`, the responses of the hidden messages first
+    pub commitment_proof: ProofSignatureGroup,
+    pub credential_proof: PoKOfSignatureProof,
+    /// Messages of the credential which are neither hidden nor updated
+    pub revealed_msgs: HashMap<usize, FieldElement>,
+}
+
+/// Kept by the holder until the issuer replies
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingUpdate {
+    blinding: Blinding,
+    hidden_count: usize,
+}
+
+fn update_challenge(
+    credential_bytes: Vec<u8>,
+    commitment_bytes: Vec<u8>,
+    commitment: &SignatureGroup,
+    nonce: &[u8],
+) -> FieldElement {
+    let mut bytes = CREDENTIAL_UPDATE.tag();
+    bytes.extend(credential_bytes);
+    bytes.extend(commitment_bytes);
+    bytes.append(&mut commitment.to_bytes());
+    bytes.extend_from_slice(nonce);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// Y_0..Y_{k-1} and g, the bases of the commitment
+fn commitment_bases(
+    blinding_key: &BlindingKey,
+    hidden_count: usize,
+    params: &Params,
+) -> Vec<SignatureGroup> {
+    let mut bases = blinding_key.Y[..hidden_count].to_vec();
+    bases.push(params.generator());
+    bases
+}
+
+/// Fails unless the hidden messages are followed by at least one other message
+fn check_hidden_count(hidden_count: usize, count_messages: usize) -> Result<(), PSError> {
+    if hidden_count == 0 || hidden_count >= count_messages {
+        return Err(PSError::GeneralError {
+            msg: format!(
+                "Hidden messages should be 1 to {} but are {}",
+                count_messages.saturating_sub(1),
+                hidden_count
+            ),
+        });
+    }
+    Ok(())
+}
+
+impl UpdateRequest {
+    /// Request to update the messages at `updated` of the credential `sig` on `messages`, whose first
+    /// `hidden_count` messages are not known to the issuer. `nonce` is given by the issuer. Fails with
+    /// `PSError::InvalidIndex` if an index of `updated` is of a hidden message or out of range.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        sig: &Signature,
+        vk: &Verkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        messages: &[FieldElement],
+        hidden_count: usize,
+        updated: &HashSet<usize>,
+        nonce: &[u8],
+    ) -> Result<(Self, PendingUpdate), PSError> {
+        let n = messages.len();
+        check_hidden_count(hidden_count, n)?;
+        if let Some(idx) = updated.iter().find(|i| **i < hidden_count || **i >= n) {
+            return Err(PSError::InvalidIndex {
+                idx: *idx,
+                valid: format!("[{}, {})", hidden_count, n),
+            });
+        }
+        if blinding_key.msg_count() != n {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: blinding_key.msg_count(),
+                given: n,
+            });
+        }
+        let revealed: HashSet<usize> = (hidden_count..n).filter(|i| !updated.contains(i)).collect();
+
+        // Hidden messages come first among the hidden messages of the proof of the credential
+        let blindings: Vec<Blinding> = (0..hidden_count + updated.len())
+            .map(|_| Blinding::random())
+            .collect();
+        let pok = PoKOfSignature::init(
+            sig,
+            vk,
+            params,
+            messages.to_vec(),
+            Some(blindings.as_slice()),
+            revealed.clone(),
+        )?;
+
+        let blinding = Blinding::random();
+        let bases = commitment_bases(blinding_key, hidden_count, params);
+        let mut secrets = messages[..hidden_count].to_vec();
+        secrets.push(blinding.as_field_element().clone());
+        let mut commitment = SignatureGroup::identity();
+        for (base, secret) in bases.iter().zip(secrets.iter()) {
+            commitment += base * secret;
+        }
+        let mut committing = ProverCommittingSignatureGroup::new();
+        for (base, b) in bases.iter().zip(blindings[..hidden_count].iter()) {
+            committing.commit(base, Some(b.as_field_element()));
+        }
+        committing.commit(&params.g, None);
+        let committed = committing.finish();
+
+        let challenge = update_challenge(pok.to_bytes(), committed.to_bytes(), &commitment, nonce);
+        let request = Self {
+            commitment,
+            commitment_proof: committed.gen_proof(&challenge, &secrets)?,
+            credential_proof: pok.gen_proof(&challenge)?,
+            revealed_msgs: revealed
+                .iter()
+                .map(|i| (*i, messages[*i].clone()))
+                .collect(),
+        };
+        Ok((
+            request,
+            PendingUpdate {
+                blinding,
+                hidden_count,
+            },
+        ))
+    }
+
+    /// Indices of the updated messages, those neither hidden nor revealed
+    pub fn updated_indices(&self, hidden_count: usize, vk: &Verkey) -> HashSet<usize> {
+        (hidden_count..vk.Y_tilde.len())
+            .filter(|i| !self.revealed_msgs.contains_key(i))
+            .collect()
+    }
+
+    /// Verify both proofs and that the commitment is to the hidden messages of the credential
+    pub fn verify(
+        &self,
+        vk: &Verkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        hidden_count: usize,
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        let n = vk.Y_tilde.len();
+        check_hidden_count(hidden_count, n)?;
+        if blinding_key.msg_count() != n
+            || self.revealed_msgs.keys().any(|i| *i < hidden_count)
+            || self.commitment_proof.responses.len() != hidden_count + 1
+        {
+            return Ok(false);
+        }
+        let bases = commitment_bases(blinding_key, hidden_count, params);
+        // Same bytes as of the `ProverCommittedSignatureGroup` of `new`, bases || commitment
+        let mut commitment_bytes = vec![];
+        for b in &bases {
+            commitment_bytes.append(&mut b.to_bytes());
+        }
+        commitment_bytes.append(&mut self.commitment_proof.commitment.to_bytes());
+        let challenge = update_challenge(
+            self.credential_proof.get_bytes_for_challenge(
+                self.revealed_msgs.keys().cloned().collect(),
+                vk,
+                params,
+            ),
+            commitment_bytes,
+            &self.commitment,
+            nonce,
+        );
+        if !self
+            .credential_proof
+            .verify(vk, params, self.revealed_msgs.clone(), &challenge)?
+            || !self
+                .commitment_proof
+                .verify(&bases, &self.commitment, &challenge)?
+        {
+            return Ok(false);
+        }
+        // Hidden messages are the first hidden messages of the proof of the credential
+        for i in 0..hidden_count {
+            if self.credential_proof.get_resp_for_message(i)? != self.commitment_proof.responses[i]
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Verify the request and sign the new credential with the messages at the updated indices replaced by
+    /// `updates`. Fails if the request does not verify or if `updates` is not for exactly the updated indices.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sign(
+        &self,
+        updates: &HashMap<usize, FieldElement>,
+        sig_key: &Sigkey,
+        vk: &Verkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        hidden_count: usize,
+        nonce: &[u8],
+    ) -> Result<Signature, PSError> {
+        if updates.keys().cloned().collect::<HashSet<usize>>()
+            != self.updated_indices(hidden_count, vk)
+        {
+            return Err(PSError::GeneralError {
+                msg: String::from("Updates should be for exactly the updated indices"),
+            });
+        }
+        if !self.verify(vk, blinding_key, params, hidden_count, nonce)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Update request does not verify"),
+            });
+        }
+        let known: Vec<FieldElement> = (hidden_count..vk.Y_tilde.len())
+            .map(|i| match updates.get(&i) {
+                Some(m) => m.clone(),
+                None => self.revealed_msgs[&i].clone(),
+            })
+            .collect();
+        BlindSignature::new(&self.commitment, &known, sig_key, blinding_key, params)
+    }
+}
+
+impl PendingUpdate {
+    /// Unblind the signature of the issuer and return it with the new messages, `messages` with `updates`
+    /// applied. Fails if it is not a signature on the new messages.
+    pub fn finish(
+        self,
+        sig: &Signature,
+        messages: &[FieldElement],
+        updates: &HashMap<usize, FieldElement>,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<(Signature, Vec<FieldElement>), PSError> {
+        let mut new_messages = messages.to_vec();
+        for (i, m) in updates {
+            if *i < self.hidden_count || *i >= new_messages.len() {
+                return Err(PSError::InvalidIndex {
+                    idx: *i,
+                    valid: format!("[{}, {})", self.hidden_count, new_messages.len()),
+                });
+            }
+            new_messages[*i] = m.clone();
+        }
+        let sig = BlindSignature::unblind(sig, &self.blinding);
+        if !sig.verify(new_messages.clone(), vk, params)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signature is not on the updated messages"),
+            });
+        }
+        Ok((sig, new_messages))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_credential_update() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(5, &params).unwrap();
+        let blinding_key = BlindingKey::new(&sk, &params);
+        let msgs: Vec<FieldElement> = FieldElementVector::random(5).into();
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        let hidden_count = 2;
+        let updated: HashSet<usize> = vec![3].into_iter().collect();
+        let nonce = FieldElement::random().to_bytes();
+
+        let (request, pending) = UpdateRequest::new(
+            &sig,
+            &vk,
+            &blinding_key,
+            &params,
+            &msgs,
+            hidden_count,
+            &updated,
+            &nonce,
+        )
+        .unwrap();
+        assert!(request
+            .verify(&vk, &blinding_key, &params, hidden_count, &nonce)
+            .unwrap());
+        assert_eq!(request.updated_indices(hidden_count, &vk), updated);
+        let mut revealed: Vec<usize> = request.revealed_msgs.keys().cloned().collect();
+        revealed.sort_unstable();
+        assert_eq!(revealed, vec![2, 4]);
+
+        let updates: HashMap<usize, FieldElement> =
+            vec![(3, FieldElement::random())].into_iter().collect();
+        let new_sig = request
+            .sign(
+                &updates,
+                &sk,
+                &vk,
+                &blinding_key,
+                &params,
+                hidden_count,
+                &nonce,
+            )
+            .unwrap();
+        let (new_sig, new_msgs) = pending
+            .finish(&new_sig, &msgs, &updates, &vk, &params)
+            .unwrap();
+        assert!(new_sig.verify(new_msgs.clone(), &vk, &params).unwrap());
+        assert_eq!(new_msgs[..3], msgs[..3]);
+        assert_eq!(new_msgs[3], updates[&3]);
+        assert_eq!(new_msgs[4], msgs[4]);
+
+        // Replayed with another nonce
+        assert!(!request
+            .verify(
+                &vk,
+                &blinding_key,
+                &params,
+                hidden_count,
+                &FieldElement::random().to_bytes()
+            )
+            .unwrap());
+        // Updates for other indices
+        let other: HashMap<usize, FieldElement> =
+            vec![(4, FieldElement::random())].into_iter().collect();
+        assert!(request
+            .sign(
+                &other,
+                &sk,
+                &vk,
+                &blinding_key,
+                &params,
+                hidden_count,
+                &nonce
+            )
+            .is_err());
+        // Commitment to other hidden messages than those of the credential
+        let mut other_msgs = msgs.clone();
+        other_msgs[1] = FieldElement::random();
+        let (other_request, _) = UpdateRequest::new(
+            &sig,
+            &vk,
+            &blinding_key,
+            &params,
+            &other_msgs,
+            hidden_count,
+            &updated,
+            &nonce,
+        )
+        .unwrap();
+        assert!(!other_request
+            .verify(&vk, &blinding_key, &params, hidden_count, &nonce)
+            .unwrap());
+        let mut swapped = request.clone();
+        swapped.commitment = other_request.commitment.clone();
+        swapped.commitment_proof = other_request.commitment_proof.clone();
+        assert!(!swapped
+            .verify(&vk, &blinding_key, &params, hidden_count, &nonce)
+            .unwrap());
+
+        // Hidden messages cannot be updated
+        let hidden_update: HashSet<usize> = vec![1].into_iter().collect();
+        match UpdateRequest::new(
+            &sig,
+            &vk,
+            &blinding_key,
+            &params,
+            &msgs,
+            hidden_count,
+            &hidden_update,
+            &nonce,
+        ) {
+            Err(PSError::InvalidIndex { idx: 1, .. }) => (),
+            r => panic!("Expected InvalidIndex but got {:?}", r.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+// Facade over the curve arithmetic of amcl_wrapper. The rest of the crate imports the scalar, group and target
+// group types and the constants from here and not from amcl_wrapper, so that another backend replaces this module
+// only, providing types of the same names and methods. Besides the types it has the few operations the schemes are
+// made of: random scalars, powers, scalar multiplication, hashing to scalars and to the groups and the pairings.
+// The groups of signatures and of verkeys, G1 or G2 depending on the feature `SignatureG1` or `SignatureG2`, are
+// chosen here as well and re-exported at the root of the crate.
+
+use crate::profiling;
+
+pub use amcl_wrapper::constants::{
+    CurveOrder, FieldElement_SIZE, GroupG1_SIZE, GroupG2_SIZE, MODBYTES,
+};
+pub use amcl_wrapper::extension_field_gt::GT;
+pub use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+pub use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+pub use amcl_wrapper::group_elem_g1::{G1Vector, G1};
+pub use amcl_wrapper::group_elem_g2::{G2Vector, G2};
+use amcl_wrapper::types::{GroupG1, GroupG2};
+
+#[cfg(feature = "SignatureG2")]
+pub type SignatureGroup = G2;
+#[cfg(feature = "SignatureG2")]
+pub type SignatureGroupVec = G2Vector;
+#[cfg(feature = "SignatureG2")]
+pub type VerkeyGroup = G1;
+#[cfg(feature = "SignatureG2")]
+pub type VerkeyGroupVec = G1Vector;
+#[cfg(feature = "SignatureG2")]
+pub const SIGNATURE_GROUP_SIZE: usize = GroupG2_SIZE;
+#[cfg(feature = "SignatureG2")]
+pub const VERKEY_GROUP_SIZE: usize = GroupG1_SIZE;
+#[cfg(feature = "SignatureG2")]
+pub fn ate_2_pairing(
+    g1: &SignatureGroup,
+    g2: &VerkeyGroup,
+    h1: &SignatureGroup,
+    h2: &VerkeyGroup,
+) -> GT {
+    profiling::record_pairings(2);
+    GT::ate_2_pairing(g2, g1, h2, h1)
+}
+#[cfg(feature = "SignatureG2")]
+pub fn ate_pairing(g: &SignatureGroup, h: &VerkeyGroup) -> GT {
+    profiling::record_pairings(1);
+    GT::ate_pairing(h, g)
+}
+#[cfg(feature = "SignatureG2")]
+pub fn ate_multi_pairing(pairs: &[(&SignatureGroup, &VerkeyGroup)]) -> GT {
+    profiling::record_pairings(pairs.len());
+    GT::ate_multi_pairing(pairs.iter().map(|(g, h)| (*h, *g)).collect())
+}
+
+#[cfg(feature = "SignatureG1")]
+pub type SignatureGroup = G1;
+#[cfg(feature = "SignatureG1")]
+pub type SignatureGroupVec = G1Vector;
+#[cfg(feature = "SignatureG1")]
+pub type VerkeyGroup = G2;
+#[cfg(feature = "SignatureG1")]
+pub type VerkeyGroupVec = G2Vector;
+#[cfg(feature = "SignatureG1")]
+pub const SIGNATURE_GROUP_SIZE: usize = GroupG1_SIZE;
+#[cfg(feature = "SignatureG1")]
+pub const VERKEY_GROUP_SIZE: usize = GroupG2_SIZE;
+#[cfg(feature = "SignatureG1")]
+pub fn ate_2_pairing(
+    g1: &SignatureGroup,
+    g2: &VerkeyGroup,
+    h1: &SignatureGroup,
+    h2: &VerkeyGroup,
+) -> GT {
+    profiling::record_pairings(2);
+    GT::ate_2_pairing(g1, g2, h1, h2)
+}
+#[cfg(feature = "SignatureG1")]
+pub fn ate_pairing(g: &SignatureGroup, h: &VerkeyGroup) -> GT {
+    profiling::record_pairings(1);
+    GT::ate_pairing(g, h)
+}
+#[cfg(feature = "SignatureG1")]
+pub fn ate_multi_pairing(pairs: &[(&SignatureGroup, &VerkeyGroup)]) -> GT {
+    profiling::record_pairings(pairs.len());
+    GT::ate_multi_pairing(pairs.to_vec())
+}
+
+/// Uniformly random non-zero scalar
+pub fn random_scalar() -> FieldElement {
+    FieldElement::random()
+}
+
+/// base^exp
+pub fn pow(base: &FieldElement, exp: &FieldElement) -> FieldElement {
+    base.pow(exp)
+}
+
+/// G1 and G2, whose variable time scalar multiplication and constant time selection are not in `GroupElement`
+pub trait CurveGroup: GroupElement {
+    fn scalar_mul_variable_time(&self, scalar: &FieldElement) -> Self;
+
+    /// `elems[d - 1]`, or the identity for d = 0, in constant time: every element is read and moved
+    /// conditionally whatever `d`, for lookups in tables of multiples by secret digits
+    fn select_const_time(elems: &[Self], d: usize) -> Self;
+}
+
+/// 1 if a = b and 0 otherwise, without branching
+fn eq_const_time(a: usize, b: usize) -> isize {
+    let x = (a ^ b) as u64;
+    (((x | x.wrapping_neg()) >> 63) ^ 1) as isize
+}
+
+impl CurveGroup for G1 {
+    fn scalar_mul_variable_time(&self, scalar: &FieldElement) -> Self {
+        G1::scalar_mul_variable_time(self, scalar)
+    }
+
+    fn select_const_time(elems: &[Self], d: usize) -> Self {
+        let mut p = GroupG1::new();
+        p.inf();
+        for (j, e) in elems.iter().enumerate() {
+            p.cmove(&e.to_ecp(), eq_const_time(j + 1, d));
+        }
+        p.into()
+    }
+}
+
+impl CurveGroup for G2 {
+    fn scalar_mul_variable_time(&self, scalar: &FieldElement) -> Self {
+        G2::scalar_mul_variable_time(self, scalar)
+    }
+
+    fn select_const_time(elems: &[Self], d: usize) -> Self {
+        let mut p = GroupG2::new();
+        p.inf();
+        for (j, e) in elems.iter().enumerate() {
+            p.cmove(&e.to_ecp(), eq_const_time(j + 1, d));
+        }
+        p.into()
+    }
+}
+
+/// elem^scalar in either group, counted by `profiling`. Not constant time, for public scalars.
+pub fn scalar_mul<G: CurveGroup>(elem: &G, scalar: &FieldElement) -> G {
+    profiling::record_scalar_muls(1);
+    CurveGroup::scalar_mul_variable_time(elem, scalar)
+}
+
+/// elem^scalar in either group in constant time, for secret scalars, counted by `profiling`
+pub fn scalar_mul_const_time<G: GroupElement>(elem: &G, scalar: &FieldElement) -> G {
+    profiling::record_scalar_muls(1);
+    elem.scalar_mul_const_time(scalar)
+}
+
+/// `x` as a scalar, built from its big endian bytes. `FieldElement::from(u64)` goes through an `isize` and gives
+/// non-canonical or wrong scalars for values from about 2^58.
+pub fn scalar_from_u64(x: u64) -> FieldElement {
+    let mut bytes = [0u8; FieldElement_SIZE];
+    bytes[FieldElement_SIZE - 8..].copy_from_slice(&x.to_be_bytes());
+    FieldElement::from(&bytes)
+}
+
+pub fn hash_to_scalar(msg: &[u8]) -> FieldElement {
+    FieldElement::from_msg_hash(msg)
+}
+
+pub fn hash_to_group<G: GroupElement>(msg: &[u8]) -> G {
+    G::from_msg_hash(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curve_operations() {
+        let g = hash_to_group::<SignatureGroup>(b"g");
+        let g_tilde = hash_to_group::<VerkeyGroup>(b"g_tilde");
+        assert_eq!(g, SignatureGroup::from_msg_hash(b"g"));
+        let (a, b) = (random_scalar(), hash_to_scalar(b"b"));
+        assert_eq!(hash_to_scalar(b"b"), b);
+        assert_ne!(a, b);
+        assert_eq!(pow(&a, &FieldElement::from(3u64)), &a * &a * &a);
+
+        // e(g^a, g_tilde^b) = e(g, g_tilde)^{a*b}
+        let lhs = ate_pairing(&scalar_mul(&g, &a), &scalar_mul_const_time(&g_tilde, &b));
+        let rhs = ate_pairing(&scalar_mul(&g, &(&a * &b)), &g_tilde);
+        assert_eq!(lhs, rhs);
+        // e(g, g_tilde) * e(g^a, g_tilde) = e(g^{1+a}, g_tilde)
+        let paired = ate_2_pairing(&g, &g_tilde, &scalar_mul(&g, &a), &g_tilde);
+        let sum = &FieldElement::one() + &a;
+        assert_eq!(paired, ate_pairing(&scalar_mul(&g, &sum), &g_tilde));
+        assert_eq!(
+            ate_multi_pairing(&[(&g, &g_tilde), (&scalar_mul(&g, &a), &g_tilde)]),
+            paired
+        );
+    }
+
+    #[test]
+    fn test_scalar_from_u64() {
+        assert_eq!(scalar_from_u64(0), FieldElement::zero());
+        assert_eq!(scalar_from_u64(3), FieldElement::from(3u64));
+        for x in &[1u64 << 58, (1 << 63) + 5, u64::MAX] {
+            let mut bytes = vec![0u8; FieldElement_SIZE - 8];
+            bytes.extend_from_slice(&x.to_be_bytes());
+            let s = scalar_from_u64(*x);
+            assert_eq!(s, FieldElement::from_bytes(&bytes).unwrap());
+            assert_eq!(s.to_bytes(), bytes);
+        }
+        // 2^64 - 1 + 1 = 2^64
+        let two_64 = &scalar_from_u64(1 << 32) * &scalar_from_u64(1 << 32);
+        assert_eq!(&scalar_from_u64(u64::MAX) + &FieldElement::one(), two_64);
+    }
+
+    #[test]
+    fn test_select_const_time() {
+        let elems: Vec<SignatureGroup> = (1..=4u64)
+            .map(|i| scalar_mul(&SignatureGroup::generator(), &FieldElement::from(i)))
+            .collect();
+        assert!(SignatureGroup::select_const_time(&elems, 0).is_identity());
+        for d in 1..=4 {
+            assert_eq!(SignatureGroup::select_const_time(&elems, d), elems[d - 1]);
+        }
+        assert!(SignatureGroup::select_const_time(&elems, 5).is_identity());
+        let elems: Vec<VerkeyGroup> = vec![VerkeyGroup::generator(); 2];
+        assert_eq!(VerkeyGroup::select_const_time(&elems, 2), elems[1]);
+        assert_eq!(eq_const_time(3, 3), 1);
+        assert_eq!(eq_const_time(0, usize::MAX), 0);
+    }
+}
@@ -0,0 +1,188 @@
+// Conversion of proofs of knowledge of signature to and from the proof block of a W3C Verifiable Credential
+// Data Integrity document (https://www.w3.org/TR/vc-data-integrity/), so that presentations can be embedded
+// in existing verifiable credential tooling. The proof bytes are carried in `proofValue` as a multibase
+// base64url (no padding) string.
+
+use crate::errors::PSError;
+use crate::pok_sig::PoKOfSignatureProof;
+use subtle_encoding::base64;
+
+pub const PROOF_TYPE: &str = "DataIntegrityProof";
+pub const CRYPTOSUITE: &str = "ps-sig-2016";
+pub const DEFAULT_PROOF_PURPOSE: &str = "assertionMethod";
+
+/// Multibase prefix for base64url without padding
+const MULTIBASE_BASE64URL: char = 'u';
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataIntegrityProof {
+    #[serde(rename = "type")]
+    pub proof_type: String,
+    pub cryptosuite: String,
+    /// Creation time as an XML Schema dateTime string, e.g. "2020-08-19T21:41:50Z"
+    pub created: String,
+    pub verification_method: String,
+    pub proof_purpose: String,
+    /// Nonce given by the verifier, if any, that the presentation's challenge was bound to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+    pub proof_value: String,
+}
+
+impl DataIntegrityProof {
+    pub fn from_pok_proof(
+        proof: &PoKOfSignatureProof,
+        created: &str,
+        verification_method: &str,
+    ) -> Self {
+        Self {
+            proof_type: PROOF_TYPE.to_string(),
+            cryptosuite: CRYPTOSUITE.to_string(),
+            created: created.to_string(),
+            verification_method: verification_method.to_string(),
+            proof_purpose: DEFAULT_PROOF_PURPOSE.to_string(),
+            challenge: None,
+            proof_value: multibase_encode(&proof.to_bytes()),
+        }
+    }
+
+    pub fn with_challenge(mut self, challenge: &str) -> Self {
+        self.challenge = Some(challenge.to_string());
+        self
+    }
+
+    pub fn with_proof_purpose(mut self, proof_purpose: &str) -> Self {
+        self.proof_purpose = proof_purpose.to_string();
+        self
+    }
+
+    /// Get the proof of knowledge back. Fails if the block is not of this crate's type and cryptosuite.
+    pub fn to_pok_proof(&self) -> Result<PoKOfSignatureProof, PSError> {
+        if self.proof_type != PROOF_TYPE || self.cryptosuite != CRYPTOSUITE {
+            return Err(PSError::DeserializationError {
+                msg: format!(
+                    "Unsupported proof type {} with cryptosuite {}",
+                    self.proof_type, self.cryptosuite
+                ),
+            });
+        }
+        PoKOfSignatureProof::from_bytes(&multibase_decode(&self.proof_value)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, PSError> {
+        serde_json::to_string(self).map_err(|e| PSError::GeneralError {
+            msg: format!("{}", e),
+        })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, PSError> {
+        serde_json::from_str(json).map_err(|e| PSError::DeserializationError {
+            msg: format!("{}", e),
+        })
+    }
+}
+
+fn multibase_encode(bytes: &[u8]) -> String {
+    let encoded = base64::encode(bytes);
+    let mut s = String::with_capacity(encoded.len() + 1);
+    s.push(MULTIBASE_BASE64URL);
+    for c in encoded.into_iter().map(char::from) {
+        match c {
+            '+' => s.push('-'),
+            '/' => s.push('_'),
+            '=' => (),
+            c => s.push(c),
+        }
+    }
+    s
+}
+
+fn multibase_decode(s: &str) -> Result<Vec<u8>, PSError> {
+    let mut chars = s.chars();
+    if chars.next() != Some(MULTIBASE_BASE64URL) {
+        return Err(PSError::DeserializationError {
+            msg: String::from("proofValue should be multibase base64url encoded"),
+        });
+    }
+    let mut encoded: Vec<u8> = chars
+        .map(|c| match c {
+            '-' => b'+',
+            '_' => b'/',
+            c => c as u8,
+        })
+        .collect();
+    while !encoded.len().is_multiple_of(4) {
+        encoded.push(b'=');
+    }
+    base64::decode(&encoded).map_err(|e| PSError::DeserializationError {
+        msg: format!("{}", e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{FieldElement, FieldElementVector};
+    use crate::keys::{keygen, Params};
+    use crate::pok_sig::PoKOfSignature;
+    use crate::signature::Signature;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_multibase() {
+        for n in 0..10 {
+            let bytes: Vec<u8> = (0..n).map(|i| (i * 37 + 250) as u8).collect();
+            let s = multibase_encode(&bytes);
+            assert!(!s.contains('=') && !s.contains('+') && !s.contains('/'));
+            assert_eq!(multibase_decode(&s).unwrap(), bytes);
+        }
+        assert!(multibase_decode("zabc").is_err());
+    }
+
+    #[test]
+    fn test_data_integrity_proof_round_trip() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(1);
+        let pok = PoKOfSignature::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone().into(),
+            None,
+            revealed_msg_indices,
+        )
+        .unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+
+        let block = DataIntegrityProof::from_pok_proof(
+            &proof,
+            "2020-08-19T21:41:50Z",
+            "did:example:issuer#key-1",
+        )
+        .with_challenge("1f44d55f");
+        let json = block.to_json().unwrap();
+        assert!(json.contains("\"type\":\"DataIntegrityProof\""));
+        assert!(json.contains("\"verificationMethod\":\"did:example:issuer#key-1\""));
+        assert!(json.contains("\"proofValue\":\"u"));
+
+        let block_1 = DataIntegrityProof::from_json(&json).unwrap();
+        assert_eq!(block, block_1);
+        let proof_1 = block_1.to_pok_proof().unwrap();
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(1, msgs[1].clone());
+        assert!(proof_1.verify(&vk, &params, revealed_msgs, &chal).unwrap());
+
+        // Block of another cryptosuite is rejected
+        let mut other = block_1;
+        other.cryptosuite = String::from("bbs-2023");
+        assert!(other.to_pok_proof().is_err());
+    }
+}
@@ -0,0 +1,464 @@
+// Redactable signatures of JSON documents, with the feature `json-document`. The document is a JSON object whose
+// leaves, the values that are not arrays or objects and the empty arrays and objects, are the messages of a
+// redactable signature. The leaves are in the order of the JSON Canonicalization Scheme (JCS, RFC 8785), which sorts
+// the members of objects by the UTF-16 code units of their names, and the message of a leaf is the hash of the JCS
+// form of its path, the array of its member names and array indices, followed by the JCS form of its value, so a
+// disclosed value cannot be moved to another path. The signer uses a key of at least as many messages as leaves and
+// the messages after the leaves are fixed padding, always disclosed, so the count of leaves is not hidden.
+// The holder redacts by JSON pointers (RFC 6901), a pointer disclosing the leaf at it or all leaves under it, and
+// gets a `RedactedDocument` with the index, path and value of each disclosed leaf and the redacted signature, which
+// serializes to self-describing JSON.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, SKrss};
+use crate::rss_signature::{RSSPublicKey, RSSignature, RedactedSignature};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const LEAF_DOMAIN: &[u8] = b"PS JSON document leaf : ";
+const PADDING_DOMAIN: &[u8] = b"PS JSON document padding : ";
+
+/// Step of the path of a leaf, a member name of an object or an index of an array
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PathSegment {
+    Index(usize),
+    Key(String),
+}
+
+/// Leaf disclosed by a `RedactedDocument`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DisclosedLeaf {
+    pub path: Vec<PathSegment>,
+    pub value: Value,
+}
+
+/// Document and its signature, kept by the holder
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedDocument {
+    pub document: Value,
+    pub signature: RSSignature,
+}
+
+/// Disclosed leaves of a signed document by message index with the redacted signature
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RedactedDocument {
+    /// Count of leaves of the document, the messages from this index on are padding
+    pub leaf_count: usize,
+    pub disclosed: BTreeMap<usize, DisclosedLeaf>,
+    pub signature: RedactedSignature,
+}
+
+/// JCS form of `value`
+pub fn canonicalize(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        // Without the feature `arbitrary_precision` of serde_json every number has an f64 value
+        Value::Number(n) => out.push_str(&es_number(n.as_f64().unwrap_or(0.0))),
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(members) => {
+            out.push('{');
+            for (i, (name, member)) in sorted_members(members).into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(name, out);
+                out.push(':');
+                write_canonical(member, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Members of an object ordered by the UTF-16 code units of their names
+fn sorted_members(members: &Map<String, Value>) -> Vec<(&String, &Value)> {
+    let mut sorted: Vec<_> = members.iter().collect();
+    sorted.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+    sorted
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Number as serialized by ECMAScript, as JCS requires. Rust formats floats with the shortest digits that round
+/// trip, as ECMAScript does, and only the placement of the decimal point and the exponent differ.
+fn es_number(f: f64) -> String {
+    if f == 0.0 {
+        return String::from("0");
+    }
+    let sign = if f < 0.0 { "-" } else { "" };
+    let scientific = format!("{:e}", f.abs());
+    let (mantissa, exponent) =
+        scientific.split_at(scientific.find('e').unwrap_or(scientific.len()));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    // f = 0.digits * 10^n
+    let n = exponent[1..].parse::<i32>().unwrap_or(0) + 1;
+    let k = digits.len() as i32;
+    let number = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let fraction = if k > 1 {
+            format!(".{}", &digits[1..])
+        } else {
+            String::new()
+        };
+        let exponent_sign = if n > 0 { "+" } else { "-" };
+        format!(
+            "{}{}e{}{}",
+            &digits[..1],
+            fraction,
+            exponent_sign,
+            (n - 1).abs()
+        )
+    };
+    format!("{}{}", sign, number)
+}
+
+/// Leaves of `document` in JCS order with their paths
+fn leaves(document: &Value) -> Vec<DisclosedLeaf> {
+    let mut leaves = vec![];
+    collect_leaves(document, &mut vec![], &mut leaves);
+    leaves
+}
+
+fn collect_leaves(value: &Value, path: &mut Vec<PathSegment>, leaves: &mut Vec<DisclosedLeaf>) {
+    match value {
+        Value::Array(items) if !items.is_empty() => {
+            for (i, item) in items.iter().enumerate() {
+                path.push(PathSegment::Index(i));
+                collect_leaves(item, path, leaves);
+                path.pop();
+            }
+        }
+        Value::Object(members) if !members.is_empty() => {
+            for (name, member) in sorted_members(members) {
+                path.push(PathSegment::Key(name.clone()));
+                collect_leaves(member, path, leaves);
+                path.pop();
+            }
+        }
+        _ => leaves.push(DisclosedLeaf {
+            path: path.clone(),
+            value: value.clone(),
+        }),
+    }
+}
+
+/// JSON pointer of `path`, with `~` and `/` of names escaped as `~0` and `~1`
+pub fn json_pointer(path: &[PathSegment]) -> String {
+    let mut pointer = String::new();
+    for segment in path {
+        pointer.push('/');
+        match segment {
+            PathSegment::Index(i) => pointer.push_str(&i.to_string()),
+            PathSegment::Key(name) => pointer.push_str(&name.replace('~', "~0").replace('/', "~1")),
+        }
+    }
+    pointer
+}
+
+impl DisclosedLeaf {
+    /// Message of the leaf, H(domain || JCS of the path || JCS of the value)
+    pub fn message(&self) -> FieldElement {
+        let path: Vec<Value> = self
+            .path
+            .iter()
+            .map(|s| match s {
+                PathSegment::Index(i) => Value::from(*i as u64),
+                PathSegment::Key(name) => Value::from(name.as_str()),
+            })
+            .collect();
+        let mut bytes = LEAF_DOMAIN.to_vec();
+        bytes.extend_from_slice(canonicalize(&Value::Array(path)).as_bytes());
+        bytes.extend_from_slice(canonicalize(&self.value).as_bytes());
+        FieldElement::from_msg_hash(&bytes)
+    }
+
+    /// Whether the leaf is at `pointer` or under it
+    fn is_under(&self, pointer: &str) -> bool {
+        let own = json_pointer(&self.path);
+        own == pointer || (own.starts_with(pointer) && own[pointer.len()..].starts_with('/'))
+    }
+}
+
+fn padding_message(index: usize) -> FieldElement {
+    let mut bytes = PADDING_DOMAIN.to_vec();
+    bytes.extend_from_slice(&(index as u64).to_be_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// Messages of the leaves followed by the padding up to `count` messages
+fn document_messages(leaves: &[DisclosedLeaf], count: usize) -> Result<Vec<FieldElement>, PSError> {
+    if leaves.len() > count {
+        return Err(PSError::UnsupportedNoOfMessages {
+            expected: count,
+            given: leaves.len(),
+        });
+    }
+    Ok(leaves
+        .iter()
+        .map(DisclosedLeaf::message)
+        .chain((leaves.len()..count).map(padding_message))
+        .collect())
+}
+
+fn check_object(document: &Value) -> Result<(), PSError> {
+    if !document.is_object() {
+        return Err(PSError::GeneralError {
+            msg: String::from("A signed document must be a JSON object"),
+        });
+    }
+    Ok(())
+}
+
+impl SignedDocument {
+    /// Sign the leaves of `document`, a JSON object of at most `sk.n` leaves
+    pub fn new(document: Value, sk: &SKrss, params: &Params) -> Result<Self, PSError> {
+        check_object(&document)?;
+        let messages = document_messages(&leaves(&document), sk.n)?;
+        let signature = RSSignature::new(&messages, sk, params)?;
+        Ok(Self {
+            document,
+            signature,
+        })
+    }
+
+    pub fn verify<K: RSSPublicKey>(&self, pk: &K) -> Result<bool, PSError> {
+        check_object(&self.document)?;
+        let messages = document_messages(&leaves(&self.document), pk.msg_count())?;
+        self.signature.verify(&messages, pk)
+    }
+
+    /// Disclose the leaves at or under each of `pointers` and redact the others. Fails with
+    /// `PSError::UnknownJsonPointer` for a pointer with no leaf.
+    pub fn redact<K: RSSPublicKey>(
+        &self,
+        pointers: &[&str],
+        pk: &K,
+    ) -> Result<RedactedDocument, PSError> {
+        let leaves = leaves(&self.document);
+        let messages = document_messages(&leaves, pk.msg_count())?;
+        let mut disclosed = BTreeMap::new();
+        for pointer in pointers {
+            let under: Vec<usize> = (0..leaves.len())
+                .filter(|i| leaves[*i].is_under(pointer))
+                .collect();
+            if under.is_empty() {
+                return Err(PSError::UnknownJsonPointer {
+                    pointer: pointer.to_string(),
+                });
+            }
+            for i in under {
+                disclosed.insert(i, leaves[i].clone());
+            }
+        }
+        let indices: HashSet<usize> = disclosed
+            .keys()
+            .cloned()
+            .chain(leaves.len()..messages.len())
+            .collect();
+        let signature = self.signature.derive(&messages, &indices, pk)?;
+        Ok(RedactedDocument {
+            leaf_count: leaves.len(),
+            disclosed,
+            signature,
+        })
+    }
+}
+
+impl RedactedDocument {
+    /// Verify the redacted signature on the disclosed leaves and the padding
+    pub fn verify<K: RSSPublicKey>(&self, pk: &K) -> Result<bool, PSError> {
+        let n = pk.msg_count();
+        if self.leaf_count > n {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: n,
+                given: self.leaf_count,
+            });
+        }
+        if let Some(idx) = self.disclosed.keys().find(|i| **i >= self.leaf_count) {
+            return Err(PSError::InvalidIndex {
+                idx: *idx,
+                valid: format!("[0, {})", self.leaf_count),
+            });
+        }
+        let disclosed_msgs: HashMap<usize, FieldElement> = self
+            .disclosed
+            .iter()
+            .map(|(i, leaf)| (*i, leaf.message()))
+            .chain((self.leaf_count..n).map(|i| (i, padding_message(i))))
+            .collect();
+        self.signature.verify(&disclosed_msgs, pk)
+    }
+
+    /// The document with only the disclosed leaves. Redacted members are absent and redacted array elements are
+    /// null, the paths of `disclosed` tell them apart from null values.
+    pub fn document(&self) -> Value {
+        let mut document = Value::Object(Map::new());
+        for leaf in self.disclosed.values() {
+            let mut node = &mut document;
+            for segment in &leaf.path {
+                node = match segment {
+                    PathSegment::Key(name) => {
+                        if !node.is_object() {
+                            *node = Value::Object(Map::new());
+                        }
+                        node.as_object_mut()
+                            .expect("Set to an object above")
+                            .entry(name.clone())
+                            .or_insert(Value::Null)
+                    }
+                    PathSegment::Index(i) => {
+                        if !node.is_array() {
+                            *node = Value::Array(vec![]);
+                        }
+                        let items = node.as_array_mut().expect("Set to an array above");
+                        if items.len() <= *i {
+                            items.resize(*i + 1, Value::Null);
+                        }
+                        &mut items[*i]
+                    }
+                };
+            }
+            *node = leaf.value.clone();
+        }
+        document
+    }
+
+    pub fn to_json(&self) -> Result<String, PSError> {
+        serde_json::to_string(self).map_err(|e| PSError::GeneralError { msg: e.to_string() })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, PSError> {
+        serde_json::from_str(json).map_err(|e| PSError::DeserializationError { msg: e.to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::rsskeygen;
+    use serde_json::json;
+
+    #[test]
+    fn test_canonicalize() {
+        let value = json!({
+            "b": [1, 2.5, -0.0, 1e21, 1e-7, 0.000001, 123.456, 100000000000000000000.0],
+            "a": "é\n\u{1}\"\\",
+            "\u{fb33}": true,
+            "\u{1f600}": null,
+            "c": {},
+        });
+        // U+1F600 is a surrogate pair in UTF-16 so it sorts before U+FB33, unlike in UTF-8
+        assert_eq!(
+            canonicalize(&value),
+            "{\"a\":\"é\\n\\u0001\\\"\\\\\",\"b\":[1,2.5,0,1e+21,1e-7,0.000001,123.456,100000000000000000000],\
+             \"c\":{},\"\u{1f600}\":null,\"\u{fb33}\":true}"
+        );
+        assert_eq!(es_number(-1.5e-9), "-1.5e-9");
+        assert_eq!(es_number(4.5), "4.5");
+    }
+
+    #[test]
+    fn test_signed_document() {
+        let params = Params::new("test".as_bytes());
+        let (sk, pk) = rsskeygen(10, &params).unwrap();
+        let document = json!({
+            "name": "Alice",
+            "birthdate": "1990-01-01",
+            "address": {"city": "Paris", "street": "1 rue de la Paix"},
+            "degrees": ["BSc", "MSc"],
+            "a/b": {},
+        });
+        let signed = SignedDocument::new(document.clone(), &sk, &params).unwrap();
+        assert!(signed.verify(&pk).unwrap());
+        assert_eq!(leaves(&document).len(), 7);
+
+        let redacted = signed
+            .redact(&["/name", "/address", "/degrees/1", "/a~1b"], &pk)
+            .unwrap();
+        assert_eq!(redacted.disclosed.len(), 5);
+        let redacted = RedactedDocument::from_json(&redacted.to_json().unwrap()).unwrap();
+        assert!(redacted.verify(&pk).unwrap());
+        assert_eq!(
+            redacted.document(),
+            json!({
+                "name": "Alice",
+                "address": {"city": "Paris", "street": "1 rue de la Paix"},
+                "degrees": [null, "MSc"],
+                "a/b": {},
+            })
+        );
+
+        // Changed value, value moved to another path and fewer leaves claimed
+        let name = |r: &mut RedactedDocument| {
+            r.disclosed
+                .values_mut()
+                .find(|l| l.value == json!("Alice"))
+                .unwrap()
+                .clone()
+        };
+        let mut changed = redacted.clone();
+        let mut leaf = name(&mut changed);
+        leaf.value = json!("Bob");
+        changed.disclosed.insert(6, leaf);
+        assert!(!changed.verify(&pk).unwrap());
+        let mut moved = redacted.clone();
+        let mut leaf = name(&mut moved);
+        leaf.path = vec![PathSegment::Key(String::from("birthdate"))];
+        moved.disclosed.insert(6, leaf);
+        assert!(!moved.verify(&pk).unwrap());
+        // "name" is the last leaf, it would be padding
+        let mut shorter = redacted.clone();
+        shorter.leaf_count -= 1;
+        assert!(shorter.verify(&pk).is_err());
+
+        match signed.redact(&["/address/zip"], &pk) {
+            Err(PSError::UnknownJsonPointer { pointer }) => assert_eq!(pointer, "/address/zip"),
+            r => panic!("Expected UnknownJsonPointer but got {:?}", r.map(|_| ())),
+        }
+        // A pointer is a prefix of paths, not of names
+        assert!(signed.redact(&["/nam"], &pk).is_err());
+        assert!(SignedDocument::new(json!([1, 2]), &sk, &params).is_err());
+        let (small_sk, _) = rsskeygen(3, &params).unwrap();
+        assert!(SignedDocument::new(document, &small_sk, &params).is_err());
+    }
+}
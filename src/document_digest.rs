@@ -0,0 +1,178 @@
+// Redactable signatures of documents whose digest is published, e.g. on a ledger by a notary. The digest is the root
+// of a `MerkleTree` over the messages of the document, see `merkle_payload`, and the signer signs the messages of
+// the document followed by the digest. A presentation discloses a subset of the messages with the digest and opens
+// each disclosed message to the digest, so the verifier checks that the signature verifies on the disclosed messages
+// and the digest it finds on the ledger, and that each disclosed message is the message at its index of the
+// document of that digest. The leaves are salted so the digest and the openings reveal nothing of the redacted
+// messages.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, SKrss};
+use crate::merkle_payload::{LeafOpening, MerkleTree};
+use crate::rss_signature::{RSSPublicKey, RSSignature, RedactedSignature};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Signature on a document and its digest, given by the signer to the holder with the tree of the digest
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DigestBoundSignature {
+    pub signature: RSSignature,
+    pub tree: MerkleTree,
+}
+
+/// Redacted signature on the disclosed messages of a document and its digest, each with its opening to the digest
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DigestPresentation {
+    pub signature: RedactedSignature,
+    pub digest: FieldElement,
+    pub disclosed: BTreeMap<usize, (FieldElement, LeafOpening)>,
+}
+
+fn leaves(document: &[FieldElement]) -> Vec<Vec<u8>> {
+    document.iter().map(|m| m.to_bytes()).collect()
+}
+
+/// Messages of the document followed by its digest
+fn signed_messages(document: &[FieldElement], digest: FieldElement) -> Vec<FieldElement> {
+    let mut messages = document.to_vec();
+    messages.push(digest);
+    messages
+}
+
+impl DigestBoundSignature {
+    /// Sign `document` and its digest with `sk`, a key for 1 message more than the document
+    pub fn new(document: &[FieldElement], sk: &SKrss, params: &Params) -> Result<Self, PSError> {
+        let tree = MerkleTree::new(&leaves(document))?;
+        let signature = RSSignature::new(&signed_messages(document, tree.root()), sk, params)?;
+        Ok(Self { signature, tree })
+    }
+
+    /// Digest of the document, to publish
+    pub fn digest(&self) -> FieldElement {
+        self.tree.root()
+    }
+
+    /// Verify the signature on `document` and the digest, and that the tree is over `document`
+    pub fn verify<K: RSSPublicKey>(
+        &self,
+        document: &[FieldElement],
+        pk: &K,
+    ) -> Result<bool, PSError> {
+        if document.len() != self.tree.leaf_count() {
+            return Ok(false);
+        }
+        let digest = self.digest();
+        for (i, leaf) in leaves(document).iter().enumerate() {
+            if !self.tree.open(i)?.verify(leaf, &digest) {
+                return Ok(false);
+            }
+        }
+        self.signature
+            .verify(&signed_messages(document, digest), pk)
+    }
+}
+
+impl DigestPresentation {
+    /// Derive a signature from `sig` on `document` disclosing the messages at `disclosed` and the digest
+    pub fn new<K: RSSPublicKey>(
+        sig: &DigestBoundSignature,
+        document: &[FieldElement],
+        disclosed: &HashSet<usize>,
+        pk: &K,
+    ) -> Result<Self, PSError> {
+        if document.len() != sig.tree.leaf_count() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: sig.tree.leaf_count(),
+                given: document.len(),
+            });
+        }
+        let digest = sig.digest();
+        let mut with_digest = disclosed.clone();
+        with_digest.insert(document.len());
+        let signature =
+            sig.signature
+                .derive(&signed_messages(document, digest.clone()), &with_digest, pk)?;
+        let mut opened = BTreeMap::new();
+        for i in disclosed {
+            opened.insert(*i, (document[*i].clone(), sig.tree.open(*i)?));
+        }
+        Ok(Self {
+            signature,
+            digest,
+            disclosed: opened,
+        })
+    }
+
+    /// Verify the presentation against `digest`, the published digest of the document
+    pub fn verify<K: RSSPublicKey>(&self, digest: &FieldElement, pk: &K) -> Result<bool, PSError> {
+        if &self.digest != digest {
+            return Ok(false);
+        }
+        // The digest is the last message, after the document
+        let document_len = match pk.msg_count().checked_sub(1) {
+            Some(len) => len,
+            None => return Ok(false),
+        };
+        let mut disclosed_msgs = HashMap::new();
+        for (i, (m, opening)) in &self.disclosed {
+            if opening.index != *i
+                || opening.leaf_count != document_len
+                || !opening.verify(&m.to_bytes(), digest)
+            {
+                return Ok(false);
+            }
+            disclosed_msgs.insert(*i, m.clone());
+        }
+        disclosed_msgs.insert(document_len, digest.clone());
+        self.signature.verify(&disclosed_msgs, pk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::rsskeygen;
+
+    #[test]
+    fn test_document_digest() {
+        let params = Params::new("test".as_bytes());
+        let len = 5;
+        let (sk, pk) = rsskeygen(len + 1, &params).unwrap();
+        let document: Vec<FieldElement> = FieldElementVector::random(len).into();
+        let sig = DigestBoundSignature::new(&document, &sk, &params).unwrap();
+        assert!(sig.verify(&document, &pk).unwrap());
+        let mut other_document = document.clone();
+        other_document[2] = FieldElement::random();
+        assert!(!sig.verify(&other_document, &pk).unwrap());
+        assert!(!sig.verify(&document[..len - 1], &pk).unwrap());
+        let published = sig.digest();
+
+        for disclosed in &[vec![], vec![0], vec![1, 3], vec![0, 1, 2, 3, 4]] {
+            let disclosed: HashSet<usize> = disclosed.iter().cloned().collect();
+            let presentation = DigestPresentation::new(&sig, &document, &disclosed, &pk).unwrap();
+            assert!(presentation.verify(&published, &pk).unwrap());
+            assert!(!presentation.verify(&FieldElement::random(), &pk).unwrap());
+        }
+
+        // A disclosed message changed, moved to another index or opened to another digest
+        let disclosed: HashSet<usize> = vec![1, 3].into_iter().collect();
+        let presentation = DigestPresentation::new(&sig, &document, &disclosed, &pk).unwrap();
+        let mut changed = presentation.clone();
+        changed.disclosed.get_mut(&1).unwrap().0 = FieldElement::random();
+        assert!(!changed.verify(&published, &pk).unwrap());
+        let mut moved = presentation.clone();
+        let entry = moved.disclosed.remove(&3).unwrap();
+        moved.disclosed.insert(2, entry);
+        assert!(!moved.verify(&published, &pk).unwrap());
+
+        // A signature of the same document with another digest does not verify against the published one
+        let other_sig = DigestBoundSignature::new(&document, &sk, &params).unwrap();
+        let other = DigestPresentation::new(&other_sig, &document, &disclosed, &pk).unwrap();
+        assert!(other.verify(&other_sig.digest(), &pk).unwrap());
+        assert!(!other.verify(&published, &pk).unwrap());
+        let mut mixed = other.clone();
+        mixed.digest = published.clone();
+        assert!(!mixed.verify(&published, &pk).unwrap());
+    }
+}
@@ -0,0 +1,368 @@
+// Encodings of field elements for interoperating with libraries that disagree on the byte order or size of scalars.
+// The native encoding of the crate, of `to_bytes` and `from_bytes` of every type, is 48 bytes big endian, the size
+// of the modulus of the base field. As the curve order is of 255 bits, scalars also fit 32 bytes, the size most
+// other libraries use, whose byte order varies. An `Encoding` picks the byte order and the size and applies to every
+// field element in the encoding of a type, the other parts, counts and group elements, staying as in the native
+// encoding. A type describes its native encoding as `Segment`s of scalars and of other bytes, from which
+// `EncodedBytes` converts its encoding in one `Encoding` to another. Decoding rejects scalars of at least the curve
+// order rather than reducing them so that every value has a single encoding.
+
+use crate::curve::{FieldElement, FieldElement_SIZE};
+use crate::errors::PSError;
+use crate::keys::{PKrss, SKrss, Sigkey, Verkey, PKRSS_HEADER_SIZE};
+use crate::pok_sig::PoKOfSignatureProof;
+use crate::proof_request::Credential;
+use crate::rss_signature::{RSSignature, RedactedSignature};
+use crate::signature::Signature;
+use crate::{SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ByteOrder {
+    BigEndian,
+    LittleEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScalarSize {
+    Bytes32,
+    Bytes48,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Encoding {
+    pub byte_order: ByteOrder,
+    pub scalar_size: ScalarSize,
+}
+
+/// Part of the native encoding of a type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Segment {
+    /// Bytes that are the same in every encoding
+    Bytes(usize),
+    /// Count of consecutive field elements
+    Scalars(usize),
+}
+
+fn invalid(msg: String) -> PSError {
+    PSError::DeserializationError { msg }
+}
+
+impl Encoding {
+    /// Encoding of `to_bytes`, 48 bytes big endian
+    pub const NATIVE: Encoding = Encoding {
+        byte_order: ByteOrder::BigEndian,
+        scalar_size: ScalarSize::Bytes48,
+    };
+
+    pub fn new(byte_order: ByteOrder, scalar_size: ScalarSize) -> Self {
+        Self {
+            byte_order,
+            scalar_size,
+        }
+    }
+
+    /// Size in bytes of a field element
+    pub fn scalar_bytes(&self) -> usize {
+        match self.scalar_size {
+            ScalarSize::Bytes32 => 32,
+            ScalarSize::Bytes48 => FieldElement_SIZE,
+        }
+    }
+
+    pub fn encode_scalar(&self, elem: &FieldElement) -> Vec<u8> {
+        let bytes = elem.to_bytes();
+        let mut encoded = bytes[FieldElement_SIZE - self.scalar_bytes()..].to_vec();
+        if self.byte_order == ByteOrder::LittleEndian {
+            encoded.reverse();
+        }
+        encoded
+    }
+
+    /// Field element of `bytes`, failing unless they are of `scalar_bytes` bytes and less than the curve order
+    pub fn decode_scalar(&self, bytes: &[u8]) -> Result<FieldElement, PSError> {
+        if bytes.len() != self.scalar_bytes() {
+            return Err(invalid(format!(
+                "Incorrect number of bytes {} for field element of {} bytes",
+                bytes.len(),
+                self.scalar_bytes()
+            )));
+        }
+        let mut native = vec![0u8; FieldElement_SIZE - bytes.len()];
+        native.extend_from_slice(bytes);
+        if self.byte_order == ByteOrder::LittleEndian {
+            native[FieldElement_SIZE - bytes.len()..].reverse();
+        }
+        let elem = FieldElement::from_bytes(&native).map_err(|e| invalid(format!("{}", e)))?;
+        if elem.to_bytes() != native {
+            return Err(invalid(String::from("Field element is not reduced")));
+        }
+        Ok(elem)
+    }
+
+    /// `bytes`, in the encoding of a type in `from` of layout `layout`, in the encoding `to`
+    fn convert_segments(
+        bytes: &[u8],
+        layout: &[Segment],
+        from: &Encoding,
+        to: &Encoding,
+    ) -> Result<Vec<u8>, PSError> {
+        let expected: usize = layout
+            .iter()
+            .map(|s| match s {
+                Segment::Bytes(len) => *len,
+                Segment::Scalars(count) => count * from.scalar_bytes(),
+            })
+            .sum();
+        if bytes.len() != expected {
+            return Err(invalid(format!(
+                "Incorrect number of bytes {}, expected {}",
+                bytes.len(),
+                expected
+            )));
+        }
+        let mut converted = Vec::with_capacity(bytes.len());
+        let mut rest = bytes;
+        for segment in layout {
+            match segment {
+                Segment::Bytes(len) => {
+                    let (b, r) = rest.split_at(*len);
+                    converted.extend_from_slice(b);
+                    rest = r;
+                }
+                Segment::Scalars(count) => {
+                    let (b, r) = rest.split_at(count * from.scalar_bytes());
+                    for chunk in b.chunks(from.scalar_bytes()) {
+                        converted.append(&mut to.encode_scalar(&from.decode_scalar(chunk)?));
+                    }
+                    rest = r;
+                }
+            }
+        }
+        Ok(converted)
+    }
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Self::NATIVE
+    }
+}
+
+/// Types whose encoding follows an `Encoding`
+pub trait EncodedBytes: Sized {
+    fn native_bytes(&self) -> Vec<u8>;
+
+    fn from_native_bytes(bytes: &[u8]) -> Result<Self, PSError>;
+
+    /// Layout of the encoding `bytes` with field elements of `scalar_bytes` bytes. Only reads what tells the counts
+    /// of elements, `to_bytes_with` and `from_bytes_with` check the length.
+    fn layout(bytes: &[u8], scalar_bytes: usize) -> Result<Vec<Segment>, PSError>;
+
+    fn to_bytes_with(&self, encoding: &Encoding) -> Vec<u8> {
+        let native = self.native_bytes();
+        let layout = Self::layout(&native, FieldElement_SIZE)
+            .expect("The native encoding has a valid layout");
+        Encoding::convert_segments(&native, &layout, &Encoding::NATIVE, encoding)
+            .expect("The native encoding converts to any encoding")
+    }
+
+    fn from_bytes_with(bytes: &[u8], encoding: &Encoding) -> Result<Self, PSError> {
+        let layout = Self::layout(bytes, encoding.scalar_bytes())?;
+        let native = Encoding::convert_segments(bytes, &layout, encoding, &Encoding::NATIVE)?;
+        Self::from_native_bytes(&native)
+    }
+}
+
+/// The encoding `bytes` of a `T` in `from` in the encoding `to`
+pub fn convert<T: EncodedBytes>(
+    bytes: &[u8],
+    from: &Encoding,
+    to: &Encoding,
+) -> Result<Vec<u8>, PSError> {
+    Ok(T::from_bytes_with(bytes, from)?.to_bytes_with(to))
+}
+
+/// Count of messages in the header of a key of the encoding `bytes`
+fn header_count(bytes: &[u8]) -> Result<usize, PSError> {
+    PKrss::msg_count_from_header(bytes)
+}
+
+macro_rules! impl_encoded_bytes {
+    ( $type:ident, $layout:ident ) => {
+        impl EncodedBytes for $type {
+            fn native_bytes(&self) -> Vec<u8> {
+                self.to_bytes()
+            }
+
+            fn from_native_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+                Self::from_bytes(bytes)
+            }
+
+            fn layout(bytes: &[u8], scalar_bytes: usize) -> Result<Vec<Segment>, PSError> {
+                $layout(bytes, scalar_bytes)
+            }
+        }
+    };
+}
+
+/// Types without field elements, whose encoding is the same in every `Encoding`
+fn no_scalars(bytes: &[u8], _: usize) -> Result<Vec<Segment>, PSError> {
+    Ok(vec![Segment::Bytes(bytes.len())])
+}
+
+fn sigkey_layout(bytes: &[u8], _: usize) -> Result<Vec<Segment>, PSError> {
+    Ok(vec![
+        Segment::Bytes(PKRSS_HEADER_SIZE),
+        Segment::Scalars(header_count(bytes)? + 1),
+    ])
+}
+
+fn skrss_layout(bytes: &[u8], _: usize) -> Result<Vec<Segment>, PSError> {
+    header_count(bytes)?;
+    Ok(vec![Segment::Bytes(PKRSS_HEADER_SIZE), Segment::Scalars(2)])
+}
+
+fn verkey_layout(bytes: &[u8], _: usize) -> Result<Vec<Segment>, PSError> {
+    let n = header_count(bytes)?;
+    Ok(vec![
+        Segment::Bytes(PKRSS_HEADER_SIZE),
+        Segment::Scalars(1),
+        Segment::Bytes((n + 1) * VERKEY_GROUP_SIZE),
+    ])
+}
+
+fn pkrss_layout(bytes: &[u8], _: usize) -> Result<Vec<Segment>, PSError> {
+    let n = header_count(bytes)?;
+    Ok(vec![
+        Segment::Bytes(PKRSS_HEADER_SIZE),
+        Segment::Scalars(1),
+        Segment::Bytes(2 * n * SIGNATURE_GROUP_SIZE + (n + 2) * VERKEY_GROUP_SIZE),
+    ])
+}
+
+/// signature || verkey || messages
+fn credential_layout(bytes: &[u8], scalar_bytes: usize) -> Result<Vec<Segment>, PSError> {
+    let sig_size = 2 * SIGNATURE_GROUP_SIZE;
+    if bytes.len() < sig_size {
+        return Err(invalid(format!(
+            "Incorrect number of bytes {} for credential",
+            bytes.len()
+        )));
+    }
+    let n = header_count(&bytes[sig_size..])?;
+    let mut layout = vec![Segment::Bytes(sig_size)];
+    layout.append(&mut verkey_layout(&bytes[sig_size..], scalar_bytes)?);
+    layout.push(Segment::Scalars(n));
+    Ok(layout)
+}
+
+/// sig || J || commitment || responses, the count of responses given by the length
+fn proof_layout(bytes: &[u8], scalar_bytes: usize) -> Result<Vec<Segment>, PSError> {
+    let fixed_size = 2 * SIGNATURE_GROUP_SIZE + 2 * VERKEY_GROUP_SIZE;
+    if bytes.len() < fixed_size || !(bytes.len() - fixed_size).is_multiple_of(scalar_bytes) {
+        return Err(invalid(format!(
+            "Incorrect number of bytes {} for proof",
+            bytes.len()
+        )));
+    }
+    Ok(vec![
+        Segment::Bytes(fixed_size),
+        Segment::Scalars((bytes.len() - fixed_size) / scalar_bytes),
+    ])
+}
+
+impl_encoded_bytes!(Signature, no_scalars);
+impl_encoded_bytes!(RSSignature, no_scalars);
+impl_encoded_bytes!(RedactedSignature, no_scalars);
+impl_encoded_bytes!(PKrss, pkrss_layout);
+impl_encoded_bytes!(Sigkey, sigkey_layout);
+impl_encoded_bytes!(SKrss, skrss_layout);
+impl_encoded_bytes!(Verkey, verkey_layout);
+impl_encoded_bytes!(Credential, credential_layout);
+impl_encoded_bytes!(PoKOfSignatureProof, proof_layout);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::{keygen, rsskeygen, Params};
+    use crate::pok_sig::PoKOfSignature;
+    use std::collections::HashSet;
+
+    fn encodings() -> Vec<Encoding> {
+        let mut encodings = vec![];
+        for byte_order in &[ByteOrder::BigEndian, ByteOrder::LittleEndian] {
+            for scalar_size in &[ScalarSize::Bytes32, ScalarSize::Bytes48] {
+                encodings.push(Encoding::new(*byte_order, *scalar_size));
+            }
+        }
+        encodings
+    }
+
+    fn check_encodings<T: EncodedBytes>(value: &T) {
+        let native = value.native_bytes();
+        assert_eq!(value.to_bytes_with(&Encoding::NATIVE), native);
+        for from in encodings() {
+            let bytes = value.to_bytes_with(&from);
+            assert_eq!(
+                T::from_bytes_with(&bytes, &from).unwrap().native_bytes(),
+                native
+            );
+            for to in encodings() {
+                assert_eq!(
+                    convert::<T>(&bytes, &from, &to).unwrap(),
+                    value.to_bytes_with(&to)
+                );
+            }
+            assert!(T::from_bytes_with(&bytes[..bytes.len() - 1], &from).is_err());
+        }
+    }
+
+    #[test]
+    fn test_encodings() {
+        let one = FieldElement::one();
+        let le_32 = Encoding::new(ByteOrder::LittleEndian, ScalarSize::Bytes32);
+        let be_32 = Encoding::new(ByteOrder::BigEndian, ScalarSize::Bytes32);
+        let mut expected = vec![0u8; 32];
+        expected[0] = 1;
+        assert_eq!(le_32.encode_scalar(&one), expected);
+        expected.reverse();
+        assert_eq!(be_32.encode_scalar(&one), expected);
+        assert_eq!(Encoding::NATIVE.encode_scalar(&one), one.to_bytes());
+        for encoding in encodings() {
+            let elem = FieldElement::random();
+            let bytes = encoding.encode_scalar(&elem);
+            assert_eq!(bytes.len(), encoding.scalar_bytes());
+            assert_eq!(encoding.decode_scalar(&bytes).unwrap(), elem);
+            // Bytes of the curve order or more are not reduced
+            assert!(encoding.decode_scalar(&vec![0xff; bytes.len()]).is_err());
+            assert!(encoding.decode_scalar(&bytes[1..]).is_err());
+        }
+
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(3).into();
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        check_encodings(&sk);
+        check_encodings(&vk);
+        check_encodings(&sig);
+        check_encodings(&Credential {
+            signature: sig.clone(),
+            messages: msgs.clone(),
+            verkey: vk.clone(),
+        });
+        let revealed: HashSet<usize> = vec![1].into_iter().collect();
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs, None, revealed).unwrap();
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        check_encodings(&pok.gen_proof(&challenge).unwrap());
+        let (sk_rss, pk_rss) = rsskeygen(2, &params).unwrap();
+        check_encodings(&sk_rss);
+        check_encodings(&pk_rss);
+        // Only the scalars change, the verkey of 32 byte scalars is 16 bytes shorter
+        assert_eq!(
+            vk.to_bytes_with(&le_32).len() + 16,
+            vk.to_bytes_with(&Encoding::NATIVE).len()
+        );
+        assert_eq!(sig.to_bytes_with(&le_32), sig.to_bytes());
+    }
+}
@@ -0,0 +1,147 @@
+// Detached and attached signatures of documents of blocks of bytes, for archival systems choosing how to store
+// documents. Each block is signed as the message `block_message`, the hash of "PS document block : " || the block,
+// with a key for as many messages as blocks. A `DetachedSignature` is the signature alone and verifies with the
+// messages of the blocks, stored separately, e.g. only their hashes in an index with the blocks in another store.
+// An `AttachedEnvelope` has the blocks with the signature and verifies on its own. Both are the same signature, so
+// `AttachedEnvelope::detach` and `DetachedSignature::attach` convert between them without the signing key, and
+// `verify` takes either as a `SignedBlocks`.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::signature::Signature;
+
+const BLOCK_DOMAIN: &[u8] = b"PS document block : ";
+
+/// Signature of a document whose blocks are stored separately
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DetachedSignature {
+    pub sig: Signature,
+}
+
+/// Blocks of a document with their signature
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttachedEnvelope {
+    pub blocks: Vec<Vec<u8>>,
+    pub sig: Signature,
+}
+
+/// Signed document in either layout, for `verify`
+#[derive(Clone, Copy, Debug)]
+pub enum SignedBlocks<'a> {
+    /// Signature with the messages of the blocks, see `block_messages`
+    Detached {
+        sig: &'a DetachedSignature,
+        messages: &'a [FieldElement],
+    },
+    Attached(&'a AttachedEnvelope),
+}
+
+/// Message signed for `block`
+pub fn block_message(block: &[u8]) -> FieldElement {
+    FieldElement::from_msg_hash(&[BLOCK_DOMAIN, block].concat())
+}
+
+/// Messages signed for the blocks in the same order
+pub fn block_messages<B: AsRef<[u8]>>(blocks: &[B]) -> Vec<FieldElement> {
+    blocks.iter().map(|b| block_message(b.as_ref())).collect()
+}
+
+impl DetachedSignature {
+    /// Sign the blocks of `messages`, from `block_messages`. `sigkey` must be for `messages.len()` messages.
+    pub fn new(
+        messages: &[FieldElement],
+        sigkey: &Sigkey,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        Ok(Self {
+            sig: Signature::new(messages, sigkey, params)?,
+        })
+    }
+
+    /// Envelope of the signature with `blocks`. Does not check the signature is of them.
+    pub fn attach(self, blocks: Vec<Vec<u8>>) -> AttachedEnvelope {
+        AttachedEnvelope {
+            blocks,
+            sig: self.sig,
+        }
+    }
+}
+
+impl AttachedEnvelope {
+    /// Sign `blocks`. `sigkey` must be for `blocks.len()` messages.
+    pub fn new(blocks: Vec<Vec<u8>>, sigkey: &Sigkey, params: &Params) -> Result<Self, PSError> {
+        let sig = Signature::new(&block_messages(&blocks), sigkey, params)?;
+        Ok(Self { blocks, sig })
+    }
+
+    /// The signature and the blocks, to store separately
+    pub fn detach(self) -> (DetachedSignature, Vec<Vec<u8>>) {
+        (DetachedSignature { sig: self.sig }, self.blocks)
+    }
+}
+
+/// Verify a signed document, detached or attached, with `vk`, a key for as many messages as blocks
+pub fn verify(signed: SignedBlocks, vk: &Verkey, params: &Params) -> Result<bool, PSError> {
+    match signed {
+        SignedBlocks::Detached { sig, messages } => sig.sig.verify(messages.to_vec(), vk, params),
+        SignedBlocks::Attached(envelope) => {
+            envelope
+                .sig
+                .verify(block_messages(&envelope.blocks), vk, params)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_detached_and_attached() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let blocks: Vec<Vec<u8>> = vec![b"title".to_vec(), b"body".to_vec(), vec![]];
+        let envelope = AttachedEnvelope::new(blocks.clone(), &sk, &params).unwrap();
+        assert!(verify(SignedBlocks::Attached(&envelope), &vk, &params).unwrap());
+
+        // Detached, the verifier has the messages of the blocks only
+        let (detached, stored) = envelope.clone().detach();
+        let messages = block_messages(&stored);
+        let signed = SignedBlocks::Detached {
+            sig: &detached,
+            messages: &messages,
+        };
+        assert!(verify(signed, &vk, &params).unwrap());
+        let signed_directly = DetachedSignature::new(&messages, &sk, &params).unwrap();
+        assert!(verify(
+            SignedBlocks::Detached {
+                sig: &signed_directly,
+                messages: &messages
+            },
+            &vk,
+            &params
+        )
+        .unwrap());
+        let reattached = signed_directly.attach(blocks.clone());
+        assert!(verify(SignedBlocks::Attached(&reattached), &vk, &params).unwrap());
+
+        // Changed or reordered blocks do not verify in either layout
+        let mut tampered = envelope.clone();
+        tampered.blocks.swap(0, 1);
+        assert!(!verify(SignedBlocks::Attached(&tampered), &vk, &params).unwrap());
+        let mut wrong = messages.clone();
+        wrong[2] = block_message(b"appended");
+        let signed = SignedBlocks::Detached {
+            sig: &detached,
+            messages: &wrong,
+        };
+        assert!(!verify(signed, &vk, &params).unwrap());
+        // The key is for as many messages as blocks
+        assert!(AttachedEnvelope::new(blocks[..2].to_vec(), &sk, &params).is_err());
+        let mut fewer = envelope;
+        fewer.blocks.pop();
+        assert!(verify(SignedBlocks::Attached(&fewer), &vk, &params).is_err());
+    }
+}
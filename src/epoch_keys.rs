@@ -0,0 +1,171 @@
+// Time-boxed signing keys for issuers signing many credentials or tokens. The master signing key stays offline and
+// derives a signing key per epoch: x + d_0 and y_i + d_i where each offset d is a hash of the master signing key, the
+// epoch and the index, so the key of an epoch tells nothing of the keys of other epochs and its compromise only
+// allows forging signatures of that epoch. The verkey of the epoch is certified by a signature of the master key on
+// a hash of the epoch and the verkey, created with the prefix of the master key for 1 message, so verifiers only
+// need the master verkey. The master key is used for certificates only and never signs credentials.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::signature::Signature;
+
+const EPOCH_KEY_DOMAIN: &[u8] = b"PS epoch key : ";
+const EPOCH_CERTIFICATE_DOMAIN: &[u8] = b"PS epoch certificate : ";
+
+/// Verkey of an epoch with its certificate from the master key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EpochVerkey {
+    pub epoch: u64,
+    pub verkey: Verkey,
+    /// Signature of the prefix of the master key for 1 message on `certificate_message`
+    pub certificate: Signature,
+}
+
+/// Keys of an epoch, derived by the issuer from its master key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EpochKeys {
+    pub sigkey: Sigkey,
+    pub verkey: EpochVerkey,
+}
+
+/// Offset of element `index` of the key of `epoch`, 0 for x and i+1 for y_i
+fn offset(master_bytes: &[u8], epoch: u64, index: usize) -> FieldElement {
+    let mut bytes = EPOCH_KEY_DOMAIN.to_vec();
+    bytes.extend_from_slice(master_bytes);
+    bytes.extend_from_slice(&epoch.to_be_bytes());
+    bytes.extend_from_slice(&(index as u64).to_be_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+fn certificate_message(epoch: u64, verkey: &Verkey) -> FieldElement {
+    let mut bytes = EPOCH_CERTIFICATE_DOMAIN.to_vec();
+    bytes.extend_from_slice(&epoch.to_be_bytes());
+    bytes.append(&mut verkey.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl EpochKeys {
+    /// Keys of `epoch` derived from `master_sk`, the same for the same epoch, with the certificate of the verkey
+    pub fn derive(master_sk: &Sigkey, epoch: u64, params: &Params) -> Result<Self, PSError> {
+        let master_bytes = master_sk.to_bytes();
+        let sigkey = Sigkey {
+            x: &master_sk.x + &offset(&master_bytes, epoch, 0),
+            y: master_sk
+                .y
+                .iter()
+                .enumerate()
+                .map(|(i, y_i)| y_i + &offset(&master_bytes, epoch, i + 1))
+                .collect(),
+        };
+        sigkey.check_quality()?;
+        let verkey = sigkey.to_verkey(params);
+        let certificate = Signature::new(
+            &[certificate_message(epoch, &verkey)],
+            &master_sk.prefix(1)?,
+            params,
+        )?;
+        Ok(Self {
+            sigkey,
+            verkey: EpochVerkey {
+                epoch,
+                verkey,
+                certificate,
+            },
+        })
+    }
+}
+
+impl EpochVerkey {
+    /// Whether this is the verkey of `epoch` certified by the master key `master_vk`
+    pub fn verify(&self, epoch: u64, master_vk: &Verkey, params: &Params) -> Result<bool, PSError> {
+        if self.epoch != epoch {
+            return Ok(false);
+        }
+        self.verkey.check_params(params)?;
+        self.certificate.verify(
+            vec![certificate_message(self.epoch, &self.verkey)],
+            &master_vk.prefix(1)?,
+            params,
+        )
+    }
+
+    /// Verify a signature of `epoch` with this verkey, failing unless it is certified by `master_vk` for `epoch`
+    pub fn verify_signature(
+        &self,
+        sig: &Signature,
+        messages: Vec<FieldElement>,
+        epoch: u64,
+        master_vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        if !self.verify(epoch, master_vk, params)? {
+            return Err(PSError::GeneralError {
+                msg: format!("Verkey is not certified for epoch {}", epoch),
+            });
+        }
+        sig.verify(messages, &self.verkey, params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_epoch_keys() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let (master_sk, master_vk) = keygen(count_msgs, &params).unwrap();
+        let keys_1 = EpochKeys::derive(&master_sk, 1, &params).unwrap();
+        let keys_2 = EpochKeys::derive(&master_sk, 2, &params).unwrap();
+        assert!(keys_1.verkey.verify(1, &master_vk, &params).unwrap());
+        assert!(keys_2.verkey.verify(2, &master_vk, &params).unwrap());
+        assert_eq!(
+            EpochKeys::derive(&master_sk, 1, &params)
+                .unwrap()
+                .verkey
+                .verkey,
+            keys_1.verkey.verkey
+        );
+        // Offsets differ per epoch and element, so a key of an epoch minus the master key is not that of another
+        assert_ne!(keys_1.verkey.verkey, keys_2.verkey.verkey);
+        assert_ne!(
+            &keys_1.sigkey.x - &master_sk.x,
+            &keys_2.sigkey.x - &master_sk.x
+        );
+        assert_ne!(
+            &keys_1.sigkey.y[0] - &master_sk.y[0],
+            &keys_1.sigkey.y[1] - &master_sk.y[1]
+        );
+
+        let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        let sig = Signature::new(&msgs, &keys_1.sigkey, &params).unwrap();
+        assert!(keys_1
+            .verkey
+            .verify_signature(&sig, msgs.clone(), 1, &master_vk, &params)
+            .unwrap());
+        assert!(!keys_2
+            .verkey
+            .verify_signature(&sig, msgs.clone(), 2, &master_vk, &params)
+            .unwrap());
+        assert!(!sig.verify(msgs.clone(), &master_vk, &params).unwrap());
+
+        // Certificate for another epoch, of another master key or of another verkey
+        assert!(!keys_1.verkey.verify(2, &master_vk, &params).unwrap());
+        assert!(keys_1
+            .verkey
+            .verify_signature(&sig, msgs.clone(), 2, &master_vk, &params)
+            .is_err());
+        let (_, other_vk) = keygen(count_msgs, &params).unwrap();
+        assert!(!keys_1.verkey.verify(1, &other_vk, &params).unwrap());
+        let mut forged = keys_1.verkey.clone();
+        forged.verkey = other_vk;
+        assert!(!forged.verify(1, &master_vk, &params).unwrap());
+        let mut relabeled = keys_1.verkey.clone();
+        relabeled.epoch = 2;
+        assert!(!relabeled.verify(2, &master_vk, &params).unwrap());
+    }
+}
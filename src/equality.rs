@@ -0,0 +1,188 @@
+// Equality of keys and signatures, for deduplicating keys and comparing signatures in caches and tests. Public types
+// are equal when their canonical encodings are, so that representations of the same group elements with different
+// coordinates compare equal. Secret types compare their encodings in constant time: the time only depends on their
+// lengths, which are the public counts of messages. Verkeys and public keys of the redactable scheme hash by their
+// fingerprint, see `inspect`, for use in `HashMap`s and `HashSet`s.
+
+use crate::blind_signature::BlindingKey;
+use crate::curve::GroupElement;
+use crate::inspect::fingerprint;
+use crate::keys::{PKrss, Params, SKrss, Sigkey, Verkey};
+use crate::link_secret::LinkSecret;
+use crate::rss_signature::{RSSignature, RedactedSignature};
+use crate::signature::Signature;
+use std::hash::{Hash, Hasher};
+
+/// Whether `a` and `b` are equal, in time depending only on their lengths
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
+
+trait CanonicalBytes {
+    fn canonical_bytes(&self) -> Vec<u8>;
+}
+
+macro_rules! impl_canonical_eq {
+    ( $type:ident, $eq:path ) => {
+        impl PartialEq for $type {
+            fn eq(&self, other: &Self) -> bool {
+                $eq(&self.canonical_bytes(), &other.canonical_bytes())
+            }
+        }
+
+        impl Eq for $type {}
+    };
+}
+
+macro_rules! impl_canonical_bytes_with_to_bytes {
+    ( $( $type:ident ),* ) => {
+        $(
+            impl CanonicalBytes for $type {
+                fn canonical_bytes(&self) -> Vec<u8> {
+                    self.to_bytes()
+                }
+            }
+        )*
+    };
+}
+
+impl_canonical_bytes_with_to_bytes!(
+    Sigkey,
+    Verkey,
+    SKrss,
+    PKrss,
+    Signature,
+    RSSignature,
+    RedactedSignature
+);
+
+impl CanonicalBytes for Params {
+    /// g || g_tilde || label_hash
+    fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.g.to_bytes();
+        bytes.append(&mut self.g_tilde.to_bytes());
+        bytes.append(&mut self.label_hash.to_bytes());
+        bytes
+    }
+}
+
+impl CanonicalBytes for BlindingKey {
+    /// Count of messages n as 8 bytes big endian followed by X || Y_1..Y_n
+    fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.Y.len() as u64).to_be_bytes().to_vec();
+        bytes.append(&mut self.X.to_bytes());
+        for Y_i in &self.Y {
+            bytes.append(&mut Y_i.to_bytes());
+        }
+        bytes
+    }
+}
+
+impl CanonicalBytes for LinkSecret {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        self.secret().to_bytes()
+    }
+}
+
+fn bytes_eq(a: &[u8], b: &[u8]) -> bool {
+    a == b
+}
+
+impl_canonical_eq!(Params, bytes_eq);
+impl_canonical_eq!(Verkey, bytes_eq);
+impl_canonical_eq!(PKrss, bytes_eq);
+impl_canonical_eq!(BlindingKey, bytes_eq);
+impl_canonical_eq!(Signature, bytes_eq);
+impl_canonical_eq!(RSSignature, bytes_eq);
+impl_canonical_eq!(RedactedSignature, bytes_eq);
+impl_canonical_eq!(Sigkey, constant_time_eq);
+impl_canonical_eq!(SKrss, constant_time_eq);
+impl_canonical_eq!(LinkSecret, constant_time_eq);
+
+impl Hash for Verkey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        fingerprint(&self.to_bytes()).hash(state)
+    }
+}
+
+impl Hash for PKrss {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        fingerprint(&self.to_bytes()).hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{FieldElement, FieldElementVector};
+    use crate::keys::{keygen, rsskeygen};
+    use crate::SignatureGroup;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_equality() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+
+        let params = Params::new("test".as_bytes());
+        assert_eq!(params, Params::new("test".as_bytes()));
+        assert_ne!(params, Params::new("other".as_bytes()));
+
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let (other_sk, other_vk) = keygen(3, &params).unwrap();
+        assert_eq!(sk, sk.clone());
+        assert_ne!(sk, other_sk);
+        assert_ne!(sk, sk.prefix(2).unwrap());
+        assert_eq!(sk.to_verkey(&params), vk);
+        assert_ne!(vk, other_vk);
+        assert_eq!(
+            BlindingKey::new(&sk, &params),
+            BlindingKey::new(&sk, &params)
+        );
+        assert_ne!(
+            BlindingKey::new(&sk, &params),
+            BlindingKey::new(&other_sk, &params)
+        );
+        let link_secret = LinkSecret::new();
+        assert_eq!(link_secret, link_secret.clone());
+        assert_ne!(link_secret, LinkSecret::new());
+
+        // The same point in other coordinates is equal
+        let msgs: Vec<FieldElement> = FieldElementVector::random(3).into();
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        let mut doubled = sig.clone();
+        doubled.sigma_1 = &(&sig.sigma_1 + &sig.sigma_1) - &sig.sigma_1;
+        assert_eq!(doubled, sig);
+        doubled.sigma_1 = &sig.sigma_1 + &SignatureGroup::random();
+        assert_ne!(doubled, sig);
+
+        // Deduplication of verkeys
+        let keys: HashSet<Verkey> = vec![vk.clone(), other_vk.clone(), vk.clone()]
+            .into_iter()
+            .collect();
+        assert_eq!(keys.len(), 2);
+        assert!(keys.contains(&other_vk));
+
+        let (sk_rss, pk_rss) = rsskeygen(4, &params).unwrap();
+        let (other_sk_rss, other_pk_rss) = rsskeygen(4, &params).unwrap();
+        assert_eq!(sk_rss, sk_rss.clone());
+        assert_ne!(sk_rss, other_sk_rss);
+        let keys: HashSet<PKrss> = vec![pk_rss.clone(), other_pk_rss, pk_rss.clone()]
+            .into_iter()
+            .collect();
+        assert_eq!(keys.len(), 2);
+        let msgs: Vec<FieldElement> = FieldElementVector::random(4).into();
+        let sig = RSSignature::new(&msgs, &sk_rss, &params).unwrap();
+        assert_eq!(sig, sig.clone());
+        assert_ne!(sig, RSSignature::new(&msgs, &sk_rss, &params).unwrap());
+        let disclosed: HashSet<usize> = vec![1, 3].into_iter().collect();
+        let redacted = sig.derive(&msgs, &disclosed, &pk_rss).unwrap();
+        assert_eq!(redacted, redacted.clone());
+        assert_ne!(redacted, sig.derive(&msgs, &disclosed, &pk_rss).unwrap());
+    }
+}
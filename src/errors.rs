@@ -1,4 +1,7 @@
-use failure::Error;
+use std::convert::TryFrom;
+#[cfg(feature = "io-errors")]
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
 
 #[derive(Debug, Fail)]
 pub enum PSError {
@@ -8,22 +11,492 @@ pub enum PSError {
     )]
     UnsupportedNoOfMessages { expected: usize, given: usize },
 
+    #[fail(
+        display = "Keys can be generated for {} to {} messages but given {} messages",
+        min, max, given
+    )]
+    UnsupportedMessageCount { given: usize, min: usize, max: usize },
+
     #[fail(
         display = "Same no of bases and exponents required. {} bases and {} exponents",
         bases, exponents
     )]
     UnequalNoOfBasesExponents { bases: usize, exponents: usize },
 
-    #[fail(
-    display = "All verification keys should have equal number of Y_tilde elements"
-    )]
+    #[fail(display = "All verification keys should have equal number of Y_tilde elements")]
     IncompatibleVerkeysForAggregation,
 
     #[fail(
-    display = "All signatures should have same first element (sigma_1). m' should be same as well if using 2018 scheme"
+        display = "All signatures should have same first element (sigma_1). m' should be same as well if using 2018 scheme"
     )]
     IncompatibleSigsForAggregation,
 
+    #[fail(
+        display = "Link secret at index {} must be proven and never revealed",
+        idx
+    )]
+    LinkSecretRevealed { idx: usize },
+
+    #[fail(display = "Validity window of the credential was not revealed")]
+    ValidityWindowNotRevealed,
+
+    #[fail(
+        display = "Time {} is outside the validity window [{}, {}]",
+        time, not_before, not_after
+    )]
+    OutsideValidityWindow {
+        time: u64,
+        not_before: u64,
+        not_after: u64,
+    },
+
+    #[fail(display = "Index {} should be in {}", idx, valid)]
+    InvalidIndex { idx: usize, valid: String },
+
+    #[fail(display = "Index {} is that of Y_(n+1), which is never part of the key", idx)]
+    ForbiddenIndex { idx: usize },
+
+    #[fail(display = "No credential satisfies the proof request")]
+    UnsatisfiableProofRequest,
+
+    #[fail(display = "Presentation must be bound to the holder key")]
+    TransferablePresentationNotAccepted,
+
+    #[fail(display = "Holder key at index {} must be revealed", idx)]
+    HolderKeyNotRevealed { idx: usize },
+
+    #[fail(display = "Message at index {} is not the holder key", idx)]
+    HolderKeyMismatch { idx: usize },
+
+    #[fail(display = "Presentation must be bound to a nonce of the verifier")]
+    MissingNonce,
+
+    #[fail(display = "A presentation with the same nonce was already accepted")]
+    ReplayedNonce,
+
+    #[fail(display = "Nonce store is full with {} nonces", capacity)]
+    NonceStoreFull { capacity: usize },
+
+    #[fail(display = "Message at index {} cannot be redacted", idx)]
+    NonRedactableMessageHidden { idx: usize },
+
+    #[fail(display = "Message at index {} cannot be modified by the sanitizer", idx)]
+    NotSanitizable { idx: usize },
+
+    #[fail(display = "Signature of the sanitizer of the message at index {} does not verify", idx)]
+    InvalidSanitizerSignature { idx: usize },
+
+    #[fail(display = "Sanitized document does not verify")]
+    InvalidSanitizedDocument,
+
+    #[fail(display = "No leaf of the document at JSON pointer {}", pointer)]
+    UnknownJsonPointer { pointer: String },
+
+    #[fail(display = "No field {} in the credential", name)]
+    UnknownField { name: String },
+
+    #[fail(
+        display = "Presentation for the challenge at {} is stale at {}, more than {} seconds apart",
+        timestamp, now, max_clock_skew
+    )]
+    StalePresentation {
+        timestamp: u64,
+        now: u64,
+        max_clock_skew: u64,
+    },
+
+    #[fail(display = "Weak key: {}", reason)]
+    WeakKey { reason: String },
+
+    #[fail(display = "Key generation cancelled after {} of {} elements", done, total)]
+    KeyGenCancelled { done: usize, total: usize },
+
+    #[fail(display = "Random number generator failure: {}", reason)]
+    RngFailure { reason: String },
+
+    #[fail(display = "Verkey share does not match its commitment")]
+    KeyShareCommitmentMismatch,
+
+    #[fail(display = "Partial signatures do not combine to a valid signature")]
+    InvalidPartialSignature,
+
+    #[fail(display = "h of the signing request is not the one derived from its id and messages")]
+    InvalidSigningRequest,
+
+    #[fail(display = "Signers of total weight {} do not meet the threshold {}", weight, threshold)]
+    InsufficientWeight { weight: usize, threshold: usize },
+
+    #[fail(display = "Signature share of signer {} does not verify", signer)]
+    InvalidSignatureShare { signer: usize },
+
+    #[fail(display = "Invalid id {:?} for the store", id)]
+    InvalidStoreId { id: String },
+
+    #[fail(display = "Error while accessing the store: {}", msg)]
+    StorageError { msg: String },
+
+    #[fail(display = "Key was generated with other params than the given ones")]
+    ParamsMismatch,
+
+    #[fail(display = "Signing key does not match the verkey at {}", element)]
+    SigkeyMismatch { element: String },
+
+    #[fail(display = "Key was made by a build with {} {} but this build has {}", field, found, expected)]
+    BuildMismatch { field: String, expected: String, found: String },
+
+    #[fail(display = "Proof type {} has a challenge domain in the registry", proof_type)]
+    ReservedChallengeDomain { proof_type: String },
+
+    #[fail(display = "Token was already redeemed in this scope")]
+    TokenAlreadySpent,
+
+    #[fail(display = "Message at index {} is not a member of the set", idx)]
+    NotInSet { idx: usize },
+
+    #[fail(display = "Value {} is outside [{}, {}]", value, min, max)]
+    ValueOutOfRange { value: u64, min: u64, max: u64 },
+
+    #[fail(display = "Input has {} {} but at most {} are allowed", given, limit, max)]
+    DeserializeLimitExceeded { limit: String, given: usize, max: usize },
+
+    #[fail(display = "No verification routine is registered for algorithm {}", id)]
+    UnknownAlgorithm { id: String },
+
+    #[fail(display = "No params are registered for name {}", name)]
+    UnknownParams { name: String },
+
+    #[fail(display = "Issuer {} is not in the trust registry", id)]
+    UntrustedIssuer { id: String },
+
+    #[fail(display = "Issuer {} is not trusted at {}", id, time)]
+    IssuerNotTrustedAt { id: String, time: u64 },
+
+    #[fail(display = "Issuer {} is not trusted for credentials of schema {}", id, schema)]
+    SchemaNotAllowed { id: String, schema: String },
+
+    #[fail(display = "Proof format version {} is not supported", version)]
+    UnsupportedProofVersion { version: u8 },
+
+    #[fail(display = "Proof of possession of the verkey at index {} does not verify", idx)]
+    InvalidProofOfPossession { idx: usize },
+
+    #[fail(display = "Status index at index {} of the credential is not revealed", idx)]
+    StatusIndexNotRevealed { idx: usize },
+
+    #[fail(display = "Credential with status index {} is revoked", status_index)]
+    CredentialRevoked { status_index: u64 },
+
+    #[fail(display = "Status list {} is not the list {} expected for the credential", id, expected)]
+    StatusListMismatch { id: String, expected: String },
+
+    #[fail(display = "Status list of epoch {} is stale at {}, older than {} seconds", epoch, now, max_age)]
+    StaleStatusList { epoch: u64, now: u64, max_age: u64 },
+
+    #[fail(display = "Presentation does not verify")]
+    InvalidPresentation,
+
+    #[fail(display = "Presentations at indices {:?} do not verify", indices)]
+    InvalidPresentations { indices: Vec<usize> },
+
+    #[fail(display = "Encrypted key does not decrypt with the passphrase or was modified")]
+    KeyDecryptionFailed,
+
+    #[fail(display = "Sealed credential does not decrypt with the key or was modified")]
+    CredentialDecryptionFailed,
+
+    #[fail(display = "Group element is not a valid element of the prime order group")]
+    InvalidGroupElement,
+
+    #[fail(display = "Error while deserializing: {}", msg)]
+    DeserializationError { msg: String },
+
     #[fail(display = "Error with message {:?}", msg)]
     GeneralError { msg: String },
 }
+
+/// Stable code of each variant of `PSError` for bindings, which map errors by code rather than by message. Codes
+/// never change and new variants get new codes, 0 is left for success.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum ErrorCode {
+    UnsupportedNoOfMessages = 1,
+    UnsupportedMessageCount = 2,
+    UnequalNoOfBasesExponents = 3,
+    IncompatibleVerkeysForAggregation = 4,
+    IncompatibleSigsForAggregation = 5,
+    LinkSecretRevealed = 6,
+    ValidityWindowNotRevealed = 7,
+    OutsideValidityWindow = 8,
+    InvalidIndex = 9,
+    UnsatisfiableProofRequest = 10,
+    TransferablePresentationNotAccepted = 11,
+    HolderKeyNotRevealed = 12,
+    HolderKeyMismatch = 13,
+    MissingNonce = 14,
+    ReplayedNonce = 15,
+    NonRedactableMessageHidden = 16,
+    NotSanitizable = 17,
+    InvalidSanitizerSignature = 18,
+    InvalidSanitizedDocument = 19,
+    UnknownJsonPointer = 20,
+    UnknownField = 21,
+    StalePresentation = 22,
+    WeakKey = 23,
+    KeyGenCancelled = 24,
+    RngFailure = 25,
+    KeyShareCommitmentMismatch = 26,
+    InvalidPartialSignature = 27,
+    InsufficientWeight = 28,
+    InvalidSignatureShare = 29,
+    InvalidStoreId = 30,
+    StorageError = 31,
+    ParamsMismatch = 32,
+    BuildMismatch = 33,
+    ReservedChallengeDomain = 34,
+    TokenAlreadySpent = 35,
+    NotInSet = 36,
+    ValueOutOfRange = 37,
+    DeserializeLimitExceeded = 38,
+    UnknownAlgorithm = 39,
+    UnknownParams = 40,
+    UntrustedIssuer = 41,
+    IssuerNotTrustedAt = 42,
+    SchemaNotAllowed = 43,
+    UnsupportedProofVersion = 44,
+    InvalidProofOfPossession = 45,
+    StatusIndexNotRevealed = 46,
+    CredentialRevoked = 47,
+    InvalidPresentation = 48,
+    InvalidPresentations = 49,
+    InvalidGroupElement = 50,
+    DeserializationError = 51,
+    GeneralError = 52,
+    KeyDecryptionFailed = 53,
+    CredentialDecryptionFailed = 54,
+    SigkeyMismatch = 55,
+    ForbiddenIndex = 56,
+    InvalidSigningRequest = 57,
+    NonceStoreFull = 58,
+    StatusListMismatch = 59,
+    StaleStatusList = 60,
+}
+
+impl PSError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            PSError::UnsupportedNoOfMessages { .. } => ErrorCode::UnsupportedNoOfMessages,
+            PSError::UnsupportedMessageCount { .. } => ErrorCode::UnsupportedMessageCount,
+            PSError::UnequalNoOfBasesExponents { .. } => ErrorCode::UnequalNoOfBasesExponents,
+            PSError::IncompatibleVerkeysForAggregation => ErrorCode::IncompatibleVerkeysForAggregation,
+            PSError::IncompatibleSigsForAggregation => ErrorCode::IncompatibleSigsForAggregation,
+            PSError::LinkSecretRevealed { .. } => ErrorCode::LinkSecretRevealed,
+            PSError::ValidityWindowNotRevealed => ErrorCode::ValidityWindowNotRevealed,
+            PSError::OutsideValidityWindow { .. } => ErrorCode::OutsideValidityWindow,
+            PSError::InvalidIndex { .. } => ErrorCode::InvalidIndex,
+            PSError::ForbiddenIndex { .. } => ErrorCode::ForbiddenIndex,
+            PSError::UnsatisfiableProofRequest => ErrorCode::UnsatisfiableProofRequest,
+            PSError::TransferablePresentationNotAccepted => ErrorCode::TransferablePresentationNotAccepted,
+            PSError::HolderKeyNotRevealed { .. } => ErrorCode::HolderKeyNotRevealed,
+            PSError::HolderKeyMismatch { .. } => ErrorCode::HolderKeyMismatch,
+            PSError::MissingNonce => ErrorCode::MissingNonce,
+            PSError::ReplayedNonce => ErrorCode::ReplayedNonce,
+            PSError::NonceStoreFull { .. } => ErrorCode::NonceStoreFull,
+            PSError::NonRedactableMessageHidden { .. } => ErrorCode::NonRedactableMessageHidden,
+            PSError::NotSanitizable { .. } => ErrorCode::NotSanitizable,
+            PSError::InvalidSanitizerSignature { .. } => ErrorCode::InvalidSanitizerSignature,
+            PSError::InvalidSanitizedDocument => ErrorCode::InvalidSanitizedDocument,
+            PSError::UnknownJsonPointer { .. } => ErrorCode::UnknownJsonPointer,
+            PSError::UnknownField { .. } => ErrorCode::UnknownField,
+            PSError::StalePresentation { .. } => ErrorCode::StalePresentation,
+            PSError::WeakKey { .. } => ErrorCode::WeakKey,
+            PSError::KeyGenCancelled { .. } => ErrorCode::KeyGenCancelled,
+            PSError::RngFailure { .. } => ErrorCode::RngFailure,
+            PSError::KeyShareCommitmentMismatch => ErrorCode::KeyShareCommitmentMismatch,
+            PSError::InvalidPartialSignature => ErrorCode::InvalidPartialSignature,
+            PSError::InvalidSigningRequest => ErrorCode::InvalidSigningRequest,
+            PSError::InsufficientWeight { .. } => ErrorCode::InsufficientWeight,
+            PSError::InvalidSignatureShare { .. } => ErrorCode::InvalidSignatureShare,
+            PSError::InvalidStoreId { .. } => ErrorCode::InvalidStoreId,
+            PSError::StorageError { .. } => ErrorCode::StorageError,
+            PSError::ParamsMismatch => ErrorCode::ParamsMismatch,
+            PSError::SigkeyMismatch { .. } => ErrorCode::SigkeyMismatch,
+            PSError::BuildMismatch { .. } => ErrorCode::BuildMismatch,
+            PSError::ReservedChallengeDomain { .. } => ErrorCode::ReservedChallengeDomain,
+            PSError::TokenAlreadySpent => ErrorCode::TokenAlreadySpent,
+            PSError::NotInSet { .. } => ErrorCode::NotInSet,
+            PSError::ValueOutOfRange { .. } => ErrorCode::ValueOutOfRange,
+            PSError::DeserializeLimitExceeded { .. } => ErrorCode::DeserializeLimitExceeded,
+            PSError::UnknownAlgorithm { .. } => ErrorCode::UnknownAlgorithm,
+            PSError::UnknownParams { .. } => ErrorCode::UnknownParams,
+            PSError::UntrustedIssuer { .. } => ErrorCode::UntrustedIssuer,
+            PSError::IssuerNotTrustedAt { .. } => ErrorCode::IssuerNotTrustedAt,
+            PSError::SchemaNotAllowed { .. } => ErrorCode::SchemaNotAllowed,
+            PSError::UnsupportedProofVersion { .. } => ErrorCode::UnsupportedProofVersion,
+            PSError::InvalidProofOfPossession { .. } => ErrorCode::InvalidProofOfPossession,
+            PSError::StatusIndexNotRevealed { .. } => ErrorCode::StatusIndexNotRevealed,
+            PSError::CredentialRevoked { .. } => ErrorCode::CredentialRevoked,
+            PSError::StatusListMismatch { .. } => ErrorCode::StatusListMismatch,
+            PSError::StaleStatusList { .. } => ErrorCode::StaleStatusList,
+            PSError::InvalidPresentation => ErrorCode::InvalidPresentation,
+            PSError::InvalidPresentations { .. } => ErrorCode::InvalidPresentations,
+            PSError::KeyDecryptionFailed => ErrorCode::KeyDecryptionFailed,
+            PSError::CredentialDecryptionFailed => ErrorCode::CredentialDecryptionFailed,
+            PSError::InvalidGroupElement => ErrorCode::InvalidGroupElement,
+            PSError::DeserializationError { .. } => ErrorCode::DeserializationError,
+            PSError::GeneralError { .. } => ErrorCode::GeneralError,
+        }
+    }
+}
+
+impl From<&PSError> for ErrorCode {
+    fn from(e: &PSError) -> Self {
+        e.code()
+    }
+}
+
+impl From<ErrorCode> for u16 {
+    fn from(code: ErrorCode) -> Self {
+        code as u16
+    }
+}
+
+impl TryFrom<u16> for ErrorCode {
+    type Error = PSError;
+
+    /// Fails with `PSError::GeneralError` for a value that is no code, e.g. of a later version of the crate
+    fn try_from(code: u16) -> Result<Self, PSError> {
+        Ok(match code {
+            1 => ErrorCode::UnsupportedNoOfMessages,
+            2 => ErrorCode::UnsupportedMessageCount,
+            3 => ErrorCode::UnequalNoOfBasesExponents,
+            4 => ErrorCode::IncompatibleVerkeysForAggregation,
+            5 => ErrorCode::IncompatibleSigsForAggregation,
+            6 => ErrorCode::LinkSecretRevealed,
+            7 => ErrorCode::ValidityWindowNotRevealed,
+            8 => ErrorCode::OutsideValidityWindow,
+            9 => ErrorCode::InvalidIndex,
+            10 => ErrorCode::UnsatisfiableProofRequest,
+            11 => ErrorCode::TransferablePresentationNotAccepted,
+            12 => ErrorCode::HolderKeyNotRevealed,
+            13 => ErrorCode::HolderKeyMismatch,
+            14 => ErrorCode::MissingNonce,
+            15 => ErrorCode::ReplayedNonce,
+            16 => ErrorCode::NonRedactableMessageHidden,
+            17 => ErrorCode::NotSanitizable,
+            18 => ErrorCode::InvalidSanitizerSignature,
+            19 => ErrorCode::InvalidSanitizedDocument,
+            20 => ErrorCode::UnknownJsonPointer,
+            21 => ErrorCode::UnknownField,
+            22 => ErrorCode::StalePresentation,
+            23 => ErrorCode::WeakKey,
+            24 => ErrorCode::KeyGenCancelled,
+            25 => ErrorCode::RngFailure,
+            26 => ErrorCode::KeyShareCommitmentMismatch,
+            27 => ErrorCode::InvalidPartialSignature,
+            28 => ErrorCode::InsufficientWeight,
+            29 => ErrorCode::InvalidSignatureShare,
+            30 => ErrorCode::InvalidStoreId,
+            31 => ErrorCode::StorageError,
+            32 => ErrorCode::ParamsMismatch,
+            33 => ErrorCode::BuildMismatch,
+            34 => ErrorCode::ReservedChallengeDomain,
+            35 => ErrorCode::TokenAlreadySpent,
+            36 => ErrorCode::NotInSet,
+            37 => ErrorCode::ValueOutOfRange,
+            38 => ErrorCode::DeserializeLimitExceeded,
+            39 => ErrorCode::UnknownAlgorithm,
+            40 => ErrorCode::UnknownParams,
+            41 => ErrorCode::UntrustedIssuer,
+            42 => ErrorCode::IssuerNotTrustedAt,
+            43 => ErrorCode::SchemaNotAllowed,
+            44 => ErrorCode::UnsupportedProofVersion,
+            45 => ErrorCode::InvalidProofOfPossession,
+            46 => ErrorCode::StatusIndexNotRevealed,
+            47 => ErrorCode::CredentialRevoked,
+            48 => ErrorCode::InvalidPresentation,
+            49 => ErrorCode::InvalidPresentations,
+            50 => ErrorCode::InvalidGroupElement,
+            51 => ErrorCode::DeserializationError,
+            52 => ErrorCode::GeneralError,
+            53 => ErrorCode::KeyDecryptionFailed,
+            54 => ErrorCode::CredentialDecryptionFailed,
+            55 => ErrorCode::SigkeyMismatch,
+            56 => ErrorCode::ForbiddenIndex,
+            57 => ErrorCode::InvalidSigningRequest,
+            58 => ErrorCode::NonceStoreFull,
+            59 => ErrorCode::StatusListMismatch,
+            60 => ErrorCode::StaleStatusList,
+            _ => {
+                return Err(PSError::GeneralError {
+                    msg: format!("Unknown error code {}", code),
+                })
+            }
+        })
+    }
+}
+
+/// Errors of serde_json when serializing or deserializing JSON, `PSError::StorageError` for errors of the underlying
+/// reader or writer and `PSError::DeserializationError` for the others
+#[cfg(feature = "serde_json")]
+impl From<serde_json::Error> for PSError {
+    fn from(e: serde_json::Error) -> Self {
+        if e.is_io() {
+            PSError::StorageError { msg: e.to_string() }
+        } else {
+            PSError::DeserializationError { msg: e.to_string() }
+        }
+    }
+}
+
+/// Errors of reading and writing as `PSError::StorageError`, with the feature `io-errors`
+#[cfg(feature = "io-errors")]
+impl From<io::Error> for PSError {
+    fn from(e: io::Error) -> Self {
+        PSError::StorageError { msg: e.to_string() }
+    }
+}
+
+/// Run `f`, turning a panic inside amcl_wrapper into `PSError::InvalidGroupElement`. Only decoding of group
+/// elements from bytes is wrapped, other operations get elements that passed its checks and lengths and indices
+/// checked by their callers. Panics cannot be caught when built with `panic = "abort"`.
+pub(crate) fn catch_amcl_panic<T, F: FnOnce() -> T>(f: F) -> Result<T, PSError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|_| PSError::InvalidGroupElement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        // Every code maps back to itself and codes are dense from 1
+        let mut code = 1;
+        while let Ok(c) = ErrorCode::try_from(code) {
+            assert_eq!(u16::from(c), code);
+            code += 1;
+        }
+        assert_eq!(code, u16::from(ErrorCode::StaleStatusList) + 1);
+        assert!(ErrorCode::try_from(0).is_err());
+        assert_eq!(ErrorCode::UnsupportedNoOfMessages as u16, 1);
+        assert_eq!(u16::from(ErrorCode::ParamsMismatch), 32);
+
+        let e = PSError::InvalidIndex {
+            idx: 3,
+            valid: String::from("[0, 2)"),
+        };
+        assert_eq!(e.code(), ErrorCode::InvalidIndex);
+        assert_eq!(ErrorCode::from(&e), ErrorCode::InvalidIndex);
+        assert_eq!(PSError::ReplayedNonce.code(), ErrorCode::ReplayedNonce);
+
+        #[cfg(feature = "serde_json")]
+        {
+            let e: PSError = serde_json::from_str::<Vec<u8>>("[1,").unwrap_err().into();
+            assert_eq!(e.code(), ErrorCode::DeserializationError);
+        }
+        #[cfg(feature = "io-errors")]
+        {
+            let e: PSError = io::Error::new(io::ErrorKind::NotFound, "gone").into();
+            assert_eq!(e.code(), ErrorCode::StorageError);
+        }
+    }
+}
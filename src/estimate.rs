@@ -0,0 +1,121 @@
+// Size and verification cost of a transferable presentation of a credential of the 2016 scheme, computed from
+// the count of messages and of revealed messages alone so that protocol designers can budget bandwidth and
+// latency without creating keys and proofs. Sizes are those of `PoKOfSignatureProof::to_bytes` and of the
+// revealed messages as field elements. Costs count the operations of `PoKOfSignatureProof::verify`: the pairings
+// share one final exponentiation and the scalar multiplications are the terms of its multi-scalar
+// multiplications, all in the verkey group.
+
+use crate::curve::FieldElement_SIZE;
+use crate::errors::PSError;
+use crate::keys::check_message_count;
+use crate::{SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresentationSize {
+    /// Bytes of the proof of knowledge of signature
+    pub proof: usize,
+    /// Bytes of the revealed messages, without their indices whose encoding depends on the serialization
+    pub revealed_msgs: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyOps {
+    /// Miller loops
+    pub pairings: usize,
+    pub final_exponentiations: usize,
+    /// Terms of multi-scalar multiplications in the verkey group
+    pub scalar_muls: usize,
+}
+
+impl PresentationSize {
+    pub fn total(&self) -> usize {
+        self.proof + self.revealed_msgs
+    }
+}
+
+fn check_counts(count_messages: usize, revealed: usize) -> Result<(), PSError> {
+    check_message_count(count_messages)?;
+    if revealed > count_messages {
+        return Err(PSError::GeneralError {
+            msg: format!(
+                "Cannot reveal {} messages out of {}",
+                revealed, count_messages
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Size of a presentation of a credential of `count_messages` messages revealing `revealed` of them
+pub fn presentation_size(
+    count_messages: usize,
+    revealed: usize,
+) -> Result<PresentationSize, PSError> {
+    check_counts(count_messages, revealed)?;
+    let hidden = count_messages - revealed;
+    Ok(PresentationSize {
+        // sigma_1, sigma_2, J, the commitment and a response per hidden message and for `t`
+        proof: 2 * SIGNATURE_GROUP_SIZE + 2 * VERKEY_GROUP_SIZE + (hidden + 1) * FieldElement_SIZE,
+        revealed_msgs: revealed * FieldElement_SIZE,
+    })
+}
+
+/// Operations to verify a presentation of a credential of `count_messages` messages revealing `revealed` of them
+pub fn verify_ops(count_messages: usize, revealed: usize) -> Result<VerifyOps, PSError> {
+    check_counts(count_messages, revealed)?;
+    let hidden = count_messages - revealed;
+    Ok(VerifyOps {
+        pairings: 2,
+        final_exponentiations: 1,
+        // g_tilde, Y_tilde_i of each hidden message and J for the proof of knowledge, then Y_tilde_i of each
+        // revealed message
+        scalar_muls: (hidden + 2) + revealed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{FieldElement, FieldElementVector};
+    use crate::keys::{keygen, Params};
+    use crate::pok_sig::PoKOfSignature;
+    use crate::signature::Signature;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_presentation_size() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 6;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        for revealed in 0..=count_msgs {
+            let revealed_msg_indices: HashSet<usize> = (0..revealed).collect();
+            let pok = PoKOfSignature::init(
+                &sig,
+                &vk,
+                &params,
+                msgs.clone().into(),
+                None,
+                revealed_msg_indices,
+            )
+            .unwrap();
+            let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+            let proof = pok.gen_proof(&chal).unwrap();
+            let size = presentation_size(count_msgs, revealed).unwrap();
+            assert_eq!(size.proof, proof.to_bytes().len());
+            assert_eq!(size.total(), size.proof + revealed * FieldElement_SIZE);
+        }
+
+        assert_eq!(
+            verify_ops(10, 3).unwrap(),
+            VerifyOps {
+                pairings: 2,
+                final_exponentiations: 1,
+                scalar_muls: 12
+            }
+        );
+        assert!(presentation_size(3, 4).is_err());
+        assert!(verify_ops(0, 0).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+// Serialization for humans, behind the feature `human-serde`: keys, params and signatures as maps with the names of
+// the papers and each element as the lowercase hex of its `to_bytes`, for interoperability documents and debugging.
+// The profile is chosen at runtime by wrapping the value in `Human`, which serializes with any serde serializer, or
+// with `SerdeProfile`. The derived serde encodings are unchanged. Deserializing checks the counts of elements and
+// that the group elements are in the group, as `from_bytes` does.
+// Names: `Params` has g, g_tilde and label_hash; `Verkey` X_tilde, Y_tilde_i for i in 1..=n and params_label_hash;
+// `PKrss` n, g, g_tilde, X_tilde, Y_tilde_i for i in 1..=n, Y_j for j in 1..=n, Y_k for k in n+2..=2n and
+// params_label_hash; and `Signature` sigma_1 and sigma_2.
+
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{PKrss, PKrssFields, Params, ParamsFields, Verkey};
+use crate::signature::{group_elem_from_bytes, Signature};
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+
+/// Value serialized in the human profile, `Human(&value)` to serialize and `Human<T>` to deserialize
+#[derive(Clone, Debug)]
+pub struct Human<T>(pub T);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerdeProfile {
+    /// The derived serde encoding
+    Compact,
+    Human,
+}
+
+/// Types with a human form
+pub trait HumanForm: Sized {
+    type Form: Serialize + DeserializeOwned;
+
+    fn to_human(&self) -> Self::Form;
+
+    fn from_human(form: Self::Form) -> Result<Self, PSError>;
+}
+
+/// Bytes serialized as a hex string
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hex(pub Vec<u8>);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HumanParams {
+    pub g: Hex,
+    pub g_tilde: Hex,
+    pub label_hash: Hex,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HumanVerkey {
+    pub X_tilde: Hex,
+    pub Y_tilde_i: Vec<Hex>,
+    pub params_label_hash: Hex,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HumanPKrss {
+    pub n: usize,
+    pub g: Hex,
+    pub g_tilde: Hex,
+    pub X_tilde: Hex,
+    pub Y_tilde_i: Vec<Hex>,
+    pub Y_j: Vec<Hex>,
+    pub Y_k: Vec<Hex>,
+    pub params_label_hash: Hex,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HumanSignature {
+    pub sigma_1: Hex,
+    pub sigma_2: Hex,
+}
+
+impl Serialize for Hex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = self.0.iter().map(|b| format!("{:02x}", b)).collect();
+        serializer.serialize_str(&hex)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        if !s.len().is_multiple_of(2) {
+            return Err(D::Error::custom("hex string of odd length"));
+        }
+        (0..s.len())
+            .step_by(2)
+            .map(|i| {
+                s.get(i..i + 2)
+                    .and_then(|b| u8::from_str_radix(b, 16).ok())
+                    .ok_or_else(|| D::Error::custom(format!("invalid hex at {}", i)))
+            })
+            .collect::<Result<Vec<u8>, _>>()
+            .map(Hex)
+    }
+}
+
+impl Hex {
+    fn of<G: GroupElement>(elem: &G) -> Self {
+        Hex(elem.to_bytes())
+    }
+
+    fn group_elem<G: GroupElement>(&self) -> Result<G, PSError> {
+        group_elem_from_bytes(&self.0)
+    }
+
+    fn field_elem(&self) -> Result<FieldElement, PSError> {
+        FieldElement::from_bytes(&self.0).map_err(|e| PSError::DeserializationError {
+            msg: format!("{}", e),
+        })
+    }
+}
+
+fn group_elems<G: GroupElement>(hexes: &[Hex]) -> Result<Vec<G>, PSError> {
+    hexes.iter().map(Hex::group_elem).collect()
+}
+
+impl<T: HumanForm> Serialize for Human<&T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.to_human().serialize(serializer)
+    }
+}
+
+impl<'de, T: HumanForm> Deserialize<'de> for Human<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let form = T::Form::deserialize(deserializer)?;
+        T::from_human(form)
+            .map(Human)
+            .map_err(|e| D::Error::custom(format!("{}", e)))
+    }
+}
+
+impl SerdeProfile {
+    pub fn to_json<T: HumanForm + Serialize>(self, value: &T) -> Result<String, PSError> {
+        match self {
+            SerdeProfile::Compact => serde_json::to_string(value),
+            SerdeProfile::Human => serde_json::to_string_pretty(&Human(value)),
+        }
+        .map_err(|e| PSError::GeneralError {
+            msg: format!("{}", e),
+        })
+    }
+
+    pub fn from_json<T: HumanForm + DeserializeOwned>(self, json: &str) -> Result<T, PSError> {
+        match self {
+            SerdeProfile::Compact => serde_json::from_str(json),
+            SerdeProfile::Human => serde_json::from_str::<Human<T>>(json).map(|h| h.0),
+        }
+        .map_err(|e| PSError::DeserializationError {
+            msg: format!("{}", e),
+        })
+    }
+}
+
+impl HumanForm for Params {
+    type Form = HumanParams;
+
+    fn to_human(&self) -> HumanParams {
+        HumanParams {
+            g: Hex::of(&self.g),
+            g_tilde: Hex::of(&self.g_tilde),
+            label_hash: Hex(self.label_hash.to_bytes()),
+        }
+    }
+
+    fn from_human(form: HumanParams) -> Result<Self, PSError> {
+        Params::try_from(ParamsFields {
+            g: form.g.group_elem()?,
+            g_tilde: form.g_tilde.group_elem()?,
+            label_hash: form.label_hash.field_elem()?,
+        })
+    }
+}
+
+impl HumanForm for Verkey {
+    type Form = HumanVerkey;
+
+    fn to_human(&self) -> HumanVerkey {
+        HumanVerkey {
+            X_tilde: Hex::of(&self.X_tilde),
+            Y_tilde_i: self.Y_tilde.iter().map(Hex::of).collect(),
+            params_label_hash: Hex(self.params_label_hash.to_bytes()),
+        }
+    }
+
+    fn from_human(form: HumanVerkey) -> Result<Self, PSError> {
+        Ok(Self {
+            X_tilde: form.X_tilde.group_elem()?,
+            Y_tilde: group_elems(&form.Y_tilde_i)?,
+            params_label_hash: form.params_label_hash.field_elem()?,
+        })
+    }
+}
+
+impl HumanForm for PKrss {
+    type Form = HumanPKrss;
+
+    fn to_human(&self) -> HumanPKrss {
+        HumanPKrss {
+            n: self.n,
+            g: Hex::of(&self.g),
+            g_tilde: Hex::of(&self.g_tilde),
+            X_tilde: Hex::of(&self.X_tilde),
+            Y_tilde_i: self.Y_tilde_i.iter().map(Hex::of).collect(),
+            Y_j: self.Y_j_1_to_n.iter().map(Hex::of).collect(),
+            Y_k: self.Y_k_nplus2_to_2n.iter().map(Hex::of).collect(),
+            params_label_hash: Hex(self.params_label_hash.to_bytes()),
+        }
+    }
+
+    fn from_human(form: HumanPKrss) -> Result<Self, PSError> {
+        // The counts of the vectors are checked against n as for the derived encoding
+        PKrss::try_from(PKrssFields {
+            n: form.n,
+            g: form.g.group_elem()?,
+            g_tilde: form.g_tilde.group_elem()?,
+            Y_j_1_to_n: group_elems(&form.Y_j)?,
+            Y_k_nplus2_to_2n: group_elems(&form.Y_k)?,
+            X_tilde: form.X_tilde.group_elem()?,
+            Y_tilde_i: group_elems(&form.Y_tilde_i)?,
+            params_label_hash: form.params_label_hash.field_elem()?,
+        })
+    }
+}
+
+impl HumanForm for Signature {
+    type Form = HumanSignature;
+
+    fn to_human(&self) -> HumanSignature {
+        HumanSignature {
+            sigma_1: Hex::of(&self.sigma_1),
+            sigma_2: Hex::of(&self.sigma_2),
+        }
+    }
+
+    fn from_human(form: HumanSignature) -> Result<Self, PSError> {
+        Ok(Self {
+            sigma_1: form.sigma_1.group_elem()?,
+            sigma_2: form.sigma_2.group_elem()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::{keygen, rsskeygen};
+
+    #[test]
+    fn test_human_profile() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let (_, pk) = rsskeygen(3, &params).unwrap();
+        let msgs = FieldElementVector::random(3);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let json = SerdeProfile::Human.to_json(&pk).unwrap();
+        for name in &[
+            "\"X_tilde\"",
+            "\"Y_j\"",
+            "\"Y_k\"",
+            "\"Y_tilde_i\"",
+            "\"g_tilde\"",
+        ] {
+            assert!(json.contains(name));
+        }
+        let g_hex: String =
+            pk.g.to_bytes()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+        assert!(json.contains(&g_hex));
+        let pk_1: PKrss = SerdeProfile::Human.from_json(&json).unwrap();
+        assert_eq!(pk_1.to_bytes(), pk.to_bytes());
+
+        // The profile is chosen at runtime, the same value round trips with both
+        for profile in &[SerdeProfile::Compact, SerdeProfile::Human] {
+            let vk_1: Verkey = profile.from_json(&profile.to_json(&vk).unwrap()).unwrap();
+            assert_eq!(vk_1.to_bytes(), vk.to_bytes());
+            let sig_1: Signature = profile.from_json(&profile.to_json(&sig).unwrap()).unwrap();
+            assert!(sig_1.verify(msgs.clone().into(), &vk, &params).unwrap());
+            let params_1: Params = profile
+                .from_json(&profile.to_json(&params).unwrap())
+                .unwrap();
+            assert_eq!(params_1.label_hash, params.label_hash);
+        }
+        let human = serde_json::to_value(Human(&sig)).unwrap();
+        assert!(human["sigma_1"].is_string());
+
+        // Counts of elements and hex are checked
+        let mut form = pk.to_human();
+        form.Y_k.pop();
+        let json = serde_json::to_string(&form).unwrap();
+        assert!(SerdeProfile::Human.from_json::<PKrss>(&json).is_err());
+        let mut form = sig.to_human();
+        form.sigma_1.0[10] ^= 1;
+        let json = serde_json::to_string(&form).unwrap();
+        assert!(SerdeProfile::Human.from_json::<Signature>(&json).is_err());
+        assert!(
+            serde_json::from_str::<Human<Signature>>(r#"{"sigma_1":"0g","sigma_2":"00"}"#).is_err()
+        );
+    }
+}
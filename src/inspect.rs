@@ -0,0 +1,307 @@
+// Description of keys and signatures for debugging tools: the count of messages, the groups and sizes of the
+// elements, a fingerprint and whether each element is canonical. `describe` returns a `Report` whose `Display`
+// prints it for humans. Sizes are of the encodings of `to_bytes` of this crate, which encodes points
+// uncompressed. The compressed sizes are those of the usual compressed encoding of BLS12-381 points, the x
+// coordinate with flags in its top bits, for comparing with other implementations.
+
+use crate::curve::{FieldElement, GroupElement, MODBYTES};
+use crate::errors::PSError;
+use crate::keys::{PKrss, Verkey};
+use crate::signature::{in_group, Signature};
+use crate::{SignatureGroup, VerkeyGroup, SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE};
+use std::fmt;
+
+const FINGERPRINT_DOMAIN: &[u8] = b"PS fingerprint : ";
+/// Bytes of the hash shown as fingerprint
+const FINGERPRINT_SIZE: usize = 8;
+
+#[cfg(feature = "SignatureG2")]
+const SIGNATURE_GROUP_NAME: &str = "G2";
+#[cfg(feature = "SignatureG2")]
+const VERKEY_GROUP_NAME: &str = "G1";
+#[cfg(feature = "SignatureG2")]
+const SIGNATURE_GROUP_COMPRESSED_SIZE: usize = 2 * MODBYTES;
+#[cfg(feature = "SignatureG2")]
+const VERKEY_GROUP_COMPRESSED_SIZE: usize = MODBYTES;
+
+#[cfg(feature = "SignatureG1")]
+const SIGNATURE_GROUP_NAME: &str = "G1";
+#[cfg(feature = "SignatureG1")]
+const VERKEY_GROUP_NAME: &str = "G2";
+#[cfg(feature = "SignatureG1")]
+const SIGNATURE_GROUP_COMPRESSED_SIZE: usize = MODBYTES;
+#[cfg(feature = "SignatureG1")]
+const VERKEY_GROUP_COMPRESSED_SIZE: usize = 2 * MODBYTES;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElementReport {
+    /// Name of the field, with the index for elements of a vector
+    pub name: String,
+    pub group: String,
+    pub size: usize,
+    pub compressed_size: usize,
+    pub identity: bool,
+    pub in_group: bool,
+    /// Decoding the encoding and encoding it again gives the same bytes
+    pub canonical: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    /// Type described
+    pub kind: String,
+    pub message_count: Option<usize>,
+    /// Bytes of `to_bytes`
+    pub size: usize,
+    pub compressed_size: usize,
+    /// Hex of the last bytes of the hash of `to_bytes`
+    pub fingerprint: String,
+    pub elements: Vec<ElementReport>,
+}
+
+pub trait Describe {
+    fn describe(&self) -> Result<Report, PSError>;
+}
+
+/// Report of a `Verkey`, `PKrss` or `Signature`
+pub fn describe<T: Describe>(item: &T) -> Result<Report, PSError> {
+    item.describe()
+}
+
+pub(crate) fn fingerprint(bytes: &[u8]) -> String {
+    let hash = FieldElement::from_msg_hash(&[FINGERPRINT_DOMAIN, bytes].concat()).to_bytes();
+    hash[hash.len() - FINGERPRINT_SIZE..]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn element<G: GroupElement>(
+    name: String,
+    elem: &G,
+    group: &str,
+    size: usize,
+    compressed_size: usize,
+) -> Result<ElementReport, PSError> {
+    let bytes = elem.to_bytes();
+    let canonical = match G::from_bytes(&bytes) {
+        Ok(decoded) => decoded.to_bytes() == bytes,
+        Err(_) => false,
+    };
+    Ok(ElementReport {
+        name,
+        group: String::from(group),
+        size,
+        compressed_size,
+        identity: elem.is_identity(),
+        in_group: in_group(elem),
+        canonical,
+    })
+}
+
+fn signature_group_element(name: String, elem: &SignatureGroup) -> Result<ElementReport, PSError> {
+    element(
+        name,
+        elem,
+        SIGNATURE_GROUP_NAME,
+        SIGNATURE_GROUP_SIZE,
+        SIGNATURE_GROUP_COMPRESSED_SIZE,
+    )
+}
+
+fn verkey_group_element(name: String, elem: &VerkeyGroup) -> Result<ElementReport, PSError> {
+    element(
+        name,
+        elem,
+        VERKEY_GROUP_NAME,
+        VERKEY_GROUP_SIZE,
+        VERKEY_GROUP_COMPRESSED_SIZE,
+    )
+}
+
+fn indexed(name: &str, i: usize) -> String {
+    format!("{}[{}]", name, i)
+}
+
+impl Report {
+    /// Whether all elements are canonical, in the group and not the identity
+    pub fn is_well_formed(&self) -> bool {
+        self.elements
+            .iter()
+            .all(|e| e.canonical && e.in_group && !e.identity)
+    }
+
+    fn new(
+        kind: &str,
+        message_count: Option<usize>,
+        bytes: &[u8],
+        other_bytes: usize,
+        elements: Vec<ElementReport>,
+    ) -> Self {
+        Self {
+            kind: String::from(kind),
+            message_count,
+            size: bytes.len(),
+            compressed_size: other_bytes
+                + elements.iter().map(|e| e.compressed_size).sum::<usize>(),
+            fingerprint: fingerprint(bytes),
+            elements,
+        }
+    }
+}
+
+impl Describe for Verkey {
+    fn describe(&self) -> Result<Report, PSError> {
+        let mut elements = vec![verkey_group_element(
+            String::from("X_tilde"),
+            &self.X_tilde,
+        )?];
+        for (i, y) in self.Y_tilde.iter().enumerate() {
+            elements.push(verkey_group_element(indexed("Y_tilde", i), y)?);
+        }
+        let bytes = self.to_bytes();
+        // Count of messages and hash of the label of the params
+        let other_bytes = bytes.len() - elements.len() * VERKEY_GROUP_SIZE;
+        Ok(Report::new(
+            "Verkey",
+            Some(self.supported_messages()),
+            &bytes,
+            other_bytes,
+            elements,
+        ))
+    }
+}
+
+impl Describe for PKrss {
+    fn describe(&self) -> Result<Report, PSError> {
+        let mut elements = vec![
+            signature_group_element(String::from("g"), &self.g)?,
+            verkey_group_element(String::from("g_tilde"), &self.g_tilde)?,
+            verkey_group_element(String::from("X_tilde"), &self.X_tilde)?,
+        ];
+        for (i, y) in self.Y_tilde_i.iter().enumerate() {
+            elements.push(verkey_group_element(indexed("Y_tilde_i", i), y)?);
+        }
+        for (i, y) in self.Y_j_1_to_n.iter().enumerate() {
+            elements.push(signature_group_element(indexed("Y_j_1_to_n", i), y)?);
+        }
+        for (i, y) in self.Y_k_nplus2_to_2n.iter().enumerate() {
+            elements.push(signature_group_element(indexed("Y_k_nplus2_to_2n", i), y)?);
+        }
+        let bytes = self.to_bytes();
+        let other_bytes = bytes.len() - elements.iter().map(|e| e.size).sum::<usize>();
+        Ok(Report::new(
+            "PKrss",
+            Some(self.supported_messages()),
+            &bytes,
+            other_bytes,
+            elements,
+        ))
+    }
+}
+
+impl Describe for Signature {
+    fn describe(&self) -> Result<Report, PSError> {
+        let elements = vec![
+            signature_group_element(String::from("sigma_1"), &self.sigma_1)?,
+            signature_group_element(String::from("sigma_2"), &self.sigma_2)?,
+        ];
+        Ok(Report::new(
+            "Signature",
+            None,
+            &self.to_bytes(),
+            0,
+            elements,
+        ))
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", self.kind, self.fingerprint)?;
+        if let Some(n) = self.message_count {
+            writeln!(f, "  messages: {}", n)?;
+        }
+        writeln!(
+            f,
+            "  size: {} bytes, {} compressed",
+            self.size, self.compressed_size
+        )?;
+        for e in &self.elements {
+            let mut flags = vec![];
+            if e.identity {
+                flags.push("identity");
+            }
+            if !e.in_group {
+                flags.push("not in group");
+            }
+            if !e.canonical {
+                flags.push("not canonical");
+            }
+            writeln!(
+                f,
+                "  {}: {} {} bytes, {} compressed{}{}",
+                e.name,
+                e.group,
+                e.size,
+                e.compressed_size,
+                if flags.is_empty() { "" } else { ", " },
+                flags.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::{keygen, rsskeygen, Params};
+
+    #[test]
+    fn test_describe() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let report = describe(&vk).unwrap();
+        assert_eq!(report.kind, "Verkey");
+        assert_eq!(report.message_count, Some(3));
+        assert_eq!(report.size, vk.to_bytes().len());
+        assert_eq!(report.elements.len(), 4);
+        assert_eq!(report.elements[1].name, "Y_tilde[0]");
+        assert_eq!(report.elements[0].group, VERKEY_GROUP_NAME);
+        assert!(report.compressed_size < report.size);
+        assert!(report.is_well_formed());
+        assert_eq!(report.fingerprint.len(), 2 * FINGERPRINT_SIZE);
+        assert_eq!(describe(&vk).unwrap().fingerprint, report.fingerprint);
+        let (_, other_vk) = keygen(3, &params).unwrap();
+        assert_ne!(describe(&other_vk).unwrap().fingerprint, report.fingerprint);
+
+        let (_, pk) = rsskeygen(4, &params).unwrap();
+        let pk_report = describe(&pk).unwrap();
+        assert_eq!(pk_report.size, pk.to_bytes().len());
+        assert_eq!(pk_report.elements.len(), 3 + 4 + 4 + 3);
+        assert!(pk_report.is_well_formed());
+
+        let msgs = FieldElementVector::random(3);
+        let mut sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let sig_report = describe(&sig).unwrap();
+        assert_eq!(sig_report.message_count, None);
+        assert_eq!(sig_report.size, 2 * SIGNATURE_GROUP_SIZE);
+        assert_eq!(
+            sig_report.compressed_size,
+            2 * SIGNATURE_GROUP_COMPRESSED_SIZE
+        );
+        assert!(sig_report.is_well_formed());
+        assert!(format!("{}", sig_report).contains("sigma_2"));
+
+        sig.sigma_1 = SignatureGroup::identity();
+        let report = describe(&sig).unwrap();
+        assert!(report.elements[0].identity);
+        assert!(!report.is_well_formed());
+        assert!(
+            format!("{}", report).contains("sigma_1: ")
+                && format!("{}", report).contains("identity")
+        );
+    }
+}
@@ -0,0 +1,225 @@
+// Integer messages for numeric predicates. `TypedMessage::Int` places a type byte above the value, so its field
+// element is not the integer and sums of messages are not sums of integers. For range proofs the message must be
+// the integer itself, here the field element of the value for unsigned integers and of value + 2^63 for signed
+// ones, which keeps their order, so that m >= lower and m <= upper are statements on the non-negative integers
+// m - lower and upper - m.
+// Range proofs show such a non-negative integer lies in [0, B^l) by proving it is sum_i d_i * B^i with each digit
+// d_i in [0, B) for B = 2^k, e.g. with the set membership proofs of `predicate_proof` over the B digits signed by
+// the verifier, as in Camenisch, Chaabouni and shelat. `DigitParams` has what such proofs share: the decomposition
+// of a value in l digits of k bits, the recomposition of a field element from digits, the weights B^i of the digits
+// in the linear relation and the set of digit values.
+
+use crate::curve::{FieldElement, FieldElement_SIZE};
+use crate::errors::PSError;
+
+/// Largest count of bits of a digit
+pub const MAX_DIGIT_BITS: u32 = 16;
+const SIGNED_OFFSET: u64 = 1 << 63;
+
+/// Message of an unsigned integer, the field element equal to it
+pub fn encode_unsigned(value: u64) -> FieldElement {
+    // From the bytes, as `FieldElement::from(u64)` sets a single limb from an isize and is wrong for large values
+    let mut bytes = [0u8; FieldElement_SIZE];
+    bytes[FieldElement_SIZE - 8..].copy_from_slice(&value.to_be_bytes());
+    FieldElement::from(&bytes)
+}
+
+/// Message of a signed integer, the field element of value + 2^63 so that it orders as the integers do
+pub fn encode_signed(value: i64) -> FieldElement {
+    encode_unsigned((value as u64) ^ SIGNED_OFFSET)
+}
+
+/// Integer of a message of `encode_unsigned`, `None` for a field element of 64 bits or more
+pub fn decode_unsigned(elem: &FieldElement) -> Option<u64> {
+    let bytes = elem.to_bytes();
+    let (high, low) = bytes.split_at(bytes.len() - 8);
+    if high.iter().any(|b| *b != 0) {
+        return None;
+    }
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(low);
+    Some(u64::from_be_bytes(buf))
+}
+
+/// Integer of a message of `encode_signed`
+pub fn decode_signed(elem: &FieldElement) -> Option<i64> {
+    decode_unsigned(elem).map(|v| (v ^ SIGNED_OFFSET) as i64)
+}
+
+/// value - lower, the integer to prove non-negative for value >= lower. Fails with `PSError::ValueOutOfRange` if
+/// value < lower.
+pub fn above(value: u64, lower: u64) -> Result<u64, PSError> {
+    value.checked_sub(lower).ok_or(PSError::ValueOutOfRange {
+        value,
+        min: lower,
+        max: u64::MAX,
+    })
+}
+
+/// upper - value, the integer to prove non-negative for value <= upper. Fails with `PSError::ValueOutOfRange` if
+/// value > upper.
+pub fn below(value: u64, upper: u64) -> Result<u64, PSError> {
+    upper.checked_sub(value).ok_or(PSError::ValueOutOfRange {
+        value,
+        min: 0,
+        max: upper,
+    })
+}
+
+/// Decomposition in `count` digits of `digit_bits` bits, little endian
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigitParams {
+    pub digit_bits: u32,
+    pub count: usize,
+}
+
+impl DigitParams {
+    /// Fails unless digits have 1 to `MAX_DIGIT_BITS` bits and all digits together at most 64 bits
+    pub fn new(digit_bits: u32, count: usize) -> Result<Self, PSError> {
+        if digit_bits == 0 || digit_bits > MAX_DIGIT_BITS || count == 0 {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "Digits should have 1 to {} bits, given {} digits of {} bits",
+                    MAX_DIGIT_BITS, count, digit_bits
+                ),
+            });
+        }
+        if digit_bits as usize * count > 64 {
+            return Err(PSError::GeneralError {
+                msg: format!("{} digits of {} bits exceed 64 bits", count, digit_bits),
+            });
+        }
+        Ok(Self { digit_bits, count })
+    }
+
+    /// Fewest digits of `digit_bits` bits for values of `bits` bits
+    pub fn for_bits(bits: u32, digit_bits: u32) -> Result<Self, PSError> {
+        Self::new(digit_bits, bits.div_ceil(digit_bits.max(1)).max(1) as usize)
+    }
+
+    /// B = 2^digit_bits
+    pub fn base(&self) -> u64 {
+        1 << self.digit_bits
+    }
+
+    /// Largest value of `count` digits
+    pub fn max_value(&self) -> u64 {
+        match self.digit_bits as usize * self.count {
+            64 => u64::MAX,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    /// Digits of `value`, least significant first. Fails with `PSError::ValueOutOfRange` if it has more digits.
+    pub fn decompose(&self, value: u64) -> Result<Vec<u64>, PSError> {
+        if value > self.max_value() {
+            return Err(PSError::ValueOutOfRange {
+                value,
+                min: 0,
+                max: self.max_value(),
+            });
+        }
+        let mask = self.base() - 1;
+        Ok((0..self.count)
+            .map(|i| (value >> (i as u32 * self.digit_bits)) & mask)
+            .collect())
+    }
+
+    /// sum_i digits_i * B^i, failing for a count of digits other than `count` or a digit not below B
+    pub fn recompose(&self, digits: &[u64]) -> Result<FieldElement, PSError> {
+        if digits.len() != self.count {
+            return Err(PSError::UnequalNoOfBasesExponents {
+                bases: self.count,
+                exponents: digits.len(),
+            });
+        }
+        if let Some(idx) = digits.iter().position(|d| *d >= self.base()) {
+            return Err(PSError::InvalidIndex {
+                idx,
+                valid: format!("digits in [0, {})", self.base()),
+            });
+        }
+        let mut sum = FieldElement::zero();
+        for (d, w) in digits.iter().zip(self.weights()) {
+            sum += &encode_unsigned(*d) * &w;
+        }
+        Ok(sum)
+    }
+
+    /// B^i for each digit i, the weights of the digits in the relation m = sum_i d_i * B^i
+    pub fn weights(&self) -> Vec<FieldElement> {
+        let base = encode_unsigned(self.base());
+        let mut weights = Vec::with_capacity(self.count);
+        let mut w = FieldElement::one();
+        for _ in 0..self.count {
+            weights.push(w.clone());
+            w = &w * &base;
+        }
+        weights
+    }
+
+    /// Messages of the digit values 0..B, the set a digit is a member of
+    pub fn digit_set(&self) -> Vec<FieldElement> {
+        (0..self.base()).map(encode_unsigned).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typed_message::TypedMessage;
+
+    #[test]
+    fn test_integer_messages() {
+        for v in &[0, 1, 42, u64::MAX] {
+            assert_eq!(decode_unsigned(&encode_unsigned(*v)), Some(*v));
+        }
+        for v in &[i64::MIN, -1, 0, 1, i64::MAX] {
+            assert_eq!(decode_signed(&encode_signed(*v)), Some(*v));
+        }
+        // Signed messages order as the integers and differences are those of the integers
+        assert_eq!(&encode_signed(5) - &encode_signed(-3), encode_unsigned(8));
+        assert!(
+            decode_unsigned(&encode_signed(-1)).unwrap()
+                < decode_unsigned(&encode_signed(0)).unwrap()
+        );
+        assert_eq!(decode_unsigned(&TypedMessage::Int(7).encode()), None);
+        assert_eq!(decode_unsigned(&FieldElement::from_msg_hash(b"x")), None);
+
+        assert_eq!(above(30, 18).unwrap(), 12);
+        assert!(above(17, 18).is_err());
+        assert_eq!(below(30, 65).unwrap(), 35);
+        match below(70, 65) {
+            Err(PSError::ValueOutOfRange {
+                value: 70, max: 65, ..
+            }) => (),
+            r => panic!("Expected ValueOutOfRange but got {:?}", r),
+        }
+
+        let digits = DigitParams::new(4, 3).unwrap();
+        assert_eq!(digits.max_value(), 0xfff);
+        let decomposed = digits.decompose(0xa5c).unwrap();
+        assert_eq!(decomposed, vec![0xc, 0x5, 0xa]);
+        assert_eq!(
+            digits.recompose(&decomposed).unwrap(),
+            encode_unsigned(0xa5c)
+        );
+        assert!(digits.decompose(0x1000).is_err());
+        assert!(digits.recompose(&[0x10, 0, 0]).is_err());
+        assert!(digits.recompose(&[1, 0]).is_err());
+        assert_eq!(digits.digit_set().len(), 16);
+        assert_eq!(digits.weights()[2], FieldElement::from(256u64));
+
+        let full = DigitParams::for_bits(64, 8).unwrap();
+        assert_eq!(full.count, 8);
+        assert_eq!(full.max_value(), u64::MAX);
+        assert_eq!(
+            full.recompose(&full.decompose(u64::MAX).unwrap()).unwrap(),
+            encode_unsigned(u64::MAX)
+        );
+        assert_eq!(DigitParams::for_bits(10, 3).unwrap().count, 4);
+        assert!(DigitParams::new(0, 4).is_err());
+        assert!(DigitParams::new(17, 1).is_err());
+        assert!(DigitParams::new(8, 9).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+// Issuer with a single key for credentials of schemas with different counts of attributes. A key of n messages
+// signs credentials of any count m <= n of messages with its prefix of m messages, `Sigkey::prefix`, and they
+// verify with `Verkey::prefix`. `Issuer::ensure_capacity` extends the key with new y_i when a schema needs more
+// messages than it has, or rejects the schema if the `CapacityPolicy` does not allow it, and records a
+// `KeyEvent` for every extension so that the issuer can publish the extended verkey. Extending keeps X_tilde and
+// the existing Y_tilde_i so credentials issued before remain valid.
+// A signature with a prefix is also a signature with a longer prefix on the same messages followed by 0s. So that
+// a credential of a schema cannot pass as one of a longer schema, the hash of the schema id is signed as the
+// last message of every credential, see `Schema::messages`.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::issuer_bundle::IssuerBundle;
+use crate::keys::{Params, Sigkey, Verkey, MAX_MESSAGES, MIN_MESSAGES};
+use crate::signature::Signature;
+
+const SCHEMA_DOMAIN: &[u8] = b"PS schema : ";
+
+/// Attributes of a kind of credential
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schema {
+    pub id: String,
+    pub attributes: Vec<String>,
+}
+
+/// Whether `Issuer::ensure_capacity` extends the key for schemas of more messages than it supports
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CapacityPolicy {
+    /// Extend up to this count of messages
+    Extend { max_messages: usize },
+    Reject,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum KeyEvent {
+    /// The key was extended for `schema_id` from `from` to `to` messages
+    Extended {
+        schema_id: String,
+        from: usize,
+        to: usize,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Issuer {
+    sigkey: Sigkey,
+    verkey: Verkey,
+    policy: CapacityPolicy,
+    events: Vec<KeyEvent>,
+}
+
+impl Schema {
+    pub fn new(id: &str, attributes: &[&str]) -> Self {
+        Self {
+            id: String::from(id),
+            attributes: attributes.iter().map(|a| String::from(*a)).collect(),
+        }
+    }
+
+    /// Count of signed messages, the attributes and the schema id
+    pub fn message_count(&self) -> usize {
+        self.attributes.len() + 1
+    }
+
+    /// Message signed after the attributes
+    pub fn id_message(&self) -> FieldElement {
+        FieldElement::from_msg_hash(&[SCHEMA_DOMAIN, self.id.as_bytes()].concat())
+    }
+
+    /// Messages to sign or verify for the attribute values `values`, the values followed by `id_message`
+    pub fn messages(&self, values: &[FieldElement]) -> Result<Vec<FieldElement>, PSError> {
+        if values.len() != self.attributes.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: self.attributes.len(),
+                given: values.len(),
+            });
+        }
+        let mut messages = values.to_vec();
+        messages.push(self.id_message());
+        Ok(messages)
+    }
+}
+
+impl Verkey {
+    /// Whether the key has enough messages for credentials of `schema`, to be verified with
+    /// `self.prefix(schema.message_count())`
+    pub fn supports(&self, schema: &Schema) -> bool {
+        self.supported_messages() >= schema.message_count()
+    }
+}
+
+impl Issuer {
+    pub fn new(sigkey: Sigkey, verkey: Verkey, policy: CapacityPolicy) -> Self {
+        Self {
+            sigkey,
+            verkey,
+            policy,
+            events: vec![],
+        }
+    }
+
+    /// Verkey of all messages of the key, published by the issuer
+    pub fn verkey(&self) -> &Verkey {
+        &self.verkey
+    }
+
+    /// Verkey to verify credentials of `schema` with
+    pub fn verkey_for(&self, schema: &Schema) -> Result<Verkey, PSError> {
+        self.verkey.prefix(schema.message_count())
+    }
+
+    /// Metadata of the issuer with credentials of `schema`, signed with its key, see `issuer_bundle`
+    pub fn bundle(
+        &self,
+        name: &str,
+        schema: Schema,
+        logo_hash: &[u8],
+        endpoints: &[&str],
+        params: &Params,
+    ) -> Result<IssuerBundle, PSError> {
+        IssuerBundle::new(&self.sigkey, self.verkey.clone(), name, schema, logo_hash, endpoints, params)
+    }
+
+    /// Extend the key if it has fewer messages than `schema` needs and the policy allows it. Fails with
+    /// `PSError::UnsupportedMessageCount` if it does not.
+    pub fn ensure_capacity(&mut self, schema: &Schema, params: &Params) -> Result<(), PSError> {
+        let needed = schema.message_count();
+        let current = self.verkey.supported_messages();
+        if needed <= current {
+            return Ok(());
+        }
+        let max = match self.policy {
+            CapacityPolicy::Extend { max_messages } => max_messages.min(MAX_MESSAGES),
+            CapacityPolicy::Reject => current,
+        };
+        if needed > max {
+            return Err(PSError::UnsupportedMessageCount {
+                given: needed,
+                min: MIN_MESSAGES,
+                max,
+            });
+        }
+        let mut extended = self.sigkey.clone();
+        for _ in current..needed {
+            extended.y.push(FieldElement::random());
+        }
+        extended.check_quality()?;
+        for y_i in &extended.y[current..] {
+            self.verkey.Y_tilde.push(&params.g_tilde * y_i);
+        }
+        self.sigkey = extended;
+        self.events.push(KeyEvent::Extended {
+            schema_id: schema.id.clone(),
+            from: current,
+            to: needed,
+        });
+        Ok(())
+    }
+
+    /// Sign a credential of `schema` with attribute values `values`, extending the key first if needed
+    pub fn sign(
+        &mut self,
+        schema: &Schema,
+        values: &[FieldElement],
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        let messages = schema.messages(values)?;
+        self.ensure_capacity(schema, params)?;
+        let sigkey = self.sigkey.prefix(messages.len())?;
+        Signature::new(&messages, &sigkey, params)
+    }
+
+    /// Events since the last call
+    pub fn take_events(&mut self) -> Vec<KeyEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_issuer_capacity() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let mut issuer = Issuer::new(sk, vk, CapacityPolicy::Extend { max_messages: 6 });
+        let small = Schema::new("email", &["address"]);
+        let large = Schema::new("passport", &["name", "birth date", "nationality", "number"]);
+        assert!(issuer.verkey().supports(&small));
+        assert!(!issuer.verkey().supports(&large));
+
+        let values: Vec<FieldElement> = FieldElementVector::random(1).into();
+        let sig = issuer.sign(&small, &values, &params).unwrap();
+        let small_vk = issuer.verkey_for(&small).unwrap();
+        assert!(sig
+            .verify(small.messages(&values).unwrap(), &small_vk, &params)
+            .unwrap());
+        assert!(issuer.take_events().is_empty());
+
+        let large_values: Vec<FieldElement> = FieldElementVector::random(4).into();
+        let large_sig = issuer.sign(&large, &large_values, &params).unwrap();
+        assert_eq!(
+            issuer.take_events(),
+            vec![KeyEvent::Extended {
+                schema_id: String::from("passport"),
+                from: 3,
+                to: 5
+            }]
+        );
+        assert!(issuer.take_events().is_empty());
+        assert!(issuer.verkey().supports(&large));
+        let large_vk = issuer.verkey_for(&large).unwrap();
+        assert!(large_sig
+            .verify(large.messages(&large_values).unwrap(), &large_vk, &params)
+            .unwrap());
+        // Credentials issued before the extension remain valid
+        assert_eq!(issuer.verkey_for(&small).unwrap(), small_vk);
+
+        // The small credential padded with 0s does not pass for a credential of a longer schema
+        let longer = Schema::new("email", &["address", "domain"]);
+        let mut padded = small.messages(&values).unwrap();
+        padded.push(FieldElement::zero());
+        let longer_vk = issuer.verkey_for(&longer).unwrap();
+        assert!(sig.verify(padded.clone(), &longer_vk, &params).unwrap());
+        assert_ne!(padded, longer.messages(&padded[..2]).unwrap());
+
+        // Beyond the policy's maximum
+        let huge = Schema::new("huge", &["a", "b", "c", "d", "e", "f"]);
+        match issuer.sign(&huge, FieldElementVector::random(6).as_slice(), &params) {
+            Err(PSError::UnsupportedMessageCount { given: 7, max: 6, .. }) => (),
+            r => panic!("Expected UnsupportedMessageCount but got {:?}", r),
+        }
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let mut rejecting = Issuer::new(sk, vk, CapacityPolicy::Reject);
+        assert!(rejecting.ensure_capacity(&small, &params).is_ok());
+        assert!(rejecting.ensure_capacity(&large, &params).is_err());
+        assert!(rejecting.take_events().is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+// Metadata of an issuer signed with its own key, so that a wallet fetches and authenticates it in one object. An
+// `IssuerBundle` has the verkey of the issuer, its display name, the schema of its credentials, the hash of its logo
+// and the endpoints of its services, e.g. issuance and status lists, signed with the prefix of 1 message of the issuing
+// key over the hash of all of them. `verify_bundle` checks the signature with the verkey of the bundle, so a valid
+// bundle shows the metadata comes from the holder of the signing key of the verkey, not that the verkey is one to
+// trust: a wallet compares the verkey with one it trusts, e.g. of a `trust_registry::TrustRegistry`.
+// The signed message is the hash of "PS issuer bundle : " || verkey.to_bytes() || name || schema id || the
+// attributes || logo_hash || the endpoints, each string and byte string preceded by its length in 8 bytes big
+// endian and each list by its count in 8 bytes big endian. As the signature with the prefix is also one with the
+// whole key on the message followed by 0s, it is not a credential of any schema of the issuer, whose last message is
+// the schema id message, see `issuer::Schema::messages`.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::issuer::Schema;
+use crate::keys::{Params, Sigkey, Verkey, MIN_MESSAGES};
+use crate::signature::Signature;
+
+const ISSUER_BUNDLE_DOMAIN: &[u8] = b"PS issuer bundle : ";
+
+/// Metadata of an issuer with its signature
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IssuerBundle {
+    pub verkey: Verkey,
+    /// Name for display in wallets
+    pub name: String,
+    /// Schema of the credentials of the issuer
+    pub schema: Schema,
+    /// Hash of the logo, the hash function being chosen by the ecosystem
+    pub logo_hash: Vec<u8>,
+    /// URLs of the services of the issuer
+    pub endpoints: Vec<String>,
+    /// Signature with the prefix of 1 message of the key of `verkey`
+    pub signature: Signature,
+}
+
+impl IssuerBundle {
+    /// Bundle of the metadata signed with `sigkey`, the signing key of `verkey`
+    pub fn new(
+        sigkey: &Sigkey,
+        verkey: Verkey,
+        name: &str,
+        schema: Schema,
+        logo_hash: &[u8],
+        endpoints: &[&str],
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        check_schema(&verkey, &schema)?;
+        let endpoints: Vec<String> = endpoints.iter().map(|e| e.to_string()).collect();
+        let msg = bundle_message(&verkey, name, &schema, logo_hash, &endpoints);
+        let signature = Signature::new(&[msg], &sigkey.prefix(1)?, params)?;
+        Ok(Self {
+            verkey,
+            name: name.to_string(),
+            schema,
+            logo_hash: logo_hash.to_vec(),
+            endpoints,
+            signature,
+        })
+    }
+}
+
+/// Verify the signature of `bundle` with its verkey. Fails with `PSError::ParamsMismatch` if the verkey is not of
+/// `params` and with `PSError::UnsupportedMessageCount` if it has too few messages for the schema.
+pub fn verify_bundle(bundle: &IssuerBundle, params: &Params) -> Result<bool, PSError> {
+    bundle.verkey.check_params(params)?;
+    check_schema(&bundle.verkey, &bundle.schema)?;
+    let msg = bundle_message(
+        &bundle.verkey,
+        &bundle.name,
+        &bundle.schema,
+        &bundle.logo_hash,
+        &bundle.endpoints,
+    );
+    bundle
+        .signature
+        .verify(vec![msg], &bundle.verkey.prefix(1)?, params)
+}
+
+fn check_schema(verkey: &Verkey, schema: &Schema) -> Result<(), PSError> {
+    if !verkey.supports(schema) {
+        return Err(PSError::UnsupportedMessageCount {
+            given: schema.message_count(),
+            min: MIN_MESSAGES,
+            max: verkey.supported_messages(),
+        });
+    }
+    Ok(())
+}
+
+fn push_bytes(bytes: &mut Vec<u8>, b: &[u8]) {
+    bytes.extend_from_slice(&(b.len() as u64).to_be_bytes());
+    bytes.extend_from_slice(b);
+}
+
+fn bundle_message(
+    verkey: &Verkey,
+    name: &str,
+    schema: &Schema,
+    logo_hash: &[u8],
+    endpoints: &[String],
+) -> FieldElement {
+    let mut bytes = ISSUER_BUNDLE_DOMAIN.to_vec();
+    bytes.append(&mut verkey.to_bytes());
+    push_bytes(&mut bytes, name.as_bytes());
+    push_bytes(&mut bytes, schema.id.as_bytes());
+    bytes.extend_from_slice(&(schema.attributes.len() as u64).to_be_bytes());
+    for a in &schema.attributes {
+        push_bytes(&mut bytes, a.as_bytes());
+    }
+    push_bytes(&mut bytes, logo_hash);
+    bytes.extend_from_slice(&(endpoints.len() as u64).to_be_bytes());
+    for e in endpoints {
+        push_bytes(&mut bytes, e.as_bytes());
+    }
+    FieldElement::from_msg_hash(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer::{CapacityPolicy, Issuer};
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_issuer_bundle() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let (other_sk, other_vk) = keygen(3, &params).unwrap();
+        let schema = Schema::new("diploma", &["name", "degree"]);
+        let endpoints = [
+            "https://university.example/issue",
+            "https://university.example/status/1",
+        ];
+        let bundle = IssuerBundle::new(
+            &sk,
+            vk.clone(),
+            "University",
+            schema.clone(),
+            &[7; 32],
+            &endpoints,
+            &params,
+        )
+        .unwrap();
+        assert!(verify_bundle(&bundle, &params).unwrap());
+        let issuer = Issuer::new(sk.clone(), vk.clone(), CapacityPolicy::Reject);
+        let from_issuer = issuer
+            .bundle("University", schema.clone(), &[7; 32], &endpoints, &params)
+            .unwrap();
+        assert!(verify_bundle(&from_issuer, &params).unwrap());
+
+        // Any change of the metadata invalidates the signature
+        let mut tampered = bundle.clone();
+        tampered.name = String::from("Universe");
+        assert!(!verify_bundle(&tampered, &params).unwrap());
+        let mut tampered = bundle.clone();
+        tampered.schema.attributes.swap(0, 1);
+        assert!(!verify_bundle(&tampered, &params).unwrap());
+        let mut tampered = bundle.clone();
+        tampered.logo_hash[0] = 8;
+        assert!(!verify_bundle(&tampered, &params).unwrap());
+        let mut tampered = bundle.clone();
+        tampered.endpoints.swap(0, 1);
+        assert!(!verify_bundle(&tampered, &params).unwrap());
+        // Concatenating the endpoints does not keep the message
+        let mut tampered = bundle.clone();
+        tampered.endpoints = vec![endpoints.concat()];
+        assert!(!verify_bundle(&tampered, &params).unwrap());
+        // Another verkey without its signature
+        let mut tampered = bundle.clone();
+        tampered.verkey = other_vk.clone();
+        assert!(!verify_bundle(&tampered, &params).unwrap());
+        // A bundle of another issuer claiming the metadata verifies for its own verkey only
+        let other = IssuerBundle::new(
+            &other_sk,
+            other_vk,
+            "University",
+            schema,
+            &[7; 32],
+            &endpoints,
+            &params,
+        )
+        .unwrap();
+        assert!(verify_bundle(&other, &params).unwrap());
+        assert_ne!(other.verkey, vk);
+
+        let large = Schema::new("transcript", &["a", "b", "c"]);
+        assert!(IssuerBundle::new(&sk, vk, "University", large, &[], &[], &params).is_err());
+        match verify_bundle(&bundle, &Params::new("other".as_bytes())) {
+            Err(PSError::ParamsMismatch) => (),
+            r => panic!("Expected ParamsMismatch but got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,313 @@
+// Proof of knowledge of a signature by one of a ring of issuers, without revealing which. Useful for federated
+// issuers whose verifiers only care that the credential comes from a known issuer. The proof is an
+// OR-composition (Cramer, Damgard, Schoenmakers) of the proof of knowledge of signature of section 6.2 of the
+// 2016 paper, one branch per verkey of the ring.
+// For the issuer that signed, the branch is the usual proof with the randomized signature and J. For every other
+// issuer, the prover picks sigma_1 = g^a and sigma_2 = g^b, and J = g_tilde^{b/a} * (X_tilde * Y_tilde_i^m_i...)^-1 for
+// the revealed m_i so that the pairing check e(sigma_1, J*X_tilde*Y_tilde_i^m_i...) == e(sigma_2, g_tilde) holds.
+// The prover cannot know the opening of such a J, so the proof of knowledge of that branch is simulated with a
+// challenge chosen in advance. The challenges of all branches must add up to the challenge of the whole proof,
+// which leaves the prover free to answer honestly only in the branch of the actual issuer.
+
+use crate::challenge_domain::ISSUER_RING;
+use crate::curve::{FieldElement, FieldElementVector, GroupElement, GroupElementVector};
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::{PoKOfSignature, PoKOfSignatureProof, ProofVerkeyGroup};
+use crate::signature::Signature;
+use crate::{VerkeyGroup, VerkeyGroupVec};
+use std::collections::{HashMap, HashSet};
+
+/// Proof of knowledge of signature for one verkey of the ring with its share of the challenge
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RingBranchProof {
+    pub challenge: FieldElement,
+    pub proof: PoKOfSignatureProof,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IssuerRingPoK {
+    pok: PoKOfSignature,
+    /// Simulated branch and its bytes for the challenge for every issuer of the ring, `None` for the signer
+    simulated: Vec<Option<(RingBranchProof, Vec<u8>)>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IssuerRingProof {
+    /// One branch per verkey of the ring, in the order of the ring
+    pub branches: Vec<RingBranchProof>,
+}
+
+impl IssuerRingPoK {
+    /// Start the proof of knowledge of `sig`, created by the verkey at index `signer` of `ring`. All verkeys of
+    /// the ring must support the same number of messages.
+    pub fn init(
+        sig: &Signature,
+        ring: &[Verkey],
+        signer: usize,
+        params: &Params,
+        messages: Vec<FieldElement>,
+        revealed_msg_indices: HashSet<usize>,
+    ) -> Result<Self, PSError> {
+        for vk in ring {
+            vk.check_params(params)?;
+        }
+        if signer >= ring.len() {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "Index of signer {} should be less than size of ring {}",
+                    signer,
+                    ring.len()
+                ),
+            });
+        }
+        for vk in ring {
+            Signature::check_verkey_and_messages_compat(messages.as_slice(), vk)?;
+        }
+        let mut revealed_msgs = HashMap::new();
+        for i in &revealed_msg_indices {
+            if let Some(m) = messages.get(*i) {
+                revealed_msgs.insert(*i, m.clone());
+            }
+        }
+
+        let mut simulated = Vec::with_capacity(ring.len());
+        for (i, vk) in ring.iter().enumerate() {
+            if i == signer {
+                simulated.push(None);
+                continue;
+            }
+            let branch = Self::simulate_branch(vk, params, &revealed_msgs)?;
+            let bytes =
+                branch
+                    .proof
+                    .get_bytes_for_challenge(revealed_msg_indices.clone(), vk, params);
+            simulated.push(Some((branch, bytes)));
+        }
+        let pok = PoKOfSignature::init(
+            sig,
+            &ring[signer],
+            params,
+            messages,
+            None,
+            revealed_msg_indices,
+        )?;
+        Ok(Self { pok, simulated })
+    }
+
+    /// Return byte representation of public elements of all branches so they can be used for challenge computation
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = ISSUER_RING.tag();
+        for s in &self.simulated {
+            match s {
+                Some((_, b)) => bytes.extend_from_slice(b),
+                None => bytes.append(&mut self.pok.to_bytes()),
+            }
+        }
+        bytes
+    }
+
+    /// The signer's branch gets the challenge minus the challenges of the simulated branches
+    pub fn gen_proof(self, challenge: &FieldElement) -> Result<IssuerRingProof, PSError> {
+        let mut signer_challenge = challenge.clone();
+        for (b, _) in self.simulated.iter().flatten() {
+            signer_challenge -= &b.challenge;
+        }
+        let proof = self.pok.gen_proof(&signer_challenge)?;
+        let mut signer_branch = Some(RingBranchProof {
+            challenge: signer_challenge,
+            proof,
+        });
+        let branches = self
+            .simulated
+            .into_iter()
+            .map(|s| match s {
+                Some((b, _)) => b,
+                None => signer_branch.take().unwrap(),
+            })
+            .collect();
+        Ok(IssuerRingProof { branches })
+    }
+
+    /// Branch for an issuer which did not sign, see the module comment
+    fn simulate_branch(
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+    ) -> Result<RingBranchProof, PSError> {
+        let a = FieldElement::random();
+        let b = FieldElement::random();
+        let sig = Signature {
+            sigma_1: &params.g * &a,
+            sigma_2: &params.g * &b,
+        };
+
+        // J = g_tilde^{b/a} * X_tilde^-1 * Y_tilde_i^-m_i for each revealed m_i
+        let mut bases = VerkeyGroupVec::with_capacity(revealed_msgs.len() + 2);
+        let mut exponents = FieldElementVector::with_capacity(revealed_msgs.len() + 2);
+        bases.push(params.generator_tilde());
+        exponents.push(&b * &a.inverse());
+        bases.push(vk.X_tilde.clone());
+        exponents.push(-FieldElement::one());
+        for (i, m) in revealed_msgs {
+            bases.push(vk.Y_tilde[*i].clone());
+            exponents.push(-m);
+        }
+        crate::profiling::record_scalar_muls(bases.len());
+        let J = bases
+            .multi_scalar_mul_var_time(exponents.as_slice())
+            .unwrap();
+
+        // Responses for g_tilde and each hidden message, the commitment is whatever they verify against
+        let challenge = FieldElement::random();
+        let hidden_msg_bases = PoKOfSignatureProof::hidden_msg_bases(vk, params, revealed_msgs);
+        let mut proof_vc = ProofVerkeyGroup {
+            commitment: VerkeyGroup::identity(),
+            responses: FieldElementVector::random(hidden_msg_bases.len()),
+        };
+        proof_vc.commitment = proof_vc.recompute_commitment(&hidden_msg_bases, &J, &challenge)?;
+        Ok(RingBranchProof {
+            challenge,
+            proof: PoKOfSignatureProof { sig, J, proof_vc },
+        })
+    }
+}
+
+impl IssuerRingProof {
+    /// Return bytes that need to be hashed for generating challenge, the bytes of each branch as in
+    /// `PoKOfSignatureProof::get_bytes_for_challenge`
+    pub fn get_bytes_for_challenge(
+        &self,
+        revealed_msg_indices: HashSet<usize>,
+        ring: &[Verkey],
+        params: &Params,
+    ) -> Vec<u8> {
+        let mut bytes = ISSUER_RING.tag();
+        for (b, vk) in self.branches.iter().zip(ring) {
+            bytes.append(&mut b.proof.get_bytes_for_challenge(
+                revealed_msg_indices.clone(),
+                vk,
+                params,
+            ));
+        }
+        bytes
+    }
+
+    /// Verify that the signature was created by one of the verkeys of `ring`, given in the same order as to the
+    /// prover. Every branch must verify with its challenge and the challenges must add up to `challenge`.
+    pub fn verify(
+        &self,
+        ring: &[Verkey],
+        params: &Params,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        if ring.is_empty() || self.branches.len() != ring.len() {
+            return Ok(false);
+        }
+        for vk in ring {
+            vk.check_params(params)?;
+        }
+        let mut sum = FieldElement::zero();
+        for b in &self.branches {
+            sum += &b.challenge;
+        }
+        if sum != *challenge {
+            return Ok(false);
+        }
+        for (b, vk) in self.branches.iter().zip(ring) {
+            if !b
+                .proof
+                .verify(vk, params, revealed_msgs.clone(), &b.challenge)?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_issuer_ring() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let keys: Vec<_> = (0..4).map(|_| keygen(count_msgs, &params).unwrap()).collect();
+        let ring: Vec<Verkey> = keys.iter().map(|(_, vk)| vk.clone()).collect();
+        let msgs = FieldElementVector::random(count_msgs);
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(1);
+        revealed_msg_indices.insert(3);
+        let mut revealed_msgs = HashMap::new();
+        for i in &revealed_msg_indices {
+            revealed_msgs.insert(*i, msgs[*i].clone());
+        }
+
+        for (signer, (sk, _)) in keys.iter().enumerate() {
+            let sig = Signature::new(msgs.as_slice(), sk, &params).unwrap();
+            let pok = IssuerRingPoK::init(
+                &sig,
+                &ring,
+                signer,
+                &params,
+                msgs.clone().into(),
+                revealed_msg_indices.clone(),
+            )
+            .unwrap();
+            let chal_prover = FieldElement::from_msg_hash(&pok.to_bytes());
+            let proof = pok.gen_proof(&chal_prover).unwrap();
+
+            let chal_verifier = FieldElement::from_msg_hash(&proof.get_bytes_for_challenge(
+                revealed_msg_indices.clone(),
+                &ring,
+                &params,
+            ));
+            assert_eq!(chal_prover, chal_verifier);
+            assert!(proof
+                .verify(&ring, &params, revealed_msgs.clone(), &chal_verifier)
+                .unwrap());
+
+            // Wrong revealed message
+            let mut wrong_msgs = revealed_msgs.clone();
+            wrong_msgs.insert(1, FieldElement::random());
+            assert!(!proof
+                .verify(&ring, &params, wrong_msgs, &chal_verifier)
+                .unwrap());
+
+            // Ring in another order
+            let mut reordered = ring.clone();
+            reordered.swap(0, 1);
+            assert!(!proof
+                .verify(&reordered, &params, revealed_msgs.clone(), &chal_verifier)
+                .unwrap());
+
+            // Ring without one issuer
+            assert!(!proof
+                .verify(&ring[1..], &params, revealed_msgs.clone(), &chal_verifier)
+                .unwrap());
+        }
+    }
+
+    #[test]
+    fn test_issuer_ring_signer_outside_ring() {
+        let count_msgs = 3;
+        let params = Params::new("test".as_bytes());
+        let ring: Vec<Verkey> = (0..3).map(|_| keygen(count_msgs, &params).unwrap().1).collect();
+        let (sk, _) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let pok = IssuerRingPoK::init(&sig, &ring, 0, &params, msgs.clone().into(), HashSet::new())
+            .unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        assert!(!proof.verify(&ring, &params, HashMap::new(), &chal).unwrap());
+
+        assert!(IssuerRingPoK::init(&sig, &ring, 3, &params, msgs.into(), HashSet::new()).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+// Two-party signing for the scheme from 2016 paper where the signing key is additively split between an online
+// service and an offline enclave: x = x_1 + x_2 and y_i = y_1_i + y_2_i. Neither party ever holds the full `Sigkey`
+// and a signature needs both. Since sigma_2 = h^{x + sum y_i*m_i} = h^{x_1 + sum y_1_i*m_i} * h^{x_2 + sum y_2_i*m_i},
+// each party signs with its share using the same h and the two partial signatures are multiplied.
+// h is never chosen by a party: it is the hash of the id and the messages of the request, derived again by the
+// enclave before signing as in `Signature::new_deterministic`. An online service choosing h could pick h = g^r and
+// recover g^{x_2 + sum y_2_i*m_i} from each reply, and after n + 1 replies compute the enclave's partial signature on
+// any messages, or reuse one h for two requests and combine the replies into a signature on other messages.
+//
+// Key generation, a party does not see the other's verkey share before committing to its own so that it cannot pick
+// its share to make the joint verkey one it knows the signing key of:
+//   1. Each party creates its `KeyShare` and sends `KeyShare::commitment`.
+//   2. On receiving the other party's commitment, each party sends `KeyShare::verkey_share`.
+//   3. Each party calls `KeyShare::joint_verkey` with the other party's verkey share and commitment and gets the
+//      verkey of the joint key, the sum of the verkey shares.
+// Signing:
+//   1. The online service creates a `SigningRequest` with a fresh id and the messages and sends it to the enclave.
+//   2. The enclave checks the messages are ones it agrees to sign and replies with `KeyShare::partial_sign`, which
+//      fails with `PSError::InvalidSigningRequest` if the h of the request is not the one derived from it.
+//   3. The online service creates its own partial signature and calls `SigningRequest::combine` which checks the
+//      resulting signature against the joint verkey.
+
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{check_message_count, Params, Sigkey, Verkey};
+use crate::signature::Signature;
+use crate::SignatureGroup;
+
+const COMMITMENT_DOMAIN: &[u8] = b"PS key share : ";
+const SIGMA_1_DOMAIN: &[u8] = b"PS joint signing : ";
+
+/// Share of the signing key of one party and the verkey for that share
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyShare {
+    sigkey: Sigkey,
+    verkey: Verkey,
+}
+
+/// Hash of the verkey share of a party, sent before the verkey share
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyShareCommitment {
+    pub commitment: FieldElement,
+}
+
+/// Sent by the online service to the enclave
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SigningRequest {
+    /// Id of the request, so that requests for the same messages get different signatures
+    pub request_id: Vec<u8>,
+    pub messages: Vec<FieldElement>,
+    /// sigma_1 of the signature, `SigningRequest::derive_h` of the id and the messages. Sent along so that a
+    /// request with any other h is refused rather than signed.
+    pub h: SignatureGroup,
+}
+
+/// h^{x_j + sum y_j_i*m_i} for the share j of a party
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartialSignature {
+    pub sigma_2: SignatureGroup,
+}
+
+impl KeyShare {
+    pub fn new(count_messages: usize, params: &Params) -> Result<Self, PSError> {
+        check_message_count(count_messages)?;
+        let sigkey = Sigkey {
+            x: FieldElement::random(),
+            y: (0..count_messages)
+                .map(|_| FieldElement::random())
+                .collect(),
+        };
+        let verkey = sigkey.to_verkey(params);
+        Ok(Self { sigkey, verkey })
+    }
+
+    pub fn verkey_share(&self) -> &Verkey {
+        &self.verkey
+    }
+
+    pub fn commitment(&self) -> KeyShareCommitment {
+        KeyShareCommitment::new(&self.verkey)
+    }
+
+    /// Verkey of the joint key given the other party's verkey share and the commitment it sent before
+    pub fn joint_verkey(
+        &self,
+        other_share: &Verkey,
+        other_commitment: &KeyShareCommitment,
+    ) -> Result<Verkey, PSError> {
+        if KeyShareCommitment::new(other_share) != *other_commitment {
+            return Err(PSError::KeyShareCommitmentMismatch);
+        }
+        if other_share.params_label_hash != self.verkey.params_label_hash {
+            return Err(PSError::ParamsMismatch);
+        }
+        if other_share.supported_messages() != self.verkey.supported_messages() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: self.verkey.supported_messages(),
+                given: other_share.supported_messages(),
+            });
+        }
+        Ok(Verkey {
+            X_tilde: &self.verkey.X_tilde + &other_share.X_tilde,
+            Y_tilde: self
+                .verkey
+                .Y_tilde
+                .iter()
+                .zip(other_share.Y_tilde.iter())
+                .map(|(a, b)| a + b)
+                .collect(),
+            params_label_hash: self.verkey.params_label_hash.clone(),
+        })
+    }
+
+    /// Partial signature on the messages of `request` with the h derived from the request. Fails with
+    /// `PSError::InvalidSigningRequest` if the request has another h.
+    pub fn partial_sign(&self, request: &SigningRequest) -> Result<PartialSignature, PSError> {
+        let h = request.checked_h()?;
+        Signature::check_sigkey_and_messages_compat(&request.messages, &self.sigkey)?;
+        let sigma_2 = Signature::sign_with_given_sigma_1(&request.messages, &self.sigkey, 0, &h)?;
+        Ok(PartialSignature { sigma_2 })
+    }
+}
+
+impl KeyShareCommitment {
+    fn new(verkey: &Verkey) -> Self {
+        let mut bytes = COMMITMENT_DOMAIN.to_vec();
+        bytes.append(&mut verkey.X_tilde.to_bytes());
+        for y in &verkey.Y_tilde {
+            bytes.append(&mut y.to_bytes());
+        }
+        Self {
+            commitment: FieldElement::from_msg_hash(&bytes),
+        }
+    }
+}
+
+impl SigningRequest {
+    /// Request `request_id` for signing `messages`. The id should not be reused for other messages.
+    pub fn new(request_id: &[u8], messages: Vec<FieldElement>) -> Self {
+        Self {
+            h: Self::derive_h(request_id, &messages),
+            request_id: request_id.to_vec(),
+            messages,
+        }
+    }
+
+    /// sigma_1 of the request of id `request_id` for `messages`, the hash of the domain, the length of the id, the
+    /// id and the messages to the group of signatures, of unknown discrete logarithm
+    pub fn derive_h(request_id: &[u8], messages: &[FieldElement]) -> SignatureGroup {
+        let mut bytes = SIGMA_1_DOMAIN.to_vec();
+        bytes.extend_from_slice(&(request_id.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(request_id);
+        for m in messages {
+            bytes.append(&mut m.to_bytes());
+        }
+        SignatureGroup::from_msg_hash(&bytes)
+    }
+
+    /// h derived from the id and the messages, failing with `PSError::InvalidSigningRequest` if `self.h` is another
+    /// one, e.g. one whose discrete logarithm the sender knows or the h of another request
+    pub fn checked_h(&self) -> Result<SignatureGroup, PSError> {
+        let h = Self::derive_h(&self.request_id, &self.messages);
+        if h != self.h {
+            return Err(PSError::InvalidSigningRequest);
+        }
+        Ok(h)
+    }
+
+    /// Signature from the partial signatures of both parties. Fails if the signature does not verify with the joint
+    /// verkey, e.g. when the other party did not sign with its share.
+    pub fn combine(
+        &self,
+        own: &PartialSignature,
+        other: &PartialSignature,
+        joint_verkey: &Verkey,
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        let sig = Signature {
+            sigma_1: self.checked_h()?,
+            sigma_2: &own.sigma_2 + &other.sigma_2,
+        };
+        if !sig.verify(self.messages.clone(), joint_verkey, params)? {
+            return Err(PSError::InvalidPartialSignature);
+        }
+        Ok(sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+
+    #[test]
+    fn test_joint_signing() {
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let online = KeyShare::new(count_msgs, &params).unwrap();
+        let enclave = KeyShare::new(count_msgs, &params).unwrap();
+
+        let online_commitment = online.commitment();
+        let enclave_commitment = enclave.commitment();
+        let vk = online
+            .joint_verkey(enclave.verkey_share(), &enclave_commitment)
+            .unwrap();
+        let vk_1 = enclave
+            .joint_verkey(online.verkey_share(), &online_commitment)
+            .unwrap();
+        assert_eq!(vk, vk_1);
+
+        let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        let request = SigningRequest::new(b"request 1", msgs.clone());
+        let enclave_partial = enclave.partial_sign(&request).unwrap();
+        let online_partial = online.partial_sign(&request).unwrap();
+        let sig = request
+            .combine(&online_partial, &enclave_partial, &vk, &params)
+            .unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+
+        // Partial signatures not from the shares of the joint key
+        let bad = PartialSignature {
+            sigma_2: SignatureGroup::random(),
+        };
+        match request.combine(&online_partial, &bad, &vk, &params) {
+            Err(PSError::InvalidPartialSignature) => (),
+            r => panic!("Expected InvalidPartialSignature but got {:?}", r),
+        }
+        // One partial signature alone is not a signature
+        let single = Signature {
+            sigma_1: request.h.clone(),
+            sigma_2: online_partial.sigma_2.clone(),
+        };
+        assert!(!single.verify(msgs.clone(), &vk, &params).unwrap());
+
+        // The enclave refuses an h chosen by the online service, one of known discrete logarithm or the h of another
+        // request reused for other messages
+        let r = FieldElement::random();
+        let mut known_log = SigningRequest::new(b"request 2", msgs.clone());
+        known_log.h = &params.g * &r;
+        let other_msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        let mut reused = SigningRequest::new(b"request 1", other_msgs.clone());
+        reused.h = request.h.clone();
+        for bad_request in &[known_log, reused] {
+            match enclave.partial_sign(bad_request) {
+                Err(PSError::InvalidSigningRequest) => (),
+                r => panic!("Expected InvalidSigningRequest but got {:?}", r),
+            }
+            assert!(bad_request
+                .combine(&online_partial, &enclave_partial, &vk, &params)
+                .is_err());
+        }
+        // h depends on the id and on the messages
+        let request_2 = SigningRequest::new(b"request 2", msgs.clone());
+        assert_ne!(request_2.h, request.h);
+        assert_ne!(SigningRequest::new(b"request 1", other_msgs).h, request.h);
+        let sig_2 = request_2
+            .combine(
+                &online.partial_sign(&request_2).unwrap(),
+                &enclave.partial_sign(&request_2).unwrap(),
+                &vk,
+                &params,
+            )
+            .unwrap();
+        assert!(sig_2.verify(msgs, &vk, &params).unwrap());
+
+        // Verkey share not matching the commitment sent before it
+        let other = KeyShare::new(count_msgs, &params).unwrap();
+        match online.joint_verkey(other.verkey_share(), &enclave_commitment) {
+            Err(PSError::KeyShareCommitmentMismatch) => (),
+            r => panic!("Expected KeyShareCommitmentMismatch but got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,458 @@
+// Credentials that can be presented at most k times in a scope, e.g. k accesses per service and epoch. The issuer
+// blind-signs a secret of the holder as a link secret, see `link_secret`, along with k. Show j of a scope, for j in
+// 1..=k, gives the tag H(scope || j)^secret with a proof that its exponent is the signed secret, as the tags of
+// `anon_token`, and reveals k and j so the verifier checks that j is at most k. A holder has k tags per scope, one
+// for each j, so a show beyond k repeats a tag of an earlier one, which the verifier detects with a
+// `SpentTagStore` and rejects with `PSError::TokenAlreadySpent`. Tags of different shows and scopes are
+// unlinkable. k is revealed so credentials of the same k should be issued to many holders; j only tells how many
+// shows the holder has left if they are made in order. Tags are checked to be in the prime order group, else adding a
+// point of small order to a tag would give a show with a new tag for the same index.
+//
+// Issuance:
+//   1. The holder calls `PendingKShowCredential::new` with a nonce from the issuer and sends the
+//      `LinkSecretCommitment`.
+//   2. The issuer replies with `KShowIssuer::issue` for the k of the credential.
+//   3. The holder gets the `KShowCredential` with `PendingKShowCredential::finish`.
+
+use crate::blind_signature::BlindingKey;
+use crate::challenge_domain::K_SHOW;
+use crate::curve::{scalar_from_u64, FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{keygen, Params, Sigkey, Verkey};
+use crate::link_secret::{LinkSecret, LinkSecretCommitment};
+use crate::pok_sig::PoKOfSignatureProof;
+use crate::scalar::Blinding;
+use crate::signature::{check_subgroup, Signature};
+use crate::VerkeyGroup;
+use std::collections::{HashMap, HashSet};
+
+const SHOW_DOMAIN: &[u8] = b"PS k-show : ";
+/// Index of k among the signed messages, after the secret
+const K_INDEX: usize = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KShowIssuer {
+    sigkey: Sigkey,
+    blinding_key: BlindingKey,
+    verkey: Verkey,
+}
+
+/// Secret of a requested credential and the blinding of its commitment, kept by the holder until the issuer replies
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingKShowCredential {
+    secret: LinkSecret,
+    blinding: Blinding,
+    k: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KShowCredential {
+    secret: LinkSecret,
+    signature: Signature,
+    k: u64,
+}
+
+/// Sent by the holder for show `index` of the credential in `scope`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Show {
+    pub scope: Vec<u8>,
+    /// Count of shows per scope of the credential
+    pub k: u64,
+    /// In 1..=k
+    pub index: u64,
+    /// H(scope || index)^secret
+    pub tag: VerkeyGroup,
+    /// H(scope || index)^blinding of the secret
+    pub tag_commitment: VerkeyGroup,
+    pub proof: PoKOfSignatureProof,
+}
+
+/// Tags of the accepted shows of each scope
+pub trait SpentTagStore {
+    /// Record `tag` as spent in `scope`. Returns false if it was already spent.
+    fn spend(&mut self, scope: &[u8], tag: &[u8]) -> Result<bool, PSError>;
+    fn is_spent(&self, scope: &[u8], tag: &[u8]) -> Result<bool, PSError>;
+}
+
+/// `SpentTagStore` in memory
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MemorySpentTags {
+    spent: HashMap<Vec<u8>, HashSet<Vec<u8>>>,
+}
+
+/// Verifier of shows recording their tags in a `SpentTagStore`
+#[derive(Clone, Debug, Default)]
+pub struct KShowVerifier<S: SpentTagStore> {
+    pub store: S,
+}
+
+fn show_base(scope: &[u8], index: u64) -> VerkeyGroup {
+    let mut bytes = SHOW_DOMAIN.to_vec();
+    bytes.extend_from_slice(&(scope.len() as u64).to_be_bytes());
+    bytes.extend_from_slice(scope);
+    bytes.extend_from_slice(&index.to_be_bytes());
+    VerkeyGroup::from_msg_hash(&bytes)
+}
+
+fn show_challenge(
+    bytes_for_challenge: Vec<u8>,
+    show_base: &VerkeyGroup,
+    tag: &VerkeyGroup,
+    tag_commitment: &VerkeyGroup,
+    nonce: &[u8],
+) -> FieldElement {
+    let mut bytes = K_SHOW.tag();
+    bytes.extend(bytes_for_challenge);
+    bytes.append(&mut show_base.to_bytes());
+    bytes.append(&mut tag.to_bytes());
+    bytes.append(&mut tag_commitment.to_bytes());
+    bytes.extend_from_slice(nonce);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+fn check_show_index(index: u64, k: u64) -> Result<(), PSError> {
+    if index == 0 || index > k {
+        return Err(PSError::InvalidIndex {
+            idx: index as usize,
+            valid: format!("[1, {}]", k),
+        });
+    }
+    Ok(())
+}
+
+impl KShowIssuer {
+    pub fn new(params: &Params) -> Result<Self, PSError> {
+        let (sigkey, verkey) = keygen(2, params)?;
+        let blinding_key = BlindingKey::new(&sigkey, params);
+        Ok(Self {
+            sigkey,
+            blinding_key,
+            verkey,
+        })
+    }
+
+    /// Verkey to verify shows with
+    pub fn verkey(&self) -> &Verkey {
+        &self.verkey
+    }
+
+    /// Key for holders to request credentials with
+    pub fn blinding_key(&self) -> &BlindingKey {
+        &self.blinding_key
+    }
+
+    /// Blind signature for the request of a holder of a credential for `k` shows per scope, `nonce` is the one
+    /// given to the holder for the request
+    pub fn issue(
+        &self,
+        request: &LinkSecretCommitment,
+        k: u64,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<Signature, PSError> {
+        if k == 0 {
+            return Err(PSError::GeneralError {
+                msg: String::from("Credential should allow at least 1 show"),
+            });
+        }
+        request.sign(
+            &[scalar_from_u64(k)],
+            &self.sigkey,
+            &self.blinding_key,
+            params,
+            nonce,
+        )
+    }
+}
+
+impl PendingKShowCredential {
+    /// New secret and the request to send to the issuer for a credential for `k` shows per scope
+    pub fn new(
+        blinding_key: &BlindingKey,
+        k: u64,
+        params: &Params,
+        nonce: &[u8],
+    ) -> (Self, LinkSecretCommitment) {
+        let secret = LinkSecret::new();
+        let (request, blinding) = secret.commit(blinding_key, params, nonce);
+        (
+            Self {
+                secret,
+                blinding,
+                k,
+            },
+            request,
+        )
+    }
+
+    /// Unblind the signature of the issuer, fails if it is not a signature on the secret and k
+    pub fn finish(
+        self,
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<KShowCredential, PSError> {
+        let signature =
+            self.secret
+                .unblind(sig, &self.blinding, &[scalar_from_u64(self.k)], vk, params)?;
+        Ok(KShowCredential {
+            secret: self.secret,
+            signature,
+            k: self.k,
+        })
+    }
+}
+
+impl KShowCredential {
+    pub fn k(&self) -> u64 {
+        self.k
+    }
+
+    /// Show `index` of the credential in `scope` for a verifier who sent `nonce`. Fails with `PSError::InvalidIndex`
+    /// if `index` is not in 1..=k.
+    pub fn show(
+        &self,
+        index: u64,
+        scope: &[u8],
+        vk: &Verkey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<Show, PSError> {
+        check_show_index(index, self.k)?;
+        let blinding = Blinding::random();
+        let revealed: HashSet<usize> = vec![K_INDEX].into_iter().collect();
+        let pok = self.secret.init_pok(
+            &self.signature,
+            vk,
+            params,
+            &[scalar_from_u64(self.k)],
+            Some(std::slice::from_ref(&blinding)),
+            revealed,
+        )?;
+        let base = show_base(scope, index);
+        let tag = &base * self.secret.secret();
+        let tag_commitment = &base * blinding.as_field_element();
+        let challenge = show_challenge(pok.to_bytes(), &base, &tag, &tag_commitment, nonce);
+        Ok(Show {
+            scope: scope.to_vec(),
+            k: self.k,
+            index,
+            tag,
+            tag_commitment,
+            proof: pok.gen_proof(&challenge)?,
+        })
+    }
+}
+
+impl Show {
+    /// Verify the proof of knowledge of a credential of `k` signed by `vk`, that `index` is in 1..=k and that `tag`
+    /// is for the secret of the credential. Does not check for overuse, see `KShowVerifier`. Fails with
+    /// `PSError::InvalidGroupElement` if `tag` or `tag_commitment` is outside the prime order group.
+    pub fn verify(&self, vk: &Verkey, params: &Params, nonce: &[u8]) -> Result<bool, PSError> {
+        check_subgroup(&[&self.tag, &self.tag_commitment])?;
+        if self.tag.is_identity() || check_show_index(self.index, self.k).is_err() {
+            return Ok(false);
+        }
+        let base = show_base(&self.scope, self.index);
+        let revealed: HashMap<usize, FieldElement> = vec![(K_INDEX, scalar_from_u64(self.k))]
+            .into_iter()
+            .collect();
+        let challenge = show_challenge(
+            self.proof
+                .get_bytes_for_challenge(revealed.keys().cloned().collect(), vk, params),
+            &base,
+            &self.tag,
+            &self.tag_commitment,
+            nonce,
+        );
+        if !self
+            .proof
+            .verify_with_link_secret(vk, params, revealed, &challenge)?
+        {
+            return Ok(false);
+        }
+        // H(scope || index)^response * tag^challenge == tag_commitment
+        let response = self.proof.get_resp_for_link_secret()?;
+        Ok(base.binary_scalar_mul(&self.tag, &response, &challenge) == self.tag_commitment)
+    }
+}
+
+impl SpentTagStore for MemorySpentTags {
+    fn spend(&mut self, scope: &[u8], tag: &[u8]) -> Result<bool, PSError> {
+        Ok(self
+            .spent
+            .entry(scope.to_vec())
+            .or_default()
+            .insert(tag.to_vec()))
+    }
+
+    fn is_spent(&self, scope: &[u8], tag: &[u8]) -> Result<bool, PSError> {
+        Ok(self
+            .spent
+            .get(scope)
+            .map(|tags| tags.contains(tag))
+            .unwrap_or(false))
+    }
+}
+
+impl MemorySpentTags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: SpentTagStore> KShowVerifier<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Accept a show in `scope` with `nonce`. Returns false if it does not verify and fails with
+    /// `PSError::TokenAlreadySpent` if a show with the same tag was accepted in `scope` before, i.e. the credential
+    /// was shown more than k times or the same show twice.
+    pub fn accept(
+        &mut self,
+        show: &Show,
+        scope: &[u8],
+        vk: &Verkey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        if show.scope != scope || !show.verify(vk, params, nonce)? {
+            return Ok(false);
+        }
+        if !self.store.spend(scope, &show.tag.to_bytes())? {
+            return Err(PSError::TokenAlreadySpent);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credential(issuer: &KShowIssuer, k: u64, params: &Params) -> KShowCredential {
+        let nonce = FieldElement::random().to_bytes();
+        let (pending, request) =
+            PendingKShowCredential::new(issuer.blinding_key(), k, params, &nonce);
+        let sig = issuer.issue(&request, k, params, &nonce).unwrap();
+        pending.finish(&sig, issuer.verkey(), params).unwrap()
+    }
+
+    #[test]
+    fn test_k_show_credentials() {
+        let params = Params::new("test".as_bytes());
+        let issuer = KShowIssuer::new(&params).unwrap();
+        let vk = issuer.verkey();
+        let cred = credential(&issuer, 3, &params);
+        let mut verifier = KShowVerifier::new(MemorySpentTags::new());
+        let scope = b"service, epoch 1".to_vec();
+
+        let mut tags = HashSet::new();
+        for index in 1..=3 {
+            let nonce = FieldElement::random().to_bytes();
+            let show = cred.show(index, &scope, vk, &params, &nonce).unwrap();
+            assert!(verifier.accept(&show, &scope, vk, &params, &nonce).unwrap());
+            tags.insert(show.tag.to_bytes());
+        }
+        assert_eq!(tags.len(), 3);
+        assert!(verifier
+            .store
+            .is_spent(&scope, tags.iter().next().unwrap())
+            .unwrap());
+
+        // A 4th show repeats a tag
+        let nonce = FieldElement::random().to_bytes();
+        let again = cred.show(2, &scope, vk, &params, &nonce).unwrap();
+        match verifier.accept(&again, &scope, vk, &params, &nonce) {
+            Err(PSError::TokenAlreadySpent) => (),
+            r => panic!("Expected TokenAlreadySpent but got {:?}", r),
+        }
+        assert!(cred.show(4, &scope, vk, &params, &nonce).is_err());
+        assert!(cred.show(0, &scope, vk, &params, &nonce).is_err());
+        // Claiming a larger k or an index beyond k
+        let mut larger_k = again.clone();
+        larger_k.k = 4;
+        larger_k.index = 4;
+        assert!(!larger_k.verify(vk, &params, &nonce).unwrap());
+        let mut beyond = again.clone();
+        beyond.index = 4;
+        assert!(!beyond.verify(vk, &params, &nonce).unwrap());
+        // Tag of another index
+        let mut other_index = again.clone();
+        other_index.index = 1;
+        assert!(!other_index.verify(vk, &params, &nonce).unwrap());
+
+        // Other scopes have their own k shows, and other credentials their own tags
+        let other_scope = b"service, epoch 2".to_vec();
+        let show = cred.show(2, &other_scope, vk, &params, &nonce).unwrap();
+        assert!(verifier
+            .accept(&show, &other_scope, vk, &params, &nonce)
+            .unwrap());
+        assert!(!tags.contains(&show.tag.to_bytes()));
+        let other = credential(&issuer, 3, &params);
+        let show = other.show(2, &scope, vk, &params, &nonce).unwrap();
+        assert!(verifier.accept(&show, &scope, vk, &params, &nonce).unwrap());
+        assert!(!verifier
+            .accept(&show, &other_scope, vk, &params, &nonce)
+            .unwrap());
+        assert!(!show
+            .verify(vk, &params, &FieldElement::random().to_bytes())
+            .unwrap());
+        let nonce = FieldElement::random().to_bytes();
+        let (_, request) = PendingKShowCredential::new(issuer.blinding_key(), 0, &params, &nonce);
+        assert!(issuer.issue(&request, 0, &params, &nonce).is_err());
+    }
+
+    #[test]
+    fn test_k_show_large_k() {
+        // k beyond the values of an isize is signed as its own scalar
+        let params = Params::new("test".as_bytes());
+        let issuer = KShowIssuer::new(&params).unwrap();
+        let vk = issuer.verkey();
+        let k = (1u64 << 63) + 1;
+        let cred = credential(&issuer, k, &params);
+        let nonce = FieldElement::random().to_bytes();
+        let show = cred.show(k, b"scope", vk, &params, &nonce).unwrap();
+        assert!(show.verify(vk, &params, &nonce).unwrap());
+        let mut other_k = show.clone();
+        other_k.k = k + 1;
+        assert!(!other_k.verify(vk, &params, &nonce).unwrap());
+    }
+
+    // The point of small order is of G1
+    #[cfg(feature = "SignatureG2")]
+    #[test]
+    fn test_tag_outside_group_rejected() {
+        let params = Params::new("test".as_bytes());
+        let issuer = KShowIssuer::new(&params).unwrap();
+        let vk = issuer.verkey();
+        let cred = credential(&issuer, 1, &params);
+        let mut verifier = KShowVerifier::new(MemorySpentTags::new());
+        let nonce = FieldElement::random().to_bytes();
+        let show = cred.show(1, b"scope", vk, &params, &nonce).unwrap();
+
+        // Point on the curve (x = 5) but outside the prime order subgroup of G1
+        let mut small_order = vec![0u8; crate::curve::GroupG1_SIZE];
+        small_order[0] = 2;
+        small_order[crate::curve::MODBYTES] = 5;
+        let small_order = VerkeyGroup::from_bytes(&small_order).unwrap();
+        let mut shifted_tag = show.clone();
+        shifted_tag.tag = &show.tag + &small_order;
+        let mut shifted_commitment = show.clone();
+        shifted_commitment.tag_commitment = &show.tag_commitment + &small_order;
+        for s in &[shifted_tag, shifted_commitment] {
+            match verifier.accept(s, b"scope", vk, &params, &nonce) {
+                Err(PSError::InvalidGroupElement) => (),
+                r => panic!("Expected InvalidGroupElement but got {:?}", r),
+            }
+        }
+
+        let mut identity = show.clone();
+        identity.tag = VerkeyGroup::identity();
+        assert!(!identity.verify(vk, &params, &nonce).unwrap());
+        assert!(verifier
+            .accept(&show, b"scope", vk, &params, &nonce)
+            .unwrap());
+    }
+}
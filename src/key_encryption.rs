@@ -0,0 +1,255 @@
+// Signing keys encrypted under a passphrase for storage at rest, behind the feature `encrypted-keys`.
+// `Sigkey::export_encrypted` derives a 32 byte key from the passphrase and a random salt with Argon2id and encrypts
+// `Sigkey::to_bytes` with XChaCha20-Poly1305 under a random nonce. The blob is
+// version || m_cost || t_cost || p_cost || salt || nonce || ciphertext, with the costs as 4 bytes big endian, and the
+// header before the ciphertext is the associated data, so changing the costs, the salt or the nonce fails decryption
+// like a wrong passphrase or a modified ciphertext, with `PSError::KeyDecryptionFailed`. Costs of a blob above
+// `MAX_M_COST`, `MAX_T_COST` and `MAX_P_COST` are rejected before deriving anything so that a blob cannot make the
+// import run for long or allocate much. The derived key and the encoded signing key are zeroized when dropped.
+
+use crate::errors::PSError;
+use crate::keys::Sigkey;
+use argon2::{Algorithm, Argon2, Params as Argon2Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::{thread_rng, RngCore};
+use zeroize::Zeroizing;
+
+/// Version of the format of the blob, its first byte
+pub const ENCRYPTED_KEY_VERSION: u8 = 1;
+
+pub const SALT_SIZE: usize = 16;
+pub const NONCE_SIZE: usize = 24;
+const KEY_SIZE: usize = 32;
+const HEADER_SIZE: usize = 1 + 3 * 4 + SALT_SIZE + NONCE_SIZE;
+
+/// Largest memory cost in KiB of a blob accepted by `Sigkey::import_encrypted`, 1 GiB
+pub const MAX_M_COST: u32 = 1 << 20;
+/// Largest count of passes of a blob accepted by `Sigkey::import_encrypted`
+pub const MAX_T_COST: u32 = 16;
+/// Largest count of lanes of a blob accepted by `Sigkey::import_encrypted`
+pub const MAX_P_COST: u32 = 16;
+
+/// Costs of Argon2id. The default is the memory and passes recommended by OWASP, 19 MiB and 2 passes, with 1 lane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfCosts {
+    /// Memory in KiB
+    pub m_cost: u32,
+    /// Passes over the memory
+    pub t_cost: u32,
+    /// Lanes
+    pub p_cost: u32,
+}
+
+impl Default for KdfCosts {
+    fn default() -> Self {
+        Self {
+            m_cost: 19 * 1024,
+            t_cost: 2,
+            p_cost: 1,
+        }
+    }
+}
+
+impl KdfCosts {
+    fn check(&self) -> Result<(), PSError> {
+        if self.m_cost > MAX_M_COST || self.t_cost > MAX_T_COST || self.p_cost > MAX_P_COST {
+            return Err(PSError::DeserializationError {
+                msg: format!(
+                    "Costs {} KiB, {} passes and {} lanes of encrypted key exceed {} KiB, {} passes and {} lanes",
+                    self.m_cost, self.t_cost, self.p_cost, MAX_M_COST, MAX_T_COST, MAX_P_COST
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    fn derive_key(
+        &self,
+        passphrase: &[u8],
+        salt: &[u8],
+    ) -> Result<Zeroizing<[u8; KEY_SIZE]>, PSError> {
+        let params = Argon2Params::new(self.m_cost, self.t_cost, self.p_cost, Some(KEY_SIZE))
+            .map_err(|e| PSError::GeneralError {
+                msg: format!("Invalid Argon2 costs: {}", e),
+            })?;
+        let mut key = Zeroizing::new([0u8; KEY_SIZE]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase, salt, &mut key[..])
+            .map_err(|e| PSError::GeneralError {
+                msg: format!("Argon2 failed: {}", e),
+            })?;
+        Ok(key)
+    }
+}
+
+impl Sigkey {
+    /// The key encrypted under `passphrase` with the default costs of Argon2id
+    pub fn export_encrypted(&self, passphrase: &[u8]) -> Result<Vec<u8>, PSError> {
+        self.export_encrypted_with_costs(passphrase, &KdfCosts::default())
+    }
+
+    /// The key encrypted under `passphrase`, deriving the key of the cipher with `costs`
+    pub fn export_encrypted_with_costs(
+        &self,
+        passphrase: &[u8],
+        costs: &KdfCosts,
+    ) -> Result<Vec<u8>, PSError> {
+        costs.check()?;
+        let mut salt = [0u8; SALT_SIZE];
+        let mut nonce = [0u8; NONCE_SIZE];
+        thread_rng().fill_bytes(&mut salt);
+        thread_rng().fill_bytes(&mut nonce);
+        let key = costs.derive_key(passphrase, &salt)?;
+
+        let mut blob = vec![ENCRYPTED_KEY_VERSION];
+        blob.extend_from_slice(&costs.m_cost.to_be_bytes());
+        blob.extend_from_slice(&costs.t_cost.to_be_bytes());
+        blob.extend_from_slice(&costs.p_cost.to_be_bytes());
+        blob.extend_from_slice(&salt);
+        blob.extend_from_slice(&nonce);
+        let plaintext = Zeroizing::new(self.to_bytes());
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&key[..]))
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &blob,
+                },
+            )
+            .map_err(|_| PSError::GeneralError {
+                msg: String::from("Encryption of the key failed"),
+            })?;
+        blob.extend_from_slice(&ciphertext);
+        Ok(blob)
+    }
+
+    /// Key of `blob`, from `export_encrypted`. Fails with `PSError::KeyDecryptionFailed` for a wrong passphrase or a
+    /// modified blob and with `PSError::DeserializationError` for a blob of another version or with costs above
+    /// the maximums.
+    pub fn import_encrypted(blob: &[u8], passphrase: &[u8]) -> Result<Self, PSError> {
+        if blob.len() <= HEADER_SIZE {
+            return Err(PSError::DeserializationError {
+                msg: format!("Encrypted key of {} bytes is too short", blob.len()),
+            });
+        }
+        if blob[0] != ENCRYPTED_KEY_VERSION {
+            return Err(PSError::DeserializationError {
+                msg: format!("Unknown version {} of encrypted key", blob[0]),
+            });
+        }
+        let (header, ciphertext) = blob.split_at(HEADER_SIZE);
+        let cost = |i: usize| {
+            let mut b = [0u8; 4];
+            b.copy_from_slice(&header[1 + 4 * i..5 + 4 * i]);
+            u32::from_be_bytes(b)
+        };
+        let costs = KdfCosts {
+            m_cost: cost(0),
+            t_cost: cost(1),
+            p_cost: cost(2),
+        };
+        costs.check()?;
+        let salt = &header[13..13 + SALT_SIZE];
+        let nonce = &header[13 + SALT_SIZE..];
+        let key = costs.derive_key(passphrase, salt)?;
+        let plaintext = XChaCha20Poly1305::new(Key::from_slice(&key[..]))
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| PSError::KeyDecryptionFailed)
+            .map(Zeroizing::new)?;
+        Self::from_bytes(&plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, Params};
+
+    /// Cheap costs so that tests run fast
+    const COSTS: KdfCosts = KdfCosts {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    fn encrypted() -> (Sigkey, Vec<u8>) {
+        let params = Params::new("test".as_bytes());
+        let (sk, _) = keygen(3, &params).unwrap();
+        let blob = sk
+            .export_encrypted_with_costs(b"correct horse", &COSTS)
+            .unwrap();
+        (sk, blob)
+    }
+
+    #[test]
+    fn test_encrypted_key_round_trip() {
+        let (sk, blob) = encrypted();
+        let sk_1 = Sigkey::import_encrypted(&blob, b"correct horse").unwrap();
+        assert_eq!(sk_1.to_bytes(), sk.to_bytes());
+        // Salt and nonce are fresh for each export
+        let blob_1 = sk
+            .export_encrypted_with_costs(b"correct horse", &COSTS)
+            .unwrap();
+        assert_ne!(blob, blob_1);
+    }
+
+    #[test]
+    fn test_encrypted_key_default_costs() {
+        let (sk, _) = encrypted();
+        let blob = sk.export_encrypted(b"correct horse").unwrap();
+        let sk_1 = Sigkey::import_encrypted(&blob, b"correct horse").unwrap();
+        assert_eq!(sk_1.to_bytes(), sk.to_bytes());
+    }
+
+    #[test]
+    fn test_encrypted_key_wrong_passphrase() {
+        let (_, blob) = encrypted();
+        match Sigkey::import_encrypted(&blob, b"battery staple") {
+            Err(PSError::KeyDecryptionFailed) => (),
+            r => panic!("Expected KeyDecryptionFailed but got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_encrypted_key_modified() {
+        // Every byte of the header after the version and of the ciphertext is authenticated
+        let (_, blob) = encrypted();
+        for i in &[4, 13, HEADER_SIZE - 1, HEADER_SIZE, blob.len() - 1] {
+            let mut modified = blob.clone();
+            modified[*i] ^= 1;
+            match Sigkey::import_encrypted(&modified, b"correct horse") {
+                Err(PSError::KeyDecryptionFailed) => (),
+                r => panic!("Expected KeyDecryptionFailed at {} but got {:?}", i, r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_encrypted_key_invalid_header() {
+        let (_, blob) = encrypted();
+        let mut other_version = blob.clone();
+        other_version[0] = 2;
+        let mut costly = blob.clone();
+        costly[1..5].copy_from_slice(&(MAX_M_COST + 1).to_be_bytes());
+        let mut many_lanes = blob.clone();
+        many_lanes[9..13].copy_from_slice(&(MAX_P_COST + 1).to_be_bytes());
+        for b in &[
+            other_version,
+            costly,
+            many_lanes,
+            blob[..HEADER_SIZE].to_vec(),
+        ] {
+            match Sigkey::import_encrypted(b, b"correct horse") {
+                Err(PSError::DeserializationError { .. }) => (),
+                r => panic!("Expected DeserializationError but got {:?}", r),
+            }
+        }
+    }
+}
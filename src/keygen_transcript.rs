@@ -0,0 +1,231 @@
+// Record of the generation of a key of the 2018 scheme for auditors. `rsskeygen_with_transcript` returns with the
+// keys a `KeygenTranscript` of the label of the params, the count of messages, the public key, a proof of possession
+// of the secret key and the time of generation. The proof of possession proves knowledge of x and y of
+// X_tilde = g_tilde^x and Y_tilde_1 = g_tilde^y with one Schnorr proof each and a shared challenge over the rest of
+// the transcript, so it cannot be moved to another transcript. The operator of the key ceremony signs the
+// transcript with a key for 1 message, giving a `SignedKeygenTranscript`: an auditor with the verkey of the
+// operator checks the signature, that the key was generated with the generators of the label, that it is
+// consistent, see `PKrss::verify_structure`, and the proof of possession. Changing any field breaks the
+// signature.
+
+use crate::challenge_domain::KEYGEN_TRANSCRIPT;
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{rsskeygen, PKrss, Params, SKrss, Sigkey, Verkey};
+use crate::pok_sig::{ProofVerkeyGroup, ProverCommittingVerkeyGroup};
+use crate::rss_signature::RSSPublicKey;
+use crate::signature::Signature;
+
+const TRANSCRIPT_DOMAIN: &[u8] = b"PS keygen transcript : ";
+
+/// Proofs of knowledge of x and y of a `SKrss`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyPossessionProof {
+    /// For X_tilde = g_tilde^x
+    pub x_proof: ProofVerkeyGroup,
+    /// For Y_tilde_1 = g_tilde^y
+    pub y_proof: ProofVerkeyGroup,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeygenTranscript {
+    /// Label the params were created from with `Params::new`
+    pub params_label: Vec<u8>,
+    pub n: usize,
+    pub public_key: PKrss,
+    pub pop: KeyPossessionProof,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedKeygenTranscript {
+    pub transcript: KeygenTranscript,
+    /// Signature of the operator on `KeygenTranscript::to_message`
+    pub signature: Signature,
+}
+
+/// Same as `rsskeygen` with the params of `params_label`, also returning the transcript of the generation at
+/// `timestamp`
+pub fn rsskeygen_with_transcript(
+    count_messages: usize,
+    params_label: &[u8],
+    timestamp: u64,
+) -> Result<(SKrss, PKrss, KeygenTranscript), PSError> {
+    let params = Params::new(params_label);
+    let (sk, pk) = rsskeygen(count_messages, &params)?;
+    let transcript = KeygenTranscript::new(&sk, &pk, params_label, timestamp)?;
+    Ok((sk, pk, transcript))
+}
+
+/// Bytes of the transcript without the proof of possession
+fn statement_bytes(params_label: &[u8], n: usize, pk: &PKrss, timestamp: u64) -> Vec<u8> {
+    let mut bytes = (params_label.len() as u64).to_be_bytes().to_vec();
+    bytes.extend_from_slice(params_label);
+    bytes.extend_from_slice(&(n as u64).to_be_bytes());
+    bytes.append(&mut pk.to_bytes());
+    bytes.extend_from_slice(&timestamp.to_be_bytes());
+    bytes
+}
+
+fn pop_challenge(statement: &[u8], x_committed: &[u8], y_committed: &[u8]) -> FieldElement {
+    let mut bytes = KEYGEN_TRANSCRIPT.tag();
+    bytes.extend_from_slice(statement);
+    bytes.extend_from_slice(x_committed);
+    bytes.extend_from_slice(y_committed);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl KeygenTranscript {
+    /// Transcript of keys generated with the params of `params_label` at `timestamp`
+    pub fn new(
+        sk: &SKrss,
+        pk: &PKrss,
+        params_label: &[u8],
+        timestamp: u64,
+    ) -> Result<Self, PSError> {
+        let params = Params::new(params_label);
+        let mut committing_x = ProverCommittingVerkeyGroup::new();
+        committing_x.commit(&params.g_tilde, None);
+        let committed_x = committing_x.finish();
+        let mut committing_y = ProverCommittingVerkeyGroup::new();
+        committing_y.commit(&params.g_tilde, None);
+        let committed_y = committing_y.finish();
+
+        let n = pk.supported_messages();
+        let challenge = pop_challenge(
+            &statement_bytes(params_label, n, pk, timestamp),
+            &committed_x.to_bytes(),
+            &committed_y.to_bytes(),
+        );
+        let pop = KeyPossessionProof {
+            x_proof: committed_x.gen_proof(&challenge, std::slice::from_ref(&sk.x))?,
+            y_proof: committed_y.gen_proof(&challenge, std::slice::from_ref(&sk.y))?,
+        };
+        Ok(Self {
+            params_label: params_label.to_vec(),
+            n,
+            public_key: pk.clone(),
+            pop,
+            timestamp,
+        })
+    }
+
+    /// Check that the key is of the count of messages `n` with the generators of `params_label`, that it is
+    /// consistent and the proof of possession
+    pub fn verify(&self) -> Result<bool, PSError> {
+        let params = Params::new(&self.params_label);
+        let pk = &self.public_key;
+        if pk.supported_messages() != self.n || pk.generators() != params.generators() {
+            return Ok(false);
+        }
+        if !pk.verify_structure()?.is_valid() {
+            return Ok(false);
+        }
+        // Same bytes as of the `ProverCommittedVerkeyGroup`s of `new`, base || commitment
+        let committed_bytes = |proof: &ProofVerkeyGroup| {
+            [params.g_tilde.to_bytes(), proof.commitment.to_bytes()].concat()
+        };
+        let challenge = pop_challenge(
+            &statement_bytes(&self.params_label, self.n, pk, self.timestamp),
+            &committed_bytes(&self.pop.x_proof),
+            &committed_bytes(&self.pop.y_proof),
+        );
+        let bases = [params.generator_tilde()];
+        Ok(self.pop.x_proof.verify(&bases, &pk.X_tilde, &challenge)?
+            && self
+                .pop
+                .y_proof
+                .verify(&bases, &pk.Y_tilde_i[0], &challenge)?)
+    }
+
+    /// Encoding of all the fields
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            statement_bytes(&self.params_label, self.n, &self.public_key, self.timestamp);
+        for proof in &[&self.pop.x_proof, &self.pop.y_proof] {
+            bytes.append(&mut proof.commitment.to_bytes());
+            for r in proof.responses.iter() {
+                bytes.append(&mut r.to_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Message the operator signs
+    pub fn to_message(&self) -> FieldElement {
+        FieldElement::from_msg_hash(&[TRANSCRIPT_DOMAIN, &self.to_bytes()].concat())
+    }
+
+    /// Sign the transcript with `operator_key`, a key for 1 message
+    pub fn sign(
+        self,
+        operator_key: &Sigkey,
+        params: &Params,
+    ) -> Result<SignedKeygenTranscript, PSError> {
+        let signature = Signature::new(&[self.to_message()], operator_key, params)?;
+        Ok(SignedKeygenTranscript {
+            transcript: self,
+            signature,
+        })
+    }
+}
+
+impl SignedKeygenTranscript {
+    /// Check the signature with the verkey of the operator and its params, and the transcript
+    pub fn verify(&self, operator_verkey: &Verkey, params: &Params) -> Result<bool, PSError> {
+        Ok(self
+            .signature
+            .verify(vec![self.transcript.to_message()], operator_verkey, params)?
+            && self.transcript.verify()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_keygen_transcript() {
+        let label = "test".as_bytes();
+        let (sk, pk, transcript) = rsskeygen_with_transcript(4, label, 1_700_000_000).unwrap();
+        assert_eq!(transcript.public_key.to_bytes(), pk.to_bytes());
+        assert!(transcript.verify().unwrap());
+
+        let operator_params = Params::new("operator".as_bytes());
+        let (operator_sk, operator_vk) = keygen(1, &operator_params).unwrap();
+        let signed = transcript
+            .clone()
+            .sign(&operator_sk, &operator_params)
+            .unwrap();
+        assert!(signed.verify(&operator_vk, &operator_params).unwrap());
+        let (_, other_vk) = keygen(1, &operator_params).unwrap();
+        assert!(!signed.verify(&other_vk, &operator_params).unwrap());
+
+        // Tampering with any field
+        let mut t = signed.clone();
+        t.transcript.timestamp += 1;
+        assert!(!t.verify(&operator_vk, &operator_params).unwrap());
+        assert!(!t.transcript.verify().unwrap());
+        let mut t = signed.clone();
+        t.transcript.params_label = b"other".to_vec();
+        assert!(!t.transcript.verify().unwrap());
+        let mut t = signed.clone();
+        t.transcript.n = 3;
+        assert!(!t.transcript.verify().unwrap());
+        let (_, other_pk) = rsskeygen(4, &Params::new(label)).unwrap();
+        let mut t = signed.clone();
+        t.transcript.public_key = other_pk.clone();
+        assert!(!t.transcript.verify().unwrap());
+
+        // Proof of possession needs the secret key of the public key
+        let (other_sk, _) = rsskeygen(4, &Params::new(label)).unwrap();
+        let wrong = KeygenTranscript::new(&other_sk, &pk, label, 1_700_000_000).unwrap();
+        assert!(!wrong.verify().unwrap());
+        let for_other = KeygenTranscript::new(&sk, &pk, label, 1_700_000_001).unwrap();
+        let mut moved = for_other.clone();
+        moved.pop = transcript.pop.clone();
+        assert!(!moved.verify().unwrap());
+    }
+}
@@ -1,12 +1,17 @@
-use std::ops::Add;
-
-use amcl_wrapper::field_elem::FieldElement;
-use amcl_wrapper::group_elem::GroupElement;
-use amcl_wrapper::group_elem_g2::G2;
-use amcl_wrapper::group_elem_g1::G1;
+use rand::rngs::ThreadRng;
+use rand::{thread_rng, CryptoRng, RngCore};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
+use crate::curve::{scalar_from_u64, FieldElement, FieldElement_SIZE, GroupElement};
 use crate::errors::PSError;
-use crate::{VerkeyGroup, SignatureGroup};
+use crate::params_registry;
+use crate::scalar::SecretScalar;
+use crate::signature::group_elem_from_bytes;
+use crate::{VerkeyGroup, SignatureGroup, SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Sigkey {
@@ -18,127 +23,1264 @@ pub struct Sigkey {
 pub struct Verkey {
     pub X_tilde: VerkeyGroup,
     pub Y_tilde: Vec<VerkeyGroup>,
+    /// `Params::label_hash` of the params the key was generated with
+    pub params_label_hash: FieldElement,
 }
 
+/// Secret key of the redactable signature scheme. `n` is the count of messages of the public key it was generated with.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SKrss {
+    pub n: usize,
     pub x: FieldElement,
     pub y: FieldElement,
 }
 
+/// Public key of the redactable signature scheme. `Y_j_1_to_n[j-1]` is g^{y^j} for j in 1..=n,
+/// `Y_k_nplus2_to_2n[k-n-2]` is g^{y^k} for k in n+2..=2n and `Y_tilde_i[i-1]` is g_tilde^{y^i} for i in 1..=n.
+/// g^{y^{n+1}} is deliberately absent. Deserialization checks the lengths of the vectors against `n`.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "PKrssFields")]
 pub struct PKrss {
-    pub g: G2,
-    pub g_tilde: G1,
-    pub Y_j_1_to_n: Vec<G2>,
-    pub Y_k_nplus2_to_2n: Vec<G2>,
-    pub X_tilde: G1,
-    Y_tilde_i: Vec<VerkeyGroup>,
+    pub n: usize,
+    pub g: SignatureGroup,
+    pub g_tilde: VerkeyGroup,
+    pub Y_j_1_to_n: Vec<SignatureGroup>,
+    pub Y_k_nplus2_to_2n: Vec<SignatureGroup>,
+    pub X_tilde: VerkeyGroup,
+    pub Y_tilde_i: Vec<VerkeyGroup>,
+    /// `Params::label_hash` of the params the key was generated with
+    pub params_label_hash: FieldElement,
+}
+
+/// Fields of `PKrss` as deserialized, before checking that they agree with each other
+#[derive(Deserialize)]
+pub(crate) struct PKrssFields {
+    pub(crate) n: usize,
+    pub(crate) g: SignatureGroup,
+    pub(crate) g_tilde: VerkeyGroup,
+    pub(crate) Y_j_1_to_n: Vec<SignatureGroup>,
+    pub(crate) Y_k_nplus2_to_2n: Vec<SignatureGroup>,
+    pub(crate) X_tilde: VerkeyGroup,
+    pub(crate) Y_tilde_i: Vec<VerkeyGroup>,
+    pub(crate) params_label_hash: FieldElement,
+}
+
+impl TryFrom<PKrssFields> for PKrss {
+    type Error = PSError;
+
+    fn try_from(f: PKrssFields) -> Result<Self, PSError> {
+        if check_message_count(f.n).is_err()
+            || f.Y_j_1_to_n.len() != f.n
+            || f.Y_k_nplus2_to_2n.len() != f.n - 1
+            || f.Y_tilde_i.len() != f.n
+        {
+            return Err(PSError::DeserializationError {
+                msg: format!("Elements of key do not match count of messages {}", f.n),
+            });
+        }
+        Ok(Self {
+            n: f.n,
+            g: f.g,
+            g_tilde: f.g_tilde,
+            Y_j_1_to_n: f.Y_j_1_to_n,
+            Y_k_nplus2_to_2n: f.Y_k_nplus2_to_2n,
+            X_tilde: f.X_tilde,
+            Y_tilde_i: f.Y_tilde_i,
+            params_label_hash: f.params_label_hash,
+        })
+    }
+}
+
+/// Size of the message count prefix in the serialized `PKrss` and `SKrss`
+pub(crate) const PKRSS_HEADER_SIZE: usize = 8;
+
+/// Offset of g in the serialized `PKrss`, after the message count and the label hash of the params
+pub(crate) const PKRSS_ELEMENTS_OFFSET: usize = PKRSS_HEADER_SIZE + FieldElement_SIZE;
+
+/// Smallest count of messages keys are generated for
+pub const MIN_MESSAGES: usize = 1;
+
+/// Largest count of messages keys are generated for. The redactable signature scheme indexes the elements Y_k
+/// for k up to 2n and the serialized `PKrss` has 2n elements of `SignatureGroup` and n + 2 of `VerkeyGroup`,
+/// this bound keeps these computations far from overflowing `usize`. The same bound applies to both schemes.
+pub const MAX_MESSAGES: usize = usize::MAX / (4 * SIGNATURE_GROUP_SIZE + 4 * VERKEY_GROUP_SIZE);
+
+/// Limits of `DeserializeLimits::default`
+pub const DEFAULT_MAX_DESERIALIZED_MESSAGES: usize = 1 << 16;
+pub const DEFAULT_MAX_DESERIALIZED_BYTES: usize = 1 << 26;
+
+/// Limits on the input of the `from_bytes_with_limits` of keys, credentials and proofs, for input from peers. The
+/// length of the input is checked before anything is read and the count of messages, from a header or implied by
+/// the length, before anything is allocated. `from_bytes` uses the default limits, which allow keys of
+/// `DEFAULT_MAX_DESERIALIZED_MESSAGES` messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeserializeLimits {
+    pub max_messages: usize,
+    pub max_bytes: usize,
+}
+
+impl Default for DeserializeLimits {
+    fn default() -> Self {
+        Self { max_messages: DEFAULT_MAX_DESERIALIZED_MESSAGES, max_bytes: DEFAULT_MAX_DESERIALIZED_BYTES }
+    }
+}
+
+impl DeserializeLimits {
+    pub fn new(max_messages: usize, max_bytes: usize) -> Self {
+        Self { max_messages, max_bytes }
+    }
+
+    /// No limits other than `MAX_MESSAGES`, for trusted input
+    pub fn unlimited() -> Self {
+        Self { max_messages: MAX_MESSAGES, max_bytes: usize::MAX }
+    }
+
+    pub(crate) fn check_bytes(&self, bytes: &[u8]) -> Result<(), PSError> {
+        if bytes.len() > self.max_bytes {
+            return Err(PSError::DeserializeLimitExceeded { limit: String::from("bytes"), given: bytes.len(), max: self.max_bytes });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_messages(&self, count_messages: usize) -> Result<(), PSError> {
+        if count_messages > self.max_messages {
+            return Err(PSError::DeserializeLimitExceeded { limit: String::from("messages"), given: count_messages, max: self.max_messages });
+        }
+        Ok(())
+    }
+
+    /// Count of messages from the header of a serialized key, see `PKrss::msg_count_from_header`
+    pub(crate) fn msg_count_from_header(&self, bytes: &[u8]) -> Result<usize, PSError> {
+        self.check_bytes(bytes)?;
+        let n = PKrss::msg_count_from_header(bytes)?;
+        self.check_messages(n)?;
+        Ok(n)
+    }
+}
+
+/// Check that keys can be generated for `count_messages` messages
+pub fn check_message_count(count_messages: usize) -> Result<(), PSError> {
+    if !(MIN_MESSAGES..=MAX_MESSAGES).contains(&count_messages) {
+        return Err(PSError::UnsupportedMessageCount {
+            given: count_messages,
+            min: MIN_MESSAGES,
+            max: MAX_MESSAGES,
+        });
+    }
+    Ok(())
+}
+
+/// Whether key generation checks the generated keys with `Sigkey::check_quality` or `SKrss::check_quality`.
+/// Randomly generated keys fail the checks only with negligible probability unless the random number generator
+/// is broken.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyQualityChecks {
+    #[default]
+    Enabled,
+    Disabled,
+}
+
+impl Sigkey {
+    /// Signing key of the secret scalars x and y_1..y_n, checked with `check_quality`
+    pub fn from_scalars(x: SecretScalar, y: Vec<SecretScalar>) -> Result<Self, PSError> {
+        let sk = Sigkey { x: x.into_field_element(), y: y.into_iter().map(SecretScalar::into_field_element).collect() };
+        sk.check_quality()?;
+        Ok(sk)
+    }
+
+    pub fn supported_messages(&self) -> usize {
+        self.y.len()
+    }
+
+    /// Fails with `PSError::WeakKey` if x or any y_i is 0 or if two y_i are equal, in which case the signature
+    /// does not bind the messages to their positions
+    pub fn check_quality(&self) -> Result<(), PSError> {
+        if self.x.is_zero() {
+            return Err(weak_key("x is 0"));
+        }
+        let mut seen = HashSet::new();
+        for (i, y_i) in self.y.iter().enumerate() {
+            if y_i.is_zero() {
+                return Err(weak_key(&format!("y_{} is 0", i)));
+            }
+            if !seen.insert(y_i.to_bytes()) {
+                return Err(weak_key(&format!("y_{} is equal to a previous y", i)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Verkey {
+    pub fn supported_messages(&self) -> usize {
+        self.Y_tilde.len()
+    }
+
+    /// Checks for keys from outside, like `Sigkey::check_quality` on the verkey: X_tilde and every Y_tilde_i must be
+    /// elements of the prime order group other than the identity and the Y_tilde_i must be distinct.
+    pub fn sanity_check(&self) -> Result<(), PSError> {
+        if self.X_tilde.is_identity() {
+            return Err(weak_key("X_tilde is the identity"));
+        }
+        if !self.X_tilde.has_correct_order() {
+            return Err(PSError::InvalidGroupElement);
+        }
+        let mut seen = HashSet::new();
+        for (i, Y_i) in self.Y_tilde.iter().enumerate() {
+            if Y_i.is_identity() {
+                return Err(weak_key(&format!("Y_tilde_{} is the identity", i)));
+            }
+            if !Y_i.has_correct_order() {
+                return Err(PSError::InvalidGroupElement);
+            }
+            if !seen.insert(Y_i.to_bytes()) {
+                return Err(weak_key(&format!("Y_tilde_{} is equal to a previous Y_tilde", i)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_prefix(count_messages: usize, supported: usize) -> Result<(), PSError> {
+    if count_messages < MIN_MESSAGES || count_messages > supported {
+        return Err(PSError::UnsupportedMessageCount { given: count_messages, min: MIN_MESSAGES, max: supported });
+    }
+    Ok(())
+}
+
+fn weak_key(reason: &str) -> PSError {
+    PSError::WeakKey { reason: String::from(reason) }
+}
+
+impl SKrss {
+    /// Secret key of the secret scalars x and y for `n` messages, checked with `check_quality`
+    pub fn from_scalars(n: usize, x: SecretScalar, y: SecretScalar) -> Result<Self, PSError> {
+        check_message_count(n)?;
+        let sk = SKrss { n, x: x.into_field_element(), y: y.into_field_element() };
+        sk.check_quality()?;
+        Ok(sk)
+    }
+
+    pub fn supported_messages(&self) -> usize {
+        self.n
+    }
+
+    /// Fails with `PSError::WeakKey` if x or y is 0 or if y^k = 1 for some k < 2n, e.g. y = 1, as then the
+    /// powers y^1..y^{2n} the key is made of are not distinct
+    pub fn check_quality(&self) -> Result<(), PSError> {
+        if self.x.is_zero() {
+            return Err(weak_key("x is 0"));
+        }
+        if self.y.is_zero() {
+            return Err(weak_key("y is 0"));
+        }
+        let mut y_k = self.y.clone();
+        for k in 1..2 * self.n {
+            if y_k.is_one() {
+                return Err(weak_key(&format!("y^{} is 1", k)));
+            }
+            y_k = &y_k * &self.y;
+        }
+        Ok(())
+    }
+
+    /// Encoding as count of messages n as 8 bytes big endian followed by x || y
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.n as u64).to_be_bytes().to_vec();
+        bytes.append(&mut self.x.to_bytes());
+        bytes.append(&mut self.y.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_bytes_with_limits(bytes, &DeserializeLimits::default())
+    }
+
+    pub fn from_bytes_with_limits(bytes: &[u8], limits: &DeserializeLimits) -> Result<Self, PSError> {
+        let n = limits.msg_count_from_header(bytes)?;
+        if bytes.len() != PKRSS_HEADER_SIZE + 2 * FieldElement_SIZE {
+            return Err(PSError::DeserializationError {
+                msg: format!("Incorrect number of bytes {} for secret key", bytes.len()),
+            });
+        }
+        let field_elem = |b: &[u8]| {
+            FieldElement::from_bytes(b).map_err(|e| PSError::DeserializationError {
+                msg: format!("{}", e),
+            })
+        };
+        let x = field_elem(&bytes[PKRSS_HEADER_SIZE..PKRSS_HEADER_SIZE + FieldElement_SIZE])?;
+        let y = field_elem(&bytes[PKRSS_HEADER_SIZE + FieldElement_SIZE..])?;
+        Ok(Self { n, x, y })
+    }
+}
+
+/// Label hash of a serialized key. Values of at least the order of the group are reduced by `from_bytes`, they are
+/// rejected so that a key has a single encoding.
+pub(crate) fn label_hash_from_bytes(bytes: &[u8]) -> Result<FieldElement, PSError> {
+    let hash = FieldElement::from_bytes(bytes).map_err(|e| PSError::DeserializationError {
+        msg: format!("{}", e),
+    })?;
+    if hash.to_bytes().as_slice() != bytes {
+        return Err(PSError::DeserializationError {
+            msg: String::from("Params label hash is not reduced"),
+        });
+    }
+    Ok(hash)
+}
+
+impl PKrss {
+    pub fn supported_messages(&self) -> usize {
+        self.n
+    }
+
+    /// Fixed size encoding as count of messages n as 8 bytes big endian followed by params_label_hash ||
+    /// g || g_tilde || X_tilde || Y_tilde_1..Y_tilde_n || Y_1..Y_n || Y_{n+2}..Y_{2n}
+    /// so that any element can be located without reading the ones before it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let n = self.n;
+        let mut bytes = Vec::with_capacity(Self::serialized_size(n));
+        bytes.extend_from_slice(&(n as u64).to_be_bytes());
+        bytes.append(&mut self.params_label_hash.to_bytes());
+        bytes.append(&mut self.g.to_bytes());
+        bytes.append(&mut self.g_tilde.to_bytes());
+        bytes.append(&mut self.X_tilde.to_bytes());
+        for e in &self.Y_tilde_i {
+            bytes.append(&mut e.to_bytes());
+        }
+        for e in self.Y_j_1_to_n.iter().chain(self.Y_k_nplus2_to_2n.iter()) {
+            bytes.append(&mut e.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_bytes_with_limits(bytes, &DeserializeLimits::default())
+    }
+
+    pub fn from_bytes_with_limits(bytes: &[u8], limits: &DeserializeLimits) -> Result<Self, PSError> {
+        let n = limits.msg_count_from_header(bytes)?;
+        if bytes.len() != Self::serialized_size(n) {
+            return Err(PSError::DeserializationError {
+                msg: format!(
+                    "Expected {} bytes for key of {} messages but found {}",
+                    Self::serialized_size(n),
+                    n,
+                    bytes.len()
+                ),
+            });
+        }
+        let params_label_hash = label_hash_from_bytes(&bytes[PKRSS_HEADER_SIZE..PKRSS_ELEMENTS_OFFSET])?;
+        let mut offset = PKRSS_ELEMENTS_OFFSET;
+        let next_s = |offset: &mut usize| {
+            let e = group_elem_from_bytes::<SignatureGroup>(&bytes[*offset..*offset + SIGNATURE_GROUP_SIZE]);
+            *offset += SIGNATURE_GROUP_SIZE;
+            e
+        };
+        let g = next_s(&mut offset)?;
+        let next_v = |offset: &mut usize| {
+            let e = group_elem_from_bytes::<VerkeyGroup>(&bytes[*offset..*offset + VERKEY_GROUP_SIZE]);
+            *offset += VERKEY_GROUP_SIZE;
+            e
+        };
+        let g_tilde = next_v(&mut offset)?;
+        let X_tilde = next_v(&mut offset)?;
+        let mut Y_tilde_i = Vec::with_capacity(n);
+        for _ in 0..n {
+            Y_tilde_i.push(next_v(&mut offset)?);
+        }
+        let mut Y_j_1_to_n = Vec::with_capacity(n);
+        for _ in 0..n {
+            Y_j_1_to_n.push(next_s(&mut offset)?);
+        }
+        let mut Y_k_nplus2_to_2n = Vec::with_capacity(n.saturating_sub(1));
+        for _ in 1..n {
+            Y_k_nplus2_to_2n.push(next_s(&mut offset)?);
+        }
+        Ok(Self { n, g, g_tilde, Y_j_1_to_n, Y_k_nplus2_to_2n, X_tilde, Y_tilde_i, params_label_hash })
+    }
+
+    /// Count of messages from the first `PKRSS_HEADER_SIZE` bytes of the serialized key
+    pub(crate) fn msg_count_from_header(bytes: &[u8]) -> Result<usize, PSError> {
+        if bytes.len() < PKRSS_HEADER_SIZE {
+            return Err(PSError::DeserializationError {
+                msg: String::from("Serialized key is too short"),
+            });
+        }
+        let mut buf = [0u8; PKRSS_HEADER_SIZE];
+        buf.copy_from_slice(&bytes[..PKRSS_HEADER_SIZE]);
+        let n = u64::from_be_bytes(buf);
+        if n < MIN_MESSAGES as u64 || n > MAX_MESSAGES as u64 {
+            return Err(PSError::DeserializationError {
+                msg: format!("Invalid count of messages {} in serialized key", n),
+            });
+        }
+        Ok(n as usize)
+    }
+
+    pub(crate) fn serialized_size(n: usize) -> usize {
+        PKRSS_ELEMENTS_OFFSET + (2 * n) * SIGNATURE_GROUP_SIZE + (n + 2) * VERKEY_GROUP_SIZE
+    }
+
+    /// Fails with `PSError::ParamsMismatch` if the key was not generated with `params`
+    pub fn check_params(&self, params: &Params) -> Result<(), PSError> {
+        if self.params_label_hash != params.label_hash {
+            return Err(PSError::ParamsMismatch);
+        }
+        Ok(())
+    }
 }
 
-// Parameters generated by random oracle.
+// Parameters generated by random oracle. `label_hash` identifies the label the generators were generated from,
+// verkeys carry the one of their params so that using a key with other params fails with `PSError::ParamsMismatch`
+// rather than as an invalid signature or proof. The generators are hashed from the label hash, so deserialization
+// checks that they are those of the label hash.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "ParamsFields")]
 pub struct Params {
     pub g: SignatureGroup,
     pub g_tilde: VerkeyGroup,
+    pub label_hash: FieldElement,
+}
+
+/// Fields of `Params` as deserialized, before checking that the generators are those of the label hash
+#[derive(Deserialize)]
+pub(crate) struct ParamsFields {
+    pub(crate) g: SignatureGroup,
+    pub(crate) g_tilde: VerkeyGroup,
+    pub(crate) label_hash: FieldElement,
+}
+
+impl TryFrom<ParamsFields> for Params {
+    type Error = PSError;
+
+    fn try_from(f: ParamsFields) -> Result<Self, PSError> {
+        let params = Self::from_label_hash(f.label_hash);
+        if params.g != f.g || params.g_tilde != f.g_tilde {
+            return Err(PSError::DeserializationError {
+                msg: String::from("Generators of params are not those of their label hash"),
+            });
+        }
+        Ok(params)
+    }
 }
 
 impl Params {
     /// Generate g1, g2. These are shared by signer and all users.
     pub fn new(label: &[u8]) -> Self {
-        let g = SignatureGroup::from_msg_hash(&[label, " : g".as_bytes()].concat());
-        let g_tilde = VerkeyGroup::from_msg_hash(&[label, " : g_tilde".as_bytes()].concat());
-        Self { g, g_tilde }
-    }
-}
-
-/// Generate signing and verification keys for scheme from 2016 paper
-pub fn keygen(count_messages: usize, params: &Params) -> (Sigkey, Verkey) {
-    // TODO: Take PRNG as argument
-    let x = FieldElement::random();
-    let X_tilde = &params.g_tilde * &x;
-    let mut y = vec![];
-    let mut Y_tilde = vec![];
-    for _ in 0..count_messages {
-        let y_i = FieldElement::random();
-        Y_tilde.push(&params.g_tilde * &y_i);
-        y.push(y_i);
-    }
-    (Sigkey { x, y }, Verkey { X_tilde, Y_tilde })
-}
-
-pub fn rsskeygen(count_messages: usize, params: &Params) -> (SKrss, PKrss) {
-    let x = FieldElement::random(); // sample x
-    let y = FieldElement::random(); // sample y
-    let g: G2 = params.g.scalar_mul_variable_time(&FieldElement::one());
-    let g_tilde: G1= params.g_tilde.scalar_mul_variable_time(&FieldElement::one());
-    let X_tilde = params.g_tilde.scalar_mul_variable_time(&x); // Need exponent, not mul
-    let mut Y_tilde_i:Vec<VerkeyGroup> = vec![];
-    let mut i_exponent = FieldElement::one(); // start of exponent
-    for _ in 0..count_messages{
-        let y_i=FieldElement::pow(&y,&i_exponent); // Calculate y^i 
-        let g_tilde_y_i = params.g_tilde.scalar_mul_variable_time(&y_i); // Calculate g_tilde^y^i
-        Y_tilde_i.push(g_tilde_y_i); // Add g_tilde^y^i to Y_tilde_i
-        let one = FieldElement::one(); // create counter to increment 
-        let i_exponent = FieldElement::add_assign_(&mut i_exponent, &one); //increment i by 1
-    }
-    let mut  Y_j_1_to_n:Vec<SignatureGroup> = vec![];
-    let mut j_exponent = FieldElement::one(); 
-    for _ in 0..count_messages{
-        let y_i=FieldElement::pow(&y,&j_exponent); // Calculate y^i 
-        let g_y_i = params.g.scalar_mul_variable_time(&y_i); // Calculate g_tilde^y^i
-        Y_j_1_to_n.push(g_y_i); // Add g_tilde^y^i to Y_tilde_i
-        let one = FieldElement::one(); // create counter to increment 
-        let j_exponent = FieldElement::add_assign_(&mut j_exponent, &one); //increment i by 1
-    }
-    let mut  Y_k_nplus2_to_2n:Vec<SignatureGroup> = vec![];
-    let mut k_exponent = FieldElement::one(); 
-    for _ in (count_messages+2)..(2*count_messages) {
-        let y_i=FieldElement::pow(&y,&k_exponent); // Calculate y^i
-        let g_y_i = params.g.scalar_mul_variable_time(&y_i);
-        let y_i = FieldElement::random();
-        Y_k_nplus2_to_2n.push(g_y_i);
-        let one = FieldElement::one(); // create counter to increment 
-        let k_exponent = FieldElement::add_assign_(&mut k_exponent, &one);
-    }
-   (SKrss {x , y}, PKrss {g , g_tilde , Y_j_1_to_n , Y_k_nplus2_to_2n , X_tilde , Y_tilde_i})
+        Self::from_label_hash(FieldElement::from_msg_hash(&[label, " : label".as_bytes()].concat()))
+    }
+
+    /// Params whose generators are hashed from `label_hash`, the same as `new` for the label of `label_hash`
+    pub fn from_label_hash(label_hash: FieldElement) -> Self {
+        let hash = label_hash.to_bytes();
+        let g = SignatureGroup::from_msg_hash(&[hash.as_slice(), " : g".as_bytes()].concat());
+        let g_tilde = VerkeyGroup::from_msg_hash(&[hash.as_slice(), " : g_tilde".as_bytes()].concat());
+        Self { g, g_tilde, label_hash }
+    }
+
+    /// Params of the well-known parameter set `name` of `params_registry::WELL_KNOWN`, like
+    /// `params_registry::RSS_BLS12381_V1`. Fails with `PSError::UnknownParams` for an unknown name.
+    pub fn well_known(name: &str) -> Result<Self, PSError> {
+        params_registry::well_known_label(name).map(Self::new).ok_or_else(|| PSError::UnknownParams { name: name.to_string() })
+    }
+
+    /// g, the same for every call
+    pub fn generator(&self) -> SignatureGroup {
+        self.g.clone()
+    }
+
+    /// g_tilde, the same for every call
+    pub fn generator_tilde(&self) -> VerkeyGroup {
+        self.g_tilde.clone()
+    }
+
+    pub fn generators(&self) -> Generators {
+        Generators { g: self.generator(), g_tilde: self.generator_tilde() }
+    }
+}
+
+/// Generators g of `SignatureGroup` and g_tilde of `VerkeyGroup`, of `Params` or of a key of the redactable
+/// signature scheme, see `RSSPublicKey::generators`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Generators {
+    pub g: SignatureGroup,
+    pub g_tilde: VerkeyGroup,
+}
+
+/// Generate signing and verification keys for scheme from 2016 paper. Fails if `count_messages` is not in
+/// `MIN_MESSAGES..=MAX_MESSAGES`.
+pub fn keygen(count_messages: usize, params: &Params) -> Result<(Sigkey, Verkey), PSError> {
+    keygen_with_checks(count_messages, params, KeyQualityChecks::default())
+}
+
+/// Same as `keygen` but with the key quality checks enabled or not
+pub fn keygen_with_checks(count_messages: usize, params: &Params, checks: KeyQualityChecks) -> Result<(Sigkey, Verkey), PSError> {
+    KeyGenBuilder::new(count_messages).with_policy(checks).generate(params)?.into_ps()
+}
+
+/// Scheme to generate keys for with `KeyGenBuilder`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scheme {
+    /// Scheme from 2016 paper, as `keygen`
+    #[default]
+    PS2016,
+    /// Scheme from 2018 paper with 1 extra element for m', as `keygen_2018`
+    PS2018,
+    /// Redactable signature scheme, as `rsskeygen`
+    Rss,
+}
+
+/// Keys returned by `KeyGenBuilder::generate`, depending on the scheme
+// Moved out right after generation so the size of the variants does not matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum GeneratedKeys {
+    PS(Sigkey, Verkey),
+    Rss(SKrss, PKrss),
+}
+
+impl GeneratedKeys {
+    /// Keys of `Scheme::PS2016` or `Scheme::PS2018`
+    pub fn into_ps(self) -> Result<(Sigkey, Verkey), PSError> {
+        match self {
+            GeneratedKeys::PS(sk, vk) => Ok((sk, vk)),
+            GeneratedKeys::Rss(..) => Err(PSError::GeneralError { msg: String::from("Keys are of the redactable signature scheme") }),
+        }
+    }
+
+    /// Keys of `Scheme::Rss`
+    pub fn into_rss(self) -> Result<(SKrss, PKrss), PSError> {
+        match self {
+            GeneratedKeys::Rss(sk, pk) => Ok((sk, pk)),
+            GeneratedKeys::PS(..) => Err(PSError::GeneralError { msg: String::from("Keys are not of the redactable signature scheme") }),
+        }
+    }
+}
+
+/// Flag to stop a key generation from another thread, see `KeyGenBuilder::with_cancellation`. Clones share the flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Callback of `KeyGenBuilder::with_progress`, called with the count of key elements computed and their total
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(usize, usize) + Send + Sync>);
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Progress and cancellation of the computation of the group elements of a key, one scalar multiplication each
+#[derive(Clone, Debug, Default)]
+struct KeyGenMonitor {
+    progress: Option<ProgressCallback>,
+    cancellation: Option<CancellationToken>,
+    done: usize,
+    total: usize,
+}
+
+impl KeyGenMonitor {
+    /// Compute an element with `f` unless cancelled, then report progress
+    fn element<T, F: FnOnce() -> T>(&mut self, f: F) -> Result<T, PSError> {
+        if self.cancellation.as_ref().map(|c| c.is_cancelled()).unwrap_or(false) {
+            return Err(PSError::KeyGenCancelled { done: self.done, total: self.total });
+        }
+        let elem = f();
+        self.done += 1;
+        if let Some(ProgressCallback(progress)) = &self.progress {
+            progress(self.done, self.total);
+        }
+        Ok(elem)
+    }
+}
+
+/// Key generation with options, which `keygen`, `keygen_2018` and `rsskeygen` and their `_with_checks` variants
+/// use with the defaults: the scheme from 2016 paper, the thread local random number generator and checks of the
+/// key quality enabled.
+#[derive(Clone, Debug)]
+pub struct KeyGenBuilder<R = ThreadRng> {
+    count_messages: usize,
+    rng: R,
+    scheme: Scheme,
+    policy: KeyQualityChecks,
+    monitor: KeyGenMonitor,
+}
+
+impl KeyGenBuilder {
+    pub fn new(count_messages: usize) -> Self {
+        Self { count_messages, rng: thread_rng(), scheme: Scheme::default(), policy: KeyQualityChecks::default(), monitor: KeyGenMonitor::default() }
+    }
+}
+
+impl<R: RngCore + CryptoRng> KeyGenBuilder<R> {
+    /// Generate the secrets with `rng` rather than the thread local random number generator
+    pub fn with_rng<S: RngCore + CryptoRng>(self, rng: S) -> KeyGenBuilder<S> {
+        KeyGenBuilder { count_messages: self.count_messages, rng, scheme: self.scheme, policy: self.policy, monitor: self.monitor }
+    }
+
+    /// Call `progress` with the count of key elements computed and their total after each element: n + 1 for the
+    /// verkey of `Scheme::PS2016`, n + 2 for `Scheme::PS2018` and 3n for the public key of `Scheme::Rss`
+    pub fn with_progress<F: Fn(usize, usize) + Send + Sync + 'static>(mut self, progress: F) -> Self {
+        self.monitor.progress = Some(ProgressCallback(Arc::new(progress)));
+        self
+    }
+
+    /// Check `token` before each key element, failing with `PSError::KeyGenCancelled` once it is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.monitor.cancellation = Some(token);
+        self
+    }
+
+    pub fn with_scheme(mut self, scheme: Scheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    /// Whether the generated keys are checked with `Sigkey::check_quality` or `SKrss::check_quality`
+    pub fn with_policy(mut self, policy: KeyQualityChecks) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Fails if `count_messages` is not in `MIN_MESSAGES..=MAX_MESSAGES`, and for `Scheme::PS2018` if
+    /// `count_messages + 1` is not either.
+    pub fn generate(mut self, params: &Params) -> Result<GeneratedKeys, PSError> {
+        check_message_count(self.count_messages)?;
+        match self.scheme {
+            Scheme::PS2016 => self.generate_ps(self.count_messages, params),
+            Scheme::PS2018 => {
+                check_message_count(self.count_messages + 1)?;
+                self.generate_ps(self.count_messages + 1, params)
+            }
+            Scheme::Rss => {
+                let x = FieldElement::random_using_rng(&mut self.rng); // sample x
+                let y = FieldElement::random_using_rng(&mut self.rng); // sample y
+                let sk = SKrss { n: self.count_messages, x, y };
+                if self.policy == KeyQualityChecks::Enabled {
+                    sk.check_quality()?;
+                }
+                let pk = sk.to_pkrss_monitored(self.count_messages, params, &mut self.monitor)?;
+                Ok(GeneratedKeys::Rss(sk, pk))
+            }
+        }
+    }
+
+    fn generate_ps(&mut self, count_messages: usize, params: &Params) -> Result<GeneratedKeys, PSError> {
+        let x = FieldElement::random_using_rng(&mut self.rng);
+        let y = (0..count_messages).map(|_| FieldElement::random_using_rng(&mut self.rng)).collect();
+        let sk = Sigkey { x, y };
+        if self.policy == KeyQualityChecks::Enabled {
+            sk.check_quality()?;
+        }
+        let vk = sk.to_verkey_monitored(params, &mut self.monitor)?;
+        Ok(GeneratedKeys::PS(sk, vk))
+    }
+}
+
+impl Sigkey {
+    /// Verification key of this signing key, the same as the one returned by `keygen` along with it
+    pub fn to_verkey(&self, params: &Params) -> Verkey {
+        self.to_verkey_monitored(params, &mut KeyGenMonitor::default()).expect("Key generation without cancellation")
+    }
+
+    fn to_verkey_monitored(&self, params: &Params, monitor: &mut KeyGenMonitor) -> Result<Verkey, PSError> {
+        monitor.total = self.y.len() + 1;
+        let X_tilde = monitor.element(|| &params.g_tilde * &self.x)?;
+        let Y_tilde = self.y.iter().map(|y_i| monitor.element(|| &params.g_tilde * y_i)).collect::<Result<_, _>>()?;
+        Ok(Verkey { X_tilde, Y_tilde, params_label_hash: params.label_hash.clone() })
+    }
+
+    /// Signing key for the first `count_messages` messages. A signature with it is also one with the whole key
+    /// on the same messages followed by messages 0.
+    pub fn prefix(&self, count_messages: usize) -> Result<Sigkey, PSError> {
+        check_prefix(count_messages, self.y.len())?;
+        Ok(Sigkey { x: self.x.clone(), y: self.y[..count_messages].to_vec() })
+    }
+
+    /// Encoding as count of messages n as 8 bytes big endian followed by x || y_1..y_n
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.y.len() as u64).to_be_bytes().to_vec();
+        bytes.append(&mut self.x.to_bytes());
+        for y_i in &self.y {
+            bytes.append(&mut y_i.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_bytes_with_limits(bytes, &DeserializeLimits::default())
+    }
+
+    /// Signing key of `bytes`, from `to_bytes`, checked to be the one of `expected_vk`, e.g. the published verkey
+    /// of an issuer. Recomputes the verkey and fails with `PSError::ParamsMismatch` if `expected_vk` is not of
+    /// `params` and with `PSError::SigkeyMismatch` naming the first element that differs.
+    pub fn import_checked(bytes: &[u8], expected_vk: &Verkey, params: &Params) -> Result<Self, PSError> {
+        expected_vk.check_params(params)?;
+        let sk = Self::from_bytes(bytes)?;
+        if sk.y.len() != expected_vk.Y_tilde.len() {
+            return Err(PSError::SigkeyMismatch { element: String::from("count of messages") });
+        }
+        if &params.g_tilde * &sk.x != expected_vk.X_tilde {
+            return Err(PSError::SigkeyMismatch { element: String::from("X_tilde") });
+        }
+        for (i, (y_i, Y_i)) in sk.y.iter().zip(&expected_vk.Y_tilde).enumerate() {
+            if &params.g_tilde * y_i != *Y_i {
+                return Err(PSError::SigkeyMismatch { element: format!("Y_tilde_{}", i + 1) });
+            }
+        }
+        Ok(sk)
+    }
+
+    pub fn from_bytes_with_limits(bytes: &[u8], limits: &DeserializeLimits) -> Result<Self, PSError> {
+        let n = limits.msg_count_from_header(bytes)?;
+        if bytes.len() != PKRSS_HEADER_SIZE + (n + 1) * FieldElement_SIZE {
+            return Err(PSError::DeserializationError {
+                msg: format!("Incorrect number of bytes {} for signing key of {} messages", bytes.len(), n),
+            });
+        }
+        let mut elems = bytes[PKRSS_HEADER_SIZE..].chunks(FieldElement_SIZE).map(|b| {
+            FieldElement::from_bytes(b).map_err(|e| PSError::DeserializationError {
+                msg: format!("{}", e),
+            })
+        });
+        let x = elems.next().unwrap()?;
+        let y = elems.collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { x, y })
+    }
 }
 
+impl Verkey {
+    /// Encoding as count of messages n as 8 bytes big endian followed by params_label_hash || X_tilde || Y_tilde_1..Y_tilde_n
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.Y_tilde.len() as u64).to_be_bytes().to_vec();
+        bytes.append(&mut self.params_label_hash.to_bytes());
+        bytes.append(&mut self.X_tilde.to_bytes());
+        for Y_i in &self.Y_tilde {
+            bytes.append(&mut Y_i.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_bytes_with_limits(bytes, &DeserializeLimits::default())
+    }
+
+    pub fn from_bytes_with_limits(bytes: &[u8], limits: &DeserializeLimits) -> Result<Self, PSError> {
+        let n = limits.msg_count_from_header(bytes)?;
+        if bytes.len() != Self::serialized_size(n) {
+            return Err(PSError::DeserializationError {
+                msg: format!("Incorrect number of bytes {} for verkey of {} messages", bytes.len(), n),
+            });
+        }
+        let (hash_bytes, elem_bytes) = bytes[PKRSS_HEADER_SIZE..].split_at(FieldElement_SIZE);
+        let params_label_hash = label_hash_from_bytes(hash_bytes)?;
+        let mut elems = elem_bytes.chunks(VERKEY_GROUP_SIZE).map(group_elem_from_bytes::<VerkeyGroup>);
+        let X_tilde = elems.next().unwrap()?;
+        let Y_tilde = elems.collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { X_tilde, Y_tilde, params_label_hash })
+    }
+
+    pub(crate) fn serialized_size(n: usize) -> usize {
+        PKRSS_HEADER_SIZE + FieldElement_SIZE + (n + 1) * VERKEY_GROUP_SIZE
+    }
+
+    /// Verkey for the first `count_messages` messages, that of `Sigkey::prefix` of the signing key
+    pub fn prefix(&self, count_messages: usize) -> Result<Verkey, PSError> {
+        check_prefix(count_messages, self.Y_tilde.len())?;
+        Ok(Verkey { X_tilde: self.X_tilde.clone(), Y_tilde: self.Y_tilde[..count_messages].to_vec(), params_label_hash: self.params_label_hash.clone() })
+    }
+
+    /// Fails with `PSError::ParamsMismatch` if the key was not generated with `params`
+    pub fn check_params(&self, params: &Params) -> Result<(), PSError> {
+        if self.params_label_hash != params.label_hash {
+            return Err(PSError::ParamsMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Generate signing and verification keys for the redactable signature scheme. Fails if `count_messages` is not
+/// in `MIN_MESSAGES..=MAX_MESSAGES`.
+pub fn rsskeygen(count_messages: usize, params: &Params) -> Result<(SKrss, PKrss), PSError> {
+    rsskeygen_with_checks(count_messages, params, KeyQualityChecks::default())
+}
+
+/// Same as `rsskeygen` but with the key quality checks enabled or not
+pub fn rsskeygen_with_checks(count_messages: usize, params: &Params, checks: KeyQualityChecks) -> Result<(SKrss, PKrss), PSError> {
+    KeyGenBuilder::new(count_messages).with_scheme(Scheme::Rss).with_policy(checks).generate(params)?.into_rss()
+}
+
+impl SKrss {
+    /// Public key of this secret key, the same as the one returned by `rsskeygen` along with it. Fails with
+    /// `PSError::UnsupportedNoOfMessages` unless `count_messages` is `self.n`: a key for more messages has
+    /// g^{y^{n+1}}, which lets anyone change the disclosed messages of signatures under the key for n messages, so a
+    /// secret key backs the public key of its one count of messages only.
+    pub fn to_pkrss(&self, count_messages: usize, params: &Params) -> Result<PKrss, PSError> {
+        self.to_pkrss_monitored(count_messages, params, &mut KeyGenMonitor::default())
+    }
+
+    fn to_pkrss_monitored(&self, count_messages: usize, params: &Params, monitor: &mut KeyGenMonitor) -> Result<PKrss, PSError> {
+        check_message_count(count_messages)?;
+        check_sk_msg_count(self, count_messages)?;
+        let Generators { g, g_tilde } = params.generators();
+        monitor.total = 3 * count_messages;
+        let X_tilde = monitor.element(|| params.g_tilde.scalar_mul_variable_time(&self.x))?; // Need exponent, not mul
+        // y^1..y^{2n}, y^i is at index i-1
+        let y_powers = powers(&self.y, 2 * count_messages);
+        let Y_tilde_i:Vec<VerkeyGroup> = y_powers[..count_messages].iter().map(|y_i| monitor.element(|| params.g_tilde.scalar_mul_variable_time(y_i))).collect::<Result<_, _>>()?;
+        let Y_j_1_to_n:Vec<SignatureGroup> = y_powers[..count_messages].iter().map(|y_i| monitor.element(|| params.g.scalar_mul_variable_time(y_i))).collect::<Result<_, _>>()?;
+        // Skip y^{n+1}
+        let Y_k_nplus2_to_2n:Vec<SignatureGroup> = y_powers.iter().skip(count_messages + 1).map(|y_i| monitor.element(|| params.g.scalar_mul_variable_time(y_i))).collect::<Result<_, _>>()?;
+        Ok(PKrss {n: count_messages, g , g_tilde , Y_j_1_to_n , Y_k_nplus2_to_2n , X_tilde , Y_tilde_i, params_label_hash: params.label_hash.clone()})
+    }
+}
+
+/// Fails with `PSError::UnsupportedNoOfMessages` unless `count_messages` is the one of `sk`
+pub(crate) fn check_sk_msg_count(sk: &SKrss, count_messages: usize) -> Result<(), PSError> {
+    if count_messages != sk.n {
+        return Err(PSError::UnsupportedNoOfMessages { expected: sk.n, given: count_messages });
+    }
+    Ok(())
+}
+
+/// `i` as a field element, e.g. the exponent i of y^i. The only conversion of an index to a field element, fails
+/// with `PSError::InvalidIndex` if `i` does not fit in a u64.
+pub fn index_exponent(i: usize) -> Result<FieldElement, PSError> {
+    u64::try_from(i).map(scalar_from_u64).map_err(|_| PSError::InvalidIndex { idx: i, valid: String::from("[0, u64::MAX]") })
+}
+
+/// y^1, y^2, ..., y^count, each power computed from the previous one by a multiplication
+pub(crate) fn powers(y: &FieldElement, count: usize) -> Vec<FieldElement> {
+    let mut powers = Vec::with_capacity(count);
+    let mut y_i = y.clone();
+    for _ in 0..count {
+        let next = &y_i * y;
+        powers.push(y_i);
+        y_i = next;
+    }
+    powers
+}
 
 /// Generate signing and verification keys for scheme from 2018 paper. The signing and verification
-/// keys will have 1 extra element for m'
-pub fn keygen_2018(count_messages: usize, params: &Params) -> (Sigkey, Verkey) {
-    keygen(count_messages + 1, params)
+/// keys will have 1 extra element for m' so `count_messages` can be at most `MAX_MESSAGES - 1`
+pub fn keygen_2018(count_messages: usize, params: &Params) -> Result<(Sigkey, Verkey), PSError> {
+    KeyGenBuilder::new(count_messages).with_scheme(Scheme::PS2018).generate(params)?.into_ps()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    // For benchmarking
-    use std::time::{Duration, Instant};
+    use std::time::Instant;
 
     #[test]
     fn test_keygen() {
         let count_msgs = 5;
         let params = Params::new("test".as_bytes());
-        let (sk, pk) = rsskeygen(count_msgs, &params);
-        println!("{:?}",pk)
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        assert_eq!(sk.y.len(), count_msgs);
+        assert_eq!(vk.Y_tilde.len(), count_msgs);
     }
 
     #[test]
     fn test_rsskeygen() {
         let count_msgs = 5;
         let params = Params::new("test".as_bytes());
-        let (sk, vk) = keygen(count_msgs, &params);
-        println!("{:?}",params)
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        assert_eq!(pk.Y_j_1_to_n.len(), count_msgs);
+        assert_eq!(pk.Y_k_nplus2_to_2n.len(), count_msgs - 1);
+        assert_eq!(pk.Y_tilde_i.len(), count_msgs);
+        assert_eq!(Generators { g: pk.g.clone(), g_tilde: pk.g_tilde.clone() }, params.generators());
+        // Y_k_nplus2_to_2n starts at g^{y^{n+2}}
+        let y_nplus2 = sk.y.pow(&index_exponent(count_msgs + 2).unwrap());
+        assert_eq!(pk.Y_k_nplus2_to_2n[0], &params.g * &y_nplus2);
+        let y_2n = sk.y.pow(&index_exponent(2 * count_msgs).unwrap());
+        assert_eq!(pk.Y_k_nplus2_to_2n[count_msgs - 2], &params.g * &y_2n);
     }
 
     #[test]
     fn test_keygen_2018() {
         let count_msgs = 5;
         let params = Params::new("test".as_bytes());
-        let (sk, vk) = keygen_2018(count_msgs, &params);
+        let (sk, vk) = keygen_2018(count_msgs, &params).unwrap();
         assert_eq!(sk.y.len(), count_msgs+1);
         assert_eq!(vk.Y_tilde.len(), count_msgs+1);
     }
+
+    #[test]
+    fn test_keygen_builder() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        let params = Params::new("test".as_bytes());
+        for (scheme, count_y) in [(Scheme::PS2016, 5), (Scheme::PS2018, 6)] {
+            let (sk, vk) = KeyGenBuilder::new(5).with_scheme(scheme).generate(&params).unwrap().into_ps().unwrap();
+            assert_eq!(sk.y.len(), count_y);
+            assert_eq!(vk.to_bytes(), sk.to_verkey(&params).to_bytes());
+        }
+        let (sk, pk) = KeyGenBuilder::new(5).with_scheme(Scheme::Rss).with_policy(KeyQualityChecks::Disabled).generate(&params).unwrap().into_rss().unwrap();
+        assert_eq!(pk.to_bytes(), sk.to_pkrss(5, &params).unwrap().to_bytes());
+        assert!(KeyGenBuilder::new(5).generate(&params).unwrap().into_rss().is_err());
+
+        // Same keys from the same seed
+        let gen = |seed: u64| KeyGenBuilder::new(3).with_rng(StdRng::seed_from_u64(seed)).generate(&params).unwrap().into_ps().unwrap().0;
+        assert_eq!(gen(1).to_bytes(), gen(1).to_bytes());
+        assert_ne!(gen(1).to_bytes(), gen(2).to_bytes());
+        let mut rng = StdRng::seed_from_u64(3);
+        let (sk_1, _) = KeyGenBuilder::new(3).with_rng(&mut rng).with_scheme(Scheme::Rss).generate(&params).unwrap().into_rss().unwrap();
+        let (sk_2, _) = KeyGenBuilder::new(3).with_rng(&mut rng).with_scheme(Scheme::Rss).generate(&params).unwrap().into_rss().unwrap();
+        assert_ne!(sk_1.x, sk_2.x);
+
+        assert!(KeyGenBuilder::new(0).generate(&params).is_err());
+        assert!(KeyGenBuilder::new(MAX_MESSAGES).with_scheme(Scheme::PS2018).generate(&params).is_err());
+    }
+
+    #[test]
+    fn test_keygen_progress_and_cancellation() {
+        use std::sync::Mutex;
+        let params = Params::new("test".as_bytes());
+        let reports = Arc::new(Mutex::new(vec![]));
+        let r = reports.clone();
+        KeyGenBuilder::new(10).with_scheme(Scheme::Rss).with_progress(move |done, total| r.lock().unwrap().push((done, total))).generate(&params).unwrap();
+        let expected: Vec<(usize, usize)> = (1..=30).map(|done| (done, 30)).collect();
+        assert_eq!(*reports.lock().unwrap(), expected);
+
+        let reports = Arc::new(Mutex::new(vec![]));
+        let r = reports.clone();
+        KeyGenBuilder::new(10).with_scheme(Scheme::PS2018).with_progress(move |done, total| r.lock().unwrap().push((done, total))).generate(&params).unwrap();
+        assert_eq!(reports.lock().unwrap().last(), Some(&(12, 12)));
+
+        // Cancelled from the callback after 7 elements
+        let token = CancellationToken::new();
+        let t = token.clone();
+        let result = KeyGenBuilder::new(10).with_scheme(Scheme::Rss).with_cancellation(token.clone()).with_progress(move |done, _| if done == 7 { t.cancel() }).generate(&params);
+        match result {
+            Err(PSError::KeyGenCancelled { done: 7, total: 30 }) => (),
+            r => panic!("Expected KeyGenCancelled but got {:?}", r),
+        }
+        assert!(token.is_cancelled());
+        assert!(KeyGenBuilder::new(5).with_cancellation(token).generate(&params).is_err());
+    }
+
+    #[test]
+    fn test_message_count_bounds() {
+        let params = Params::new("test".as_bytes());
+        assert!(check_message_count(MIN_MESSAGES).is_ok());
+        assert!(check_message_count(MAX_MESSAGES).is_ok());
+        assert!(keygen(MIN_MESSAGES, &params).is_ok());
+        assert!(rsskeygen(MIN_MESSAGES, &params).is_ok());
+        assert!(keygen_2018(MIN_MESSAGES, &params).is_ok());
+
+        // Rejected before allocating anything
+        for &count_msgs in &[MIN_MESSAGES - 1, MAX_MESSAGES + 1, usize::MAX] {
+            match keygen(count_msgs, &params) {
+                Err(PSError::UnsupportedMessageCount { given, min, max }) => {
+                    assert_eq!(given, count_msgs);
+                    assert_eq!(min, MIN_MESSAGES);
+                    assert_eq!(max, MAX_MESSAGES);
+                }
+                r => panic!("Expected UnsupportedMessageCount but got {:?}", r.map(|_| ())),
+            }
+            assert!(rsskeygen(count_msgs, &params).is_err());
+            assert!(keygen_2018(count_msgs, &params).is_err());
+            let (sk, _) = rsskeygen(2, &params).unwrap();
+            assert!(sk.to_pkrss(count_msgs, &params).is_err());
+        }
+        // m' takes one more message
+        assert!(keygen_2018(MAX_MESSAGES, &params).is_err());
+    }
+
+    #[test]
+    fn test_key_quality_checks() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen_with_checks(4, &params, KeyQualityChecks::Enabled).unwrap();
+        assert!(sk.check_quality().is_ok());
+        assert!(vk.sanity_check().is_ok());
+        assert!(keygen_with_checks(4, &params, KeyQualityChecks::Disabled).is_ok());
+
+        let mut zero_x = sk.clone();
+        zero_x.x = FieldElement::zero();
+        let mut zero_y = sk.clone();
+        zero_y.y[2] = FieldElement::zero();
+        let mut duplicate_y = sk.clone();
+        duplicate_y.y[3] = duplicate_y.y[1].clone();
+        for weak in &[zero_x, zero_y, duplicate_y] {
+            match weak.check_quality() {
+                Err(PSError::WeakKey { .. }) => (),
+                r => panic!("Expected WeakKey but got {:?}", r),
+            }
+            match weak.to_verkey(&params).sanity_check() {
+                Err(PSError::WeakKey { .. }) => (),
+                r => panic!("Expected WeakKey but got {:?}", r),
+            }
+        }
+
+        let (sk, _) = rsskeygen_with_checks(4, &params, KeyQualityChecks::Enabled).unwrap();
+        assert!(sk.check_quality().is_ok());
+        let mut rss_weak = vec![];
+        for y in &[FieldElement::zero(), FieldElement::one(), FieldElement::minus_one()] {
+            let mut weak = sk.clone();
+            weak.y = y.clone();
+            rss_weak.push(weak);
+        }
+        let mut zero_x = sk;
+        zero_x.x = FieldElement::zero();
+        rss_weak.push(zero_x);
+        for weak in &rss_weak {
+            match weak.check_quality() {
+                Err(PSError::WeakKey { .. }) => (),
+                r => panic!("Expected WeakKey but got {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_verkey() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let vk_1 = sk.to_verkey(&params);
+        assert_eq!(vk_1.X_tilde, vk.X_tilde);
+        assert_eq!(vk_1.Y_tilde, vk.Y_tilde);
+
+        // Verkey of another signing key differs
+        let (sk_2, _) = keygen(count_msgs, &params).unwrap();
+        assert_ne!(sk_2.to_verkey(&params).X_tilde, vk.X_tilde);
+    }
+
+    #[test]
+    fn test_sigkey_verkey_bytes() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params).unwrap();
+        let sk_bytes = sk.to_bytes();
+        let sk_1 = Sigkey::from_bytes(&sk_bytes).unwrap();
+        assert_eq!(sk_1.x, sk.x);
+        assert_eq!(sk_1.y, sk.y);
+        assert_eq!(Verkey::from_bytes(&vk.to_bytes()).unwrap(), vk);
+
+        assert!(Sigkey::from_bytes(&sk_bytes[..sk_bytes.len() - 1]).is_err());
+        assert!(Verkey::from_bytes(&vk.to_bytes()[1..]).is_err());
+        assert!(Verkey::from_bytes(&sk_bytes).is_err());
+
+        // The label hash goes with the verkey
+        let vk_1 = Verkey::from_bytes(&vk.to_bytes()).unwrap();
+        assert!(vk_1.check_params(&params).is_ok());
+        assert_eq!(Params::new("test".as_bytes()).label_hash, params.label_hash);
+        match vk_1.check_params(&Params::new("other".as_bytes())) {
+            Err(PSError::ParamsMismatch) => (),
+            r => panic!("Expected ParamsMismatch but got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_sigkey_import_checked() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let sk_1 = Sigkey::import_checked(&sk.to_bytes(), &vk, &params).unwrap();
+        assert_eq!(sk_1.y, sk.y);
+
+        let (other_sk, other_vk) = keygen(3, &params).unwrap();
+        match Sigkey::import_checked(&other_sk.to_bytes(), &vk, &params) {
+            Err(PSError::SigkeyMismatch { element }) => assert_eq!(element, "X_tilde"),
+            r => panic!("Expected SigkeyMismatch but got {:?}", r),
+        }
+        let mut wrong_y = sk.clone();
+        wrong_y.y[1] = other_sk.y[1].clone();
+        match Sigkey::import_checked(&wrong_y.to_bytes(), &vk, &params) {
+            Err(PSError::SigkeyMismatch { element }) => assert_eq!(element, "Y_tilde_2"),
+            r => panic!("Expected SigkeyMismatch but got {:?}", r),
+        }
+        assert!(Sigkey::import_checked(&sk.prefix(2).unwrap().to_bytes(), &vk, &params).is_err());
+        assert!(Sigkey::import_checked(&sk.to_bytes(), &other_vk, &params).is_err());
+        match Sigkey::import_checked(&sk.to_bytes(), &vk, &Params::new("other".as_bytes())) {
+            Err(PSError::ParamsMismatch) => (),
+            r => panic!("Expected ParamsMismatch but got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_to_pkrss() {
+        let params = Params::new("test".as_bytes());
+        for count_msgs in 1..5 {
+            let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+            assert_eq!(sk.to_pkrss(count_msgs, &params).unwrap().to_bytes(), pk.to_bytes());
+        }
+    }
+
+    #[test]
+    fn test_to_pkrss_other_count() {
+        // A key for more messages would have g^{y^{n+1}}
+        let params = Params::new("test".as_bytes());
+        let (sk, _) = rsskeygen(3, &params).unwrap();
+        for count_msgs in &[2, 4, 6] {
+            match sk.to_pkrss(*count_msgs, &params) {
+                Err(PSError::UnsupportedNoOfMessages { expected, given }) => {
+                    assert_eq!(expected, 3);
+                    assert_eq!(given, *count_msgs);
+                }
+                r => panic!("Expected UnsupportedNoOfMessages but got {:?}", r.map(|p| p.n)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_supported_messages() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        assert_eq!(sk.supported_messages(), count_msgs);
+        assert_eq!(vk.supported_messages(), count_msgs);
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        assert_eq!(sk.supported_messages(), count_msgs);
+        assert_eq!(pk.supported_messages(), count_msgs);
+
+        let sk_1 = SKrss::from_bytes(&sk.to_bytes()).unwrap();
+        assert_eq!(sk_1.supported_messages(), count_msgs);
+        assert_eq!(sk_1.to_pkrss(sk_1.n, &params).unwrap().to_bytes(), pk.to_bytes());
+        assert!(SKrss::from_bytes(&sk.to_bytes()[1..]).is_err());
+    }
+
+    #[test]
+    fn test_pkrss_deserialize_checks_count() {
+        let params = Params::new("test".as_bytes());
+        let (_, pk) = rsskeygen(3, &params).unwrap();
+        let mut pk_1 = pk.clone();
+        pk_1.n = 4;
+        let mut pk_2 = pk.clone();
+        pk_2.Y_k_nplus2_to_2n.pop();
+        for (p, valid) in [(pk, true), (pk_1, false), (pk_2, false)].iter() {
+            let json = serde_json::to_string(p).unwrap();
+            assert_eq!(serde_json::from_str::<PKrss>(&json).is_ok(), *valid);
+        }
+    }
+
+    #[test]
+    fn test_powers() {
+        let y = FieldElement::random();
+        let p = powers(&y, 6);
+        for (i, y_i) in p.iter().enumerate() {
+            assert_eq!(*y_i, y.pow(&index_exponent(i + 1).unwrap()));
+        }
+        assert!(powers(&y, 0).is_empty());
+        assert_eq!(index_exponent(3).unwrap(), FieldElement::from(3u64));
+        // Canonical beyond the values of an isize
+        let i = (1u64 << 63) + 1;
+        let mut bytes = vec![0u8; FieldElement_SIZE - 8];
+        bytes.extend_from_slice(&i.to_be_bytes());
+        assert_eq!(index_exponent(i as usize).unwrap(), FieldElement::from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn timing_rsskeygen() {
+        // Run with `cargo test --release timing_rsskeygen -- --nocapture`
+        let count_msgs = 256;
+        let params = Params::new("test".as_bytes());
+        let y = FieldElement::random();
+
+        let start = Instant::now();
+        let expected: Vec<_> = (1..=2 * count_msgs)
+            .map(|i| y.pow(&index_exponent(i).unwrap()))
+            .collect();
+        println!("Time to compute {} powers with exponentiation {:?}", 2 * count_msgs, start.elapsed());
+
+        let start = Instant::now();
+        let p = powers(&y, 2 * count_msgs);
+        println!("Time to compute {} powers with multiplication {:?}", 2 * count_msgs, start.elapsed());
+        assert_eq!(p, expected);
+
+        let start = Instant::now();
+        rsskeygen(count_msgs, &params).unwrap();
+        println!("Time to generate keys for {} messages {:?}", count_msgs, start.elapsed());
+    }
+
+    #[test]
+    fn test_pkrss_bytes() {
+        let params = Params::new("test".as_bytes());
+        for count_msgs in 1..5 {
+            let (_, pk) = rsskeygen(count_msgs, &params).unwrap();
+            let bytes = pk.to_bytes();
+            assert_eq!(bytes.len(), PKrss::serialized_size(count_msgs));
+            let pk_1 = PKrss::from_bytes(&bytes).unwrap();
+            assert_eq!(pk_1.to_bytes(), bytes);
+            assert!(PKrss::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_label_hash_not_reduced() {
+        let params = Params::new("test".as_bytes());
+        let (_, vk) = keygen(3, &params).unwrap();
+        let (_, pk) = rsskeygen(3, &params).unwrap();
+        let hash_range = PKRSS_HEADER_SIZE..PKRSS_HEADER_SIZE + FieldElement_SIZE;
+        let mut vk_bytes = vk.to_bytes();
+        vk_bytes[hash_range.clone()].copy_from_slice(&[0xff; FieldElement_SIZE]);
+        assert!(Verkey::from_bytes(&vk_bytes).is_err());
+        let mut pk_bytes = pk.to_bytes();
+        pk_bytes[hash_range].copy_from_slice(&[0xff; FieldElement_SIZE]);
+        assert!(PKrss::from_bytes(&pk_bytes).is_err());
+    }
+
+    #[test]
+    fn test_params_generators_of_label_hash() {
+        let params = Params::new("test".as_bytes());
+        let params_1 = Params::from_label_hash(params.label_hash.clone());
+        assert_eq!(params_1.g, params.g);
+        assert_eq!(params_1.g_tilde, params.g_tilde);
+        let fields = |g: &SignatureGroup, g_tilde: &VerkeyGroup| ParamsFields {
+            g: g.clone(),
+            g_tilde: g_tilde.clone(),
+            label_hash: params.label_hash.clone(),
+        };
+        assert!(Params::try_from(fields(&params.g, &params.g_tilde)).is_ok());
+        // Generators of other params with the label hash of these
+        let other = Params::new("other".as_bytes());
+        assert!(Params::try_from(fields(&other.g, &params.g_tilde)).is_err());
+        assert!(Params::try_from(fields(&params.g, &other.g_tilde)).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_limits() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(5, &params).unwrap();
+        let (_, pk) = rsskeygen(5, &params).unwrap();
+        let limits = DeserializeLimits::new(4, 1 << 16);
+        assert!(Verkey::from_bytes(&vk.to_bytes()).is_ok());
+        assert!(Verkey::from_bytes_with_limits(&vk.to_bytes(), &DeserializeLimits::new(5, 1 << 16)).is_ok());
+        for r in [
+            Verkey::from_bytes_with_limits(&vk.to_bytes(), &limits).map(|_| ()),
+            Sigkey::from_bytes_with_limits(&sk.to_bytes(), &limits).map(|_| ()),
+            PKrss::from_bytes_with_limits(&pk.to_bytes(), &limits).map(|_| ()),
+        ] {
+            match r {
+                Err(PSError::DeserializeLimitExceeded { given: 5, max: 4, .. }) => (),
+                r => panic!("Expected DeserializeLimitExceeded but got {:?}", r),
+            }
+        }
+        match PKrss::from_bytes_with_limits(&pk.to_bytes(), &DeserializeLimits::new(5, 100)) {
+            Err(PSError::DeserializeLimitExceeded { max: 100, .. }) => (),
+            r => panic!("Expected DeserializeLimitExceeded but got {:?}", r.map(|_| ())),
+        }
+
+        // A header claiming 2^32 messages is rejected by the default limits before the length is checked
+        let mut bytes = vk.to_bytes();
+        bytes[..PKRSS_HEADER_SIZE].copy_from_slice(&(1u64 << 32).to_be_bytes());
+        match Verkey::from_bytes(&bytes) {
+            Err(PSError::DeserializeLimitExceeded { given, .. }) => assert_eq!(given as u64, 1 << 32),
+            r => panic!("Expected DeserializeLimitExceeded but got {:?}", r.map(|_| ())),
+        }
+        assert!(Verkey::from_bytes_with_limits(&bytes, &DeserializeLimits::unlimited()).is_err());
+    }
 }
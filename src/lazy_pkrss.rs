@@ -0,0 +1,287 @@
+// Public key of the redactable signature scheme read on demand from its serialized form (`PKrss::to_bytes`).
+// For keys supporting thousands of messages, deserializing (and checking) every group element is slow while
+// deriving or verifying a signature only touches some of them. `LazyPKrss` reads g, g_tilde and X_tilde upfront and
+// each Y/Y_tilde element only when asked for, keeping the most recently used ones in a small cache.
+// The source can be a file, a `Cursor` over a byte slice or anything that can be read and seeked.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{label_hash_from_bytes, PKrss, PKRSS_ELEMENTS_OFFSET, PKRSS_HEADER_SIZE};
+use crate::rss_signature::{check_Y_index, check_Y_tilde_index, MsgIndex, RSSPublicKey};
+use crate::signature::group_elem_from_bytes;
+use crate::{SignatureGroup, VerkeyGroup, SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Seek, SeekFrom};
+
+/// Number of elements of each group kept in the cache by default
+pub const DEFAULT_CACHE_CAPACITY: usize = 128;
+
+pub struct LazyPKrss<R: Read + Seek> {
+    n: usize,
+    g: SignatureGroup,
+    g_tilde: VerkeyGroup,
+    X_tilde: VerkeyGroup,
+    params_label_hash: FieldElement,
+    inner: RefCell<Inner<R>>,
+}
+
+struct Inner<R> {
+    reader: R,
+    Y: LruCache<SignatureGroup>,
+    Y_tilde: LruCache<VerkeyGroup>,
+}
+
+/// Least recently used cache keyed by the index of the element in the paper
+struct LruCache<T> {
+    capacity: usize,
+    elems: HashMap<usize, T>,
+    // Front is the least recently used
+    order: VecDeque<usize>,
+}
+
+impl<T: Clone> LruCache<T> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            elems: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn get(&mut self, i: usize) -> Option<T> {
+        let e = self.elems.get(&i)?.clone();
+        self.touch(i);
+        Some(e)
+    }
+
+    fn insert(&mut self, i: usize, e: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.elems.insert(i, e).is_some() {
+            self.touch(i);
+            return;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.elems.remove(&evicted);
+            }
+        }
+        self.order.push_back(i);
+    }
+
+    fn touch(&mut self, i: usize) {
+        if let Some(pos) = self.order.iter().position(|j| *j == i) {
+            self.order.remove(pos);
+        }
+        self.order.push_back(i);
+    }
+}
+
+impl<R: Read + Seek> LazyPKrss<R> {
+    /// Read the key with a cache of `DEFAULT_CACHE_CAPACITY` elements of each group
+    pub fn new(reader: R) -> Result<Self, PSError> {
+        Self::with_cache_capacity(reader, DEFAULT_CACHE_CAPACITY)
+    }
+
+    pub fn with_cache_capacity(mut reader: R, cache_capacity: usize) -> Result<Self, PSError> {
+        let mut header = [0u8; PKRSS_HEADER_SIZE];
+        read_at(&mut reader, 0, &mut header)?;
+        let n = PKrss::msg_count_from_header(&header)?;
+
+        let mut buf = vec![
+            0u8;
+            PKRSS_ELEMENTS_OFFSET - PKRSS_HEADER_SIZE
+                + SIGNATURE_GROUP_SIZE
+                + 2 * VERKEY_GROUP_SIZE
+        ];
+        read_at(&mut reader, PKRSS_HEADER_SIZE as u64, &mut buf)?;
+        let (params_label_hash, rest) = buf.split_at(PKRSS_ELEMENTS_OFFSET - PKRSS_HEADER_SIZE);
+        let params_label_hash = label_hash_from_bytes(params_label_hash)?;
+        let (g, rest) = rest.split_at(SIGNATURE_GROUP_SIZE);
+        let (g_tilde, X_tilde) = rest.split_at(VERKEY_GROUP_SIZE);
+        let g = group_elem_from_bytes(g)?;
+        let g_tilde = group_elem_from_bytes(g_tilde)?;
+        let X_tilde = group_elem_from_bytes(X_tilde)?;
+
+        // Fail early on a truncated key rather than on some later lookup
+        let end = reader.seek(SeekFrom::End(0)).map_err(io_error)?;
+        if end < PKrss::serialized_size(n) as u64 {
+            return Err(PSError::DeserializationError {
+                msg: format!(
+                    "Expected {} bytes for key of {} messages but found {}",
+                    PKrss::serialized_size(n),
+                    n,
+                    end
+                ),
+            });
+        }
+
+        Ok(Self {
+            n,
+            g,
+            g_tilde,
+            X_tilde,
+            params_label_hash,
+            inner: RefCell::new(Inner {
+                reader,
+                Y: LruCache::new(cache_capacity),
+                Y_tilde: LruCache::new(cache_capacity),
+            }),
+        })
+    }
+
+    /// Byte offset of Y_tilde_i
+    fn Y_tilde_offset(&self, i: usize) -> u64 {
+        (PKRSS_ELEMENTS_OFFSET + SIGNATURE_GROUP_SIZE + (i + 1) * VERKEY_GROUP_SIZE) as u64
+    }
+
+    /// Byte offset of Y_i. Y_{n+1} is not serialized so the indices after it are shifted by one.
+    fn Y_offset(&self, i: usize) -> u64 {
+        let pos = if i <= self.n { i } else { i - 1 };
+        (PKRSS_ELEMENTS_OFFSET + pos * SIGNATURE_GROUP_SIZE + (self.n + 2) * VERKEY_GROUP_SIZE)
+            as u64
+    }
+}
+
+impl<R: Read + Seek> RSSPublicKey for LazyPKrss<R> {
+    fn msg_count(&self) -> usize {
+        self.n
+    }
+
+    fn g(&self) -> SignatureGroup {
+        self.g.clone()
+    }
+
+    fn g_tilde(&self) -> VerkeyGroup {
+        self.g_tilde.clone()
+    }
+
+    fn X_tilde(&self) -> VerkeyGroup {
+        self.X_tilde.clone()
+    }
+
+    fn params_label_hash(&self) -> FieldElement {
+        self.params_label_hash.clone()
+    }
+
+    fn Y_tilde(&self, i: MsgIndex) -> Result<VerkeyGroup, PSError> {
+        check_Y_tilde_index(i, self.n)?;
+        let i = i.get();
+        let mut inner = self.inner.borrow_mut();
+        if let Some(e) = inner.Y_tilde.get(i) {
+            return Ok(e);
+        }
+        let mut buf = [0u8; VERKEY_GROUP_SIZE];
+        read_at(&mut inner.reader, self.Y_tilde_offset(i), &mut buf)?;
+        let e: VerkeyGroup = group_elem_from_bytes(&buf)?;
+        inner.Y_tilde.insert(i, e.clone());
+        Ok(e)
+    }
+
+    fn Y(&self, i: MsgIndex) -> Result<SignatureGroup, PSError> {
+        check_Y_index(i, self.n)?;
+        let i = i.get();
+        let mut inner = self.inner.borrow_mut();
+        if let Some(e) = inner.Y.get(i) {
+            return Ok(e);
+        }
+        let mut buf = [0u8; SIGNATURE_GROUP_SIZE];
+        read_at(&mut inner.reader, self.Y_offset(i), &mut buf)?;
+        let e: SignatureGroup = group_elem_from_bytes(&buf)?;
+        inner.Y.insert(i, e.clone());
+        Ok(e)
+    }
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, buf: &mut [u8]) -> Result<(), PSError> {
+    reader.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+    reader.read_exact(buf).map_err(io_error)
+}
+
+fn io_error(e: std::io::Error) -> PSError {
+    PSError::DeserializationError {
+        msg: format!("{}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{FieldElementVector, FieldElement_SIZE};
+    use crate::keys::{rsskeygen, Params};
+    use crate::rss_signature::RSSignature;
+    use std::collections::HashSet;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_lazy_pkrss_elements() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (_, pk) = rsskeygen(count_msgs, &params).unwrap();
+        // Small cache to exercise eviction
+        let lazy = LazyPKrss::with_cache_capacity(Cursor::new(pk.to_bytes()), 2).unwrap();
+        assert_eq!(lazy.msg_count(), count_msgs);
+        assert_eq!(lazy.g(), pk.g);
+        assert_eq!(lazy.g_tilde(), pk.g_tilde);
+        assert_eq!(lazy.X_tilde(), pk.X_tilde);
+        let idx = |i| MsgIndex::new(i).unwrap();
+        for _ in 0..2 {
+            for i in (1..=count_msgs).map(idx) {
+                assert_eq!(lazy.Y_tilde(i).unwrap(), pk.Y_tilde(i).unwrap());
+            }
+            for i in (1..=count_msgs)
+                .chain(count_msgs + 2..=2 * count_msgs)
+                .map(idx)
+            {
+                assert_eq!(lazy.Y(i).unwrap(), pk.Y(i).unwrap());
+            }
+        }
+        assert!(lazy.Y(idx(count_msgs + 1)).is_err());
+        assert!(lazy.Y_tilde(idx(count_msgs + 1)).is_err());
+
+        let bytes = pk.to_bytes();
+        assert!(LazyPKrss::new(Cursor::new(&bytes[..bytes.len() - 1])).is_err());
+    }
+
+    #[test]
+    fn test_lazy_pkrss_label_hash_not_reduced() {
+        // As `PKrss::from_bytes`, a label hash of the order of the group or more is rejected rather than reduced
+        let params = Params::new("test".as_bytes());
+        let (_, pk) = rsskeygen(3, &params).unwrap();
+        let not_reduced = [0xff; FieldElement_SIZE];
+        assert!(FieldElement::from_bytes(&not_reduced).is_ok());
+        let mut bytes = pk.to_bytes();
+        bytes[PKRSS_HEADER_SIZE..PKRSS_ELEMENTS_OFFSET].copy_from_slice(&not_reduced);
+        match LazyPKrss::new(Cursor::new(bytes)) {
+            Err(PSError::DeserializationError { .. }) => (),
+            r => panic!("Expected DeserializationError but got {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_lazy_pkrss_derive_verify() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 8;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let lazy = LazyPKrss::new(Cursor::new(pk.to_bytes())).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(sig.verify(msgs.as_slice(), &lazy).unwrap());
+
+        let disclosed: HashSet<usize> = vec![0, 5, 7].into_iter().collect();
+        let mut disclosed_msgs = HashMap::new();
+        for i in &disclosed {
+            disclosed_msgs.insert(*i, msgs[*i].clone());
+        }
+        // Derive with one key and verify with the other
+        let derived = sig.derive(msgs.as_slice(), &disclosed, &lazy).unwrap();
+        assert!(derived.verify(&disclosed_msgs, &pk).unwrap());
+        let derived = sig.derive(msgs.as_slice(), &disclosed, &pk).unwrap();
+        assert!(derived.verify(&disclosed_msgs, &lazy).unwrap());
+
+        disclosed_msgs.insert(5, FieldElement::random());
+        assert!(!derived.verify(&disclosed_msgs, &lazy).unwrap());
+    }
+}
@@ -0,0 +1,234 @@
+// Keys serialized with serde by earlier versions of the crate, behind the feature `legacy-keys`. Verkeys of versions
+// before the label hash of their params was added serialized only their group elements, X_tilde and the vector of
+// Y_tilde_i, as a map or as a sequence depending on the serializer; deserializing them as `Verkey` fails for the
+// missing label hash. `key_from_json` reads a key in the JSON of the derived serde encoding of either version,
+// detects the layout of the fields and returns the key with its `KeyLayout`. A legacy verkey gets the label hash of
+// the params given by the caller, as it carries no trace of its params, and is checked with `Verkey::sanity_check`.
+// Public keys of the redactable signature scheme likewise lacked the label hash before it was added and get the one of
+// the params given. Signing keys of both schemes did not change and are read as they are.
+// `migrate_key_file` converts a key file to the canonical format of `wire::WireMessage`, whose encoding does not
+// depend on the fields of the structs.
+
+use crate::errors::PSError;
+use crate::keys::{PKrss, PKrssFields, Params, SKrss, Sigkey, Verkey};
+use crate::wire::WireMessage;
+use crate::{SignatureGroup, VerkeyGroup};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+/// Fields of a key file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyLayout {
+    /// The derived serde encoding of the current version
+    Current,
+    /// A verkey or `PKrss` without the label hash of its params
+    Legacy,
+}
+
+/// Key of any kind read from a key file
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum StoredKey {
+    Sigkey(Sigkey),
+    Verkey(Verkey),
+    SKrss(SKrss),
+    PKrss(PKrss),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LegacyVerkey {
+    X_tilde: VerkeyGroup,
+    Y_tilde: Vec<VerkeyGroup>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LegacyPKrss {
+    n: usize,
+    g: SignatureGroup,
+    g_tilde: VerkeyGroup,
+    Y_j_1_to_n: Vec<SignatureGroup>,
+    Y_k_nplus2_to_2n: Vec<SignatureGroup>,
+    X_tilde: VerkeyGroup,
+    Y_tilde_i: Vec<VerkeyGroup>,
+}
+
+/// Layouts tried in order, the current keys before the legacy ones which have a subset of their fields
+#[derive(Deserialize)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+enum AnyLayout {
+    Sigkey(Sigkey),
+    Verkey(Verkey),
+    LegacyVerkey(LegacyVerkey),
+    SKrss(SKrss),
+    PKrss(PKrss),
+    LegacyPKrss(LegacyPKrss),
+}
+
+impl StoredKey {
+    /// The key in the canonical format
+    pub fn to_wire_bytes(&self) -> Vec<u8> {
+        match self {
+            StoredKey::Sigkey(k) => k.to_wire_bytes(),
+            StoredKey::Verkey(k) => k.to_wire_bytes(),
+            StoredKey::SKrss(k) => k.to_wire_bytes(),
+            StoredKey::PKrss(k) => k.to_wire_bytes(),
+        }
+    }
+}
+
+/// Key of `json`, the derived serde encoding of a key of this or an earlier version, with its layout. A legacy
+/// verkey or `PKrss` gets the label hash of `params`. Fails with `PSError::DeserializationError` if `json` is no key.
+pub fn key_from_json(json: &str, params: &Params) -> Result<(StoredKey, KeyLayout), PSError> {
+    let key: AnyLayout = serde_json::from_str(json).map_err(|e| PSError::DeserializationError {
+        msg: format!("No key in a known layout: {}", e),
+    })?;
+    Ok(match key {
+        AnyLayout::Sigkey(k) => (StoredKey::Sigkey(k), KeyLayout::Current),
+        AnyLayout::Verkey(k) => (StoredKey::Verkey(k), KeyLayout::Current),
+        AnyLayout::LegacyVerkey(k) => {
+            let vk = Verkey {
+                X_tilde: k.X_tilde,
+                Y_tilde: k.Y_tilde,
+                params_label_hash: params.label_hash.clone(),
+            };
+            vk.sanity_check()?;
+            (StoredKey::Verkey(vk), KeyLayout::Legacy)
+        }
+        AnyLayout::SKrss(k) => (StoredKey::SKrss(k), KeyLayout::Current),
+        AnyLayout::PKrss(k) => (StoredKey::PKrss(k), KeyLayout::Current),
+        AnyLayout::LegacyPKrss(k) => {
+            let pk = PKrss::try_from(PKrssFields {
+                n: k.n,
+                g: k.g,
+                g_tilde: k.g_tilde,
+                Y_j_1_to_n: k.Y_j_1_to_n,
+                Y_k_nplus2_to_2n: k.Y_k_nplus2_to_2n,
+                X_tilde: k.X_tilde,
+                Y_tilde_i: k.Y_tilde_i,
+                params_label_hash: params.label_hash.clone(),
+            })?;
+            (StoredKey::PKrss(pk), KeyLayout::Legacy)
+        }
+    })
+}
+
+/// Convert the key file at `from`, in the JSON of the derived serde encoding of any version, to the canonical format
+/// in the file at `to`, returning the layout it was in. Fails with `PSError::StorageError` if a file cannot be read
+/// or written.
+pub fn migrate_key_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    from: P,
+    to: Q,
+    params: &Params,
+) -> Result<KeyLayout, PSError> {
+    let json = fs::read_to_string(from).map_err(storage_error)?;
+    let (key, layout) = key_from_json(&json, params)?;
+    fs::write(to, key.to_wire_bytes()).map_err(storage_error)?;
+    Ok(layout)
+}
+
+fn storage_error(e: std::io::Error) -> PSError {
+    PSError::StorageError {
+        msg: format!("{}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, rsskeygen};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_legacy_keys() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let (rss_sk, pk) = rsskeygen(3, &params).unwrap();
+
+        // Legacy verkeys as maps and as sequences
+        let legacy_map =
+            serde_json::json!({"X_tilde": vk.X_tilde, "Y_tilde": vk.Y_tilde}).to_string();
+        let legacy_seq = serde_json::json!([vk.X_tilde, vk.Y_tilde]).to_string();
+        assert!(serde_json::from_str::<Verkey>(&legacy_map).is_err());
+        for json in &[legacy_map.as_str(), legacy_seq.as_str()] {
+            match key_from_json(json, &params).unwrap() {
+                (StoredKey::Verkey(k), KeyLayout::Legacy) => {
+                    assert_eq!(k, vk);
+                    assert!(k.check_params(&params).is_ok());
+                }
+                r => panic!("Expected a legacy verkey but got {:?}", r),
+            }
+        }
+        // Legacy PKrss without the label hash
+        let mut legacy_pk = serde_json::to_value(&pk).unwrap();
+        legacy_pk
+            .as_object_mut()
+            .unwrap()
+            .remove("params_label_hash")
+            .unwrap();
+        match key_from_json(&legacy_pk.to_string(), &params).unwrap() {
+            (StoredKey::PKrss(k), KeyLayout::Legacy) => {
+                assert_eq!(k.to_bytes(), pk.to_bytes());
+                assert!(k.check_params(&params).is_ok());
+            }
+            r => panic!("Expected a legacy PKrss but got {:?}", r),
+        }
+        // Current keys of each kind
+        let current = [
+            serde_json::to_string(&sk).unwrap(),
+            serde_json::to_string(&vk).unwrap(),
+            serde_json::to_string(&rss_sk).unwrap(),
+            serde_json::to_string(&pk).unwrap(),
+        ];
+        let kinds: Vec<&str> = current
+            .iter()
+            .map(|json| match key_from_json(json, &params).unwrap() {
+                (StoredKey::Sigkey(_), KeyLayout::Current) => "sigkey",
+                (StoredKey::Verkey(k), KeyLayout::Current) => {
+                    assert_eq!(k, vk);
+                    "verkey"
+                }
+                (StoredKey::SKrss(_), KeyLayout::Current) => "skrss",
+                (StoredKey::PKrss(_), KeyLayout::Current) => "pkrss",
+                r => panic!("Unexpected {:?}", r),
+            })
+            .collect();
+        assert_eq!(kinds, vec!["sigkey", "verkey", "skrss", "pkrss"]);
+        assert!(key_from_json(r#"{"X_tilde": 1}"#, &params).is_err());
+
+        // Migrating a file gives the canonical format
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir();
+        let from = dir.join(format!(
+            "ps_sig_legacy_{}_{}.json",
+            std::process::id(),
+            nanos
+        ));
+        let to = dir.join(format!(
+            "ps_sig_legacy_{}_{}.bin",
+            std::process::id(),
+            nanos
+        ));
+        fs::write(&from, &legacy_map).unwrap();
+        assert_eq!(
+            migrate_key_file(&from, &to, &params).unwrap(),
+            KeyLayout::Legacy
+        );
+        assert_eq!(
+            Verkey::from_wire_bytes(&fs::read(&to).unwrap()).unwrap(),
+            vk
+        );
+        fs::remove_file(&from).unwrap();
+        fs::remove_file(&to).unwrap();
+        match migrate_key_file(&from, &to, &params) {
+            Err(PSError::StorageError { .. }) => (),
+            r => panic!("Expected StorageError but got {:?}", r),
+        }
+    }
+}
@@ -3,48 +3,17 @@
 #[cfg(all(feature = "SignatureG2", feature = "SignatureG1"))]
 compile_error!("features `SignatureG2` and `SignatureG1` are mutually exclusive");
 
-#[macro_use]
 extern crate amcl_wrapper;
 
-use amcl_wrapper::extension_field_gt::GT;
-
-#[cfg(feature = "SignatureG2")]
-pub type SignatureGroup = amcl_wrapper::group_elem_g2::G2;
-#[cfg(feature = "SignatureG2")]
-pub type SignatureGroupVec = amcl_wrapper::group_elem_g2::G2Vector;
-#[cfg(feature = "SignatureG2")]
-pub type VerkeyGroup = amcl_wrapper::group_elem_g1::G1;
-#[cfg(feature = "SignatureG2")]
-pub type VerkeyGroupVec = amcl_wrapper::group_elem_g1::G1Vector;
-#[cfg(feature = "SignatureG2")]
-pub fn ate_2_pairing(
-    g1: &SignatureGroup,
-    g2: &VerkeyGroup,
-    h1: &SignatureGroup,
-    h2: &VerkeyGroup,
-) -> GT {
-    GT::ate_2_pairing(g2, g1, h2, h1)
-}
-
-#[cfg(feature = "SignatureG1")]
-pub type SignatureGroup = amcl_wrapper::group_elem_g1::G1;
-#[cfg(feature = "SignatureG1")]
-pub type SignatureGroupVec = amcl_wrapper::group_elem_g1::G1Vector;
-#[cfg(feature = "SignatureG1")]
-pub type VerkeyGroup = amcl_wrapper::group_elem_g2::G2;
-#[cfg(feature = "SignatureG1")]
-pub type VerkeyGroupVec = amcl_wrapper::group_elem_g2::G2Vector;
-#[cfg(feature = "SignatureG1")]
-pub fn ate_2_pairing(
-    g1: &SignatureGroup,
-    g2: &VerkeyGroup,
-    h1: &SignatureGroup,
-    h2: &VerkeyGroup,
-) -> GT {
-    GT::ate_2_pairing(g1, g2, h1, h2)
-}
+pub use curve::{
+    ate_2_pairing, ate_pairing, SignatureGroup, SignatureGroupVec, VerkeyGroup, VerkeyGroupVec,
+    SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE,
+};
+
+pub use build_features::features;
 
 extern crate rand;
+extern crate rayon;
 #[macro_use]
 extern crate failure;
 
@@ -52,8 +21,128 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 
+#[cfg(any(
+    feature = "data-integrity",
+    feature = "human-serde",
+    feature = "json-document",
+    feature = "legacy-keys",
+    feature = "server"
+))]
+extern crate serde_json;
+#[cfg(feature = "data-integrity")]
+extern crate subtle_encoding;
+#[cfg(feature = "encrypted-keys")]
+extern crate argon2;
+#[cfg(any(feature = "encrypted-keys", feature = "sealed-credentials"))]
+extern crate chacha20poly1305;
+#[cfg(feature = "signature-traits")]
+extern crate sig_traits;
+#[cfg(feature = "sealed-credentials")]
+extern crate x25519_dalek;
+#[cfg(feature = "sealed-credentials")]
+extern crate hkdf;
+#[cfg(feature = "sealed-credentials")]
+extern crate sha2;
+#[cfg(feature = "ark-bridge")]
+extern crate ark_bls12_381;
+#[cfg(feature = "ark-bridge")]
+extern crate ark_ec;
+#[cfg(feature = "ark-bridge")]
+extern crate ark_ff;
+#[cfg(feature = "ark-bridge")]
+extern crate ark_serialize;
+#[cfg(feature = "derive")]
+extern crate ps_sig_derive;
+
+#[allow(non_local_definitions)]
 pub mod errors;
 #[macro_use]
-
+pub mod pok_vc;
+pub mod algorithm;
+pub mod anon_token;
+#[cfg(feature = "ark-bridge")]
+pub mod ark_bridge;
+pub mod auditable;
+pub mod blind_signature;
+pub mod build_features;
+pub mod challenge_domain;
+pub mod conditional_issuance;
+pub mod context;
+pub mod credential_struct;
+pub mod credential_update;
+pub mod curve;
+#[cfg(feature = "data-integrity")]
+pub mod data_integrity;
+#[cfg(feature = "json-document")]
+pub mod document;
+pub mod document_digest;
+pub mod encoding;
+pub mod envelope;
+pub mod epoch_keys;
+pub mod equality;
+pub mod estimate;
+#[cfg(feature = "human-serde")]
+pub mod human_serde;
+pub mod inspect;
+pub mod integer_message;
+pub mod issuer;
+pub mod issuer_bundle;
+pub mod issuer_ring;
+pub mod joint_signing;
+pub mod k_show;
+#[cfg(feature = "encrypted-keys")]
+pub mod key_encryption;
+pub mod keygen_transcript;
 pub mod keys;
+pub mod lazy_pkrss;
+#[cfg(feature = "legacy-keys")]
+pub mod legacy;
+pub mod link_secret;
+pub mod linking_proof;
+pub mod merkle_payload;
+pub mod msm;
+pub mod nonce_store;
+pub mod params_registry;
+pub mod perf_hints;
+pub mod pok_committed;
+pub mod pok_sig;
+pub mod position_binding;
+pub mod predicate_proof;
+pub mod presentation;
+pub mod presentation_builder;
+pub mod proof_format;
+pub mod profiling;
+pub mod proof_request;
+pub mod quick;
+pub mod redaction_padding;
+pub mod redaction_policy;
+pub mod rng_health;
+pub mod rss_signature;
+pub mod rss_upgrade;
+pub mod salted_message;
+pub mod sanitizable;
+pub mod scalar;
+#[cfg(feature = "sealed-credentials")]
+pub mod sealed_credential;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod signature;
+#[cfg(feature = "signature-traits")]
+pub mod signer;
+pub mod signing_domain;
+pub mod status_list;
+pub mod storage;
+pub mod streaming_keygen;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+pub mod text_equality;
+pub mod threshold;
+pub mod trust_registry;
+pub mod typed_message;
+#[cfg(feature = "ursa-keys")]
+pub mod ursa_keys;
+pub mod validity;
+pub mod verification_report;
+pub mod verifier_policy;
+pub mod wire;
 
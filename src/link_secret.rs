@@ -0,0 +1,376 @@
+// Holder binding with a link secret. The link secret is a message known only to the holder (and ideally
+// kept on the holder's device). It is blinded when requesting the signature and is never revealed in a
+// presentation: every proof of knowledge of the signature also proves knowledge of the link secret, so a
+// credential cannot be lent to someone without also handing over the link secret.
+
+use crate::blind_signature::{BlindSignature, BlindingKey};
+use crate::challenge_domain::LINK_SECRET;
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::pok_sig::{
+    PoKOfSignature, PoKOfSignatureProof, ProofSignatureGroup, ProverCommittingSignatureGroup,
+};
+use crate::scalar::{Blinding, BlindingScalar, MessageScalar};
+use crate::signature::Signature;
+use crate::SignatureGroup;
+use std::collections::{HashMap, HashSet};
+
+/// Index of the message reserved for the link secret. `BlindSignature` expects the committed messages
+/// to come first so the link secret always occupies the first slot of the signed messages.
+pub const LINK_SECRET_INDEX: usize = 0;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinkSecret {
+    secret: FieldElement,
+}
+
+/// Sent by the holder to the signer when requesting a signature bound to its link secret.
+/// Contains the commitment `Y_0^link_secret * g^blinding` and a proof of knowledge of its opening.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinkSecretCommitment {
+    pub commitment: SignatureGroup,
+    pub proof: ProofSignatureGroup,
+}
+
+impl Default for LinkSecret {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinkSecret {
+    pub fn new() -> Self {
+        Self {
+            secret: FieldElement::random(),
+        }
+    }
+
+    /// Commit to the link secret for a blind signature request. `nonce` is chosen by the signer to
+    /// prevent replay of the request. Returns the request and the blinding which must be kept to unblind
+    /// the signature.
+    pub fn commit(
+        &self,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> (LinkSecretCommitment, Blinding) {
+        let blinding = Blinding::random();
+        let commitment = SignatureGroup::binary_scalar_mul(
+            &blinding_key.Y[LINK_SECRET_INDEX],
+            &params.g,
+            &self.secret,
+            blinding.as_field_element(),
+        );
+
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(&blinding_key.Y[LINK_SECRET_INDEX], None);
+        committing.commit(&params.g, None);
+        let committed = committing.finish();
+        let mut bytes = LINK_SECRET.tag();
+        bytes.append(&mut committed.to_bytes());
+        bytes.append(&mut commitment.to_bytes());
+        bytes.extend_from_slice(nonce);
+        let challenge = FieldElement::from_msg_hash(&bytes);
+        let proof = committed
+            .gen_proof(&challenge, &[self.secret.clone(), blinding.as_field_element().clone()])
+            .unwrap();
+        (LinkSecretCommitment { commitment, proof }, blinding)
+    }
+
+    /// Unblind the signature received from the signer and check that it is a valid signature over
+    /// the link secret followed by `messages`.
+    pub fn unblind<B: BlindingScalar, M: MessageScalar>(
+        &self,
+        sig: &Signature,
+        blinding: &B,
+        messages: &[M],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        let sig = BlindSignature::unblind(sig, blinding);
+        if !sig.verify(self.with_messages(messages), vk, params)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signature does not verify with the link secret"),
+            });
+        }
+        Ok(sig)
+    }
+
+    /// Start a proof of knowledge of a signature bound to this link secret. `messages` are the
+    /// messages other than the link secret and `revealed_msg_indices` are indices over all signed messages,
+    /// i.e. the first of `messages` has index `LINK_SECRET_INDEX + 1`.
+    pub fn init_pok<M: MessageScalar>(
+        &self,
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        messages: &[M],
+        blindings: Option<&[Blinding]>,
+        revealed_msg_indices: HashSet<usize>,
+    ) -> Result<PoKOfSignature, PSError> {
+        if revealed_msg_indices.contains(&LINK_SECRET_INDEX) {
+            return Err(PSError::LinkSecretRevealed {
+                idx: LINK_SECRET_INDEX,
+            });
+        }
+        PoKOfSignature::init(
+            sig,
+            vk,
+            params,
+            self.with_messages(messages),
+            blindings,
+            revealed_msg_indices,
+        )
+    }
+
+    pub(crate) fn secret(&self) -> &FieldElement {
+        &self.secret
+    }
+
+    fn with_messages<M: MessageScalar>(&self, messages: &[M]) -> Vec<FieldElement> {
+        let mut msgs = Vec::with_capacity(messages.len() + 1);
+        msgs.push(self.secret.clone());
+        msgs.extend_from_slice(&M::field_elements(messages));
+        msgs
+    }
+}
+
+impl LinkSecretCommitment {
+    /// Signer verifies the holder knows the opening of the commitment.
+    pub fn verify(
+        &self,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        let bases = [blinding_key.Y[LINK_SECRET_INDEX].clone(), params.generator()];
+        // The challenge is recomputed the same way as the holder did in `LinkSecret::commit`.
+        let mut bytes = LINK_SECRET.tag();
+        for b in bases.iter() {
+            bytes.append(&mut b.to_bytes());
+        }
+        bytes.append(&mut self.proof.commitment.to_bytes());
+        bytes.append(&mut self.commitment.to_bytes());
+        bytes.extend_from_slice(nonce);
+        let challenge = FieldElement::from_msg_hash(&bytes);
+        self.proof.verify(&bases, &self.commitment, &challenge)
+    }
+
+    /// Signer verifies the request and signs the link secret along with the known `messages`, which
+    /// occupy indices `LINK_SECRET_INDEX + 1..`.
+    pub fn sign<M: MessageScalar>(
+        &self,
+        messages: &[M],
+        sig_key: &Sigkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<Signature, PSError> {
+        if messages.len() + 1 != blinding_key.msg_count() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: blinding_key.msg_count() - 1,
+                given: messages.len(),
+            });
+        }
+        if !self.verify(blinding_key, params, nonce)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Proof of knowledge of link secret does not verify"),
+            });
+        }
+        BlindSignature::new(&self.commitment, messages, sig_key, blinding_key, params)
+    }
+}
+
+impl PoKOfSignatureProof {
+    /// Verify a proof of knowledge of a signature bound to a link secret. Fails if the link secret was
+    /// revealed rather than proven.
+    pub fn verify_with_link_secret(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        if revealed_msgs.contains_key(&LINK_SECRET_INDEX) {
+            return Err(PSError::LinkSecretRevealed {
+                idx: LINK_SECRET_INDEX,
+            });
+        }
+        self.verify(vk, params, revealed_msgs, challenge)
+    }
+
+    /// Response for the link secret. Equal across proofs that used the same challenge and the same
+    /// blinding for the link secret, which proves they are bound to the same holder.
+    pub fn get_resp_for_link_secret(&self) -> Result<FieldElement, PSError> {
+        self.get_resp_for_message(LINK_SECRET_INDEX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    fn issue(
+        link_secret: &LinkSecret,
+        msgs: &[FieldElement],
+        sk: &Sigkey,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Signature {
+        let blinding_key = BlindingKey::new(sk, params);
+        let nonce = FieldElement::random().to_bytes();
+        let (req, blinding) = link_secret.commit(&blinding_key, params, &nonce);
+        let sig_blinded = req.sign(msgs, sk, &blinding_key, params, &nonce).unwrap();
+        link_secret
+            .unblind(&sig_blinded, &blinding, msgs, vk, params)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_link_secret_issuance_and_presentation() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let link_secret = LinkSecret::new();
+        let msgs = FieldElementVector::random(count_msgs - 1);
+        let sig = issue(&link_secret, msgs.as_slice(), &sk, &vk, &params);
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(2);
+        let pok = link_secret
+            .init_pok(
+                &sig,
+                &vk,
+                &params,
+                msgs.as_slice(),
+                None,
+                revealed_msg_indices,
+            )
+            .unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+
+        let mut revealed_msgs = HashMap::new();
+        // Index 2 over all messages is index 1 among the messages other than the link secret
+        revealed_msgs.insert(2, msgs[1].clone());
+        assert!(proof
+            .verify_with_link_secret(&vk, &params, revealed_msgs.clone(), &chal)
+            .unwrap());
+
+        // A proof revealing the link secret is rejected by the verifier
+        revealed_msgs.insert(LINK_SECRET_INDEX, FieldElement::random());
+        assert!(proof
+            .verify_with_link_secret(&vk, &params, revealed_msgs, &chal)
+            .is_err());
+    }
+
+    #[test]
+    fn test_link_secret_cannot_be_revealed() {
+        let count_msgs = 3;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let link_secret = LinkSecret::new();
+        let msgs = FieldElementVector::random(count_msgs - 1);
+        let sig = issue(&link_secret, msgs.as_slice(), &sk, &vk, &params);
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(LINK_SECRET_INDEX);
+        assert!(link_secret
+            .init_pok(
+                &sig,
+                &vk,
+                &params,
+                msgs.as_slice(),
+                None,
+                revealed_msg_indices
+            )
+            .is_err());
+
+        // A different link secret cannot present the credential
+        let other = LinkSecret::new();
+        let pok = other
+            .init_pok(&sig, &vk, &params, msgs.as_slice(), None, HashSet::new())
+            .unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        assert!(!proof
+            .verify_with_link_secret(&vk, &params, HashMap::new(), &chal)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_link_secret_commitment_checked_by_signer() {
+        let count_msgs = 3;
+        let params = Params::new("test".as_bytes());
+        let (sk, _) = keygen(count_msgs, &params).unwrap();
+        let blinding_key = BlindingKey::new(&sk, &params);
+        let link_secret = LinkSecret::new();
+        let nonce = FieldElement::random().to_bytes();
+        let (req, _) = link_secret.commit(&blinding_key, &params, &nonce);
+        assert!(req.verify(&blinding_key, &params, &nonce).unwrap());
+
+        // Replayed with a different nonce
+        let msgs = FieldElementVector::random(count_msgs - 1);
+        let other_nonce = FieldElement::random().to_bytes();
+        assert!(!req.verify(&blinding_key, &params, &other_nonce).unwrap());
+        assert!(req
+            .sign(msgs.as_slice(), &sk, &blinding_key, &params, &other_nonce)
+            .is_err());
+    }
+
+    #[test]
+    fn test_same_link_secret_across_credentials() {
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let (sk_1, vk_1) = keygen(count_msgs, &params).unwrap();
+        let (sk_2, vk_2) = keygen(count_msgs, &params).unwrap();
+        let link_secret = LinkSecret::new();
+        let msgs_1 = FieldElementVector::random(count_msgs - 1);
+        let msgs_2 = FieldElementVector::random(count_msgs - 1);
+        let sig_1 = issue(&link_secret, msgs_1.as_slice(), &sk_1, &vk_1, &params);
+        let sig_2 = issue(&link_secret, msgs_2.as_slice(), &sk_2, &vk_2, &params);
+
+        let same_blinding = Blinding::random();
+        let mut blindings_1: Vec<Blinding> = (1..count_msgs).map(|_| Blinding::random()).collect();
+        blindings_1.insert(LINK_SECRET_INDEX, same_blinding.clone());
+        let mut blindings_2: Vec<Blinding> = (1..count_msgs).map(|_| Blinding::random()).collect();
+        blindings_2.insert(LINK_SECRET_INDEX, same_blinding);
+
+        let pok_1 = link_secret
+            .init_pok(
+                &sig_1,
+                &vk_1,
+                &params,
+                msgs_1.as_slice(),
+                Some(blindings_1.as_slice()),
+                HashSet::new(),
+            )
+            .unwrap();
+        let pok_2 = link_secret
+            .init_pok(
+                &sig_2,
+                &vk_2,
+                &params,
+                msgs_2.as_slice(),
+                Some(blindings_2.as_slice()),
+                HashSet::new(),
+            )
+            .unwrap();
+        let chal = FieldElement::from_msg_hash(&[pok_1.to_bytes(), pok_2.to_bytes()].concat());
+        let proof_1 = pok_1.gen_proof(&chal).unwrap();
+        let proof_2 = pok_2.gen_proof(&chal).unwrap();
+        assert!(proof_1
+            .verify_with_link_secret(&vk_1, &params, HashMap::new(), &chal)
+            .unwrap());
+        assert!(proof_2
+            .verify_with_link_secret(&vk_2, &params, HashMap::new(), &chal)
+            .unwrap());
+        assert_eq!(
+            proof_1.get_resp_for_link_secret().unwrap(),
+            proof_2.get_resp_for_link_secret().unwrap()
+        );
+    }
+}
@@ -0,0 +1,338 @@
+// Proof that presentations of any number of credentials share the hidden link secret, see `link_secret`. The
+// presentations are created together with one challenge and the same blinding for the link secret, so their
+// responses for it are equal exactly when the link secret is. The proof also gives the session tag
+// H(session id)^link secret with a proof that its exponent is the link secret, like the tags of `anon_token`.
+// Tags of linking proofs of the same holder in the same session are equal, so a verifier can correlate all the
+// presentations of a session. Tags of different sessions are unlinkable, and so are the presentations. Tags are
+// checked to be in the prime order group, else adding a point of small order to the tag would give the holder other
+// tags in the same session.
+
+use crate::challenge_domain::LINKING_PROOF;
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::link_secret::LinkSecret;
+use crate::pok_sig::PoKOfSignatureProof;
+use crate::scalar::Blinding;
+use crate::signature::{check_subgroup, Signature};
+use crate::VerkeyGroup;
+use std::collections::{HashMap, HashSet};
+
+const SESSION_DOMAIN: &[u8] = b"PS session : ";
+
+/// Credential bound to the link secret to present in a session. `messages` are the messages other than the link
+/// secret and `revealed_msg_indices` are over all signed messages, as for `LinkSecret::init_pok`.
+#[derive(Clone, Debug)]
+pub struct LinkedCredential<'a> {
+    pub signature: &'a Signature,
+    pub verkey: &'a Verkey,
+    pub messages: &'a [FieldElement],
+    pub revealed_msg_indices: HashSet<usize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinkedPresentation {
+    pub proof: PoKOfSignatureProof,
+    pub revealed_msgs: HashMap<usize, FieldElement>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinkingProof {
+    pub session_id: Vec<u8>,
+    /// H(session id)^link secret
+    pub tag: VerkeyGroup,
+    /// H(session id)^blinding of the link secret
+    pub tag_commitment: VerkeyGroup,
+    pub presentations: Vec<LinkedPresentation>,
+}
+
+fn session_base(session_id: &[u8]) -> VerkeyGroup {
+    VerkeyGroup::from_msg_hash(&[SESSION_DOMAIN, session_id].concat())
+}
+
+fn linking_challenge(
+    session_id: &[u8],
+    bytes_for_challenge: Vec<Vec<u8>>,
+    tag: &VerkeyGroup,
+    tag_commitment: &VerkeyGroup,
+    nonce: &[u8],
+) -> FieldElement {
+    let mut bytes = LINKING_PROOF.tag();
+    bytes.extend_from_slice(&(session_id.len() as u64).to_be_bytes());
+    bytes.extend_from_slice(session_id);
+    bytes.extend_from_slice(&(bytes_for_challenge.len() as u64).to_be_bytes());
+    for b in bytes_for_challenge {
+        bytes.extend(b);
+    }
+    bytes.append(&mut tag.to_bytes());
+    bytes.append(&mut tag_commitment.to_bytes());
+    bytes.extend_from_slice(nonce);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl LinkingProof {
+    /// Presentations of `credentials`, all bound to `link_secret`, in the session `session_id` for a verifier who
+    /// sent `nonce`
+    pub fn new(
+        link_secret: &LinkSecret,
+        session_id: &[u8],
+        credentials: &[LinkedCredential],
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<Self, PSError> {
+        if credentials.is_empty() {
+            return Err(PSError::GeneralError {
+                msg: String::from("Linking proof needs at least 1 credential"),
+            });
+        }
+        let blinding = Blinding::random();
+        let mut poks = Vec::with_capacity(credentials.len());
+        let mut revealed = Vec::with_capacity(credentials.len());
+        for cred in credentials {
+            // The link secret is hidden and comes first among the hidden messages
+            let hidden_count =
+                (cred.messages.len() + 1).saturating_sub(cred.revealed_msg_indices.len());
+            let mut blindings = vec![blinding.clone()];
+            blindings.extend((1..hidden_count).map(|_| Blinding::random()));
+            poks.push(link_secret.init_pok(
+                cred.signature,
+                cred.verkey,
+                params,
+                cred.messages,
+                Some(blindings.as_slice()),
+                cred.revealed_msg_indices.clone(),
+            )?);
+            revealed.push(
+                cred.revealed_msg_indices
+                    .iter()
+                    .map(|i| (*i, cred.messages[*i - 1].clone()))
+                    .collect(),
+            );
+        }
+        let base = session_base(session_id);
+        let tag = &base * link_secret.secret();
+        let tag_commitment = &base * blinding.as_field_element();
+        let challenge = linking_challenge(
+            session_id,
+            poks.iter().map(|p| p.to_bytes()).collect(),
+            &tag,
+            &tag_commitment,
+            nonce,
+        );
+        let mut presentations = Vec::with_capacity(poks.len());
+        for (pok, revealed_msgs) in poks.into_iter().zip(revealed) {
+            presentations.push(LinkedPresentation {
+                proof: pok.gen_proof(&challenge)?,
+                revealed_msgs,
+            });
+        }
+        Ok(Self {
+            session_id: session_id.to_vec(),
+            tag,
+            tag_commitment,
+            presentations,
+        })
+    }
+
+    /// Verify the presentations, the i-th with the i-th of `verkeys`, and that they and `tag` share the link secret.
+    /// Fails with `PSError::InvalidGroupElement` if `tag` or `tag_commitment` is outside the prime order group.
+    pub fn verify(
+        &self,
+        verkeys: &[&Verkey],
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        check_subgroup(&[&self.tag, &self.tag_commitment])?;
+        if self.tag.is_identity()
+            || self.presentations.is_empty()
+            || self.presentations.len() != verkeys.len()
+        {
+            return Ok(false);
+        }
+        let challenge = linking_challenge(
+            &self.session_id,
+            self.presentations
+                .iter()
+                .zip(verkeys)
+                .map(|(p, vk)| {
+                    p.proof.get_bytes_for_challenge(
+                        p.revealed_msgs.keys().cloned().collect(),
+                        vk,
+                        params,
+                    )
+                })
+                .collect(),
+            &self.tag,
+            &self.tag_commitment,
+            nonce,
+        );
+        let mut response = None;
+        for (p, vk) in self.presentations.iter().zip(verkeys) {
+            if !p
+                .proof
+                .verify_with_link_secret(vk, params, p.revealed_msgs.clone(), &challenge)?
+            {
+                return Ok(false);
+            }
+            let r = p.proof.get_resp_for_link_secret()?;
+            match &response {
+                Some(first) if *first != r => return Ok(false),
+                Some(_) => (),
+                None => response = Some(r),
+            }
+        }
+        // H(session id)^response * tag^challenge == tag_commitment
+        let response = response.unwrap();
+        Ok(
+            session_base(&self.session_id).binary_scalar_mul(&self.tag, &response, &challenge)
+                == self.tag_commitment,
+        )
+    }
+
+    /// Whether both proofs are of the same holder in the same session. Only meaningful for verified proofs.
+    pub fn same_session_holder(&self, other: &Self) -> bool {
+        self.session_id == other.session_id && self.tag == other.tag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blind_signature::BlindingKey;
+    use crate::curve::FieldElementVector;
+    use crate::keys::{keygen, Sigkey};
+
+    fn issue(
+        link_secret: &LinkSecret,
+        msgs: &[FieldElement],
+        sk: &Sigkey,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Signature {
+        let blinding_key = BlindingKey::new(sk, params);
+        let nonce = FieldElement::random().to_bytes();
+        let (req, blinding) = link_secret.commit(&blinding_key, params, &nonce);
+        let sig_blinded = req.sign(msgs, sk, &blinding_key, params, &nonce).unwrap();
+        link_secret
+            .unblind(&sig_blinded, &blinding, msgs, vk, params)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_linking_proof() {
+        let params = Params::new("test".as_bytes());
+        let (sk_1, vk_1) = keygen(3, &params).unwrap();
+        let (sk_2, vk_2) = keygen(5, &params).unwrap();
+        let link_secret = LinkSecret::new();
+        let msgs_1: Vec<FieldElement> = FieldElementVector::random(2).into();
+        let msgs_2: Vec<FieldElement> = FieldElementVector::random(4).into();
+        let sig_1 = issue(&link_secret, &msgs_1, &sk_1, &vk_1, &params);
+        let sig_2 = issue(&link_secret, &msgs_2, &sk_2, &vk_2, &params);
+        let creds = [
+            LinkedCredential {
+                signature: &sig_1,
+                verkey: &vk_1,
+                messages: &msgs_1,
+                revealed_msg_indices: vec![1].into_iter().collect(),
+            },
+            LinkedCredential {
+                signature: &sig_2,
+                verkey: &vk_2,
+                messages: &msgs_2,
+                revealed_msg_indices: vec![2, 4].into_iter().collect(),
+            },
+            LinkedCredential {
+                signature: &sig_1,
+                verkey: &vk_1,
+                messages: &msgs_1,
+                revealed_msg_indices: HashSet::new(),
+            },
+        ];
+        let verkeys = [&vk_1, &vk_2, &vk_1];
+        let nonce = FieldElement::random().to_bytes();
+
+        let proof = LinkingProof::new(&link_secret, b"session 1", &creds, &params, &nonce).unwrap();
+        assert!(proof.verify(&verkeys, &params, &nonce).unwrap());
+        assert_eq!(proof.presentations[1].revealed_msgs[&4], msgs_2[3]);
+        assert!(!proof
+            .verify(&verkeys, &params, &FieldElement::random().to_bytes())
+            .unwrap());
+        assert!(!proof.verify(&verkeys[..2], &params, &nonce).unwrap());
+
+        // Correlated within the session but not across sessions
+        let again =
+            LinkingProof::new(&link_secret, b"session 1", &creds[1..], &params, &nonce).unwrap();
+        assert!(again.verify(&verkeys[1..], &params, &nonce).unwrap());
+        assert!(proof.same_session_holder(&again));
+        let other_session =
+            LinkingProof::new(&link_secret, b"session 2", &creds, &params, &nonce).unwrap();
+        assert!(other_session.verify(&verkeys, &params, &nonce).unwrap());
+        assert!(!proof.same_session_holder(&other_session));
+        assert_ne!(proof.tag, other_session.tag);
+
+        // Presentations of different link secrets cannot be combined
+        let other_secret = LinkSecret::new();
+        let sig_3 = issue(&other_secret, &msgs_2, &sk_2, &vk_2, &params);
+        let other_creds = [LinkedCredential {
+            signature: &sig_3,
+            verkey: &vk_2,
+            messages: &msgs_2,
+            revealed_msg_indices: HashSet::new(),
+        }];
+        let other_proof =
+            LinkingProof::new(&other_secret, b"session 1", &other_creds, &params, &nonce).unwrap();
+        assert!(!proof.same_session_holder(&other_proof));
+        let mut mixed = proof.clone();
+        mixed.presentations[1] = other_proof.presentations[0].clone();
+        assert!(!mixed.verify(&verkeys, &params, &nonce).unwrap());
+        // nor can the tag be replaced
+        let mut wrong_tag = proof.clone();
+        wrong_tag.tag = other_proof.tag.clone();
+        assert!(!wrong_tag.verify(&verkeys, &params, &nonce).unwrap());
+
+        // A credential of another link secret cannot be presented
+        let wrong_secret =
+            LinkingProof::new(&link_secret, b"session 1", &other_creds, &params, &nonce).unwrap();
+        assert!(!wrong_secret.verify(&[&vk_2], &params, &nonce).unwrap());
+        assert!(LinkingProof::new(&link_secret, b"session 1", &[], &params, &nonce).is_err());
+    }
+
+    // The point of small order is of G1
+    #[cfg(feature = "SignatureG2")]
+    #[test]
+    fn test_tag_outside_group_rejected() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let link_secret = LinkSecret::new();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(2).into();
+        let sig = issue(&link_secret, &msgs, &sk, &vk, &params);
+        let creds = [LinkedCredential {
+            signature: &sig,
+            verkey: &vk,
+            messages: &msgs,
+            revealed_msg_indices: HashSet::new(),
+        }];
+        let nonce = FieldElement::random().to_bytes();
+        let proof = LinkingProof::new(&link_secret, b"session", &creds, &params, &nonce).unwrap();
+
+        // Point on the curve (x = 5) but outside the prime order subgroup of G1
+        let mut small_order = vec![0u8; crate::curve::GroupG1_SIZE];
+        small_order[0] = 2;
+        small_order[crate::curve::MODBYTES] = 5;
+        let small_order = VerkeyGroup::from_bytes(&small_order).unwrap();
+        let mut shifted_tag = proof.clone();
+        shifted_tag.tag = &proof.tag + &small_order;
+        let mut shifted_commitment = proof.clone();
+        shifted_commitment.tag_commitment = &proof.tag_commitment + &small_order;
+        for p in &[shifted_tag, shifted_commitment] {
+            match p.verify(&[&vk], &params, &nonce) {
+                Err(PSError::InvalidGroupElement) => (),
+                r => panic!("Expected InvalidGroupElement but got {:?}", r),
+            }
+        }
+
+        let mut identity = proof;
+        identity.tag = VerkeyGroup::identity();
+        assert!(!identity.verify(&[&vk], &params, &nonce).unwrap());
+    }
+}
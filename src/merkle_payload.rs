@@ -0,0 +1,338 @@
+// Messages of redactable signatures which are large payloads, e.g. a file split into chunks. Each message is the
+// root of a Merkle tree over the chunks of its payload so the signer signs one message per payload whatever its
+// size. The holder discloses the root of a payload alongside the redacted signature and opens any of its chunks
+// with a `LeafOpening`, the chunks not opened remain hidden, giving selective disclosure of chunks.
+// A leaf is the hash of a random salt and the chunk so that the root and the openings reveal nothing about the
+// chunks not opened, even low entropy ones. A node is the hash of its 2 children, the last node of a level of odd
+// length is carried to the next level unchanged. The root is the hash of the count of leaves and the top node.
+// Leaves, nodes and the root hash with different domains. The signer gives the `MerkleTree`, which has the salts,
+// to the holder with the signature.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::rss_signature::{RSSPublicKey, RSSignature, RedactedSignature};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+const LEAF_DOMAIN: &[u8] = b"PS merkle leaf : ";
+const NODE_DOMAIN: &[u8] = b"PS merkle node : ";
+const ROOT_DOMAIN: &[u8] = b"PS merkle root : ";
+
+/// Merkle tree over the chunks of a payload, without the chunks
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MerkleTree {
+    salts: Vec<FieldElement>,
+    /// Leaves first, the top node last
+    levels: Vec<Vec<FieldElement>>,
+}
+
+/// Opening of the chunk at `index` of a payload to the root of its tree
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LeafOpening {
+    pub index: usize,
+    pub leaf_count: usize,
+    pub salt: FieldElement,
+    /// Siblings from the leaf up, none for a level where the node is carried up
+    pub path: Vec<FieldElement>,
+}
+
+/// Root of a disclosed payload and its opened chunks with their openings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpenedPayload {
+    pub root: FieldElement,
+    pub chunks: Vec<(LeafOpening, Vec<u8>)>,
+}
+
+/// Redacted signature on the roots of payloads with the disclosed payloads keyed by index of message
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChunkPresentation {
+    pub signature: RedactedSignature,
+    pub payloads: BTreeMap<usize, OpenedPayload>,
+}
+
+fn hash_leaf(salt: &FieldElement, chunk: &[u8]) -> FieldElement {
+    let mut bytes = LEAF_DOMAIN.to_vec();
+    bytes.append(&mut salt.to_bytes());
+    bytes.extend_from_slice(chunk);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+fn hash_node(left: &FieldElement, right: &FieldElement) -> FieldElement {
+    let mut bytes = NODE_DOMAIN.to_vec();
+    bytes.append(&mut left.to_bytes());
+    bytes.append(&mut right.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+fn hash_root(leaf_count: usize, top: &FieldElement) -> FieldElement {
+    let mut bytes = ROOT_DOMAIN.to_vec();
+    bytes.extend_from_slice(&(leaf_count as u64).to_be_bytes());
+    bytes.append(&mut top.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl MerkleTree {
+    /// Tree over `chunks` with random salts
+    pub fn new<C: AsRef<[u8]>>(chunks: &[C]) -> Result<Self, PSError> {
+        let salts = (0..chunks.len()).map(|_| FieldElement::random()).collect();
+        Self::with_salts(chunks, salts)
+    }
+
+    /// Tree over `chunks` with a salt per chunk
+    pub fn with_salts<C: AsRef<[u8]>>(
+        chunks: &[C],
+        salts: Vec<FieldElement>,
+    ) -> Result<Self, PSError> {
+        if chunks.is_empty() {
+            return Err(PSError::GeneralError {
+                msg: String::from("Payload should have at least 1 chunk"),
+            });
+        }
+        if salts.len() != chunks.len() {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "Need a salt per chunk but got {} salts for {} chunks",
+                    salts.len(),
+                    chunks.len()
+                ),
+            });
+        }
+        let mut levels = vec![salts
+            .iter()
+            .zip(chunks)
+            .map(|(s, c)| hash_leaf(s, c.as_ref()))
+            .collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Ok(Self { salts, levels })
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.salts.len()
+    }
+
+    /// Message to sign for the payload
+    pub fn root(&self) -> FieldElement {
+        hash_root(self.leaf_count(), &self.levels[self.levels.len() - 1][0])
+    }
+
+    pub fn open(&self, index: usize) -> Result<LeafOpening, PSError> {
+        if index >= self.leaf_count() {
+            return Err(PSError::InvalidIndex {
+                idx: index,
+                valid: format!("[0, {})", self.leaf_count()),
+            });
+        }
+        let mut path = vec![];
+        let mut i = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = i ^ 1;
+            if sibling < level.len() {
+                path.push(level[sibling].clone());
+            }
+            i /= 2;
+        }
+        Ok(LeafOpening {
+            index,
+            leaf_count: self.leaf_count(),
+            salt: self.salts[index].clone(),
+            path,
+        })
+    }
+}
+
+impl LeafOpening {
+    /// Root of the tree with `chunk` at `self.index`, `None` if the path does not fit the count of leaves
+    pub fn root(&self, chunk: &[u8]) -> Option<FieldElement> {
+        if self.index >= self.leaf_count {
+            return None;
+        }
+        let mut node = hash_leaf(&self.salt, chunk);
+        let mut path = self.path.iter();
+        let (mut i, mut len) = (self.index, self.leaf_count);
+        while len > 1 {
+            let sibling = i ^ 1;
+            if sibling < len {
+                let s = path.next()?;
+                node = if i % 2 == 0 {
+                    hash_node(&node, s)
+                } else {
+                    hash_node(s, &node)
+                };
+            }
+            i /= 2;
+            len = len.div_ceil(2);
+        }
+        if path.next().is_some() {
+            return None;
+        }
+        Some(hash_root(self.leaf_count, &node))
+    }
+
+    pub fn verify(&self, chunk: &[u8], root: &FieldElement) -> bool {
+        self.root(chunk).as_ref() == Some(root)
+    }
+}
+
+/// Messages to sign for the payloads of `trees`, their roots
+pub fn payload_messages(trees: &[MerkleTree]) -> Vec<FieldElement> {
+    trees.iter().map(|t| t.root()).collect()
+}
+
+impl ChunkPresentation {
+    /// Derive a signature from `sig` on the roots of `trees` disclosing the payloads of the message indices of
+    /// `opened` and open their chunks at the given indices. `payloads` are the chunks of every payload, in the
+    /// order of `trees`.
+    pub fn new<K: RSSPublicKey, C: AsRef<[u8]>>(
+        sig: &RSSignature,
+        trees: &[MerkleTree],
+        payloads: &[Vec<C>],
+        opened: &BTreeMap<usize, BTreeSet<usize>>,
+        pk: &K,
+    ) -> Result<Self, PSError> {
+        if payloads.len() != trees.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: trees.len(),
+                given: payloads.len(),
+            });
+        }
+        let messages = payload_messages(trees);
+        let disclosed: HashSet<usize> = opened.keys().cloned().collect();
+        let signature = sig.derive(&messages, &disclosed, pk)?;
+        let mut disclosed_payloads = BTreeMap::new();
+        for (i, chunk_indices) in opened {
+            let (tree, chunks) = (&trees[*i], &payloads[*i]);
+            if chunks.len() != tree.leaf_count() {
+                return Err(PSError::GeneralError {
+                    msg: format!(
+                        "Payload {} has {} chunks but its tree {} leaves",
+                        i,
+                        chunks.len(),
+                        tree.leaf_count()
+                    ),
+                });
+            }
+            let mut opened_chunks = vec![];
+            for j in chunk_indices {
+                opened_chunks.push((tree.open(*j)?, chunks[*j].as_ref().to_vec()));
+            }
+            disclosed_payloads.insert(
+                *i,
+                OpenedPayload {
+                    root: tree.root(),
+                    chunks: opened_chunks,
+                },
+            );
+        }
+        Ok(Self {
+            signature,
+            payloads: disclosed_payloads,
+        })
+    }
+
+    /// Verify the openings of the chunks and the signature on the disclosed roots
+    pub fn verify<K: RSSPublicKey>(&self, pk: &K) -> Result<bool, PSError> {
+        for payload in self.payloads.values() {
+            if !payload
+                .chunks
+                .iter()
+                .all(|(opening, chunk)| opening.verify(chunk, &payload.root))
+            {
+                return Ok(false);
+            }
+        }
+        let disclosed_msgs: HashMap<usize, FieldElement> = self
+            .payloads
+            .iter()
+            .map(|(i, p)| (*i, p.root.clone()))
+            .collect();
+        self.signature.verify(&disclosed_msgs, pk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{rsskeygen, Params};
+
+    fn chunks(count: usize, tag: u8) -> Vec<Vec<u8>> {
+        (0..count).map(|i| vec![tag, i as u8, 7, 7, 7]).collect()
+    }
+
+    #[test]
+    fn test_merkle_openings() {
+        for count in 1..=9 {
+            let payload = chunks(count, 0);
+            let tree = MerkleTree::new(&payload).unwrap();
+            let root = tree.root();
+            for (i, chunk) in payload.iter().enumerate() {
+                let opening = tree.open(i).unwrap();
+                assert!(opening.verify(chunk, &root));
+                assert!(!opening.verify(&payload[(i + 1) % count][..4], &root));
+
+                let mut bad_count = opening.clone();
+                bad_count.leaf_count += 1;
+                assert!(!bad_count.verify(chunk, &root));
+                let mut long_path = opening.clone();
+                long_path.path.push(root.clone());
+                assert!(!long_path.verify(chunk, &root));
+            }
+            assert!(tree.open(count).is_err());
+        }
+        // Same chunks, different salts
+        let payload = chunks(4, 0);
+        assert_ne!(
+            MerkleTree::new(&payload).unwrap().root(),
+            MerkleTree::new(&payload).unwrap().root()
+        );
+        assert!(MerkleTree::new::<Vec<u8>>(&[]).is_err());
+    }
+
+    #[test]
+    fn test_chunk_presentation() {
+        let params = Params::new("test".as_bytes());
+        let payloads = vec![chunks(5, 1), chunks(3, 2), chunks(8, 3)];
+        let trees: Vec<MerkleTree> = payloads
+            .iter()
+            .map(|p| MerkleTree::new(p).unwrap())
+            .collect();
+        let (sk, pk) = rsskeygen(payloads.len(), &params).unwrap();
+        let sig = RSSignature::new(&payload_messages(&trees), &sk, &params).unwrap();
+
+        let mut opened = BTreeMap::new();
+        opened.insert(0, vec![1, 4].into_iter().collect::<BTreeSet<usize>>());
+        opened.insert(2, vec![0].into_iter().collect::<BTreeSet<usize>>());
+        let presentation = ChunkPresentation::new(&sig, &trees, &payloads, &opened, &pk).unwrap();
+        assert!(presentation.verify(&pk).unwrap());
+        assert_eq!(presentation.payloads[&0].chunks[1].1, payloads[0][4]);
+        assert!(!presentation.payloads.contains_key(&1));
+
+        // Chunk altered
+        let mut altered = presentation.clone();
+        altered.payloads.get_mut(&0).unwrap().chunks[0].1 = vec![9];
+        assert!(!altered.verify(&pk).unwrap());
+        // Root of another tree with its openings
+        let mut other_root = presentation.clone();
+        let other = MerkleTree::new(&payloads[2]).unwrap();
+        other_root.payloads.insert(
+            2,
+            OpenedPayload {
+                root: other.root(),
+                chunks: vec![(other.open(0).unwrap(), payloads[2][0].clone())],
+            },
+        );
+        assert!(!other_root.verify(&pk).unwrap());
+
+        let mut out_of_range = BTreeMap::new();
+        out_of_range.insert(1, vec![3].into_iter().collect::<BTreeSet<usize>>());
+        assert!(ChunkPresentation::new(&sig, &trees, &payloads, &out_of_range, &pk).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+// Replay protection for verifiers. A presentation is bound to the nonce of the verifier, for fresh presentations the
+// nonce of the `FreshnessChallenge`, but nothing stops it from being sent twice to the verifier that gave the nonce.
+// `ReplayProtectedVerifier` verifies presentations like `presentation::verify_presentation` and
+// `verify_fresh_presentation` and records the nonce of each accepted one in a `NonceStore`, rejecting a presentation
+// with a recorded nonce with `PSError::ReplayedNonce`. Nonces are recorded after the presentation verifies so that
+// invalid presentations cannot use up the nonces of honest holders.
+// `MemoryNonceStore` keeps every nonce, for short lived verifiers. `TtlNonceStore` forgets a nonce `ttl` seconds after
+// recording it, so its size is bounded by the rate of presentations. The ttl must be longer than the nonces are
+// accepted for, for fresh presentations twice the maximum clock skew, otherwise a presentation can be replayed once
+// its nonce is forgotten. `TtlNonceStore::with_capacity` also bounds the count of nonces kept, failing to record
+// more with `PSError::NonceStoreFull` rather than forgetting unexpired ones. Both count lookups, replays and
+// forgotten nonces in `ReplayMetrics`.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::presentation::{
+    verify_fresh_presentation, verify_presentation, FreshnessChallenge, Presentation,
+    PresentationPolicy, VerifiedPresentation,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Counts of a `NonceStore`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayMetrics {
+    /// Calls to `NonceStore::record`
+    pub lookups: u64,
+    /// Lookups of a recorded nonce, i.e. replays
+    pub hits: u64,
+    /// Nonces forgotten after their ttl
+    pub expired: u64,
+}
+
+/// Nonces of the accepted presentations
+pub trait NonceStore {
+    /// Record `nonce` at time `now`, seconds since the Unix epoch. Returns false if it was already recorded.
+    fn record(&mut self, nonce: &[u8], now: u64) -> Result<bool, PSError>;
+    fn metrics(&self) -> ReplayMetrics;
+}
+
+/// `NonceStore` in memory keeping every nonce
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MemoryNonceStore {
+    nonces: HashSet<Vec<u8>>,
+    metrics: ReplayMetrics,
+}
+
+/// `NonceStore` in memory forgetting nonces `ttl` seconds after recording them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TtlNonceStore {
+    ttl: u64,
+    /// Count of unexpired nonces kept at most
+    capacity: Option<usize>,
+    /// Time each nonce expires at
+    expiries: HashMap<Vec<u8>, u64>,
+    /// Nonces in the order they were recorded, with their expiry
+    queue: VecDeque<(u64, Vec<u8>)>,
+    metrics: ReplayMetrics,
+}
+
+/// Verifier of presentations recording their nonces in a `NonceStore`
+#[derive(Clone, Debug, Default)]
+pub struct ReplayProtectedVerifier<S: NonceStore> {
+    pub store: S,
+}
+
+impl ReplayMetrics {
+    fn count(&mut self, recorded: bool) {
+        self.lookups += 1;
+        if !recorded {
+            self.hits += 1;
+        }
+    }
+}
+
+impl MemoryNonceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NonceStore for MemoryNonceStore {
+    fn record(&mut self, nonce: &[u8], _now: u64) -> Result<bool, PSError> {
+        let recorded = self.nonces.insert(nonce.to_vec());
+        self.metrics.count(recorded);
+        Ok(recorded)
+    }
+
+    fn metrics(&self) -> ReplayMetrics {
+        self.metrics
+    }
+}
+
+impl TtlNonceStore {
+    pub fn new(ttl: u64) -> Self {
+        Self {
+            ttl,
+            capacity: None,
+            expiries: HashMap::new(),
+            queue: VecDeque::new(),
+            metrics: ReplayMetrics::default(),
+        }
+    }
+
+    /// Store keeping at most `capacity` unexpired nonces
+    pub fn with_capacity(ttl: u64, capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity),
+            ..Self::new(ttl)
+        }
+    }
+
+    /// Forget `nonce` if it is recorded and not expired at `now`, returning whether it was. For stores of the nonces
+    /// given out and not used yet, where a nonce is taken once when used.
+    pub fn take(&mut self, nonce: &[u8], now: u64) -> bool {
+        self.expire(now);
+        if self.expiries.remove(nonce).is_none() {
+            return false;
+        }
+        self.queue.retain(|(_, n)| n.as_slice() != nonce);
+        true
+    }
+
+    /// Count of nonces recorded and not yet forgotten
+    pub fn len(&self) -> usize {
+        self.expiries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expiries.is_empty()
+    }
+
+    /// Forget the nonces expired at `now`
+    fn expire(&mut self, now: u64) {
+        while let Some((expiry, _)) = self.queue.front() {
+            if *expiry > now {
+                break;
+            }
+            let (expiry, nonce) = self.queue.pop_front().expect("Queue is not empty");
+            // A nonce recorded again after expiring has a later expiry in the map
+            if self.expiries.get(&nonce) == Some(&expiry) {
+                self.expiries.remove(&nonce);
+                self.metrics.expired += 1;
+            }
+        }
+    }
+}
+
+impl NonceStore for TtlNonceStore {
+    fn record(&mut self, nonce: &[u8], now: u64) -> Result<bool, PSError> {
+        self.expire(now);
+        let recorded = match self.expiries.get(nonce) {
+            Some(expiry) if *expiry > now => false,
+            _ => {
+                if let Some(capacity) = self.capacity {
+                    if self.expiries.len() >= capacity {
+                        return Err(PSError::NonceStoreFull { capacity });
+                    }
+                }
+                let expiry = now.saturating_add(self.ttl);
+                self.expiries.insert(nonce.to_vec(), expiry);
+                self.queue.push_back((expiry, nonce.to_vec()));
+                true
+            }
+        };
+        self.metrics.count(recorded);
+        Ok(recorded)
+    }
+
+    fn metrics(&self) -> ReplayMetrics {
+        self.metrics
+    }
+}
+
+impl<S: NonceStore> ReplayProtectedVerifier<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Verify like `presentation::verify_presentation` and record `nonce` at `now`. Fails with
+    /// `PSError::ReplayedNonce` if a presentation with `nonce` was accepted before.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_presentation(
+        &mut self,
+        presentation: &Presentation,
+        issuer: &Verkey,
+        params: &Params,
+        challenge: &FieldElement,
+        nonce: &[u8],
+        policy: &PresentationPolicy,
+        now: u64,
+    ) -> Result<VerifiedPresentation, PSError> {
+        let verified = verify_presentation(presentation, issuer, params, challenge, nonce, policy)?;
+        self.record(nonce, now)?;
+        Ok(verified)
+    }
+
+    /// Verify like `presentation::verify_fresh_presentation` and record the nonce of `freshness` at `now`. Fails
+    /// with `PSError::ReplayedNonce` if a presentation answering the same nonce was accepted before.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_fresh_presentation(
+        &mut self,
+        presentation: &Presentation,
+        issuer: &Verkey,
+        params: &Params,
+        freshness: &FreshnessChallenge,
+        policy: &PresentationPolicy,
+        now: u64,
+        max_clock_skew: u64,
+    ) -> Result<VerifiedPresentation, PSError> {
+        let verified = verify_fresh_presentation(
+            presentation,
+            issuer,
+            params,
+            freshness,
+            policy,
+            now,
+            max_clock_skew,
+        )?;
+        self.record(&freshness.nonce, now)?;
+        Ok(verified)
+    }
+
+    fn record(&mut self, nonce: &[u8], now: u64) -> Result<(), PSError> {
+        if !self.store.record(nonce, now)? {
+            return Err(PSError::ReplayedNonce);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use crate::presentation_builder::PresentationBuilder;
+    use crate::signature::Signature;
+
+    #[test]
+    fn test_replay_protection() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let msgs = vec![
+            FieldElement::random(),
+            FieldElement::random(),
+            FieldElement::random(),
+        ];
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        let policy = PresentationPolicy::transferable();
+        let now = 1_600_000_000;
+        let present = |freshness: &FreshnessChallenge| {
+            PresentationBuilder::new(&sig, msgs.clone(), &vk, &params)
+                .unwrap()
+                .disclose(&[1])
+                .unwrap()
+                .bind_context(freshness.clone())
+                .unwrap()
+                .finish()
+                .unwrap()
+        };
+
+        let mut verifier = ReplayProtectedVerifier::new(MemoryNonceStore::new());
+        let freshness = FreshnessChallenge::new(now);
+        let presentation = present(&freshness);
+        // An invalid presentation does not use up the nonce
+        let mut tampered = presentation.clone();
+        tampered.revealed_msgs.insert(1, FieldElement::random());
+        assert!(verifier
+            .verify_fresh_presentation(&tampered, &vk, &params, &freshness, &policy, now, 60)
+            .is_err());
+        assert!(verifier
+            .verify_fresh_presentation(&presentation, &vk, &params, &freshness, &policy, now, 60)
+            .is_ok());
+        match verifier.verify_fresh_presentation(
+            &presentation,
+            &vk,
+            &params,
+            &freshness,
+            &policy,
+            now + 1,
+            60,
+        ) {
+            Err(PSError::ReplayedNonce) => (),
+            r => panic!("Expected ReplayedNonce but got {:?}", r),
+        }
+        let other = FreshnessChallenge::new(now);
+        assert!(verifier
+            .verify_fresh_presentation(&present(&other), &vk, &params, &other, &policy, now, 60)
+            .is_ok());
+        assert_eq!(
+            verifier.store.metrics(),
+            ReplayMetrics {
+                lookups: 3,
+                hits: 1,
+                expired: 0
+            }
+        );
+
+        let mut store = TtlNonceStore::new(120);
+        assert!(store.record(b"a", now).unwrap());
+        assert!(store.record(b"b", now + 60).unwrap());
+        assert!(!store.record(b"a", now + 119).unwrap());
+        assert_eq!(store.len(), 2);
+        // a is forgotten after its ttl, b is not yet
+        assert!(store.record(b"a", now + 120).unwrap());
+        assert!(!store.record(b"b", now + 120).unwrap());
+        assert_eq!(store.len(), 2);
+        assert!(store.record(b"c", now + 1000).unwrap());
+        assert_eq!(store.len(), 1);
+        assert!(store.take(b"c", now + 1001));
+        assert!(!store.take(b"c", now + 1001));
+        assert!(store.is_empty());
+        assert_eq!(
+            store.metrics(),
+            ReplayMetrics {
+                lookups: 6,
+                hits: 2,
+                expired: 3
+            }
+        );
+
+        // A full store refuses new nonces until some expire or are taken
+        let mut store = TtlNonceStore::with_capacity(120, 2);
+        assert!(store.record(b"a", now).unwrap());
+        assert!(store.record(b"b", now).unwrap());
+        match store.record(b"c", now) {
+            Err(PSError::NonceStoreFull { capacity }) => assert_eq!(capacity, 2),
+            r => panic!("Expected NonceStoreFull but got {:?}", r),
+        }
+        assert!(!store.record(b"a", now).unwrap());
+        assert!(store.take(b"a", now));
+        assert!(store.record(b"c", now).unwrap());
+        assert!(store.record(b"d", now + 120).unwrap());
+        assert!(!store.take(b"b", now + 120));
+    }
+}
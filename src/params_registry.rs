@@ -0,0 +1,159 @@
+// Named parameter sets. `Params::new` derives the generators from any label, so two deployments agree on
+// parameters only if they agree on the label byte for byte. The well-known sets give fixed labels a name:
+// `Params::well_known(RSS_BLS12381_V1)` is `Params::new(b"ps_sig/params/RSS-BLS12381-V1")`, for each name in
+// `WELL_KNOWN` the label `ps_sig/params/` || name. From the label, the domain separation tags of the hashes to the
+// curve are label || " : g" for g, label || " : g_tilde" for g_tilde and label || " : label" for `label_hash`, each
+// hashed with `from_msg_hash` of amcl_wrapper for BLS12-381. g is in the group of signatures and g_tilde in the
+// group of verkeys of the build, so a set is the same across builds with the same one of `SignatureG1` and
+// `SignatureG2`. A new version of a set gets a new name, existing ones never change.
+// `ParamsRegistry` starts with the well-known sets and applications register their own names for their labels,
+// e.g. an ecosystem publishing its parameters. Names of the registry cannot be registered again, so a registered
+// name always means the same generators.
+
+use crate::errors::PSError;
+use crate::keys::Params;
+use std::collections::HashMap;
+
+/// Parameters for the redactable signature scheme on BLS12-381
+pub const RSS_BLS12381_V1: &str = "RSS-BLS12381-V1";
+/// Parameters for the schemes from the 2016 and 2018 papers on BLS12-381
+pub const PS_BLS12381_V1: &str = "PS-BLS12381-V1";
+
+/// Names of the well-known parameter sets with their labels
+pub const WELL_KNOWN: &[(&str, &[u8])] = &[
+    (RSS_BLS12381_V1, b"ps_sig/params/RSS-BLS12381-V1"),
+    (PS_BLS12381_V1, b"ps_sig/params/PS-BLS12381-V1"),
+];
+
+/// Label of the well-known parameter set `name`
+pub fn well_known_label(name: &str) -> Option<&'static [u8]> {
+    WELL_KNOWN
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, label)| *label)
+}
+
+/// Names of parameter sets with their labels
+#[derive(Clone, Debug)]
+pub struct ParamsRegistry {
+    labels: HashMap<String, Vec<u8>>,
+}
+
+impl Default for ParamsRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParamsRegistry {
+    /// Registry with the well-known parameter sets
+    pub fn new() -> Self {
+        let labels = WELL_KNOWN
+            .iter()
+            .map(|(name, label)| (name.to_string(), label.to_vec()))
+            .collect();
+        Self { labels }
+    }
+
+    /// Registry without any parameter set
+    pub fn empty() -> Self {
+        Self {
+            labels: HashMap::new(),
+        }
+    }
+
+    /// Register `name` for the params of `label`. Fails if `name` is already registered.
+    pub fn register(&mut self, name: &str, label: &[u8]) -> Result<(), PSError> {
+        if self.supports(name) {
+            return Err(PSError::GeneralError {
+                msg: format!("Params {} are already registered", name),
+            });
+        }
+        self.labels.insert(name.to_string(), label.to_vec());
+        Ok(())
+    }
+
+    pub fn supports(&self, name: &str) -> bool {
+        self.labels.contains_key(name)
+    }
+
+    /// Names of the registered parameter sets in increasing order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.labels.keys().map(|name| name.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Label of the params of `name`, `None` if it is not registered
+    pub fn label(&self, name: &str) -> Option<&[u8]> {
+        self.labels.get(name).map(|label| label.as_slice())
+    }
+
+    /// Names registered for `label`, e.g. to name the params of an `algorithm::Artifact`, in increasing order
+    pub fn names_of(&self, label: &[u8]) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .labels
+            .iter()
+            .filter(|(_, l)| l.as_slice() == label)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Params of `name`. Fails with `PSError::UnknownParams` if it is not registered.
+    pub fn params(&self, name: &str) -> Result<Params, PSError> {
+        self.label(name)
+            .map(Params::new)
+            .ok_or_else(|| PSError::UnknownParams {
+                name: name.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, rsskeygen};
+
+    #[test]
+    fn test_params_registry() {
+        // Fixed labels, so the generators are the same for every caller
+        let params = Params::well_known(RSS_BLS12381_V1).unwrap();
+        assert_eq!(params, Params::new(b"ps_sig/params/RSS-BLS12381-V1"));
+        assert_eq!(params, Params::well_known(RSS_BLS12381_V1).unwrap());
+        assert_ne!(params, Params::well_known(PS_BLS12381_V1).unwrap());
+        match Params::well_known("RSS-BLS12381-V0") {
+            Err(PSError::UnknownParams { name }) => assert_eq!(name, "RSS-BLS12381-V0"),
+            r => panic!("Expected UnknownParams but got {:?}", r),
+        }
+        assert!(rsskeygen(3, &params).is_ok());
+
+        let mut registry = ParamsRegistry::new();
+        assert_eq!(registry.names(), vec![PS_BLS12381_V1, RSS_BLS12381_V1]);
+        assert_eq!(
+            registry.params(PS_BLS12381_V1).unwrap(),
+            Params::well_known(PS_BLS12381_V1).unwrap()
+        );
+        registry.register("ACME-V1", b"acme params").unwrap();
+        // A registered name cannot be registered again, for another label or not
+        assert!(registry.register("ACME-V1", b"other").is_err());
+        assert!(registry.register(RSS_BLS12381_V1, b"other").is_err());
+        registry.register("ACME-LATEST", b"acme params").unwrap();
+        assert_eq!(
+            registry.names_of(b"acme params"),
+            vec!["ACME-LATEST", "ACME-V1"]
+        );
+        let acme = registry.params("ACME-V1").unwrap();
+        assert_eq!(acme, Params::new(b"acme params"));
+        let (_, vk) = keygen(2, &acme).unwrap();
+        assert!(vk
+            .check_params(&registry.params("ACME-LATEST").unwrap())
+            .is_ok());
+        assert!(vk.check_params(&params).is_err());
+
+        let empty = ParamsRegistry::empty();
+        assert!(!empty.supports(RSS_BLS12381_V1));
+        assert!(empty.params(RSS_BLS12381_V1).is_err());
+    }
+}
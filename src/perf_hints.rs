@@ -0,0 +1,173 @@
+// Choice of the precomputation for fixed base scalar multiplications. Contexts and batch operations keep a
+// `FixedBaseTable` of multiples of each base they multiply, which turns a multiplication into an addition per
+// window of the scalar but costs (2^w - 1) additions per window of w bits to build and as much memory. A table
+// pays off only when the base is multiplied often enough, so `PerfHints` takes the expected count of
+// multiplications per base and picks the window minimizing the cost of building the table and of the
+// multiplications, or no table when multiplying without one is cheaper, e.g. for a context used once.
+// Costs are counted in group additions, a doubling counting as an addition: a multiplication without table takes
+// about as long as `PLAIN_MUL_COST` of them, measured with amcl on G1, its doublings being cheaper. The tables of
+// all bases must also fit in `max_table_bytes`, so for keys of many messages, whose tables would take gigabytes
+// with wide windows, the window shrinks with the count of messages.
+
+use crate::curve::CurveOrder;
+use crate::pok_sig::MAX_WINDOW_SIZE;
+use crate::{SignatureGroup, VerkeyGroup};
+use std::mem::size_of;
+
+/// Cost of a multiplication without table, in group additions
+pub const PLAIN_MUL_COST: usize = 250;
+/// Memory for the tables of `PerfHints::new`, 64 MiB
+pub const DEFAULT_MAX_TABLE_BYTES: usize = 64 << 20;
+
+/// Expected use of the tables of a context
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PerfHints {
+    /// Count of bases, the messages of the key
+    pub msg_count: usize,
+    /// Count of multiplications expected per base over the lifetime of the tables
+    pub expected_reuse: usize,
+    /// Memory the tables of all bases may take
+    pub max_table_bytes: usize,
+}
+
+/// Windows of `window` bits in a scalar
+fn window_count(window: usize) -> usize {
+    CurveOrder.nbits().div_ceil(window)
+}
+
+/// Size in memory of the table of a base with windows of `window` bits
+fn table_bytes(window: usize) -> usize {
+    let elem_size = size_of::<SignatureGroup>().max(size_of::<VerkeyGroup>());
+    window_count(window) * ((1 << window) - 1) * elem_size
+}
+
+impl PerfHints {
+    pub fn new(msg_count: usize, expected_reuse: usize) -> Self {
+        Self {
+            msg_count,
+            expected_reuse,
+            max_table_bytes: DEFAULT_MAX_TABLE_BYTES,
+        }
+    }
+
+    pub fn with_max_table_bytes(mut self, max_table_bytes: usize) -> Self {
+        self.max_table_bytes = max_table_bytes;
+        self
+    }
+
+    /// Cost in group additions of `expected_reuse` multiplications of a base with windows of `window` bits,
+    /// building the table included, or without table for window 0
+    pub fn cost(&self, window: usize) -> usize {
+        if window == 0 {
+            return self.expected_reuse.saturating_mul(PLAIN_MUL_COST);
+        }
+        let windows = window_count(window);
+        let build = windows * ((1 << window) - 1) + windows * window;
+        build.saturating_add(self.expected_reuse.saturating_mul(windows))
+    }
+
+    /// Size in bits of the windows of the tables, 0 for no tables
+    pub fn window_size(&self) -> usize {
+        let bases = self.msg_count.max(1);
+        (1..=MAX_WINDOW_SIZE)
+            .filter(|w| table_bytes(*w).saturating_mul(bases) <= self.max_table_bytes)
+            .fold(0, |best, w| {
+                if self.cost(w) < self.cost(best) {
+                    w
+                } else {
+                    best
+                }
+            })
+    }
+
+    /// Whether building tables pays off
+    pub fn builds_tables(&self) -> bool {
+        self.window_size() != 0
+    }
+
+    /// Memory the tables of all bases take with the window of `window_size`
+    pub fn table_bytes(&self) -> usize {
+        match self.window_size() {
+            0 => 0,
+            w => table_bytes(w) * self.msg_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{SignContext, VerifyContext};
+    use crate::curve::{FieldElement, FieldElementVector, GroupElement};
+    use crate::keys::{keygen, rsskeygen, Params};
+    use crate::pok_sig::FixedBaseTable;
+    use crate::rss_signature::{redact_many, PreparedPKrss, RSSignature};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_perf_hints() {
+        // Used once or twice, tables do not pay off
+        assert_eq!(PerfHints::new(5, 1).window_size(), 0);
+        assert!(!PerfHints::new(5, 2).builds_tables());
+        assert_eq!(PerfHints::new(5, 2).table_bytes(), 0);
+        // The window widens with the reuse
+        let windows: Vec<usize> = [10, 100, 10_000, 1_000_000]
+            .iter()
+            .map(|r| PerfHints::new(5, *r).window_size())
+            .collect();
+        assert!(windows[0] > 0);
+        assert!(windows.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(windows[3], MAX_WINDOW_SIZE);
+        // and shrinks with the count of messages to fit the memory
+        let many = PerfHints::new(100_000, 1_000_000);
+        assert!(many.window_size() < MAX_WINDOW_SIZE);
+        assert!(many.table_bytes() <= DEFAULT_MAX_TABLE_BYTES);
+        assert_eq!(many.with_max_table_bytes(0).window_size(), 0);
+        let hints = PerfHints::new(5, 100);
+        assert!(hints.cost(hints.window_size()) <= hints.cost(0));
+
+        // Contexts with the tables of the hints, or none, verify alike
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let msgs = FieldElementVector::random(3);
+        for reuse in &[1, 1000] {
+            let hints = PerfHints::new(3, *reuse);
+            let sig = SignContext::with_hints(&sk, &params, &hints)
+                .sign(msgs.as_slice())
+                .unwrap();
+            let ctx = VerifyContext::with_hints(&vk, &params, &hints).unwrap();
+            assert!(ctx.verify(&sig, msgs.as_slice()).unwrap());
+            assert!(sig.verify(msgs.as_slice().to_vec(), &vk, &params).unwrap());
+        }
+        let (rss_sk, pk) = rsskeygen(3, &params).unwrap();
+        let rss_sig = RSSignature::new(msgs.as_slice(), &rss_sk, &params).unwrap();
+        let disclosed: HashMap<usize, FieldElement> =
+            vec![(1, msgs[1].clone())].into_iter().collect();
+        let derived = redact_many(
+            &rss_sig,
+            msgs.as_slice(),
+            &[vec![1].into_iter().collect()],
+            &pk,
+        )
+        .unwrap();
+        let prepared = PreparedPKrss::with_hints(&pk, &PerfHints::new(3, 1)).unwrap();
+        assert!(derived[0].verify_prepared(&disclosed, &prepared).unwrap());
+
+        // Tables of every window multiply alike
+        let base = SignatureGroup::random();
+        let scalars = vec![
+            FieldElement::random(),
+            FieldElement::zero(),
+            FieldElement::one(),
+            -FieldElement::one(),
+        ];
+        for w in 0..=MAX_WINDOW_SIZE {
+            let table = FixedBaseTable::with_window(&base, w);
+            for s in &scalars {
+                let mut accum = SignatureGroup::identity();
+                table.mul_add(s, &mut accum);
+                assert_eq!(accum, &base * s, "window {}", w);
+            }
+        }
+    }
+}
@@ -0,0 +1,318 @@
+// Proof of knowledge of signature where some hidden messages are the openings of Pedersen commitments given to
+// the verifier, so that another protocol (e.g. a payment system) can consume the committed value without it being
+// revealed. The proof of knowledge of signature and the proof of knowledge of the opening of each commitment
+// g^m * h^r use the same blinding for m and the same challenge, so the responses for m must be equal in both.
+
+use crate::challenge_domain::POK_SIG_WITH_COMMITMENTS;
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::{
+    PoKOfSignature, PoKOfSignatureProof, ProofSignatureGroup, ProverCommittedSignatureGroup,
+    ProverCommittingSignatureGroup,
+};
+use crate::scalar::Blinding;
+use crate::signature::Signature;
+use crate::SignatureGroup;
+use std::collections::{HashMap, HashSet};
+
+/// Pedersen commitment g^m * h^r to a message m
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PedersenCommitment {
+    pub g: SignatureGroup,
+    pub h: SignatureGroup,
+    pub commitment: SignatureGroup,
+}
+
+/// How each message of the signature is presented
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum HiddenMessageMode {
+    Hidden,
+    Revealed,
+    /// Hidden but equal to the opening of the commitment
+    Committed(Box<PedersenCommitment>),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoKOfSignatureWithCommitments {
+    pub pok: PoKOfSignature,
+    /// Index of the message, proof of knowledge of the opening of its commitment and the opening `(m, r)`
+    pub committed: Vec<(usize, ProverCommittedSignatureGroup, [FieldElement; 2])>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoKOfSignatureWithCommitmentsProof {
+    pub proof: PoKOfSignatureProof,
+    /// Index of the message and proof of knowledge of the opening of its commitment, in increasing order of index
+    pub committed: Vec<(usize, ProofSignatureGroup)>,
+}
+
+impl PedersenCommitment {
+    pub fn new(
+        g: &SignatureGroup,
+        h: &SignatureGroup,
+        message: &FieldElement,
+        randomness: &FieldElement,
+    ) -> Self {
+        Self {
+            g: g.clone(),
+            h: h.clone(),
+            commitment: g.binary_scalar_mul(h, message, randomness),
+        }
+    }
+}
+
+impl PoKOfSignatureWithCommitments {
+    /// `modes` has the mode of each message and `randomness` the randomness r of the commitment of each
+    /// message in `Committed` mode, keyed by the index of the message
+    pub fn init(
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        messages: Vec<FieldElement>,
+        modes: &[HiddenMessageMode],
+        randomness: &HashMap<usize, FieldElement>,
+    ) -> Result<Self, PSError> {
+        if modes.len() != messages.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: messages.len(),
+                given: modes.len(),
+            });
+        }
+        let mut revealed_msg_indices = HashSet::new();
+        // Blindings of the hidden messages in increasing order of index
+        let mut blindings = vec![];
+        let mut committed = vec![];
+        for (i, mode) in modes.iter().enumerate() {
+            match mode {
+                HiddenMessageMode::Revealed => {
+                    revealed_msg_indices.insert(i);
+                }
+                HiddenMessageMode::Hidden => blindings.push(Blinding::random()),
+                HiddenMessageMode::Committed(comm) => {
+                    let r = randomness.get(&i).ok_or_else(|| PSError::GeneralError {
+                        msg: format!("No randomness for commitment of message {}", i),
+                    })?;
+                    if PedersenCommitment::new(&comm.g, &comm.h, &messages[i], r) != **comm {
+                        return Err(PSError::GeneralError {
+                            msg: format!("Commitment is not to message {}", i),
+                        });
+                    }
+                    let blinding = Blinding::random();
+                    let mut committing = ProverCommittingSignatureGroup::new();
+                    committing.commit(&comm.g, Some(blinding.as_field_element()));
+                    committing.commit(&comm.h, None);
+                    committed.push((i, committing.finish(), [messages[i].clone(), r.clone()]));
+                    blindings.push(blinding);
+                }
+            }
+        }
+        let pok = PoKOfSignature::init(
+            sig,
+            vk,
+            params,
+            messages,
+            Some(blindings.as_slice()),
+            revealed_msg_indices,
+        )?;
+        Ok(Self { pok, committed })
+    }
+
+    /// Return byte representation of public elements so they can be used for challenge computation
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = POK_SIG_WITH_COMMITMENTS.tag();
+        bytes.append(&mut self.pok.to_bytes());
+        for (_, c, _) in &self.committed {
+            bytes.append(&mut c.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn gen_proof(
+        self,
+        challenge: &FieldElement,
+    ) -> Result<PoKOfSignatureWithCommitmentsProof, PSError> {
+        let proof = self.pok.gen_proof(challenge)?;
+        let mut committed = vec![];
+        for (i, c, opening) in self.committed {
+            committed.push((i, c.gen_proof(challenge, &opening)?));
+        }
+        Ok(PoKOfSignatureWithCommitmentsProof { proof, committed })
+    }
+}
+
+impl PoKOfSignatureWithCommitmentsProof {
+    /// Return bytes that need to be hashed for generating challenge
+    pub fn get_bytes_for_challenge(
+        &self,
+        modes: &[HiddenMessageMode],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Vec<u8> {
+        let mut bytes = POK_SIG_WITH_COMMITMENTS.tag();
+        bytes.append(
+            &mut self
+                .proof
+                .get_bytes_for_challenge(revealed_indices(modes), vk, params),
+        );
+        for (i, p) in &self.committed {
+            if let Some(HiddenMessageMode::Committed(comm)) = modes.get(*i) {
+                bytes.append(&mut comm.g.to_bytes());
+                bytes.append(&mut comm.h.to_bytes());
+            }
+            bytes.append(&mut p.commitment.to_bytes());
+        }
+        bytes
+    }
+
+    /// Verify the proof of knowledge of signature and that the message at each `Committed` index is the opening
+    /// of its commitment. `revealed_msgs` are the messages at the `Revealed` indices.
+    pub fn verify(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        modes: &[HiddenMessageMode],
+        revealed_msgs: HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        if modes.len() != vk.supported_messages() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: vk.supported_messages(),
+                given: modes.len(),
+            });
+        }
+        let revealed = revealed_indices(modes);
+        if revealed.len() != revealed_msgs.len()
+            || !revealed_msgs.keys().all(|i| revealed.contains(i))
+        {
+            return Ok(false);
+        }
+
+        let committed_modes: Vec<_> = modes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, m)| match m {
+                HiddenMessageMode::Committed(c) => Some((i, c)),
+                _ => None,
+            })
+            .collect();
+        if committed_modes.len() != self.committed.len() {
+            return Ok(false);
+        }
+        for ((i, comm), (j, p)) in committed_modes.into_iter().zip(&self.committed) {
+            if i != *j {
+                return Ok(false);
+            }
+            if !p.verify(
+                &[comm.g.clone(), comm.h.clone()],
+                &comm.commitment,
+                challenge,
+            )? {
+                return Ok(false);
+            }
+            // Position of the message among the hidden messages
+            let hidden_idx = i - revealed.iter().filter(|r| **r < i).count();
+            if self.proof.get_resp_for_message(hidden_idx)? != p.responses[0] {
+                return Ok(false);
+            }
+        }
+        self.proof.verify(vk, params, revealed_msgs, challenge)
+    }
+}
+
+fn revealed_indices(modes: &[HiddenMessageMode]) -> HashSet<usize> {
+    modes
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| **m == HiddenMessageMode::Revealed)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_PoK_with_committed_messages() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        // Bases of the other protocol
+        let g = SignatureGroup::from_msg_hash(b"payment g");
+        let h = SignatureGroup::from_msg_hash(b"payment h");
+        let r = FieldElement::random();
+        let comm = PedersenCommitment::new(&g, &h, &msgs[3], &r);
+        let mut randomness = HashMap::new();
+        randomness.insert(3, r.clone());
+
+        let modes = vec![
+            HiddenMessageMode::Revealed,
+            HiddenMessageMode::Hidden,
+            HiddenMessageMode::Revealed,
+            HiddenMessageMode::Committed(Box::new(comm.clone())),
+            HiddenMessageMode::Hidden,
+        ];
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, msgs[0].clone());
+        revealed_msgs.insert(2, msgs[2].clone());
+
+        let pok = PoKOfSignatureWithCommitments::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone().into(),
+            &modes,
+            &randomness,
+        )
+        .unwrap();
+        let chal_prover = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal_prover).unwrap();
+
+        let chal_verifier =
+            FieldElement::from_msg_hash(&proof.get_bytes_for_challenge(&modes, &vk, &params));
+        assert_eq!(chal_prover, chal_verifier);
+        assert!(proof
+            .verify(&vk, &params, &modes, revealed_msgs.clone(), &chal_verifier)
+            .unwrap());
+
+        // Commitment to another value
+        let other_comm = PedersenCommitment::new(&g, &h, &FieldElement::random(), &r);
+        let mut other_modes = modes.clone();
+        other_modes[3] = HiddenMessageMode::Committed(Box::new(other_comm.clone()));
+        assert!(!proof
+            .verify(
+                &vk,
+                &params,
+                &other_modes,
+                revealed_msgs.clone(),
+                &chal_verifier
+            )
+            .unwrap());
+
+        // Commitment for another message
+        let mut moved_modes = modes.clone();
+        moved_modes[3] = HiddenMessageMode::Hidden;
+        moved_modes[4] = HiddenMessageMode::Committed(Box::new(comm));
+        assert!(!proof
+            .verify(&vk, &params, &moved_modes, revealed_msgs, &chal_verifier)
+            .unwrap());
+
+        // Prover cannot use a commitment it can't open to the message
+        other_modes[3] = HiddenMessageMode::Committed(Box::new(other_comm));
+        assert!(PoKOfSignatureWithCommitments::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.into(),
+            &other_modes,
+            &randomness
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,1173 @@
+// Proof of knowledge of signature, section 6.2 of the 2016 paper
+
+use crate::challenge_domain::POK_SIG;
+use crate::curve::{
+    CurveGroup, CurveOrder, FieldElement, FieldElementVector, FieldElement_SIZE, GroupElement,
+    GroupElementVector,
+};
+use crate::errors::PSError;
+use crate::keys::{DeserializeLimits, Params, Verkey};
+use crate::msm::multi_scalar_mul_var_time;
+use crate::scalar::{Blinding, MessageScalar};
+use crate::signature::{
+    check_subgroup, group_elem_from_bytes, identity_report, in_group, non_identity_in_group,
+    Signature,
+};
+use crate::verification_report::{Check, VerificationReport};
+use crate::{
+    ate_2_pairing, ate_pairing, SignatureGroup, SignatureGroupVec, VerkeyGroup, VerkeyGroupVec,
+    SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE,
+};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+impl_PoK_VC!(
+    ProverCommittingSignatureGroup,
+    ProverCommittedSignatureGroup,
+    ProofSignatureGroup,
+    SignatureGroup,
+    SignatureGroupVec
+);
+
+impl_PoK_VC!(
+    ProverCommittingVerkeyGroup,
+    ProverCommittedVerkeyGroup,
+    ProofVerkeyGroup,
+    VerkeyGroup,
+    VerkeyGroupVec
+);
+
+/*
+As section 6.2 describes, for proving knowledge of a signature, the signature sigma is first randomized and also
+transformed into a sequential aggregate signature with extra message t for public key g_tilde (and secret key 1).
+1. Say the signature sigma is transformed to sigma_prime = (sigma_prime_1, sigma_prime_2) like step 1 in 6.2
+2. The prover then sends sigma_prime and the value J = X_tilde * Y_tilde_1^m1 * Y_tilde_2^m2 * ..... * g_tilde^t and the proof J is formed correctly.
+The verifier now checks whether e(sigma_prime_1, J) == e(sigma_prime_2, g_tilde). Since X_tilde is known,
+the verifier can send following a modified value J' where J' = Y_tilde_1^m_1 * Y_tilde_2^m_2 * ..... * g_tilde^t with the proof of knowledge of elements of J'.
+The verifier will then check the pairing e(sigma_prime_1, J'*X_tilde) == e(sigma_prime_2, g_tilde).
+
+To reveal some of the messages from the signature but not all, in above protocol, construct J to be of the hidden values only, the verifier will
+then add the revealed values (raise the respective generators to get a commitment) to J and perform the pairing check.
+*/
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoKOfSignature {
+    pub secrets: FieldElementVector,
+    pub sig: Signature,
+    pub J: VerkeyGroup,
+    pub pok_vc: ProverCommittedVerkeyGroup,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoKOfSignatureProof {
+    pub sig: Signature,
+    pub J: VerkeyGroup,
+    pub proof_vc: ProofVerkeyGroup,
+}
+
+/// Size in bits of the windows in which scalars are split for fixed base multiplication, unless chosen with
+/// `perf_hints::PerfHints`
+pub(crate) const WINDOW_SIZE: usize = 4;
+/// Largest size of the windows, so that a window is within 2 bytes of the scalar
+pub(crate) const MAX_WINDOW_SIZE: usize = 8;
+
+fn default_window() -> usize {
+    WINDOW_SIZE
+}
+
+/// Multiples of a fixed base for each window of a scalar, `table[k][d-1]` is `base * d * 2^{window*k}`,
+/// so that multiplying by a scalar only needs an addition per window and no doublings. With window 0, there is no
+/// table and `table[0][0]` is the base, multiplied by each scalar. Deserialization checks that the table is the one
+/// of its base, so that a table from outside can neither index out of bounds nor multiply by another base.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(
+    try_from = "FixedBaseTableFields<G>",
+    bound(deserialize = "G: GroupElement + PartialEq + serde::Deserialize<'de>")
+)]
+pub(crate) struct FixedBaseTable<G> {
+    window: usize,
+    table: Vec<Vec<G>>,
+}
+
+/// Fields of `FixedBaseTable` as deserialized, before checking the window and the table
+#[derive(Deserialize)]
+pub(crate) struct FixedBaseTableFields<G> {
+    #[serde(default = "default_window")]
+    window: usize,
+    table: Vec<Vec<G>>,
+}
+
+impl<G: GroupElement + PartialEq> TryFrom<FixedBaseTableFields<G>> for FixedBaseTable<G> {
+    type Error = PSError;
+
+    fn try_from(f: FixedBaseTableFields<G>) -> Result<Self, PSError> {
+        if f.window > MAX_WINDOW_SIZE {
+            return Err(PSError::DeserializationError {
+                msg: format!(
+                    "Window of {} bits is larger than {}",
+                    f.window, MAX_WINDOW_SIZE
+                ),
+            });
+        }
+        let (window_count, multiple_count) = table_shape(f.window);
+        if f.table.len() != window_count || f.table.iter().any(|m| m.len() != multiple_count) {
+            return Err(PSError::DeserializationError {
+                msg: format!("Table does not have the shape of window {}", f.window),
+            });
+        }
+        let base = &f.table[0][0];
+        check_subgroup(&[base])?;
+        let table = Self::with_window(base, f.window);
+        if table.table != f.table {
+            return Err(PSError::DeserializationError {
+                msg: String::from("Table is not the one of its base"),
+            });
+        }
+        Ok(table)
+    }
+}
+
+/// Count of windows and of multiples per window of a table of `window` bits
+fn table_shape(window: usize) -> (usize, usize) {
+    if window == 0 {
+        return (1, 1);
+    }
+    (CurveOrder.nbits().div_ceil(window), (1 << window) - 1)
+}
+
+impl<G: GroupElement> FixedBaseTable<G> {
+    pub(crate) fn new(base: &G) -> Self {
+        Self::with_window(base, WINDOW_SIZE)
+    }
+
+    /// Table of windows of `window` bits, at most `MAX_WINDOW_SIZE`, or the base only for window 0
+    pub(crate) fn with_window(base: &G, window: usize) -> Self {
+        let window = window.min(MAX_WINDOW_SIZE);
+        if window == 0 {
+            return Self {
+                window,
+                table: vec![vec![base.clone()]],
+            };
+        }
+        let (window_count, multiple_count) = table_shape(window);
+        let mut table = Vec::with_capacity(window_count);
+        let mut b = base.clone();
+        for _ in 0..window_count {
+            let mut multiples = Vec::with_capacity(multiple_count);
+            multiples.push(b.clone());
+            for j in 1..multiple_count {
+                let next = multiples[j - 1].plus(&b);
+                multiples.push(next);
+            }
+            for _ in 0..window {
+                b.double_mut();
+            }
+            table.push(multiples);
+        }
+        Self { window, table }
+    }
+
+    pub(crate) fn base(&self) -> &G {
+        &self.table[0][0]
+    }
+
+    /// Add `base * scalar` to `accum`. Reads the windows from the bytes of the scalar on the stack so that
+    /// it does not allocate, unless there is no table. The lookups depend on the scalar, so this is for public
+    /// scalars only, see `mul_add_const_time` for secret ones.
+    pub(crate) fn mul_add(&self, scalar: &FieldElement, accum: &mut G) {
+        if self.window == 0 {
+            accum.add_assign_(&self.table[0][0].scalar_mul_const_time(scalar));
+            return;
+        }
+        let bytes = scalar_bytes(scalar);
+        for (k, multiples) in self.table.iter().enumerate() {
+            let d = self.digit(&bytes, k);
+            if d != 0 {
+                accum.add_assign_(&multiples[d - 1]);
+            }
+        }
+    }
+
+    /// Digit of window `k` of the scalar of big endian `bytes`. The window starts at bit `window * k` from the
+    /// least significant one and spans at most 2 bytes.
+    fn digit(&self, bytes: &[u8; FieldElement_SIZE], k: usize) -> usize {
+        let bit = self.window * k;
+        let low = bytes[FieldElement_SIZE - 1 - bit / 8] as usize;
+        let high = match FieldElement_SIZE.checked_sub(2 + bit / 8) {
+            Some(i) => bytes[i] as usize,
+            None => 0,
+        };
+        ((high << 8 | low) >> (bit % 8)) & ((1 << self.window) - 1)
+    }
+}
+
+impl<G: CurveGroup> FixedBaseTable<G> {
+    /// Same as `mul_add` in constant time, for secret scalars: every multiple of each window is read with
+    /// `CurveGroup::select_const_time` and the selected one, the identity for a 0 digit, is added whatever the digit.
+    pub(crate) fn mul_add_const_time(&self, scalar: &FieldElement, accum: &mut G) {
+        if self.window == 0 {
+            accum.add_assign_(&self.table[0][0].scalar_mul_const_time(scalar));
+            return;
+        }
+        let bytes = scalar_bytes(scalar);
+        for (k, multiples) in self.table.iter().enumerate() {
+            accum.add_assign_(&G::select_const_time(multiples, self.digit(&bytes, k)));
+        }
+    }
+}
+
+/// Big endian bytes of the reduced scalar, on the stack
+fn scalar_bytes(scalar: &FieldElement) -> [u8; FieldElement_SIZE] {
+    let mut bytes = [0u8; FieldElement_SIZE];
+    scalar.to_bignum().tobytes(&mut bytes);
+    bytes
+}
+
+/// Sum of `tables[i] * scalars[i]` in constant time, for secret scalars
+pub(crate) fn fixed_base_multi_scalar_mul_const_time<'a, G: CurveGroup + 'a>(
+    tables: impl IntoIterator<Item = &'a FixedBaseTable<G>>,
+    scalars: &[FieldElement],
+) -> G {
+    let mut accum = G::identity();
+    crate::profiling::record_scalar_muls(scalars.len());
+    for (t, s) in tables.into_iter().zip(scalars) {
+        t.mul_add_const_time(s, &mut accum);
+    }
+    accum
+}
+
+/// Prover state that only depends on the signature and keys and not on the messages being revealed.
+/// Creating several proofs of knowledge from the same signature with `PoKPrecomputed::init` avoids
+/// redoing the fixed base multiplications of sigma_1, sigma_2, g_tilde and each Y_tilde_i for every
+/// proof, at the cost of keeping tables of their multiples. The randomizers, hidden messages and blindings are
+/// secret so the tables are read in constant time. Deserialization checks that the tables are those of the verkey
+/// and of g_tilde.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "PoKPrecomputedFields")]
+pub struct PoKPrecomputed {
+    vk: Verkey,
+    g_tilde: VerkeyGroup,
+    sigma_1: FixedBaseTable<SignatureGroup>,
+    sigma_2: FixedBaseTable<SignatureGroup>,
+    g_tilde_table: FixedBaseTable<VerkeyGroup>,
+    Y_tilde_tables: Vec<FixedBaseTable<VerkeyGroup>>,
+}
+
+/// Fields of `PoKPrecomputed` as deserialized, before checking that the tables agree with the verkey
+#[derive(Deserialize)]
+struct PoKPrecomputedFields {
+    vk: Verkey,
+    g_tilde: VerkeyGroup,
+    sigma_1: FixedBaseTable<SignatureGroup>,
+    sigma_2: FixedBaseTable<SignatureGroup>,
+    g_tilde_table: FixedBaseTable<VerkeyGroup>,
+    Y_tilde_tables: Vec<FixedBaseTable<VerkeyGroup>>,
+}
+
+impl TryFrom<PoKPrecomputedFields> for PoKPrecomputed {
+    type Error = PSError;
+
+    fn try_from(f: PoKPrecomputedFields) -> Result<Self, PSError> {
+        if f.Y_tilde_tables.len() != f.vk.Y_tilde.len()
+            || f.Y_tilde_tables
+                .iter()
+                .zip(f.vk.Y_tilde.iter())
+                .any(|(t, Y_tilde)| t.base() != Y_tilde)
+        {
+            return Err(PSError::DeserializationError {
+                msg: String::from("Tables are not those of the Y_tilde of the verkey"),
+            });
+        }
+        if f.g_tilde_table.base() != &f.g_tilde {
+            return Err(PSError::DeserializationError {
+                msg: String::from("Table is not the one of g_tilde"),
+            });
+        }
+        Ok(Self {
+            vk: f.vk,
+            g_tilde: f.g_tilde,
+            sigma_1: f.sigma_1,
+            sigma_2: f.sigma_2,
+            g_tilde_table: f.g_tilde_table,
+            Y_tilde_tables: f.Y_tilde_tables,
+        })
+    }
+}
+
+impl PoKPrecomputed {
+    /// Tables for proofs of `sig`. Fails with `PSError::ParamsMismatch` if `vk` is not of `params`.
+    pub fn new(sig: &Signature, vk: &Verkey, params: &Params) -> Result<Self, PSError> {
+        vk.check_params(params)?;
+        Ok(Self {
+            vk: vk.clone(),
+            g_tilde: params.generator_tilde(),
+            sigma_1: FixedBaseTable::new(&sig.sigma_1),
+            sigma_2: FixedBaseTable::new(&sig.sigma_2),
+            g_tilde_table: FixedBaseTable::new(&params.g_tilde),
+            Y_tilde_tables: vk.Y_tilde.iter().map(FixedBaseTable::new).collect(),
+        })
+    }
+
+    /// Same as `PoKOfSignature::init` with the signature and keys given to `new`
+    pub fn init<M: MessageScalar>(
+        &self,
+        messages: Vec<M>,
+        blindings: Option<&[Blinding]>,
+        revealed_msg_indices: HashSet<usize>,
+    ) -> Result<PoKOfSignature, PSError> {
+        let messages = M::into_field_elements(messages);
+        Signature::check_verkey_and_messages_compat(messages.as_slice(), &self.vk)?;
+        PoKOfSignature::validate_revealed_indices(messages.as_slice(), &revealed_msg_indices)?;
+
+        let hidden_msg_count = messages.len() - revealed_msg_indices.len();
+        let mut blindings = PoKOfSignature::blindings_for_hidden_msgs(blindings, hidden_msg_count)?;
+
+        let r = FieldElement::random();
+        let t = FieldElement::random();
+
+        // Transform signature to an aggregate signature on (messages, t)
+        // sigma_prime_1 = sigma_1^r, sigma_prime_2 = sigma_1^{t*r} * sigma_2^r
+        crate::profiling::record_scalar_muls(3);
+        let mut sigma_prime_1 = SignatureGroup::identity();
+        self.sigma_1.mul_add_const_time(&r, &mut sigma_prime_1);
+        let mut sigma_prime_2 = SignatureGroup::identity();
+        self.sigma_1
+            .mul_add_const_time(&(&t * &r), &mut sigma_prime_2);
+        self.sigma_2.mul_add_const_time(&r, &mut sigma_prime_2);
+
+        // +1 for `t`
+        let mut bases = VerkeyGroupVec::with_capacity(hidden_msg_count + 1);
+        let mut tables = Vec::with_capacity(hidden_msg_count + 1);
+        let mut exponents = FieldElementVector::with_capacity(hidden_msg_count + 1);
+        bases.push(self.g_tilde.clone());
+        tables.push(&self.g_tilde_table);
+        exponents.push(t);
+        for (i, msg) in messages.into_iter().enumerate() {
+            if revealed_msg_indices.contains(&i) {
+                continue;
+            }
+            bases.push(self.vk.Y_tilde[i].clone());
+            tables.push(&self.Y_tilde_tables[i]);
+            exponents.push(msg);
+        }
+        // Prove knowledge of m_1, m_2, ... for all hidden m_i and t in J = Y_tilde_1^m_1 * Y_tilde_2^m_2 * ..... * g_tilde^t
+        let J =
+            fixed_base_multi_scalar_mul_const_time(tables.iter().cloned(), exponents.as_slice());
+
+        // For proving knowledge of messages in J.
+        let mut committing = ProverCommittingVerkeyGroup::new();
+        committing.commit(&self.g_tilde, None);
+        for b in bases.as_slice()[1..].iter() {
+            committing.commit(b, blindings.remove(0));
+        }
+        let committed = committing.finish_with_precomputation(|blindings| {
+            fixed_base_multi_scalar_mul_const_time(tables, blindings)
+        });
+
+        let sigma_prime = Signature {
+            sigma_1: sigma_prime_1,
+            sigma_2: sigma_prime_2,
+        };
+        Ok(PoKOfSignature {
+            secrets: exponents,
+            sig: sigma_prime,
+            J,
+            pok_vc: committed,
+        })
+    }
+}
+
+impl PoKOfSignature {
+    /// Section 6.2 of paper. `blindings` are the blindings used in the proof of knowledge of the
+    /// hidden messages, in increasing order of message index. They are useful when proving equality
+    /// of hidden messages across proofs. To create several proofs from the same signature, use
+    /// `PoKPrecomputed`.
+    pub fn init<M: MessageScalar>(
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        messages: Vec<M>,
+        blindings: Option<&[Blinding]>,
+        revealed_msg_indices: HashSet<usize>,
+    ) -> Result<Self, PSError> {
+        let messages = M::into_field_elements(messages);
+        Signature::check_verkey_and_messages_compat(messages.as_slice(), vk)?;
+        vk.check_params(params)?;
+        Self::validate_revealed_indices(messages.as_slice(), &revealed_msg_indices)?;
+
+        let hidden_msg_count = messages.len() - revealed_msg_indices.len();
+        let mut blindings = PoKOfSignature::blindings_for_hidden_msgs(blindings, hidden_msg_count)?;
+
+        let r = FieldElement::random();
+        let t = FieldElement::random();
+
+        // Transform signature to an aggregate signature on (messages, t)
+        crate::profiling::record_scalar_muls(3);
+        let sigma_prime_1 = &sig.sigma_1 * &r;
+        let sigma_prime_2 = (&sig.sigma_2 + (&sig.sigma_1 * &t)) * &r;
+
+        // +1 for `t`
+        let mut bases = VerkeyGroupVec::with_capacity(hidden_msg_count + 1);
+        let mut exponents = FieldElementVector::with_capacity(hidden_msg_count + 1);
+        bases.push(params.generator_tilde());
+        exponents.push(t);
+        for (i, msg) in messages.into_iter().enumerate() {
+            if revealed_msg_indices.contains(&i) {
+                continue;
+            }
+            bases.push(vk.Y_tilde[i].clone());
+            exponents.push(msg);
+        }
+        // Prove knowledge of m_1, m_2, ... for all hidden m_i and t in J = Y_tilde_1^m_1 * Y_tilde_2^m_2 * ..... * g_tilde^t
+        crate::profiling::record_scalar_muls(bases.len());
+        let J = bases
+            .multi_scalar_mul_const_time(exponents.as_slice())
+            .unwrap();
+
+        // For proving knowledge of messages in J.
+        let mut committing = ProverCommittingVerkeyGroup::new();
+        committing.commit(&params.g_tilde, None);
+        for b in bases.as_slice()[1..].iter() {
+            committing.commit(b, blindings.remove(0));
+        }
+        let committed = committing.finish();
+
+        let sigma_prime = Signature {
+            sigma_1: sigma_prime_1,
+            sigma_2: sigma_prime_2,
+        };
+        Ok(Self {
+            secrets: exponents,
+            sig: sigma_prime,
+            J,
+            pok_vc: committed,
+        })
+    }
+
+    /// Return byte representation of public elements so they can be used for challenge computation
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = POK_SIG.tag();
+        bytes.append(&mut self.sig.to_bytes());
+        bytes.append(&mut self.J.to_bytes());
+        bytes.append(&mut self.pok_vc.to_bytes());
+        bytes
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> Result<PoKOfSignatureProof, PSError> {
+        let proof_vc = self.pok_vc.gen_proof(challenge, self.secrets.as_slice())?;
+        Ok(PoKOfSignatureProof {
+            sig: self.sig,
+            J: self.J,
+            proof_vc,
+        })
+    }
+
+    /// Blinding for each hidden message, `None` if a random one should be generated
+    fn blindings_for_hidden_msgs(
+        blindings: Option<&[Blinding]>,
+        hidden_msg_count: usize,
+    ) -> Result<Vec<Option<&FieldElement>>, PSError> {
+        match blindings {
+            Some(b) => {
+                if hidden_msg_count != b.len() {
+                    return Err(PSError::GeneralError {
+                        msg: format!(
+                            "No of blindings {} not equal to number of hidden messages {}",
+                            b.len(),
+                            hidden_msg_count
+                        ),
+                    });
+                }
+                Ok(b.iter().map(|b| Some(b.as_field_element())).collect())
+            }
+            None => Ok((0..hidden_msg_count).map(|_| None).collect()),
+        }
+    }
+
+    pub(crate) fn validate_revealed_indices(
+        messages: &[FieldElement],
+        revealed_msg_indices: &HashSet<usize>,
+    ) -> Result<(), PSError> {
+        for idx in revealed_msg_indices {
+            if *idx >= messages.len() {
+                return Err(PSError::GeneralError {
+                    msg: format!("Index {} should be less than {}", idx, messages.len()),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PoKOfSignatureProof {
+    /// Byte representation of the proof as `sig || J || commitment || responses`. The number of responses
+    /// is implied by the length.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.append(&mut self.sig.to_bytes());
+        bytes.append(&mut self.J.to_bytes());
+        bytes.append(&mut self.proof_vc.commitment.to_bytes());
+        for r in self.proof_vc.responses.iter() {
+            bytes.append(&mut r.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_bytes_with_limits(bytes, &DeserializeLimits::default())
+    }
+
+    /// The count of messages of the proof is the count of responses less the one for `t`
+    pub fn from_bytes_with_limits(
+        bytes: &[u8],
+        limits: &DeserializeLimits,
+    ) -> Result<Self, PSError> {
+        limits.check_bytes(bytes)?;
+        let sig_size = 2 * SIGNATURE_GROUP_SIZE;
+        let fixed_size = sig_size + 2 * VERKEY_GROUP_SIZE;
+        // At least 1 response is needed, for the randomness `t`
+        if bytes.len() < fixed_size + FieldElement_SIZE
+            || !(bytes.len() - fixed_size).is_multiple_of(FieldElement_SIZE)
+        {
+            return Err(PSError::DeserializationError {
+                msg: format!("Incorrect number of bytes {} for proof", bytes.len()),
+            });
+        }
+        limits.check_messages((bytes.len() - fixed_size) / FieldElement_SIZE - 1)?;
+        let sig = Signature::from_bytes(&bytes[..sig_size])?;
+        let J = group_elem_from_bytes(&bytes[sig_size..sig_size + VERKEY_GROUP_SIZE])?;
+        let commitment = group_elem_from_bytes(&bytes[sig_size + VERKEY_GROUP_SIZE..fixed_size])?;
+        let mut responses =
+            FieldElementVector::with_capacity((bytes.len() - fixed_size) / FieldElement_SIZE);
+        for chunk in bytes[fixed_size..].chunks(FieldElement_SIZE) {
+            responses.push(FieldElement::from_bytes(chunk).map_err(|e| {
+                PSError::DeserializationError {
+                    msg: format!("{}", e),
+                }
+            })?);
+        }
+        Ok(Self {
+            sig,
+            J,
+            proof_vc: ProofVerkeyGroup {
+                commitment,
+                responses,
+            },
+        })
+    }
+
+    /// Return bytes that need to be hashed for generating challenge. Takes `self.sig`, `self.J`,
+    /// the bases of the hidden messages and commitment of the proof of knowledge of hidden messages.
+    pub fn get_bytes_for_challenge(
+        &self,
+        revealed_msg_indices: HashSet<usize>,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Vec<u8> {
+        let mut bytes = POK_SIG.tag();
+        bytes.append(&mut self.sig.to_bytes());
+        bytes.append(&mut self.J.to_bytes());
+        bytes.append(&mut params.g_tilde.to_bytes());
+        for (i, y) in vk.Y_tilde.iter().enumerate() {
+            if revealed_msg_indices.contains(&i) {
+                continue;
+            }
+            bytes.append(&mut y.to_bytes());
+        }
+        bytes.append(&mut self.proof_vc.commitment.to_bytes());
+        bytes
+    }
+
+    /// Get the response from post-challenge phase of the Sigma protocol for the given hidden message
+    /// `msg_idx`, counted among the hidden messages only. Used when comparing message equality
+    pub fn get_resp_for_message(&self, msg_idx: usize) -> Result<FieldElement, PSError> {
+        // 1 element in self.proof_vc.responses is reserved for the random `t`
+        let hidden = self.proof_vc.responses.len().saturating_sub(1);
+        if msg_idx >= hidden {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "Message index was given {} but should be less than {}",
+                    msg_idx, hidden
+                ),
+            });
+        }
+        // 1 added to the index, since 0th index is reserved for randomization (`t`)
+        Ok(self.proof_vc.responses[1 + msg_idx].clone())
+    }
+
+    pub fn verify(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        match self.verify_without_pairing(vk, params, &revealed_msgs, challenge)? {
+            // Verify the pairing check e(sigma_prime_1, J*X_tilde*Y_tilde_i^m_i...) == e(sigma_prime_2, g_tilde)
+            Some(J_X_tilde) => Ok(ate_2_pairing(
+                &self.sig.sigma_1,
+                &J_X_tilde,
+                &(-&self.sig.sigma_2),
+                &params.g_tilde,
+            )
+            .is_one()),
+            None => Ok(false),
+        }
+    }
+
+    /// Checks of `verify` but the pairing check, whose J*X_tilde*Y_tilde_i^m_i... it returns if they pass
+    pub(crate) fn verify_without_pairing(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<Option<VerkeyGroup>, PSError> {
+        vk.check_params(params)?;
+        if self.sig.sigma_1.is_identity() || self.sig.sigma_2.is_identity() {
+            return Ok(None);
+        }
+        Self::validate_revealed_msgs(vk, revealed_msgs)?;
+
+        // Verify the proof of knowledge of hidden messages
+        let bases = Self::hidden_msg_bases(vk, params, revealed_msgs);
+        if !self.proof_vc.verify(bases.as_slice(), &self.J, challenge)? {
+            return Ok(None);
+        }
+        Ok(Some(self.J_X_tilde(vk, revealed_msgs.clone())))
+    }
+
+    /// Checks without pairings for rejecting malformed proofs before `verify`, as `Signature::precheck`: the
+    /// revealed indices are less than the count of messages, there is a response for each hidden message and `t`,
+    /// sigma_1 and sigma_2 are not the identity and all group elements are in the prime order groups.
+    pub fn precheck(
+        &self,
+        vk: &Verkey,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+    ) -> Result<bool, PSError> {
+        Self::validate_revealed_msgs(vk, revealed_msgs)?;
+        if self.proof_vc.responses.len() != vk.Y_tilde.len() - revealed_msgs.len() + 1 {
+            return Ok(false);
+        }
+        Ok(
+            non_identity_in_group(&[&self.sig.sigma_1, &self.sig.sigma_2])
+                && in_group(&self.J)
+                && in_group(&self.proof_vc.commitment),
+        )
+    }
+
+    /// Same as `verify` but reports which check failed, see `verification_report`
+    pub fn verify_detailed(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<VerificationReport, PSError> {
+        vk.check_params(params)?;
+        Self::validate_revealed_msgs(vk, &revealed_msgs)?;
+        if let Some(report) = identity_report(&[&self.sig.sigma_1, &self.sig.sigma_2]) {
+            return Ok(report);
+        }
+        let bases = Self::hidden_msg_bases(vk, params, &revealed_msgs);
+        let recomputed =
+            self.proof_vc
+                .recompute_commitment(bases.as_slice(), &self.J, challenge)?;
+        if recomputed != self.proof_vc.commitment {
+            return Ok(VerificationReport::failed(
+                Check::ProofOfKnowledge,
+                None,
+                recomputed.to_hex(),
+                self.proof_vc.commitment.to_hex(),
+            ));
+        }
+        Ok(VerificationReport::pairing_equation(
+            Check::SignaturePairing,
+            None,
+            ate_pairing(&self.sig.sigma_1, &self.J_X_tilde(vk, revealed_msgs)),
+            ate_pairing(&self.sig.sigma_2, &params.g_tilde),
+        ))
+    }
+
+    pub(crate) fn validate_revealed_msgs(
+        vk: &Verkey,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+    ) -> Result<(), PSError> {
+        for idx in revealed_msgs.keys() {
+            if *idx >= vk.Y_tilde.len() {
+                return Err(PSError::GeneralError {
+                    msg: format!("Index {} should be less than {}", idx, vk.Y_tilde.len()),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// g_tilde followed by Y_tilde_i of each hidden message
+    pub(crate) fn hidden_msg_bases(
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+    ) -> Vec<VerkeyGroup> {
+        let mut bases = vec![params.generator_tilde()];
+        for (i, y) in vk.Y_tilde.iter().enumerate() {
+            if revealed_msgs.contains_key(&i) {
+                continue;
+            }
+            bases.push(y.clone());
+        }
+        bases
+    }
+
+    /// J * X_tilde * Y_tilde_i^m_i for each revealed message m_i
+    fn J_X_tilde(&self, vk: &Verkey, revealed_msgs: HashMap<usize, FieldElement>) -> VerkeyGroup {
+        let mut J = self.J.clone();
+        if !revealed_msgs.is_empty() {
+            let mut b = VerkeyGroupVec::with_capacity(revealed_msgs.len());
+            let mut e = FieldElementVector::with_capacity(revealed_msgs.len());
+            for (i, m) in revealed_msgs {
+                b.push(vk.Y_tilde[i].clone());
+                e.push(m);
+            }
+            J += multi_scalar_mul_var_time(b.as_slice(), e.as_slice()).unwrap();
+        }
+        J + &vk.X_tilde
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use crate::signature::fuzzed_bytes;
+    use std::time::Instant;
+
+    #[test]
+    fn test_PoK_sig() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(sig.verify(msgs.clone().into(), &vk, &params).unwrap());
+
+        let pok = PoKOfSignature::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone().into(),
+            None,
+            HashSet::new(),
+        )
+        .unwrap();
+
+        let chal_prover = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal_prover).unwrap();
+
+        // The verifier generates the challenge on its own.
+        let chal_bytes = proof.get_bytes_for_challenge(HashSet::new(), &vk, &params);
+        let chal_verifier = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof
+            .verify(&vk, &params, HashMap::new(), &chal_verifier)
+            .unwrap());
+
+        // PoK with supplied blindings
+        let blindings: Vec<Blinding> = (0..count_msgs).map(|_| Blinding::random()).collect();
+        let pok_1 = PoKOfSignature::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.into(),
+            Some(blindings.as_slice()),
+            HashSet::new(),
+        )
+        .unwrap();
+        let chal = pok_1.pok_vc.gen_challenge(pok_1.J.to_bytes());
+        let proof_1 = pok_1.gen_proof(&chal).unwrap();
+        assert!(proof_1.verify(&vk, &params, HashMap::new(), &chal).unwrap());
+    }
+
+    #[test]
+    fn test_PoK_sig_reveal_messages() {
+        let count_msgs = 10;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(2);
+        revealed_msg_indices.insert(4);
+        revealed_msg_indices.insert(9);
+
+        let pok = PoKOfSignature::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone().into(),
+            None,
+            revealed_msg_indices.clone(),
+        )
+        .unwrap();
+
+        let chal_prover = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal_prover).unwrap();
+
+        let mut revealed_msgs = HashMap::new();
+        for i in &revealed_msg_indices {
+            revealed_msgs.insert(*i, msgs[*i].clone());
+        }
+        let chal_bytes = proof.get_bytes_for_challenge(revealed_msg_indices.clone(), &vk, &params);
+        let chal_verifier = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof
+            .verify(&vk, &params, revealed_msgs.clone(), &chal_verifier)
+            .unwrap());
+
+        let proof_1 = PoKOfSignatureProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert!(proof_1
+            .verify(&vk, &params, revealed_msgs.clone(), &chal_verifier)
+            .unwrap());
+        assert!(PoKOfSignatureProof::from_bytes(&proof.to_bytes()[1..]).is_err());
+        let hidden = count_msgs - revealed_msg_indices.len();
+        assert!(PoKOfSignatureProof::from_bytes_with_limits(
+            &proof.to_bytes(),
+            &DeserializeLimits::new(hidden, 1 << 16)
+        )
+        .is_ok());
+        assert!(PoKOfSignatureProof::from_bytes_with_limits(
+            &proof.to_bytes(),
+            &DeserializeLimits::new(hidden - 1, 1 << 16)
+        )
+        .is_err());
+
+        // Reveal wrong message
+        let mut revealed_msgs_1 = revealed_msgs.clone();
+        revealed_msgs_1.insert(2, FieldElement::random());
+        assert!(!proof
+            .verify(&vk, &params, revealed_msgs_1, &chal_verifier)
+            .unwrap());
+
+        // Revealing an index outside the verkey is an error
+        let mut revealed_msgs_2 = revealed_msgs.clone();
+        revealed_msgs_2.insert(count_msgs, FieldElement::random());
+        assert!(proof
+            .verify(&vk, &params, revealed_msgs_2.clone(), &chal_verifier)
+            .is_err());
+
+        assert!(proof.precheck(&vk, &revealed_msgs).unwrap());
+        assert!(proof.precheck(&vk, &revealed_msgs_2).is_err());
+        // Responses not matching the count of hidden messages
+        let mut fewer = revealed_msgs.clone();
+        fewer.remove(&2);
+        assert!(!proof.precheck(&vk, &fewer).unwrap());
+        let mut identity = proof.clone();
+        identity.sig.sigma_1 = SignatureGroup::identity();
+        assert!(!identity.precheck(&vk, &revealed_msgs).unwrap());
+    }
+
+    #[test]
+    fn test_PoK_multiple_sigs_with_same_msg() {
+        // Prove knowledge of 2 signatures and also the equality of a message under both signatures.
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+
+        let same_msg = FieldElement::random();
+        let mut msgs_1 = FieldElementVector::random(count_msgs - 1);
+        msgs_1.push(same_msg.clone());
+        let sig_1 = Signature::new(msgs_1.as_slice(), &sk, &params).unwrap();
+
+        let mut msgs_2 = FieldElementVector::random(count_msgs - 1);
+        msgs_2.push(same_msg);
+        let sig_2 = Signature::new(msgs_2.as_slice(), &sk, &params).unwrap();
+
+        // A particular message is same
+        assert_eq!(msgs_1[count_msgs - 1], msgs_2[count_msgs - 1]);
+
+        let same_blinding = Blinding::random();
+        let mut blindings_1: Vec<Blinding> = (1..count_msgs).map(|_| Blinding::random()).collect();
+        blindings_1.push(same_blinding.clone());
+        let mut blindings_2: Vec<Blinding> = (1..count_msgs).map(|_| Blinding::random()).collect();
+        blindings_2.push(same_blinding);
+
+        let pok_1 = PoKOfSignature::init(
+            &sig_1,
+            &vk,
+            &params,
+            msgs_1.into(),
+            Some(blindings_1.as_slice()),
+            HashSet::new(),
+        )
+        .unwrap();
+        let pok_2 = PoKOfSignature::init(
+            &sig_2,
+            &vk,
+            &params,
+            msgs_2.into(),
+            Some(blindings_2.as_slice()),
+            HashSet::new(),
+        )
+        .unwrap();
+
+        let mut chal_bytes = vec![];
+        chal_bytes.append(&mut pok_1.to_bytes());
+        chal_bytes.append(&mut pok_2.to_bytes());
+        let chal = FieldElement::from_msg_hash(&chal_bytes);
+
+        let proof_1 = pok_1.gen_proof(&chal).unwrap();
+        let proof_2 = pok_2.gen_proof(&chal).unwrap();
+
+        assert!(proof_1.verify(&vk, &params, HashMap::new(), &chal).unwrap());
+        assert!(proof_2.verify(&vk, &params, HashMap::new(), &chal).unwrap());
+        assert_eq!(
+            proof_1.get_resp_for_message(count_msgs - 1).unwrap(),
+            proof_2.get_resp_for_message(count_msgs - 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_PoK_precomputed() {
+        let count_msgs = 6;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let precomputed = PoKPrecomputed::new(&sig, &vk, &params).unwrap();
+        let reveal_sets: Vec<Vec<usize>> =
+            vec![vec![], vec![1], vec![0, 3, 5], (0..count_msgs).collect()];
+        for r in reveal_sets {
+            let revealed_msg_indices: HashSet<usize> = r.into_iter().collect();
+            let pok = precomputed
+                .init(msgs.clone().into(), None, revealed_msg_indices.clone())
+                .unwrap();
+            let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+            let proof = pok.gen_proof(&chal).unwrap();
+
+            let mut revealed_msgs = HashMap::new();
+            for i in &revealed_msg_indices {
+                revealed_msgs.insert(*i, msgs[*i].clone());
+            }
+            let chal_bytes = proof.get_bytes_for_challenge(revealed_msg_indices, &vk, &params);
+            assert_eq!(FieldElement::from_msg_hash(&chal_bytes), chal);
+            assert!(proof.verify(&vk, &params, revealed_msgs, &chal).unwrap());
+        }
+
+        // Proofs from the same precomputation are still randomized
+        let pok_1 = precomputed
+            .init(msgs.clone().into(), None, HashSet::new())
+            .unwrap();
+        let pok_2 = precomputed
+            .init(msgs.clone().into(), None, HashSet::new())
+            .unwrap();
+        assert_ne!(pok_1.sig.sigma_1, pok_2.sig.sigma_1);
+        assert_ne!(pok_1.J, pok_2.J);
+
+        // Wrong number of messages
+        assert!(precomputed
+            .init(msgs.as_slice()[1..].to_vec(), None, HashSet::new())
+            .is_err());
+
+        // Verkey of other params
+        match PoKPrecomputed::new(&sig, &vk, &Params::new("other".as_bytes())) {
+            Err(PSError::ParamsMismatch) => (),
+            r => panic!("Expected ParamsMismatch but got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_fixed_base_table_const_time() {
+        let base = SignatureGroup::random();
+        let scalars = vec![
+            FieldElement::zero(),
+            FieldElement::one(),
+            FieldElement::minus_one(),
+            FieldElement::random(),
+        ];
+        for window in 0..=MAX_WINDOW_SIZE {
+            let table = FixedBaseTable::with_window(&base, window);
+            for s in &scalars {
+                let mut accum = SignatureGroup::identity();
+                table.mul_add_const_time(s, &mut accum);
+                assert_eq!(accum, &base * s);
+                let mut accum_var = SignatureGroup::identity();
+                table.mul_add(s, &mut accum_var);
+                assert_eq!(accum_var, accum);
+            }
+        }
+        let tables: Vec<_> = (0..3)
+            .map(|_| FixedBaseTable::new(&VerkeyGroup::random()))
+            .collect();
+        let bases: Vec<VerkeyGroup> = tables.iter().map(|t| t.table[0][0].clone()).collect();
+        let exps = FieldElementVector::random(3);
+        let expected = VerkeyGroupVec::from(bases)
+            .multi_scalar_mul_const_time(exps.as_slice())
+            .unwrap();
+        assert_eq!(
+            fixed_base_multi_scalar_mul_const_time(&tables, exps.as_slice()),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_PoK_precomputed_deserialize_checks_tables() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let msgs = FieldElementVector::random(3);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let precomputed = PoKPrecomputed::new(&sig, &vk, &params).unwrap();
+        // Window too large
+        let mut precomputed_1 = precomputed.clone();
+        precomputed_1.sigma_1.window = MAX_WINDOW_SIZE + 1;
+        // Window missing
+        let mut precomputed_2 = precomputed.clone();
+        precomputed_2.sigma_2.table.pop();
+        // Multiple not of the base
+        let mut precomputed_3 = precomputed.clone();
+        precomputed_3.g_tilde_table.table[1][2] = VerkeyGroup::random();
+        // Table of another base than Y_tilde_2
+        let mut precomputed_4 = precomputed.clone();
+        precomputed_4.Y_tilde_tables[1] = FixedBaseTable::new(&VerkeyGroup::random());
+        // Table of Y_tilde_3 missing
+        let mut precomputed_5 = precomputed.clone();
+        precomputed_5.Y_tilde_tables.pop();
+        // Table of another base than g_tilde
+        let mut precomputed_6 = precomputed.clone();
+        precomputed_6.g_tilde_table = FixedBaseTable::new(&VerkeyGroup::random());
+        for (p, valid) in [
+            (precomputed, true),
+            (precomputed_1, false),
+            (precomputed_2, false),
+            (precomputed_3, false),
+            (precomputed_4, false),
+            (precomputed_5, false),
+            (precomputed_6, false),
+        ]
+        .iter()
+        {
+            let json = serde_json::to_string(p).unwrap();
+            assert_eq!(
+                serde_json::from_str::<PoKPrecomputed>(&json).is_ok(),
+                *valid
+            );
+        }
+    }
+
+    #[test]
+    fn timing_PoK_precomputed() {
+        let count_msgs = 20;
+        let count_proofs = 20;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let start = Instant::now();
+        for _ in 0..count_proofs {
+            PoKOfSignature::init(
+                &sig,
+                &vk,
+                &params,
+                msgs.clone().into(),
+                None,
+                HashSet::new(),
+            )
+            .unwrap();
+        }
+        println!(
+            "Time to create {} PoK without precomputation {:?}",
+            count_proofs,
+            start.elapsed()
+        );
+
+        let start = Instant::now();
+        let precomputed = PoKPrecomputed::new(&sig, &vk, &params).unwrap();
+        for _ in 0..count_proofs {
+            precomputed
+                .init(msgs.clone().into(), None, HashSet::new())
+                .unwrap();
+        }
+        println!(
+            "Time to create {} PoK with precomputation {:?}",
+            count_proofs,
+            start.elapsed()
+        );
+    }
+
+    #[test]
+    fn test_PoK_sig_from_fuzzed_bytes() {
+        let count_msgs = 3;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let pok =
+            PoKOfSignature::init(&sig, &vk, &params, msgs.into(), None, HashSet::new()).unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let mut proof = pok.gen_proof(&chal).unwrap();
+        for bytes in fuzzed_bytes(&proof.to_bytes(), 40) {
+            if let Ok(p) = PoKOfSignatureProof::from_bytes(&bytes) {
+                match p.verify(&vk, &params, HashMap::new(), &chal) {
+                    // Fuzzing the length of the responses gives a proof for another count of hidden messages
+                    Ok(_) | Err(PSError::UnequalNoOfBasesExponents { .. }) => (),
+                    Err(e) => panic!("Unexpected error {:?}", e),
+                }
+                let _ = p.get_resp_for_message(count_msgs);
+            }
+        }
+
+        // A proof without responses has no response for any message
+        proof.proof_vc.responses = FieldElementVector::new(0);
+        assert!(proof.get_resp_for_message(0).is_err());
+    }
+
+    #[test]
+    fn test_PoK_verify_detailed() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let revealed_msg_indices: HashSet<usize> = vec![1, 3].into_iter().collect();
+        let pok = PoKOfSignature::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone().into(),
+            None,
+            revealed_msg_indices.clone(),
+        )
+        .unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        let mut revealed_msgs = HashMap::new();
+        for i in &revealed_msg_indices {
+            revealed_msgs.insert(*i, msgs[*i].clone());
+        }
+        assert!(proof
+            .verify_detailed(&vk, &params, revealed_msgs.clone(), &chal)
+            .unwrap()
+            .is_valid());
+
+        // Wrong challenge fails the proof of knowledge
+        let failure = proof
+            .verify_detailed(&vk, &params, revealed_msgs.clone(), &FieldElement::random())
+            .unwrap()
+            .failure
+            .unwrap();
+        assert_eq!(failure.check, Check::ProofOfKnowledge);
+        assert_eq!(failure.expected, proof.proof_vc.commitment.to_hex());
+
+        // Wrong revealed message fails the pairing check
+        let mut wrong = revealed_msgs;
+        wrong.insert(3, FieldElement::random());
+        let failure = proof
+            .verify_detailed(&vk, &params, wrong, &chal)
+            .unwrap()
+            .failure
+            .unwrap();
+        assert_eq!(failure.check, Check::SignaturePairing);
+    }
+}
@@ -0,0 +1,247 @@
+// Proof of knowledge of committed values in a vector Pedersen commitment.
+
+/// Generates the prover and verifier types for a Schnorr-style proof of knowledge of the
+/// opening of a commitment `bases[0]^x[0] * bases[1]^x[1] * ...` in the given group. Expects
+/// `FieldElement`, `FieldElementVector`, `GroupElement`, `GroupElementVector` and `PSError` in scope.
+#[macro_export]
+macro_rules! impl_PoK_VC {
+    ( $ProverCommitting:ident, $ProverCommitted:ident, $Proof:ident, $group_element:ident, $group_element_vec:ident ) => {
+        /// Proof of knowledge of messages in a vector commitment.
+        /// Commit for each message.
+        #[derive(Clone, Debug, Serialize, Deserialize)]
+        pub struct $ProverCommitting {
+            gens: $group_element_vec,
+            blindings: FieldElementVector,
+        }
+
+        /// Receive or generate challenge. Compute response and proof
+        #[derive(Clone, Debug, Serialize, Deserialize)]
+        pub struct $ProverCommitted {
+            gens: $group_element_vec,
+            blindings: FieldElementVector,
+            commitment: $group_element,
+        }
+
+        #[derive(Clone, Debug, Serialize, Deserialize)]
+        pub struct $Proof {
+            pub commitment: $group_element,
+            pub responses: FieldElementVector,
+        }
+
+        impl Default for $ProverCommitting {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl $ProverCommitting {
+            pub fn new() -> Self {
+                Self {
+                    gens: $group_element_vec::new(0),
+                    blindings: FieldElementVector::new(0),
+                }
+            }
+
+            /// Generate a new random blinding if None provided
+            pub fn commit(
+                &mut self,
+                gen: &$group_element,
+                blinding: Option<&FieldElement>,
+            ) -> usize {
+                let blinding = match blinding {
+                    Some(b) => b.clone(),
+                    None => FieldElement::random(),
+                };
+                let idx = self.gens.len();
+                self.gens.push(gen.clone());
+                self.blindings.push(blinding);
+                idx
+            }
+
+            /// Add pairwise product of (`self.gens`, `self.blindings`). Uses multi-exponentiation.
+            pub fn finish(self) -> $ProverCommitted {
+                $crate::profiling::record_scalar_muls(self.gens.len());
+                let commitment = self
+                    .gens
+                    .multi_scalar_mul_const_time(self.blindings.as_slice())
+                    .unwrap();
+                $ProverCommitted {
+                    gens: self.gens,
+                    blindings: self.blindings,
+                    commitment,
+                }
+            }
+
+            /// Same as `finish` but the commitment is computed by `multi_scalar_mul` given the blindings
+            /// in the order of `commit` calls, e.g. using precomputed tables of the gens.
+            pub fn finish_with_precomputation<F>(self, multi_scalar_mul: F) -> $ProverCommitted
+            where
+                F: FnOnce(&[FieldElement]) -> $group_element,
+            {
+                let commitment = multi_scalar_mul(self.blindings.as_slice());
+                $ProverCommitted {
+                    gens: self.gens,
+                    blindings: self.blindings,
+                    commitment,
+                }
+            }
+
+            pub fn get_index(
+                &self,
+                idx: usize,
+            ) -> Result<(&$group_element, &FieldElement), PSError> {
+                if idx >= self.gens.len() {
+                    return Err(PSError::GeneralError {
+                        msg: format!("index {} greater than size {}", idx, self.gens.len()),
+                    });
+                }
+                Ok((&self.gens[idx], &self.blindings[idx]))
+            }
+        }
+
+        impl $ProverCommitted {
+            pub fn to_bytes(&self) -> Vec<u8> {
+                let mut bytes = vec![];
+                for b in self.gens.as_slice() {
+                    bytes.append(&mut b.to_bytes());
+                }
+                bytes.append(&mut self.commitment.to_bytes());
+                bytes
+            }
+
+            /// This step will be done by the main protocol for which this PoK is a sub-protocol
+            pub fn gen_challenge(&self, mut extra: Vec<u8>) -> FieldElement {
+                let mut bytes = $crate::challenge_domain::POK_VC.tag();
+                bytes.append(&mut self.to_bytes());
+                bytes.append(&mut extra);
+                FieldElement::from_msg_hash(&bytes)
+            }
+
+            /// For each secret, generate a response as self.blinding[i] - challenge*secrets[i].
+            pub fn gen_proof(
+                self,
+                challenge: &FieldElement,
+                secrets: &[FieldElement],
+            ) -> Result<$Proof, PSError> {
+                if secrets.len() != self.gens.len() {
+                    return Err(PSError::UnequalNoOfBasesExponents {
+                        bases: self.gens.len(),
+                        exponents: secrets.len(),
+                    });
+                }
+                let mut responses = FieldElementVector::with_capacity(self.gens.len());
+                for (b, s) in self.blindings.iter().zip(secrets) {
+                    responses.push(b - (challenge * s));
+                }
+                Ok($Proof {
+                    commitment: self.commitment,
+                    responses,
+                })
+            }
+        }
+
+        impl $Proof {
+            /// Verify that bases[0]^responses[0] * bases[1]^responses[1] * ... bases[i]^responses[i] * commitment^challenge == random_commitment
+            pub fn verify(
+                &self,
+                bases: &[$group_element],
+                commitment: &$group_element,
+                challenge: &FieldElement,
+            ) -> Result<bool, PSError> {
+                let pr = self.recompute_commitment(bases, commitment, challenge)?;
+                Ok(pr == self.commitment)
+            }
+
+            /// The random commitment as bases[0]^responses[0] * bases[1]^responses[1] * ... bases[i]^responses[i] * commitment^challenge.
+            /// Equals `self.commitment` for a valid proof.
+            pub fn recompute_commitment(
+                &self,
+                bases: &[$group_element],
+                commitment: &$group_element,
+                challenge: &FieldElement,
+            ) -> Result<$group_element, PSError> {
+                if bases.len() != self.responses.len() {
+                    return Err(PSError::UnequalNoOfBasesExponents {
+                        bases: bases.len(),
+                        exponents: self.responses.len(),
+                    });
+                }
+                let mut points = $group_element_vec::from(bases);
+                let mut scalars = self.responses.clone();
+                points.push(commitment.clone());
+                scalars.push(challenge.clone());
+                $crate::msm::multi_scalar_mul_var_time(points.as_slice(), scalars.as_slice())
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::curve::{FieldElement, FieldElementVector, GroupElement, GroupElementVector};
+    use crate::pok_sig::{ProverCommittingSignatureGroup, ProverCommittingVerkeyGroup};
+    use crate::{SignatureGroup, SignatureGroupVec, VerkeyGroup, VerkeyGroupVec};
+
+    macro_rules! test_PoK_VC {
+        ( $n:ident, $ProverCommitting:ident, $group_element:ident, $group_element_vec:ident ) => {
+            let mut gens = $group_element_vec::with_capacity($n);
+            let mut secrets = FieldElementVector::with_capacity($n);
+            let mut commiting = $ProverCommitting::new();
+            for _ in 0..$n - 1 {
+                let g = $group_element::random();
+                commiting.commit(&g, None);
+                gens.push(g);
+                secrets.push(FieldElement::random());
+            }
+
+            // Add one of the blindings externally
+            let g = $group_element::random();
+            let r = FieldElement::random();
+            commiting.commit(&g, Some(&r));
+            let (g_, r_) = commiting.get_index($n - 1).unwrap();
+            assert_eq!(g, *g_);
+            assert_eq!(r, *r_);
+            gens.push(g);
+            secrets.push(FieldElement::random());
+
+            // Bound check for get_index
+            assert!(commiting.get_index($n).is_err());
+            assert!(commiting.get_index($n + 1).is_err());
+
+            let committed = commiting.finish();
+            let commitment = gens
+                .multi_scalar_mul_const_time(secrets.as_slice())
+                .unwrap();
+            let challenge = committed.gen_challenge(commitment.to_bytes());
+            let proof = committed.gen_proof(&challenge, secrets.as_slice()).unwrap();
+
+            assert!(proof
+                .verify(gens.as_slice(), &commitment, &challenge)
+                .unwrap());
+            // Wrong challenge or commitment fails to verify
+            assert!(!proof
+                .verify(gens.as_slice(), &$group_element::random(), &challenge)
+                .unwrap());
+            assert!(!proof
+                .verify(gens.as_slice(), &commitment, &FieldElement::random())
+                .unwrap());
+        };
+    }
+
+    #[test]
+    fn test_pok_vc_signature_group() {
+        let n = 5;
+        test_PoK_VC!(
+            n,
+            ProverCommittingSignatureGroup,
+            SignatureGroup,
+            SignatureGroupVec
+        );
+    }
+
+    #[test]
+    fn test_pok_vc_verkey_group() {
+        let n = 5;
+        test_PoK_VC!(n, ProverCommittingVerkeyGroup, VerkeyGroup, VerkeyGroupVec);
+    }
+}
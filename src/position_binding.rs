@@ -0,0 +1,148 @@
+// Position binding of the messages of the redactable signature. The scheme ties the message at index i to
+// Y_tilde_{i+1} so disclosed messages are only accepted at the indices they were signed at. With
+// `MessageEncoding::PositionBound` the index also becomes part of the signed value, the message at index i is
+// signed as H(i || m_i). Then the same value at two indices gives unrelated signed messages, and a signed value
+// taken from one index is no message at any other. Signer, holder and verifier must use the same encoding.
+
+use crate::curve::FieldElement;
+use std::collections::HashMap;
+
+const POSITION_BINDING_DOMAIN: &[u8] = b"PS RSS message position : ";
+
+/// Encoding applied to messages before they are signed, derived or verified
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MessageEncoding {
+    /// Messages are signed as given
+    #[default]
+    Plain,
+    /// Message at index i is signed as H(i || m_i)
+    PositionBound,
+}
+
+impl MessageEncoding {
+    /// Encode all messages, the message at index i of `messages` being at index i
+    pub fn encode(&self, messages: &[FieldElement]) -> Vec<FieldElement> {
+        messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| self.encode_message(i, m))
+            .collect()
+    }
+
+    /// Encode disclosed messages keyed by their index
+    pub fn encode_disclosed(
+        &self,
+        disclosed_msgs: &HashMap<usize, FieldElement>,
+    ) -> HashMap<usize, FieldElement> {
+        disclosed_msgs
+            .iter()
+            .map(|(i, m)| (*i, self.encode_message(*i, m)))
+            .collect()
+    }
+
+    pub fn encode_message(&self, index: usize, message: &FieldElement) -> FieldElement {
+        match self {
+            MessageEncoding::Plain => message.clone(),
+            MessageEncoding::PositionBound => {
+                let mut bytes = POSITION_BINDING_DOMAIN.to_vec();
+                bytes.extend_from_slice(&(index as u64).to_be_bytes());
+                bytes.append(&mut message.to_bytes());
+                FieldElement::from_msg_hash(&bytes)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::{rsskeygen, Params};
+    use crate::rss_signature::RSSignature;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_position_bound_encoding() {
+        let m = FieldElement::random();
+        let e = MessageEncoding::PositionBound;
+        assert_eq!(MessageEncoding::Plain.encode_message(3, &m), m);
+        assert_eq!(e.encode_message(3, &m), e.encode_message(3, &m));
+        assert_ne!(e.encode_message(3, &m), e.encode_message(4, &m));
+        assert_ne!(e.encode_message(3, &m), m);
+    }
+
+    #[test]
+    fn test_swapped_disclosed_messages_rejected() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let disclosed: HashSet<usize> = vec![0, 3].into_iter().collect();
+
+        for encoding in &[MessageEncoding::Plain, MessageEncoding::PositionBound] {
+            let signed = encoding.encode(msgs.as_slice());
+            let sig = RSSignature::new(&signed, &sk, &params).unwrap();
+            let derived = sig.derive(&signed, &disclosed, &pk).unwrap();
+
+            let mut disclosed_msgs = HashMap::new();
+            disclosed_msgs.insert(0, msgs[0].clone());
+            disclosed_msgs.insert(3, msgs[3].clone());
+            assert!(derived
+                .verify(&encoding.encode_disclosed(&disclosed_msgs), &pk)
+                .unwrap());
+
+            // Holder swaps the indices of the disclosed messages
+            let mut swapped = HashMap::new();
+            swapped.insert(0, msgs[3].clone());
+            swapped.insert(3, msgs[0].clone());
+            assert!(!derived
+                .verify(&encoding.encode_disclosed(&swapped), &pk)
+                .unwrap());
+
+            // Holder moves a disclosed message to a redacted index
+            let mut moved = HashMap::new();
+            moved.insert(0, msgs[0].clone());
+            moved.insert(2, msgs[3].clone());
+            assert!(!derived
+                .verify(&encoding.encode_disclosed(&moved), &pk)
+                .unwrap());
+        }
+    }
+
+    #[test]
+    fn test_position_bound_signed_values_not_reusable() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let e = MessageEncoding::PositionBound;
+
+        // Same value at indices 1 and 2
+        let mut msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        msgs[2] = msgs[1].clone();
+        let signed = e.encode(&msgs);
+        assert_ne!(signed[1], signed[2]);
+        let sig = RSSignature::new(&signed, &sk, &params).unwrap();
+
+        let disclosed: HashSet<usize> = vec![1].into_iter().collect();
+        let derived = sig.derive(&signed, &disclosed, &pk).unwrap();
+        let mut disclosed_msgs = HashMap::new();
+        disclosed_msgs.insert(1, msgs[1].clone());
+        assert!(derived
+            .verify(&e.encode_disclosed(&disclosed_msgs), &pk)
+            .unwrap());
+
+        // Signature disclosing index 1 doesn't pass for index 2 even though the values are equal
+        let mut other_index = HashMap::new();
+        other_index.insert(2, msgs[1].clone());
+        assert!(!derived
+            .verify(&e.encode_disclosed(&other_index), &pk)
+            .unwrap());
+
+        // The signed (encoded) value is not accepted as a message, not even at its own index
+        let mut encoded_as_msg = HashMap::new();
+        encoded_as_msg.insert(1, signed[1].clone());
+        assert!(!derived
+            .verify(&e.encode_disclosed(&encoded_as_msg), &pk)
+            .unwrap());
+    }
+}
@@ -0,0 +1,332 @@
+// Predicates on hidden messages of a credential. A set membership proof shows that a hidden message is a member
+// of a set published by a set signer, e.g. the country is in a list of countries, without revealing which member
+// it is. It uses the signature based technique of Camenisch, Chaabouni and shelat: the set signer signs each
+// member with signatures of the 2016 scheme on a single message under a key of the set, the `MembershipSet`, and
+// the holder proves knowledge of the signature on its hidden message with that message hidden. The hidden message
+// shares its blinding in the proof of knowledge of the credential and in the proof of knowledge of the signature of
+// the set, so equal responses prove that it is the same message. A value outside the set would need a forged
+// signature of the set signer. The key of the set must not sign anything else.
+// The proofs of a `PredicatePresentation` share the challenge, computed over the credential proof, each
+// predicate proof with its index and the nonce of the verifier.
+
+use crate::challenge_domain::PREDICATE_PRESENTATION;
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::pok_sig::{PoKOfSignature, PoKOfSignatureProof};
+use crate::scalar::Blinding;
+use crate::signature::Signature;
+use std::collections::{HashMap, HashSet};
+
+/// Set published by a set signer: the verkey of the set and a signature on each member
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MembershipSet {
+    pub verkey: Verkey,
+    pub members: Vec<(FieldElement, Signature)>,
+}
+
+/// Proof that the hidden message at `index` of the credential is a member of a `MembershipSet`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SetMembershipProof {
+    pub index: usize,
+    /// Proof of knowledge of the signature of the set on the hidden message
+    pub proof: PoKOfSignatureProof,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PredicateProof {
+    SetMembership(SetMembershipProof),
+}
+
+/// Proof of knowledge of a credential with the revealed messages and proofs of predicates on hidden messages
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PredicatePresentation {
+    pub proof: PoKOfSignatureProof,
+    pub revealed_msgs: HashMap<usize, FieldElement>,
+    pub predicates: Vec<PredicateProof>,
+}
+
+impl MembershipSet {
+    /// Sign each of `values` with `sigkey`, a key for 1 message whose verkey is `verkey`
+    pub fn new(
+        values: &[FieldElement],
+        sigkey: &Sigkey,
+        verkey: &Verkey,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        verkey.check_params(params)?;
+        let mut members = Vec::with_capacity(values.len());
+        for v in values {
+            let sig = Signature::new(std::slice::from_ref(v), sigkey, params)?;
+            members.push((v.clone(), sig));
+        }
+        Ok(Self {
+            verkey: verkey.clone(),
+            members,
+        })
+    }
+
+    pub fn contains(&self, value: &FieldElement) -> bool {
+        self.signature_of(value).is_some()
+    }
+
+    fn signature_of(&self, value: &FieldElement) -> Option<&Signature> {
+        self.members
+            .iter()
+            .find(|(v, _)| v == value)
+            .map(|(_, sig)| sig)
+    }
+}
+
+/// Index of the response of the message at `index` among the hidden messages
+fn hidden_index(index: usize, revealed_msg_indices: &HashSet<usize>) -> usize {
+    index - revealed_msg_indices.iter().filter(|i| **i < index).count()
+}
+
+fn presentation_challenge(
+    bytes_for_challenge: Vec<u8>,
+    predicate_bytes: Vec<(usize, Vec<u8>)>,
+    nonce: &[u8],
+) -> FieldElement {
+    let mut bytes = PREDICATE_PRESENTATION.tag();
+    bytes.extend(bytes_for_challenge);
+    for (index, b) in predicate_bytes {
+        bytes.extend_from_slice(&(index as u64).to_be_bytes());
+        bytes.extend(b);
+    }
+    bytes.extend_from_slice(nonce);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl PredicatePresentation {
+    /// Presentation of the credential `sig` on `messages` revealing `revealed_msg_indices` and proving for each
+    /// (index, set) of `memberships` that the hidden message at index is a member of the set. Fails with
+    /// `PSError::NotInSet` if it is not.
+    pub fn new(
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        messages: Vec<FieldElement>,
+        revealed_msg_indices: HashSet<usize>,
+        memberships: &[(usize, &MembershipSet)],
+        nonce: &[u8],
+    ) -> Result<Self, PSError> {
+        let hidden_count = messages.len().saturating_sub(revealed_msg_indices.len());
+        let blindings: Vec<Blinding> = (0..hidden_count).map(|_| Blinding::random()).collect();
+        let mut set_poks = Vec::with_capacity(memberships.len());
+        for (index, set) in memberships {
+            if *index >= messages.len() || revealed_msg_indices.contains(index) {
+                return Err(PSError::InvalidIndex {
+                    idx: *index,
+                    valid: String::from("hidden messages"),
+                });
+            }
+            let value = &messages[*index];
+            let set_sig = set
+                .signature_of(value)
+                .ok_or(PSError::NotInSet { idx: *index })?;
+            let blinding = &blindings[hidden_index(*index, &revealed_msg_indices)];
+            let pok = PoKOfSignature::init(
+                set_sig,
+                &set.verkey,
+                params,
+                vec![value.clone()],
+                Some(std::slice::from_ref(blinding)),
+                HashSet::new(),
+            )?;
+            set_poks.push((*index, pok));
+        }
+
+        let revealed_msgs = revealed_msg_indices
+            .iter()
+            .filter(|i| **i < messages.len())
+            .map(|i| (*i, messages[*i].clone()))
+            .collect();
+        let pok = PoKOfSignature::init(
+            sig,
+            vk,
+            params,
+            messages,
+            Some(blindings.as_slice()),
+            revealed_msg_indices,
+        )?;
+        let challenge = presentation_challenge(
+            pok.to_bytes(),
+            set_poks.iter().map(|(i, p)| (*i, p.to_bytes())).collect(),
+            nonce,
+        );
+        let mut predicates = Vec::with_capacity(set_poks.len());
+        for (index, p) in set_poks {
+            predicates.push(PredicateProof::SetMembership(SetMembershipProof {
+                index,
+                proof: p.gen_proof(&challenge)?,
+            }));
+        }
+        Ok(Self {
+            proof: pok.gen_proof(&challenge)?,
+            revealed_msgs,
+            predicates,
+        })
+    }
+
+    /// Verify the presentation for `nonce` and that it proves exactly the memberships of `memberships`, in order
+    pub fn verify(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        memberships: &[(usize, &MembershipSet)],
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        if self.predicates.len() != memberships.len() {
+            return Ok(false);
+        }
+        let revealed_msg_indices: HashSet<usize> = self.revealed_msgs.keys().cloned().collect();
+        let mut predicate_bytes = Vec::with_capacity(memberships.len());
+        for (predicate, (index, set)) in self.predicates.iter().zip(memberships) {
+            match predicate {
+                PredicateProof::SetMembership(p) => {
+                    if p.index != *index || revealed_msg_indices.contains(index) {
+                        return Ok(false);
+                    }
+                    predicate_bytes.push((
+                        p.index,
+                        p.proof
+                            .get_bytes_for_challenge(HashSet::new(), &set.verkey, params),
+                    ));
+                }
+            }
+        }
+        let challenge = presentation_challenge(
+            self.proof
+                .get_bytes_for_challenge(revealed_msg_indices.clone(), vk, params),
+            predicate_bytes,
+            nonce,
+        );
+        if !self
+            .proof
+            .verify(vk, params, self.revealed_msgs.clone(), &challenge)?
+        {
+            return Ok(false);
+        }
+        for (predicate, (index, set)) in self.predicates.iter().zip(memberships) {
+            match predicate {
+                PredicateProof::SetMembership(p) => {
+                    if *index >= vk.Y_tilde.len()
+                        || !p
+                            .proof
+                            .verify(&set.verkey, params, HashMap::new(), &challenge)?
+                    {
+                        return Ok(false);
+                    }
+                    // Same response for the hidden message in both proofs
+                    let hidden = hidden_index(*index, &revealed_msg_indices);
+                    if p.proof.get_resp_for_message(0)?
+                        != self.proof.get_resp_for_message(hidden)?
+                    {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_set_membership() {
+        let params = Params::new("test".as_bytes());
+        let (set_sk, set_vk) = keygen(1, &params).unwrap();
+        let countries: Vec<FieldElement> = ["AT", "BE", "DE", "FR"]
+            .iter()
+            .map(|c| FieldElement::from_msg_hash(c.as_bytes()))
+            .collect();
+        let eu = MembershipSet::new(&countries, &set_sk, &set_vk, &params).unwrap();
+        assert!(eu.contains(&countries[2]));
+
+        let (sk, vk) = keygen(4, &params).unwrap();
+        let mut msgs: Vec<FieldElement> = FieldElementVector::random(4).into();
+        msgs[2] = countries[2].clone();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let revealed: HashSet<usize> = vec![0].into_iter().collect();
+        let nonce = FieldElement::random().to_bytes();
+        let memberships = [(2, &eu)];
+
+        let p = PredicatePresentation::new(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone(),
+            revealed.clone(),
+            &memberships,
+            &nonce,
+        )
+        .unwrap();
+        assert!(p.verify(&vk, &params, &memberships, &nonce).unwrap());
+        assert!(!p.revealed_msgs.contains_key(&2));
+        assert!(!p
+            .verify(
+                &vk,
+                &params,
+                &memberships,
+                &FieldElement::random().to_bytes()
+            )
+            .unwrap());
+        assert!(!p.verify(&vk, &params, &[(1, &eu)], &nonce).unwrap());
+        assert!(!p.verify(&vk, &params, &[], &nonce).unwrap());
+
+        // Another set with the same members
+        let (other_sk, other_vk) = keygen(1, &params).unwrap();
+        let other = MembershipSet::new(&countries, &other_sk, &other_vk, &params).unwrap();
+        assert!(!p.verify(&vk, &params, &[(2, &other)], &nonce).unwrap());
+
+        // The message is not in the set
+        match PredicatePresentation::new(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone(),
+            revealed.clone(),
+            &[(1, &eu)],
+            &nonce,
+        ) {
+            Err(PSError::NotInSet { idx: 1 }) => (),
+            r => panic!("Expected NotInSet but got {:?}", r.map(|_| ())),
+        }
+        // or revealed
+        assert!(PredicatePresentation::new(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone(),
+            revealed.clone(),
+            &[(0, &eu)],
+            &nonce,
+        )
+        .is_err());
+
+        // Proof of the signature of the set on another member than the hidden message
+        let mut forged = p.clone();
+        let fake_msgs = vec![countries[0].clone()];
+        let pok = PoKOfSignature::init(
+            &eu.members[0].1,
+            &eu.verkey,
+            &params,
+            fake_msgs,
+            None,
+            HashSet::new(),
+        )
+        .unwrap();
+        let challenge = FieldElement::random();
+        forged.predicates = vec![PredicateProof::SetMembership(SetMembershipProof {
+            index: 2,
+            proof: pok.gen_proof(&challenge).unwrap(),
+        })];
+        assert!(!forged.verify(&vk, &params, &memberships, &nonce).unwrap());
+    }
+}
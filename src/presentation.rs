@@ -0,0 +1,783 @@
+// Transferable and non-transferable presentations. A transferable presentation is just the proof of knowledge
+// of signature with the revealed messages, anyone holding it can forward it to another verifier. A
+// non-transferable presentation is bound to a holder key: the hash of the holder's public key is one of the
+// signed messages, it is revealed, and the holder signs the presentation transcript (which includes the
+// verifier's nonce) with a Schnorr signature under that key. The verifier's `PresentationPolicy` decides which
+// modes are acceptable.
+// For freshness, the verifier sends a `FreshnessChallenge` with its current time and the holder derives the
+// challenge of the proof of knowledge from it, so a presentation cannot be computed before the verifier asked for
+// it. `Presentation::verify_fresh` rejects presentations answering a challenge more than the allowed clock skew
+// away from the time of verification.
+// `verify_presentation` is the path for applications to consume presentations: rather than a bool, it returns a
+// `VerifiedPresentation` with the disclosed messages, the id of the key of the issuer and the context the
+// presentation was verified in, and fails with `PSError::InvalidPresentation` if it does not verify.
+// `verify_presentations_batch` verifies many presentations, of the same or different issuers, with one
+// multi-pairing: the pairing checks e(sigma_1_i, J_X_tilde_i) * e(-sigma_2_i, g_tilde) == 1 of each presentation are
+// raised to random r_i and multiplied, so that sharing g_tilde they are the single check
+// prod_i e(sigma_1_i^r_i, J_X_tilde_i) * e(-sum_i sigma_2_i^r_i, g_tilde) == 1 of one final exponentiation, which
+// passes for an invalid presentation with negligible probability over the r_i. When it fails, each presentation is
+// verified alone to tell which ones are invalid.
+
+use crate::challenge_domain::HOLDER_SIGNATURE;
+use crate::curve::{ate_multi_pairing, scalar_mul, FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::inspect::fingerprint;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::PoKOfSignatureProof;
+use crate::{SignatureGroup, VerkeyGroup};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentationMode {
+    Transferable,
+    NonTransferable,
+}
+
+/// Key of the holder used to sign non-transferable presentations. The public key is `g_tilde^secret`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HolderKey {
+    secret: FieldElement,
+    pub public: VerkeyGroup,
+}
+
+/// Schnorr signature (c, s) with c = H(g_tilde^s * public^-c || public || message)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HolderSignature {
+    pub challenge: FieldElement,
+    pub response: FieldElement,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HolderBinding {
+    pub holder_key: VerkeyGroup,
+    pub signature: HolderSignature,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Presentation {
+    pub proof: PoKOfSignatureProof,
+    pub revealed_msgs: HashMap<usize, FieldElement>,
+    /// Present only for non-transferable presentations
+    pub holder_binding: Option<HolderBinding>,
+    /// Present only for presentations answering a freshness challenge
+    #[serde(default)]
+    pub freshness: Option<FreshnessChallenge>,
+}
+
+/// Challenge of the verifier for a fresh presentation
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FreshnessChallenge {
+    /// Time of the verifier when creating the challenge, seconds since the Unix epoch
+    pub timestamp: u64,
+    pub nonce: Vec<u8>,
+}
+
+/// Presentation which verified, holding what the application can trust of it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedPresentation {
+    /// Messages disclosed by the holder, by index in the credential
+    pub disclosed: BTreeMap<usize, FieldElement>,
+    /// Fingerprint of the verkey of the issuer, as in `inspect::Report`
+    pub issuer_key_id: String,
+    pub context: PresentationContext,
+}
+
+/// Context a presentation was verified in
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PresentationContext {
+    pub mode: PresentationMode,
+    /// Key of the holder for non-transferable presentations
+    pub holder_key: Option<VerkeyGroup>,
+    /// Nonce of the verifier
+    pub nonce: Vec<u8>,
+    /// Challenge answered by fresh presentations
+    pub freshness: Option<FreshnessChallenge>,
+}
+
+/// Presentation to verify in a batch with what `Presentation::verify` takes for it
+#[derive(Clone, Debug)]
+pub struct BatchItem<'a> {
+    pub presentation: &'a Presentation,
+    pub issuer: &'a Verkey,
+    pub challenge: &'a FieldElement,
+    pub nonce: &'a [u8],
+}
+
+/// Modes of presentation the verifier accepts
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresentationPolicy {
+    /// Index of the signed message holding the holder key, `None` if transferable presentations are acceptable
+    pub holder_key_index: Option<usize>,
+}
+
+impl HolderKey {
+    pub fn new(params: &Params) -> Self {
+        let secret = FieldElement::random();
+        let public = &params.g_tilde * &secret;
+        Self { secret, public }
+    }
+
+    /// Message to include in the credential at the index the verifier expects the holder key at
+    pub fn to_message(&self) -> FieldElement {
+        holder_key_message(&self.public)
+    }
+
+    pub fn sign(&self, message: &[u8], params: &Params) -> HolderSignature {
+        let k = FieldElement::random();
+        let R = &params.g_tilde * &k;
+        let challenge = holder_signature_challenge(&R, &self.public, message);
+        let response = k + (&challenge * &self.secret);
+        HolderSignature {
+            challenge,
+            response,
+        }
+    }
+}
+
+/// Hash of the holder's public key as a message
+pub fn holder_key_message(public: &VerkeyGroup) -> FieldElement {
+    let mut bytes = b"PS holder key : ".to_vec();
+    bytes.append(&mut public.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl HolderSignature {
+    pub fn verify(&self, message: &[u8], public: &VerkeyGroup, params: &Params) -> bool {
+        // R = g_tilde^s * public^-c
+        let R = params
+            .g_tilde
+            .binary_scalar_mul(public, &self.response, &(-&self.challenge));
+        holder_signature_challenge(&R, public, message) == self.challenge
+    }
+}
+
+fn holder_signature_challenge(
+    R: &VerkeyGroup,
+    public: &VerkeyGroup,
+    message: &[u8],
+) -> FieldElement {
+    let mut bytes = HOLDER_SIGNATURE.tag();
+    bytes.append(&mut R.to_bytes());
+    bytes.append(&mut public.to_bytes());
+    bytes.extend_from_slice(message);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl FreshnessChallenge {
+    /// Challenge with a random nonce
+    pub fn new(timestamp: u64) -> Self {
+        Self {
+            timestamp,
+            nonce: FieldElement::random().to_bytes(),
+        }
+    }
+
+    /// Challenge of the proof of knowledge of signature from the bytes for the challenge, `PoKOfSignature::to_bytes`
+    /// for the holder. Hashes them with the timestamp and the nonce.
+    pub fn proof_challenge(&self, bytes_for_challenge: &[u8]) -> FieldElement {
+        let mut bytes = bytes_for_challenge.to_vec();
+        bytes.extend_from_slice(b"PS freshness : ");
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce);
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+impl PresentationPolicy {
+    /// Accept presentations of either mode
+    pub fn transferable() -> Self {
+        Self {
+            holder_key_index: None,
+        }
+    }
+
+    /// Only accept presentations bound to the holder key signed as the message at `holder_key_index`
+    pub fn non_transferable(holder_key_index: usize) -> Self {
+        Self {
+            holder_key_index: Some(holder_key_index),
+        }
+    }
+}
+
+impl Presentation {
+    pub fn new_transferable(
+        proof: PoKOfSignatureProof,
+        revealed_msgs: HashMap<usize, FieldElement>,
+    ) -> Self {
+        Self {
+            proof,
+            revealed_msgs,
+            holder_binding: None,
+            freshness: None,
+        }
+    }
+
+    /// Create a presentation only usable with the verifier that gave `nonce`. The holder key must have been
+    /// signed in the credential and its message revealed in `revealed_msgs`.
+    pub fn new_non_transferable(
+        proof: PoKOfSignatureProof,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        holder_key: &HolderKey,
+        nonce: &[u8],
+        params: &Params,
+    ) -> Self {
+        let transcript = transcript(&proof, &revealed_msgs, Some(&holder_key.public), nonce);
+        let signature = holder_key.sign(&transcript, params);
+        Self {
+            proof,
+            revealed_msgs,
+            holder_binding: Some(HolderBinding {
+                holder_key: holder_key.public.clone(),
+                signature,
+            }),
+            freshness: None,
+        }
+    }
+
+    /// Mark the presentation as answering `freshness`. The proof must have been created with the challenge
+    /// `freshness.proof_challenge(&pok.to_bytes())`.
+    pub fn with_freshness(mut self, freshness: FreshnessChallenge) -> Self {
+        self.freshness = Some(freshness);
+        self
+    }
+
+    pub fn mode(&self) -> PresentationMode {
+        match self.holder_binding {
+            Some(_) => PresentationMode::NonTransferable,
+            None => PresentationMode::Transferable,
+        }
+    }
+
+    /// Verify the proof of knowledge of signature and, when the policy requires a non-transferable
+    /// presentation, that the holder key was signed and revealed at the expected index and that the holder
+    /// signed the transcript with `nonce`. Fails with an error when the presentation's mode is not accepted.
+    pub fn verify(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        challenge: &FieldElement,
+        nonce: &[u8],
+        policy: &PresentationPolicy,
+    ) -> Result<bool, PSError> {
+        Ok(self.verify_holder_binding(params, nonce, policy)?
+            && self
+                .proof
+                .verify(vk, params, self.revealed_msgs.clone(), challenge)?)
+    }
+
+    /// Checks of `verify` for the policy, without the proof of knowledge of signature
+    fn verify_holder_binding(
+        &self,
+        params: &Params,
+        nonce: &[u8],
+        policy: &PresentationPolicy,
+    ) -> Result<bool, PSError> {
+        if let Some(idx) = policy.holder_key_index {
+            let binding = match &self.holder_binding {
+                Some(b) => b,
+                None => return Err(PSError::TransferablePresentationNotAccepted),
+            };
+            let holder_key_msg = match self.revealed_msgs.get(&idx) {
+                Some(m) => m,
+                None => return Err(PSError::HolderKeyNotRevealed { idx }),
+            };
+            if *holder_key_msg != holder_key_message(&binding.holder_key) {
+                return Ok(false);
+            }
+            let transcript = transcript(
+                &self.proof,
+                &self.revealed_msgs,
+                Some(&binding.holder_key),
+                nonce,
+            );
+            return Ok(binding
+                .signature
+                .verify(&transcript, &binding.holder_key, params));
+        }
+        Ok(true)
+    }
+
+    /// Verify like `verify` a presentation answering `freshness`, the challenge the verifier sent, at time `now`.
+    /// The challenge of the proof is computed from `freshness` rather than given. Fails with
+    /// `PSError::StalePresentation` when `now` is more than `max_clock_skew` seconds away from the timestamp of
+    /// the challenge, and returns false when the presentation does not answer `freshness`.
+    pub fn verify_fresh(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        freshness: &FreshnessChallenge,
+        policy: &PresentationPolicy,
+        now: u64,
+        max_clock_skew: u64,
+    ) -> Result<bool, PSError> {
+        let skew = now.abs_diff(freshness.timestamp);
+        if skew > max_clock_skew {
+            return Err(PSError::StalePresentation {
+                timestamp: freshness.timestamp,
+                now,
+                max_clock_skew,
+            });
+        }
+        if self.freshness.as_ref() != Some(freshness) {
+            return Ok(false);
+        }
+        let revealed_msg_indices = self.revealed_msgs.keys().cloned().collect();
+        let challenge = freshness.proof_challenge(&self.proof.get_bytes_for_challenge(
+            revealed_msg_indices,
+            vk,
+            params,
+        ));
+        self.verify(vk, params, &challenge, &freshness.nonce, policy)
+    }
+}
+
+/// Verify `presentation` of a credential of `issuer` like `Presentation::verify` and return what it discloses. Fails
+/// with `PSError::InvalidPresentation` if it does not verify and with the errors of `Presentation::verify` for the
+/// policy.
+pub fn verify_presentation(
+    presentation: &Presentation,
+    issuer: &Verkey,
+    params: &Params,
+    challenge: &FieldElement,
+    nonce: &[u8],
+    policy: &PresentationPolicy,
+) -> Result<VerifiedPresentation, PSError> {
+    if !presentation.verify(issuer, params, challenge, nonce, policy)? {
+        return Err(PSError::InvalidPresentation);
+    }
+    Ok(verified(presentation, issuer, policy, nonce, None))
+}
+
+/// Same as `verify_presentation` for a presentation answering `freshness`, verified like
+/// `Presentation::verify_fresh`
+pub fn verify_fresh_presentation(
+    presentation: &Presentation,
+    issuer: &Verkey,
+    params: &Params,
+    freshness: &FreshnessChallenge,
+    policy: &PresentationPolicy,
+    now: u64,
+    max_clock_skew: u64,
+) -> Result<VerifiedPresentation, PSError> {
+    if !presentation.verify_fresh(issuer, params, freshness, policy, now, max_clock_skew)? {
+        return Err(PSError::InvalidPresentation);
+    }
+    Ok(verified(
+        presentation,
+        issuer,
+        policy,
+        &freshness.nonce,
+        Some(freshness.clone()),
+    ))
+}
+
+/// Verify `items` like `Presentation::verify` with one multi-pairing for all pairing checks, see the module doc.
+/// Fails with `PSError::InvalidPresentations` with the indices of the items that do not verify or whose verification
+/// fails.
+pub fn verify_presentations_batch(
+    items: &[BatchItem],
+    params: &Params,
+    policy: &PresentationPolicy,
+) -> Result<(), PSError> {
+    let mut invalid = vec![];
+    let mut sigma_1s = vec![];
+    let mut J_X_tildes = vec![];
+    let mut sigma_2_sum = SignatureGroup::identity();
+    for (idx, item) in items.iter().enumerate() {
+        let presentation = item.presentation;
+        let J_X_tilde = match presentation.verify_holder_binding(params, item.nonce, policy) {
+            Ok(true) => presentation.proof.verify_without_pairing(
+                item.issuer,
+                params,
+                &presentation.revealed_msgs,
+                item.challenge,
+            ),
+            Ok(false) => Ok(None),
+            Err(e) => Err(e),
+        };
+        match J_X_tilde {
+            Ok(Some(J_X_tilde)) => {
+                let r = FieldElement::random();
+                sigma_1s.push(scalar_mul(&presentation.proof.sig.sigma_1, &r));
+                sigma_2_sum += scalar_mul(&presentation.proof.sig.sigma_2, &r);
+                J_X_tildes.push(J_X_tilde);
+            }
+            _ => invalid.push(idx),
+        }
+    }
+    if !sigma_1s.is_empty() {
+        let neg_sigma_2_sum = -&sigma_2_sum;
+        let mut pairs: Vec<(&SignatureGroup, &VerkeyGroup)> =
+            sigma_1s.iter().zip(J_X_tildes.iter()).collect();
+        pairs.push((&neg_sigma_2_sum, &params.g_tilde));
+        if !ate_multi_pairing(&pairs).is_one() {
+            // Find the presentations failing the pairing check
+            for (idx, item) in items.iter().enumerate() {
+                if invalid.contains(&idx) {
+                    continue;
+                }
+                match item.presentation.verify(
+                    item.issuer,
+                    params,
+                    item.challenge,
+                    item.nonce,
+                    policy,
+                ) {
+                    Ok(true) => (),
+                    _ => invalid.push(idx),
+                }
+            }
+            invalid.sort_unstable();
+        }
+    }
+    if !invalid.is_empty() {
+        return Err(PSError::InvalidPresentations { indices: invalid });
+    }
+    Ok(())
+}
+
+fn verified(
+    presentation: &Presentation,
+    issuer: &Verkey,
+    policy: &PresentationPolicy,
+    nonce: &[u8],
+    freshness: Option<FreshnessChallenge>,
+) -> VerifiedPresentation {
+    VerifiedPresentation {
+        disclosed: presentation
+            .revealed_msgs
+            .iter()
+            .map(|(i, m)| (*i, m.clone()))
+            .collect(),
+        issuer_key_id: fingerprint(&issuer.to_bytes()),
+        context: PresentationContext {
+            mode: presentation.mode(),
+            // The holder key is only checked for policies of non-transferable presentations
+            holder_key: policy
+                .holder_key_index
+                .and(presentation.holder_binding.as_ref())
+                .map(|b| b.holder_key.clone()),
+            nonce: nonce.to_vec(),
+            freshness,
+        },
+    }
+}
+
+/// Bytes signed by the holder: proof || revealed messages in increasing order of index || holder key || nonce
+fn transcript(
+    proof: &PoKOfSignatureProof,
+    revealed_msgs: &HashMap<usize, FieldElement>,
+    holder_key: Option<&VerkeyGroup>,
+    nonce: &[u8],
+) -> Vec<u8> {
+    let mut bytes = proof.to_bytes();
+    let sorted: BTreeMap<_, _> = revealed_msgs.iter().collect();
+    for (i, m) in sorted {
+        bytes.extend_from_slice(&(*i as u64).to_be_bytes());
+        bytes.append(&mut m.to_bytes());
+    }
+    if let Some(k) = holder_key {
+        bytes.append(&mut k.to_bytes());
+    }
+    bytes.extend_from_slice(nonce);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+    use crate::pok_sig::PoKOfSignature;
+    use crate::signature::Signature;
+    use std::collections::HashSet;
+
+    const HOLDER_KEY_INDEX: usize = 2;
+
+    fn presentation_proof(
+        holder_key: &HolderKey,
+        params: &Params,
+    ) -> (
+        Verkey,
+        PoKOfSignatureProof,
+        HashMap<usize, FieldElement>,
+        FieldElement,
+    ) {
+        let count_msgs = 4;
+        let (sk, vk) = keygen(count_msgs, params).unwrap();
+        let mut msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        msgs[HOLDER_KEY_INDEX] = holder_key.to_message();
+        let sig = Signature::new(msgs.as_slice(), &sk, params).unwrap();
+
+        let revealed_msg_indices: HashSet<usize> = vec![0, HOLDER_KEY_INDEX].into_iter().collect();
+        let pok = PoKOfSignature::init(
+            &sig,
+            &vk,
+            params,
+            msgs.clone(),
+            None,
+            revealed_msg_indices.clone(),
+        )
+        .unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        let mut revealed_msgs = HashMap::new();
+        for i in revealed_msg_indices {
+            revealed_msgs.insert(i, msgs[i].clone());
+        }
+        (vk, proof, revealed_msgs, chal)
+    }
+
+    #[test]
+    fn test_holder_signature() {
+        let params = Params::new("test".as_bytes());
+        let key = HolderKey::new(&params);
+        let sig = key.sign(b"transcript", &params);
+        assert!(sig.verify(b"transcript", &key.public, &params));
+        assert!(!sig.verify(b"other", &key.public, &params));
+        assert!(!sig.verify(b"transcript", &HolderKey::new(&params).public, &params));
+    }
+
+    #[test]
+    fn test_presentation_modes() {
+        let params = Params::new("test".as_bytes());
+        let holder_key = HolderKey::new(&params);
+        let (vk, proof, revealed_msgs, chal) = presentation_proof(&holder_key, &params);
+        let nonce = b"verifier nonce";
+
+        let transferable = Presentation::new_transferable(proof.clone(), revealed_msgs.clone());
+        assert_eq!(transferable.mode(), PresentationMode::Transferable);
+        assert!(transferable
+            .verify(
+                &vk,
+                &params,
+                &chal,
+                nonce,
+                &PresentationPolicy::transferable()
+            )
+            .unwrap());
+        match transferable.verify(
+            &vk,
+            &params,
+            &chal,
+            nonce,
+            &PresentationPolicy::non_transferable(HOLDER_KEY_INDEX),
+        ) {
+            Err(PSError::TransferablePresentationNotAccepted) => (),
+            r => panic!(
+                "Expected TransferablePresentationNotAccepted but got {:?}",
+                r
+            ),
+        }
+
+        let non_transferable = Presentation::new_non_transferable(
+            proof.clone(),
+            revealed_msgs.clone(),
+            &holder_key,
+            nonce,
+            &params,
+        );
+        assert_eq!(non_transferable.mode(), PresentationMode::NonTransferable);
+        let policy = PresentationPolicy::non_transferable(HOLDER_KEY_INDEX);
+        assert!(non_transferable
+            .verify(&vk, &params, &chal, nonce, &policy)
+            .unwrap());
+        // Non-transferable presentations also satisfy a transferable policy
+        assert!(non_transferable
+            .verify(
+                &vk,
+                &params,
+                &chal,
+                nonce,
+                &PresentationPolicy::transferable()
+            )
+            .unwrap());
+
+        // Forwarded to a verifier with another nonce
+        assert!(!non_transferable
+            .verify(&vk, &params, &chal, b"other nonce", &policy)
+            .unwrap());
+
+        // Someone else re-binding the presentation to their own key
+        let other_key = HolderKey::new(&params);
+        let rebound = Presentation::new_non_transferable(
+            proof.clone(),
+            revealed_msgs.clone(),
+            &other_key,
+            nonce,
+            &params,
+        );
+        assert!(!rebound.verify(&vk, &params, &chal, nonce, &policy).unwrap());
+
+        // Result of verification for the application
+        let verified =
+            verify_presentation(&non_transferable, &vk, &params, &chal, nonce, &policy).unwrap();
+        let expected: BTreeMap<usize, FieldElement> = revealed_msgs.clone().into_iter().collect();
+        assert_eq!(verified.disclosed, expected);
+        assert_eq!(
+            verified.issuer_key_id,
+            crate::inspect::describe(&vk).unwrap().fingerprint
+        );
+        assert_eq!(verified.context.mode, PresentationMode::NonTransferable);
+        assert_eq!(verified.context.holder_key, Some(holder_key.public.clone()));
+        assert_eq!(verified.context.nonce, nonce.to_vec());
+        let verified = verify_presentation(
+            &transferable,
+            &vk,
+            &params,
+            &chal,
+            nonce,
+            &PresentationPolicy::transferable(),
+        )
+        .unwrap();
+        assert_eq!(verified.context.holder_key, None);
+        match verify_presentation(&rebound, &vk, &params, &chal, nonce, &policy) {
+            Err(PSError::InvalidPresentation) => (),
+            r => panic!("Expected InvalidPresentation but got {:?}", r),
+        }
+
+        // Holder key at an index that is not revealed
+        match non_transferable.verify(
+            &vk,
+            &params,
+            &chal,
+            nonce,
+            &PresentationPolicy::non_transferable(1),
+        ) {
+            Err(PSError::HolderKeyNotRevealed { idx: 1 }) => (),
+            r => panic!("Expected HolderKeyNotRevealed but got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_fresh_presentation() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let revealed_msg_indices: HashSet<usize> = vec![1].into_iter().collect();
+        let revealed_msgs: HashMap<usize, FieldElement> =
+            vec![(1, msgs[1].clone())].into_iter().collect();
+        let policy = PresentationPolicy::transferable();
+
+        // Verifier sends the challenge, the holder answers it
+        let freshness = FreshnessChallenge::new(1_000);
+        let pok = PoKOfSignature::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone(),
+            None,
+            revealed_msg_indices.clone(),
+        )
+        .unwrap();
+        let chal = freshness.proof_challenge(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        let fresh = Presentation::new_transferable(proof, revealed_msgs.clone())
+            .with_freshness(freshness.clone());
+
+        assert!(fresh
+            .verify_fresh(&vk, &params, &freshness, &policy, 1_020, 30)
+            .unwrap());
+        let verified =
+            verify_fresh_presentation(&fresh, &vk, &params, &freshness, &policy, 1_020, 30)
+                .unwrap();
+        assert_eq!(verified.context.freshness, Some(freshness.clone()));
+        assert_eq!(verified.context.nonce, freshness.nonce);
+        assert_eq!(verified.disclosed.get(&1), Some(&msgs[1]));
+        // Verifier clock behind the challenge, within the skew
+        assert!(fresh
+            .verify_fresh(&vk, &params, &freshness, &policy, 990, 30)
+            .unwrap());
+        match fresh.verify_fresh(&vk, &params, &freshness, &policy, 1_031, 30) {
+            Err(PSError::StalePresentation { .. }) => (),
+            r => panic!("Expected StalePresentation but got {:?}", r),
+        }
+
+        // Presentation for another challenge or relabelled with the new challenge
+        let freshness_1 = FreshnessChallenge::new(1_000);
+        assert!(!fresh
+            .verify_fresh(&vk, &params, &freshness_1, &policy, 1_000, 30)
+            .unwrap());
+        let relabelled = fresh.clone().with_freshness(freshness_1.clone());
+        assert!(!relabelled
+            .verify_fresh(&vk, &params, &freshness_1, &policy, 1_000, 30)
+            .unwrap());
+
+        // Precomputed presentation not answering any challenge
+        let pok =
+            PoKOfSignature::init(&sig, &vk, &params, msgs, None, revealed_msg_indices).unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let stale = Presentation::new_transferable(pok.gen_proof(&chal).unwrap(), revealed_msgs);
+        assert!(!stale
+            .verify_fresh(&vk, &params, &freshness, &policy, 1_000, 30)
+            .unwrap());
+    }
+
+    fn batch_items<'a>(
+        presentations: &'a [Presentation],
+        issuers: &'a [Verkey],
+        challenges: &'a [FieldElement],
+        nonce: &'a [u8],
+    ) -> Vec<BatchItem<'a>> {
+        presentations
+            .iter()
+            .zip(issuers.iter().zip(challenges.iter()))
+            .map(|(presentation, (issuer, challenge))| BatchItem {
+                presentation,
+                issuer,
+                challenge,
+                nonce,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_presentations_batch() {
+        let params = Params::new("test".as_bytes());
+        let nonce = b"nonce";
+        let holder_key = HolderKey::new(&params);
+        // Presentations of different issuers, one per credential
+        let proofs: Vec<_> = (0..4)
+            .map(|_| presentation_proof(&holder_key, &params))
+            .collect();
+        let mut presentations: Vec<Presentation> = proofs
+            .iter()
+            .map(|(_, proof, revealed_msgs, _)| {
+                Presentation::new_transferable(proof.clone(), revealed_msgs.clone())
+            })
+            .collect();
+        let vks: Vec<Verkey> = proofs.iter().map(|p| p.0.clone()).collect();
+        let chals: Vec<FieldElement> = proofs.iter().map(|p| p.3.clone()).collect();
+        let policy = PresentationPolicy::transferable();
+        verify_presentations_batch(
+            &batch_items(&presentations, &vks, &chals, nonce),
+            &params,
+            &policy,
+        )
+        .unwrap();
+        verify_presentations_batch(&[], &params, &policy).unwrap();
+
+        // A revealed message changed only fails the pairing check, a presentation checked with the key of another
+        // issuer fails the proof of knowledge as well
+        presentations[1]
+            .revealed_msgs
+            .insert(0, FieldElement::random());
+        let mut batch = batch_items(&presentations, &vks, &chals, nonce);
+        batch[3].issuer = &vks[0];
+        match verify_presentations_batch(&batch, &params, &policy) {
+            Err(PSError::InvalidPresentations { indices }) => assert_eq!(indices, vec![1, 3]),
+            r => panic!("Expected InvalidPresentations but got {:?}", r),
+        }
+        // Transferable presentations are not accepted by the policy
+        match verify_presentations_batch(
+            &batch_items(&presentations[2..], &vks[2..], &chals[2..], nonce),
+            &params,
+            &PresentationPolicy::non_transferable(HOLDER_KEY_INDEX),
+        ) {
+            Err(PSError::InvalidPresentations { indices }) => assert_eq!(indices, vec![0, 1]),
+            r => panic!("Expected InvalidPresentations but got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,258 @@
+// Type-state builder for presentations. `PresentationBuilder` goes through the states `Incomplete`, `Disclosed` and
+// `Bound`, each with only the methods valid in it, so a presentation is always created with its disclosed messages
+// and bound to a nonce of the verifier: `finish` is only on `Bound`, which only `bind_context` reaches. The
+// configuration is checked as it is given, the messages against the signature and the verkey in `new`, each
+// disclosed index in `disclose`, the holder key in `bind_holder` and the nonce in `bind_context`, each failing
+// with its own error, rather than when the verifier checks the pairing.
+// The nonce is that of a `FreshnessChallenge` and the challenge of the proof is derived from it, so the
+// presentation verifies with `Presentation::verify_fresh` or `verify_fresh_presentation`.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::PoKOfSignature;
+use crate::presentation::{FreshnessChallenge, HolderKey, Presentation};
+use crate::signature::Signature;
+use std::collections::{HashMap, HashSet};
+
+/// State of a builder before the disclosed messages are set
+#[derive(Clone, Debug)]
+pub struct Incomplete;
+
+/// State of a builder with the disclosed messages set, before it is bound to a nonce
+#[derive(Clone, Debug)]
+pub struct Disclosed;
+
+/// State of a builder ready to `finish`, with the challenge of the verifier
+#[derive(Clone, Debug)]
+pub struct Bound {
+    freshness: FreshnessChallenge,
+}
+
+#[derive(Clone, Debug)]
+pub struct PresentationBuilder<'a, S> {
+    sig: &'a Signature,
+    vk: &'a Verkey,
+    params: &'a Params,
+    messages: Vec<FieldElement>,
+    revealed: HashSet<usize>,
+    /// For non-transferable presentations
+    holder: Option<&'a HolderKey>,
+    state: S,
+}
+
+impl<'a, S> PresentationBuilder<'a, S> {
+    fn into_state<T>(self, state: T) -> PresentationBuilder<'a, T> {
+        PresentationBuilder {
+            sig: self.sig,
+            vk: self.vk,
+            params: self.params,
+            messages: self.messages,
+            revealed: self.revealed,
+            holder: self.holder,
+            state,
+        }
+    }
+
+    fn check_index(&self, idx: usize) -> Result<(), PSError> {
+        if idx >= self.messages.len() {
+            return Err(PSError::InvalidIndex {
+                idx,
+                valid: format!("[0, {})", self.messages.len()),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<'a> PresentationBuilder<'a, Incomplete> {
+    /// Builder of a presentation of `sig` on `messages`. Fails if the count of messages is not that of `vk` or if
+    /// `sig` does not verify.
+    pub fn new(
+        sig: &'a Signature,
+        messages: Vec<FieldElement>,
+        vk: &'a Verkey,
+        params: &'a Params,
+    ) -> Result<Self, PSError> {
+        Signature::check_verkey_and_messages_compat(&messages, vk)?;
+        if !sig.verify(messages.clone(), vk, params)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signature does not verify on the messages"),
+            });
+        }
+        Ok(Self {
+            sig,
+            vk,
+            params,
+            messages,
+            revealed: HashSet::new(),
+            holder: None,
+            state: Incomplete,
+        })
+    }
+
+    /// Disclose the messages at `indices`, failing with `PSError::InvalidIndex` for an index out of range
+    pub fn disclose(
+        self,
+        indices: &[usize],
+    ) -> Result<PresentationBuilder<'a, Disclosed>, PSError> {
+        let mut builder = self;
+        for idx in indices {
+            builder.check_index(*idx)?;
+            builder.revealed.insert(*idx);
+        }
+        Ok(builder.into_state(Disclosed))
+    }
+}
+
+impl<'a> PresentationBuilder<'a, Disclosed> {
+    /// Make the presentation non-transferable with `holder_key`, signed as the message at `holder_key_index`, which
+    /// is disclosed. Fails with `PSError::HolderKeyMismatch` if that message is not the holder key.
+    pub fn bind_holder(
+        mut self,
+        holder_key: &'a HolderKey,
+        holder_key_index: usize,
+    ) -> Result<Self, PSError> {
+        self.check_index(holder_key_index)?;
+        if self.messages[holder_key_index] != holder_key.to_message() {
+            return Err(PSError::HolderKeyMismatch {
+                idx: holder_key_index,
+            });
+        }
+        self.revealed.insert(holder_key_index);
+        self.holder = Some(holder_key);
+        Ok(self)
+    }
+
+    /// Bind the presentation to `freshness`, the challenge of the verifier. Fails with `PSError::MissingNonce` if its
+    /// nonce is empty.
+    pub fn bind_context(
+        self,
+        freshness: FreshnessChallenge,
+    ) -> Result<PresentationBuilder<'a, Bound>, PSError> {
+        if freshness.nonce.is_empty() {
+            return Err(PSError::MissingNonce);
+        }
+        Ok(self.into_state(Bound { freshness }))
+    }
+}
+
+impl<'a> PresentationBuilder<'a, Bound> {
+    /// Create the proof of knowledge of signature with the challenge of the freshness challenge and the presentation
+    pub fn finish(self) -> Result<Presentation, PSError> {
+        let freshness = self.state.freshness;
+        let pok = PoKOfSignature::init(
+            self.sig,
+            self.vk,
+            self.params,
+            self.messages.clone(),
+            None,
+            self.revealed.clone(),
+        )?;
+        let challenge = freshness.proof_challenge(&pok.to_bytes());
+        let proof = pok.gen_proof(&challenge)?;
+        let messages = &self.messages;
+        let revealed_msgs: HashMap<usize, FieldElement> = self
+            .revealed
+            .iter()
+            .map(|i| (*i, messages[*i].clone()))
+            .collect();
+        let presentation = match self.holder {
+            Some(holder_key) => Presentation::new_non_transferable(
+                proof,
+                revealed_msgs,
+                holder_key,
+                &freshness.nonce,
+                self.params,
+            ),
+            None => Presentation::new_transferable(proof, revealed_msgs),
+        };
+        Ok(presentation.with_freshness(freshness))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+    use crate::presentation::{verify_fresh_presentation, PresentationMode, PresentationPolicy};
+
+    #[test]
+    fn test_presentation_builder() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let holder_key = HolderKey::new(&params);
+        let mut msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        msgs[3] = holder_key.to_message();
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        let now = 1_600_000_000;
+        let freshness = FreshnessChallenge::new(now);
+
+        let presentation = PresentationBuilder::new(&sig, msgs.clone(), &vk, &params)
+            .unwrap()
+            .disclose(&[0, 2])
+            .unwrap()
+            .bind_context(freshness.clone())
+            .unwrap()
+            .finish()
+            .unwrap();
+        let verified = verify_fresh_presentation(
+            &presentation,
+            &vk,
+            &params,
+            &freshness,
+            &PresentationPolicy::transferable(),
+            now,
+            0,
+        )
+        .unwrap();
+        assert_eq!(verified.disclosed.len(), 2);
+        assert_eq!(verified.disclosed[&2], msgs[2]);
+
+        let presentation = PresentationBuilder::new(&sig, msgs.clone(), &vk, &params)
+            .unwrap()
+            .disclose(&[0])
+            .unwrap()
+            .bind_holder(&holder_key, 3)
+            .unwrap()
+            .bind_context(freshness.clone())
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(presentation.mode(), PresentationMode::NonTransferable);
+        assert!(presentation
+            .verify_fresh(
+                &vk,
+                &params,
+                &freshness,
+                &PresentationPolicy::non_transferable(3),
+                now,
+                0
+            )
+            .unwrap());
+
+        // Invalid configurations fail when given
+        let builder = PresentationBuilder::new(&sig, msgs.clone(), &vk, &params).unwrap();
+        match builder.clone().disclose(&[1, 4]) {
+            Err(PSError::InvalidIndex { idx: 4, .. }) => (),
+            r => panic!("Expected InvalidIndex but got {:?}", r.map(|_| ())),
+        }
+        let disclosed = builder.disclose(&[1]).unwrap();
+        match disclosed.clone().bind_holder(&holder_key, 2) {
+            Err(PSError::HolderKeyMismatch { idx: 2 }) => (),
+            r => panic!("Expected HolderKeyMismatch but got {:?}", r.map(|_| ())),
+        }
+        let mut no_nonce = freshness.clone();
+        no_nonce.nonce = vec![];
+        match disclosed.bind_context(no_nonce) {
+            Err(PSError::MissingNonce) => (),
+            r => panic!("Expected MissingNonce but got {:?}", r.map(|_| ())),
+        }
+        assert!(PresentationBuilder::new(&sig, msgs[..3].to_vec(), &vk, &params).is_err());
+        let mut other_msgs = msgs.clone();
+        other_msgs[0] = FieldElement::random();
+        assert!(PresentationBuilder::new(&sig, other_msgs, &vk, &params).is_err());
+    }
+}
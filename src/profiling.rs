@@ -0,0 +1,219 @@
+// Counters of the operations and allocations of a call, behind the feature `profiling`, for integrators deciding
+// what fits on a constrained device. `profile` runs a closure and returns a `ProfileReport` of the pairings, final
+// exponentiations and scalar multiplications done by the signing, proving and verification routines of this crate,
+// counted where they call amcl_wrapper: `ate_pairing` and `ate_2_pairing`, the terms of multi-scalar
+// multiplications and the scalar multiplications of signing and randomizing signatures. Additions, field
+// operations and hashing to the group are not counted.
+// Allocations are counted by `CountingAllocator`, which the binary installs with `#[global_allocator]`, a library
+// cannot. Without it the report has no allocations and `allocations_tracked` is false.
+// Counters are of the thread, so calls profiled on several threads do not count each other's operations, and work
+// a call hands to other threads is not counted, e.g. the keys of `rsskeygen` computed with rayon.
+// Without the feature, the recording functions called by the rest of the crate do nothing.
+
+#[cfg(feature = "profiling")]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(feature = "profiling")]
+use std::cell::Cell;
+#[cfg(feature = "profiling")]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "profiling")]
+thread_local! {
+    // No lazy initialization since the allocator uses them
+    static PAIRINGS: Cell<usize> = const { Cell::new(0) };
+    static FINAL_EXPONENTIATIONS: Cell<usize> = const { Cell::new(0) };
+    static SCALAR_MULS: Cell<usize> = const { Cell::new(0) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static HEAP_BYTES: Cell<isize> = const { Cell::new(0) };
+    static PEAK_HEAP_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+#[cfg(feature = "profiling")]
+static ALLOCATOR_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Operations and allocations of a call given to `profile`
+#[cfg(feature = "profiling")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileReport {
+    /// Miller loops
+    pub pairings: usize,
+    pub final_exponentiations: usize,
+    /// Scalar multiplications in either group, a multi-scalar multiplication counts one per term
+    pub scalar_muls: usize,
+    /// Count of heap allocations, including reallocations
+    pub allocations: usize,
+    /// Most bytes on the heap during the call above those when it started, counting memory freed on the thread
+    /// even if allocated on another
+    pub peak_heap_bytes: usize,
+    /// Whether `CountingAllocator` is the global allocator, else `allocations` and `peak_heap_bytes` are 0
+    pub allocations_tracked: bool,
+}
+
+/// Allocator of the system counting allocations for `ProfileReport`, install it in the binary with
+/// `#[global_allocator] static A: CountingAllocator = CountingAllocator;`
+#[cfg(feature = "profiling")]
+pub struct CountingAllocator;
+
+#[cfg(feature = "profiling")]
+fn add(counter: &'static std::thread::LocalKey<Cell<usize>>, count: usize) {
+    // Fails only while the thread is being destroyed
+    let _ = counter.try_with(|c| c.set(c.get() + count));
+}
+
+#[cfg(feature = "profiling")]
+fn heap_changed(bytes: isize) {
+    let _ = HEAP_BYTES.try_with(|h| {
+        h.set(h.get() + bytes);
+        let _ = PEAK_HEAP_BYTES.try_with(|p| p.set(p.get().max(h.get())));
+    });
+}
+
+#[cfg(feature = "profiling")]
+impl CountingAllocator {
+    fn allocated(size: usize) {
+        ALLOCATOR_INSTALLED.store(true, Ordering::Relaxed);
+        add(&ALLOCATIONS, 1);
+        heap_changed(size as isize);
+    }
+}
+
+#[cfg(feature = "profiling")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        heap_changed(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            heap_changed(-(layout.size() as isize));
+            Self::allocated(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Run `f` and count its operations and allocations on this thread
+#[cfg(feature = "profiling")]
+pub fn profile<T, F: FnOnce() -> T>(f: F) -> (T, ProfileReport) {
+    let pairings = PAIRINGS.with(Cell::get);
+    let final_exponentiations = FINAL_EXPONENTIATIONS.with(Cell::get);
+    let scalar_muls = SCALAR_MULS.with(Cell::get);
+    let allocations = ALLOCATIONS.with(Cell::get);
+    let heap_bytes = HEAP_BYTES.with(Cell::get);
+    // Restored after the call so that a profiled call within `f` does not lower the peak of this one
+    let outer_peak = PEAK_HEAP_BYTES.with(|p| p.replace(heap_bytes));
+
+    let result = f();
+
+    let peak = PEAK_HEAP_BYTES.with(|p| p.replace(p.get().max(outer_peak)));
+    let report = ProfileReport {
+        pairings: PAIRINGS.with(Cell::get) - pairings,
+        final_exponentiations: FINAL_EXPONENTIATIONS.with(Cell::get) - final_exponentiations,
+        scalar_muls: SCALAR_MULS.with(Cell::get) - scalar_muls,
+        allocations: ALLOCATIONS.with(Cell::get) - allocations,
+        peak_heap_bytes: (peak - heap_bytes) as usize,
+        allocations_tracked: ALLOCATOR_INSTALLED.load(Ordering::Relaxed),
+    };
+    (result, report)
+}
+
+/// `count` Miller loops sharing a final exponentiation
+#[doc(hidden)]
+#[cfg(feature = "profiling")]
+pub fn record_pairings(count: usize) {
+    add(&PAIRINGS, count);
+    add(&FINAL_EXPONENTIATIONS, 1);
+}
+
+#[doc(hidden)]
+#[cfg(feature = "profiling")]
+pub fn record_scalar_muls(count: usize) {
+    add(&SCALAR_MULS, count);
+}
+
+#[doc(hidden)]
+#[cfg(not(feature = "profiling"))]
+#[inline(always)]
+pub fn record_pairings(_count: usize) {}
+
+#[doc(hidden)]
+#[cfg(not(feature = "profiling"))]
+#[inline(always)]
+pub fn record_scalar_muls(_count: usize) {}
+
+#[cfg(all(test, feature = "profiling"))]
+mod tests {
+    use super::*;
+    use crate::curve::{FieldElement, FieldElementVector};
+    use crate::keys::{keygen, Params};
+    use crate::pok_sig::PoKOfSignature;
+    use crate::signature::Signature;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_profile_report() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+
+        let (sig, report) = profile(|| Signature::new(&msgs, &sk, &params).unwrap());
+        assert_eq!(report.pairings, 0);
+        assert_eq!(report.scalar_muls, 2);
+        let (verified, report) = profile(|| sig.verify(msgs.clone(), &vk, &params).unwrap());
+        assert!(verified);
+        assert_eq!(report.pairings, 2);
+        assert_eq!(report.final_exponentiations, 1);
+        assert_eq!(report.scalar_muls, count_msgs);
+
+        // The tests have another global allocator, so `CountingAllocator` is called directly
+        let layout = Layout::from_size_align(1000, 8).unwrap();
+        let (_, report) = profile(|| unsafe {
+            let ptr = CountingAllocator.alloc(layout);
+            CountingAllocator.dealloc(ptr, layout);
+        });
+        assert!(report.allocations_tracked);
+        assert_eq!(report.allocations, 1);
+        assert_eq!(report.peak_heap_bytes, 1000);
+
+        // Proof revealing 2 messages: J over t and 3 hidden messages, and the commitment to their blindings
+        let revealed: HashSet<usize> = vec![0, 1].into_iter().collect();
+        let (pok, report) = profile(|| {
+            PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), None, revealed.clone()).unwrap()
+        });
+        assert_eq!(report.pairings, 0);
+        // Randomizing the signature is 3 scalar multiplications
+        assert_eq!(
+            report.scalar_muls,
+            3 + 2 * (count_msgs - revealed.len() + 1)
+        );
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&challenge).unwrap();
+        let revealed_msgs: HashMap<usize, FieldElement> =
+            revealed.iter().map(|i| (*i, msgs[*i].clone())).collect();
+        let (verified, report) = profile(|| {
+            proof
+                .verify(&vk, &params, revealed_msgs, &challenge)
+                .unwrap()
+        });
+        assert!(verified);
+        assert_eq!(report.pairings, 2);
+    }
+}
@@ -0,0 +1,301 @@
+// Versioned encodings of proofs of knowledge of a signature, so that verifiers accept proofs of wallets of an older
+// version while a new format rolls out. The first byte of an encoding is its version:
+// - version 1, `FORMAT_WIRE`, is the message of the canonical format of `wire`, `WireMessage::to_wire_bytes`
+// - version 2, `FORMAT_COMPACT`, is the version followed by tagged sections, each the tag as 1 byte || the length
+//   of its content as an unsigned LEB128 varint of at most 4 bytes in its shortest form || the content. The sections
+//   are the signature (tag 1, sigma_1 || sigma_2), J (tag 2), the commitment (tag 3) and the responses (tag 4, the
+//   responses one after the other, their count implied by the length), each exactly once and in increasing order of
+//   tag. Elements must be in their canonical encoding as in `wire`, so each proof has exactly one encoding.
+// `PoKOfSignatureProof::parse_any` decodes a proof of any version of `SUPPORTED_VERSIONS` and fails with
+// `PSError::UnsupportedProofVersion` for others. `negotiate` has the version to send to a peer advertising the
+// versions it parses, the highest version both support. `PoKOfSignatureProof::to_bytes` has no version and is not
+// accepted by `parse_any`, as the first byte of a group element can be any version.
+
+use crate::curve::{FieldElement, FieldElement_SIZE, GroupElement};
+use crate::errors::PSError;
+use crate::keys::DeserializeLimits;
+use crate::pok_sig::{PoKOfSignatureProof, ProofVerkeyGroup};
+use crate::signature::Signature;
+use crate::wire::{WireMessage, WireReader, WIRE_VERSION};
+use crate::{SignatureGroup, VerkeyGroup};
+
+/// Version of the canonical format of `wire`
+pub const FORMAT_WIRE: u8 = WIRE_VERSION;
+/// Version of the compact format with tagged sections
+pub const FORMAT_COMPACT: u8 = 2;
+/// Versions accepted by `PoKOfSignatureProof::parse_any`, in increasing order
+pub const SUPPORTED_VERSIONS: &[u8] = &[FORMAT_WIRE, FORMAT_COMPACT];
+
+const SECTION_SIGNATURE: u8 = 1;
+const SECTION_J: u8 = 2;
+const SECTION_COMMITMENT: u8 = 3;
+const SECTION_RESPONSES: u8 = 4;
+/// Most bytes of the varint of a length, lengths are less than 2^28
+const MAX_VARINT_SIZE: usize = 4;
+
+/// Highest version of `SUPPORTED_VERSIONS` in `peer_versions`. Fails with `PSError::UnsupportedProofVersion` for the
+/// highest version of the peer if there is none.
+pub fn negotiate(peer_versions: &[u8]) -> Result<u8, PSError> {
+    SUPPORTED_VERSIONS
+        .iter()
+        .rev()
+        .find(|v| peer_versions.contains(v))
+        .cloned()
+        .ok_or_else(|| PSError::UnsupportedProofVersion {
+            version: peer_versions.iter().max().cloned().unwrap_or(0),
+        })
+}
+
+/// Version of a versioned encoding of a proof, its first byte
+pub fn version_of(bytes: &[u8]) -> Result<u8, PSError> {
+    bytes
+        .first()
+        .cloned()
+        .ok_or_else(|| invalid(String::from("Empty proof")))
+}
+
+fn invalid(msg: String) -> PSError {
+    PSError::DeserializationError { msg }
+}
+
+fn put_section(bytes: &mut Vec<u8>, tag: u8, content: &[u8]) {
+    bytes.push(tag);
+    let mut len = content.len();
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0x80);
+    }
+    bytes.extend_from_slice(content);
+}
+
+/// Content of the section `tag` at the start of `bytes` and the bytes after it
+fn take_section(bytes: &[u8], tag: u8) -> Result<(&[u8], &[u8]), PSError> {
+    match bytes.first() {
+        Some(t) if *t == tag => (),
+        Some(t) => return Err(invalid(format!("Expected section {} but found {}", tag, t))),
+        None => return Err(invalid(format!("Missing section {}", tag))),
+    }
+    let mut len = 0usize;
+    let mut pos = 1;
+    loop {
+        let byte = match bytes.get(pos) {
+            Some(b) if pos <= MAX_VARINT_SIZE => *b,
+            _ => return Err(invalid(format!("Invalid length of section {}", tag))),
+        };
+        len |= ((byte & 0x7f) as usize) << (7 * (pos - 1));
+        pos += 1;
+        if byte & 0x80 == 0 {
+            // A last byte of 0 after others is a longer encoding of the same length
+            if byte == 0 && pos > 2 {
+                return Err(invalid(format!(
+                    "Length of section {} is not in its shortest form",
+                    tag
+                )));
+            }
+            break;
+        }
+    }
+    if bytes.len() - pos < len {
+        return Err(invalid(format!(
+            "Section {} needs {} bytes but has {}",
+            tag,
+            len,
+            bytes.len() - pos
+        )));
+    }
+    Ok((&bytes[pos..pos + len], &bytes[pos + len..]))
+}
+
+impl PoKOfSignatureProof {
+    /// Encoding in the compact format, version `FORMAT_COMPACT`
+    pub fn to_compact_bytes(&self) -> Vec<u8> {
+        let mut responses = vec![];
+        for r in self.proof_vc.responses.iter() {
+            responses.append(&mut r.to_bytes());
+        }
+        let mut bytes = vec![FORMAT_COMPACT];
+        put_section(&mut bytes, SECTION_SIGNATURE, &self.sig.to_bytes());
+        put_section(&mut bytes, SECTION_J, &self.J.to_bytes());
+        put_section(
+            &mut bytes,
+            SECTION_COMMITMENT,
+            &self.proof_vc.commitment.to_bytes(),
+        );
+        put_section(&mut bytes, SECTION_RESPONSES, &responses);
+        bytes
+    }
+
+    /// Encoding in the format of `version`, failing with `PSError::UnsupportedProofVersion` for a version not in
+    /// `SUPPORTED_VERSIONS`
+    pub fn to_versioned_bytes(&self, version: u8) -> Result<Vec<u8>, PSError> {
+        match version {
+            FORMAT_WIRE => Ok(self.to_wire_bytes()),
+            FORMAT_COMPACT => Ok(self.to_compact_bytes()),
+            _ => Err(PSError::UnsupportedProofVersion { version }),
+        }
+    }
+
+    pub fn from_compact_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_compact_bytes_with_limits(bytes, &DeserializeLimits::default())
+    }
+
+    /// The count of messages of the proof is the count of responses less the one for `t`
+    pub fn from_compact_bytes_with_limits(
+        bytes: &[u8],
+        limits: &DeserializeLimits,
+    ) -> Result<Self, PSError> {
+        limits.check_bytes(bytes)?;
+        match version_of(bytes)? {
+            FORMAT_COMPACT => (),
+            version => return Err(PSError::UnsupportedProofVersion { version }),
+        }
+        let (sig, rest) = take_section(&bytes[1..], SECTION_SIGNATURE)?;
+        let (J, rest) = take_section(rest, SECTION_J)?;
+        let (commitment, rest) = take_section(rest, SECTION_COMMITMENT)?;
+        let (responses, rest) = take_section(rest, SECTION_RESPONSES)?;
+        if !rest.is_empty() {
+            return Err(invalid(format!("{} bytes after the sections", rest.len())));
+        }
+        // At least 1 response is needed, for the randomness `t`
+        if responses.is_empty() || !responses.len().is_multiple_of(FieldElement_SIZE) {
+            return Err(invalid(format!(
+                "Incorrect number of bytes {} for responses",
+                responses.len()
+            )));
+        }
+        let count = responses.len() / FieldElement_SIZE;
+        limits.check_messages(count - 1)?;
+
+        let mut r = WireReader::new(sig, limits);
+        let sig = Signature {
+            sigma_1: r.get::<SignatureGroup>()?,
+            sigma_2: r.get::<SignatureGroup>()?,
+        };
+        r.finish()?;
+        let mut r = WireReader::new(J, limits);
+        let J = r.get::<VerkeyGroup>()?;
+        r.finish()?;
+        let mut r = WireReader::new(commitment, limits);
+        let commitment = r.get::<VerkeyGroup>()?;
+        r.finish()?;
+        let mut r = WireReader::new(responses, limits);
+        let mut elems = Vec::with_capacity(count);
+        for _ in 0..count {
+            elems.push(r.get::<FieldElement>()?);
+        }
+        Ok(Self {
+            sig,
+            J,
+            proof_vc: ProofVerkeyGroup {
+                commitment,
+                responses: elems.into(),
+            },
+        })
+    }
+
+    /// Decode a proof encoded in any version of `SUPPORTED_VERSIONS`, e.g. by wallets of an older version
+    pub fn parse_any(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::parse_any_with_limits(bytes, &DeserializeLimits::default())
+    }
+
+    pub fn parse_any_with_limits(
+        bytes: &[u8],
+        limits: &DeserializeLimits,
+    ) -> Result<Self, PSError> {
+        match version_of(bytes)? {
+            FORMAT_WIRE => Self::from_wire_bytes_with_limits(bytes, limits),
+            FORMAT_COMPACT => Self::from_compact_bytes_with_limits(bytes, limits),
+            version => Err(PSError::UnsupportedProofVersion { version }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, Params};
+    use crate::pok_sig::PoKOfSignature;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_versioned_proof_formats() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(5, &params).unwrap();
+        let msgs: Vec<FieldElement> = (0..5).map(|_| FieldElement::random()).collect();
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        let revealed: HashSet<usize> = vec![1, 3].into_iter().collect();
+        let pok =
+            PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), None, revealed.clone()).unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+
+        let compact = proof.to_compact_bytes();
+        let wire = proof.to_wire_bytes();
+        assert!(compact.len() < wire.len());
+        assert_eq!(proof.to_versioned_bytes(FORMAT_COMPACT).unwrap(), compact);
+        assert_eq!(proof.to_versioned_bytes(FORMAT_WIRE).unwrap(), wire);
+        assert!(proof.to_versioned_bytes(3).is_err());
+        for bytes in &[&compact, &wire] {
+            let parsed = PoKOfSignatureProof::parse_any(bytes).unwrap();
+            assert_eq!(parsed.to_bytes(), proof.to_bytes());
+            assert!(parsed
+                .verify(&vk, &params, revealed_msgs(&msgs, &revealed), &chal)
+                .unwrap());
+        }
+
+        match PoKOfSignatureProof::parse_any(&proof.to_bytes()) {
+            Err(PSError::UnsupportedProofVersion { .. })
+            | Err(PSError::DeserializationError { .. }) => (),
+            r => panic!("Expected an error but got {:?}", r.map(|_| ())),
+        }
+        let mut future = compact.clone();
+        future[0] = 9;
+        match PoKOfSignatureProof::parse_any(&future) {
+            Err(PSError::UnsupportedProofVersion { version: 9 }) => (),
+            r => panic!(
+                "Expected UnsupportedProofVersion but got {:?}",
+                r.map(|_| ())
+            ),
+        }
+        // Truncated, with trailing bytes and with sections swapped
+        assert!(PoKOfSignatureProof::parse_any(&compact[..compact.len() - 1]).is_err());
+        let mut trailing = compact.clone();
+        trailing.push(0);
+        assert!(PoKOfSignatureProof::parse_any(&trailing).is_err());
+        let mut swapped = vec![FORMAT_COMPACT];
+        put_section(&mut swapped, SECTION_J, &proof.J.to_bytes());
+        put_section(&mut swapped, SECTION_SIGNATURE, &proof.sig.to_bytes());
+        let (_, rest) = take_section(&compact[1..], SECTION_SIGNATURE).unwrap();
+        let (_, rest) = take_section(rest, SECTION_J).unwrap();
+        swapped.extend_from_slice(rest);
+        assert!(PoKOfSignatureProof::parse_any(&swapped).is_err());
+        // A length of 0 as 2 bytes
+        let mut long = vec![FORMAT_COMPACT];
+        long.extend_from_slice(&[SECTION_SIGNATURE, 0x80, 0x00]);
+        assert!(PoKOfSignatureProof::parse_any(&long).is_err());
+        let limits = DeserializeLimits {
+            max_messages: 2,
+            ..DeserializeLimits::default()
+        };
+        assert!(PoKOfSignatureProof::parse_any_with_limits(&compact, &limits).is_err());
+
+        assert_eq!(negotiate(&[1, 2, 7]).unwrap(), FORMAT_COMPACT);
+        assert_eq!(negotiate(&[1]).unwrap(), FORMAT_WIRE);
+        match negotiate(&[7, 8]) {
+            Err(PSError::UnsupportedProofVersion { version: 8 }) => (),
+            r => panic!("Expected UnsupportedProofVersion but got {:?}", r),
+        }
+    }
+
+    fn revealed_msgs(
+        msgs: &[FieldElement],
+        revealed: &HashSet<usize>,
+    ) -> std::collections::HashMap<usize, FieldElement> {
+        revealed.iter().map(|i| (*i, msgs[*i].clone())).collect()
+    }
+}
@@ -0,0 +1,319 @@
+// Proof requests for negotiating a presentation. The verifier builds a `ProofRequest` with `ProofRequestBuilder`
+// listing the indices of the messages it wants revealed, the predicates the credential must satisfy and a nonce,
+// and sends it serialized to the holder. The holder uses `satisfy` to pick one of its credentials that fulfills the
+// request and create the proof of knowledge of signature revealing what is needed. The challenge of the proof is
+// bound to the nonce of the request so a response cannot be replayed for another request.
+
+use crate::curve::{FieldElement, FieldElement_SIZE};
+use crate::errors::PSError;
+use crate::keys::{DeserializeLimits, Params, Verkey};
+use crate::pok_sig::{PoKOfSignature, PoKOfSignatureProof};
+use crate::signature::Signature;
+use crate::validity::{ValidityWindow, NOT_AFTER_INDEX, NOT_BEFORE_INDEX};
+use crate::SIGNATURE_GROUP_SIZE;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Condition on the credential checked without revealing more than what the condition needs
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Predicate {
+    /// The validity window of the credential contains the time, see `validity`. Reveals the window.
+    ValidAt(u64),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofRequest {
+    /// Indices of the messages to reveal
+    pub requested_attributes: BTreeSet<usize>,
+    pub predicates: Vec<Predicate>,
+    pub nonce: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ProofRequestBuilder {
+    requested_attributes: BTreeSet<usize>,
+    predicates: Vec<Predicate>,
+    nonce: Option<Vec<u8>>,
+}
+
+/// Credential of the holder, a signature with its messages and the verkey of the signer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Credential {
+    pub signature: Signature,
+    pub messages: Vec<FieldElement>,
+    pub verkey: Verkey,
+}
+
+/// Response of the holder to a `ProofRequest`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofResponse {
+    pub proof: PoKOfSignatureProof,
+    pub revealed_msgs: HashMap<usize, FieldElement>,
+}
+
+impl Credential {
+    /// Encoding as signature || verkey || messages, the count of messages being the one in the encoding of the verkey
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.signature.to_bytes();
+        bytes.append(&mut self.verkey.to_bytes());
+        for m in &self.messages {
+            bytes.append(&mut m.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_bytes_with_limits(bytes, &DeserializeLimits::default())
+    }
+
+    pub fn from_bytes_with_limits(
+        bytes: &[u8],
+        limits: &DeserializeLimits,
+    ) -> Result<Self, PSError> {
+        limits.check_bytes(bytes)?;
+        if bytes.len() < 2 * SIGNATURE_GROUP_SIZE {
+            return Err(PSError::DeserializationError {
+                msg: format!("Incorrect number of bytes {} for credential", bytes.len()),
+            });
+        }
+        let (sig_bytes, rest) = bytes.split_at(2 * SIGNATURE_GROUP_SIZE);
+        let n = limits.msg_count_from_header(rest)?;
+        let verkey_size = Verkey::serialized_size(n);
+        if rest.len() != verkey_size + n * FieldElement_SIZE {
+            return Err(PSError::DeserializationError {
+                msg: format!(
+                    "Incorrect number of bytes {} for credential of {} messages",
+                    bytes.len(),
+                    n
+                ),
+            });
+        }
+        let (verkey_bytes, msg_bytes) = rest.split_at(verkey_size);
+        let messages = msg_bytes
+            .chunks(FieldElement_SIZE)
+            .map(|b| {
+                FieldElement::from_bytes(b).map_err(|e| PSError::DeserializationError {
+                    msg: format!("{}", e),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            signature: Signature::from_bytes(sig_bytes)?,
+            messages,
+            verkey: Verkey::from_bytes_with_limits(verkey_bytes, limits)?,
+        })
+    }
+}
+
+impl ProofRequestBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request_attribute(mut self, index: usize) -> Self {
+        self.requested_attributes.insert(index);
+        self
+    }
+
+    pub fn require_predicate(mut self, predicate: Predicate) -> Self {
+        self.predicates.push(predicate);
+        self
+    }
+
+    pub fn require_valid_at(self, time: u64) -> Self {
+        self.require_predicate(Predicate::ValidAt(time))
+    }
+
+    /// Nonce of the request, a random one is chosen if not set
+    pub fn nonce(mut self, nonce: &[u8]) -> Self {
+        self.nonce = Some(nonce.to_vec());
+        self
+    }
+
+    pub fn build(self) -> ProofRequest {
+        ProofRequest {
+            requested_attributes: self.requested_attributes,
+            predicates: self.predicates,
+            nonce: self
+                .nonce
+                .unwrap_or_else(|| FieldElement::random().to_bytes()),
+        }
+    }
+}
+
+impl ProofRequest {
+    /// Indices of all messages the holder has to reveal, the requested ones and those needed by the predicates
+    pub fn revealed_msg_indices(&self) -> HashSet<usize> {
+        let mut indices: HashSet<usize> = self.requested_attributes.iter().cloned().collect();
+        for p in &self.predicates {
+            match p {
+                Predicate::ValidAt(_) => {
+                    indices.insert(NOT_BEFORE_INDEX);
+                    indices.insert(NOT_AFTER_INDEX);
+                }
+            }
+        }
+        indices
+    }
+
+    /// Whether the credential has all revealed messages and satisfies the predicates. Does not verify the signature.
+    pub fn is_satisfied_by(&self, credential: &Credential) -> bool {
+        let count = credential.messages.len();
+        if count != credential.verkey.supported_messages()
+            || self.revealed_msg_indices().iter().any(|i| *i >= count)
+        {
+            return false;
+        }
+        self.predicates.iter().all(|p| match p {
+            Predicate::ValidAt(time) => {
+                let mut msgs = HashMap::new();
+                msgs.insert(
+                    NOT_BEFORE_INDEX,
+                    credential.messages[NOT_BEFORE_INDEX].clone(),
+                );
+                msgs.insert(
+                    NOT_AFTER_INDEX,
+                    credential.messages[NOT_AFTER_INDEX].clone(),
+                );
+                match ValidityWindow::from_revealed_msgs(&msgs) {
+                    Ok(w) => w.contains(*time),
+                    Err(_) => false,
+                }
+            }
+        })
+    }
+
+    /// Challenge for the proof, the bytes of the proof for the challenge followed by the nonce
+    fn challenge(&self, proof: &PoKOfSignatureProof, vk: &Verkey, params: &Params) -> FieldElement {
+        let mut bytes = proof.get_bytes_for_challenge(self.revealed_msg_indices(), vk, params);
+        bytes.extend_from_slice(&self.nonce);
+        FieldElement::from_msg_hash(&bytes)
+    }
+
+    /// Verify the response of the holder against the verkey of the signer. Fails with an error when a predicate
+    /// does not hold.
+    pub fn verify(
+        &self,
+        response: &ProofResponse,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        let revealed_msg_indices = self.revealed_msg_indices();
+        if response.revealed_msgs.len() != revealed_msg_indices.len()
+            || !revealed_msg_indices
+                .iter()
+                .all(|i| response.revealed_msgs.contains_key(i))
+        {
+            return Ok(false);
+        }
+        for p in &self.predicates {
+            match p {
+                Predicate::ValidAt(time) => {
+                    let window = ValidityWindow::from_revealed_msgs(&response.revealed_msgs)?;
+                    if !window.contains(*time) {
+                        return Err(PSError::OutsideValidityWindow {
+                            time: *time,
+                            not_before: window.not_before,
+                            not_after: window.not_after,
+                        });
+                    }
+                }
+            }
+        }
+        let challenge = self.challenge(&response.proof, vk, params);
+        response
+            .proof
+            .verify(vk, params, response.revealed_msgs.clone(), &challenge)
+    }
+}
+
+/// Pick the first of `credentials` satisfying `request` and create the response to it
+pub fn satisfy(
+    request: &ProofRequest,
+    credentials: &[Credential],
+    params: &Params,
+) -> Result<ProofResponse, PSError> {
+    let credential = match credentials.iter().find(|c| request.is_satisfied_by(c)) {
+        Some(c) => c,
+        None => return Err(PSError::UnsatisfiableProofRequest),
+    };
+    let revealed_msg_indices = request.revealed_msg_indices();
+    let pok = PoKOfSignature::init(
+        &credential.signature,
+        &credential.verkey,
+        params,
+        credential.messages.clone(),
+        None,
+        revealed_msg_indices.clone(),
+    )?;
+    let mut bytes = pok.to_bytes();
+    bytes.extend_from_slice(&request.nonce);
+    let challenge = FieldElement::from_msg_hash(&bytes);
+    let proof = pok.gen_proof(&challenge)?;
+    let revealed_msgs = revealed_msg_indices
+        .into_iter()
+        .map(|i| (i, credential.messages[i].clone()))
+        .collect();
+    Ok(ProofResponse {
+        proof,
+        revealed_msgs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    fn credential(count_msgs: usize, window: &ValidityWindow, params: &Params) -> Credential {
+        let (sk, vk) = keygen(count_msgs, params).unwrap();
+        let mut msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        window.set_in_messages(&mut msgs).unwrap();
+        let sig = Signature::new(msgs.as_slice(), &sk, params).unwrap();
+        Credential {
+            signature: sig,
+            messages: msgs,
+            verkey: vk,
+        }
+    }
+
+    #[test]
+    fn test_proof_request() {
+        let params = Params::new("test".as_bytes());
+        let expired = credential(6, &ValidityWindow::new(1_000, 2_000).unwrap(), &params);
+        let short = credential(4, &ValidityWindow::new(1_000, 5_000).unwrap(), &params);
+        let valid = credential(6, &ValidityWindow::new(1_000, 5_000).unwrap(), &params);
+        let credentials = vec![expired, short, valid.clone()];
+
+        let request = ProofRequestBuilder::new()
+            .request_attribute(3)
+            .request_attribute(5)
+            .require_valid_at(3_000)
+            .nonce(b"nonce")
+            .build();
+
+        let response = satisfy(&request, &credentials, &params).unwrap();
+        assert_eq!(response.revealed_msgs.len(), 4);
+        assert_eq!(response.revealed_msgs[&5], valid.messages[5]);
+        assert!(request.verify(&response, &valid.verkey, &params).unwrap());
+
+        // Response to another request with the same disclosures but another nonce
+        let mut other_request = request.clone();
+        other_request.nonce = b"other nonce".to_vec();
+        assert!(!other_request
+            .verify(&response, &valid.verkey, &params)
+            .unwrap());
+
+        // Request for a later time fails the predicate
+        let mut later = request.clone();
+        later.predicates = vec![Predicate::ValidAt(6_000)];
+        assert!(later.verify(&response, &valid.verkey, &params).is_err());
+
+        // No credential has an attribute at index 6
+        let request_1 = ProofRequestBuilder::new().request_attribute(6).build();
+        match satisfy(&request_1, &credentials, &params) {
+            Err(PSError::UnsatisfiableProofRequest) => (),
+            r => panic!("Expected UnsatisfiableProofRequest but got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// One-shot proofs disclosing a single attribute of a credential and hiding the others, the most common
+// presentation, without setting up a `PoKOfSignature` and its challenge. `prove_single_disclosure` creates a proof
+// of knowledge of the signature of a `proof_request::Credential` revealing the message at one index, with the
+// challenge derived from the `Context` of the verifier, its params and a nonce, and `verify_single_disclosure`
+// checks it for the index the verifier asked for and returns the disclosed value.
+// The challenge is the hash of the tag of `challenge_domain::QUICK_DISCLOSURE` || the length of the nonce in 8 bytes
+// big endian || the nonce || the index in 8 bytes big endian || the disclosed message || the bytes of the proof for
+// the challenge, `PoKOfSignature::to_bytes`. For other presentations, e.g. with several disclosed messages or bound
+// to the holder, see `presentation_builder::PresentationBuilder`.
+
+use crate::challenge_domain::QUICK_DISCLOSURE;
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::{PoKOfSignature, PoKOfSignatureProof};
+use crate::proof_request::Credential;
+use std::collections::{HashMap, HashSet};
+
+/// Params of the issuer and nonce of the verifier the proof is bound to
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Context {
+    pub params: Params,
+    pub nonce: Vec<u8>,
+}
+
+/// Proof disclosing the message at `index` of a credential
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SingleDisclosure {
+    pub index: usize,
+    pub value: FieldElement,
+    pub proof: PoKOfSignatureProof,
+}
+
+impl Context {
+    pub fn new(params: &Params, nonce: &[u8]) -> Self {
+        Self {
+            params: params.clone(),
+            nonce: nonce.to_vec(),
+        }
+    }
+
+    fn challenge(&self, index: usize, value: &FieldElement, proof_bytes: &[u8]) -> FieldElement {
+        let mut bytes = QUICK_DISCLOSURE.tag();
+        bytes.extend_from_slice(&(self.nonce.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&(index as u64).to_be_bytes());
+        bytes.append(&mut value.to_bytes());
+        bytes.extend_from_slice(proof_bytes);
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+/// Prove knowledge of the signature of `credential` disclosing its message at `index` only
+pub fn prove_single_disclosure(
+    credential: &Credential,
+    index: usize,
+    context: &Context,
+) -> Result<SingleDisclosure, PSError> {
+    let revealed: HashSet<usize> = vec![index].into_iter().collect();
+    let pok = PoKOfSignature::init(
+        &credential.signature,
+        &credential.verkey,
+        &context.params,
+        credential.messages.clone(),
+        None,
+        revealed,
+    )?;
+    let value = credential.messages[index].clone();
+    let challenge = context.challenge(index, &value, &pok.to_bytes());
+    Ok(SingleDisclosure {
+        index,
+        value,
+        proof: pok.gen_proof(&challenge)?,
+    })
+}
+
+/// Verify `disclosure` of the message at `index` of a credential of `verkey` and return the message. Fails with
+/// `PSError::InvalidIndex` if it discloses another index and with `PSError::InvalidPresentation` if it does not
+/// verify for `context`.
+pub fn verify_single_disclosure(
+    disclosure: &SingleDisclosure,
+    index: usize,
+    verkey: &Verkey,
+    context: &Context,
+) -> Result<FieldElement, PSError> {
+    if disclosure.index != index {
+        return Err(PSError::InvalidIndex {
+            idx: disclosure.index,
+            valid: format!("[{}, {}]", index, index),
+        });
+    }
+    let revealed: HashSet<usize> = vec![index].into_iter().collect();
+    let proof_bytes = disclosure
+        .proof
+        .get_bytes_for_challenge(revealed, verkey, &context.params);
+    let challenge = context.challenge(index, &disclosure.value, &proof_bytes);
+    let revealed_msgs: HashMap<usize, FieldElement> = vec![(index, disclosure.value.clone())]
+        .into_iter()
+        .collect();
+    if !disclosure
+        .proof
+        .verify(verkey, &context.params, revealed_msgs, &challenge)?
+    {
+        return Err(PSError::InvalidPresentation);
+    }
+    Ok(disclosure.value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+    use crate::signature::Signature;
+
+    #[test]
+    fn test_single_disclosure() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params).unwrap();
+        let messages: Vec<FieldElement> = FieldElementVector::random(4).into();
+        let credential = Credential {
+            signature: Signature::new(&messages, &sk, &params).unwrap(),
+            messages: messages.clone(),
+            verkey: vk.clone(),
+        };
+        let context = Context::new(&params, b"nonce");
+        let disclosure = prove_single_disclosure(&credential, 2, &context).unwrap();
+        assert_eq!(
+            verify_single_disclosure(&disclosure, 2, &vk, &context).unwrap(),
+            messages[2]
+        );
+        assert!(prove_single_disclosure(&credential, 4, &context).is_err());
+
+        // Another index than the requested one
+        match verify_single_disclosure(&disclosure, 1, &vk, &context) {
+            Err(PSError::InvalidIndex { idx: 2, .. }) => (),
+            r => panic!("Expected InvalidIndex but got {:?}", r),
+        }
+        // Another value, nonce or issuer
+        let mut tampered = disclosure.clone();
+        tampered.value = messages[1].clone();
+        assert!(verify_single_disclosure(&tampered, 2, &vk, &context).is_err());
+        let other = Context::new(&params, b"other nonce");
+        match verify_single_disclosure(&disclosure, 2, &vk, &other) {
+            Err(PSError::InvalidPresentation) => (),
+            r => panic!("Expected InvalidPresentation but got {:?}", r),
+        }
+        let (_, other_vk) = keygen(4, &params).unwrap();
+        assert!(verify_single_disclosure(&disclosure, 2, &other_vk, &context).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+// Padding of the messages of a redactable signature so that a derived signature does not tell how many messages
+// were redacted. A verifier learns the count of messages of the key and the disclosed messages, so with a key for
+// exactly the messages of a credential it learns how many were redacted, which is sensitive when the count itself
+// is, e.g. the count of items of a list. The signer pads the messages with random messages up to the next multiple
+// of a bucket size and signs with the key for the padded count. One `SKrss` backs exactly one count of messages,
+// see `SKrss::to_pkrss`, so the signer generates a separate key with `rsskeygen(padded_count, ..)` for each padded
+// count it issues. Padding messages are always redacted and look like any other redacted message, so the verifier
+// learns only the bucket of the credential.
+// The padding is random rather than a fixed value so that padding disclosed by mistake does not show as padding.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{check_message_count, Params, SKrss};
+use crate::rss_signature::{RSSPublicKey, RSSignature, RedactedSignature};
+use std::collections::HashSet;
+
+/// Messages of a credential followed by its padding
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaddedMessages {
+    /// Messages of the credential followed by the padding messages
+    pub messages: Vec<FieldElement>,
+    /// Count of messages of the credential, the padding starts at this index
+    pub real_count: usize,
+}
+
+impl PaddedMessages {
+    /// `messages` padded with random messages to the next multiple of `bucket_size`. Fails if `bucket_size` is 0
+    /// or if no key can be generated for the padded count.
+    pub fn new(messages: &[FieldElement], bucket_size: usize) -> Result<Self, PSError> {
+        let count = Self::padded_count(messages.len(), bucket_size)?;
+        let mut padded = messages.to_vec();
+        padded.extend((messages.len()..count).map(|_| FieldElement::random()));
+        Ok(Self {
+            messages: padded,
+            real_count: messages.len(),
+        })
+    }
+
+    /// Count of messages of `count` messages padded to the next multiple of `bucket_size`, which the key of the
+    /// signer must support
+    pub fn padded_count(count: usize, bucket_size: usize) -> Result<usize, PSError> {
+        if bucket_size == 0 {
+            return Err(PSError::GeneralError {
+                msg: String::from("Bucket size should be at least 1"),
+            });
+        }
+        let padded = count.max(1).div_ceil(bucket_size) * bucket_size;
+        check_message_count(padded)?;
+        Ok(padded)
+    }
+
+    pub fn padding_count(&self) -> usize {
+        self.messages.len() - self.real_count
+    }
+
+    /// Messages of the credential without the padding
+    pub fn real_messages(&self) -> &[FieldElement] {
+        &self.messages[..self.real_count]
+    }
+
+    /// Fails with `PSError::InvalidIndex` for an index of a padding message in `disclosed`
+    fn check_disclosed(&self, disclosed: &HashSet<usize>) -> Result<(), PSError> {
+        match disclosed.iter().find(|i| **i >= self.real_count) {
+            Some(idx) => Err(PSError::InvalidIndex {
+                idx: *idx,
+                valid: format!("[0, {})", self.real_count),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl RSSignature {
+    /// Sign the padded messages with `sk`, the key generated for `padded.messages.len()` messages
+    pub fn new_padded(
+        padded: &PaddedMessages,
+        sk: &SKrss,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        Self::new(&padded.messages, sk, params)
+    }
+
+    /// Derive a signature of a signature created with `new_padded` disclosing the messages at `disclosed` and
+    /// redacting the padding. Fails with `PSError::InvalidIndex` if `disclosed` has the index of a padding message.
+    /// The derived signature is verified with `RedactedSignature::verify` as any other.
+    pub fn derive_padded<K: RSSPublicKey>(
+        &self,
+        padded: &PaddedMessages,
+        disclosed: &HashSet<usize>,
+        pk: &K,
+    ) -> Result<RedactedSignature, PSError> {
+        padded.check_disclosed(disclosed)?;
+        self.derive(&padded.messages, disclosed, pk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::rsskeygen;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_padded_count() {
+        assert_eq!(PaddedMessages::padded_count(4, 4).unwrap(), 4);
+        assert_eq!(PaddedMessages::padded_count(5, 4).unwrap(), 8);
+        assert_eq!(PaddedMessages::padded_count(0, 4).unwrap(), 4);
+        assert!(PaddedMessages::padded_count(3, 0).is_err());
+    }
+
+    #[test]
+    fn test_padded_redaction() {
+        let params = Params::new("test".as_bytes());
+        let bucket_size = 4;
+        // A key per padded count
+        let keys: HashMap<usize, _> = [bucket_size, 2 * bucket_size]
+            .iter()
+            .map(|n| (*n, rsskeygen(*n, &params).unwrap()))
+            .collect();
+
+        // Credentials of 5 and 7 messages both have 8 messages. With 2 disclosed messages, a verifier sees 6
+        // redacted for both.
+        let disclosed: HashSet<usize> = vec![0, 2].into_iter().collect();
+        for (count, redacted) in &[(3, 2), (5, 6), (7, 6)] {
+            let msgs: Vec<FieldElement> = FieldElementVector::random(*count).into();
+            let padded = PaddedMessages::new(&msgs, bucket_size).unwrap();
+            assert_eq!(padded.padding_count(), padded.messages.len() - count);
+            assert_eq!(padded.real_messages(), msgs.as_slice());
+            let (sk, pk) = &keys[&padded.messages.len()];
+            let sig = RSSignature::new_padded(&padded, sk, &params).unwrap();
+            assert!(sig.verify(&padded.messages, pk).unwrap());
+
+            let derived = sig.derive_padded(&padded, &disclosed, pk).unwrap();
+            let disclosed_msgs: HashMap<usize, FieldElement> =
+                disclosed.iter().map(|i| (*i, msgs[*i].clone())).collect();
+            assert!(derived.verify(&disclosed_msgs, pk).unwrap());
+            assert_eq!(pk.msg_count() - disclosed_msgs.len(), *redacted);
+        }
+    }
+
+    #[test]
+    fn test_padding_not_disclosed() {
+        let params = Params::new("test".as_bytes());
+        let (sk, pk) = rsskeygen(4, &params).unwrap();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(3).into();
+        let padded = PaddedMessages::new(&msgs, 4).unwrap();
+        let sig = RSSignature::new_padded(&padded, &sk, &params).unwrap();
+        let with_padding: HashSet<usize> = vec![0, 3].into_iter().collect();
+        match sig.derive_padded(&padded, &with_padding, &pk) {
+            Err(PSError::InvalidIndex { idx, .. }) => assert_eq!(idx, 3),
+            r => panic!("Expected InvalidIndex but got {:?}", r),
+        }
+    }
+}
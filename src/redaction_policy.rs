@@ -0,0 +1,190 @@
+// Messages of a redactable signature that the signer forbids redacting, e.g. the type of a document required by
+// regulation. The signer lists their indices in `SigningOptions` and the hash of the list is signed as an extra
+// message after the others, at the last index of the key. Derived signatures always disclose it, so a verifier
+// given the list can check it is the one signed and that all the listed messages are disclosed. A holder cannot
+// drop an index from the list as the hash would no longer match the signed one.
+// The key must support one message more than the signer's messages. A verifier must know that credentials of a
+// kind are signed with options and verify them with `RedactedSignature::verify_with_options`, as plain `verify`
+// does not look at the policy message.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, SKrss};
+use crate::rss_signature::{RSSPublicKey, RSSignature, RedactedSignature};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+const POLICY_DOMAIN: &[u8] = b"PS redaction policy : ";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningOptions {
+    /// Indices of the messages every derived signature must disclose
+    pub non_redactable: BTreeSet<usize>,
+}
+
+impl SigningOptions {
+    /// Options allowing to redact any message
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options forbidding to redact the messages at `indices`
+    pub fn non_redactable<I: IntoIterator<Item = usize>>(indices: I) -> Self {
+        Self {
+            non_redactable: indices.into_iter().collect(),
+        }
+    }
+
+    /// Message signed after the signer's messages, the hash of the non-redactable indices in increasing order
+    pub fn policy_message(&self) -> FieldElement {
+        let mut bytes = POLICY_DOMAIN.to_vec();
+        for i in &self.non_redactable {
+            bytes.extend_from_slice(&(*i as u64).to_be_bytes());
+        }
+        FieldElement::from_msg_hash(&bytes)
+    }
+
+    /// Fails with `PSError::NonRedactableMessageHidden` for the first non-redactable index not disclosed
+    fn check_disclosed<F: Fn(usize) -> bool>(&self, is_disclosed: F) -> Result<(), PSError> {
+        match self.non_redactable.iter().find(|i| !is_disclosed(**i)) {
+            Some(idx) => Err(PSError::NonRedactableMessageHidden { idx: *idx }),
+            None => Ok(()),
+        }
+    }
+
+    /// `messages` followed by the policy message
+    fn with_policy_message(&self, messages: &[FieldElement]) -> Vec<FieldElement> {
+        let mut all = messages.to_vec();
+        all.push(self.policy_message());
+        all
+    }
+}
+
+impl RSSignature {
+    /// Sign `messages` and the policy message of `options`. `sk` must be for `messages.len() + 1` messages.
+    pub fn new_with_options(
+        messages: &[FieldElement],
+        sk: &SKrss,
+        params: &Params,
+        options: &SigningOptions,
+    ) -> Result<Self, PSError> {
+        if messages.len() + 1 != sk.n {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: sk.n - 1,
+                given: messages.len(),
+            });
+        }
+        if let Some(idx) = options
+            .non_redactable
+            .iter()
+            .find(|i| **i >= messages.len())
+        {
+            return Err(PSError::InvalidIndex {
+                idx: *idx,
+                valid: format!("[0, {})", messages.len()),
+            });
+        }
+        Self::new(&options.with_policy_message(messages), sk, params)
+    }
+
+    /// Derive a signature of a signature created with `new_with_options` disclosing the messages at `disclosed`
+    /// and the policy message. `messages` are the signed messages without the policy message. Fails with
+    /// `PSError::NonRedactableMessageHidden` if `disclosed` lacks a non-redactable index.
+    pub fn derive_with_options<K: RSSPublicKey>(
+        &self,
+        messages: &[FieldElement],
+        disclosed: &HashSet<usize>,
+        options: &SigningOptions,
+        pk: &K,
+    ) -> Result<RedactedSignature, PSError> {
+        options.check_disclosed(|i| disclosed.contains(&i))?;
+        let mut disclosed = disclosed.clone();
+        disclosed.insert(messages.len());
+        self.derive(&options.with_policy_message(messages), &disclosed, pk)
+    }
+}
+
+impl RedactedSignature {
+    /// Verify a signature derived with `RSSignature::derive_with_options` given the disclosed messages, without
+    /// the policy message, and the options it was signed with. Fails with `PSError::NonRedactableMessageHidden`
+    /// if a non-redactable message is not disclosed and returns false if `options` are not the signed ones.
+    pub fn verify_with_options<K: RSSPublicKey>(
+        &self,
+        disclosed_msgs: &HashMap<usize, FieldElement>,
+        options: &SigningOptions,
+        pk: &K,
+    ) -> Result<bool, PSError> {
+        options.check_disclosed(|i| disclosed_msgs.contains_key(&i))?;
+        let policy_index = pk.msg_count() - 1;
+        if disclosed_msgs.contains_key(&policy_index) {
+            return Err(PSError::InvalidIndex {
+                idx: policy_index,
+                valid: format!("[0, {})", policy_index),
+            });
+        }
+        let mut disclosed_msgs = disclosed_msgs.clone();
+        disclosed_msgs.insert(policy_index, options.policy_message());
+        self.verify(&disclosed_msgs, pk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::rsskeygen;
+
+    #[test]
+    fn test_non_redactable_messages() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, pk) = rsskeygen(count_msgs + 1, &params).unwrap();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        let options = SigningOptions::non_redactable(vec![0, 3]);
+        let sig = RSSignature::new_with_options(&msgs, &sk, &params, &options).unwrap();
+        let disclosed_msgs = |indices: &HashSet<usize>| -> HashMap<usize, FieldElement> {
+            indices.iter().map(|i| (*i, msgs[*i].clone())).collect()
+        };
+
+        let disclosed: HashSet<usize> = vec![0, 2, 3].into_iter().collect();
+        let derived = sig
+            .derive_with_options(&msgs, &disclosed, &options, &pk)
+            .unwrap();
+        assert!(derived
+            .verify_with_options(&disclosed_msgs(&disclosed), &options, &pk)
+            .unwrap());
+
+        // Holder hiding a non-redactable message
+        let hiding: HashSet<usize> = vec![0, 2].into_iter().collect();
+        match sig.derive_with_options(&msgs, &hiding, &options, &pk) {
+            Err(PSError::NonRedactableMessageHidden { idx: 3 }) => (),
+            r => panic!("Expected NonRedactableMessageHidden but got {:?}", r),
+        }
+        // and deriving without the options, then claiming fewer non-redactable messages
+        let mut all_msgs = msgs.clone();
+        all_msgs.push(options.policy_message());
+        let mut hiding_with_policy = hiding.clone();
+        hiding_with_policy.insert(count_msgs);
+        let derived_1 = sig.derive(&all_msgs, &hiding_with_policy, &pk).unwrap();
+        match derived_1.verify_with_options(&disclosed_msgs(&hiding), &options, &pk) {
+            Err(PSError::NonRedactableMessageHidden { idx: 3 }) => (),
+            r => panic!("Expected NonRedactableMessageHidden but got {:?}", r),
+        }
+        let weaker = SigningOptions::non_redactable(vec![0]);
+        assert!(!derived_1
+            .verify_with_options(&disclosed_msgs(&hiding), &weaker, &pk)
+            .unwrap());
+        // Policy message hidden as well
+        let derived_2 = sig.derive(&all_msgs, &hiding, &pk).unwrap();
+        assert!(!derived_2
+            .verify_with_options(&disclosed_msgs(&hiding), &SigningOptions::new(), &pk)
+            .unwrap());
+
+        // Key without a slot for the policy message and index out of range
+        assert!(RSSignature::new_with_options(&all_msgs, &sk, &params, &options).is_err());
+        let out_of_range = SigningOptions::non_redactable(vec![count_msgs]);
+        match RSSignature::new_with_options(&msgs, &sk, &params, &out_of_range) {
+            Err(PSError::InvalidIndex { .. }) => (),
+            r => panic!("Expected InvalidIndex but got {:?}", r),
+        }
+    }
+}
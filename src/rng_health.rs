@@ -0,0 +1,181 @@
+// Health checks of the randomness of signatures. The nonce u of a signature must never be 0, which gives the
+// identity as sigma_1, nor be that of another signature of the same key, which gives away the signing key from the
+// two signatures, so a broken random number generator makes weak signatures without anything failing. Signing draws
+// its nonces through a `NonceHealthCheck`, which fails with `PSError::RngFailure` on a zero nonce or on one equal to
+// one of the last `RECENT_NONCES` it gave. `Signature::new` and `RSSignature::new` use one per thread over the thread
+// local random number generator, the `new_with_source` variants take one over any `ScalarSource`.
+// For tests, `TestRng` is a `ScalarSource` which can be made to fail by returning zero or repeated scalars, so tests
+// check the failures come out as errors.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use rand::rngs::ThreadRng;
+use rand::{thread_rng, CryptoRng, RngCore};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+/// Count of recent nonces a `NonceHealthCheck` compares new nonces with
+pub const RECENT_NONCES: usize = 16;
+
+/// Source of the random scalars of signing
+pub trait ScalarSource {
+    fn next_scalar(&mut self) -> FieldElement;
+}
+
+impl<R: RngCore + CryptoRng> ScalarSource for R {
+    fn next_scalar(&mut self) -> FieldElement {
+        FieldElement::random_using_rng(self)
+    }
+}
+
+/// Nonces of a `ScalarSource` checked to be non-zero and different from the recent ones
+#[derive(Clone, Debug)]
+pub struct NonceHealthCheck<S> {
+    source: S,
+    recent: VecDeque<FieldElement>,
+}
+
+impl<S: ScalarSource> NonceHealthCheck<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            recent: VecDeque::with_capacity(RECENT_NONCES),
+        }
+    }
+
+    /// Next nonce of the source, failing with `PSError::RngFailure` if it is zero or a recent nonce
+    pub fn nonce(&mut self) -> Result<FieldElement, PSError> {
+        let nonce = self.source.next_scalar();
+        if nonce.is_zero() {
+            return Err(PSError::RngFailure {
+                reason: String::from("zero nonce"),
+            });
+        }
+        if self.recent.contains(&nonce) {
+            return Err(PSError::RngFailure {
+                reason: String::from("repeated nonce"),
+            });
+        }
+        if self.recent.len() == RECENT_NONCES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(nonce.clone());
+        Ok(nonce)
+    }
+}
+
+thread_local! {
+    static THREAD_NONCES: RefCell<NonceHealthCheck<ThreadRng>> =
+        RefCell::new(NonceHealthCheck::new(thread_rng()));
+}
+
+/// Nonce of the check of this thread over the thread local random number generator
+pub(crate) fn thread_nonce() -> Result<FieldElement, PSError> {
+    THREAD_NONCES.with(|n| n.borrow_mut().nonce())
+}
+
+/// Failure `TestRng` injects
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RngFault {
+    /// Every scalar is 0
+    Zero,
+    /// Every scalar is the first one
+    Repeat,
+}
+
+/// Seeded `ScalarSource` for tests, with an optional failure
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Clone, Debug)]
+pub struct TestRng {
+    rng: rand::rngs::StdRng,
+    fault: Option<RngFault>,
+    first: Option<FieldElement>,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl TestRng {
+    /// Scalars of a generator seeded with `seed`, the same for the same seed
+    pub fn seeded(seed: u64) -> Self {
+        use rand::SeedableRng;
+        Self {
+            rng: rand::rngs::StdRng::seed_from_u64(seed),
+            fault: None,
+            first: None,
+        }
+    }
+
+    /// Inject `fault` in the scalars returned from now on
+    pub fn with_fault(mut self, fault: RngFault) -> Self {
+        self.fault = Some(fault);
+        self
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl ScalarSource for TestRng {
+    fn next_scalar(&mut self) -> FieldElement {
+        if self.fault == Some(RngFault::Zero) {
+            return FieldElement::zero();
+        }
+        let scalar = FieldElement::random_using_rng(&mut self.rng);
+        match (self.fault, &self.first) {
+            (Some(RngFault::Repeat), Some(first)) => first.clone(),
+            _ => {
+                self.first.get_or_insert_with(|| scalar.clone());
+                scalar
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, rsskeygen, Params};
+    use crate::rss_signature::RSSignature;
+    use crate::signature::Signature;
+
+    #[test]
+    fn test_nonce_health_checks() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(2, &params).unwrap();
+        let (sk_rss, pk_rss) = rsskeygen(2, &params).unwrap();
+        let msgs = vec![FieldElement::random(), FieldElement::random()];
+
+        let mut nonces = NonceHealthCheck::new(TestRng::seeded(1));
+        for _ in 0..2 * RECENT_NONCES {
+            let sig = Signature::new_with_source(&msgs, &sk, &params, &mut nonces).unwrap();
+            assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+        }
+        let sig = RSSignature::new_with_source(&msgs, &sk_rss, &params, &mut nonces).unwrap();
+        assert!(sig.verify(&msgs, &pk_rss).unwrap());
+        // The same seed gives the same nonces
+        assert_eq!(
+            TestRng::seeded(1).next_scalar(),
+            TestRng::seeded(1).next_scalar()
+        );
+
+        let mut zero = NonceHealthCheck::new(TestRng::seeded(2).with_fault(RngFault::Zero));
+        match Signature::new_with_source(&msgs, &sk, &params, &mut zero) {
+            Err(PSError::RngFailure { .. }) => (),
+            r => panic!("Expected RngFailure but got {:?}", r),
+        }
+        assert!(RSSignature::new_with_source(&msgs, &sk_rss, &params, &mut zero).is_err());
+
+        // The first signature is fine, the next ones would reuse its nonce
+        let mut repeat = NonceHealthCheck::new(TestRng::seeded(3).with_fault(RngFault::Repeat));
+        let sig = Signature::new_with_source(&msgs, &sk, &params, &mut repeat).unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+        match Signature::new_with_source(&msgs, &sk, &params, &mut repeat) {
+            Err(PSError::RngFailure { .. }) => (),
+            r => panic!("Expected RngFailure but got {:?}", r),
+        }
+        assert!(RSSignature::new_with_source(&msgs, &sk_rss, &params, &mut repeat).is_err());
+
+        // The thread local generator passes the checks
+        for _ in 0..RECENT_NONCES + 1 {
+            thread_nonce().unwrap();
+        }
+    }
+}
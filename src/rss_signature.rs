@@ -0,0 +1,1718 @@
+// Redactable signature scheme from the paper "Efficient Redactable Signature and Application to Anonymous
+// Credentials" by Olivier Sanders, PKC 2020. A signature over n messages can be derived by anyone into a
+// signature over any subset of the messages, the redacted messages stay hidden and the derived signature is
+// unlinkable to the original.
+// The paper indexes messages from 1 to n, the API here uses indices from 0 to n-1 like the rest of the crate
+// so message at index `i` corresponds to m_{i+1} of the paper. Indices as in the paper, used with the elements
+// of the public key, are typed as `MsgIndex` so the two kinds cannot be mixed up.
+
+use crate::challenge_domain::RSS_DERIVE;
+use crate::curve::{FieldElement, FieldElementVector, GroupElement, GroupElementVector};
+use crate::errors::PSError;
+use crate::keys::{check_message_count, powers, Generators, PKrss, Params, SKrss};
+use crate::msm::multi_scalar_mul_var_time;
+use crate::perf_hints::PerfHints;
+use crate::pok_sig::{FixedBaseTable, WINDOW_SIZE};
+use crate::rng_health::{thread_nonce, NonceHealthCheck, ScalarSource};
+use crate::scalar::MessageScalar;
+use crate::signature::{
+    check_subgroup, group_elem_from_bytes_with_checks, identity_report, in_group,
+    non_identity_in_group, SubgroupChecks,
+};
+use crate::verification_report::{Check, VerificationReport};
+use crate::{
+    ate_2_pairing, ate_pairing, SignatureGroup, SignatureGroupVec, VerkeyGroup, VerkeyGroupVec,
+    SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE,
+};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::num::NonZeroUsize;
+
+/// Index of a message, or of an element of the public key, as in the paper, i.e. starting from 1. Converting
+/// from the 0-based indices of the rest of the API is done by `from_zero_based`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MsgIndex(NonZeroUsize);
+
+impl MsgIndex {
+    pub fn new(i: usize) -> Option<Self> {
+        NonZeroUsize::new(i).map(MsgIndex)
+    }
+
+    /// Index of the message at 0-based index `i`, i.e. i+1
+    pub fn from_zero_based(i: usize) -> Result<Self, PSError> {
+        i.checked_add(1)
+            .and_then(Self::new)
+            .ok_or_else(|| PSError::InvalidIndex {
+                idx: i,
+                valid: String::from("[0, usize::MAX)"),
+            })
+    }
+
+    /// Index of a message for a key of `n` messages, checks `i` is in [1, n]
+    pub fn message(i: usize, n: usize) -> Result<Self, PSError> {
+        let idx = Self::try_from(i)?;
+        check_Y_tilde_index(idx, n)?;
+        Ok(idx)
+    }
+
+    /// Index of an element Y_i of a key of `n` messages, checks `i` is in [1, n] or [n+2, 2n]
+    pub fn key_element(i: usize, n: usize) -> Result<Self, PSError> {
+        let idx = Self::try_from(i)?;
+        check_Y_index(idx, n)?;
+        Ok(idx)
+    }
+
+    /// Index n + plus - minus of an element Y_i of a key of `n` messages, failing instead of wrapping around and as
+    /// `key_element`
+    pub fn key_element_offset(n: usize, plus: usize, minus: usize) -> Result<Self, PSError> {
+        let i = n
+            .checked_add(plus)
+            .and_then(|i| i.checked_sub(minus))
+            .ok_or_else(|| PSError::InvalidIndex {
+                idx: minus,
+                valid: format!("[0, {}]", n.saturating_add(plus)),
+            })?;
+        Self::key_element(i, n)
+    }
+
+    pub fn get(self) -> usize {
+        self.0.get()
+    }
+
+    pub fn zero_based(self) -> usize {
+        self.0.get() - 1
+    }
+}
+
+impl TryFrom<usize> for MsgIndex {
+    type Error = PSError;
+
+    fn try_from(i: usize) -> Result<Self, PSError> {
+        Self::new(i).ok_or_else(|| PSError::InvalidIndex {
+            idx: i,
+            valid: String::from("[1, usize::MAX]"),
+        })
+    }
+}
+
+impl From<MsgIndex> for usize {
+    fn from(i: MsgIndex) -> usize {
+        i.get()
+    }
+}
+
+/// Elements of the RSS public key needed to derive and verify signatures. Implemented by `PKrss` and by
+/// keys which are not fully held in memory. Indices are as in the paper, see `MsgIndex`.
+pub trait RSSPublicKey {
+    /// Number of messages `n` supported by the key
+    fn msg_count(&self) -> usize;
+
+    fn g(&self) -> SignatureGroup;
+
+    fn g_tilde(&self) -> VerkeyGroup;
+
+    fn generators(&self) -> Generators {
+        Generators {
+            g: self.g(),
+            g_tilde: self.g_tilde(),
+        }
+    }
+
+    fn X_tilde(&self) -> VerkeyGroup;
+
+    /// `Params::label_hash` of the params the key was generated with
+    fn params_label_hash(&self) -> FieldElement;
+
+    /// Fails with `PSError::ParamsMismatch` if the key was not generated with `params`
+    fn check_params(&self, params: &Params) -> Result<(), PSError> {
+        if self.params_label_hash() != params.label_hash {
+            return Err(PSError::ParamsMismatch);
+        }
+        Ok(())
+    }
+
+    /// g_tilde^{y^i} for i in 1..=n
+    fn Y_tilde(&self, i: MsgIndex) -> Result<VerkeyGroup, PSError>;
+
+    /// g^{y^i} for i in 1..=n and n+2..=2n, failing with `PSError::ForbiddenIndex` for n+1
+    fn Y(&self, i: MsgIndex) -> Result<SignatureGroup, PSError>;
+}
+
+impl RSSPublicKey for PKrss {
+    fn msg_count(&self) -> usize {
+        self.n
+    }
+
+    fn g(&self) -> SignatureGroup {
+        self.g.clone()
+    }
+
+    fn g_tilde(&self) -> VerkeyGroup {
+        self.g_tilde.clone()
+    }
+
+    fn X_tilde(&self) -> VerkeyGroup {
+        self.X_tilde.clone()
+    }
+
+    fn params_label_hash(&self) -> FieldElement {
+        self.params_label_hash.clone()
+    }
+
+    fn Y_tilde(&self, i: MsgIndex) -> Result<VerkeyGroup, PSError> {
+        check_Y_tilde_index(i, self.msg_count())?;
+        Ok(self.Y_tilde_i[i.zero_based()].clone())
+    }
+
+    fn Y(&self, i: MsgIndex) -> Result<SignatureGroup, PSError> {
+        self.y(i).cloned()
+    }
+}
+
+impl PKrss {
+    /// g^{y^i} for i in 1..=n and n+2..=2n, skipping the gap at n+1 between `Y_j_1_to_n` and `Y_k_nplus2_to_2n`.
+    /// Fails with `PSError::ForbiddenIndex` for i = `skipped_index(n)` and with `PSError::InvalidIndex` after 2n.
+    /// Every access to the Y elements of the key, including those of derivation and verification, goes through it.
+    pub fn y(&self, i: MsgIndex) -> Result<&SignatureGroup, PSError> {
+        let n = self.msg_count();
+        check_Y_index(i, n)?;
+        let i = i.get();
+        if i <= n {
+            Ok(&self.Y_j_1_to_n[i - 1])
+        } else {
+            Ok(&self.Y_k_nplus2_to_2n[i - n - 2])
+        }
+    }
+
+    /// Bases Y_{n+1-i} of the verification of a signature disclosing the 0-based indices `disclosed`, in increasing
+    /// order of the disclosed index i+1, i.e. the elements raised to the challenges c_i in `RedactedSignature::verify`
+    pub fn bases_for(&self, disclosed: &DisclosureSet) -> Result<Vec<&SignatureGroup>, PSError> {
+        let n = self.msg_count();
+        verification_base_indices(&sorted_indices(disclosed.iter().cloned(), n)?, n)?
+            .into_iter()
+            .map(|i| self.y(i))
+            .collect()
+    }
+}
+
+/// Indices n+1-i of the bases raised to the challenges, for each disclosed message i, i.e. n-j for a 0-based index j
+fn verification_base_indices(
+    disclosed: &BTreeSet<usize>,
+    n: usize,
+) -> Result<Vec<MsgIndex>, PSError> {
+    disclosed
+        .iter()
+        .map(|i| MsgIndex::key_element_offset(n, 0, *i))
+        .collect()
+}
+
+pub(crate) fn check_Y_tilde_index(i: MsgIndex, n: usize) -> Result<(), PSError> {
+    if i.get() > n {
+        return Err(PSError::InvalidIndex {
+            idx: i.get(),
+            valid: format!("[1, {}]", n),
+        });
+    }
+    Ok(())
+}
+
+/// Offset from n of the index of the element g^{y^{n+1}}, which a key must never have: with it anyone could forge
+/// signatures on any messages
+pub const SKIPPED_INDEX_OFFSET: usize = 1;
+
+/// Index n+1 of the element Y_{n+1} missing from a key of `n` messages
+pub fn skipped_index(n: usize) -> usize {
+    n + SKIPPED_INDEX_OFFSET
+}
+
+/// Y_{n+1} is never part of the key, asking for it fails with `PSError::ForbiddenIndex`
+pub(crate) fn check_Y_index(i: MsgIndex, n: usize) -> Result<(), PSError> {
+    if i.get() == skipped_index(n) {
+        return Err(PSError::ForbiddenIndex { idx: i.get() });
+    }
+    if i.get() > 2 * n {
+        return Err(PSError::InvalidIndex {
+            idx: i.get(),
+            valid: format!("[1, {}] or [{}, {}]", n, n + 2, 2 * n),
+        });
+    }
+    Ok(())
+}
+
+/// Signature over all messages, as created by the signer. Its elements are checked to be in the prime order group
+/// when deserialized with serde, as with `from_bytes`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "RSSignatureElements")]
+pub struct RSSignature {
+    pub sigma_1: SignatureGroup,
+    pub sigma_2: SignatureGroup,
+}
+
+/// Signature over a subset of messages, derived from `RSSignature` by the holder. Its elements are checked to be in
+/// the prime order group when deserialized with serde, as with `from_bytes`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "RedactedSignatureElements")]
+pub struct RedactedSignature {
+    pub sigma_1: SignatureGroup,
+    pub sigma_2: SignatureGroup,
+    pub sigma_3: VerkeyGroup,
+    pub sigma_4: SignatureGroup,
+}
+
+#[derive(Deserialize)]
+struct RSSignatureElements {
+    sigma_1: SignatureGroup,
+    sigma_2: SignatureGroup,
+}
+
+#[derive(Deserialize)]
+struct RedactedSignatureElements {
+    sigma_1: SignatureGroup,
+    sigma_2: SignatureGroup,
+    sigma_3: VerkeyGroup,
+    sigma_4: SignatureGroup,
+}
+
+impl TryFrom<RSSignatureElements> for RSSignature {
+    type Error = PSError;
+
+    fn try_from(e: RSSignatureElements) -> Result<Self, PSError> {
+        check_subgroup(&[&e.sigma_1, &e.sigma_2])?;
+        Ok(Self {
+            sigma_1: e.sigma_1,
+            sigma_2: e.sigma_2,
+        })
+    }
+}
+
+impl TryFrom<RedactedSignatureElements> for RedactedSignature {
+    type Error = PSError;
+
+    fn try_from(e: RedactedSignatureElements) -> Result<Self, PSError> {
+        check_subgroup(&[&e.sigma_1, &e.sigma_2, &e.sigma_4])?;
+        check_subgroup(&[&e.sigma_3])?;
+        Ok(Self {
+            sigma_1: e.sigma_1,
+            sigma_2: e.sigma_2,
+            sigma_3: e.sigma_3,
+            sigma_4: e.sigma_4,
+        })
+    }
+}
+
+impl RSSignature {
+    /// Sign all messages, exactly `sk.n` of them. sigma_1 = g^u and
+    /// sigma_2 = g^{u*(x + y*m_1 + y^2*m_2 + ... y^n*m_n)} for a random u
+    pub fn new<M: MessageScalar>(
+        messages: &[M],
+        sk: &SKrss,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        Self::new_with_nonce(&M::field_elements(messages), sk, params, thread_nonce)
+    }
+
+    /// Same as `new` with the nonce u from `nonces` rather than the thread local random number generator
+    pub fn new_with_source<M: MessageScalar, S: ScalarSource>(
+        messages: &[M],
+        sk: &SKrss,
+        params: &Params,
+        nonces: &mut NonceHealthCheck<S>,
+    ) -> Result<Self, PSError> {
+        Self::new_with_nonce(&M::field_elements(messages), sk, params, || nonces.nonce())
+    }
+
+    fn new_with_nonce<F: FnOnce() -> Result<FieldElement, PSError>>(
+        messages: &[FieldElement],
+        sk: &SKrss,
+        params: &Params,
+        nonce: F,
+    ) -> Result<Self, PSError> {
+        // The key must not sign any other count of messages, see `SKrss::to_pkrss`
+        if messages.len() != sk.n {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: sk.n,
+                given: messages.len(),
+            });
+        }
+        let u = nonce()?;
+        let mut exp = sk.x.clone();
+        for (y_i, m) in powers(&sk.y, messages.len()).iter().zip(messages) {
+            exp += y_i * m;
+        }
+        crate::profiling::record_scalar_muls(2);
+        let sigma_1 = &params.g * &u;
+        let sigma_2 = &params.g * &(exp * &u);
+        Ok(Self { sigma_1, sigma_2 })
+    }
+
+    /// Verify the signature over all messages
+    pub fn verify<M: MessageScalar, K: RSSPublicKey>(
+        &self,
+        messages: &[M],
+        pk: &K,
+    ) -> Result<bool, PSError> {
+        let messages = &M::field_elements(messages);
+        check_pk_and_messages_compat(messages.len(), pk)?;
+        if self.sigma_1.is_identity() || self.sigma_2.is_identity() {
+            return Ok(false);
+        }
+        // e(sigma_1, X_tilde * Y_tilde_1^m_1 * ... Y_tilde_n^m_n) == e(sigma_2, g_tilde)
+        let J = X_tilde_Y_m(messages, pk)?;
+        Ok(ate_2_pairing(&self.sigma_1, &J, &(-&self.sigma_2), &pk.g_tilde()).is_one())
+    }
+
+    /// Same as `verify` for a signature expected under `params`, failing fast with `PSError::ParamsMismatch` if the
+    /// key was generated with other params
+    pub fn verify_with_params<M: MessageScalar, K: RSSPublicKey>(
+        &self,
+        messages: &[M],
+        pk: &K,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        pk.check_params(params)?;
+        self.verify(messages, pk)
+    }
+
+    /// Checks without pairings for rejecting malformed signatures before `verify`, as `Signature::precheck`
+    pub fn precheck<M: MessageScalar, K: RSSPublicKey>(
+        &self,
+        messages: &[M],
+        pk: &K,
+    ) -> Result<bool, PSError> {
+        check_pk_and_messages_compat(messages.len(), pk)?;
+        Ok(non_identity_in_group(&[&self.sigma_1, &self.sigma_2]))
+    }
+
+    /// Same as `verify` but reports which check failed, see `verification_report`. When an equation fails
+    /// because of an inconsistent element of the public key, the index of that element is reported.
+    pub fn verify_detailed<M: MessageScalar, K: RSSPublicKey>(
+        &self,
+        messages: &[M],
+        pk: &K,
+    ) -> Result<VerificationReport, PSError> {
+        let messages = &M::field_elements(messages);
+        check_pk_and_messages_compat(messages.len(), pk)?;
+        if let Some(report) = identity_report(&[&self.sigma_1, &self.sigma_2]) {
+            return Ok(report);
+        }
+        let J = X_tilde_Y_m(messages, pk)?;
+        let report = VerificationReport::pairing_equation(
+            Check::SignaturePairing,
+            None,
+            ate_pairing(&self.sigma_1, &J),
+            ate_pairing(&self.sigma_2, &pk.g_tilde()),
+        );
+        if report.is_valid() {
+            return Ok(report);
+        }
+        Ok(key_elements_report(pk, 1..=messages.len())?.unwrap_or(report))
+    }
+
+    /// Derive a signature on messages with indices in `disclosed`, the remaining messages are redacted.
+    /// `messages` are all signed messages.
+    pub fn derive<M: MessageScalar, K: RSSPublicKey>(
+        &self,
+        messages: &[M],
+        disclosed: &HashSet<usize>,
+        pk: &K,
+    ) -> Result<RedactedSignature, PSError> {
+        let messages = &M::field_elements(messages);
+        let n = messages.len();
+        check_pk_and_messages_compat(n, pk)?;
+        let disclosed = sorted_indices(disclosed.iter().cloned(), n)?;
+
+        let r = FieldElement::random();
+        let t = FieldElement::random();
+        // sigma_1' = sigma_1^r, sigma_2' = (sigma_2 * sigma_1^t)^r
+        crate::profiling::record_scalar_muls(3);
+        let sigma_1 = &self.sigma_1 * &r;
+        let sigma_2 = (&self.sigma_2 + (&self.sigma_1 * &t)) * &r;
+
+        // sigma_3 = g_tilde^t * prod_{j not disclosed} Y_tilde_j^m_j
+        let mut bases = VerkeyGroupVec::with_capacity(n - disclosed.len() + 1);
+        let mut exps = FieldElementVector::with_capacity(n - disclosed.len() + 1);
+        bases.push(pk.g_tilde());
+        exps.push(t.clone());
+        for (j, m) in messages.iter().enumerate() {
+            if disclosed.contains(&j) {
+                continue;
+            }
+            bases.push(pk.Y_tilde(MsgIndex::from_zero_based(j)?)?);
+            exps.push(m.clone());
+        }
+        crate::profiling::record_scalar_muls(bases.len());
+        let sigma_3 = bases.multi_scalar_mul_const_time(exps.as_slice()).unwrap();
+
+        // sigma_4 = prod_{i disclosed} (Y_{n+1-i}^t * prod_{j not disclosed} Y_{n+1-i+j}^m_j)^c_i
+        let c = challenges(&sigma_1, &sigma_2, &sigma_3, &disclosed);
+        let mut bases =
+            SignatureGroupVec::with_capacity(disclosed.len() * (n - disclosed.len() + 1));
+        let mut exps =
+            FieldElementVector::with_capacity(disclosed.len() * (n - disclosed.len() + 1));
+        for (i, c_i) in disclosed.iter().map(|i| i + 1).zip(c.iter()) {
+            bases.push(pk.Y(MsgIndex::key_element_offset(n, 1, i)?)?);
+            exps.push(c_i * &t);
+            for (j, m) in messages.iter().enumerate() {
+                if disclosed.contains(&j) {
+                    continue;
+                }
+                bases.push(pk.Y(MsgIndex::key_element_offset(n, j + 2, i)?)?);
+                exps.push(c_i * m);
+            }
+        }
+        let sigma_4 = if bases.len() == 0 {
+            SignatureGroup::identity()
+        } else {
+            crate::profiling::record_scalar_muls(bases.len());
+            bases.multi_scalar_mul_const_time(exps.as_slice()).unwrap()
+        };
+
+        Ok(RedactedSignature {
+            sigma_1,
+            sigma_2,
+            sigma_3,
+            sigma_4,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.append(&mut self.sigma_1.to_bytes());
+        bytes.append(&mut self.sigma_2.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_bytes_with_checks(bytes, SubgroupChecks::default())
+    }
+
+    /// Same as `from_bytes` with the subgroup checks of the elements enabled or not
+    pub fn from_bytes_with_checks(bytes: &[u8], checks: SubgroupChecks) -> Result<Self, PSError> {
+        if bytes.len() != 2 * SIGNATURE_GROUP_SIZE {
+            return Err(PSError::DeserializationError {
+                msg: format!(
+                    "Signature needs {} bytes but given {}",
+                    2 * SIGNATURE_GROUP_SIZE,
+                    bytes.len()
+                ),
+            });
+        }
+        let (sigma_1, sigma_2) = bytes.split_at(SIGNATURE_GROUP_SIZE);
+        Ok(Self {
+            sigma_1: group_elem_from_bytes_with_checks(sigma_1, checks)?,
+            sigma_2: group_elem_from_bytes_with_checks(sigma_2, checks)?,
+        })
+    }
+}
+
+/// Indices of the messages disclosed by a derived signature
+pub type DisclosureSet = HashSet<usize>;
+
+/// Same as calling `RSSignature::derive` for each of `disclosure_sets` but the elements of the public key are
+/// fetched once, tables of multiples of them and of the signature are shared by all derivations and the
+/// derivations run in parallel. The tables cost a few scalar multiplications per element, so their windows are
+/// chosen by `PerfHints` for the count of derivations and a single derivation builds none, see test
+/// `timing_redact_many`. The tables are read in constant time as `derive` multiplies in constant time.
+pub fn redact_many<M: MessageScalar, K: RSSPublicKey>(
+    sig: &RSSignature,
+    messages: &[M],
+    disclosure_sets: &[DisclosureSet],
+    pk: &K,
+) -> Result<Vec<RedactedSignature>, PSError> {
+    let messages = &M::field_elements(messages);
+    let n = messages.len();
+    check_pk_and_messages_compat(n, pk)?;
+    let disclosure_sets = disclosure_sets
+        .iter()
+        .map(|d| sorted_indices(d.iter().cloned(), n))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Only the elements used by some derivation. Y_tilde_j for hidden j, Y_{n+1-i} and Y_{n+1-i+j} for
+    // disclosed i and hidden j (paper indices).
+    let mut Y_tilde_indices = BTreeSet::new();
+    let mut Y_indices = BTreeSet::new();
+    for disclosed in &disclosure_sets {
+        let hidden: Vec<usize> = (1..=n).filter(|j| !disclosed.contains(&(j - 1))).collect();
+        Y_tilde_indices.extend(hidden.iter().cloned());
+        for i in disclosed.iter().map(|i| i + 1) {
+            Y_indices.insert(n + 1 - i);
+            Y_indices.extend(hidden.iter().map(|j| n + 1 - i + j));
+        }
+    }
+    let mut Y_tilde = HashMap::new();
+    for j in Y_tilde_indices {
+        Y_tilde.insert(j, pk.Y_tilde(MsgIndex::message(j, n)?)?);
+    }
+    let mut Y = HashMap::new();
+    for k in Y_indices {
+        Y.insert(k, pk.Y(MsgIndex::key_element(k, n)?)?);
+    }
+
+    // sigma_1 is multiplied twice per derivation, the other bases at most once
+    let count = disclosure_sets.len();
+    let sigma_1_window = PerfHints::new(1, 2 * count).window_size();
+    let window = PerfHints::new(Y_tilde.len() + Y.len() + 2, count).window_size();
+    let sigma_1_table = FixedBaseTable::with_window(&sig.sigma_1, sigma_1_window);
+    let sigma_2_table = FixedBaseTable::with_window(&sig.sigma_2, window);
+    let g_tilde_table = FixedBaseTable::with_window(&pk.g_tilde(), window);
+    let Y_tilde_tables: HashMap<usize, FixedBaseTable<VerkeyGroup>> = Y_tilde
+        .into_par_iter()
+        .map(|(j, e)| (j, FixedBaseTable::with_window(&e, window)))
+        .collect();
+    let Y_tables: HashMap<usize, FixedBaseTable<SignatureGroup>> = Y
+        .into_par_iter()
+        .map(|(k, e)| (k, FixedBaseTable::with_window(&e, window)))
+        .collect();
+
+    Ok(disclosure_sets
+        .par_iter()
+        .map(|disclosed| {
+            let r = FieldElement::random();
+            let t = FieldElement::random();
+            // r, t and the hidden messages are secret, so the tables are read in constant time
+            // sigma_1' = sigma_1^r, sigma_2' = sigma_1^{t*r} * sigma_2^r
+            let mut sigma_1 = SignatureGroup::identity();
+            sigma_1_table.mul_add_const_time(&r, &mut sigma_1);
+            let mut sigma_2 = SignatureGroup::identity();
+            sigma_1_table.mul_add_const_time(&(&t * &r), &mut sigma_2);
+            sigma_2_table.mul_add_const_time(&r, &mut sigma_2);
+
+            // sigma_3 = g_tilde^t * prod_{j not disclosed} Y_tilde_j^m_j
+            let mut sigma_3 = VerkeyGroup::identity();
+            g_tilde_table.mul_add_const_time(&t, &mut sigma_3);
+            for (j, m) in messages.iter().enumerate() {
+                if !disclosed.contains(&j) {
+                    Y_tilde_tables[&(j + 1)].mul_add_const_time(m, &mut sigma_3);
+                }
+            }
+
+            // sigma_4 as in `derive` with the exponents of each Y_k added up first
+            let c = challenges(&sigma_1, &sigma_2, &sigma_3, disclosed);
+            let mut exps: BTreeMap<usize, FieldElement> = BTreeMap::new();
+            for (i, c_i) in disclosed.iter().map(|i| i + 1).zip(c.iter()) {
+                *exps.entry(n + 1 - i).or_insert_with(FieldElement::zero) += c_i * &t;
+                for (j, m) in messages.iter().enumerate() {
+                    if !disclosed.contains(&j) {
+                        *exps
+                            .entry(n + 1 - i + j + 1)
+                            .or_insert_with(FieldElement::zero) += c_i * m;
+                    }
+                }
+            }
+            let mut sigma_4 = SignatureGroup::identity();
+            for (k, e) in exps {
+                Y_tables[&k].mul_add_const_time(&e, &mut sigma_4);
+            }
+
+            RedactedSignature {
+                sigma_1,
+                sigma_2,
+                sigma_3,
+                sigma_4,
+            }
+        })
+        .collect())
+}
+
+/// Elements of a public key for verifying many derived signatures: g_tilde, X_tilde and tables of multiples of
+/// Y_tilde_i and Y_i for i in 1..=n, the elements the verification equations take, so that their multi-scalar
+/// multiplications are additions of table entries. Built once and shared by the threads of
+/// `verify_batch_parallel`. The tables cost a few scalar multiplications and about a thousand group elements of
+/// memory per element. Deserialization checks the counts of tables against the count of messages and each table
+/// against its base.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "PreparedPKrssFields")]
+pub struct PreparedPKrss {
+    n: usize,
+    g_tilde: VerkeyGroup,
+    X_tilde: VerkeyGroup,
+    Y_tilde_tables: Vec<FixedBaseTable<VerkeyGroup>>,
+    /// Of Y_1..Y_n, the elements Y_{n+1-i} for disclosed i
+    Y_tables: Vec<FixedBaseTable<SignatureGroup>>,
+}
+
+/// Fields of `PreparedPKrss` as deserialized, before checking the counts of tables against `n`
+#[derive(Deserialize)]
+struct PreparedPKrssFields {
+    n: usize,
+    g_tilde: VerkeyGroup,
+    X_tilde: VerkeyGroup,
+    Y_tilde_tables: Vec<FixedBaseTable<VerkeyGroup>>,
+    Y_tables: Vec<FixedBaseTable<SignatureGroup>>,
+}
+
+impl TryFrom<PreparedPKrssFields> for PreparedPKrss {
+    type Error = PSError;
+
+    fn try_from(f: PreparedPKrssFields) -> Result<Self, PSError> {
+        if check_message_count(f.n).is_err()
+            || f.Y_tilde_tables.len() != f.n
+            || f.Y_tables.len() != f.n
+        {
+            return Err(PSError::DeserializationError {
+                msg: format!("Tables of key do not match count of messages {}", f.n),
+            });
+        }
+        check_subgroup(&[&f.g_tilde, &f.X_tilde])?;
+        Ok(Self {
+            n: f.n,
+            g_tilde: f.g_tilde,
+            X_tilde: f.X_tilde,
+            Y_tilde_tables: f.Y_tilde_tables,
+            Y_tables: f.Y_tables,
+        })
+    }
+}
+
+/// Threads `verify_batch_parallel` runs on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ThreadBudget {
+    /// The global pool of rayon, shared with the rest of the process
+    #[default]
+    Global,
+    /// A pool of its own of this many threads, created for the call
+    Threads(NonZeroUsize),
+}
+
+/// Derived signature to verify in a batch with its disclosed messages
+#[derive(Clone, Debug)]
+pub struct RedactedBatchItem<'a> {
+    pub sig: &'a RedactedSignature,
+    pub disclosed_msgs: &'a HashMap<usize, FieldElement>,
+}
+
+impl PreparedPKrss {
+    pub fn new<K: RSSPublicKey>(pk: &K) -> Result<Self, PSError> {
+        Self::with_window(pk, WINDOW_SIZE)
+    }
+
+    /// Same as `new` with the tables chosen by `hints`, e.g. for the count of signatures to verify
+    pub fn with_hints<K: RSSPublicKey>(pk: &K, hints: &PerfHints) -> Result<Self, PSError> {
+        Self::with_window(pk, hints.window_size())
+    }
+
+    fn with_window<K: RSSPublicKey>(pk: &K, window: usize) -> Result<Self, PSError> {
+        let n = pk.msg_count();
+        let mut Y_tilde = Vec::with_capacity(n);
+        let mut Y = Vec::with_capacity(n);
+        for i in 1..=n {
+            Y_tilde.push(pk.Y_tilde(MsgIndex::message(i, n)?)?);
+            Y.push(pk.Y(MsgIndex::key_element(i, n)?)?);
+        }
+        Ok(Self {
+            n,
+            g_tilde: pk.g_tilde(),
+            X_tilde: pk.X_tilde(),
+            Y_tilde_tables: Y_tilde
+                .par_iter()
+                .map(|b| FixedBaseTable::with_window(b, window))
+                .collect(),
+            Y_tables: Y
+                .par_iter()
+                .map(|b| FixedBaseTable::with_window(b, window))
+                .collect(),
+        })
+    }
+
+    pub fn msg_count(&self) -> usize {
+        self.n
+    }
+}
+
+/// Verify each of `items` like `RedactedSignature::verify` with the tables of `pk`, in parallel on the threads of
+/// `threads`. Returns the result of each item in the order of `items`, so that one invalid presentation of a batch
+/// does not fail the others. Items are split between the threads by the work stealing of rayon, so a thread done
+/// with its items takes over those of a busy one and items disclosing different counts of messages balance out.
+/// Fails only if a pool of `ThreadBudget::Threads` cannot be created. See test `timing_verify_batch_parallel` for
+/// the throughput.
+pub fn verify_batch_parallel(
+    items: &[RedactedBatchItem],
+    pk: &PreparedPKrss,
+    threads: ThreadBudget,
+) -> Result<Vec<Result<bool, PSError>>, PSError> {
+    let verify = || {
+        items
+            .par_iter()
+            .map(|item| item.sig.verify_prepared(item.disclosed_msgs, pk))
+            .collect()
+    };
+    match threads {
+        ThreadBudget::Global => Ok(verify()),
+        ThreadBudget::Threads(count) => {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(count.get())
+                .build()
+                .map_err(|e| PSError::GeneralError {
+                    msg: format!("Cannot create a pool of {} threads: {}", count, e),
+                })?;
+            Ok(pool.install(verify))
+        }
+    }
+}
+
+impl RedactedSignature {
+    /// Verify the derived signature given the disclosed messages
+    pub fn verify<K: RSSPublicKey>(
+        &self,
+        disclosed_msgs: &HashMap<usize, FieldElement>,
+        pk: &K,
+    ) -> Result<bool, PSError> {
+        let n = pk.msg_count();
+        let disclosed = sorted_indices(disclosed_msgs.keys().cloned(), n)?;
+        if self.sigma_1.is_identity() || self.sigma_2.is_identity() {
+            return Ok(false);
+        }
+
+        // e(sigma_1, X_tilde * sigma_3 * prod_{i disclosed} Y_tilde_i^m_i) == e(sigma_2, g_tilde)
+        let J = self.J(&disclosed, disclosed_msgs, pk)?;
+        let g_tilde = pk.g_tilde();
+        if !ate_2_pairing(&self.sigma_1, &J, &(-&self.sigma_2), &g_tilde).is_one() {
+            return Ok(false);
+        }
+
+        // e(sigma_4, g_tilde) == e(prod_{i disclosed} Y_{n+1-i}^c_i, sigma_3)
+        let Y_c = self.Y_c(&disclosed, pk)?;
+        Ok(ate_2_pairing(&self.sigma_4, &g_tilde, &(-Y_c), &self.sigma_3).is_one())
+    }
+
+    /// Same as `verify` for a signature expected under `params`, failing fast with `PSError::ParamsMismatch` if the
+    /// key was generated with other params
+    pub fn verify_with_params<K: RSSPublicKey>(
+        &self,
+        disclosed_msgs: &HashMap<usize, FieldElement>,
+        pk: &K,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        pk.check_params(params)?;
+        self.verify(disclosed_msgs, pk)
+    }
+
+    /// Same as `verify` with the elements and tables of a `PreparedPKrss`
+    pub fn verify_prepared(
+        &self,
+        disclosed_msgs: &HashMap<usize, FieldElement>,
+        pk: &PreparedPKrss,
+    ) -> Result<bool, PSError> {
+        let n = pk.n;
+        let disclosed = sorted_indices(disclosed_msgs.keys().cloned(), n)?;
+        if self.sigma_1.is_identity() || self.sigma_2.is_identity() {
+            return Ok(false);
+        }
+
+        let mut J = pk.X_tilde.plus(&self.sigma_3);
+        for i in &disclosed {
+            pk.Y_tilde_tables[*i].mul_add(&disclosed_msgs[i], &mut J);
+        }
+        if !ate_2_pairing(&self.sigma_1, &J, &(-&self.sigma_2), &pk.g_tilde).is_one() {
+            return Ok(false);
+        }
+
+        let c = challenges(&self.sigma_1, &self.sigma_2, &self.sigma_3, &disclosed);
+        let mut Y_c = SignatureGroup::identity();
+        for (k, c_i) in verification_base_indices(&disclosed, n)?
+            .into_iter()
+            .zip(c.iter())
+        {
+            pk.Y_tables[k.zero_based()].mul_add(c_i, &mut Y_c);
+        }
+        Ok(ate_2_pairing(&self.sigma_4, &pk.g_tilde, &(-Y_c), &self.sigma_3).is_one())
+    }
+
+    /// Checks without pairings for rejecting malformed signatures before `verify`, as `Signature::precheck`:
+    /// the disclosed indices are less than the count of messages, sigma_1 and sigma_2 are not the identity and
+    /// all 4 elements are in the prime order groups.
+    pub fn precheck<K: RSSPublicKey>(
+        &self,
+        disclosed_msgs: &HashMap<usize, FieldElement>,
+        pk: &K,
+    ) -> Result<bool, PSError> {
+        sorted_indices(disclosed_msgs.keys().cloned(), pk.msg_count())?;
+        Ok(non_identity_in_group(&[&self.sigma_1, &self.sigma_2])
+            && in_group(&self.sigma_3)
+            && in_group(&self.sigma_4))
+    }
+
+    /// Same as `verify` but reports which check failed, see `verification_report`. When an equation fails
+    /// because of an inconsistent element of the public key, the index of that element is reported.
+    pub fn verify_detailed<K: RSSPublicKey>(
+        &self,
+        disclosed_msgs: &HashMap<usize, FieldElement>,
+        pk: &K,
+    ) -> Result<VerificationReport, PSError> {
+        let n = pk.msg_count();
+        let disclosed = sorted_indices(disclosed_msgs.keys().cloned(), n)?;
+        if let Some(report) = identity_report(&[&self.sigma_1, &self.sigma_2]) {
+            return Ok(report);
+        }
+
+        let J = self.J(&disclosed, disclosed_msgs, pk)?;
+        let g_tilde = pk.g_tilde();
+        let report = VerificationReport::pairing_equation(
+            Check::SignaturePairing,
+            None,
+            ate_pairing(&self.sigma_1, &J),
+            ate_pairing(&self.sigma_2, &g_tilde),
+        );
+        if !report.is_valid() {
+            let used = disclosed.iter().map(|i| i + 1);
+            return Ok(key_elements_report(pk, used)?.unwrap_or(report));
+        }
+
+        let Y_c = self.Y_c(&disclosed, pk)?;
+        let report = VerificationReport::pairing_equation(
+            Check::RedactionPairing,
+            None,
+            ate_pairing(&self.sigma_4, &g_tilde),
+            ate_pairing(&Y_c, &self.sigma_3),
+        );
+        if !report.is_valid() {
+            // Y elements used by the holder in sigma_4
+            let mut used = BTreeSet::new();
+            for i in disclosed.iter().map(|i| i + 1) {
+                used.insert(n + 1 - i);
+                for j in (0..n).filter(|j| !disclosed.contains(j)) {
+                    used.insert(n + 1 - i + j + 1);
+                }
+            }
+            return Ok(key_elements_report(pk, used)?.unwrap_or(report));
+        }
+        Ok(report)
+    }
+
+    /// X_tilde * sigma_3 * prod_{i disclosed} Y_tilde_i^m_i
+    fn J<K: RSSPublicKey>(
+        &self,
+        disclosed: &BTreeSet<usize>,
+        disclosed_msgs: &HashMap<usize, FieldElement>,
+        pk: &K,
+    ) -> Result<VerkeyGroup, PSError> {
+        let mut J = pk.X_tilde() + &self.sigma_3;
+        if !disclosed.is_empty() {
+            let mut bases = VerkeyGroupVec::with_capacity(disclosed.len());
+            let mut exps = FieldElementVector::with_capacity(disclosed.len());
+            for i in disclosed {
+                bases.push(pk.Y_tilde(MsgIndex::from_zero_based(*i)?)?);
+                exps.push(disclosed_msgs[i].clone());
+            }
+            J += multi_scalar_mul_var_time(bases.as_slice(), exps.as_slice())?;
+        }
+        Ok(J)
+    }
+
+    /// prod_{i disclosed} Y_{n+1-i}^c_i
+    fn Y_c<K: RSSPublicKey>(
+        &self,
+        disclosed: &BTreeSet<usize>,
+        pk: &K,
+    ) -> Result<SignatureGroup, PSError> {
+        if disclosed.is_empty() {
+            return Ok(SignatureGroup::identity());
+        }
+        let n = pk.msg_count();
+        let c = challenges(&self.sigma_1, &self.sigma_2, &self.sigma_3, disclosed);
+        let mut bases = SignatureGroupVec::with_capacity(disclosed.len());
+        for i in verification_base_indices(disclosed, n)? {
+            bases.push(pk.Y(i)?);
+        }
+        multi_scalar_mul_var_time(bases.as_slice(), c.as_slice())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.append(&mut self.sigma_1.to_bytes());
+        bytes.append(&mut self.sigma_2.to_bytes());
+        bytes.append(&mut self.sigma_3.to_bytes());
+        bytes.append(&mut self.sigma_4.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_bytes_with_checks(bytes, SubgroupChecks::default())
+    }
+
+    /// Same as `from_bytes` with the subgroup checks of the elements enabled or not
+    pub fn from_bytes_with_checks(bytes: &[u8], checks: SubgroupChecks) -> Result<Self, PSError> {
+        let size = 3 * SIGNATURE_GROUP_SIZE + VERKEY_GROUP_SIZE;
+        if bytes.len() != size {
+            return Err(PSError::DeserializationError {
+                msg: format!(
+                    "Redacted signature needs {} bytes but given {}",
+                    size,
+                    bytes.len()
+                ),
+            });
+        }
+        let (sigma_1, rest) = bytes.split_at(SIGNATURE_GROUP_SIZE);
+        let (sigma_2, rest) = rest.split_at(SIGNATURE_GROUP_SIZE);
+        let (sigma_3, sigma_4) = rest.split_at(VERKEY_GROUP_SIZE);
+        Ok(Self {
+            sigma_1: group_elem_from_bytes_with_checks(sigma_1, checks)?,
+            sigma_2: group_elem_from_bytes_with_checks(sigma_2, checks)?,
+            sigma_3: group_elem_from_bytes_with_checks(sigma_3, checks)?,
+            sigma_4: group_elem_from_bytes_with_checks(sigma_4, checks)?,
+        })
+    }
+}
+
+/// Check that the elements of the public key are consistent with each other, i.e. e(Y_i, g_tilde) == e(g, Y_tilde_i)
+/// for i in 1..=n and e(Y_i, g_tilde) == e(Y_n, Y_tilde_{i-n}) for i in n+2..=2n. Reports the first inconsistent index.
+pub fn verify_key_detailed<K: RSSPublicKey>(pk: &K) -> Result<VerificationReport, PSError> {
+    let n = pk.msg_count();
+    let indices = (1..=n).chain(n + 2..=2 * n);
+    Ok(key_elements_report(pk, indices)?.unwrap_or_else(VerificationReport::valid))
+}
+
+/// Same as `verify_key_detailed` and also checks that the elements are powers of a single y, i.e.
+/// e(Y_i, g_tilde) == e(Y_{i-1}, Y_tilde_1) for i in 2..=n, which the pairings of `verify_key_detailed` do not
+/// detect for a key generated from unrelated y_1..y_n. Together they imply that Y_tilde_i and Y_k are powers of y
+/// as well. Reports the first inconsistent index.
+pub fn verify_key_structure<K: RSSPublicKey>(pk: &K) -> Result<VerificationReport, PSError> {
+    let n = pk.msg_count();
+    let g_tilde = pk.g_tilde();
+    let Y_tilde_1 = pk.Y_tilde(MsgIndex::message(1, n)?)?;
+    let mut Y_prev = pk.Y(MsgIndex::message(1, n)?)?;
+    for i in 2..=n {
+        let Y_i = pk.Y(MsgIndex::message(i, n)?)?;
+        let report = VerificationReport::pairing_equation(
+            Check::KeyPowers,
+            Some(i),
+            ate_pairing(&Y_i, &g_tilde),
+            ate_pairing(&Y_prev, &Y_tilde_1),
+        );
+        if !report.is_valid() {
+            return Ok(report);
+        }
+        Y_prev = Y_i;
+    }
+    verify_key_detailed(pk)
+}
+
+impl PKrss {
+    /// See `verify_key_structure`
+    pub fn verify_structure(&self) -> Result<VerificationReport, PSError> {
+        verify_key_structure(self)
+    }
+}
+
+/// Report for the first of the given Y indices (in increasing order) whose element is inconsistent
+fn key_elements_report<K: RSSPublicKey>(
+    pk: &K,
+    indices: impl IntoIterator<Item = usize>,
+) -> Result<Option<VerificationReport>, PSError> {
+    let n = pk.msg_count();
+    let mut indices: BTreeSet<usize> = indices.into_iter().collect();
+    // Elements after n+1 are checked against Y_n
+    if indices.iter().any(|i| *i > n) {
+        indices.insert(n);
+    }
+    let g_tilde = pk.g_tilde();
+    for i in indices {
+        let idx = MsgIndex::key_element(i, n)?;
+        let recomputed = ate_pairing(&pk.Y(idx)?, &g_tilde);
+        let expected = if i <= n {
+            ate_pairing(&pk.g(), &pk.Y_tilde(idx)?)
+        } else {
+            ate_pairing(
+                &pk.Y(MsgIndex::message(n, n)?)?,
+                &pk.Y_tilde(MsgIndex::message(i - n, n)?)?,
+            )
+        };
+        let report =
+            VerificationReport::pairing_equation(Check::KeyElement, Some(i), recomputed, expected);
+        if !report.is_valid() {
+            return Ok(Some(report));
+        }
+    }
+    Ok(None)
+}
+
+/// X_tilde * Y_tilde_1^m_1 * ... Y_tilde_n^m_n
+fn X_tilde_Y_m<K: RSSPublicKey>(messages: &[FieldElement], pk: &K) -> Result<VerkeyGroup, PSError> {
+    let mut bases = VerkeyGroupVec::with_capacity(messages.len());
+    for i in 0..messages.len() {
+        bases.push(pk.Y_tilde(MsgIndex::from_zero_based(i)?)?);
+    }
+    Ok(multi_scalar_mul_var_time(bases.as_slice(), messages)? + pk.X_tilde())
+}
+
+fn check_pk_and_messages_compat<K: RSSPublicKey>(count: usize, pk: &K) -> Result<(), PSError> {
+    if count != pk.msg_count() {
+        return Err(PSError::UnsupportedNoOfMessages {
+            expected: pk.msg_count(),
+            given: count,
+        });
+    }
+    Ok(())
+}
+
+fn sorted_indices(
+    indices: impl Iterator<Item = usize>,
+    n: usize,
+) -> Result<BTreeSet<usize>, PSError> {
+    let indices: BTreeSet<usize> = indices.collect();
+    if let Some(i) = indices.iter().next_back() {
+        if *i >= n {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", i, n),
+            });
+        }
+    }
+    Ok(indices)
+}
+
+/// c_i = H(sigma_1 || sigma_2 || sigma_3 || disclosed indices || i) for each disclosed index i
+fn challenges(
+    sigma_1: &SignatureGroup,
+    sigma_2: &SignatureGroup,
+    sigma_3: &VerkeyGroup,
+    disclosed: &BTreeSet<usize>,
+) -> FieldElementVector {
+    let mut bytes = RSS_DERIVE.tag();
+    bytes.append(&mut sigma_1.to_bytes());
+    bytes.append(&mut sigma_2.to_bytes());
+    bytes.append(&mut sigma_3.to_bytes());
+    for i in disclosed {
+        bytes.extend_from_slice(&(*i as u64).to_be_bytes());
+    }
+    let mut c = FieldElementVector::with_capacity(disclosed.len());
+    for i in disclosed {
+        let mut b = bytes.clone();
+        b.extend_from_slice(b" : ");
+        b.extend_from_slice(&(*i as u64).to_be_bytes());
+        c.push(FieldElement::from_msg_hash(&b));
+    }
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::rsskeygen;
+    use crate::signature::fuzzed_bytes;
+    use std::time::Instant;
+
+    #[test]
+    fn test_rss_sign_verify() {
+        let params = Params::new("test".as_bytes());
+        for count_msgs in 1..6 {
+            let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+            let msgs = FieldElementVector::random(count_msgs);
+            let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+            assert!(sig.verify(msgs.as_slice(), &pk).unwrap());
+
+            let mut wrong = msgs.clone();
+            wrong[0] = FieldElement::random();
+            assert!(!sig.verify(wrong.as_slice(), &pk).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_rss_derive_and_verify() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 6;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let disclosure_sets: Vec<Vec<usize>> = vec![
+            vec![],
+            vec![0],
+            vec![count_msgs - 1],
+            vec![1, 3, 4],
+            (0..count_msgs).collect(),
+        ];
+        for d in disclosure_sets {
+            let disclosed: HashSet<usize> = d.into_iter().collect();
+            let derived = sig.derive(msgs.as_slice(), &disclosed, &pk).unwrap();
+            let mut disclosed_msgs = HashMap::new();
+            for i in &disclosed {
+                disclosed_msgs.insert(*i, msgs[*i].clone());
+            }
+            assert!(derived.verify(&disclosed_msgs, &pk).unwrap());
+
+            // Wrong disclosed message fails
+            if let Some(i) = disclosed.iter().next() {
+                let mut wrong = disclosed_msgs.clone();
+                wrong.insert(*i, FieldElement::random());
+                assert!(!derived.verify(&wrong, &pk).unwrap());
+            }
+            // Claiming an extra disclosed message fails
+            if disclosed.len() < count_msgs {
+                let i = (0..count_msgs).find(|i| !disclosed.contains(i)).unwrap();
+                let mut extra = disclosed_msgs.clone();
+                extra.insert(i, msgs[i].clone());
+                assert!(!derived.verify(&extra, &pk).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_rss_params_mismatch() {
+        let params = Params::new("test".as_bytes());
+        let other_params = Params::new("other".as_bytes());
+        let count_msgs = 4;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let (_, other_pk) = rsskeygen(count_msgs, &other_params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let disclosed: HashSet<usize> = vec![0, 2].into_iter().collect();
+        let derived = sig.derive(msgs.as_slice(), &disclosed, &pk).unwrap();
+        let disclosed_msgs: HashMap<usize, FieldElement> =
+            disclosed.iter().map(|i| (*i, msgs[*i].clone())).collect();
+
+        assert!(pk.check_params(&params).is_ok());
+        assert!(sig
+            .verify_with_params(msgs.as_slice(), &pk, &params)
+            .unwrap());
+        assert!(derived
+            .verify_with_params(&disclosed_msgs, &pk, &params)
+            .unwrap());
+        // The label hash survives encoding
+        let decoded = PKrss::from_bytes(&pk.to_bytes()).unwrap();
+        assert_eq!(decoded.params_label_hash, params.label_hash);
+
+        // Keys of other params fail, also read lazily
+        let lazy_other =
+            crate::lazy_pkrss::LazyPKrss::new(std::io::Cursor::new(other_pk.to_bytes())).unwrap();
+        for r in &[
+            sig.verify_with_params(msgs.as_slice(), &other_pk, &params),
+            sig.verify_with_params(msgs.as_slice(), &pk, &other_params),
+            derived.verify_with_params(&disclosed_msgs, &other_pk, &params),
+            sig.verify_with_params(msgs.as_slice(), &lazy_other, &params),
+            derived.verify_with_params(&disclosed_msgs, &lazy_other, &params),
+        ] {
+            match r {
+                Err(PSError::ParamsMismatch) => (),
+                r => panic!("Expected ParamsMismatch but got {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_rss_sign_other_count() {
+        let params = Params::new("test".as_bytes());
+        let (sk, _) = rsskeygen(3, &params).unwrap();
+        for count_msgs in &[2, 4] {
+            let msgs = FieldElementVector::random(*count_msgs);
+            match RSSignature::new(msgs.as_slice(), &sk, &params) {
+                Err(PSError::UnsupportedNoOfMessages { expected, given }) => {
+                    assert_eq!(expected, 3);
+                    assert_eq!(given, *count_msgs);
+                }
+                r => panic!("Expected UnsupportedNoOfMessages but got {:?}", r),
+            }
+        }
+        let msgs: Vec<FieldElement> = vec![];
+        assert!(RSSignature::new(&msgs, &sk, &params).is_err());
+    }
+
+    #[test]
+    fn test_rss_precheck() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(sig.precheck(msgs.as_slice(), &pk).unwrap());
+        assert!(sig.precheck(&msgs.as_slice()[1..], &pk).is_err());
+        let mut identity = sig.clone();
+        identity.sigma_1 = SignatureGroup::identity();
+        assert!(!identity.precheck(msgs.as_slice(), &pk).unwrap());
+
+        let disclosed: HashSet<usize> = vec![1, 2].into_iter().collect();
+        let derived = sig.derive(msgs.as_slice(), &disclosed, &pk).unwrap();
+        let mut disclosed_msgs: HashMap<usize, FieldElement> =
+            disclosed.iter().map(|i| (*i, msgs[*i].clone())).collect();
+        assert!(derived.precheck(&disclosed_msgs, &pk).unwrap());
+        let mut identity = derived.clone();
+        identity.sigma_2 = SignatureGroup::identity();
+        assert!(!identity.precheck(&disclosed_msgs, &pk).unwrap());
+        disclosed_msgs.insert(count_msgs, FieldElement::random());
+        assert!(derived.precheck(&disclosed_msgs, &pk).is_err());
+    }
+
+    #[test]
+    fn test_rss_derived_signatures_are_rerandomized() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let disclosed: HashSet<usize> = vec![2].into_iter().collect();
+        let d1 = sig.derive(msgs.as_slice(), &disclosed, &pk).unwrap();
+        let d2 = sig.derive(msgs.as_slice(), &disclosed, &pk).unwrap();
+        assert_ne!(d1.sigma_1, d2.sigma_1);
+        assert_ne!(d1.sigma_3, d2.sigma_3);
+    }
+
+    #[test]
+    fn test_rss_index_errors() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let disclosed: HashSet<usize> = vec![count_msgs].into_iter().collect();
+        assert!(sig.derive(msgs.as_slice(), &disclosed, &pk).is_err());
+        assert!(sig.verify(&msgs.as_slice()[1..], &pk).is_err());
+
+        // Y_{n+1} is never available
+        let idx = |i| MsgIndex::new(i).unwrap();
+        assert!(pk.Y(idx(count_msgs + 1)).is_err());
+        match pk.y(idx(skipped_index(count_msgs))) {
+            Err(PSError::ForbiddenIndex { idx }) => assert_eq!(idx, count_msgs + 1),
+            r => panic!("Expected ForbiddenIndex but got {:?}", r),
+        }
+        assert!(pk.Y(idx(2 * count_msgs + 1)).is_err());
+        assert_eq!(pk.y(idx(count_msgs + 2)).unwrap(), &pk.Y_k_nplus2_to_2n[0]);
+        assert!(pk.Y(idx(2 * count_msgs)).is_ok());
+        assert!(pk.Y_tilde(idx(count_msgs + 1)).is_err());
+        assert!(pk.Y_tilde(idx(count_msgs)).is_ok());
+    }
+
+    #[test]
+    fn test_msg_index() {
+        let n = 3;
+        assert!(MsgIndex::new(0).is_none());
+        assert!(MsgIndex::try_from(0).is_err());
+        let i = MsgIndex::from_zero_based(0).unwrap();
+        assert_eq!(i.get(), 1);
+        assert_eq!(i.zero_based(), 0);
+        assert_eq!(usize::from(i), 1);
+        assert!(MsgIndex::from_zero_based(usize::MAX).is_err());
+
+        assert!(MsgIndex::message(n, n).is_ok());
+        assert!(MsgIndex::message(0, n).is_err());
+        match MsgIndex::message(n + 1, n) {
+            Err(PSError::InvalidIndex { idx, .. }) => assert_eq!(idx, n + 1),
+            r => panic!("Expected InvalidIndex but got {:?}", r),
+        }
+        for i in (1..=n).chain(n + 2..=2 * n) {
+            assert_eq!(MsgIndex::key_element(i, n).unwrap().get(), i);
+        }
+        for i in &[0, n + 1, 2 * n + 1] {
+            assert!(MsgIndex::key_element(*i, n).is_err());
+        }
+        match MsgIndex::key_element(n + 1, n) {
+            Err(PSError::ForbiddenIndex { idx }) => assert_eq!(idx, n + 1),
+            r => panic!("Expected ForbiddenIndex but got {:?}", r),
+        }
+        assert_eq!(MsgIndex::key_element_offset(n, 1, 2).unwrap().get(), n - 1);
+        assert!(MsgIndex::key_element_offset(n, 0, n + 1).is_err());
+        assert!(MsgIndex::key_element_offset(n, usize::MAX, 0).is_err());
+    }
+
+    #[test]
+    fn test_bases_for() {
+        let params = Params::new("test".as_bytes());
+        let n = 5;
+        let (_, pk) = rsskeygen(n, &params).unwrap();
+        // Messages 1, 3 and 5, 0-based 0, 2 and 4, use Y_5, Y_3 and Y_1
+        let disclosed: DisclosureSet = vec![4, 0, 2].into_iter().collect();
+        let bases = pk.bases_for(&disclosed).unwrap();
+        assert_eq!(bases.len(), 3);
+        for (base, i) in bases.iter().zip(&[5, 3, 1]) {
+            assert_eq!(**base, pk.Y(MsgIndex::key_element(*i, n).unwrap()).unwrap());
+        }
+        assert!(pk.bases_for(&DisclosureSet::new()).unwrap().is_empty());
+        let out_of_range: DisclosureSet = vec![n].into_iter().collect();
+        assert!(pk.bases_for(&out_of_range).is_err());
+    }
+
+    #[test]
+    fn test_redact_many() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 6;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let disclosure_sets: Vec<DisclosureSet> = vec![
+            vec![],
+            vec![0],
+            vec![1, 3, 4],
+            vec![count_msgs - 1],
+            (0..count_msgs).collect(),
+        ]
+        .into_iter()
+        .map(|d| d.into_iter().collect())
+        .collect();
+
+        let derived = redact_many(&sig, msgs.as_slice(), &disclosure_sets, &pk).unwrap();
+        assert_eq!(derived.len(), disclosure_sets.len());
+        for (d, disclosed) in derived.iter().zip(disclosure_sets.iter()) {
+            let disclosed_msgs: HashMap<usize, FieldElement> =
+                disclosed.iter().map(|i| (*i, msgs[*i].clone())).collect();
+            assert!(d.verify(&disclosed_msgs, &pk).unwrap());
+
+            // The lazily loaded key gives the same result
+            let lazy =
+                crate::lazy_pkrss::LazyPKrss::new(std::io::Cursor::new(pk.to_bytes())).unwrap();
+            let from_lazy = redact_many(
+                &sig,
+                msgs.as_slice(),
+                std::slice::from_ref(disclosed),
+                &lazy,
+            )
+            .unwrap();
+            assert!(from_lazy[0].verify(&disclosed_msgs, &pk).unwrap());
+        }
+
+        let out_of_range: DisclosureSet = vec![count_msgs].into_iter().collect();
+        assert!(redact_many(&sig, msgs.as_slice(), &[out_of_range], &pk).is_err());
+    }
+
+    #[test]
+    fn timing_redact_many() {
+        // Run with `cargo test --release timing_redact_many -- --nocapture`
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 20;
+        let count_derivations = 20;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let disclosure_sets: Vec<DisclosureSet> = (0..count_derivations)
+            .map(|k| (0..count_msgs).filter(|i| (i + k) % 3 == 0).collect())
+            .collect();
+
+        let start = Instant::now();
+        for d in &disclosure_sets {
+            sig.derive(msgs.as_slice(), d, &pk).unwrap();
+        }
+        println!(
+            "Time to derive {} signatures over {} messages one by one {:?}",
+            count_derivations,
+            count_msgs,
+            start.elapsed()
+        );
+
+        let start = Instant::now();
+        redact_many(&sig, msgs.as_slice(), &disclosure_sets, &pk).unwrap();
+        println!(
+            "Time to derive {} signatures over {} messages with redact_many {:?}",
+            count_derivations,
+            count_msgs,
+            start.elapsed()
+        );
+    }
+
+    #[test]
+    fn test_verify_batch_parallel() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let prepared = PreparedPKrss::new(&pk).unwrap();
+        assert_eq!(prepared.msg_count(), count_msgs);
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let disclosure_sets: Vec<DisclosureSet> = vec![
+            HashSet::new(),
+            vec![0].into_iter().collect(),
+            vec![1, 3].into_iter().collect(),
+            (0..count_msgs).collect(),
+        ];
+        let derived = redact_many(&sig, msgs.as_slice(), &disclosure_sets, &pk).unwrap();
+        let mut disclosed_msgs: Vec<HashMap<usize, FieldElement>> = disclosure_sets
+            .iter()
+            .map(|d| d.iter().map(|i| (*i, msgs[*i].clone())).collect())
+            .collect();
+        for (d, m) in derived.iter().zip(&disclosed_msgs) {
+            assert!(d.verify_prepared(m, &prepared).unwrap());
+        }
+        // A wrong message in item 2 and an index out of range in item 3
+        disclosed_msgs[2].insert(1, FieldElement::random());
+        disclosed_msgs[3].insert(count_msgs, FieldElement::random());
+        let items: Vec<RedactedBatchItem> = derived
+            .iter()
+            .zip(&disclosed_msgs)
+            .map(|(sig, disclosed_msgs)| RedactedBatchItem {
+                sig,
+                disclosed_msgs,
+            })
+            .collect();
+        for threads in &[
+            ThreadBudget::Global,
+            ThreadBudget::Threads(NonZeroUsize::new(2).unwrap()),
+        ] {
+            let results = verify_batch_parallel(&items, &prepared, *threads).unwrap();
+            assert_eq!(results.len(), 4);
+            assert!(results[0].as_ref().unwrap());
+            assert!(results[1].as_ref().unwrap());
+            assert!(!results[2].as_ref().unwrap());
+            assert!(results[3].is_err());
+            for (item, r) in items.iter().zip(&results).take(3) {
+                assert_eq!(
+                    item.sig.verify(item.disclosed_msgs, &pk).unwrap(),
+                    *r.as_ref().unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_prepared_pk_deserialize_checks_tables() {
+        let params = Params::new("test".as_bytes());
+        let (_, pk) = rsskeygen(3, &params).unwrap();
+        let prepared = PreparedPKrss::new(&pk).unwrap();
+        let mut prepared_1 = prepared.clone();
+        prepared_1.n = 4;
+        let mut prepared_2 = prepared.clone();
+        prepared_2.Y_tables.pop();
+        let mut prepared_3 = prepared.clone();
+        prepared_3
+            .Y_tilde_tables
+            .push(FixedBaseTable::new(&VerkeyGroup::random()));
+        for (p, valid) in [
+            (prepared, true),
+            (prepared_1, false),
+            (prepared_2, false),
+            (prepared_3, false),
+        ]
+        .iter()
+        {
+            let json = serde_json::to_string(p).unwrap();
+            assert_eq!(serde_json::from_str::<PreparedPKrss>(&json).is_ok(), *valid);
+        }
+    }
+
+    #[test]
+    fn timing_verify_batch_parallel() {
+        // Run with `cargo test --release timing_verify_batch_parallel -- --nocapture`
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 10;
+        let count_items = 64;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let disclosure_sets: Vec<DisclosureSet> = (0..count_items)
+            .map(|k| (0..count_msgs).filter(|i| (i + k) % 2 == 0).collect())
+            .collect();
+        let derived = redact_many(&sig, msgs.as_slice(), &disclosure_sets, &pk).unwrap();
+        let disclosed_msgs: Vec<HashMap<usize, FieldElement>> = disclosure_sets
+            .iter()
+            .map(|d| d.iter().map(|i| (*i, msgs[*i].clone())).collect())
+            .collect();
+        let items: Vec<RedactedBatchItem> = derived
+            .iter()
+            .zip(&disclosed_msgs)
+            .map(|(sig, disclosed_msgs)| RedactedBatchItem {
+                sig,
+                disclosed_msgs,
+            })
+            .collect();
+
+        let start = Instant::now();
+        for item in &items {
+            assert!(item.sig.verify(item.disclosed_msgs, &pk).unwrap());
+        }
+        let sequential = start.elapsed();
+        let start = Instant::now();
+        let prepared = PreparedPKrss::new(&pk).unwrap();
+        println!("Time to prepare the key {:?}", start.elapsed());
+        for (threads, budget) in &[
+            ("the global pool", ThreadBudget::Global),
+            (
+                "1 thread",
+                ThreadBudget::Threads(NonZeroUsize::new(1).unwrap()),
+            ),
+        ] {
+            let start = Instant::now();
+            let results = verify_batch_parallel(&items, &prepared, *budget).unwrap();
+            let elapsed = start.elapsed();
+            assert!(results.iter().all(|r| *r.as_ref().unwrap()));
+            println!(
+                "Verified {} signatures over {} messages on {} in {:?}, {:.0} per second",
+                count_items,
+                count_msgs,
+                threads,
+                elapsed,
+                count_items as f64 / elapsed.as_secs_f64()
+            );
+        }
+        println!(
+            "Verified {} signatures sequentially with `verify` in {:?}, {:.0} per second",
+            count_items,
+            sequential,
+            count_items as f64 / sequential.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn test_rss_from_fuzzed_bytes() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let disclosed: HashSet<usize> = vec![1].into_iter().collect();
+        let derived = sig.derive(msgs.as_slice(), &disclosed, &pk).unwrap();
+        let bytes = derived.to_bytes();
+        assert_eq!(
+            RedactedSignature::from_bytes(&bytes).unwrap().to_bytes(),
+            bytes
+        );
+
+        let mut disclosed_msgs = HashMap::new();
+        disclosed_msgs.insert(1, msgs[1].clone());
+        for bytes in fuzzed_bytes(&bytes, 40) {
+            if let Ok(d) = RedactedSignature::from_bytes(&bytes) {
+                assert!(!d.verify(&disclosed_msgs, &pk).unwrap());
+            }
+        }
+        for bytes in fuzzed_bytes(&pk.to_bytes(), 20) {
+            // Any key decoded from modified bytes must be canonical
+            if let Ok(k) = PKrss::from_bytes(&bytes) {
+                assert_eq!(k.to_bytes(), bytes);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rss_subgroup_checks() {
+        let params = Params::new("test".as_bytes());
+        let (sk, pk) = rsskeygen(3, &params).unwrap();
+        let msgs = FieldElementVector::random(3);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let disclosed: HashSet<usize> = vec![1].into_iter().collect();
+        let mut bytes = sig
+            .derive(msgs.as_slice(), &disclosed, &pk)
+            .unwrap()
+            .to_bytes();
+
+        // Point on the curve but outside the prime order subgroup of G1, as in
+        // `signature::tests::test_group_elem_from_bytes_rejects_invalid`, in place of the element in G1
+        #[cfg(feature = "SignatureG2")]
+        let offset = 2 * SIGNATURE_GROUP_SIZE;
+        #[cfg(feature = "SignatureG1")]
+        let offset = 0;
+        let small_order = &mut bytes[offset..offset + crate::curve::GroupG1_SIZE];
+        small_order.iter_mut().for_each(|b| *b = 0);
+        small_order[0] = 2;
+        small_order[crate::curve::MODBYTES] = 5;
+        match RedactedSignature::from_bytes(&bytes) {
+            Err(PSError::InvalidGroupElement) => (),
+            r => panic!("Expected InvalidGroupElement but got {:?}", r),
+        }
+        let unchecked =
+            RedactedSignature::from_bytes_with_checks(&bytes, SubgroupChecks::Disabled).unwrap();
+        let mut disclosed_msgs = HashMap::new();
+        disclosed_msgs.insert(1, msgs[1].clone());
+        assert!(!unchecked.verify(&disclosed_msgs, &pk).unwrap());
+
+        // Deserialization with serde goes through the same checks
+        let elements = RedactedSignatureElements {
+            sigma_1: unchecked.sigma_1.clone(),
+            sigma_2: unchecked.sigma_2.clone(),
+            sigma_3: unchecked.sigma_3.clone(),
+            sigma_4: unchecked.sigma_4.clone(),
+        };
+        assert!(RedactedSignature::try_from(elements).is_err());
+        let elements = RSSignatureElements {
+            sigma_1: sig.sigma_1.clone(),
+            sigma_2: sig.sigma_2.clone(),
+        };
+        assert_eq!(
+            RSSignature::try_from(elements).unwrap().to_bytes(),
+            sig.to_bytes()
+        );
+        assert!(
+            RSSignature::from_bytes_with_checks(&sig.to_bytes(), SubgroupChecks::Enabled).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_rss_verify_key_structure() {
+        let params = Params::new("test".as_bytes());
+        let n = 4;
+        let (_, pk) = rsskeygen(n, &params).unwrap();
+        assert!(pk.verify_structure().unwrap().is_valid());
+
+        // Key of unrelated y_1..y_n with Y_k = Y_n^{y_{k-n}}, which the pairings of `verify_key_detailed` accept
+        let y: Vec<FieldElement> = (0..n).map(|_| FieldElement::random()).collect();
+        let mut crafted = pk.clone();
+        crafted.Y_j_1_to_n = y.iter().map(|y_i| &params.g * y_i).collect();
+        crafted.Y_tilde_i = y.iter().map(|y_i| &params.g_tilde * y_i).collect();
+        crafted.Y_k_nplus2_to_2n = (2..=n)
+            .map(|j| &params.g * &(&y[n - 1] * &y[j - 1]))
+            .collect();
+        assert!(verify_key_detailed(&crafted).unwrap().is_valid());
+        let failure = crafted.verify_structure().unwrap().failure.unwrap();
+        assert_eq!(failure.check, Check::KeyPowers);
+        assert_eq!(failure.index, Some(2));
+
+        // Inconsistencies found by `verify_key_detailed` are reported as well
+        let mut bad_pk = pk;
+        bad_pk.Y_k_nplus2_to_2n[0] = SignatureGroup::random();
+        let failure = bad_pk.verify_structure().unwrap().failure.unwrap();
+        assert_eq!(failure.check, Check::KeyElement);
+        assert_eq!(failure.index, Some(n + 2));
+    }
+
+    #[test]
+    fn test_rss_verify_detailed() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+        assert!(verify_key_detailed(&pk).unwrap().is_valid());
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(sig
+            .verify_detailed(msgs.as_slice(), &pk)
+            .unwrap()
+            .is_valid());
+
+        let disclosed: HashSet<usize> = vec![0, 2].into_iter().collect();
+        let derived = sig.derive(msgs.as_slice(), &disclosed, &pk).unwrap();
+        let mut disclosed_msgs = HashMap::new();
+        for i in &disclosed {
+            disclosed_msgs.insert(*i, msgs[*i].clone());
+        }
+        assert!(derived
+            .verify_detailed(&disclosed_msgs, &pk)
+            .unwrap()
+            .is_valid());
+
+        let mut wrong = disclosed_msgs.clone();
+        wrong.insert(2, FieldElement::random());
+        let failure = derived
+            .verify_detailed(&wrong, &pk)
+            .unwrap()
+            .failure
+            .unwrap();
+        assert_eq!(failure.check, Check::SignaturePairing);
+        assert_eq!(failure.index, None);
+
+        // Derived with a corrupted Y_{n+3}, the offending element is reported
+        let mut bad_pk = pk.clone();
+        bad_pk.Y_k_nplus2_to_2n[1] = SignatureGroup::random();
+        let failure = verify_key_detailed(&bad_pk).unwrap().failure.unwrap();
+        assert_eq!(failure.check, Check::KeyElement);
+        assert_eq!(failure.index, Some(count_msgs + 3));
+        let derived = sig.derive(msgs.as_slice(), &disclosed, &bad_pk).unwrap();
+        assert!(!derived.verify(&disclosed_msgs, &pk).unwrap());
+        let failure = derived
+            .verify_detailed(&disclosed_msgs, &bad_pk)
+            .unwrap()
+            .failure
+            .unwrap();
+        assert_eq!(failure.check, Check::KeyElement);
+        assert_eq!(failure.index, Some(count_msgs + 3));
+        let failure = derived
+            .verify_detailed(&disclosed_msgs, &pk)
+            .unwrap()
+            .failure
+            .unwrap();
+        assert_eq!(failure.check, Check::RedactionPairing);
+
+        // Corrupted Y_tilde_2 is reported when verifying the full signature
+        let mut bad_pk = pk;
+        bad_pk.Y_tilde_i[1] = VerkeyGroup::random();
+        let failure = sig
+            .verify_detailed(msgs.as_slice(), &bad_pk)
+            .unwrap()
+            .failure
+            .unwrap();
+        assert_eq!(failure.check, Check::KeyElement);
+        assert_eq!(failure.index, Some(2));
+    }
+}
@@ -0,0 +1,155 @@
+// Upgrade of credentials of the 2016 scheme to redactable signatures, for issuers migrating to redactable
+// credentials. The issuer checks the holder's `Signature` on the messages with its verkey and signs the same message
+// vector with its key of the redactable scheme. With the new signature it gives an `UpgradeLink`, a proof of
+// knowledge of x of the redactable key, the discrete log of X_tilde in base g_tilde, whose challenge hashes the old
+// signature, its verkey, the new signature and the redactable public key. Anyone holding both signatures checks
+// with it that the holder of the redactable key issued the new signature as the upgrade of the old one, so a holder
+// keeps the continuity of its credential, e.g. with a verifier which knew the old one.
+
+use crate::challenge_domain::RSS_UPGRADE;
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{PKrss, Params, SKrss, Verkey};
+use crate::pok_sig::{ProofVerkeyGroup, ProverCommittingVerkeyGroup};
+use crate::rss_signature::RSSignature;
+use crate::signature::Signature;
+
+/// Redactable signature on the messages of an upgraded signature with the link to it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UpgradedCredential {
+    pub signature: RSSignature,
+    pub link: UpgradeLink,
+}
+
+/// Proof of knowledge of x of the redactable key bound to the old and new signatures
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UpgradeLink {
+    pub proof: ProofVerkeyGroup,
+}
+
+fn link_challenge(
+    ps_sig: &Signature,
+    ps_vk: &Verkey,
+    rss_sig: &RSSignature,
+    pk_rss: &PKrss,
+    committed: &[u8],
+) -> FieldElement {
+    let mut bytes = RSS_UPGRADE.tag();
+    bytes.append(&mut ps_sig.to_bytes());
+    bytes.append(&mut ps_vk.to_bytes());
+    bytes.append(&mut rss_sig.to_bytes());
+    bytes.append(&mut pk_rss.to_bytes());
+    bytes.extend_from_slice(committed);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// Sign `messages`, signed by `ps_sig` under `ps_vk`, with `sk_rss` and link the new signature to `ps_sig`. Fails if
+/// `ps_sig` does not verify or if `sk_rss` is not the key of `pk_rss`.
+pub fn upgrade_to_rss(
+    ps_sig: &Signature,
+    messages: &[FieldElement],
+    ps_vk: &Verkey,
+    sk_rss: &SKrss,
+    pk_rss: &PKrss,
+    params: &Params,
+) -> Result<UpgradedCredential, PSError> {
+    if !ps_sig.verify(messages.to_vec(), ps_vk, params)? {
+        return Err(PSError::GeneralError {
+            msg: String::from("Signature to upgrade does not verify"),
+        });
+    }
+    let signature = RSSignature::new(messages, sk_rss, params)?;
+    if !signature.verify(messages, pk_rss)? {
+        return Err(PSError::GeneralError {
+            msg: String::from("Redactable key is not the key of the public key"),
+        });
+    }
+    let mut committing = ProverCommittingVerkeyGroup::new();
+    committing.commit(&pk_rss.g_tilde, None);
+    let committed = committing.finish();
+    let challenge = link_challenge(ps_sig, ps_vk, &signature, pk_rss, &committed.to_bytes());
+    let proof = committed.gen_proof(&challenge, std::slice::from_ref(&sk_rss.x))?;
+    Ok(UpgradedCredential {
+        signature,
+        link: UpgradeLink { proof },
+    })
+}
+
+impl UpgradeLink {
+    /// Whether `rss_sig` was issued with the key of `pk_rss` as the upgrade of `ps_sig` of `ps_vk`. Does not verify
+    /// the signatures.
+    pub fn verify(
+        &self,
+        ps_sig: &Signature,
+        ps_vk: &Verkey,
+        rss_sig: &RSSignature,
+        pk_rss: &PKrss,
+    ) -> Result<bool, PSError> {
+        // Same bytes as of the `ProverCommittedVerkeyGroup` of `upgrade_to_rss`, g_tilde || commitment
+        let mut committed = pk_rss.g_tilde.to_bytes();
+        committed.append(&mut self.proof.commitment.to_bytes());
+        let challenge = link_challenge(ps_sig, ps_vk, rss_sig, pk_rss, &committed);
+        self.proof.verify(
+            std::slice::from_ref(&pk_rss.g_tilde),
+            &pk_rss.X_tilde,
+            &challenge,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::{keygen, rsskeygen};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_upgrade_to_rss() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let (sk_rss, pk_rss) = rsskeygen(count_msgs, &params).unwrap();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        let ps_sig = Signature::new(&msgs, &sk, &params).unwrap();
+
+        let upgraded = upgrade_to_rss(&ps_sig, &msgs, &vk, &sk_rss, &pk_rss, &params).unwrap();
+        assert!(upgraded.signature.verify(&msgs, &pk_rss).unwrap());
+        assert!(upgraded
+            .link
+            .verify(&ps_sig, &vk, &upgraded.signature, &pk_rss)
+            .unwrap());
+        // The upgraded signature is redactable
+        let disclosed: HashSet<usize> = vec![0, 2].into_iter().collect();
+        let derived = upgraded
+            .signature
+            .derive(&msgs, &disclosed, &pk_rss)
+            .unwrap();
+        let disclosed_msgs = vec![(0, msgs[0].clone()), (2, msgs[2].clone())]
+            .into_iter()
+            .collect();
+        assert!(derived.verify(&disclosed_msgs, &pk_rss).unwrap());
+
+        // The link is to these signatures and keys only
+        let other_sig = Signature::new(&msgs, &sk, &params).unwrap();
+        assert!(!upgraded
+            .link
+            .verify(&other_sig, &vk, &upgraded.signature, &pk_rss)
+            .unwrap());
+        let other_rss_sig = RSSignature::new(&msgs, &sk_rss, &params).unwrap();
+        assert!(!upgraded
+            .link
+            .verify(&ps_sig, &vk, &other_rss_sig, &pk_rss)
+            .unwrap());
+        let (other_sk_rss, other_pk_rss) = rsskeygen(count_msgs, &params).unwrap();
+        assert!(!upgraded
+            .link
+            .verify(&ps_sig, &vk, &upgraded.signature, &other_pk_rss)
+            .unwrap());
+
+        // Messages not of the signature or a redactable key not of the public key
+        let other_msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        assert!(upgrade_to_rss(&ps_sig, &other_msgs, &vk, &sk_rss, &pk_rss, &params).is_err());
+        assert!(upgrade_to_rss(&ps_sig, &msgs, &vk, &other_sk_rss, &pk_rss, &params).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+// Salted attribute values, for low-entropy attributes such as a birth year. With the encoding of `typed_message`, a
+// verifier can guess a hidden attribute of few possible values by trying each against the elements of a proof,
+// e.g. the revealed messages of another presentation or a commitment of the attribute. A salted value is signed as
+// m_i = H(domain || salt_i || encoding of the value) with a random salt per attribute, so the message tells nothing
+// of the value without the salt. The salts are kept in the `SaltedCredential` and, when an attribute is revealed,
+// the holder sends its `SaltedValue` with the proof. The verifier checks that the revealed message is the hash of the
+// value and salt with `check_disclosed` and reads the value.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::issuer::{Issuer, Schema};
+use crate::keys::Params;
+use crate::proof_request::Credential;
+use crate::typed_message::TypedMessage;
+use rand::{thread_rng, RngCore};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+pub const SALT_SIZE: usize = 32;
+const SALTED_MESSAGE_DOMAIN: &[u8] = b"PS salted message : ";
+
+pub type Salt = [u8; SALT_SIZE];
+
+/// Value of an attribute with its salt, disclosed by the holder for a revealed attribute
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaltedValue {
+    pub value: TypedMessage,
+    pub salt: Salt,
+}
+
+/// Credential of salted attribute values, followed by the id of the schema as in `Schema::messages`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaltedCredential {
+    pub credential: Credential,
+    pub values: Vec<SaltedValue>,
+}
+
+pub fn random_salt() -> Salt {
+    let mut salt = [0u8; SALT_SIZE];
+    thread_rng().fill_bytes(&mut salt);
+    salt
+}
+
+impl SaltedValue {
+    /// `value` with a random salt
+    pub fn new(value: TypedMessage) -> Self {
+        Self {
+            value,
+            salt: random_salt(),
+        }
+    }
+
+    /// Message signed for the value
+    pub fn message(&self) -> FieldElement {
+        let mut bytes = SALTED_MESSAGE_DOMAIN.to_vec();
+        bytes.extend_from_slice(&self.salt);
+        bytes.append(&mut self.value.encode().to_bytes());
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+impl Schema {
+    /// Same as `messages` for salted values
+    pub fn salted_messages(&self, values: &[SaltedValue]) -> Result<Vec<FieldElement>, PSError> {
+        let values: Vec<FieldElement> = values.iter().map(SaltedValue::message).collect();
+        self.messages(&values)
+    }
+}
+
+impl Issuer {
+    /// Sign a credential of `schema` with attribute values `values`, each with a random salt
+    pub fn sign_salted(
+        &mut self,
+        schema: &Schema,
+        values: &[TypedMessage],
+        params: &Params,
+    ) -> Result<SaltedCredential, PSError> {
+        let values: Vec<SaltedValue> = values.iter().cloned().map(SaltedValue::new).collect();
+        let messages = schema.salted_messages(&values)?;
+        let signature = self.sign(schema, &messages[..values.len()], params)?;
+        Ok(SaltedCredential {
+            credential: Credential {
+                signature,
+                messages,
+                verkey: self.verkey_for(schema)?,
+            },
+            values,
+        })
+    }
+}
+
+impl SaltedCredential {
+    /// Salted values of the attributes at `indices`, to send with a proof revealing them
+    pub fn disclose(
+        &self,
+        indices: &HashSet<usize>,
+    ) -> Result<BTreeMap<usize, SaltedValue>, PSError> {
+        indices
+            .iter()
+            .map(|i| match self.values.get(*i) {
+                Some(v) => Ok((*i, v.clone())),
+                None => Err(PSError::InvalidIndex {
+                    idx: *i,
+                    valid: format!("[0, {})", self.values.len()),
+                }),
+            })
+            .collect()
+    }
+}
+
+/// Whether the revealed attribute messages `revealed_msgs` of a proof are exactly the messages of the salted values
+/// `disclosed`. Revealed messages that are not attributes, such as the schema id, are not in `revealed_msgs`.
+pub fn check_disclosed(
+    revealed_msgs: &HashMap<usize, FieldElement>,
+    disclosed: &BTreeMap<usize, SaltedValue>,
+) -> bool {
+    revealed_msgs.len() == disclosed.len()
+        && disclosed
+            .iter()
+            .all(|(i, v)| revealed_msgs.get(i) == Some(&v.message()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer::CapacityPolicy;
+    use crate::keys::keygen;
+    use crate::pok_sig::PoKOfSignature;
+
+    #[test]
+    fn test_salted_messages() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let mut issuer = Issuer::new(sk, vk, CapacityPolicy::Reject);
+        let schema = Schema::new("person", &["birth year", "name"]);
+        let values = vec![
+            TypedMessage::Int(1990),
+            TypedMessage::Text(String::from("Alice")),
+        ];
+        let cred = issuer.sign_salted(&schema, &values, &params).unwrap();
+        assert!(cred
+            .credential
+            .signature
+            .verify(
+                cred.credential.messages.clone(),
+                &cred.credential.verkey,
+                &params
+            )
+            .unwrap());
+        assert_ne!(cred.values[0].salt, cred.values[1].salt);
+        assert!(issuer.sign_salted(&schema, &values[..1], &params).is_err());
+
+        // The message of the birth year is none of the unsalted encodings of the years
+        let revealed: HashSet<usize> = vec![0].into_iter().collect();
+        let pok = PoKOfSignature::init(
+            &cred.credential.signature,
+            &cred.credential.verkey,
+            &params,
+            cred.credential.messages.clone(),
+            None,
+            revealed.clone(),
+        )
+        .unwrap();
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&challenge).unwrap();
+        let revealed_msgs: HashMap<usize, FieldElement> =
+            vec![(0, cred.credential.messages[0].clone())]
+                .into_iter()
+                .collect();
+        assert!(proof
+            .verify(
+                &cred.credential.verkey,
+                &params,
+                revealed_msgs.clone(),
+                &challenge
+            )
+            .unwrap());
+        assert!((1900..2030).all(|y| TypedMessage::Int(y).encode() != revealed_msgs[&0]));
+
+        // The verifier reads the value from the disclosed salt and value
+        let disclosed = cred.disclose(&revealed).unwrap();
+        assert!(check_disclosed(&revealed_msgs, &disclosed));
+        assert_eq!(disclosed[&0].value, TypedMessage::Int(1990));
+        let mut other_value = disclosed.clone();
+        other_value.get_mut(&0).unwrap().value = TypedMessage::Int(1991);
+        assert!(!check_disclosed(&revealed_msgs, &other_value));
+        let mut other_salt = disclosed.clone();
+        other_salt.get_mut(&0).unwrap().salt = random_salt();
+        assert!(!check_disclosed(&revealed_msgs, &other_salt));
+        let all: HashSet<usize> = vec![0, 1].into_iter().collect();
+        assert!(!check_disclosed(
+            &revealed_msgs,
+            &cred.disclose(&all).unwrap()
+        ));
+        assert!(cred.disclose(&vec![2].into_iter().collect()).is_err());
+    }
+}
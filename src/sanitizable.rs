@@ -0,0 +1,366 @@
+// Sanitizable regions of a redactable signature: messages at indices chosen by the signer can be modified, not only
+// redacted, by a designated sanitizer with a key of its own, e.g. an authorized editor of a document. The signer
+// names the sanitizable indices and the verkey of the sanitizer, a key for 3 messages of `keys::keygen`, in a
+// `SanitizerPolicy` and signs the messages followed by a random document id and the policy message, the hash of the
+// policy, with a key for 2 messages more than the document. To modify blocks, the sanitizer derives a signature
+// redacting them and signs each new value with its key over the document id, the index as `TypedMessage::Int` and
+// the value. A verifier of the `SanitizedDocument` checks that every modified block is sanitizable and signed by
+// the sanitizer of the policy for this document and index, and that the derived signature verifies on the other
+// blocks, the document id and the policy message, and learns for each block whether it is the signer's original
+// or the sanitizer's.
+// The original value of a modified block is hidden. The sanitizer can make any number of versions of a document
+// and a verifier cannot tell which one is the latest. Anyone with the `SanitizableSignature` and the messages can
+// present the document unmodified.
+// Policy message: the hash of "PS sanitizable policy : " || the sanitizable indices in increasing order, each in 8
+// bytes big endian || the verkey of the sanitizer as `Verkey::to_bytes`.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, SKrss, Sigkey, Verkey};
+use crate::rss_signature::{RSSPublicKey, RSSignature, RedactedSignature};
+use crate::signature::Signature;
+use crate::typed_message::TypedMessage;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+const SANITIZER_POLICY_DOMAIN: &[u8] = b"PS sanitizable policy : ";
+/// Messages signed by the sanitizer for a block: the document id, the index and the value
+pub const SANITIZER_KEY_MESSAGES: usize = 3;
+
+/// Indices the sanitizer may modify and its verkey
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SanitizerPolicy {
+    pub sanitizable: BTreeSet<usize>,
+    pub sanitizer: Verkey,
+}
+
+/// Signature of the signer of a document with sanitizable blocks
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SanitizableSignature {
+    pub sig: RSSignature,
+    /// Random message signed after the blocks, binding the signatures of the sanitizer to this document
+    pub doc_id: FieldElement,
+    pub policy: SanitizerPolicy,
+}
+
+/// Who a block of a `SanitizedDocument` comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockOrigin {
+    /// The block is the one signed by the signer
+    Signer,
+    /// The block was modified by the sanitizer
+    Sanitizer,
+}
+
+/// Document presented to a verifier, with its blocks as modified by the sanitizer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SanitizedDocument {
+    /// Signature disclosing the blocks of the signer, the document id and the policy message
+    pub sig: RedactedSignature,
+    pub doc_id: FieldElement,
+    pub policy: SanitizerPolicy,
+    pub blocks: Vec<FieldElement>,
+    /// Signatures of the sanitizer of the modified blocks by index
+    pub sanitizer_sigs: BTreeMap<usize, Signature>,
+}
+
+impl SanitizerPolicy {
+    /// Policy letting the sanitizer of `sanitizer`, a verkey for `SANITIZER_KEY_MESSAGES` messages, modify the
+    /// messages at `indices`
+    pub fn new<I: IntoIterator<Item = usize>>(
+        indices: I,
+        sanitizer: &Verkey,
+    ) -> Result<Self, PSError> {
+        if sanitizer.supported_messages() != SANITIZER_KEY_MESSAGES {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: SANITIZER_KEY_MESSAGES,
+                given: sanitizer.supported_messages(),
+            });
+        }
+        Ok(Self {
+            sanitizable: indices.into_iter().collect(),
+            sanitizer: sanitizer.clone(),
+        })
+    }
+
+    /// Message signed after the document id
+    pub fn policy_message(&self) -> FieldElement {
+        let mut bytes = SANITIZER_POLICY_DOMAIN.to_vec();
+        for i in &self.sanitizable {
+            bytes.extend_from_slice(&(*i as u64).to_be_bytes());
+        }
+        bytes.append(&mut self.sanitizer.to_bytes());
+        FieldElement::from_msg_hash(&bytes)
+    }
+
+    /// Fails with `PSError::NotSanitizable` unless the message at `idx` is sanitizable
+    fn check_sanitizable(&self, idx: usize) -> Result<(), PSError> {
+        if !self.sanitizable.contains(&idx) {
+            return Err(PSError::NotSanitizable { idx });
+        }
+        Ok(())
+    }
+}
+
+/// Messages the sanitizer signs for the block at `idx` of value `value`
+fn block_messages(doc_id: &FieldElement, idx: usize, value: &FieldElement) -> Vec<FieldElement> {
+    vec![
+        doc_id.clone(),
+        TypedMessage::Int(idx as u64).encode(),
+        value.clone(),
+    ]
+}
+
+impl SanitizableSignature {
+    /// Sign `messages` with the sanitizable blocks of `policy`. `sk` must be for `messages.len() + 2` messages.
+    pub fn new(
+        messages: &[FieldElement],
+        policy: SanitizerPolicy,
+        sk: &SKrss,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        if messages.len() + 2 != sk.n {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: sk.n.saturating_sub(2),
+                given: messages.len(),
+            });
+        }
+        if let Some(idx) = policy.sanitizable.iter().find(|i| **i >= messages.len()) {
+            return Err(PSError::InvalidIndex {
+                idx: *idx,
+                valid: format!("[0, {})", messages.len()),
+            });
+        }
+        let doc_id = FieldElement::random();
+        let sig = RSSignature::new(&with_trailer(messages, &doc_id, &policy), sk, params)?;
+        Ok(Self {
+            sig,
+            doc_id,
+            policy,
+        })
+    }
+
+    /// The document of `messages` without modifications, all blocks being the signer's
+    pub fn present<K: RSSPublicKey>(
+        &self,
+        messages: &[FieldElement],
+        pk: &K,
+    ) -> Result<SanitizedDocument, PSError> {
+        self.sanitized(messages, BTreeMap::new(), pk)
+    }
+
+    /// The document of `messages` with the blocks of `modifications` replaced by their new values, signed by the
+    /// sanitizer with `sanitizer_sk`, the signing key of the verkey of the policy. Fails with
+    /// `PSError::NotSanitizable` for a block the policy does not let the sanitizer modify.
+    pub fn sanitize<K: RSSPublicKey>(
+        &self,
+        messages: &[FieldElement],
+        modifications: &BTreeMap<usize, FieldElement>,
+        sanitizer_sk: &Sigkey,
+        pk: &K,
+        params: &Params,
+    ) -> Result<SanitizedDocument, PSError> {
+        let mut sanitizer_sigs = BTreeMap::new();
+        for (idx, value) in modifications {
+            self.policy.check_sanitizable(*idx)?;
+            let msgs = block_messages(&self.doc_id, *idx, value);
+            sanitizer_sigs.insert(*idx, Signature::new(&msgs, sanitizer_sk, params)?);
+        }
+        let mut doc = self.sanitized(messages, sanitizer_sigs, pk)?;
+        for (idx, value) in modifications {
+            doc.blocks[*idx] = value.clone();
+        }
+        Ok(doc)
+    }
+
+    fn sanitized<K: RSSPublicKey>(
+        &self,
+        messages: &[FieldElement],
+        sanitizer_sigs: BTreeMap<usize, Signature>,
+        pk: &K,
+    ) -> Result<SanitizedDocument, PSError> {
+        let n = messages.len();
+        let disclosed: HashSet<usize> = (0..n + 2)
+            .filter(|i| !sanitizer_sigs.contains_key(i))
+            .collect();
+        let sig = self.sig.derive(
+            &with_trailer(messages, &self.doc_id, &self.policy),
+            &disclosed,
+            pk,
+        )?;
+        Ok(SanitizedDocument {
+            sig,
+            doc_id: self.doc_id.clone(),
+            policy: self.policy.clone(),
+            blocks: messages.to_vec(),
+            sanitizer_sigs,
+        })
+    }
+}
+
+/// `messages` followed by the document id and the policy message
+fn with_trailer(
+    messages: &[FieldElement],
+    doc_id: &FieldElement,
+    policy: &SanitizerPolicy,
+) -> Vec<FieldElement> {
+    let mut all = messages.to_vec();
+    all.push(doc_id.clone());
+    all.push(policy.policy_message());
+    all
+}
+
+impl SanitizedDocument {
+    /// Verify the document with `pk`, the key of the signer, returning the origin of each block. Fails with
+    /// `PSError::NotSanitizable` for a modified block the policy does not let the sanitizer modify, with
+    /// `PSError::InvalidSanitizerSignature` if the signature of a modified block does not verify with the verkey of
+    /// the sanitizer and with `PSError::InvalidSanitizedDocument` if the signature of the signer does not verify.
+    pub fn verify<K: RSSPublicKey>(
+        &self,
+        pk: &K,
+        params: &Params,
+    ) -> Result<Vec<BlockOrigin>, PSError> {
+        let n = self.blocks.len();
+        if pk.msg_count() != n + 2 {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: pk.msg_count().saturating_sub(2),
+                given: n,
+            });
+        }
+        for (idx, sig) in &self.sanitizer_sigs {
+            self.policy.check_sanitizable(*idx)?;
+            if *idx >= n {
+                return Err(PSError::InvalidIndex {
+                    idx: *idx,
+                    valid: format!("[0, {})", n),
+                });
+            }
+            let msgs = block_messages(&self.doc_id, *idx, &self.blocks[*idx]);
+            if !sig.verify(msgs, &self.policy.sanitizer, params)? {
+                return Err(PSError::InvalidSanitizerSignature { idx: *idx });
+            }
+        }
+        let mut disclosed_msgs: HashMap<usize, FieldElement> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.sanitizer_sigs.contains_key(i))
+            .map(|(i, m)| (i, m.clone()))
+            .collect();
+        disclosed_msgs.insert(n, self.doc_id.clone());
+        disclosed_msgs.insert(n + 1, self.policy.policy_message());
+        if !self.sig.verify(&disclosed_msgs, pk)? {
+            return Err(PSError::InvalidSanitizedDocument);
+        }
+        Ok((0..n)
+            .map(|i| {
+                if self.sanitizer_sigs.contains_key(&i) {
+                    BlockOrigin::Sanitizer
+                } else {
+                    BlockOrigin::Signer
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::{keygen, rsskeygen};
+
+    #[test]
+    fn test_sanitizable_blocks() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let (sk, pk) = rsskeygen(count_msgs + 2, &params).unwrap();
+        let (sanitizer_sk, sanitizer_vk) = keygen(SANITIZER_KEY_MESSAGES, &params).unwrap();
+        let (other_sk, other_vk) = keygen(SANITIZER_KEY_MESSAGES, &params).unwrap();
+        assert!(SanitizerPolicy::new(vec![1], &keygen(2, &params).unwrap().1).is_err());
+        let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        let policy = SanitizerPolicy::new(vec![1, 3], &sanitizer_vk).unwrap();
+        let signed = SanitizableSignature::new(&msgs, policy.clone(), &sk, &params).unwrap();
+        assert!(SanitizableSignature::new(&msgs[..3], policy, &sk, &params).is_err());
+
+        let doc = signed.present(&msgs, &pk).unwrap();
+        assert_eq!(
+            doc.verify(&pk, &params).unwrap(),
+            vec![BlockOrigin::Signer; count_msgs]
+        );
+
+        let new_value = FieldElement::random();
+        let modifications: BTreeMap<usize, FieldElement> =
+            vec![(3, new_value.clone())].into_iter().collect();
+        let doc = signed
+            .sanitize(&msgs, &modifications, &sanitizer_sk, &pk, &params)
+            .unwrap();
+        assert_eq!(doc.blocks[3], new_value);
+        assert_eq!(
+            doc.verify(&pk, &params).unwrap(),
+            vec![
+                BlockOrigin::Signer,
+                BlockOrigin::Signer,
+                BlockOrigin::Signer,
+                BlockOrigin::Sanitizer
+            ]
+        );
+
+        // Blocks outside the policy cannot be modified
+        let outside: BTreeMap<usize, FieldElement> =
+            vec![(2, FieldElement::random())].into_iter().collect();
+        match signed.sanitize(&msgs, &outside, &sanitizer_sk, &pk, &params) {
+            Err(PSError::NotSanitizable { idx: 2 }) => (),
+            r => panic!("Expected NotSanitizable but got {:?}", r),
+        }
+        // The value of a modified block is bound to the signature of the sanitizer
+        let mut tampered = doc.clone();
+        tampered.blocks[3] = FieldElement::random();
+        match tampered.verify(&pk, &params) {
+            Err(PSError::InvalidSanitizerSignature { idx: 3 }) => (),
+            r => panic!("Expected InvalidSanitizerSignature but got {:?}", r),
+        }
+        // and a block of the signer to its signature
+        let mut tampered = doc.clone();
+        tampered.blocks[0] = FieldElement::random();
+        match tampered.verify(&pk, &params) {
+            Err(PSError::InvalidSanitizedDocument) => (),
+            r => panic!("Expected InvalidSanitizedDocument but got {:?}", r),
+        }
+        // Another sanitizer is not the designated one, either with its own key or substituted in the policy
+        assert!(signed
+            .sanitize(&msgs, &modifications, &other_sk, &pk, &params)
+            .unwrap()
+            .verify(&pk, &params)
+            .is_err());
+        let mut tampered = signed.clone();
+        tampered.policy.sanitizer = other_vk;
+        let tampered_doc = tampered
+            .sanitize(&msgs, &modifications, &other_sk, &pk, &params)
+            .unwrap();
+        assert!(tampered_doc.verify(&pk, &params).is_err());
+        // Blocks signed by the sanitizer for another document are not accepted
+        let other_signed = SanitizableSignature::new(
+            &msgs,
+            SanitizerPolicy::new(vec![1, 3], &sanitizer_vk).unwrap(),
+            &sk,
+            &params,
+        )
+        .unwrap();
+        let mut replayed = other_signed
+            .sanitize(&msgs, &modifications, &sanitizer_sk, &pk, &params)
+            .unwrap();
+        replayed.sanitizer_sigs = doc.sanitizer_sigs.clone();
+        match replayed.verify(&pk, &params) {
+            Err(PSError::InvalidSanitizerSignature { idx: 3 }) => (),
+            r => panic!("Expected InvalidSanitizerSignature but got {:?}", r),
+        }
+        // The derived signature must hide the modified blocks
+        let mut unhidden = signed.present(&msgs, &pk).unwrap();
+        unhidden.sanitizer_sigs = doc.sanitizer_sigs.clone();
+        unhidden.blocks[3] = new_value;
+        match unhidden.verify(&pk, &params) {
+            Err(PSError::InvalidSanitizedDocument) => (),
+            r => panic!("Expected InvalidSanitizedDocument but got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// Roles of scalars. Messages, blindings and key scalars are all field elements, so a blinding can be passed where a
+// message is expected and the compiler cannot tell. `Message`, `Blinding` and `SecretScalar` wrap a field element
+// for each role with explicit conversions: `new` or `random` to wrap one and `as_field_element` or
+// `into_field_element` to unwrap it.
+// Signing, blind signing, proofs of knowledge and signing, verifying and deriving redactable signatures take messages
+// as any `MessageScalar`, `Message` or a bare `FieldElement` for code not using the newtypes, so a `Blinding` or
+// `SecretScalar` is rejected as a message. Blindings
+// of proofs of knowledge and of the commitments of blind issuance are `Blinding`s, and `BlindSignature::unblind` and
+// `LinkSecret::unblind` take any `BlindingScalar`. Key scalars are `SecretScalar`s in `Sigkey::from_scalars` and
+// `SKrss::from_scalars`. `SecretScalar` does not print its value with `Debug`.
+
+use crate::curve::FieldElement;
+use std::borrow::Cow;
+use std::fmt;
+
+/// Message to sign or prove knowledge of
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Message(FieldElement);
+
+/// Blinding of a commitment or of a hidden message in a proof of knowledge
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Blinding(FieldElement);
+
+/// Scalar of a secret key
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretScalar(FieldElement);
+
+/// Scalars accepted as messages
+pub trait MessageScalar: Sized {
+    fn as_message(&self) -> &FieldElement;
+
+    /// Field elements of `messages`, borrowed when they are field elements already
+    fn field_elements(messages: &[Self]) -> Cow<'_, [FieldElement]> {
+        Cow::Owned(messages.iter().map(|m| m.as_message().clone()).collect())
+    }
+
+    fn into_field_elements(messages: Vec<Self>) -> Vec<FieldElement> {
+        messages.iter().map(|m| m.as_message().clone()).collect()
+    }
+}
+
+/// Scalars accepted as blindings
+pub trait BlindingScalar {
+    fn as_blinding(&self) -> &FieldElement;
+}
+
+macro_rules! impl_scalar_newtype {
+    ( $name:ident ) => {
+        impl $name {
+            pub fn new(elem: FieldElement) -> Self {
+                Self(elem)
+            }
+
+            pub fn random() -> Self {
+                Self(FieldElement::random())
+            }
+
+            pub fn as_field_element(&self) -> &FieldElement {
+                &self.0
+            }
+
+            pub fn into_field_element(self) -> FieldElement {
+                self.0
+            }
+        }
+    };
+}
+
+impl_scalar_newtype!(Message);
+impl_scalar_newtype!(Blinding);
+impl_scalar_newtype!(SecretScalar);
+
+impl Message {
+    /// Message of the hash of `bytes`
+    pub fn from_msg_hash(bytes: &[u8]) -> Self {
+        Self(FieldElement::from_msg_hash(bytes))
+    }
+
+    /// Messages of field elements, in the same order
+    pub fn all(elems: Vec<FieldElement>) -> Vec<Self> {
+        elems.into_iter().map(Self).collect()
+    }
+}
+
+impl fmt::Debug for SecretScalar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretScalar(..)")
+    }
+}
+
+impl MessageScalar for FieldElement {
+    fn as_message(&self) -> &FieldElement {
+        self
+    }
+
+    fn field_elements(messages: &[Self]) -> Cow<'_, [FieldElement]> {
+        Cow::Borrowed(messages)
+    }
+
+    fn into_field_elements(messages: Vec<Self>) -> Vec<FieldElement> {
+        messages
+    }
+}
+
+impl MessageScalar for Message {
+    fn as_message(&self) -> &FieldElement {
+        &self.0
+    }
+}
+
+impl BlindingScalar for FieldElement {
+    fn as_blinding(&self) -> &FieldElement {
+        self
+    }
+}
+
+impl BlindingScalar for Blinding {
+    fn as_blinding(&self) -> &FieldElement {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blind_signature::{BlindIssuanceRequest, BlindingKey};
+    use crate::keys::{keygen, rsskeygen, Params, SKrss, Sigkey};
+    use crate::pok_sig::PoKOfSignature;
+    use crate::rss_signature::{redact_many, RSSignature};
+    use crate::signature::Signature;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_typed_scalars() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let msgs = vec![
+            Message::from_msg_hash(b"a"),
+            Message::from_msg_hash(b"b"),
+            Message::random(),
+        ];
+        let elems: Vec<FieldElement> = msgs.iter().map(|m| m.as_field_element().clone()).collect();
+        assert_eq!(Message::all(elems.clone()), msgs);
+        // Typed and bare messages give the same signatures
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+        assert!(sig.verify(elems.clone(), &vk, &params).unwrap());
+        assert_eq!(
+            Signature::new_deterministic(&msgs, &sk).unwrap().to_bytes(),
+            Signature::new_deterministic(&elems, &sk)
+                .unwrap()
+                .to_bytes()
+        );
+
+        let blindings = vec![Blinding::random(), Blinding::random()];
+        let revealed: HashSet<usize> = vec![1].into_iter().collect();
+        let pok =
+            PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), Some(&blindings), revealed)
+                .unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        // The response for hidden message 0 is its blinding - c * m_0
+        assert_eq!(
+            proof.get_resp_for_message(0).unwrap(),
+            blindings[0].as_field_element() - &(&chal * msgs[0].as_field_element())
+        );
+
+        let blinding_key = BlindingKey::new(&sk, &params);
+        let nonce = b"nonce";
+        let (request, pending) = BlindIssuanceRequest::with_public_messages(
+            &msgs[..1],
+            &msgs[1..],
+            &blinding_key,
+            &params,
+            nonce,
+        )
+        .unwrap();
+        let blinded = request.sign(&sk, &blinding_key, &params, nonce).unwrap();
+        assert_eq!(pending.finish(&blinded, &vk, &params).unwrap().1, elems);
+
+        // Key scalars
+        let x = SecretScalar::random();
+        let y = vec![SecretScalar::random(), SecretScalar::random()];
+        let sk = Sigkey::from_scalars(x.clone(), y.clone()).unwrap();
+        assert_eq!(&sk.x, x.as_field_element());
+        assert_eq!(sk.y.len(), 2);
+        assert!(Sigkey::from_scalars(SecretScalar::new(FieldElement::zero()), y).is_err());
+        assert!(SKrss::from_scalars(3, x.clone(), SecretScalar::random()).is_ok());
+        assert!(SKrss::from_scalars(3, x.clone(), SecretScalar::new(FieldElement::one())).is_err());
+        assert_eq!(format!("{:?}", x), "SecretScalar(..)");
+    }
+
+    #[test]
+    fn test_typed_scalars_redactable() {
+        let params = Params::new("test".as_bytes());
+        let (sk, pk) = rsskeygen(3, &params).unwrap();
+        let msgs = vec![Message::random(), Message::random(), Message::random()];
+        let elems: Vec<FieldElement> = msgs.iter().map(|m| m.as_field_element().clone()).collect();
+        let sig = RSSignature::new(&msgs, &sk, &params).unwrap();
+        assert!(sig.verify(&msgs, &pk).unwrap());
+        assert!(sig.verify(&elems, &pk).unwrap());
+        assert!(sig.verify_with_params(&msgs, &pk, &params).unwrap());
+        assert!(sig.precheck(&msgs, &pk).unwrap());
+        assert!(sig.verify_detailed(&msgs, &pk).unwrap().is_valid());
+
+        let disclosed: HashSet<usize> = vec![1].into_iter().collect();
+        let derived = sig.derive(&msgs, &disclosed, &pk).unwrap();
+        let disclosed_msgs = vec![(1, elems[1].clone())].into_iter().collect();
+        assert!(derived.verify(&disclosed_msgs, &pk).unwrap());
+        let derived = redact_many(&sig, &msgs, &[disclosed], &pk).unwrap();
+        assert!(derived[0].verify(&disclosed_msgs, &pk).unwrap());
+    }
+}
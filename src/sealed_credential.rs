@@ -0,0 +1,204 @@
+// Credentials encrypted to their holder for transport over untrusted channels, behind the feature
+// `sealed-credentials`. The issuer signs first and `seal_credential` encrypts `Credential::to_bytes` to the X25519 key
+// of the holder: an ephemeral X25519 key agrees a shared secret with the key of the holder, HKDF-SHA256 of it with the
+// ephemeral and the recipient public keys as salt and `SEAL_INFO` as info gives the key of ChaCha20-Poly1305, and the
+// ephemeral public key is the associated data. The key is used once, so the nonce is 0. `open_credential` decrypts
+// with the secret key of the holder and fails with `PSError::CredentialDecryptionFailed` for another key or a modified
+// credential. The signature of the opened credential is not verified, the holder verifies it with the verkey of the
+// issuer it expects as for any credential. Shared secrets of low order points are rejected, so a recipient key of
+// low order fails sealing with `PSError::WeakKey`.
+
+use crate::errors::PSError;
+use crate::proof_request::Credential;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand::{thread_rng, RngCore};
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, SharedSecret, StaticSecret};
+
+/// Info of HKDF for the key of a sealed credential
+pub const SEAL_INFO: &[u8] = b"ps_sig sealed credential";
+
+const X25519_KEY_SIZE: usize = 32;
+
+/// Credential encrypted to a holder
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedCredential {
+    /// X25519 public key of the ephemeral secret of the issuer
+    pub ephemeral_pk: [u8; X25519_KEY_SIZE],
+    /// `Credential::to_bytes` encrypted, with the tag
+    pub ciphertext: Vec<u8>,
+}
+
+/// New X25519 key pair of a holder, from the thread local random number generator
+pub fn recipient_keypair() -> (StaticSecret, PublicKey) {
+    let sk = StaticSecret::from(random_secret());
+    let pk = PublicKey::from(&sk);
+    (sk, pk)
+}
+
+fn random_secret() -> [u8; X25519_KEY_SIZE] {
+    let mut bytes = [0u8; X25519_KEY_SIZE];
+    thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Cipher keyed with HKDF of `shared`
+fn cipher(
+    shared: &SharedSecret,
+    ephemeral_pk: &[u8],
+    recipient_pk: &PublicKey,
+) -> ChaCha20Poly1305 {
+    let salt = [ephemeral_pk, recipient_pk.as_bytes()].concat();
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes())
+        .expand(SEAL_INFO, &mut key)
+        .expect("32 bytes are a valid length for HKDF-SHA256");
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+/// `credential`, signed by the issuer, encrypted to `recipient_pk`
+pub fn seal_credential(
+    credential: &Credential,
+    recipient_pk: &PublicKey,
+) -> Result<SealedCredential, PSError> {
+    let ephemeral_sk = StaticSecret::from(random_secret());
+    let ephemeral_pk = PublicKey::from(&ephemeral_sk).to_bytes();
+    let shared = ephemeral_sk.diffie_hellman(recipient_pk);
+    if !shared.was_contributory() {
+        return Err(PSError::WeakKey {
+            reason: String::from("Recipient key is of low order"),
+        });
+    }
+    let ciphertext = cipher(&shared, &ephemeral_pk, recipient_pk)
+        .encrypt(
+            Nonce::from_slice(&[0u8; 12]),
+            Payload {
+                msg: &credential.to_bytes(),
+                aad: &ephemeral_pk,
+            },
+        )
+        .map_err(|_| PSError::GeneralError {
+            msg: String::from("Encryption of the credential failed"),
+        })?;
+    Ok(SealedCredential {
+        ephemeral_pk,
+        ciphertext,
+    })
+}
+
+/// Credential of `sealed` decrypted with `recipient_sk`, the secret key of the public key it was sealed to
+pub fn open_credential(
+    sealed: &SealedCredential,
+    recipient_sk: &StaticSecret,
+) -> Result<Credential, PSError> {
+    let shared = recipient_sk.diffie_hellman(&PublicKey::from(sealed.ephemeral_pk));
+    if !shared.was_contributory() {
+        return Err(PSError::CredentialDecryptionFailed);
+    }
+    let recipient_pk = PublicKey::from(recipient_sk);
+    let plaintext = cipher(&shared, &sealed.ephemeral_pk, &recipient_pk)
+        .decrypt(
+            Nonce::from_slice(&[0u8; 12]),
+            Payload {
+                msg: &sealed.ciphertext,
+                aad: &sealed.ephemeral_pk,
+            },
+        )
+        .map_err(|_| PSError::CredentialDecryptionFailed)?;
+    Credential::from_bytes(&plaintext)
+}
+
+impl SealedCredential {
+    /// Encoding as ephemeral_pk || ciphertext
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [&self.ephemeral_pk[..], &self.ciphertext].concat()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        if bytes.len() <= X25519_KEY_SIZE {
+            return Err(PSError::DeserializationError {
+                msg: format!("Sealed credential of {} bytes is too short", bytes.len()),
+            });
+        }
+        let mut ephemeral_pk = [0u8; X25519_KEY_SIZE];
+        ephemeral_pk.copy_from_slice(&bytes[..X25519_KEY_SIZE]);
+        Ok(Self {
+            ephemeral_pk,
+            ciphertext: bytes[X25519_KEY_SIZE..].to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::{keygen, Params};
+    use crate::signature::Signature;
+
+    fn credential() -> (Params, Credential) {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let msgs = FieldElementVector::random(3);
+        let credential = Credential {
+            signature: Signature::new(msgs.as_slice(), &sk, &params).unwrap(),
+            messages: msgs.into(),
+            verkey: vk,
+        };
+        (params, credential)
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let (params, credential) = credential();
+        let (sk, pk) = recipient_keypair();
+        let sealed = seal_credential(&credential, &pk).unwrap();
+        let sealed = SealedCredential::from_bytes(&sealed.to_bytes()).unwrap();
+        let opened = open_credential(&sealed, &sk).unwrap();
+        assert_eq!(opened.to_bytes(), credential.to_bytes());
+        assert!(opened
+            .signature
+            .verify(opened.messages.clone(), &opened.verkey, &params)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_open_with_other_key() {
+        let (_, credential) = credential();
+        let (_, pk) = recipient_keypair();
+        let (other_sk, _) = recipient_keypair();
+        let sealed = seal_credential(&credential, &pk).unwrap();
+        match open_credential(&sealed, &other_sk) {
+            Err(PSError::CredentialDecryptionFailed) => (),
+            r => panic!("Expected CredentialDecryptionFailed but got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_open_modified() {
+        let (_, credential) = credential();
+        let (sk, pk) = recipient_keypair();
+        let sealed = seal_credential(&credential, &pk).unwrap();
+        let mut modified_ciphertext = sealed.clone();
+        modified_ciphertext.ciphertext[0] ^= 1;
+        let mut modified_pk = sealed;
+        modified_pk.ephemeral_pk[0] ^= 1;
+        for s in &[modified_ciphertext, modified_pk] {
+            match open_credential(s, &sk) {
+                Err(PSError::CredentialDecryptionFailed) => (),
+                r => panic!("Expected CredentialDecryptionFailed but got {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_low_order_recipient_key() {
+        let (_, credential) = credential();
+        match seal_credential(&credential, &PublicKey::from([0u8; 32])) {
+            Err(PSError::WeakKey { .. }) => (),
+            r => panic!("Expected WeakKey but got {:?}", r),
+        }
+    }
+}
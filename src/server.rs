@@ -0,0 +1,587 @@
+// Reference issuance server, behind the feature `server`. It publishes the params and keys of an issuer and runs
+// blind issuance rounds bound to a link secret, see `link_secret`, with the serde JSON encodings of the types of
+// this crate as bodies:
+//   GET  /params        `Params`
+//   GET  /verkey        `Verkey`
+//   GET  /blinding-key  `BlindingKey`
+//   POST /nonce         nonce for one issuance, as JSON array of bytes
+//   POST /issue         `IssueRequest` to the blinded `Signature`, which the holder unblinds with `LinkSecret::unblind`
+// Each nonce is accepted by one issuance only and for `NONCE_TTL` seconds. Nonces given out and not used yet are kept
+// in a `nonce_store::TtlNonceStore` of at most `MAX_PENDING_NONCES`, `POST /nonce` answers 503 when it is full, so
+// clients asking for nonces without using them cannot grow the memory of the server. `IssuanceServer::handle` maps a
+// request to a response without any transport so it can be mounted in the HTTP framework of a deployment. `serve` is
+// a minimal HTTP/1.1 server on a std `TcpListener` with one request per connection, each on its own thread, enough
+// for integration tests and as a starting point. Reads and writes of a connection time out after `IO_TIMEOUT` and the
+// whole request after `REQUEST_DEADLINE`, so neither a silent client nor one sending a byte at a time can hold a
+// thread for long. At most `MAX_CONNECTIONS` connections are served at once, further ones are answered 503 and
+// closed, and failing accepts, e.g. when out of file descriptors, are retried rather than stopping the server. It
+// signs the messages sent by the holder, a deployment checks them first, e.g. in a proxy or with its own route.
+
+use crate::blind_signature::BlindingKey;
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{keygen, Params, Sigkey, Verkey};
+use crate::link_secret::LinkSecretCommitment;
+use crate::nonce_store::{NonceStore, TtlNonceStore};
+use rand::{thread_rng, RngCore};
+use serde::Serialize;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Bodies larger than this are rejected with status 413
+pub const MAX_BODY_SIZE: usize = 1 << 20;
+/// Bytes of the request line and headers read at most
+pub const MAX_HEADERS_SIZE: usize = 1 << 14;
+/// Bytes of the nonces of `POST /nonce`
+pub const NONCE_SIZE: usize = 32;
+/// Seconds a nonce of `POST /nonce` is accepted for
+pub const NONCE_TTL: u64 = 300;
+/// Nonces given out and not used yet kept at most
+pub const MAX_PENDING_NONCES: usize = 10_000;
+/// Reads and writes of a connection of `serve` fail after this long
+pub const IO_TIMEOUT: Duration = Duration::from_secs(10);
+/// Reading a request and writing its response fail after this long in total, however often the client sends
+pub const REQUEST_DEADLINE: Duration = Duration::from_secs(30);
+/// Connections served at once by `serve` at most
+pub const MAX_CONNECTIONS: usize = 256;
+/// Wait of `serve` before accepting again after accepting failed
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// Body of `POST /issue`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IssueRequest {
+    pub nonce: Vec<u8>,
+    pub request: LinkSecretCommitment,
+    /// Messages other than the link secret
+    pub messages: Vec<FieldElement>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    /// JSON
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct IssuanceServer {
+    params: Params,
+    sigkey: Sigkey,
+    verkey: Verkey,
+    blinding_key: BlindingKey,
+    /// Nonces given out and not used yet
+    nonces: Mutex<TtlNonceStore>,
+    io_timeout: Duration,
+    request_deadline: Duration,
+    max_connections: usize,
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+impl Response {
+    fn json<T: Serialize>(value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self { status: 200, body },
+            Err(e) => Self::error(500, &format!("{}", e)),
+        }
+    }
+
+    fn error(status: u16, msg: &str) -> Self {
+        let body = serde_json::to_vec(&serde_json::json!({ "error": msg }))
+            .unwrap_or_else(|_| b"{}".to_vec());
+        Self { status, body }
+    }
+
+    /// HTTP/1.1 encoding
+    pub fn to_http(&self) -> Vec<u8> {
+        let mut bytes = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason(self.status),
+            self.body.len()
+        )
+        .into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+}
+
+impl IssuanceServer {
+    /// Server with a new key for `count_messages` messages, including the link secret
+    pub fn new(count_messages: usize, params: Params) -> Result<Self, PSError> {
+        let (sigkey, verkey) = keygen(count_messages, &params)?;
+        Ok(Self::with_keys(sigkey, verkey, params))
+    }
+
+    pub fn with_keys(sigkey: Sigkey, verkey: Verkey, params: Params) -> Self {
+        let blinding_key = BlindingKey::new(&sigkey, &params);
+        Self {
+            params,
+            sigkey,
+            verkey,
+            blinding_key,
+            nonces: Mutex::new(TtlNonceStore::with_capacity(NONCE_TTL, MAX_PENDING_NONCES)),
+            io_timeout: IO_TIMEOUT,
+            request_deadline: REQUEST_DEADLINE,
+            max_connections: MAX_CONNECTIONS,
+        }
+    }
+
+    /// Same server keeping the nonces given out in `nonces` instead of the default store
+    pub fn with_nonce_store(mut self, nonces: TtlNonceStore) -> Self {
+        self.nonces = Mutex::new(nonces);
+        self
+    }
+
+    /// Same server timing out reads and writes of a connection after `timeout` instead of `IO_TIMEOUT`
+    pub fn with_io_timeout(mut self, timeout: Duration) -> Self {
+        self.io_timeout = timeout;
+        self
+    }
+
+    /// Same server failing a connection `deadline` after it was accepted instead of `REQUEST_DEADLINE`
+    pub fn with_request_deadline(mut self, deadline: Duration) -> Self {
+        self.request_deadline = deadline;
+        self
+    }
+
+    /// Same server serving at most `max` connections at once instead of `MAX_CONNECTIONS`
+    pub fn with_max_connections(mut self, max: usize) -> Self {
+        self.max_connections = max;
+        self
+    }
+
+    pub fn verkey(&self) -> &Verkey {
+        &self.verkey
+    }
+
+    /// Response to the request with `method` for `path` with `body`
+    pub fn handle(&self, method: &str, path: &str, body: &[u8]) -> Response {
+        match (method, path) {
+            ("GET", "/params") => Response::json(&self.params),
+            ("GET", "/verkey") => Response::json(&self.verkey),
+            ("GET", "/blinding-key") => Response::json(&self.blinding_key),
+            ("POST", "/nonce") => match self.new_nonce(unix_now()) {
+                Ok(nonce) => Response::json(&nonce),
+                Err(e) => Response::error(503, &format!("{}", e)),
+            },
+            ("POST", "/issue") => match self.issue(body, unix_now()) {
+                Ok(r) => r,
+                Err(e) => Response::error(400, &format!("{}", e)),
+            },
+            (_, "/params")
+            | (_, "/verkey")
+            | (_, "/blinding-key")
+            | (_, "/nonce")
+            | (_, "/issue") => Response::error(405, "Method not allowed"),
+            _ => Response::error(404, "Not found"),
+        }
+    }
+
+    /// Serve the connections of `listener`, each on its own thread, forever. Connections beyond the maximum are
+    /// answered 503 and closed.
+    pub fn serve(&self, listener: &TcpListener) -> io::Result<()> {
+        let active = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for stream in listener.incoming() {
+                // Accepting fails e.g. while out of file descriptors, which passes as connections close
+                let stream = match stream {
+                    Ok(s) => s,
+                    Err(_) => {
+                        thread::sleep(ACCEPT_RETRY_DELAY);
+                        continue;
+                    }
+                };
+                let slot = match ConnectionSlot::take(&active, self.max_connections) {
+                    Some(slot) => slot,
+                    None => {
+                        refuse(stream);
+                        continue;
+                    }
+                };
+                // A failing connection does not stop the server
+                scope.spawn(move || {
+                    let _slot = slot;
+                    self.serve_connection(stream)
+                });
+            }
+            Ok(())
+        })
+    }
+
+    /// Read one request from `stream` and write the response, within the request deadline
+    pub fn serve_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let mut stream = DeadlineStream {
+            stream,
+            io_timeout: self.io_timeout,
+            deadline: Instant::now() + self.request_deadline,
+        };
+        let response = match read_request(&mut stream)? {
+            Ok(r) => self.handle(&r.method, &r.path, &r.body),
+            Err(r) => r,
+        };
+        stream.write_all(&response.to_http())?;
+        stream.flush()
+    }
+
+    /// Nonce accepted until `now + NONCE_TTL`. Fails with `PSError::NonceStoreFull` if too many are not used yet.
+    fn new_nonce(&self, now: u64) -> Result<Vec<u8>, PSError> {
+        let mut nonce = vec![0; NONCE_SIZE];
+        thread_rng().fill_bytes(&mut nonce);
+        self.nonces.lock().unwrap().record(&nonce, now)?;
+        Ok(nonce)
+    }
+
+    fn issue(&self, body: &[u8], now: u64) -> Result<Response, PSError> {
+        let req: IssueRequest =
+            serde_json::from_slice(body).map_err(|e| PSError::DeserializationError {
+                msg: format!("{}", e),
+            })?;
+        if !self.nonces.lock().unwrap().take(&req.nonce, now) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Unknown, expired or used nonce"),
+            });
+        }
+        let sig = req.request.sign(
+            &req.messages,
+            &self.sigkey,
+            &self.blinding_key,
+            &self.params,
+            &req.nonce,
+        )?;
+        Ok(Response::json(&sig))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Place of a connection among those served at once, given back when dropped
+struct ConnectionSlot<'a>(&'a AtomicUsize);
+
+impl<'a> ConnectionSlot<'a> {
+    /// A place if fewer than `max` are taken
+    fn take(active: &'a AtomicUsize, max: usize) -> Option<Self> {
+        active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                if n < max {
+                    Some(n + 1)
+                } else {
+                    None
+                }
+            })
+            .ok()
+            .map(|_| Self(active))
+    }
+}
+
+impl Drop for ConnectionSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Answer 503 to a connection beyond the maximum and close it. The response fits in the send buffer of a new
+/// connection, so a nonblocking write does not hold the accept loop for a client that does not read.
+fn refuse(stream: TcpStream) {
+    let _ = stream.set_nonblocking(true);
+    let _ = (&stream).write_all(&Response::error(503, "Too many connections").to_http());
+}
+
+/// Connection whose reads and writes time out after `io_timeout` and fail once `deadline` passed, so that the
+/// deadline holds however many reads a request takes
+struct DeadlineStream {
+    stream: TcpStream,
+    io_timeout: Duration,
+    deadline: Instant,
+}
+
+impl DeadlineStream {
+    /// Time left for the next read or write, the I/O timeout at most. Fails once the deadline passed.
+    fn timeout(&self) -> io::Result<Duration> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Deadline of the request passed",
+            ));
+        }
+        Ok(left.min(self.io_timeout))
+    }
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.timeout()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for DeadlineStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.timeout()?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// The request, or the response for a malformed one. Reads at most `MAX_HEADERS_SIZE + MAX_BODY_SIZE` bytes.
+fn read_request<R: Read>(stream: R) -> io::Result<Result<Request, Response>> {
+    let mut reader = BufReader::new(stream.take((MAX_HEADERS_SIZE + MAX_BODY_SIZE) as u64));
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(m), Some(p)) => (m.to_string(), p.to_string()),
+        _ => return Ok(Err(Response::error(400, "Malformed request line"))),
+    };
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = match value.trim().parse::<usize>() {
+                    Ok(l) => l,
+                    Err(_) => return Ok(Err(Response::error(400, "Malformed Content-Length"))),
+                };
+            }
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Ok(Err(Response::error(413, "Body too large")));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Ok(Request { method, path, body }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::link_secret::LinkSecret;
+    use crate::signature::Signature;
+
+    fn get<T: serde::de::DeserializeOwned>(server: &IssuanceServer, method: &str, path: &str) -> T {
+        let r = server.handle(method, path, &[]);
+        assert_eq!(r.status, 200);
+        serde_json::from_slice(&r.body).unwrap()
+    }
+
+    #[test]
+    fn test_issuance_rounds() {
+        let server = IssuanceServer::new(4, Params::new("test".as_bytes())).unwrap();
+        let params: Params = get(&server, "GET", "/params");
+        let vk: Verkey = get(&server, "GET", "/verkey");
+        let blinding_key: BlindingKey = get(&server, "GET", "/blinding-key");
+
+        let link_secret = LinkSecret::new();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(3).into();
+        let nonce: Vec<u8> = get(&server, "POST", "/nonce");
+        assert_eq!(nonce.len(), NONCE_SIZE);
+        let (request, blinding) = link_secret.commit(&blinding_key, &params, &nonce);
+        let body = serde_json::to_vec(&IssueRequest {
+            nonce: nonce.clone(),
+            request,
+            messages: msgs.clone(),
+        })
+        .unwrap();
+        let r = server.handle("POST", "/issue", &body);
+        assert_eq!(r.status, 200);
+        let sig: Signature = serde_json::from_slice(&r.body).unwrap();
+        assert!(link_secret
+            .unblind(&sig, &blinding, &msgs, &vk, &params)
+            .is_ok());
+
+        // The nonce was used
+        assert_eq!(server.handle("POST", "/issue", &body).status, 400);
+        assert_eq!(server.handle("POST", "/issue", b"{").status, 400);
+        assert_eq!(server.handle("GET", "/issue", &[]).status, 405);
+        assert_eq!(server.handle("GET", "/other", &[]).status, 404);
+    }
+
+    #[test]
+    fn test_pending_nonces() {
+        let server = IssuanceServer::new(2, Params::new("test".as_bytes()))
+            .unwrap()
+            .with_nonce_store(TtlNonceStore::with_capacity(NONCE_TTL, 2));
+        let now = unix_now();
+        let issue_body = |nonce: &[u8]| {
+            let params = &server.params;
+            let (request, _) = LinkSecret::new().commit(&server.blinding_key, params, nonce);
+            serde_json::to_vec(&IssueRequest {
+                nonce: nonce.to_vec(),
+                request,
+                messages: FieldElementVector::random(1).into(),
+            })
+            .unwrap()
+        };
+        // Nonces not used fill the store and further ones are refused
+        let first = server.new_nonce(now).unwrap();
+        let second = server.new_nonce(now).unwrap();
+        match server.new_nonce(now) {
+            Err(PSError::NonceStoreFull { capacity }) => assert_eq!(capacity, 2),
+            r => panic!("Expected NonceStoreFull but got {:?}", r),
+        }
+        assert_eq!(server.handle("POST", "/nonce", &[]).status, 503);
+        // Using a nonce frees its place
+        assert!(server.issue(&issue_body(&first), now + 1).is_ok());
+        assert!(server.new_nonce(now + 1).is_ok());
+        // Expired nonces are refused and free their place
+        assert!(server.issue(&issue_body(&second), now + NONCE_TTL).is_err());
+        assert!(server.new_nonce(now + NONCE_TTL).is_ok());
+    }
+
+    #[test]
+    fn test_serve_times_out_silent_clients() {
+        let server = IssuanceServer::new(2, Params::new("test".as_bytes()))
+            .unwrap()
+            .with_io_timeout(Duration::from_millis(100));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // The client sends part of a request and waits
+        let client = TcpStream::connect(addr).unwrap();
+        (&client).write_all(b"GET /verkey HTTP/1.1\r\n").unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        assert!(server.serve_connection(stream).is_err());
+        drop(client);
+    }
+
+    #[test]
+    fn test_serve_deadline_of_slow_clients() {
+        let server = IssuanceServer::new(2, Params::new("test".as_bytes()))
+            .unwrap()
+            .with_io_timeout(Duration::from_millis(200))
+            .with_request_deadline(Duration::from_millis(300));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // The client sends a byte before each read times out, for longer than the deadline
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            for b in b"GET /verkey HTTP/1.1\r\n".iter() {
+                if stream.write_all(&[*b]).is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let start = Instant::now();
+        assert!(server.serve_connection(stream).is_err());
+        assert!(start.elapsed() < Duration::from_millis(1000));
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn test_serve_connections_concurrently() {
+        let server = IssuanceServer::new(2, Params::new("test".as_bytes())).unwrap();
+        let vk = server.verkey().to_bytes();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || server.serve(&listener));
+        // A silent client does not hold the next one until it times out
+        let silent = TcpStream::connect(addr).unwrap();
+        let start = Instant::now();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET /verkey HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(start.elapsed() < IO_TIMEOUT);
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let received: Verkey = serde_json::from_str(body).unwrap();
+        assert_eq!(received.to_bytes(), vk);
+        drop(silent);
+    }
+
+    #[test]
+    fn test_serve_refuses_connections_beyond_maximum() {
+        let server = IssuanceServer::new(2, Params::new("test".as_bytes()))
+            .unwrap()
+            .with_max_connections(1);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || server.serve(&listener));
+        // A silent client takes the only place
+        let silent = TcpStream::connect(addr).unwrap();
+        let mut refused = TcpStream::connect(addr).unwrap();
+        let mut response = String::new();
+        refused.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+
+        // Its place is given back once it closes
+        drop(silent);
+        let mut served = false;
+        for _ in 0..50 {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            let mut response = String::new();
+            if stream
+                .write_all(b"GET /verkey HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .and_then(|_| stream.read_to_string(&mut response))
+                .is_ok()
+                && response.starts_with("HTTP/1.1 200 OK\r\n")
+            {
+                served = true;
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(served);
+    }
+
+    #[test]
+    fn test_serve_over_tcp() {
+        let server = IssuanceServer::new(2, Params::new("test".as_bytes())).unwrap();
+        let vk = server.verkey().to_bytes();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(b"GET /verkey HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .unwrap();
+            let mut response = vec![];
+            stream.read_to_end(&mut response).unwrap();
+            response
+        });
+        let (stream, _) = listener.accept().unwrap();
+        server.serve_connection(stream).unwrap();
+        let response = String::from_utf8(client.join().unwrap()).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let received: Verkey = serde_json::from_str(body).unwrap();
+        assert_eq!(received.to_bytes(), vk);
+    }
+}
@@ -0,0 +1,556 @@
+// Scheme defined in section 4.2 of the 2016 paper
+
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::{catch_amcl_panic, PSError};
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::msm::multi_scalar_mul_var_time;
+use crate::rng_health::{thread_nonce, NonceHealthCheck, ScalarSource};
+use crate::scalar::MessageScalar;
+use crate::verification_report::{Check, VerificationReport};
+use crate::{ate_2_pairing, ate_pairing, SignatureGroup, VerkeyGroup, SIGNATURE_GROUP_SIZE};
+use std::convert::TryFrom;
+
+// Deserialized with serde through `SignatureElements` so that its elements are checked to be in the prime order
+// group, as with `from_bytes`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "SignatureElements")]
+pub struct Signature {
+    pub sigma_1: SignatureGroup,
+    pub sigma_2: SignatureGroup,
+}
+
+#[derive(Deserialize)]
+struct SignatureElements {
+    sigma_1: SignatureGroup,
+    sigma_2: SignatureGroup,
+}
+
+/// Whether deserialization checks that group elements are in the prime order group. A point of small order passed
+/// as an element of a signature can confine the pairings of the verification equations to a small subgroup, so
+/// the checks are only to be disabled for bytes from trusted storage, e.g. signatures stored by the verifier after
+/// verifying them. Each check is a scalar multiplication by the group order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SubgroupChecks {
+    #[default]
+    Enabled,
+    Disabled,
+}
+
+impl TryFrom<SignatureElements> for Signature {
+    type Error = PSError;
+
+    fn try_from(e: SignatureElements) -> Result<Self, PSError> {
+        check_subgroup(&[&e.sigma_1, &e.sigma_2])?;
+        Ok(Self {
+            sigma_1: e.sigma_1,
+            sigma_2: e.sigma_2,
+        })
+    }
+}
+
+impl Signature {
+    /// Create a new signature. The signature generation involves generating a random value for `sigma_1` so different
+    /// calls to this method with same messages and signing key will give different value
+    pub fn new<M: MessageScalar>(
+        messages: &[M],
+        sigkey: &Sigkey,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        let messages = &M::field_elements(messages);
+        Self::check_sigkey_and_messages_compat(messages, sigkey)?;
+        // A random h should be generated which is same as generating a random u and then computing h = g^u
+        let u = thread_nonce()?;
+        let (sigma_1, sigma_2) =
+            Self::sign_with_sigma_1_generated_from_given_exp(messages, sigkey, &u, 0, &params.g)?;
+        Ok(Self { sigma_1, sigma_2 })
+    }
+
+    /// Same as `new` with the nonce u from `nonces` rather than the thread local random number generator
+    pub fn new_with_source<M: MessageScalar, S: ScalarSource>(
+        messages: &[M],
+        sigkey: &Sigkey,
+        params: &Params,
+        nonces: &mut NonceHealthCheck<S>,
+    ) -> Result<Self, PSError> {
+        let messages = &M::field_elements(messages);
+        Self::check_sigkey_and_messages_compat(messages, sigkey)?;
+        let u = nonces.nonce()?;
+        let (sigma_1, sigma_2) =
+            Self::sign_with_sigma_1_generated_from_given_exp(messages, sigkey, &u, 0, &params.g)?;
+        Ok(Self { sigma_1, sigma_2 })
+    }
+
+    /// Create a new signature. The signature generation doesn't involve generating a random value but
+    /// the messages are hashed to get a pseudorandom value for `sigma_1`. Hence different calls to this method
+    /// with same messages and signing key will give same value
+    pub fn new_deterministic<M: MessageScalar>(
+        messages: &[M],
+        sigkey: &Sigkey,
+    ) -> Result<Self, PSError> {
+        let messages = &M::field_elements(messages);
+        Self::check_sigkey_and_messages_compat(messages, sigkey)?;
+        let sigma_1 = Self::generate_sigma_1_from_messages(messages);
+        let sigma_2 = Self::sign_with_given_sigma_1(messages, sigkey, 0, &sigma_1)?;
+        Ok(Self { sigma_1, sigma_2 })
+    }
+
+    /// Generate signature when first element of signature tuple is generated using given exponent.
+    /// Does only 2 scalar multiplications. `offset` is the index of the first message in `messages`
+    /// among all messages the signing key supports.
+    pub fn sign_with_sigma_1_generated_from_given_exp(
+        messages: &[FieldElement],
+        sigkey: &Sigkey,
+        u: &FieldElement,
+        offset: usize,
+        g: &SignatureGroup,
+    ) -> Result<(SignatureGroup, SignatureGroup), PSError> {
+        crate::profiling::record_scalar_muls(2);
+        // h = g^u
+        let h = g * u;
+        // h^(x + y_j*m_j + y_{j+1}*m_{j+1} + y_{j+2}*m_{j+2} + ...) = g^{u * (x + y_j*m_j + y_{j+1}*m_{j+1} + y_{j+2}*m_{j+2} + ...)}
+        let exp = Self::sigkey_exponent(messages, sigkey, offset)? * u;
+        let g_sigma_2 = g * &exp;
+        Ok((h, g_sigma_2))
+    }
+
+    /// Generate signature when first element of signature tuple is given
+    pub fn sign_with_given_sigma_1(
+        messages: &[FieldElement],
+        sigkey: &Sigkey,
+        offset: usize,
+        h: &SignatureGroup,
+    ) -> Result<SignatureGroup, PSError> {
+        // h^(x + y_j*m_j + y_{j+1}*m_{j+1} + y_{j+2}*m_{j+2} + ...)
+        let exp = Self::sigkey_exponent(messages, sigkey, offset)?;
+        crate::profiling::record_scalar_muls(1);
+        Ok(h * &exp)
+    }
+
+    /// Verify a signature. Can verify unblinded sig received from a signer and the aggregate sig as well.
+    pub fn verify<M: MessageScalar>(
+        &self,
+        messages: Vec<M>,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        let messages = M::into_field_elements(messages);
+        Self::check_verkey_and_messages_compat(messages.as_slice(), vk)?;
+        vk.check_params(params)?;
+        if self.sigma_1.is_identity() || self.sigma_2.is_identity() {
+            return Ok(false);
+        }
+        Ok(self.pairing_check(messages, vk, params))
+    }
+
+    /// Checks without pairings for rejecting malformed signatures before `verify`: the count of messages,
+    /// sigma_1 and sigma_2 are not the identity and are in the prime order group. Fails with an error when the
+    /// count of messages is wrong and returns false when an element is invalid. The group membership checks
+    /// are a scalar multiplication per element, needed for signatures built from their elements or deserialized
+    /// with `SubgroupChecks::Disabled`, as `from_bytes` and serde check it.
+    pub fn precheck(&self, messages: &[FieldElement], vk: &Verkey) -> Result<bool, PSError> {
+        Self::check_verkey_and_messages_compat(messages, vk)?;
+        Ok(non_identity_in_group(&[&self.sigma_1, &self.sigma_2]))
+    }
+
+    /// Same as `verify` but reports which check failed, see `verification_report`
+    pub fn verify_detailed<M: MessageScalar>(
+        &self,
+        messages: Vec<M>,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<VerificationReport, PSError> {
+        let messages = M::into_field_elements(messages);
+        Self::check_verkey_and_messages_compat(messages.as_slice(), vk)?;
+        vk.check_params(params)?;
+        if let Some(report) = identity_report(&[&self.sigma_1, &self.sigma_2]) {
+            return Ok(report);
+        }
+        Ok(VerificationReport::pairing_equation(
+            Check::SignaturePairing,
+            None,
+            ate_pairing(&self.sigma_1, &Self::X_tilde_Y_m(messages, vk)),
+            ate_pairing(&self.sigma_2, &params.g_tilde),
+        ))
+    }
+
+    /// Byte representation of the signature
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.append(&mut self.sigma_1.to_bytes());
+        bytes.append(&mut self.sigma_2.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_bytes_with_checks(bytes, SubgroupChecks::default())
+    }
+
+    /// Same as `from_bytes` with the subgroup checks of the elements enabled or not
+    pub fn from_bytes_with_checks(bytes: &[u8], checks: SubgroupChecks) -> Result<Self, PSError> {
+        if bytes.len() != 2 * SIGNATURE_GROUP_SIZE {
+            return Err(PSError::DeserializationError {
+                msg: format!(
+                    "Signature needs {} bytes but given {}",
+                    2 * SIGNATURE_GROUP_SIZE,
+                    bytes.len()
+                ),
+            });
+        }
+        let (s1, s2) = bytes.split_at(SIGNATURE_GROUP_SIZE);
+        Ok(Self {
+            sigma_1: group_elem_from_bytes_with_checks(s1, checks)?,
+            sigma_2: group_elem_from_bytes_with_checks(s2, checks)?,
+        })
+    }
+
+    pub fn check_verkey_and_messages_compat(
+        messages: &[FieldElement],
+        verkey: &Verkey,
+    ) -> Result<(), PSError> {
+        if messages.len() != verkey.Y_tilde.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: verkey.Y_tilde.len(),
+                given: messages.len(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn check_sigkey_and_messages_compat(
+        messages: &[FieldElement],
+        sigkey: &Sigkey,
+    ) -> Result<(), PSError> {
+        if messages.len() != sigkey.y.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: sigkey.y.len(),
+                given: messages.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Helper for pairing check e(sigma_1, X_tilde * Y_tilde_1^m_1 * Y_tilde_2^m_2 *...) == e(sigma_2, g_tilde)
+    pub(crate) fn pairing_check(
+        &self,
+        messages: Vec<FieldElement>,
+        vk: &Verkey,
+        params: &Params,
+    ) -> bool {
+        // e(sigma_1, X_tilde * Y_tilde_1^m_1 * Y_tilde_2^m_2 *...) == e(sigma_2, g_tilde) =>
+        // e(sigma_1, X_tilde * Y_tilde_1^m_1 * Y_tilde_2^m_2 *...) * e(-sigma_2, g_tilde) == 1
+        let e = ate_2_pairing(
+            &self.sigma_1,
+            &Self::X_tilde_Y_m(messages, vk),
+            &(-&self.sigma_2),
+            &params.g_tilde,
+        );
+        e.is_one()
+    }
+
+    /// X_tilde * Y_tilde_1^m_1 * Y_tilde_2^m_2 *...
+    pub(crate) fn X_tilde_Y_m(messages: Vec<FieldElement>, vk: &Verkey) -> VerkeyGroup {
+        let Y_m = multi_scalar_mul_var_time(&vk.Y_tilde[..messages.len()], &messages).unwrap();
+        Y_m + &vk.X_tilde
+    }
+
+    /// x + y_{offset}*m_0 + y_{offset+1}*m_1 + ...
+    pub(crate) fn sigkey_exponent(
+        messages: &[FieldElement],
+        sigkey: &Sigkey,
+        offset: usize,
+    ) -> Result<FieldElement, PSError> {
+        if offset + messages.len() > sigkey.y.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: sigkey.y.len(),
+                given: offset + messages.len(),
+            });
+        }
+        let mut exp = sigkey.x.clone();
+        for (y, m) in sigkey.y[offset..].iter().zip(messages) {
+            exp += y * m;
+        }
+        Ok(exp)
+    }
+
+    /// Hash messages to get a group element. Used for deterministic signatures
+    fn generate_sigma_1_from_messages(messages: &[FieldElement]) -> SignatureGroup {
+        let mut msg_bytes = vec![];
+        for i in messages {
+            msg_bytes.append(&mut i.to_bytes());
+        }
+        SignatureGroup::from_msg_hash(&msg_bytes)
+    }
+}
+
+/// Report of failed `Check::SignatureNotIdentity` with the hex of the first element that is the identity
+pub(crate) fn identity_report(elems: &[&SignatureGroup]) -> Option<VerificationReport> {
+    elems.iter().find(|e| e.is_identity()).map(|e| {
+        VerificationReport::failed(
+            Check::SignatureNotIdentity,
+            None,
+            e.to_hex(),
+            String::from("non-identity element"),
+        )
+    })
+}
+
+/// Whether none of `elems` is the identity and all are in the prime order group
+pub(crate) fn non_identity_in_group<G: GroupElement>(elems: &[&G]) -> bool {
+    elems.iter().all(|e| !e.is_identity() && in_group(*e))
+}
+
+/// Whether `elem` is in the prime order group
+pub(crate) fn in_group<G: GroupElement>(elem: &G) -> bool {
+    elem.has_correct_order()
+}
+
+/// Fails with `PSError::InvalidGroupElement` if an element other than the identity is outside the prime order group
+pub(crate) fn check_subgroup<G: GroupElement>(elems: &[&G]) -> Result<(), PSError> {
+    for e in elems {
+        if !e.is_identity() && !in_group(*e) {
+            return Err(PSError::InvalidGroupElement);
+        }
+    }
+    Ok(())
+}
+
+/// Deserialize a group element received from outside. amcl_wrapper decodes bytes of a point not on the
+/// curve as the identity and accepts points outside the prime order subgroup, both are rejected here with
+/// `PSError::InvalidGroupElement`. The identity is only accepted in its canonical encoding.
+pub(crate) fn group_elem_from_bytes<G: GroupElement>(bytes: &[u8]) -> Result<G, PSError> {
+    group_elem_from_bytes_with_checks(bytes, SubgroupChecks::Enabled)
+}
+
+/// Same as `group_elem_from_bytes`, points outside the prime order subgroup accepted if `checks` is disabled
+pub(crate) fn group_elem_from_bytes_with_checks<G: GroupElement>(
+    bytes: &[u8],
+    checks: SubgroupChecks,
+) -> Result<G, PSError> {
+    let elem =
+        catch_amcl_panic(|| G::from_bytes(bytes))?.map_err(|e| PSError::DeserializationError {
+            msg: format!("{}", e),
+        })?;
+    let valid = if elem.is_identity() {
+        bytes == G::identity().to_bytes().as_slice()
+    } else {
+        checks == SubgroupChecks::Disabled || in_group(&elem)
+    };
+    if !valid {
+        return Err(PSError::InvalidGroupElement);
+    }
+    Ok(elem)
+}
+
+/// Random modifications of a valid serialization for checking that deserialization never panics
+#[cfg(test)]
+pub(crate) fn fuzzed_bytes(valid: &[u8], count: usize) -> Vec<Vec<u8>> {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let mut fuzzed = Vec::with_capacity(count);
+    for i in 0..count {
+        let mut b = valid.to_vec();
+        match i % 4 {
+            // Flip a bit
+            0 => {
+                let j = rng.gen_range(0, b.len());
+                b[j] ^= 1 << rng.gen_range(0, 8);
+            }
+            // Overwrite a run of bytes
+            1 => {
+                let j = rng.gen_range(0, b.len());
+                let k = rng.gen_range(j, b.len());
+                for x in &mut b[j..=k] {
+                    *x = rng.gen();
+                }
+            }
+            // Truncate
+            2 => b.truncate(rng.gen_range(0, b.len())),
+            // Random bytes of same length
+            _ => {
+                for x in b.iter_mut() {
+                    *x = rng.gen();
+                }
+            }
+        }
+        fuzzed.push(b);
+    }
+    fuzzed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_signature_all_known_messages() {
+        let params = Params::new("test".as_bytes());
+        for i in 0..10 {
+            let count_msgs = (i % 5) + 1;
+            let (sk, vk) = keygen(count_msgs, &params).unwrap();
+            let msgs = (0..count_msgs)
+                .map(|_| FieldElement::random())
+                .collect::<Vec<FieldElement>>();
+            let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+            assert!(sig.verify(msgs, &vk, &params).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_deterministic_signature_all_known_messages() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = (0..count_msgs)
+            .map(|_| FieldElement::random())
+            .collect::<Vec<FieldElement>>();
+        let sig_1 = Signature::new_deterministic(msgs.as_slice(), &sk).unwrap();
+        let sig_2 = Signature::new_deterministic(msgs.as_slice(), &sk).unwrap();
+        assert_eq!(sig_1.sigma_1, sig_2.sigma_1);
+        assert_eq!(sig_1.sigma_2, sig_2.sigma_2);
+        assert!(sig_1.verify(msgs, &vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_signature_wrong_messages() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let mut msgs = (0..count_msgs)
+            .map(|_| FieldElement::random())
+            .collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        msgs[2] = FieldElement::random();
+        assert!(!sig.verify(msgs.clone(), &vk, &params).unwrap());
+
+        // Incorrect number of messages
+        assert!(Signature::new(&msgs[1..], &sk, &params).is_err());
+        assert!(sig.verify(msgs[1..].to_vec(), &vk, &params).is_err());
+
+        // Params of another label
+        let other_params = Params::new("other".as_bytes());
+        match sig.verify(msgs.clone(), &vk, &other_params) {
+            Err(PSError::ParamsMismatch) => (),
+            r => panic!("Expected ParamsMismatch but got {:?}", r),
+        }
+        assert!(sig.verify_detailed(msgs, &vk, &other_params).is_err());
+    }
+
+    #[test]
+    fn test_signature_to_and_from_bytes() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = (0..count_msgs)
+            .map(|_| FieldElement::random())
+            .collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let bytes = sig.to_bytes();
+        let sig_1 = Signature::from_bytes(&bytes).unwrap();
+        assert!(sig_1.verify(msgs, &vk, &params).unwrap());
+        assert!(Signature::from_bytes(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn test_group_elem_from_bytes_rejects_invalid() {
+        let valid = SignatureGroup::random().to_bytes();
+        assert!(group_elem_from_bytes::<SignatureGroup>(&valid).is_ok());
+        let identity = SignatureGroup::identity().to_bytes();
+        assert!(group_elem_from_bytes::<SignatureGroup>(&identity)
+            .unwrap()
+            .is_identity());
+
+        // Coordinates not less than the field modulus decode to the identity in amcl_wrapper
+        let all_set = vec![0xffu8; SIGNATURE_GROUP_SIZE];
+        match group_elem_from_bytes::<SignatureGroup>(&all_set) {
+            Err(PSError::InvalidGroupElement) => (),
+            r => panic!("Expected InvalidGroupElement but got {:?}", r),
+        }
+
+        // Point on the curve (x = 5) but outside the prime order subgroup of G1
+        let mut small_order = vec![0u8; crate::curve::GroupG1_SIZE];
+        small_order[0] = 2;
+        small_order[crate::curve::MODBYTES] = 5;
+        match group_elem_from_bytes::<crate::curve::G1>(&small_order) {
+            Err(PSError::InvalidGroupElement) => (),
+            r => panic!("Expected InvalidGroupElement but got {:?}", r),
+        }
+
+        assert!(group_elem_from_bytes::<SignatureGroup>(&valid[1..]).is_err());
+    }
+
+    #[test]
+    fn test_signature_precheck() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(sig.precheck(msgs.as_slice(), &vk).unwrap());
+        assert!(sig.precheck(&msgs.as_slice()[1..], &vk).is_err());
+        let mut identity = sig.clone();
+        identity.sigma_2 = SignatureGroup::identity();
+        assert!(!identity.precheck(msgs.as_slice(), &vk).unwrap());
+
+        // Point on the curve but outside the prime order subgroup of G1, as in
+        // `test_group_elem_from_bytes_rejects_invalid`
+        let mut small_order = vec![0u8; crate::curve::GroupG1_SIZE];
+        small_order[0] = 2;
+        small_order[crate::curve::MODBYTES] = 5;
+        let small_order = crate::curve::G1::from_bytes(&small_order).unwrap();
+        assert!(!small_order.is_identity());
+        assert!(!non_identity_in_group(&[&small_order]));
+        assert!(non_identity_in_group(&[&crate::curve::G1::random()]));
+    }
+
+    #[test]
+    fn test_signature_from_fuzzed_bytes() {
+        let count_msgs = 3;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        for bytes in fuzzed_bytes(&sig.to_bytes(), 40) {
+            // Must not panic, and anything decoded must be well formed
+            if let Ok(s) = Signature::from_bytes(&bytes) {
+                assert_eq!(s.to_bytes(), bytes);
+                let _ = s.verify(msgs.clone().into(), &vk, &params).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_signature_verify_detailed() {
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(sig
+            .verify_detailed(msgs.clone().into(), &vk, &params)
+            .unwrap()
+            .is_valid());
+
+        let mut wrong: Vec<FieldElement> = msgs.clone().into();
+        wrong[2] = FieldElement::random();
+        let failure = sig
+            .verify_detailed(wrong, &vk, &params)
+            .unwrap()
+            .failure
+            .unwrap();
+        assert_eq!(failure.check, Check::SignaturePairing);
+        assert_ne!(failure.recomputed, failure.expected);
+
+        let sig_1 = Signature {
+            sigma_1: SignatureGroup::identity(),
+            sigma_2: sig.sigma_2.clone(),
+        };
+        let failure = sig_1
+            .verify_detailed(msgs.into(), &vk, &params)
+            .unwrap()
+            .failure
+            .unwrap();
+        assert_eq!(failure.check, Check::SignatureNotIdentity);
+    }
+}
@@ -0,0 +1,157 @@
+// The traits `Signer` and `Verifier` of the `signature` crate for PS signatures of a single message, behind the
+// feature `signature-traits`, so that generic signing infrastructure like certificate tooling can use keys of this
+// crate. The bytes to sign are hashed to the message `single_message`, the hash of "PS single message : " || the
+// bytes, and signed with a key for 1 message. The traits carry no detail in their errors, so `SingleMessageSigner`
+// and `SingleMessageVerifier` check the count of messages and the params of the keys when they are created and
+// `Signature` implements `SignatureEncoding` with `Signature::to_bytes`.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::signature::Signature;
+use sig_traits::{Error, SignatureEncoding, Signer, Verifier};
+use std::convert::TryFrom;
+
+const SINGLE_MESSAGE_DOMAIN: &[u8] = b"PS single message : ";
+
+/// Message signed for `msg`
+pub fn single_message(msg: &[u8]) -> FieldElement {
+    FieldElement::from_msg_hash(&[SINGLE_MESSAGE_DOMAIN, msg].concat())
+}
+
+/// Signing key for 1 message with its params, a `Signer<Signature>`
+#[derive(Clone, Debug)]
+pub struct SingleMessageSigner {
+    sigkey: Sigkey,
+    params: Params,
+}
+
+/// Verkey for 1 message with its params, a `Verifier<Signature>`
+#[derive(Clone, Debug)]
+pub struct SingleMessageVerifier {
+    verkey: Verkey,
+    params: Params,
+}
+
+fn check_single_message(count_messages: usize) -> Result<(), PSError> {
+    if count_messages != 1 {
+        return Err(PSError::UnsupportedNoOfMessages {
+            expected: 1,
+            given: count_messages,
+        });
+    }
+    Ok(())
+}
+
+impl SingleMessageSigner {
+    /// Fails with `PSError::UnsupportedNoOfMessages` unless `sigkey` is for 1 message
+    pub fn new(sigkey: Sigkey, params: Params) -> Result<Self, PSError> {
+        check_single_message(sigkey.supported_messages())?;
+        Ok(Self { sigkey, params })
+    }
+
+    /// Verifier of the signatures of this signer
+    pub fn verifier(&self) -> SingleMessageVerifier {
+        SingleMessageVerifier {
+            verkey: self.sigkey.to_verkey(&self.params),
+            params: self.params.clone(),
+        }
+    }
+}
+
+impl SingleMessageVerifier {
+    /// Fails with `PSError::UnsupportedNoOfMessages` unless `verkey` is for 1 message and with
+    /// `PSError::ParamsMismatch` if it is not of `params`
+    pub fn new(verkey: Verkey, params: Params) -> Result<Self, PSError> {
+        check_single_message(verkey.supported_messages())?;
+        verkey.check_params(&params)?;
+        Ok(Self { verkey, params })
+    }
+}
+
+impl Signer<Signature> for SingleMessageSigner {
+    fn try_sign(&self, msg: &[u8]) -> Result<Signature, Error> {
+        Signature::new(&[single_message(msg)], &self.sigkey, &self.params).map_err(|_| Error::new())
+    }
+}
+
+impl Verifier<Signature> for SingleMessageVerifier {
+    fn verify(&self, msg: &[u8], signature: &Signature) -> Result<(), Error> {
+        match signature.verify(vec![single_message(msg)], &self.verkey, &self.params) {
+            Ok(true) => Ok(()),
+            _ => Err(Error::new()),
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for Signature {
+    type Error = PSError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, PSError> {
+        Signature::from_bytes(bytes)
+    }
+}
+
+impl From<Signature> for Vec<u8> {
+    fn from(sig: Signature) -> Self {
+        sig.to_bytes()
+    }
+}
+
+impl SignatureEncoding for Signature {
+    type Repr = Vec<u8>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    fn signer() -> SingleMessageSigner {
+        let params = Params::new("test".as_bytes());
+        let (sk, _) = keygen(1, &params).unwrap();
+        SingleMessageSigner::new(sk, params).unwrap()
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = signer();
+        let sig = signer.try_sign(b"certificate").unwrap();
+        assert!(signer.verifier().verify(b"certificate", &sig).is_ok());
+        assert!(signer.verifier().verify(b"other", &sig).is_err());
+    }
+
+    #[test]
+    fn test_signature_encoding() {
+        let signer = signer();
+        let sig = signer.sign(b"certificate");
+        let bytes = SignatureEncoding::to_bytes(&sig);
+        let sig_1 = Signature::try_from(bytes.as_slice()).unwrap();
+        assert!(signer.verifier().verify(b"certificate", &sig_1).is_ok());
+        assert!(Signature::try_from(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn test_keys_for_other_counts_rejected() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(2, &params).unwrap();
+        match SingleMessageSigner::new(sk, params.clone()) {
+            Err(PSError::UnsupportedNoOfMessages {
+                expected: 1,
+                given: 2,
+            }) => (),
+            r => panic!("Expected UnsupportedNoOfMessages but got {:?}", r),
+        }
+        assert!(SingleMessageVerifier::new(vk, params).is_err());
+    }
+
+    #[test]
+    fn test_verifier_of_other_params_rejected() {
+        let signer = signer();
+        let vk = signer.verifier().verkey;
+        match SingleMessageVerifier::new(vk, Params::new("other".as_bytes())) {
+            Err(PSError::ParamsMismatch) => (),
+            r => panic!("Expected ParamsMismatch but got {:?}", r),
+        }
+    }
+}
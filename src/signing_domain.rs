@@ -0,0 +1,250 @@
+// Signing domains of organizations with several issuing services. Each service has its own key of the 2016 scheme
+// and the organization publishes a `DomainVerkey`: the verkeys of the services and their aggregate, the sum of each
+// of their parts. A signature of any service verifies against the domain key, so verifiers trust the domain and not
+// each service, and signatures of all services on the same messages aggregate to a signature verifying with the
+// aggregate verkey, see `Signature::aggregate`.
+// A service could pick its verkey as the verkey of a key it knows minus those of the others, so that it alone can
+// create signatures for the aggregate verkey. `Verkey::aggregate` therefore requires a `ProofOfPossession` of each
+// verkey, a proof of knowledge of the discrete logs of X_tilde and each Y_tilde_i in base g_tilde, which the service
+// cannot create for such a verkey.
+
+use crate::challenge_domain::KEY_POSSESSION;
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::pok_sig::{ProofVerkeyGroup, ProverCommittingVerkeyGroup};
+use crate::signature::Signature;
+use crate::VerkeyGroup;
+
+/// Proof of knowledge of the signing key of a verkey, one proof per element of the verkey with a shared challenge
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofOfPossession {
+    /// For X_tilde, then each Y_tilde_i
+    pub proofs: Vec<ProofVerkeyGroup>,
+}
+
+/// Verkeys of the signers of a domain and their aggregate
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DomainVerkey {
+    pub aggregate: Verkey,
+    pub members: Vec<Verkey>,
+}
+
+fn possession_challenge(vk: &Verkey, commitments: &[u8]) -> FieldElement {
+    let mut bytes = KEY_POSSESSION.tag();
+    bytes.append(&mut vk.to_bytes());
+    bytes.extend_from_slice(commitments);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl ProofOfPossession {
+    /// Proof of knowledge of `sigkey` for its verkey `verkey`
+    pub fn new(sigkey: &Sigkey, verkey: &Verkey, params: &Params) -> Result<Self, PSError> {
+        verkey.check_params(params)?;
+        let secrets: Vec<&FieldElement> =
+            std::iter::once(&sigkey.x).chain(sigkey.y.iter()).collect();
+        if secrets.len() != verkey.Y_tilde.len() + 1 {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: verkey.supported_messages(),
+                given: sigkey.y.len(),
+            });
+        }
+        let committed: Vec<_> = secrets
+            .iter()
+            .map(|_| {
+                let mut committing = ProverCommittingVerkeyGroup::new();
+                committing.commit(&params.g_tilde, None);
+                committing.finish()
+            })
+            .collect();
+        let mut commitments = vec![];
+        for c in &committed {
+            commitments.append(&mut c.to_bytes());
+        }
+        let challenge = possession_challenge(verkey, &commitments);
+        let mut proofs = Vec::with_capacity(committed.len());
+        for (c, s) in committed.into_iter().zip(secrets) {
+            proofs.push(c.gen_proof(&challenge, std::slice::from_ref(s))?);
+        }
+        Ok(Self { proofs })
+    }
+
+    pub fn verify(&self, verkey: &Verkey, params: &Params) -> Result<bool, PSError> {
+        verkey.check_params(params)?;
+        if self.proofs.len() != verkey.Y_tilde.len() + 1 {
+            return Ok(false);
+        }
+        let mut commitments = vec![];
+        // Same bytes as of the `ProverCommittedVerkeyGroup`s of `new`, g_tilde || commitment
+        for p in &self.proofs {
+            commitments.append(&mut params.g_tilde.to_bytes());
+            commitments.append(&mut p.commitment.to_bytes());
+        }
+        let challenge = possession_challenge(verkey, &commitments);
+        let elements = std::iter::once(&verkey.X_tilde).chain(verkey.Y_tilde.iter());
+        for (p, elem) in self.proofs.iter().zip(elements) {
+            if !p.verify(std::slice::from_ref(&params.g_tilde), elem, &challenge)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl Verkey {
+    /// Domain key of the verkeys of `members`, each with its proof of possession. Fails with
+    /// `PSError::InvalidProofOfPossession` for the index of a member whose proof does not verify, and if the members
+    /// are for different params or counts of messages.
+    pub fn aggregate(
+        members: &[(Verkey, ProofOfPossession)],
+        params: &Params,
+    ) -> Result<DomainVerkey, PSError> {
+        let (first, _) = members.first().ok_or_else(|| PSError::GeneralError {
+            msg: String::from("A domain needs at least one verkey"),
+        })?;
+        let mut aggregate = first.clone();
+        aggregate.X_tilde = VerkeyGroup::identity();
+        aggregate.Y_tilde = vec![VerkeyGroup::identity(); first.Y_tilde.len()];
+        for (idx, (vk, pop)) in members.iter().enumerate() {
+            if vk.supported_messages() != first.supported_messages() {
+                return Err(PSError::UnsupportedNoOfMessages {
+                    expected: first.supported_messages(),
+                    given: vk.supported_messages(),
+                });
+            }
+            if !pop.verify(vk, params)? {
+                return Err(PSError::InvalidProofOfPossession { idx });
+            }
+            aggregate.X_tilde += &vk.X_tilde;
+            for (a, y) in aggregate.Y_tilde.iter_mut().zip(vk.Y_tilde.iter()) {
+                *a += y;
+            }
+        }
+        Ok(DomainVerkey {
+            aggregate,
+            members: members.iter().map(|(vk, _)| vk.clone()).collect(),
+        })
+    }
+}
+
+impl DomainVerkey {
+    /// Index of the member whose signature `sig` is, if any
+    pub fn signer(
+        &self,
+        sig: &Signature,
+        messages: &[FieldElement],
+        params: &Params,
+    ) -> Result<Option<usize>, PSError> {
+        for (i, vk) in self.members.iter().enumerate() {
+            if sig.verify(messages.to_vec(), vk, params)? {
+                return Ok(Some(i));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Verify a signature of any member or the aggregate signature of all members
+    pub fn verify(
+        &self,
+        sig: &Signature,
+        messages: &[FieldElement],
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        Ok(sig.verify(messages.to_vec(), &self.aggregate, params)?
+            || self.signer(sig, messages, params)?.is_some())
+    }
+}
+
+impl Signature {
+    /// Aggregate the signatures of several signers on the same messages, created with `Signature::new_deterministic`
+    /// so that they have the same sigma_1. Verifies with the sum of their verkeys, e.g. `DomainVerkey::aggregate`.
+    pub fn aggregate(sigs: &[Signature]) -> Result<Signature, PSError> {
+        let first = sigs.first().ok_or_else(|| PSError::GeneralError {
+            msg: String::from("No signatures to aggregate"),
+        })?;
+        let mut sigma_2 = first.sigma_2.clone();
+        for sig in &sigs[1..] {
+            if sig.sigma_1 != first.sigma_1 {
+                return Err(PSError::GeneralError {
+                    msg: String::from("Signatures to aggregate should have the same sigma_1"),
+                });
+            }
+            sigma_2 += &sig.sigma_2;
+        }
+        Ok(Signature {
+            sigma_1: first.sigma_1.clone(),
+            sigma_2,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_signing_domain() {
+        let count_msgs = 3;
+        let params = Params::new("test".as_bytes());
+        let keys: Vec<(Sigkey, Verkey)> = (0..3)
+            .map(|_| keygen(count_msgs, &params).unwrap())
+            .collect();
+        let members: Vec<(Verkey, ProofOfPossession)> = keys
+            .iter()
+            .map(|(sk, vk)| (vk.clone(), ProofOfPossession::new(sk, vk, &params).unwrap()))
+            .collect();
+        let domain = Verkey::aggregate(&members, &params).unwrap();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+
+        // A signature of any member
+        for (i, (sk, _)) in keys.iter().enumerate() {
+            let sig = Signature::new(&msgs, sk, &params).unwrap();
+            assert!(domain.verify(&sig, &msgs, &params).unwrap());
+            assert_eq!(domain.signer(&sig, &msgs, &params).unwrap(), Some(i));
+        }
+        let (other_sk, other_vk) = keygen(count_msgs, &params).unwrap();
+        let sig = Signature::new(&msgs, &other_sk, &params).unwrap();
+        assert!(!domain.verify(&sig, &msgs, &params).unwrap());
+
+        // Aggregate signature of all members
+        let sigs: Vec<Signature> = keys
+            .iter()
+            .map(|(sk, _)| Signature::new_deterministic(&msgs, sk).unwrap())
+            .collect();
+        let sig = Signature::aggregate(&sigs).unwrap();
+        assert!(sig
+            .verify(msgs.clone(), &domain.aggregate, &params)
+            .unwrap());
+        assert!(domain.verify(&sig, &msgs, &params).unwrap());
+        assert_eq!(domain.signer(&sig, &msgs, &params).unwrap(), None);
+        assert!(Signature::aggregate(&[
+            sigs[0].clone(),
+            Signature::new(&msgs, &keys[1].0, &params).unwrap()
+        ])
+        .is_err());
+
+        // A rogue key, the verkey of a known key minus those of the others, has no proof of possession
+        let mut rogue = other_vk.clone();
+        for (vk, _) in &members {
+            rogue.X_tilde = &rogue.X_tilde - &vk.X_tilde;
+            for (r, y) in rogue.Y_tilde.iter_mut().zip(vk.Y_tilde.iter()) {
+                *r = &*r - y;
+            }
+        }
+        let pop = ProofOfPossession::new(&other_sk, &other_vk, &params).unwrap();
+        assert!(pop.verify(&other_vk, &params).unwrap());
+        assert!(!pop.verify(&rogue, &params).unwrap());
+        let mut with_rogue = members.clone();
+        with_rogue.push((rogue, pop));
+        match Verkey::aggregate(&with_rogue, &params) {
+            Err(PSError::InvalidProofOfPossession { idx: 3 }) => (),
+            r => panic!(
+                "Expected InvalidProofOfPossession but got {:?}",
+                r.map(|_| ())
+            ),
+        }
+        assert!(Verkey::aggregate(&[], &params).is_err());
+    }
+}
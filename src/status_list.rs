@@ -0,0 +1,397 @@
+// Revocation with status lists, simpler to deploy than accumulators. Each credential is signed with the id of its
+// list, `status_list_id_message`, and a status index, `status_message`, as messages at indices chosen by the issuer.
+// The issuer keeps a `StatusList`, a bitmap with the bit of each revoked index set, and periodically publishes it as a
+// `SignedStatusList`: the bitmap compressed by run-length encoding, signed with a key of the issuer for 1 message over
+// the id of the list, the epoch of publication, the count of entries and the compressed bitmap. A presentation
+// reveals the list id and the status index and the verifier checks the bit of the index with
+// `SignedStatusList::check_not_revoked`, which also checks that the list is the one of the credential and the one
+// the verifier expects and is not older than the `StatusCheck::max_age` of the verifier, so that a holder cannot
+// show a list published before the revocation or another list of the issuer.
+// Presentations do not prove non-revocation in zero knowledge: the status index is revealed, so presentations of
+// the same credential are linkable by it. A proof of non-revocation against the bitmap that hides the index is not
+// implemented. Many credentials share a list, so fetching the list does not tell the issuer which credential is
+// checked.
+// Compressed bitmap: runs of equal bytes, each as its length in 2 bytes, big endian, and the byte. The bits of index
+// i are at bit 7 - i % 8 of byte i / 8.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::signature::Signature;
+use crate::typed_message::TypedMessage;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+const STATUS_LIST_DOMAIN: &[u8] = b"PS status list : ";
+/// Bytes of a run of the compressed bitmap
+const RUN_SIZE: usize = 3;
+
+/// Bitmap of the revoked status indices, kept by the issuer
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusList {
+    pub id: String,
+    /// Count of status indices
+    pub size: usize,
+    pub bitmap: Vec<u8>,
+}
+
+/// Status list published by the issuer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedStatusList {
+    pub id: String,
+    /// Time of publication, seconds since the Unix epoch
+    pub epoch: u64,
+    pub size: usize,
+    pub compressed: Vec<u8>,
+    pub signature: Signature,
+}
+
+/// What `SignedStatusList::check_not_revoked` checks a presentation and the list against
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatusCheck {
+    /// Id of the list the verifier expects
+    pub list_id: String,
+    /// Index of the message `status_list_id_message` in the credential
+    pub list_id_position: usize,
+    /// Index of the message `status_message` in the credential
+    pub status_index_position: usize,
+    /// Current time, seconds since the Unix epoch
+    pub now: u64,
+    /// Seconds after its epoch a list is accepted for
+    pub max_age: u64,
+}
+
+/// Message holding the status index `index` in a credential
+pub fn status_message(index: u64) -> FieldElement {
+    TypedMessage::Int(index).encode()
+}
+
+/// Message holding the id of the status list of a credential
+pub fn status_list_id_message(id: &str) -> FieldElement {
+    TypedMessage::Text(id.to_string()).encode()
+}
+
+fn check_status_index(index: usize, size: usize) -> Result<(), PSError> {
+    if index >= size {
+        return Err(PSError::InvalidIndex {
+            idx: index,
+            valid: format!("[0, {})", size),
+        });
+    }
+    Ok(())
+}
+
+fn compress(bitmap: &[u8]) -> Vec<u8> {
+    let mut compressed = vec![];
+    let mut i = 0;
+    while i < bitmap.len() {
+        let byte = bitmap[i];
+        let run = bitmap[i..]
+            .iter()
+            .take(u16::MAX as usize)
+            .take_while(|b| **b == byte)
+            .count();
+        compressed.extend_from_slice(&(run as u16).to_be_bytes());
+        compressed.push(byte);
+        i += run;
+    }
+    compressed
+}
+
+/// Inverse of `compress`, failing unless the compressed bitmap is of exactly `len` bytes
+fn decompress(compressed: &[u8], len: usize) -> Result<Vec<u8>, PSError> {
+    let invalid = || PSError::DeserializationError {
+        msg: String::from("Invalid compressed status list"),
+    };
+    if !compressed.len().is_multiple_of(RUN_SIZE) {
+        return Err(invalid());
+    }
+    // Not allocated for `len` upfront as `len` comes from the untrusted list
+    let mut bitmap = vec![];
+    for run in compressed.chunks(RUN_SIZE) {
+        let count = u16::from_be_bytes([run[0], run[1]]) as usize;
+        // Checked before extending so that a short input cannot make a large bitmap
+        if count == 0 || bitmap.len() + count > len {
+            return Err(invalid());
+        }
+        bitmap.resize(bitmap.len() + count, run[2]);
+    }
+    if bitmap.len() != len {
+        return Err(invalid());
+    }
+    Ok(bitmap)
+}
+
+impl StatusList {
+    /// List of `size` status indices, none revoked
+    pub fn new(id: &str, size: usize) -> Self {
+        Self {
+            id: id.to_string(),
+            size,
+            bitmap: vec![0; size.div_ceil(8)],
+        }
+    }
+
+    pub fn revoke(&mut self, index: usize) -> Result<(), PSError> {
+        check_status_index(index, self.size)?;
+        self.bitmap[index / 8] |= 0x80 >> (index % 8);
+        Ok(())
+    }
+
+    pub fn is_revoked(&self, index: usize) -> Result<bool, PSError> {
+        check_status_index(index, self.size)?;
+        Ok(self.bitmap[index / 8] & (0x80 >> (index % 8)) != 0)
+    }
+
+    /// Publish the list at time `epoch` signed with `sigkey`, a key for 1 message
+    pub fn sign(
+        &self,
+        epoch: u64,
+        sigkey: &Sigkey,
+        params: &Params,
+    ) -> Result<SignedStatusList, PSError> {
+        let compressed = compress(&self.bitmap);
+        let msg = list_message(&self.id, epoch, self.size, &compressed);
+        Ok(SignedStatusList {
+            id: self.id.clone(),
+            epoch,
+            size: self.size,
+            compressed,
+            signature: Signature::new(&[msg], sigkey, params)?,
+        })
+    }
+}
+
+fn list_message(id: &str, epoch: u64, size: usize, compressed: &[u8]) -> FieldElement {
+    let mut bytes = STATUS_LIST_DOMAIN.to_vec();
+    bytes.extend_from_slice(&(id.len() as u64).to_be_bytes());
+    bytes.extend_from_slice(id.as_bytes());
+    bytes.extend_from_slice(&epoch.to_be_bytes());
+    bytes.extend_from_slice(&(size as u64).to_be_bytes());
+    bytes.extend_from_slice(compressed);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl SignedStatusList {
+    /// Verify the signature of the issuer with `verkey`, its key for 1 message
+    pub fn verify(&self, verkey: &Verkey, params: &Params) -> Result<bool, PSError> {
+        let msg = list_message(&self.id, self.epoch, self.size, &self.compressed);
+        self.signature.verify(vec![msg], verkey, params)
+    }
+
+    /// The list of the issuer, failing if the compressed bitmap is invalid
+    pub fn status_list(&self) -> Result<StatusList, PSError> {
+        Ok(StatusList {
+            id: self.id.clone(),
+            size: self.size,
+            bitmap: decompress(&self.compressed, self.size.div_ceil(8))?,
+        })
+    }
+
+    /// Check the credential of a presentation with the revealed messages `revealed_msgs` is not revoked in this list,
+    /// its list id and status index being the messages at the positions of `check`. Fails with
+    /// `PSError::StatusIndexNotRevealed` if either is not revealed, with `PSError::StatusListMismatch` if the list is
+    /// not the one of `check` or of the credential, with `PSError::StaleStatusList` if it is older than
+    /// `check.max_age`, with `PSError::CredentialRevoked` if the credential is revoked and with
+    /// `PSError::GeneralError` if the list does not verify with `verkey`.
+    pub fn check_not_revoked(
+        &self,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+        check: &StatusCheck,
+        verkey: &Verkey,
+        params: &Params,
+    ) -> Result<(), PSError> {
+        let status_index_position = check.status_index_position;
+        let list_id =
+            revealed_msgs
+                .get(&check.list_id_position)
+                .ok_or(PSError::StatusIndexNotRevealed {
+                    idx: check.list_id_position,
+                })?;
+        if self.id != check.list_id || *list_id != status_list_id_message(&self.id) {
+            return Err(PSError::StatusListMismatch {
+                id: self.id.clone(),
+                expected: check.list_id.clone(),
+            });
+        }
+        if self.epoch.saturating_add(check.max_age) < check.now {
+            return Err(PSError::StaleStatusList {
+                epoch: self.epoch,
+                now: check.now,
+                max_age: check.max_age,
+            });
+        }
+        let status_index = match revealed_msgs
+            .get(&status_index_position)
+            .map(TypedMessage::decode)
+        {
+            Some(Some(TypedMessage::Int(i))) => i,
+            Some(_) => {
+                return Err(PSError::GeneralError {
+                    msg: String::from("Revealed message is not a status index"),
+                })
+            }
+            None => {
+                return Err(PSError::StatusIndexNotRevealed {
+                    idx: status_index_position,
+                })
+            }
+        };
+        if !self.verify(verkey, params)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Status list does not verify"),
+            });
+        }
+        let index = usize::try_from(status_index).map_err(|_| PSError::InvalidIndex {
+            idx: usize::MAX,
+            valid: format!("[0, {})", self.size),
+        })?;
+        if self.status_list()?.is_revoked(index)? {
+            return Err(PSError::CredentialRevoked { status_index });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+    use crate::pok_sig::PoKOfSignature;
+    use std::collections::HashSet;
+
+    const LIST_ID_POSITION: usize = 1;
+    const STATUS_POSITION: usize = 2;
+
+    #[test]
+    fn test_status_list() {
+        let params = Params::new("test".as_bytes());
+        let (list_sk, list_vk) = keygen(1, &params).unwrap();
+        let (sk, vk) = keygen(4, &params).unwrap();
+        let mut list = StatusList::new("https://issuer.example/status/1", 100_000);
+        list.revoke(7).unwrap();
+        list.revoke(99_999).unwrap();
+        assert!(list.revoke(100_000).is_err());
+        assert!(list.is_revoked(7).unwrap());
+        assert!(!list.is_revoked(8).unwrap());
+
+        let signed = list.sign(1_600_000_000, &list_sk, &params).unwrap();
+        assert!(signed.verify(&list_vk, &params).unwrap());
+        // 100000 bits of which 2 set compress to few runs
+        assert!(signed.compressed.len() < 20);
+        assert_eq!(signed.status_list().unwrap(), list);
+
+        let present_for_list = |list_id: &str, status_index: u64| {
+            let mut msgs: Vec<FieldElement> = FieldElementVector::random(4).into();
+            msgs[LIST_ID_POSITION] = status_list_id_message(list_id);
+            msgs[STATUS_POSITION] = status_message(status_index);
+            let sig = Signature::new(&msgs, &sk, &params).unwrap();
+            let revealed: HashSet<usize> = vec![LIST_ID_POSITION, STATUS_POSITION]
+                .into_iter()
+                .collect();
+            let pok =
+                PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), None, revealed).unwrap();
+            let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+            let proof = pok.gen_proof(&challenge).unwrap();
+            let revealed_msgs: HashMap<usize, FieldElement> = vec![
+                (LIST_ID_POSITION, msgs[LIST_ID_POSITION].clone()),
+                (STATUS_POSITION, msgs[STATUS_POSITION].clone()),
+            ]
+            .into_iter()
+            .collect();
+            assert!(proof
+                .verify(&vk, &params, revealed_msgs.clone(), &challenge)
+                .unwrap());
+            revealed_msgs
+        };
+        let present = |status_index: u64| present_for_list(&list.id, status_index);
+        let check = StatusCheck {
+            list_id: list.id.clone(),
+            list_id_position: LIST_ID_POSITION,
+            status_index_position: STATUS_POSITION,
+            now: 1_600_000_100,
+            max_age: 3600,
+        };
+        signed
+            .check_not_revoked(&present(8), &check, &list_vk, &params)
+            .unwrap();
+        match signed.check_not_revoked(&present(7), &check, &list_vk, &params) {
+            Err(PSError::CredentialRevoked { status_index: 7 }) => (),
+            r => panic!("Expected CredentialRevoked but got {:?}", r),
+        }
+        let mut status_hidden = present(8);
+        status_hidden.remove(&STATUS_POSITION);
+        match signed.check_not_revoked(&status_hidden, &check, &list_vk, &params) {
+            Err(PSError::StatusIndexNotRevealed {
+                idx: STATUS_POSITION,
+            }) => (),
+            r => panic!("Expected StatusIndexNotRevealed but got {:?}", r),
+        }
+        let mut list_id_hidden = present(8);
+        list_id_hidden.remove(&LIST_ID_POSITION);
+        match signed.check_not_revoked(&list_id_hidden, &check, &list_vk, &params) {
+            Err(PSError::StatusIndexNotRevealed {
+                idx: LIST_ID_POSITION,
+            }) => (),
+            r => panic!("Expected StatusIndexNotRevealed but got {:?}", r),
+        }
+        assert!(signed
+            .check_not_revoked(&present(100_000), &check, &list_vk, &params)
+            .is_err());
+
+        // A list older than the maximum age, e.g. from before a revocation, is refused
+        let before_revocation = StatusList::new(&list.id, list.size)
+            .sign(1_600_000_000 - 7200, &list_sk, &params)
+            .unwrap();
+        match before_revocation.check_not_revoked(&present(7), &check, &list_vk, &params) {
+            Err(PSError::StaleStatusList { .. }) => (),
+            r => panic!("Expected StaleStatusList but got {:?}", r),
+        }
+        let mut later = check.clone();
+        later.now = 1_600_000_000 + 3601;
+        match signed.check_not_revoked(&present(8), &later, &list_vk, &params) {
+            Err(PSError::StaleStatusList { .. }) => (),
+            r => panic!("Expected StaleStatusList but got {:?}", r),
+        }
+
+        // Another list of the issuer, for the credential or the verifier, is refused
+        let other_list = StatusList::new("https://issuer.example/status/2", list.size)
+            .sign(1_600_000_000, &list_sk, &params)
+            .unwrap();
+        match other_list.check_not_revoked(&present(7), &check, &list_vk, &params) {
+            Err(PSError::StatusListMismatch { .. }) => (),
+            r => panic!("Expected StatusListMismatch but got {:?}", r),
+        }
+        let mut other_check = check.clone();
+        other_check.list_id = other_list.id.clone();
+        let of_other_list = present_for_list(&other_list.id, 7);
+        match other_list.check_not_revoked(&present(7), &other_check, &list_vk, &params) {
+            Err(PSError::StatusListMismatch { .. }) => (),
+            r => panic!("Expected StatusListMismatch but got {:?}", r),
+        }
+        match signed.check_not_revoked(&of_other_list, &check, &list_vk, &params) {
+            Err(PSError::StatusListMismatch { .. }) => (),
+            r => panic!("Expected StatusListMismatch but got {:?}", r),
+        }
+        other_list
+            .check_not_revoked(&of_other_list, &other_check, &list_vk, &params)
+            .unwrap();
+
+        // A list changed after signing or signed by another key
+        let mut tampered = signed.clone();
+        tampered.compressed = compress(&StatusList::new(&list.id, list.size).bitmap);
+        assert!(!tampered.verify(&list_vk, &params).unwrap());
+        assert!(tampered
+            .check_not_revoked(&present(7), &check, &list_vk, &params)
+            .is_err());
+        let (_, other_vk) = keygen(1, &params).unwrap();
+        assert!(!signed.verify(&other_vk, &params).unwrap());
+
+        // Invalid compressed bitmaps
+        let len = list.bitmap.len();
+        for c in &[vec![0, 1], vec![0, 0, 0], vec![0xff, 0xff, 0], vec![]] {
+            assert!(decompress(c, len).is_err());
+        }
+    }
+}
@@ -0,0 +1,346 @@
+// Storage of signing keys and credentials behind traits so that code managing them can persist its state without
+// depending on a particular database. `KeyStore` holds the keys of an issuer and `CredentialStore` the credentials
+// of a holder, each item under an id chosen by the caller. `MemoryStore` keeps them in memory, for tests and short
+// lived processes. `FileStore` keeps one file per item in a directory, keys in subdirectory `keys` and credentials in
+// `credentials`, with the byte encodings of `Sigkey`, `Verkey` and `Credential`. Files are written to a temporary
+// file first and then renamed so that a crash does not leave a truncated item. Ids are limited to ASCII letters,
+// digits, '-', '_' and '.' and cannot start with '.', so they are file names on any platform and cannot point outside
+// the directory.
+// Signing keys are stored unencrypted, `FileStore` only restricts their files to the owner on Unix.
+
+use crate::errors::PSError;
+use crate::keys::{Sigkey, Verkey};
+use crate::proof_request::Credential;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const KEYS_DIR: &str = "keys";
+const CREDENTIALS_DIR: &str = "credentials";
+
+/// Signing keys with their verkeys by id
+pub trait KeyStore {
+    fn get_key(&self, id: &str) -> Result<Option<(Sigkey, Verkey)>, PSError>;
+    /// Replaces any key stored under `id`
+    fn put_key(&mut self, id: &str, sigkey: &Sigkey, verkey: &Verkey) -> Result<(), PSError>;
+    /// Ids of the stored keys in ascending order
+    fn list_keys(&self) -> Result<Vec<String>, PSError>;
+    /// Whether a key was stored under `id`
+    fn delete_key(&mut self, id: &str) -> Result<bool, PSError>;
+}
+
+/// Credentials by id
+pub trait CredentialStore {
+    fn get_credential(&self, id: &str) -> Result<Option<Credential>, PSError>;
+    /// Replaces any credential stored under `id`
+    fn put_credential(&mut self, id: &str, credential: &Credential) -> Result<(), PSError>;
+    /// Ids of the stored credentials in ascending order
+    fn list_credentials(&self) -> Result<Vec<String>, PSError>;
+    /// Whether a credential was stored under `id`
+    fn delete_credential(&mut self, id: &str) -> Result<bool, PSError>;
+}
+
+fn check_id(id: &str) -> Result<(), PSError> {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.';
+    if id.is_empty() || id.starts_with('.') || !id.chars().all(valid_char) {
+        return Err(PSError::InvalidStoreId {
+            id: String::from(id),
+        });
+    }
+    Ok(())
+}
+
+fn storage_error(e: io::Error) -> PSError {
+    PSError::StorageError {
+        msg: format!("{}", e),
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    keys: BTreeMap<String, (Sigkey, Verkey)>,
+    credentials: BTreeMap<String, Credential>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl KeyStore for MemoryStore {
+    fn get_key(&self, id: &str) -> Result<Option<(Sigkey, Verkey)>, PSError> {
+        check_id(id)?;
+        Ok(self.keys.get(id).cloned())
+    }
+
+    fn put_key(&mut self, id: &str, sigkey: &Sigkey, verkey: &Verkey) -> Result<(), PSError> {
+        check_id(id)?;
+        self.keys
+            .insert(String::from(id), (sigkey.clone(), verkey.clone()));
+        Ok(())
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>, PSError> {
+        Ok(self.keys.keys().cloned().collect())
+    }
+
+    fn delete_key(&mut self, id: &str) -> Result<bool, PSError> {
+        check_id(id)?;
+        Ok(self.keys.remove(id).is_some())
+    }
+}
+
+impl CredentialStore for MemoryStore {
+    fn get_credential(&self, id: &str) -> Result<Option<Credential>, PSError> {
+        check_id(id)?;
+        Ok(self.credentials.get(id).cloned())
+    }
+
+    fn put_credential(&mut self, id: &str, credential: &Credential) -> Result<(), PSError> {
+        check_id(id)?;
+        self.credentials
+            .insert(String::from(id), credential.clone());
+        Ok(())
+    }
+
+    fn list_credentials(&self) -> Result<Vec<String>, PSError> {
+        Ok(self.credentials.keys().cloned().collect())
+    }
+
+    fn delete_credential(&mut self, id: &str) -> Result<bool, PSError> {
+        check_id(id)?;
+        Ok(self.credentials.remove(id).is_some())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    /// Store in directory `dir`, created along with its subdirectories if missing
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, PSError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(dir.join(KEYS_DIR)).map_err(storage_error)?;
+        fs::create_dir_all(dir.join(CREDENTIALS_DIR)).map_err(storage_error)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, subdir: &str, id: &str) -> Result<PathBuf, PSError> {
+        check_id(id)?;
+        Ok(self.dir.join(subdir).join(id))
+    }
+
+    fn read(&self, subdir: &str, id: &str) -> Result<Option<Vec<u8>>, PSError> {
+        match fs::read(self.path(subdir, id)?) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    fn write(&self, subdir: &str, id: &str, bytes: &[u8], secret: bool) -> Result<(), PSError> {
+        let path = self.path(subdir, id)?;
+        // Not a valid id so never listed
+        let temp = self.dir.join(subdir).join(format!(".{}.tmp", id));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            if secret {
+                options.mode(0o600);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = secret;
+        let mut file = options.open(&temp).map_err(storage_error)?;
+        file.write_all(bytes).map_err(storage_error)?;
+        file.sync_all().map_err(storage_error)?;
+        fs::rename(&temp, &path).map_err(storage_error)
+    }
+
+    fn list(&self, subdir: &str) -> Result<Vec<String>, PSError> {
+        let mut ids = vec![];
+        for entry in fs::read_dir(self.dir.join(subdir)).map_err(storage_error)? {
+            let name = entry.map_err(storage_error)?.file_name();
+            // Skips temporary files and files not created by the store
+            if let Some(id) = name.to_str() {
+                if check_id(id).is_ok() {
+                    ids.push(String::from(id));
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn delete(&self, subdir: &str, id: &str) -> Result<bool, PSError> {
+        match fs::remove_file(self.path(subdir, id)?) {
+            Ok(()) => Ok(true),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+}
+
+impl KeyStore for FileStore {
+    fn get_key(&self, id: &str) -> Result<Option<(Sigkey, Verkey)>, PSError> {
+        match self.read(KEYS_DIR, id)? {
+            Some(bytes) => {
+                let (sk_bytes, vk_bytes) = split_key_bytes(&bytes)?;
+                Ok(Some((
+                    Sigkey::from_bytes(sk_bytes)?,
+                    Verkey::from_bytes(vk_bytes)?,
+                )))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn put_key(&mut self, id: &str, sigkey: &Sigkey, verkey: &Verkey) -> Result<(), PSError> {
+        let mut sk_bytes = sigkey.to_bytes();
+        let mut bytes = (sk_bytes.len() as u64).to_be_bytes().to_vec();
+        bytes.append(&mut sk_bytes);
+        bytes.append(&mut verkey.to_bytes());
+        self.write(KEYS_DIR, id, &bytes, true)
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>, PSError> {
+        self.list(KEYS_DIR)
+    }
+
+    fn delete_key(&mut self, id: &str) -> Result<bool, PSError> {
+        self.delete(KEYS_DIR, id)
+    }
+}
+
+/// Splits a stored key in the encodings of the `Sigkey` and `Verkey`, the first prefixed with its length as 8 bytes
+/// big endian
+fn split_key_bytes(bytes: &[u8]) -> Result<(&[u8], &[u8]), PSError> {
+    let error = || PSError::DeserializationError {
+        msg: String::from("Stored key is truncated"),
+    };
+    if bytes.len() < 8 {
+        return Err(error());
+    }
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[..8]);
+    let sk_len = u64::from_be_bytes(buf);
+    if sk_len > (bytes.len() - 8) as u64 {
+        return Err(error());
+    }
+    Ok(bytes[8..].split_at(sk_len as usize))
+}
+
+impl CredentialStore for FileStore {
+    fn get_credential(&self, id: &str) -> Result<Option<Credential>, PSError> {
+        match self.read(CREDENTIALS_DIR, id)? {
+            Some(bytes) => Ok(Some(Credential::from_bytes(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn put_credential(&mut self, id: &str, credential: &Credential) -> Result<(), PSError> {
+        self.write(CREDENTIALS_DIR, id, &credential.to_bytes(), false)
+    }
+
+    fn list_credentials(&self) -> Result<Vec<String>, PSError> {
+        self.list(CREDENTIALS_DIR)
+    }
+
+    fn delete_credential(&mut self, id: &str) -> Result<bool, PSError> {
+        self.delete(CREDENTIALS_DIR, id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::{keygen, Params};
+    use crate::signature::Signature;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn check_store<S: KeyStore + CredentialStore>(store: &mut S) {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let (sk_1, vk_1) = keygen(5, &params).unwrap();
+        let msgs = FieldElementVector::random(3);
+        let credential = Credential {
+            signature: Signature::new(msgs.as_slice(), &sk, &params).unwrap(),
+            messages: msgs.into(),
+            verkey: vk.clone(),
+        };
+
+        assert!(store.get_key("issuer-1").unwrap().is_none());
+        store.put_key("issuer-1", &sk, &vk).unwrap();
+        store.put_key("issuer-0", &sk_1, &vk_1).unwrap();
+        let (sk_2, vk_2) = store.get_key("issuer-1").unwrap().unwrap();
+        assert_eq!(sk_2.to_bytes(), sk.to_bytes());
+        assert_eq!(vk_2, vk);
+        assert_eq!(store.list_keys().unwrap(), vec!["issuer-0", "issuer-1"]);
+        // Replacing a key
+        store.put_key("issuer-1", &sk_1, &vk_1).unwrap();
+        assert_eq!(store.get_key("issuer-1").unwrap().unwrap().1, vk_1);
+        assert!(store.delete_key("issuer-1").unwrap());
+        assert!(!store.delete_key("issuer-1").unwrap());
+        assert_eq!(store.list_keys().unwrap(), vec!["issuer-0"]);
+
+        store.put_credential("degree.2020", &credential).unwrap();
+        let credential_1 = store.get_credential("degree.2020").unwrap().unwrap();
+        assert_eq!(
+            credential_1.signature.to_bytes(),
+            credential.signature.to_bytes()
+        );
+        assert_eq!(credential_1.messages, credential.messages);
+        assert_eq!(credential_1.verkey, credential.verkey);
+        // Keys and credentials have separate ids
+        assert!(store.get_key("degree.2020").unwrap().is_none());
+        assert_eq!(store.list_credentials().unwrap(), vec!["degree.2020"]);
+        assert!(store.delete_credential("degree.2020").unwrap());
+        assert!(store.get_credential("degree.2020").unwrap().is_none());
+
+        for id in &["", ".hidden", "../issuer-0", "a/b", "key id"] {
+            match store.put_key(id, &sk, &vk) {
+                Err(PSError::InvalidStoreId { .. }) => (),
+                r => panic!("Expected InvalidStoreId but got {:?}", r),
+            }
+            assert!(store.get_credential(id).is_err());
+        }
+    }
+
+    #[test]
+    fn test_memory_store() {
+        check_store(&mut MemoryStore::new());
+    }
+
+    #[test]
+    fn test_file_store() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir =
+            std::env::temp_dir().join(format!("ps_sig_store_{}_{}", std::process::id(), nanos));
+        let mut store = FileStore::open(&dir).unwrap();
+        check_store(&mut store);
+
+        // Items persist across instances
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(2, &params).unwrap();
+        store.put_key("issuer", &sk, &vk).unwrap();
+        let store_1 = FileStore::open(&dir).unwrap();
+        assert_eq!(store_1.get_key("issuer").unwrap().unwrap().1, vk);
+        assert_eq!(store_1.list_keys().unwrap(), vec!["issuer", "issuer-0"]);
+
+        // Corrupted file
+        fs::write(dir.join(KEYS_DIR).join("issuer"), [1, 2, 3]).unwrap();
+        assert!(store_1.get_key("issuer").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+// Key generation of the redactable signature scheme for many messages without the public key in memory. A `PKrss`
+// for n messages has 3n group elements and `rsskeygen` computes all of them, along with the 2n powers of y, before
+// returning, so keys for 10^4 messages and more need a lot of memory only to be written out. `rsskeygen_streaming`
+// computes the elements in chunks of `DEFAULT_CHUNK_SIZE`, the powers of y of a chunk from the last one of the
+// previous chunk, and writes each chunk to the writer in the encoding of `PKrss::to_bytes` before computing the next,
+// so the memory used is that of a chunk whatever the count of messages. The elements of a chunk are computed in
+// parallel with rayon. On the other end `read_pkrss` reads a key from any `Read`, like a socket, a chunk at a time
+// without first reading all its bytes as `PKrss::from_bytes` has to; `lazy_pkrss::LazyPKrss` is for reading only
+// some elements of a key in a file.
+
+use crate::curve::{FieldElement, GroupElement};
+use crate::errors::PSError;
+use crate::keys::{
+    check_message_count, check_sk_msg_count, DeserializeLimits, PKrss, Params, SKrss,
+    PKRSS_ELEMENTS_OFFSET, PKRSS_HEADER_SIZE,
+};
+use crate::signature::group_elem_from_bytes;
+use crate::{SignatureGroup, VerkeyGroup, SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE};
+use rayon::prelude::*;
+use std::io::{Read, Write};
+
+/// Count of key elements computed, written or read at a time by default
+pub const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// Generate a secret key for `count_messages` messages like `rsskeygen` and write its public key to `writer` as
+/// `PKrss::to_bytes` would, `DEFAULT_CHUNK_SIZE` elements at a time. Returns the secret key.
+pub fn rsskeygen_streaming<W: Write>(
+    count_messages: usize,
+    params: &Params,
+    writer: W,
+) -> Result<SKrss, PSError> {
+    rsskeygen_streaming_with_chunk_size(count_messages, params, DEFAULT_CHUNK_SIZE, writer)
+}
+
+/// Same as `rsskeygen_streaming` with `chunk_size` elements at a time, at least 1
+pub fn rsskeygen_streaming_with_chunk_size<W: Write>(
+    count_messages: usize,
+    params: &Params,
+    chunk_size: usize,
+    writer: W,
+) -> Result<SKrss, PSError> {
+    check_message_count(count_messages)?;
+    let sk = SKrss {
+        n: count_messages,
+        x: FieldElement::random(),
+        y: FieldElement::random(),
+    };
+    sk.check_quality()?;
+    write_pkrss(&sk, count_messages, params, chunk_size, writer)?;
+    Ok(sk)
+}
+
+/// Write the public key for `count_messages` messages of `sk` to `writer`, the bytes of
+/// `sk.to_pkrss(count_messages, params)?.to_bytes()`, `chunk_size` elements at a time. Fails like `to_pkrss` unless
+/// `count_messages` is `sk.n` and with `PSError::StorageError` if writing fails.
+pub fn write_pkrss<W: Write>(
+    sk: &SKrss,
+    count_messages: usize,
+    params: &Params,
+    chunk_size: usize,
+    mut writer: W,
+) -> Result<(), PSError> {
+    check_message_count(count_messages)?;
+    check_sk_msg_count(sk, count_messages)?;
+    let chunk_size = chunk_size.max(1);
+    let mut bytes = (count_messages as u64).to_be_bytes().to_vec();
+    bytes.append(&mut params.label_hash.to_bytes());
+    bytes.append(&mut params.g.to_bytes());
+    bytes.append(&mut params.g_tilde.to_bytes());
+    bytes.append(&mut params.g_tilde.scalar_mul_variable_time(&sk.x).to_bytes());
+    writer.write_all(&bytes).map_err(storage_error)?;
+
+    // Y_tilde_1..Y_tilde_n
+    let mut y_i = sk.y.clone();
+    write_powers(
+        &mut writer,
+        |p| params.g_tilde.scalar_mul_variable_time(p).to_bytes(),
+        &sk.y,
+        &mut y_i,
+        count_messages,
+        chunk_size,
+    )?;
+    // Y_1..Y_n, then Y_{n+2}..Y_{2n} skipping y^{n+1}
+    let mut y_i = sk.y.clone();
+    write_powers(
+        &mut writer,
+        |p| params.g.scalar_mul_variable_time(p).to_bytes(),
+        &sk.y,
+        &mut y_i,
+        count_messages,
+        chunk_size,
+    )?;
+    y_i = &y_i * &sk.y;
+    write_powers(
+        &mut writer,
+        |p| params.g.scalar_mul_variable_time(p).to_bytes(),
+        &sk.y,
+        &mut y_i,
+        count_messages - 1,
+        chunk_size,
+    )?;
+    writer.flush().map_err(storage_error)
+}
+
+/// Write the bytes of `elem` of y_i, y_i * y, ... for `count` powers, leaving in `y_i` the power after the last one
+fn write_powers<F: Fn(&FieldElement) -> Vec<u8> + Sync, W: Write>(
+    writer: &mut W,
+    elem: F,
+    y: &FieldElement,
+    y_i: &mut FieldElement,
+    count: usize,
+    chunk_size: usize,
+) -> Result<(), PSError> {
+    let mut left = count;
+    while left > 0 {
+        let len = left.min(chunk_size);
+        let mut powers = Vec::with_capacity(len);
+        for _ in 0..len {
+            let next = &*y_i * y;
+            powers.push(std::mem::replace(y_i, next));
+        }
+        let elems: Vec<Vec<u8>> = powers.par_iter().map(&elem).collect();
+        writer.write_all(&elems.concat()).map_err(storage_error)?;
+        left -= len;
+    }
+    Ok(())
+}
+
+/// Read a public key written by `write_pkrss` or `PKrss::to_bytes` from `reader`, `DEFAULT_CHUNK_SIZE` elements at
+/// a time, checking the count of messages and the size of the key against `limits` before reading the elements.
+/// Reading stops at the end of the key so the reader can have more data after it.
+pub fn read_pkrss<R: Read>(mut reader: R, limits: &DeserializeLimits) -> Result<PKrss, PSError> {
+    let mut header = [0u8; PKRSS_HEADER_SIZE];
+    reader.read_exact(&mut header).map_err(io_error)?;
+    let n = limits.msg_count_from_header(&header)?;
+    let size = PKrss::serialized_size(n);
+    if size > limits.max_bytes {
+        return Err(PSError::DeserializeLimitExceeded {
+            limit: String::from("bytes"),
+            given: size,
+            max: limits.max_bytes,
+        });
+    }
+    let mut hash = [0u8; PKRSS_ELEMENTS_OFFSET - PKRSS_HEADER_SIZE];
+    reader.read_exact(&mut hash).map_err(io_error)?;
+    let params_label_hash =
+        FieldElement::from_bytes(&hash).map_err(|e| PSError::DeserializationError {
+            msg: format!("{}", e),
+        })?;
+    let g = read_elems::<SignatureGroup, _>(&mut reader, 1, SIGNATURE_GROUP_SIZE)?.remove(0);
+    let mut elems = read_elems::<VerkeyGroup, _>(&mut reader, 2, VERKEY_GROUP_SIZE)?;
+    let X_tilde = elems.remove(1);
+    let g_tilde = elems.remove(0);
+    let Y_tilde_i = read_elems(&mut reader, n, VERKEY_GROUP_SIZE)?;
+    let Y_j_1_to_n = read_elems(&mut reader, n, SIGNATURE_GROUP_SIZE)?;
+    let Y_k_nplus2_to_2n = read_elems(&mut reader, n - 1, SIGNATURE_GROUP_SIZE)?;
+    Ok(PKrss {
+        n,
+        g,
+        g_tilde,
+        Y_j_1_to_n,
+        Y_k_nplus2_to_2n,
+        X_tilde,
+        Y_tilde_i,
+        params_label_hash,
+    })
+}
+
+/// Read `count` elements of `elem_size` bytes, deserializing each chunk in parallel
+fn read_elems<G: GroupElement + Send, R: Read>(
+    reader: &mut R,
+    count: usize,
+    elem_size: usize,
+) -> Result<Vec<G>, PSError> {
+    let mut elems = Vec::with_capacity(count);
+    let mut buf = vec![0u8; count.min(DEFAULT_CHUNK_SIZE) * elem_size];
+    let mut left = count;
+    while left > 0 {
+        let len = left.min(DEFAULT_CHUNK_SIZE);
+        let chunk = &mut buf[..len * elem_size];
+        reader.read_exact(chunk).map_err(io_error)?;
+        let chunk: Vec<G> = chunk
+            .par_chunks(elem_size)
+            .map(group_elem_from_bytes)
+            .collect::<Result<_, _>>()?;
+        elems.extend(chunk);
+        left -= len;
+    }
+    Ok(elems)
+}
+
+fn storage_error(e: std::io::Error) -> PSError {
+    PSError::StorageError {
+        msg: format!("{}", e),
+    }
+}
+
+fn io_error(e: std::io::Error) -> PSError {
+    PSError::DeserializationError {
+        msg: format!("{}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::rss_signature::RSSignature;
+    use std::collections::{HashMap, HashSet};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_rsskeygen_streaming() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 10;
+        // Chunks not dividing the count of messages
+        let mut bytes = vec![];
+        let sk = rsskeygen_streaming_with_chunk_size(count_msgs, &params, 3, &mut bytes).unwrap();
+        let pk_bytes = sk.to_pkrss(count_msgs, &params).unwrap().to_bytes();
+        assert_eq!(bytes, pk_bytes);
+        let mut other = vec![];
+        write_pkrss(&sk, count_msgs, &params, 0, &mut other).unwrap();
+        assert_eq!(other, pk_bytes);
+
+        // More data after the key is not read
+        bytes.extend_from_slice(b"rest");
+        let mut reader = Cursor::new(&bytes);
+        let pk = read_pkrss(&mut reader, &DeserializeLimits::default()).unwrap();
+        assert_eq!(pk.to_bytes(), pk_bytes);
+        assert_eq!(reader.position() as usize, pk_bytes.len());
+
+        let msgs = FieldElementVector::random(count_msgs);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let disclosed: HashSet<usize> = vec![1, 4].into_iter().collect();
+        let disclosed_msgs: HashMap<usize, FieldElement> =
+            disclosed.iter().map(|i| (*i, msgs[*i].clone())).collect();
+        let derived = sig.derive(msgs.as_slice(), &disclosed, &pk).unwrap();
+        assert!(derived.verify(&disclosed_msgs, &pk).unwrap());
+
+        assert!(read_pkrss(
+            Cursor::new(&pk_bytes[..pk_bytes.len() - 1]),
+            &DeserializeLimits::default()
+        )
+        .is_err());
+        let limits = DeserializeLimits::new(count_msgs - 1, usize::MAX);
+        match read_pkrss(Cursor::new(&pk_bytes), &limits) {
+            Err(PSError::DeserializeLimitExceeded { given, .. }) => assert_eq!(given, count_msgs),
+            r => panic!("Expected DeserializeLimitExceeded but got {:?}", r),
+        }
+        let limits = DeserializeLimits::new(count_msgs, pk_bytes.len() - 1);
+        assert!(read_pkrss(Cursor::new(&pk_bytes), &limits).is_err());
+        assert!(rsskeygen_streaming(0, &params, vec![]).is_err());
+        assert!(write_pkrss(&sk, count_msgs + 1, &params, 3, vec![]).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+// Fixtures for tests of crates using this one, enabled with the `test-utils` feature. The keys are derived
+// deterministically from fixed labels so every run sees the same keys, and are cached for the life of the process
+// so that tests asking for keys of many messages pay for the scalar multiplications only once. The secret elements
+// of the key of n messages are the first n of a single sequence, so keys of fewer messages are prefixes of keys
+// of more messages and come from the cache as well. Keys of the redactable signature scheme have their own x and y
+// for each count of messages, as a secret key backs a single count, see `SKrss::to_pkrss`.
+// These keys are public knowledge, never use them outside of tests.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{check_message_count, PKrss, Params, SKrss, Sigkey, Verkey};
+use crate::pok_sig::PoKOfSignature;
+use crate::presentation::Presentation;
+use crate::proof_request::Credential;
+use crate::signature::Signature;
+use crate::VerkeyGroup;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+const LABEL: &[u8] = b"ps_sig test utils";
+
+/// Elements of the keys computed so far, see the module comment
+struct Cache {
+    y: Vec<FieldElement>,
+    Y_tilde: Vec<VerkeyGroup>,
+    /// Public key of the redactable signature scheme by count of messages
+    rss: HashMap<usize, PKrss>,
+}
+
+fn cache() -> &'static Mutex<Cache> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+    CACHE.get_or_init(|| {
+        Mutex::new(Cache {
+            y: vec![],
+            Y_tilde: vec![],
+            rss: HashMap::new(),
+        })
+    })
+}
+
+fn scalar(name: &str, index: usize) -> FieldElement {
+    let mut bytes = LABEL.to_vec();
+    bytes.extend_from_slice(name.as_bytes());
+    bytes.extend_from_slice(&(index as u64).to_be_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// The same params on every call
+pub fn params() -> Params {
+    Params::new(LABEL)
+}
+
+/// Keys for the scheme from 2016 paper, the same on every call for the same `count_messages`
+pub fn keypair(count_messages: usize) -> Result<(Sigkey, Verkey), PSError> {
+    check_message_count(count_messages)?;
+    let params = params();
+    let mut cache = cache().lock().unwrap();
+    for i in cache.y.len()..count_messages {
+        let y_i = scalar("y", i);
+        let Y_tilde_i = &params.g_tilde * &y_i;
+        cache.y.push(y_i);
+        cache.Y_tilde.push(Y_tilde_i);
+    }
+    let x = scalar("x", 0);
+    let vk = Verkey {
+        X_tilde: &params.g_tilde * &x,
+        Y_tilde: cache.Y_tilde[..count_messages].to_vec(),
+        params_label_hash: params.label_hash.clone(),
+    };
+    let sk = Sigkey {
+        x,
+        y: cache.y[..count_messages].to_vec(),
+    };
+    Ok((sk, vk))
+}
+
+/// Keys for the redactable signature scheme, the same on every call for the same `count_messages`
+pub fn rss_keypair(count_messages: usize) -> Result<(SKrss, PKrss), PSError> {
+    check_message_count(count_messages)?;
+    let sk = SKrss {
+        n: count_messages,
+        x: scalar("rss x", count_messages),
+        y: scalar("rss y", count_messages),
+    };
+    let mut cache = cache().lock().unwrap();
+    let pk = match cache.rss.get(&count_messages) {
+        Some(pk) => pk.clone(),
+        None => {
+            let pk = sk.to_pkrss(count_messages, &params())?;
+            cache.rss.insert(count_messages, pk.clone());
+            pk
+        }
+    };
+    Ok((sk, pk))
+}
+
+/// Message at `index` of the sample credentials
+pub fn message(index: usize) -> FieldElement {
+    scalar("message", index)
+}
+
+/// Credential over `message(0)..message(count_messages - 1)` signed with `keypair(count_messages)`. The randomness
+/// of the signature is derived from the messages so the credential is the same on every call.
+pub fn credential(count_messages: usize) -> Result<Credential, PSError> {
+    let (sk, vk) = keypair(count_messages)?;
+    let params = params();
+    let messages: Vec<FieldElement> = (0..count_messages).map(message).collect();
+    let u = scalar("signature", count_messages);
+    let (sigma_1, sigma_2) =
+        Signature::sign_with_sigma_1_generated_from_given_exp(&messages, &sk, &u, 0, &params.g)?;
+    Ok(Credential {
+        signature: Signature { sigma_1, sigma_2 },
+        messages,
+        verkey: vk,
+    })
+}
+
+/// Transferable presentation of `credential(count_messages)` revealing the messages at `revealed_msg_indices`,
+/// with the challenge to verify it with. Unlike the keys and credentials, the presentation is randomized and
+/// differs on every call.
+pub fn presentation(
+    count_messages: usize,
+    revealed_msg_indices: HashSet<usize>,
+) -> Result<(Presentation, FieldElement), PSError> {
+    let credential = credential(count_messages)?;
+    let params = params();
+    let pok = PoKOfSignature::init(
+        &credential.signature,
+        &credential.verkey,
+        &params,
+        credential.messages.clone(),
+        None,
+        revealed_msg_indices.clone(),
+    )?;
+    let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+    let proof = pok.gen_proof(&challenge)?;
+    let revealed_msgs: HashMap<usize, FieldElement> = revealed_msg_indices
+        .into_iter()
+        .map(|i| (i, credential.messages[i].clone()))
+        .collect();
+    Ok((
+        Presentation::new_transferable(proof, revealed_msgs),
+        challenge,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::rsskeygen;
+    use crate::presentation::PresentationPolicy;
+    use crate::rss_signature::RSSignature;
+
+    #[test]
+    fn test_keypair() {
+        let params = params();
+        let (sk, vk) = keypair(6).unwrap();
+        assert_eq!(sk.to_verkey(&params), vk);
+        assert_eq!(keypair(6).unwrap().1, vk);
+        // Smaller key after a bigger one is a prefix of it
+        let (sk_1, vk_1) = keypair(3).unwrap();
+        assert_eq!(sk_1.y[..], sk.y[..3]);
+        assert_eq!(vk_1.X_tilde, vk.X_tilde);
+        assert!(keypair(0).is_err());
+    }
+
+    #[test]
+    fn test_rss_keypair() {
+        let params = params();
+        let (sk, pk) = rss_keypair(5).unwrap();
+        assert_eq!(sk.to_pkrss(5, &params).unwrap().to_bytes(), pk.to_bytes());
+        assert_eq!(rss_keypair(5).unwrap().1.to_bytes(), pk.to_bytes());
+        let msgs = FieldElementVector::random(5);
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(sig.verify(msgs.as_slice(), &pk).unwrap());
+        // Not the key of a real keygen
+        assert_ne!(rsskeygen(5, &params).unwrap().1.to_bytes(), pk.to_bytes());
+        assert!(rss_keypair(0).is_err());
+    }
+
+    #[test]
+    fn test_rss_keypairs_of_other_counts_are_unrelated() {
+        let (sk, pk) = rss_keypair(4).unwrap();
+        let (sk_1, pk_1) = rss_keypair(2).unwrap();
+        assert_ne!(sk_1.x, sk.x);
+        assert_ne!(sk_1.y, sk.y);
+        assert_ne!(pk_1.X_tilde, pk.X_tilde);
+        assert_ne!(pk_1.Y_j_1_to_n[0], pk.Y_j_1_to_n[0]);
+    }
+
+    #[test]
+    fn test_credential() {
+        let params = params();
+        let credential_1 = credential(4).unwrap();
+        assert_eq!(credential_1.messages[2], message(2));
+        assert!(credential_1
+            .signature
+            .verify(credential_1.messages.clone(), &credential_1.verkey, &params)
+            .unwrap());
+        assert_eq!(
+            credential(4).unwrap().signature.to_bytes(),
+            credential_1.signature.to_bytes()
+        );
+    }
+
+    #[test]
+    fn test_presentation() {
+        let revealed: HashSet<usize> = vec![1, 3].into_iter().collect();
+        let (presentation, challenge) = presentation(4, revealed).unwrap();
+        assert_eq!(presentation.revealed_msgs[&3], message(3));
+        assert!(presentation
+            .verify(
+                &credential(4).unwrap().verkey,
+                &params(),
+                &challenge,
+                &[],
+                &PresentationPolicy::transferable()
+            )
+            .unwrap());
+    }
+}
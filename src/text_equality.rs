@@ -0,0 +1,183 @@
+// Proof that messages of a credential equal texts known to the verifier, e.g. that the employer is "ACME", with the
+// other messages hidden, for the common case where the full predicate machinery of `predicate_proof` is not needed.
+// A message equal to a known value is as good as revealed, so the proof is a proof of knowledge of the credential
+// revealing these messages, but they are not sent with it: the verifier encodes its expected texts with
+// `TypedMessage::Text` and verifies with them as the revealed messages, which fails unless the credential has them.
+// The challenge covers the indices of the texts and the nonce of the verifier.
+
+use crate::challenge_domain::TEXT_EQUALITY;
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::{PoKOfSignature, PoKOfSignatureProof};
+use crate::signature::Signature;
+use crate::typed_message::TypedMessage;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Proof that the messages at `indices` of a credential equal texts known to the verifier
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TextEqualityProof {
+    /// Indices of the texts in increasing order
+    pub indices: Vec<usize>,
+    pub proof: PoKOfSignatureProof,
+}
+
+/// Encoded texts by index, failing for an index given twice
+fn expected_messages(expected: &[(usize, &str)]) -> Result<BTreeMap<usize, FieldElement>, PSError> {
+    let mut msgs = BTreeMap::new();
+    for (index, text) in expected {
+        if msgs
+            .insert(*index, TypedMessage::Text(text.to_string()).encode())
+            .is_some()
+        {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} is given more than once", index),
+            });
+        }
+    }
+    Ok(msgs)
+}
+
+fn equality_challenge(
+    bytes_for_challenge: Vec<u8>,
+    indices: &[usize],
+    nonce: &[u8],
+) -> FieldElement {
+    let mut bytes = TEXT_EQUALITY.tag();
+    bytes.extend(bytes_for_challenge);
+    bytes.extend_from_slice(&(indices.len() as u64).to_be_bytes());
+    for i in indices {
+        bytes.extend_from_slice(&(*i as u64).to_be_bytes());
+    }
+    bytes.extend_from_slice(nonce);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl TextEqualityProof {
+    /// Prove that the messages of the credential `sig` on `messages` at the indices of `expected` are the texts of
+    /// `expected` encoded with `TypedMessage::Text`. Fails with `PSError::InvalidIndex` for an index out of range
+    /// and with `PSError::GeneralError` if a message is not the expected text.
+    pub fn new(
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        messages: Vec<FieldElement>,
+        expected: &[(usize, &str)],
+        nonce: &[u8],
+    ) -> Result<Self, PSError> {
+        let expected = expected_messages(expected)?;
+        for (index, msg) in &expected {
+            match messages.get(*index) {
+                None => {
+                    return Err(PSError::InvalidIndex {
+                        idx: *index,
+                        valid: format!("[0, {})", messages.len()),
+                    })
+                }
+                Some(m) if m != msg => {
+                    return Err(PSError::GeneralError {
+                        msg: format!("Message at index {} is not the expected text", index),
+                    })
+                }
+                Some(_) => (),
+            }
+        }
+        let indices: Vec<usize> = expected.keys().cloned().collect();
+        let pok = PoKOfSignature::init(
+            sig,
+            vk,
+            params,
+            messages,
+            None,
+            indices.iter().cloned().collect(),
+        )?;
+        let challenge = equality_challenge(pok.to_bytes(), &indices, nonce);
+        Ok(Self {
+            indices,
+            proof: pok.gen_proof(&challenge)?,
+        })
+    }
+
+    /// Verify that the credential has the texts of `expected` at their indices, and no other, for `nonce`
+    pub fn verify(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        expected: &[(usize, &str)],
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        let expected = expected_messages(expected)?;
+        if !expected.keys().eq(self.indices.iter()) {
+            return Ok(false);
+        }
+        let revealed: HashSet<usize> = self.indices.iter().cloned().collect();
+        let challenge = equality_challenge(
+            self.proof.get_bytes_for_challenge(revealed, vk, params),
+            &self.indices,
+            nonce,
+        );
+        let revealed_msgs: HashMap<usize, FieldElement> = expected.into_iter().collect();
+        self.proof.verify(vk, params, revealed_msgs, &challenge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_text_equality() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params).unwrap();
+        let mut msgs: Vec<FieldElement> = FieldElementVector::random(4).into();
+        msgs[1] = TypedMessage::Text(String::from("ACME")).encode();
+        msgs[3] = TypedMessage::Text(String::from("engineer")).encode();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let nonce = FieldElement::random().to_bytes();
+        let expected = [(1, "ACME"), (3, "engineer")];
+
+        let proof =
+            TextEqualityProof::new(&sig, &vk, &params, msgs.clone(), &expected, &nonce).unwrap();
+        assert_eq!(proof.indices, vec![1, 3]);
+        assert!(proof.verify(&vk, &params, &expected, &nonce).unwrap());
+        // Expected in another order
+        assert!(proof
+            .verify(&vk, &params, &[(3, "engineer"), (1, "ACME")], &nonce)
+            .unwrap());
+
+        // Other texts, indices or nonce
+        assert!(!proof
+            .verify(&vk, &params, &[(1, "Initech"), (3, "engineer")], &nonce)
+            .unwrap());
+        assert!(!proof.verify(&vk, &params, &[(1, "ACME")], &nonce).unwrap());
+        assert!(!proof
+            .verify(&vk, &params, &expected, &FieldElement::random().to_bytes())
+            .unwrap());
+
+        // The credential does not have the text
+        assert!(TextEqualityProof::new(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone(),
+            &[(1, "Initech")],
+            &nonce
+        )
+        .is_err());
+        match TextEqualityProof::new(&sig, &vk, &params, msgs.clone(), &[(4, "ACME")], &nonce) {
+            Err(PSError::InvalidIndex { idx: 4, .. }) => (),
+            r => panic!("Expected InvalidIndex but got {:?}", r.map(|_| ())),
+        }
+        assert!(TextEqualityProof::new(
+            &sig,
+            &vk,
+            &params,
+            msgs,
+            &[(1, "ACME"), (1, "ACME")],
+            &nonce
+        )
+        .is_err());
+    }
+}
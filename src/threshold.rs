@@ -0,0 +1,363 @@
+// Threshold signing for the scheme from the 2016 paper with signers of different weights, for approval policies
+// where some signers count more than others, e.g. an HSM counting as 3 board members. A dealer splits the signing key
+// with Shamir's secret sharing: x and each y_i are the values at 0 of random polynomials of degree t - 1 for the
+// threshold t, and a signer of weight w holds the values of all polynomials at w points, its shares. Signers of
+// total weight at least t hold at least t points and can sign together, signers of less weight learn nothing of the
+// key. Each signer has its own points, the points of signer s following those of signer s - 1, starting at 1.
+// Signing is as in `joint_signing`: the combiner sends a `SigningRequest` with an id and the messages, each signer
+// derives h from the request and replies a `PartialSignature` h^{x(j) + sum y_i(j)*m_i} per point j, and the combiner
+// interpolates t of them at 0 in the exponent with the Lagrange coefficients of their points. A combiner choosing h
+// would learn g^{x(j) + sum y_i(j)*m_i} for each point and could sign without any signer, so signers refuse a
+// request whose h is not derived from it and the combiner derives h again before checking and combining. The verkey
+// of each point is published with the verkey of the key so that the combiner checks every partial signature and
+// tells which signer misbehaved.
+// The dealer knows the signing key, as with `keygen`, and should erase it after sharing.
+
+use crate::curve::{scalar_from_u64, FieldElement};
+use crate::errors::PSError;
+use crate::joint_signing::{PartialSignature, SigningRequest};
+use crate::keys::{check_message_count, Params, Sigkey, Verkey};
+use crate::msm::multi_scalar_mul_var_time;
+use crate::signature::Signature;
+use crate::SignatureGroup;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Shares of a signer, its signing key at each of its points
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ThresholdKeyShare {
+    pub signer: usize,
+    shares: Vec<(u64, Sigkey)>,
+}
+
+/// Verkey of the shared key with the threshold, the weights of the signers and the verkey of each point
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ThresholdVerkey {
+    pub verkey: Verkey,
+    pub threshold: usize,
+    pub weights: Vec<usize>,
+    pub share_verkeys: BTreeMap<u64, Verkey>,
+}
+
+/// Partial signatures of a signer, one for each of its points
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignatureShare {
+    pub signer: usize,
+    pub partials: Vec<(u64, PartialSignature)>,
+}
+
+/// Points of each signer of `weights`
+fn signer_points(weights: &[usize]) -> Vec<Vec<u64>> {
+    let mut next = 1u64;
+    weights
+        .iter()
+        .map(|w| {
+            let points = (next..next + *w as u64).collect();
+            next += *w as u64;
+            points
+        })
+        .collect()
+}
+
+/// Value at `point` of the polynomial of coefficients `coefficients`, the constant one first
+fn evaluate(coefficients: &[FieldElement], point: u64) -> FieldElement {
+    let x = scalar_from_u64(point);
+    coefficients
+        .iter()
+        .rev()
+        .fold(FieldElement::zero(), |acc, c| &(&acc * &x) + c)
+}
+
+/// Lagrange coefficient at 0 of `point` for the set of points `points`
+fn lagrange_at_zero(point: u64, points: &[u64]) -> FieldElement {
+    let x = scalar_from_u64(point);
+    let mut numerator = FieldElement::one();
+    let mut denominator = FieldElement::one();
+    for p in points.iter().filter(|p| **p != point) {
+        let x_p = scalar_from_u64(*p);
+        numerator = &numerator * &x_p;
+        denominator = &denominator * &(&x_p - &x);
+    }
+    &numerator * &denominator.inverse()
+}
+
+/// Key of `count_messages` messages shared among signers of weights `weights`, signing with total weight at least
+/// `threshold`
+pub fn threshold_keygen(
+    weights: &[usize],
+    threshold: usize,
+    count_messages: usize,
+    params: &Params,
+) -> Result<(ThresholdVerkey, Vec<ThresholdKeyShare>), PSError> {
+    check_message_count(count_messages)?;
+    let total: usize = weights.iter().sum();
+    if weights.contains(&0) {
+        return Err(PSError::GeneralError {
+            msg: String::from("Weights of signers should be at least 1"),
+        });
+    }
+    if threshold == 0 || threshold > total {
+        return Err(PSError::InsufficientWeight {
+            weight: total,
+            threshold,
+        });
+    }
+    let random_polynomial =
+        || -> Vec<FieldElement> { (0..threshold).map(|_| FieldElement::random()).collect() };
+    let x_poly = random_polynomial();
+    let y_polys: Vec<Vec<FieldElement>> =
+        (0..count_messages).map(|_| random_polynomial()).collect();
+    let sigkey_at = |point: u64| Sigkey {
+        x: evaluate(&x_poly, point),
+        y: y_polys.iter().map(|p| evaluate(p, point)).collect(),
+    };
+
+    let secret = sigkey_at(0);
+    secret.check_quality()?;
+    let mut share_verkeys = BTreeMap::new();
+    let mut shares = vec![];
+    for (signer, points) in signer_points(weights).into_iter().enumerate() {
+        let mut signer_shares = vec![];
+        for point in points {
+            let sigkey = sigkey_at(point);
+            share_verkeys.insert(point, sigkey.to_verkey(params));
+            signer_shares.push((point, sigkey));
+        }
+        shares.push(ThresholdKeyShare {
+            signer,
+            shares: signer_shares,
+        });
+    }
+    let verkey = ThresholdVerkey {
+        verkey: secret.to_verkey(params),
+        threshold,
+        weights: weights.to_vec(),
+        share_verkeys,
+    };
+    Ok((verkey, shares))
+}
+
+impl ThresholdKeyShare {
+    pub fn weight(&self) -> usize {
+        self.shares.len()
+    }
+
+    /// Partial signatures on the messages of `request` with the h derived from the request. Fails with
+    /// `PSError::InvalidSigningRequest` if the request has another h.
+    pub fn partial_sign(&self, request: &SigningRequest) -> Result<SignatureShare, PSError> {
+        let h = request.checked_h()?;
+        let mut partials = Vec::with_capacity(self.shares.len());
+        for (point, sigkey) in &self.shares {
+            Signature::check_sigkey_and_messages_compat(&request.messages, sigkey)?;
+            let sigma_2 = Signature::sign_with_given_sigma_1(&request.messages, sigkey, 0, &h)?;
+            partials.push((*point, PartialSignature { sigma_2 }));
+        }
+        Ok(SignatureShare {
+            signer: self.signer,
+            partials,
+        })
+    }
+}
+
+impl ThresholdVerkey {
+    /// Whether `share` has a partial signature on `request` for each point of its signer and no other. Fails with
+    /// `PSError::InvalidSigningRequest` if the h of the request is not the one derived from it.
+    pub fn verify_share(
+        &self,
+        share: &SignatureShare,
+        request: &SigningRequest,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        let points = match signer_points(&self.weights).into_iter().nth(share.signer) {
+            Some(points) => points,
+            None => return Ok(false),
+        };
+        if share.partials.iter().map(|(p, _)| *p).ne(points) {
+            return Ok(false);
+        }
+        let h = request.checked_h()?;
+        for (point, partial) in &share.partials {
+            let sig = Signature {
+                sigma_1: h.clone(),
+                sigma_2: partial.sigma_2.clone(),
+            };
+            // A deserialized verkey can lack the verkey of a point of its weights
+            let vk = match self.share_verkeys.get(point) {
+                Some(vk) => vk,
+                None => return Ok(false),
+            };
+            if !sig.verify(request.messages.clone(), vk, params)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Signature on `request` from the shares of signers of total weight at least the threshold. Fails with
+    /// `PSError::InvalidSignatureShare` for the first share that does not verify or of a signer already seen and with
+    /// `PSError::InsufficientWeight` if the signers do not meet the threshold.
+    pub fn combine(
+        &self,
+        request: &SigningRequest,
+        shares: &[SignatureShare],
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        let mut signers = BTreeSet::new();
+        let mut partials = BTreeMap::new();
+        for share in shares {
+            if !signers.insert(share.signer) || !self.verify_share(share, request, params)? {
+                return Err(PSError::InvalidSignatureShare {
+                    signer: share.signer,
+                });
+            }
+            for (point, partial) in &share.partials {
+                partials.insert(*point, partial.sigma_2.clone());
+            }
+        }
+        if partials.len() < self.threshold {
+            return Err(PSError::InsufficientWeight {
+                weight: partials.len(),
+                threshold: self.threshold,
+            });
+        }
+        let (points, sigma_2s): (Vec<u64>, Vec<SignatureGroup>) =
+            partials.into_iter().take(self.threshold).unzip();
+        let coefficients: Vec<FieldElement> = points
+            .iter()
+            .map(|p| lagrange_at_zero(*p, &points))
+            .collect();
+        Ok(Signature {
+            sigma_1: request.checked_h()?,
+            sigma_2: multi_scalar_mul_var_time(&sigma_2s, &coefficients)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{FieldElementVector, GroupElement};
+
+    /// Sets of signers, by index
+    type SignerSets = Vec<Vec<usize>>;
+
+    #[test]
+    fn test_weighted_threshold_signing() {
+        let count_msgs = 3;
+        let params = Params::new("test".as_bytes());
+        let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        // Each distribution of weights with the subsets of signers meeting the threshold and not
+        let cases: Vec<(Vec<usize>, usize, SignerSets, SignerSets)> = vec![
+            (
+                vec![1, 1, 1],
+                2,
+                vec![vec![0, 1], vec![1, 2], vec![0, 1, 2]],
+                vec![vec![2]],
+            ),
+            (
+                vec![3, 1, 1, 1],
+                4,
+                vec![vec![0, 1], vec![0, 3], vec![0, 1, 2, 3]],
+                vec![vec![1, 2, 3], vec![0]],
+            ),
+            (
+                vec![2, 2, 5],
+                5,
+                vec![vec![2], vec![0, 1, 2]],
+                vec![vec![0, 1]],
+            ),
+            (vec![1], 1, vec![vec![0]], vec![vec![]]),
+        ];
+        for (weights, threshold, meeting, not_meeting) in cases {
+            let (vk, shares) = threshold_keygen(&weights, threshold, count_msgs, &params).unwrap();
+            assert_eq!(
+                shares.iter().map(|s| s.weight()).collect::<Vec<_>>(),
+                weights
+            );
+            let request = SigningRequest::new(b"request", msgs.clone());
+            let signature_shares: Vec<SignatureShare> = shares
+                .iter()
+                .map(|s| s.partial_sign(&request).unwrap())
+                .collect();
+            for s in &signature_shares {
+                assert!(vk.verify_share(s, &request, &params).unwrap());
+            }
+            for signers in meeting {
+                let subset: Vec<SignatureShare> = signers
+                    .iter()
+                    .map(|i| signature_shares[*i].clone())
+                    .collect();
+                let sig = vk.combine(&request, &subset, &params).unwrap();
+                assert!(sig.verify(msgs.clone(), &vk.verkey, &params).unwrap());
+            }
+            for signers in not_meeting {
+                let subset: Vec<SignatureShare> = signers
+                    .iter()
+                    .map(|i| signature_shares[*i].clone())
+                    .collect();
+                match vk.combine(&request, &subset, &params) {
+                    Err(PSError::InsufficientWeight { .. }) => (),
+                    r => panic!("Expected InsufficientWeight but got {:?}", r),
+                }
+            }
+        }
+
+        let (vk, shares) = threshold_keygen(&[3, 1, 1], 3, count_msgs, &params).unwrap();
+        let request = SigningRequest::new(b"request", msgs.clone());
+        let share_0 = shares[0].partial_sign(&request).unwrap();
+        let share_1 = shares[1].partial_sign(&request).unwrap();
+        // A share counted twice, a share with a wrong partial signature or claiming points of another signer
+        match vk.combine(&request, &[share_1.clone(), share_1.clone()], &params) {
+            Err(PSError::InvalidSignatureShare { signer: 1 }) => (),
+            r => panic!("Expected InvalidSignatureShare but got {:?}", r),
+        }
+        let mut bad = share_0.clone();
+        bad.partials[1].1.sigma_2 = SignatureGroup::random();
+        assert!(!vk.verify_share(&bad, &request, &params).unwrap());
+        match vk.combine(&request, &[bad, share_1.clone()], &params) {
+            Err(PSError::InvalidSignatureShare { signer: 0 }) => (),
+            r => panic!("Expected InvalidSignatureShare but got {:?}", r),
+        }
+        let mut relabeled = share_1.clone();
+        relabeled.signer = 2;
+        assert!(!vk.verify_share(&relabeled, &request, &params).unwrap());
+        let other_request = SigningRequest::new(b"other request", msgs.clone());
+        assert!(!vk.verify_share(&share_0, &other_request, &params).unwrap());
+
+        // A request with an h chosen by the combiner is refused by the signers and by the combination
+        let mut chosen = request.clone();
+        chosen.h = &params.g * &FieldElement::random();
+        match shares[0].partial_sign(&chosen) {
+            Err(PSError::InvalidSigningRequest) => (),
+            r => panic!("Expected InvalidSigningRequest but got {:?}", r),
+        }
+        assert!(vk.verify_share(&share_0, &chosen, &params).is_err());
+        assert!(vk
+            .combine(&chosen, &[share_0.clone(), share_1.clone()], &params)
+            .is_err());
+        let mut reused =
+            SigningRequest::new(b"request", FieldElementVector::random(count_msgs).into());
+        reused.h = request.h.clone();
+        assert!(shares[1].partial_sign(&reused).is_err());
+
+        assert!(threshold_keygen(&[1, 2], 4, count_msgs, &params).is_err());
+        assert!(threshold_keygen(&[1, 2], 0, count_msgs, &params).is_err());
+        assert!(threshold_keygen(&[1, 0], 1, count_msgs, &params).is_err());
+    }
+
+    #[test]
+    fn test_verify_share_with_missing_point_verkey() {
+        let params = Params::new("test".as_bytes());
+        let msgs: Vec<FieldElement> = FieldElementVector::random(2).into();
+        let (vk, shares) = threshold_keygen(&[2, 1], 2, 2, &params).unwrap();
+        let request = SigningRequest::new(b"request", msgs);
+        let share = shares[0].partial_sign(&request).unwrap();
+        // Weights and verkeys of points that disagree, as in a tampered serialized verkey
+        let mut tampered = vk.clone();
+        tampered.share_verkeys.remove(&2);
+        assert!(!tampered.verify_share(&share, &request, &params).unwrap());
+        match tampered.combine(&request, &[share], &params) {
+            Err(PSError::InvalidSignatureShare { signer: 0 }) => (),
+            r => panic!("Expected InvalidSignatureShare but got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,264 @@
+// Registry of the issuers a verifier trusts. A `TrustRegistry` maps issuer ids to their verkeys, the window of time
+// each verkey is trusted in and the ids of the `issuer::Schema`s the issuer may issue credentials of, and is
+// serialized with serde so that one registry can be distributed to all verifiers of an ecosystem.
+// `TrustRegistry::verify_presentation` verifies a presentation of a credential of a schema from an issuer of the
+// registry: it fails with `PSError::UntrustedIssuer` for an issuer not in the registry, with
+// `PSError::IssuerNotTrustedAt` outside the window of the issuer and with `PSError::SchemaNotAllowed` for a schema
+// not allowed for it, then verifies the presentation with the prefix of the verkey for the schema, see
+// `issuer::Issuer`. The id message of the schema, the last signed message, must be revealed so that a credential
+// of another schema of the same issuer is not accepted. An issuer with no allowed schema is trusted for none.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::issuer::Schema;
+use crate::keys::{Params, Verkey};
+use crate::presentation::{
+    verify_fresh_presentation, verify_presentation, FreshnessChallenge, Presentation,
+    PresentationPolicy, VerifiedPresentation,
+};
+use crate::validity::ValidityWindow;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Issuer of a `TrustRegistry`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrustedIssuer {
+    pub verkey: Verkey,
+    /// Times the verkey is trusted at, seconds since the Unix epoch
+    pub window: ValidityWindow,
+    /// Ids of the schemas of the credentials the issuer may issue
+    pub schemas: BTreeSet<String>,
+}
+
+/// Trusted issuers by id
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TrustRegistry {
+    issuers: BTreeMap<String, TrustedIssuer>,
+}
+
+impl TrustedIssuer {
+    pub fn new(verkey: Verkey, window: ValidityWindow, schemas: &[&str]) -> Self {
+        Self {
+            verkey,
+            window,
+            schemas: schemas.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+impl TrustRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `issuer` under `id`. Fails if `id` is already in the registry.
+    pub fn add(&mut self, id: &str, issuer: TrustedIssuer) -> Result<(), PSError> {
+        if self.issuers.contains_key(id) {
+            return Err(PSError::GeneralError {
+                msg: format!("Issuer {} is already in the registry", id),
+            });
+        }
+        self.issuers.insert(id.to_string(), issuer);
+        Ok(())
+    }
+
+    /// Replace the issuer of `id`, e.g. with a rotated verkey or other schemas, returning the previous one
+    pub fn replace(&mut self, id: &str, issuer: TrustedIssuer) -> Option<TrustedIssuer> {
+        self.issuers.insert(id.to_string(), issuer)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<TrustedIssuer> {
+        self.issuers.remove(id)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&TrustedIssuer> {
+        self.issuers.get(id)
+    }
+
+    /// Ids of the issuers in increasing order
+    pub fn ids(&self) -> Vec<&str> {
+        self.issuers.keys().map(|id| id.as_str()).collect()
+    }
+
+    /// Ids of the issuers trusted at `now` for credentials of `schema_id`, in increasing order
+    pub fn trusted_for(&self, schema_id: &str, now: u64) -> Vec<&str> {
+        self.issuers
+            .iter()
+            .filter(|(_, i)| i.window.contains(now) && i.schemas.contains(schema_id))
+            .map(|(id, _)| id.as_str())
+            .collect()
+    }
+
+    /// Verkey of issuer `id` for credentials of `schema` at `now`, the prefix of its verkey for the schema. Fails
+    /// with `PSError::UntrustedIssuer`, `PSError::IssuerNotTrustedAt` or `PSError::SchemaNotAllowed` if the issuer is
+    /// not trusted for them.
+    pub fn verkey_for(&self, id: &str, schema: &Schema, now: u64) -> Result<Verkey, PSError> {
+        let issuer = self
+            .issuers
+            .get(id)
+            .ok_or_else(|| PSError::UntrustedIssuer { id: id.to_string() })?;
+        if !issuer.window.contains(now) {
+            return Err(PSError::IssuerNotTrustedAt {
+                id: id.to_string(),
+                time: now,
+            });
+        }
+        if !issuer.schemas.contains(&schema.id) {
+            return Err(PSError::SchemaNotAllowed {
+                id: id.to_string(),
+                schema: schema.id.clone(),
+            });
+        }
+        issuer.verkey.prefix(schema.message_count())
+    }
+
+    /// Verify `presentation` of a credential of `schema` from issuer `id` at `now` like
+    /// `presentation::verify_presentation`. Fails as `verkey_for` if the issuer is not trusted for it and with
+    /// `PSError::NonRedactableMessageHidden` if the id message of the schema is not revealed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_presentation(
+        &self,
+        presentation: &Presentation,
+        id: &str,
+        schema: &Schema,
+        params: &Params,
+        challenge: &FieldElement,
+        nonce: &[u8],
+        policy: &PresentationPolicy,
+        now: u64,
+    ) -> Result<VerifiedPresentation, PSError> {
+        let verkey = self.verkey_for(id, schema, now)?;
+        check_schema_revealed(presentation, schema)?;
+        verify_presentation(presentation, &verkey, params, challenge, nonce, policy)
+    }
+
+    /// Same as `verify_presentation` for a presentation answering `freshness`, verified like
+    /// `presentation::verify_fresh_presentation`
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_fresh_presentation(
+        &self,
+        presentation: &Presentation,
+        id: &str,
+        schema: &Schema,
+        params: &Params,
+        freshness: &FreshnessChallenge,
+        policy: &PresentationPolicy,
+        now: u64,
+        max_clock_skew: u64,
+    ) -> Result<VerifiedPresentation, PSError> {
+        let verkey = self.verkey_for(id, schema, now)?;
+        check_schema_revealed(presentation, schema)?;
+        verify_fresh_presentation(
+            presentation,
+            &verkey,
+            params,
+            freshness,
+            policy,
+            now,
+            max_clock_skew,
+        )
+    }
+}
+
+/// Fails unless the id message of `schema` is revealed at its index. A presentation revealing another message there
+/// is not of a credential of `schema` and fails with `PSError::InvalidPresentation`.
+fn check_schema_revealed(presentation: &Presentation, schema: &Schema) -> Result<(), PSError> {
+    let idx = schema.message_count() - 1;
+    match presentation.revealed_msgs.get(&idx) {
+        Some(m) if *m == schema.id_message() => Ok(()),
+        Some(_) => Err(PSError::InvalidPresentation),
+        None => Err(PSError::NonRedactableMessageHidden { idx }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use crate::presentation_builder::PresentationBuilder;
+    use crate::signature::Signature;
+
+    #[test]
+    fn test_trust_registry() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params).unwrap();
+        let (_, other_vk) = keygen(4, &params).unwrap();
+        let diploma = Schema::new("diploma", &["name", "degree"]);
+        let license = Schema::new("license", &["name", "class", "expiry"]);
+        let now = 1_600_000_000;
+
+        let mut registry = TrustRegistry::new();
+        let window = ValidityWindow::new(now - 1000, now + 1000).unwrap();
+        registry
+            .add(
+                "university",
+                TrustedIssuer::new(vk.clone(), window, &["diploma"]),
+            )
+            .unwrap();
+        registry
+            .add("dmv", TrustedIssuer::new(other_vk, window, &["license"]))
+            .unwrap();
+        assert!(registry
+            .add("dmv", TrustedIssuer::new(vk.clone(), window, &[]))
+            .is_err());
+        assert_eq!(registry.ids(), vec!["dmv", "university"]);
+        assert_eq!(registry.trusted_for("diploma", now), vec!["university"]);
+        assert!(registry.trusted_for("diploma", now + 1001).is_empty());
+
+        let values = vec![FieldElement::random(), FieldElement::random()];
+        let msgs = diploma.messages(&values).unwrap();
+        let sig = Signature::new(&msgs, &sk.prefix(3).unwrap(), &params).unwrap();
+        let freshness = FreshnessChallenge::new(now);
+        let policy = PresentationPolicy::transferable();
+        let present = |disclosed: &[usize]| {
+            PresentationBuilder::new(&sig, msgs.clone(), &vk.prefix(3).unwrap(), &params)
+                .unwrap()
+                .disclose(disclosed)
+                .unwrap()
+                .bind_context(freshness.clone())
+                .unwrap()
+                .finish()
+                .unwrap()
+        };
+        let presentation = present(&[1, 2]);
+        let verify =
+            |registry: &TrustRegistry, p: &Presentation, id: &str, schema: &Schema, at: u64| {
+                registry.verify_fresh_presentation(
+                    p, id, schema, &params, &freshness, &policy, at, 10_000,
+                )
+            };
+        let verified = verify(&registry, &presentation, "university", &diploma, now).unwrap();
+        assert_eq!(verified.disclosed.get(&1), Some(&values[1]));
+
+        match verify(&registry, &presentation, "college", &diploma, now) {
+            Err(PSError::UntrustedIssuer { id }) => assert_eq!(id, "college"),
+            r => panic!("Expected UntrustedIssuer but got {:?}", r),
+        }
+        match verify(&registry, &presentation, "university", &diploma, now + 1001) {
+            Err(PSError::IssuerNotTrustedAt { time, .. }) => assert_eq!(time, now + 1001),
+            r => panic!("Expected IssuerNotTrustedAt but got {:?}", r),
+        }
+        match verify(&registry, &presentation, "university", &license, now) {
+            Err(PSError::SchemaNotAllowed { schema, .. }) => assert_eq!(schema, "license"),
+            r => panic!("Expected SchemaNotAllowed but got {:?}", r),
+        }
+        // From an issuer trusted for the schema but with another key
+        assert!(verify(&registry, &presentation, "dmv", &diploma, now).is_err());
+        // The schema id must be revealed
+        match verify(&registry, &present(&[1]), "university", &diploma, now) {
+            Err(PSError::NonRedactableMessageHidden { idx: 2 }) => (),
+            r => panic!("Expected NonRedactableMessageHidden but got {:?}", r),
+        }
+
+        // A replaced issuer is trusted for its new schemas only
+        let old = registry
+            .replace(
+                "university",
+                TrustedIssuer::new(vk.clone(), window, &["transcript"]),
+            )
+            .unwrap();
+        assert!(old.schemas.contains("diploma"));
+        assert!(verify(&registry, &presentation, "university", &diploma, now).is_err());
+        assert!(registry.remove("university").is_some());
+        assert_eq!(registry.ids(), vec!["dmv"]);
+    }
+}
@@ -0,0 +1,157 @@
+// Encoding of typed attributes as messages. Integers, dates and booleans are encoded reversibly: the field element is
+// the value in the last 8 bytes, big endian, preceded by a byte for the type, so a verifier can decode a disclosed
+// message and display it without knowing its type in advance. Dates are seconds since the Unix epoch, negative for
+// dates before it, placed as their two's complement. Text does not fit in a field element and is hashed with a domain
+// separator, so it cannot be decoded but the verifier can check a disclosed message against an expected text with
+// `TypedMessage::Text(..).encode()`. The hash of a text has a negligible chance of looking like a reversible
+// encoding, since those have all bytes but the last 9 zero.
+
+use crate::curve::{FieldElement, FieldElement_SIZE};
+
+const TAG_INT: u8 = 1;
+const TAG_DATE: u8 = 2;
+const TAG_BOOL: u8 = 3;
+/// Index of the type byte in the big endian bytes of the field element
+const TAG_INDEX: usize = FieldElement_SIZE - 9;
+const TEXT_DOMAIN: &[u8] = b"PS text message : ";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TypedMessage {
+    Int(u64),
+    /// Seconds since the Unix epoch
+    Date(i64),
+    Text(String),
+    Bool(bool),
+}
+
+impl TypedMessage {
+    pub fn encode(&self) -> FieldElement {
+        match self {
+            TypedMessage::Int(i) => encode_tagged(TAG_INT, *i),
+            TypedMessage::Date(d) => encode_tagged(TAG_DATE, *d as u64),
+            TypedMessage::Bool(b) => encode_tagged(TAG_BOOL, *b as u64),
+            TypedMessage::Text(t) => {
+                let mut bytes = TEXT_DOMAIN.to_vec();
+                bytes.extend_from_slice(t.as_bytes());
+                FieldElement::from_msg_hash(&bytes)
+            }
+        }
+    }
+
+    /// Encode each of `messages`, in order, for signing
+    pub fn encode_all(messages: &[TypedMessage]) -> Vec<FieldElement> {
+        messages.iter().map(|m| m.encode()).collect()
+    }
+
+    /// Value of a message encoded by `encode`. `None` for an encoded text, or any field element not output by
+    /// `encode`, as these are not reversible.
+    pub fn decode(elem: &FieldElement) -> Option<Self> {
+        let bytes = elem.to_bytes();
+        if bytes[..TAG_INDEX].iter().any(|b| *b != 0) {
+            return None;
+        }
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&bytes[TAG_INDEX + 1..]);
+        let value = u64::from_be_bytes(buf);
+        match bytes[TAG_INDEX] {
+            TAG_INT => Some(TypedMessage::Int(value)),
+            TAG_DATE => Some(TypedMessage::Date(value as i64)),
+            TAG_BOOL if value <= 1 => Some(TypedMessage::Bool(value == 1)),
+            _ => None,
+        }
+    }
+}
+
+fn encode_tagged(tag: u8, value: u64) -> FieldElement {
+    let mut bytes = [0u8; FieldElement_SIZE];
+    bytes[TAG_INDEX] = tag;
+    bytes[TAG_INDEX + 1..].copy_from_slice(&value.to_be_bytes());
+    FieldElement::from(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, Params};
+    use crate::pok_sig::PoKOfSignature;
+    use crate::signature::Signature;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_typed_message_encoding() {
+        let reversible = vec![
+            TypedMessage::Int(0),
+            TypedMessage::Int(42),
+            TypedMessage::Int(u64::MAX),
+            TypedMessage::Date(1_600_000_000),
+            TypedMessage::Date(-86_400),
+            TypedMessage::Date(i64::MIN),
+            TypedMessage::Bool(true),
+            TypedMessage::Bool(false),
+        ];
+        for m in &reversible {
+            assert_eq!(TypedMessage::decode(&m.encode()).as_ref(), Some(m));
+        }
+
+        // Same value with different types are different messages
+        let encodings: HashSet<Vec<u8>> = [
+            TypedMessage::Int(1),
+            TypedMessage::Date(1),
+            TypedMessage::Bool(true),
+            TypedMessage::Text(String::from("1")),
+        ]
+        .iter()
+        .map(|m| m.encode().to_bytes())
+        .collect();
+        assert_eq!(encodings.len(), 4);
+
+        let text = TypedMessage::Text(String::from("Alice"));
+        assert_eq!(
+            text.encode(),
+            TypedMessage::Text(String::from("Alice")).encode()
+        );
+        assert_eq!(TypedMessage::decode(&text.encode()), None);
+        assert_eq!(TypedMessage::decode(&FieldElement::from(5u64)), None);
+        assert_eq!(TypedMessage::decode(&FieldElement::random()), None);
+    }
+
+    #[test]
+    fn test_typed_messages_in_presentation() {
+        let params = Params::new("test".as_bytes());
+        let messages = vec![
+            TypedMessage::Text(String::from("Alice")),
+            TypedMessage::Date(631_152_000),
+            TypedMessage::Int(3),
+            TypedMessage::Bool(true),
+        ];
+        let (sk, vk) = keygen(messages.len(), &params).unwrap();
+        let msgs = TypedMessage::encode_all(&messages);
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+
+        let revealed_msg_indices: HashSet<usize> = vec![1, 3].into_iter().collect();
+        let pok = PoKOfSignature::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone(),
+            None,
+            revealed_msg_indices.clone(),
+        )
+        .unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        let revealed_msgs = revealed_msg_indices
+            .iter()
+            .map(|i| (*i, msgs[*i].clone()))
+            .collect();
+        assert!(proof.verify(&vk, &params, revealed_msgs, &chal).unwrap());
+
+        // The verifier displays the disclosed values from the field elements alone
+        for i in &revealed_msg_indices {
+            assert_eq!(
+                TypedMessage::decode(&msgs[*i]).as_ref(),
+                Some(&messages[*i])
+            );
+        }
+    }
+}
@@ -0,0 +1,214 @@
+// Keys in the layout of the PS signatures of hyperledger-ursa, behind the feature `ursa-keys`, so that deployments
+// issuing credentials with ursa can adopt the keys of this crate and the other way round. Both use amcl_wrapper for
+// BLS12-381 and its serde encoding of field and group elements, so `UrsaSigkey`, `UrsaVerkey` and `UrsaParams`
+// serialize and deserialize in ursa's layout with any serde format. The signing keys have the same fields x and
+// y_1..y_n. Ursa's verkeys have only X_tilde and the Y_tilde_i and its params only g and g_tilde, neither carries the
+// label hash of the params: an imported verkey gets the label hash of the params given by the caller and is checked
+// with `Verkey::sanity_check`, and `UrsaParams::to_params` checks that a label gives the generators of ursa's params.
+// Ursa hashes the label itself to the generators and this crate hashes the label hash, so params made by ursa do not
+// convert with any label: its signing keys convert both ways and their verkeys under these params are computed with
+// `Sigkey::to_verkey`, and params and verkeys of this crate export to ursa, which loads any generators. Keys of ursa
+// built with the other group of signatures than this build have elements of the other groups and fail to
+// deserialize.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::{SignatureGroup, VerkeyGroup};
+
+/// Signing key in the layout of ursa
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UrsaSigkey {
+    pub x: FieldElement,
+    pub y: Vec<FieldElement>,
+}
+
+/// Verkey in the layout of ursa, without the label hash of its params
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UrsaVerkey {
+    pub X_tilde: VerkeyGroup,
+    pub Y_tilde: Vec<VerkeyGroup>,
+}
+
+/// Params in the layout of ursa, without the label hash
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UrsaParams {
+    pub g: SignatureGroup,
+    pub g_tilde: VerkeyGroup,
+}
+
+impl From<&Sigkey> for UrsaSigkey {
+    fn from(sk: &Sigkey) -> Self {
+        Self {
+            x: sk.x.clone(),
+            y: sk.y.clone(),
+        }
+    }
+}
+
+impl UrsaSigkey {
+    /// The signing key, failing with `PSError::WeakKey` if it does not pass `Sigkey::check_quality`
+    pub fn to_sigkey(&self) -> Result<Sigkey, PSError> {
+        let sk = Sigkey {
+            x: self.x.clone(),
+            y: self.y.clone(),
+        };
+        sk.check_quality()?;
+        Ok(sk)
+    }
+}
+
+impl From<&Verkey> for UrsaVerkey {
+    fn from(vk: &Verkey) -> Self {
+        Self {
+            X_tilde: vk.X_tilde.clone(),
+            Y_tilde: vk.Y_tilde.clone(),
+        }
+    }
+}
+
+impl UrsaVerkey {
+    /// The verkey with the label hash of `params`, which must be the params it was generated with as the key does
+    /// not tell. Fails like `Verkey::sanity_check`.
+    pub fn to_verkey(&self, params: &Params) -> Result<Verkey, PSError> {
+        let vk = Verkey {
+            X_tilde: self.X_tilde.clone(),
+            Y_tilde: self.Y_tilde.clone(),
+            params_label_hash: params.label_hash.clone(),
+        };
+        vk.sanity_check()?;
+        Ok(vk)
+    }
+}
+
+impl From<&Params> for UrsaParams {
+    fn from(params: &Params) -> Self {
+        Self {
+            g: params.g.clone(),
+            g_tilde: params.g_tilde.clone(),
+        }
+    }
+}
+
+impl UrsaParams {
+    /// `Params::new(label)`, failing with `PSError::ParamsMismatch` if its generators are not those of these params
+    pub fn to_params(&self, label: &[u8]) -> Result<Params, PSError> {
+        let params = Params::new(label);
+        if params.g != self.g || params.g_tilde != self.g_tilde {
+            return Err(PSError::ParamsMismatch);
+        }
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{FieldElementVector, GroupElement};
+    use crate::keys::keygen;
+    use crate::signature::Signature;
+
+    #[test]
+    fn test_ursa_keys_round_trip() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let ursa_sk = UrsaSigkey::from(&sk);
+        let ursa_vk = UrsaVerkey::from(&vk);
+        let sk_1 = ursa_sk.to_sigkey().unwrap();
+        let vk_1 = ursa_vk.to_verkey(&params).unwrap();
+        assert_eq!(sk_1.to_bytes(), sk.to_bytes());
+        assert_eq!(vk_1, vk);
+        assert!(vk_1.check_params(&params).is_ok());
+
+        // Keys converted back sign and verify
+        let msgs: Vec<FieldElement> = FieldElementVector::random(3).into();
+        let sig = Signature::new(&msgs, &sk_1, &params).unwrap();
+        assert!(sig.verify(msgs, &vk_1, &params).unwrap());
+    }
+
+    #[test]
+    fn test_ursa_weak_keys_rejected() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params).unwrap();
+        let mut ursa_sk = UrsaSigkey::from(&sk);
+        ursa_sk.y[2] = ursa_sk.y[0].clone();
+        match ursa_sk.to_sigkey() {
+            Err(PSError::WeakKey { .. }) => (),
+            r => panic!("Expected WeakKey but got {:?}", r),
+        }
+        let mut ursa_vk = UrsaVerkey::from(&vk);
+        ursa_vk.X_tilde = VerkeyGroup::identity();
+        match ursa_vk.to_verkey(&params) {
+            Err(PSError::WeakKey { .. }) => (),
+            r => panic!("Expected WeakKey but got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_ursa_params() {
+        let params = Params::new("test".as_bytes());
+        let ursa_params = UrsaParams::from(&params);
+        assert_eq!(
+            ursa_params.to_params("test".as_bytes()).unwrap().label_hash,
+            params.label_hash
+        );
+        match ursa_params.to_params("other".as_bytes()) {
+            Err(PSError::ParamsMismatch) => (),
+            r => panic!("Expected ParamsMismatch but got {:?}", r),
+        }
+    }
+
+    // Keys and params in the layout of ursa as serde_json encodes them, for the signing key x = 5, y = (7, 11) and
+    // `Params::new("ursa fixture")` with signatures in G2. The fixture was written with this crate, not produced by
+    // ursa: it pins the field names and the encodings of amcl_wrapper the layout is made of, so that a change to
+    // either is noticed, but it does not show that ursa reads it.
+    #[cfg(feature = "SignatureG2")]
+    #[test]
+    fn test_ursa_layout_known_answers() {
+        const SIGKEY: &str = "{\"x\":\"000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000005\",\"y\":[\"000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000007\",\"00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000B\"]}";
+        let verkey = concat!(
+            "{\"X_tilde\":\"",
+            "1 0120251C77FEF9D9A1EEEA5CC441D607ADDA7F3FC98918139D6074E827B69DDAB4EAB3D5DF3502A194C992AEC655EF9B 1 0C0234A6E5A56B6D47A5327354144269FC6811D2C61752B67E4DEA20FC78BEDCF9752E01CD86B221ADC690CA36EF7D1E 2 13317C30F3A0D636D56A23C34FDD80B891ECBDE7C2B7D6E16B0F4B0B7E6D26CB6147ACDE629C4A23C57400D203A9FB84",
+            "\",\"Y_tilde\":[\"",
+            "1 11F160D5BD6C92D614FF1B7893521E0D498BF7CE3C972A8E69048E6FBE67FEE13D26A426BDDB964ADC24AAA9DB5DED27 1 0780192FBC50E22F2C96F9BB211CBB52AEA7CA201FC8FAEB99E517B4B1B10A6B5996CB2EED5CB9147696D20961CB2E2F 2 13317C30F3A0D636D56A23C34FDD80B891ECBDE7C2B7D6E16B0F4B0B7E6D26CB6147ACDE629C4A23C57400D203A9FB84",
+            "\",\"",
+            "1 07C5FF39AD683EDF20BDA8252CEC2E1FAF11418666EB52B87ADEB28A2968E1824D3EA84FC5BC9E6400472F49D625ADF2 1 17A2FD82937A52F29656A72C89D2E8B072488D6FD05EE1F85D7260A399378D60485AEA39CE34EF4E9798DB751B0FF786 2 13317C30F3A0D636D56A23C34FDD80B891ECBDE7C2B7D6E16B0F4B0B7E6D26CB6147ACDE629C4A23C57400D203A9FB84",
+            "\"]}",
+        );
+        let params_json = concat!(
+            "{\"g\":\"",
+            "1 19FD32F9ADBC02BF42F40C4258602B9811777476EF1D869B4C6B8D50B209B494AC50FAA229E445187A3EF6A771070D2C 1 186F7C521F8BEFAFF4B21C8A5485D4B4AE97633A78638F30443BD1DAD194B1DD7C699E72D77E2D9E765B36EC4609A848 1 10D718DB07D73F3A87164CEB3396E6B5B11BCBF4131A348ABB8EDEC1B870CC6C9253BD000F0FC3861A55E6789803FE89 1 168B40D33392E392FF45E1C0D3C00D7C3423A478561B2101AA9E1169CF5E55A3216E6714BA45B853831D75A531BD5395 2 13317C30F3A0D636D56A23C34FDD80B891ECBDE7C2B7D6E16B0F4B0B7E6D26CB6147ACDE629C4A23C57400D203A9FB84 1 000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+            "\",\"g_tilde\":\"",
+            "1 05C714A37EDCD75DF62B82362C4F998A3186297757ECAF41A0C240B91A058D06DDCD842C9ECC48689BE1726B1C94CF80 1 018D686912734A69A61F610BAE0D93488E05DEF5D59872023EDA3244512F1E074791A623681CA62B769BFCFC9E94DF30 2 13317C30F3A0D636D56A23C34FDD80B891ECBDE7C2B7D6E16B0F4B0B7E6D26CB6147ACDE629C4A23C57400D203A9FB84",
+            "\"}",
+        );
+
+        let params = Params::new("ursa fixture".as_bytes());
+        let sk = Sigkey {
+            x: FieldElement::from(5u64),
+            y: vec![FieldElement::from(7u64), FieldElement::from(11u64)],
+        };
+        assert_eq!(
+            serde_json::to_string(&UrsaSigkey::from(&sk)).unwrap(),
+            SIGKEY
+        );
+        let sk_1 = serde_json::from_str::<UrsaSigkey>(SIGKEY)
+            .unwrap()
+            .to_sigkey()
+            .unwrap();
+        assert_eq!(sk_1.x, sk.x);
+        assert_eq!(sk_1.y, sk.y);
+
+        // Elements are compared as points, their encoding is not unique
+        let ursa_vk: UrsaVerkey = serde_json::from_str(verkey).unwrap();
+        assert_eq!(ursa_vk, UrsaVerkey::from(&sk.to_verkey(&params)));
+        let vk = ursa_vk.to_verkey(&params).unwrap();
+        let msgs = vec![FieldElement::from(1u64), FieldElement::from(2u64)];
+        let sig = Signature::new(&msgs, &sk_1, &params).unwrap();
+        assert!(sig.verify(msgs, &vk, &params).unwrap());
+
+        let ursa_params: UrsaParams = serde_json::from_str(params_json).unwrap();
+        assert_eq!(ursa_params, UrsaParams::from(&params));
+        assert!(ursa_params.to_params("ursa fixture".as_bytes()).is_ok());
+    }
+}
@@ -0,0 +1,248 @@
+// Validity window of a credential as signed system attributes. The `not_before` and `not_after` timestamps
+// (seconds since the Unix epoch) are signed as messages at reserved indices, right after the link secret.
+// A verifier that wants to check the credential is valid at some time asks for both to be revealed.
+
+use crate::curve::{FieldElement, FieldElement_SIZE};
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::link_secret::LINK_SECRET_INDEX;
+use crate::pok_sig::PoKOfSignatureProof;
+use std::collections::HashMap;
+
+/// Index of the message holding the `not_before` timestamp
+pub const NOT_BEFORE_INDEX: usize = LINK_SECRET_INDEX + 1;
+/// Index of the message holding the `not_after` timestamp
+pub const NOT_AFTER_INDEX: usize = LINK_SECRET_INDEX + 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidityWindow {
+    pub not_before: u64,
+    pub not_after: u64,
+}
+
+impl ValidityWindow {
+    pub fn new(not_before: u64, not_after: u64) -> Result<Self, PSError> {
+        if not_before > not_after {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "not_before {} should not be greater than not_after {}",
+                    not_before, not_after
+                ),
+            });
+        }
+        Ok(Self {
+            not_before,
+            not_after,
+        })
+    }
+
+    pub fn contains(&self, time: u64) -> bool {
+        self.not_before <= time && time <= self.not_after
+    }
+
+    /// Place the encoded timestamps at `NOT_BEFORE_INDEX` and `NOT_AFTER_INDEX` of `messages`. The
+    /// messages at the reserved indices are overwritten, hence `messages` must be long enough.
+    pub fn set_in_messages(&self, messages: &mut [FieldElement]) -> Result<(), PSError> {
+        if messages.len() <= NOT_AFTER_INDEX {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: NOT_AFTER_INDEX + 1,
+                given: messages.len(),
+            });
+        }
+        messages[NOT_BEFORE_INDEX] = encode_timestamp(self.not_before);
+        messages[NOT_AFTER_INDEX] = encode_timestamp(self.not_after);
+        Ok(())
+    }
+
+    /// Get the validity window from the revealed messages of a presentation
+    pub fn from_revealed_msgs(
+        revealed_msgs: &HashMap<usize, FieldElement>,
+    ) -> Result<Self, PSError> {
+        match (
+            revealed_msgs.get(&NOT_BEFORE_INDEX),
+            revealed_msgs.get(&NOT_AFTER_INDEX),
+        ) {
+            (Some(nb), Some(na)) => Self::new(decode_timestamp(nb)?, decode_timestamp(na)?),
+            _ => Err(PSError::ValidityWindowNotRevealed),
+        }
+    }
+}
+
+/// Encode the timestamp as a field element. Doesn't use `FieldElement::from(u64)` as that only works
+/// for values that fit in an `isize`.
+pub fn encode_timestamp(time: u64) -> FieldElement {
+    let mut bytes = [0u8; FieldElement_SIZE];
+    bytes[FieldElement_SIZE - 8..].copy_from_slice(&time.to_be_bytes());
+    FieldElement::from(&bytes)
+}
+
+/// Inverse of `encode_timestamp`. Fails for field elements that don't fit in a u64.
+pub fn decode_timestamp(elem: &FieldElement) -> Result<u64, PSError> {
+    let bytes = elem.to_bytes();
+    // Big endian bytes, only the last 8 can be non-zero
+    let (high, low) = bytes.split_at(bytes.len() - 8);
+    if high.iter().any(|b| *b != 0) {
+        return Err(PSError::GeneralError {
+            msg: String::from("Field element is not an encoded timestamp"),
+        });
+    }
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(low);
+    Ok(u64::from_be_bytes(buf))
+}
+
+/// Checks a verifier can ask for in addition to the proof of knowledge of signature.
+#[derive(Clone, Debug, Default)]
+pub struct VerifierOptions {
+    pub valid_at: Option<u64>,
+}
+
+impl VerifierOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require the validity window to be revealed and to contain `time`
+    pub fn require_valid_at(mut self, time: u64) -> Self {
+        self.valid_at = Some(time);
+        self
+    }
+}
+
+impl PoKOfSignatureProof {
+    /// Verify the proof of knowledge of signature and the checks requested in `options`
+    pub fn verify_with_options(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+        options: &VerifierOptions,
+    ) -> Result<bool, PSError> {
+        if let Some(time) = options.valid_at {
+            let window = ValidityWindow::from_revealed_msgs(&revealed_msgs)?;
+            if !window.contains(time) {
+                return Err(PSError::OutsideValidityWindow {
+                    time,
+                    not_before: window.not_before,
+                    not_after: window.not_after,
+                });
+            }
+        }
+        self.verify(vk, params, revealed_msgs, challenge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+    use crate::pok_sig::PoKOfSignature;
+    use crate::signature::Signature;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_timestamp_encoding() {
+        for t in &[0, 1, 1_600_000_000, u64::MAX] {
+            assert_eq!(decode_timestamp(&encode_timestamp(*t)).unwrap(), *t);
+        }
+        assert!(decode_timestamp(&FieldElement::random()).is_err());
+        assert!(ValidityWindow::new(10, 9).is_err());
+    }
+
+    #[test]
+    fn test_require_valid_at() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let window = ValidityWindow::new(1_000, 2_000).unwrap();
+        let mut msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        window.set_in_messages(&mut msgs).unwrap();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(NOT_BEFORE_INDEX);
+        revealed_msg_indices.insert(NOT_AFTER_INDEX);
+        let pok = PoKOfSignature::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone(),
+            None,
+            revealed_msg_indices.clone(),
+        )
+        .unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+
+        let mut revealed_msgs = HashMap::new();
+        for i in &revealed_msg_indices {
+            revealed_msgs.insert(*i, msgs[*i].clone());
+        }
+        assert_eq!(
+            ValidityWindow::from_revealed_msgs(&revealed_msgs).unwrap(),
+            window
+        );
+
+        for t in &[1_000, 1_500, 2_000] {
+            let options = VerifierOptions::new().require_valid_at(*t);
+            assert!(proof
+                .verify_with_options(&vk, &params, revealed_msgs.clone(), &chal, &options)
+                .unwrap());
+        }
+        for t in &[999, 2_001] {
+            let options = VerifierOptions::new().require_valid_at(*t);
+            assert!(proof
+                .verify_with_options(&vk, &params, revealed_msgs.clone(), &chal, &options)
+                .is_err());
+        }
+
+        // Without the option, the window need not be checked
+        assert!(proof
+            .verify_with_options(
+                &vk,
+                &params,
+                revealed_msgs.clone(),
+                &chal,
+                &VerifierOptions::new()
+            )
+            .unwrap());
+
+        // Lying about the window fails the proof
+        let mut forged = revealed_msgs;
+        forged.insert(NOT_AFTER_INDEX, encode_timestamp(3_000));
+        let options = VerifierOptions::new().require_valid_at(2_500);
+        assert!(!proof
+            .verify_with_options(&vk, &params, forged, &chal, &options)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_validity_window_must_be_revealed() {
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let mut msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        ValidityWindow::new(1_000, 2_000)
+            .unwrap()
+            .set_in_messages(&mut msgs)
+            .unwrap();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs, None, HashSet::new()).unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        let options = VerifierOptions::new().require_valid_at(1_500);
+        assert!(proof
+            .verify_with_options(&vk, &params, HashMap::new(), &chal, &options)
+            .is_err());
+
+        // Too few messages for the reserved indices
+        let mut short = vec![FieldElement::random(); NOT_AFTER_INDEX];
+        assert!(ValidityWindow::new(1, 2)
+            .unwrap()
+            .set_in_messages(&mut short)
+            .is_err());
+    }
+}
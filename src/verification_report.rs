@@ -0,0 +1,83 @@
+// Structured result of verification for debugging interoperability issues. The `verify_detailed` methods
+// evaluate each side of the verification equations separately and report the first equation that does not
+// hold rather than a bare bool. They are slower than `verify` which shares the final exponentiation of the
+// pairings, so are meant for diagnosing failures and not for the regular verification path.
+
+use crate::curve::GT;
+
+/// Verification equation of a scheme
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Check {
+    /// sigma_1 and sigma_2 of the signature must not be the identity
+    SignatureNotIdentity,
+    /// e(sigma_1, X_tilde * Y_tilde_1^m_1 * Y_tilde_2^m_2 * ...) == e(sigma_2, g_tilde)
+    SignaturePairing,
+    /// Proof of knowledge of the hidden messages and `t` in J. The compared values are the commitment
+    /// recomputed from the responses and the one in the proof.
+    ProofOfKnowledge,
+    /// e(sigma_4, g_tilde) == e(prod_{i disclosed} Y_{n+1-i}^c_i, sigma_3) of a redacted signature
+    RedactionPairing,
+    /// Element of the RSS public key inconsistent with the others, e(Y_i, g_tilde) == e(g, Y_tilde_i) for
+    /// i <= n and e(Y_i, g_tilde) == e(Y_n, Y_tilde_{i-n}) for i >= n+2
+    KeyElement,
+    /// Element of the RSS public key which is not the previous one to the power y, e(Y_i, g_tilde) ==
+    /// e(Y_{i-1}, Y_tilde_1) for 2 <= i <= n
+    KeyPowers,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedCheck {
+    pub check: Check,
+    /// Index of the offending Y element, as in the paper, when the failure can be attributed to one
+    pub index: Option<usize>,
+    /// Hex of the side of the equation computed from the input being verified
+    pub recomputed: String,
+    /// Hex of the side of the equation it should have been equal to
+    pub expected: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationReport {
+    /// `None` if all checks passed
+    pub failure: Option<FailedCheck>,
+}
+
+impl VerificationReport {
+    pub fn valid() -> Self {
+        Self { failure: None }
+    }
+
+    pub fn failed(
+        check: Check,
+        index: Option<usize>,
+        recomputed: String,
+        expected: String,
+    ) -> Self {
+        Self {
+            failure: Some(FailedCheck {
+                check,
+                index,
+                recomputed,
+                expected,
+            }),
+        }
+    }
+
+    /// Report for pairing equation `recomputed == expected`
+    pub fn pairing_equation(
+        check: Check,
+        index: Option<usize>,
+        recomputed: GT,
+        expected: GT,
+    ) -> Self {
+        if recomputed == expected {
+            Self::valid()
+        } else {
+            Self::failed(check, index, recomputed.to_hex(), expected.to_hex())
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.failure.is_none()
+    }
+}
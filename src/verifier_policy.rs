@@ -0,0 +1,338 @@
+// Verifier policy engine. A `VerifierPolicy` lists everything a verifier requires of a presentation besides a
+// valid proof of knowledge of signature: the indices of the messages that must be revealed, the maximum age of the
+// credential, the issuers it accepts, whether the credential must be checked against a revocation list and the
+// presentation mode. `verify_presentation_with_policy` evaluates all of them and reports every violated
+// requirement rather than stopping at the first one, so that a holder can be told everything that is missing.
+// Revocation is by a revocation handle signed as a message at a known index and revealed in the presentation,
+// hence presentations of credentials subject to revocation are linkable by the handle.
+
+use crate::curve::FieldElement;
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::presentation::{Presentation, PresentationPolicy};
+use crate::validity::ValidityWindow;
+use std::collections::BTreeSet;
+
+/// Revocation list checked against the revocation handle of the credential
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RevocationRequirement {
+    /// Index of the message holding the revocation handle
+    pub handle_index: usize,
+    /// Handles of the revoked credentials
+    pub revoked: Vec<FieldElement>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VerifierPolicy {
+    /// Indices of the messages that must be revealed
+    pub required_disclosures: BTreeSet<usize>,
+    /// Maximum seconds between `not_before` of the validity window of the credential and the time of
+    /// verification. Requires the validity window to be revealed, see `validity`.
+    pub max_credential_age: Option<u64>,
+    /// Verkeys of the accepted issuers, any issuer is accepted when empty
+    pub accepted_issuers: Vec<Verkey>,
+    pub revocation: Option<RevocationRequirement>,
+    pub presentation: PresentationPolicy,
+}
+
+/// Requirement of the policy that the presentation does not meet
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PolicyViolation {
+    MissingDisclosure {
+        idx: usize,
+    },
+    ValidityWindowNotRevealed,
+    CredentialTooOld {
+        age: u64,
+        max_age: u64,
+    },
+    IssuerNotAccepted,
+    RevocationHandleNotRevealed {
+        idx: usize,
+    },
+    Revoked,
+    TransferablePresentationNotAccepted,
+    HolderKeyNotRevealed {
+        idx: usize,
+    },
+    /// The proof of knowledge of signature or the holder binding does not verify
+    InvalidProof,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyReport {
+    /// Empty if the presentation satisfies the policy
+    pub violations: Vec<PolicyViolation>,
+}
+
+impl PolicyReport {
+    pub fn is_satisfied(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl Default for VerifierPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VerifierPolicy {
+    /// Policy accepting any valid transferable presentation from any issuer
+    pub fn new() -> Self {
+        Self {
+            required_disclosures: BTreeSet::new(),
+            max_credential_age: None,
+            accepted_issuers: vec![],
+            revocation: None,
+            presentation: PresentationPolicy::transferable(),
+        }
+    }
+
+    pub fn require_disclosure(mut self, index: usize) -> Self {
+        self.required_disclosures.insert(index);
+        self
+    }
+
+    pub fn max_credential_age(mut self, seconds: u64) -> Self {
+        self.max_credential_age = Some(seconds);
+        self
+    }
+
+    pub fn accept_issuer(mut self, vk: Verkey) -> Self {
+        self.accepted_issuers.push(vk);
+        self
+    }
+
+    pub fn require_not_revoked(mut self, handle_index: usize, revoked: Vec<FieldElement>) -> Self {
+        self.revocation = Some(RevocationRequirement {
+            handle_index,
+            revoked,
+        });
+        self
+    }
+
+    pub fn presentation_policy(mut self, policy: PresentationPolicy) -> Self {
+        self.presentation = policy;
+        self
+    }
+}
+
+/// Verify `presentation` of a credential issued by `issuer` against `policy` at time `now`, seconds since the Unix
+/// epoch. `challenge` and `nonce` are as in `Presentation::verify`. Fails with an error only when the presentation
+/// cannot be verified at all, e.g. when its proof is for another count of messages than the verkey.
+pub fn verify_presentation_with_policy(
+    presentation: &Presentation,
+    issuer: &Verkey,
+    params: &Params,
+    challenge: &FieldElement,
+    nonce: &[u8],
+    policy: &VerifierPolicy,
+    now: u64,
+) -> Result<PolicyReport, PSError> {
+    let revealed_msgs = &presentation.revealed_msgs;
+    let mut violations = vec![];
+
+    for idx in &policy.required_disclosures {
+        if !revealed_msgs.contains_key(idx) {
+            violations.push(PolicyViolation::MissingDisclosure { idx: *idx });
+        }
+    }
+
+    if let Some(max_age) = policy.max_credential_age {
+        match ValidityWindow::from_revealed_msgs(revealed_msgs) {
+            Ok(window) => {
+                let age = now.saturating_sub(window.not_before);
+                if age > max_age {
+                    violations.push(PolicyViolation::CredentialTooOld { age, max_age });
+                }
+            }
+            Err(_) => violations.push(PolicyViolation::ValidityWindowNotRevealed),
+        }
+    }
+
+    if !policy.accepted_issuers.is_empty() && !policy.accepted_issuers.contains(issuer) {
+        violations.push(PolicyViolation::IssuerNotAccepted);
+    }
+
+    if let Some(revocation) = &policy.revocation {
+        match revealed_msgs.get(&revocation.handle_index) {
+            Some(handle) => {
+                if revocation.revoked.contains(handle) {
+                    violations.push(PolicyViolation::Revoked);
+                }
+            }
+            None => violations.push(PolicyViolation::RevocationHandleNotRevealed {
+                idx: revocation.handle_index,
+            }),
+        }
+    }
+
+    match presentation.verify(issuer, params, challenge, nonce, &policy.presentation) {
+        Ok(true) => (),
+        Ok(false) => violations.push(PolicyViolation::InvalidProof),
+        Err(PSError::TransferablePresentationNotAccepted) => {
+            violations.push(PolicyViolation::TransferablePresentationNotAccepted)
+        }
+        Err(PSError::HolderKeyNotRevealed { idx }) => {
+            violations.push(PolicyViolation::HolderKeyNotRevealed { idx })
+        }
+        Err(e) => return Err(e),
+    }
+
+    Ok(PolicyReport { violations })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::FieldElementVector;
+    use crate::keys::keygen;
+    use crate::pok_sig::PoKOfSignature;
+    use crate::signature::Signature;
+    use crate::validity::{NOT_AFTER_INDEX, NOT_BEFORE_INDEX};
+    use std::collections::{HashMap, HashSet};
+
+    const HANDLE_INDEX: usize = 4;
+
+    fn present(
+        revealed: &[usize],
+        sig: &Signature,
+        vk: &Verkey,
+        msgs: &[FieldElement],
+        params: &Params,
+    ) -> (Presentation, FieldElement) {
+        let revealed_msg_indices: HashSet<usize> = revealed.iter().cloned().collect();
+        let pok = PoKOfSignature::init(
+            sig,
+            vk,
+            params,
+            msgs.to_vec(),
+            None,
+            revealed_msg_indices.clone(),
+        )
+        .unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        let revealed_msgs: HashMap<usize, FieldElement> = revealed_msg_indices
+            .into_iter()
+            .map(|i| (i, msgs[i].clone()))
+            .collect();
+        (Presentation::new_transferable(proof, revealed_msgs), chal)
+    }
+
+    #[test]
+    fn test_verifier_policy() {
+        let count_msgs = 6;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let (_, other_vk) = keygen(count_msgs, &params).unwrap();
+        let mut msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        ValidityWindow::new(1_000, 10_000)
+            .unwrap()
+            .set_in_messages(&mut msgs)
+            .unwrap();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let (presentation, chal) = present(
+            &[NOT_BEFORE_INDEX, NOT_AFTER_INDEX, HANDLE_INDEX],
+            &sig,
+            &vk,
+            &msgs,
+            &params,
+        );
+        let nonce = b"nonce";
+
+        let policy = VerifierPolicy::new()
+            .require_disclosure(HANDLE_INDEX)
+            .max_credential_age(5_000)
+            .accept_issuer(other_vk.clone())
+            .accept_issuer(vk.clone())
+            .require_not_revoked(HANDLE_INDEX, vec![FieldElement::random()]);
+        let report = verify_presentation_with_policy(
+            &presentation,
+            &vk,
+            &params,
+            &chal,
+            nonce,
+            &policy,
+            3_000,
+        )
+        .unwrap();
+        assert!(report.is_satisfied());
+
+        // Everything wrong at once is reported at once
+        let strict = VerifierPolicy::new()
+            .require_disclosure(3)
+            .require_disclosure(5)
+            .max_credential_age(1_000)
+            .accept_issuer(other_vk)
+            .require_not_revoked(HANDLE_INDEX, vec![msgs[HANDLE_INDEX].clone()])
+            .presentation_policy(PresentationPolicy::non_transferable(3));
+        let report = verify_presentation_with_policy(
+            &presentation,
+            &vk,
+            &params,
+            &chal,
+            nonce,
+            &strict,
+            3_000,
+        )
+        .unwrap();
+        assert_eq!(
+            report.violations,
+            vec![
+                PolicyViolation::MissingDisclosure { idx: 3 },
+                PolicyViolation::MissingDisclosure { idx: 5 },
+                PolicyViolation::CredentialTooOld {
+                    age: 2_000,
+                    max_age: 1_000
+                },
+                PolicyViolation::IssuerNotAccepted,
+                PolicyViolation::Revoked,
+                PolicyViolation::TransferablePresentationNotAccepted,
+            ]
+        );
+
+        // Revocation handle and validity window not revealed
+        let policy_1 = VerifierPolicy::new()
+            .max_credential_age(5_000)
+            .require_not_revoked(HANDLE_INDEX, vec![]);
+        let report = verify_presentation_with_policy(
+            &presentation,
+            &vk,
+            &params,
+            &chal,
+            nonce,
+            &policy_1,
+            3_000,
+        )
+        .unwrap();
+        assert!(report.is_satisfied());
+        let (hiding, chal_1) = present(&[NOT_AFTER_INDEX], &sig, &vk, &msgs, &params);
+        let report = verify_presentation_with_policy(
+            &hiding, &vk, &params, &chal_1, nonce, &policy_1, 3_000,
+        )
+        .unwrap();
+        assert_eq!(
+            report.violations,
+            vec![
+                PolicyViolation::ValidityWindowNotRevealed,
+                PolicyViolation::RevocationHandleNotRevealed { idx: HANDLE_INDEX },
+            ]
+        );
+        // Proof with the wrong challenge
+        let report =
+            verify_presentation_with_policy(&hiding, &vk, &params, &chal, nonce, &policy_1, 3_000)
+                .unwrap();
+        assert!(report.violations.contains(&PolicyViolation::InvalidProof));
+
+        // Revealed messages not matching the proof cannot be verified at all
+        let mut tampered = presentation.clone();
+        tampered.revealed_msgs.remove(&HANDLE_INDEX);
+        assert!(verify_presentation_with_policy(
+            &tampered, &vk, &params, &chal, nonce, &policy_1, 3_000
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,716 @@
+// Canonical binary format of the crate, for exchanging keys, signatures, proofs and credentials with other
+// implementations without relying on the derived serde encodings or on lengths implied by the size of the input.
+// A message is the format version as 1 byte || the tag of its type as 1 byte || the length of the body as 8 bytes
+// big endian || the body. The body has the fields of the type in the order of its definition:
+// - field elements and group elements at their fixed sizes, as their `to_bytes`
+// - integers as 8 bytes big endian and booleans as 1 byte, 0 or 1
+// - strings and byte strings as their length as 8 bytes big endian || their bytes
+// - sequences as their count of elements as 8 bytes big endian || the elements, maps of indices as sequences of
+//   (index, value) and sets of indices as sequences of indices, both in strictly increasing order of index
+// Decoding rejects input with bytes after the message, bytes after the body, counts of elements more than the
+// remaining bytes, maps or sets not in strictly increasing order and elements which do not encode back to the same
+// bytes, so each value has exactly one encoding. Counts of messages of keys and credentials are checked against
+// `DeserializeLimits` before their elements are read.
+
+use crate::blind_signature::{BlindIssuanceRequest, BlindingKey};
+use crate::curve::{
+    FieldElement, FieldElementVector, FieldElement_SIZE, GroupElement, GroupG1_SIZE, GroupG2_SIZE,
+    G1, G2,
+};
+use crate::errors::PSError;
+use crate::keys::{
+    DeserializeLimits, PKrss, PKrssFields, Params, ParamsFields, SKrss, Sigkey, Verkey,
+};
+use crate::pok_sig::{PoKOfSignatureProof, ProofSignatureGroup, ProofVerkeyGroup};
+use crate::proof_request::{Credential, ProofResponse};
+use crate::rss_signature::{RSSignature, RedactedSignature};
+use crate::signature::{group_elem_from_bytes, Signature};
+use crate::signing_domain::ProofOfPossession;
+use crate::status_list::SignedStatusList;
+use crate::text_equality::TextEqualityProof;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+pub const WIRE_VERSION: u8 = 1;
+/// Version, tag and length of the body
+const HEADER_SIZE: usize = 10;
+
+/// Values with an encoding in the canonical format, fields of messages
+pub trait WireEncode: Sized {
+    fn encode(&self, w: &mut WireWriter);
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError>;
+}
+
+/// Types exchanged as messages of the canonical format
+pub trait WireMessage: WireEncode {
+    /// Tag of the type in the header
+    const TAG: u8;
+
+    fn to_wire_bytes(&self) -> Vec<u8> {
+        let mut body = WireWriter::default();
+        self.encode(&mut body);
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + body.bytes.len());
+        bytes.push(WIRE_VERSION);
+        bytes.push(Self::TAG);
+        bytes.extend_from_slice(&(body.bytes.len() as u64).to_be_bytes());
+        bytes.append(&mut body.bytes);
+        bytes
+    }
+
+    fn from_wire_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        Self::from_wire_bytes_with_limits(bytes, &DeserializeLimits::default())
+    }
+
+    fn from_wire_bytes_with_limits(
+        bytes: &[u8],
+        limits: &DeserializeLimits,
+    ) -> Result<Self, PSError> {
+        limits.check_bytes(bytes)?;
+        let mut header = WireReader::new(bytes, limits);
+        let version = header.take(1)?[0];
+        if version != WIRE_VERSION {
+            return Err(invalid(format!("Unsupported format version {}", version)));
+        }
+        let tag = header.take(1)?[0];
+        if tag != Self::TAG {
+            return Err(invalid(format!(
+                "Expected a message of tag {} but found {}",
+                Self::TAG,
+                tag
+            )));
+        }
+        let len = header.length()?;
+        let body = header.take(len)?;
+        header.finish()?;
+        let mut r = WireReader::new(body, limits);
+        let value = Self::decode(&mut r)?;
+        r.finish()?;
+        Ok(value)
+    }
+}
+
+fn invalid(msg: String) -> PSError {
+    PSError::DeserializationError { msg }
+}
+
+/// Body of a message being encoded
+#[derive(Default)]
+pub struct WireWriter {
+    bytes: Vec<u8>,
+}
+
+impl WireWriter {
+    pub fn put_u64(&mut self, n: u64) {
+        self.bytes.extend_from_slice(&n.to_be_bytes());
+    }
+
+    pub fn put_bool(&mut self, b: bool) {
+        self.bytes.push(b as u8);
+    }
+
+    pub fn put_bytes(&mut self, b: &[u8]) {
+        self.put_u64(b.len() as u64);
+        self.bytes.extend_from_slice(b);
+    }
+
+    pub fn put_str(&mut self, s: &str) {
+        self.put_bytes(s.as_bytes());
+    }
+
+    pub fn put<T: WireEncode>(&mut self, value: &T) {
+        value.encode(self);
+    }
+
+    /// Count of elements || elements
+    pub fn put_seq<'a, T: WireEncode + 'a>(
+        &mut self,
+        count: usize,
+        values: impl IntoIterator<Item = &'a T>,
+    ) {
+        self.put_u64(count as u64);
+        for v in values {
+            v.encode(self);
+        }
+    }
+}
+
+/// Body of a message being decoded
+pub struct WireReader<'a> {
+    bytes: &'a [u8],
+    limits: &'a DeserializeLimits,
+}
+
+impl<'a> WireReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8], limits: &'a DeserializeLimits) -> Self {
+        Self { bytes, limits }
+    }
+
+    pub fn limits(&self) -> &DeserializeLimits {
+        self.limits
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], PSError> {
+        if self.bytes.len() < len {
+            return Err(invalid(format!(
+                "Needs {} more bytes but has {}",
+                len,
+                self.bytes.len()
+            )));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    pub fn u64(&mut self) -> Result<u64, PSError> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_be_bytes(buf))
+    }
+
+    pub fn usize(&mut self) -> Result<usize, PSError> {
+        let n = self.u64()?;
+        usize::try_from(n).map_err(|_| invalid(format!("{} is too large", n)))
+    }
+
+    pub fn bool(&mut self) -> Result<bool, PSError> {
+        match self.take(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(invalid(format!("Invalid boolean {}", b))),
+        }
+    }
+
+    /// Length of bytes or count of elements, failing unless that many bytes remain as each element is at least 1
+    /// byte, so that a short input cannot make a large allocation
+    pub fn length(&mut self) -> Result<usize, PSError> {
+        let len = self.usize()?;
+        if len > self.bytes.len() {
+            return Err(invalid(format!(
+                "Length {} is more than the {} remaining bytes",
+                len,
+                self.bytes.len()
+            )));
+        }
+        Ok(len)
+    }
+
+    pub fn bytes(&mut self) -> Result<Vec<u8>, PSError> {
+        let len = self.length()?;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    pub fn string(&mut self) -> Result<String, PSError> {
+        String::from_utf8(self.bytes()?).map_err(|e| invalid(format!("{}", e)))
+    }
+
+    pub fn get<T: WireEncode>(&mut self) -> Result<T, PSError> {
+        T::decode(self)
+    }
+
+    pub fn seq<T: WireEncode>(&mut self) -> Result<Vec<T>, PSError> {
+        let count = self.length()?;
+        self.elements(count)
+    }
+
+    /// Sequence of the elements of a key or credential of as many messages, its count checked against the limits
+    pub fn message_seq<T: WireEncode>(&mut self) -> Result<Vec<T>, PSError> {
+        let count = self.length()?;
+        self.limits.check_messages(count)?;
+        self.elements(count)
+    }
+
+    fn elements<T: WireEncode>(&mut self, count: usize) -> Result<Vec<T>, PSError> {
+        // Not allocated for `count` upfront as elements can be larger than 1 byte
+        let mut values = vec![];
+        for _ in 0..count {
+            values.push(T::decode(self)?);
+        }
+        Ok(values)
+    }
+
+    pub(crate) fn finish(&self) -> Result<(), PSError> {
+        if !self.bytes.is_empty() {
+            return Err(invalid(format!("{} trailing bytes", self.bytes.len())));
+        }
+        Ok(())
+    }
+}
+
+impl WireEncode for u64 {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put_u64(*self);
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        r.u64()
+    }
+}
+
+impl WireEncode for usize {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put_u64(*self as u64);
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        r.usize()
+    }
+}
+
+impl WireEncode for FieldElement {
+    fn encode(&self, w: &mut WireWriter) {
+        w.bytes.append(&mut self.to_bytes());
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        let bytes = r.take(FieldElement_SIZE)?;
+        let elem = FieldElement::from_bytes(bytes).map_err(|e| invalid(format!("{}", e)))?;
+        // Values of at least the order of the group are reduced by `from_bytes`
+        if elem.to_bytes().as_slice() != bytes {
+            return Err(invalid(String::from("Field element is not reduced")));
+        }
+        Ok(elem)
+    }
+}
+
+macro_rules! impl_wire_group_element {
+    ( $group_element:ident, $size:ident ) => {
+        impl WireEncode for $group_element {
+            fn encode(&self, w: &mut WireWriter) {
+                w.bytes.append(&mut self.to_bytes());
+            }
+
+            fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+                let bytes = r.take($size)?;
+                let elem = group_elem_from_bytes::<$group_element>(bytes)?;
+                if elem.to_bytes().as_slice() != bytes {
+                    return Err(invalid(String::from(
+                        "Group element is not in its canonical encoding",
+                    )));
+                }
+                Ok(elem)
+            }
+        }
+    };
+}
+
+impl_wire_group_element!(G1, GroupG1_SIZE);
+impl_wire_group_element!(G2, GroupG2_SIZE);
+
+impl<T: WireEncode> WireEncode for Vec<T> {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put_seq(self.len(), self);
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        r.seq()
+    }
+}
+
+impl WireEncode for FieldElementVector {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put_seq(self.len(), self.iter());
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        Ok(r.seq::<FieldElement>()?.into())
+    }
+}
+
+/// Indices in strictly increasing order
+fn check_increasing<'a>(indices: impl Iterator<Item = &'a usize>) -> Result<(), PSError> {
+    let mut last = None;
+    for i in indices {
+        if last.is_some_and(|l| l >= *i) {
+            return Err(invalid(String::from(
+                "Indices are not in strictly increasing order",
+            )));
+        }
+        last = Some(*i);
+    }
+    Ok(())
+}
+
+impl WireEncode for HashSet<usize> {
+    fn encode(&self, w: &mut WireWriter) {
+        let mut indices: Vec<&usize> = self.iter().collect();
+        indices.sort();
+        w.put_seq(indices.len(), indices);
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        let indices: Vec<usize> = r.seq()?;
+        check_increasing(indices.iter())?;
+        Ok(indices.into_iter().collect())
+    }
+}
+
+impl WireEncode for HashMap<usize, FieldElement> {
+    fn encode(&self, w: &mut WireWriter) {
+        let mut entries: Vec<(&usize, &FieldElement)> = self.iter().collect();
+        entries.sort_by_key(|(i, _)| **i);
+        w.put_u64(entries.len() as u64);
+        for (i, m) in entries {
+            w.put(i);
+            w.put(m);
+        }
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        let count = r.length()?;
+        let mut entries = vec![];
+        for _ in 0..count {
+            entries.push((r.usize()?, r.get::<FieldElement>()?));
+        }
+        check_increasing(entries.iter().map(|(i, _)| i))?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+/// `WireEncode` encoding the fields in order and `WireMessage` with the tag, for structs whose fields are all
+/// `WireEncode` and which need no check after decoding
+macro_rules! impl_wire_message {
+    ( $type:ident, $tag:expr, $( $field:ident ),+ ) => {
+        impl WireEncode for $type {
+            fn encode(&self, w: &mut WireWriter) {
+                $( w.put(&self.$field); )+
+            }
+
+            fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+                Ok(Self {
+                    $( $field: r.get()?, )+
+                })
+            }
+        }
+
+        impl WireMessage for $type {
+            const TAG: u8 = $tag;
+        }
+    };
+}
+
+impl_wire_message!(Signature, 4, sigma_1, sigma_2);
+impl_wire_message!(RSSignature, 7, sigma_1, sigma_2);
+impl_wire_message!(RedactedSignature, 8, sigma_1, sigma_2, sigma_3, sigma_4);
+impl_wire_message!(PoKOfSignatureProof, 9, sig, J, proof_vc);
+impl_wire_message!(ProofResponse, 11, proof, revealed_msgs);
+impl_wire_message!(ProofSignatureGroup, 13, commitment, responses);
+impl_wire_message!(ProofVerkeyGroup, 14, commitment, responses);
+impl_wire_message!(BlindIssuanceRequest, 15, commitment, proof, public_messages);
+impl_wire_message!(ProofOfPossession, 17, proofs);
+impl_wire_message!(TextEqualityProof, 18, indices, proof);
+
+impl WireEncode for Params {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put(&self.g);
+        w.put(&self.g_tilde);
+        w.put(&self.label_hash);
+    }
+
+    /// Fails unless the generators are those of the label hash, as deserializing with serde
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        Params::try_from(ParamsFields {
+            g: r.get()?,
+            g_tilde: r.get()?,
+            label_hash: r.get()?,
+        })
+    }
+}
+
+impl WireMessage for Params {
+    const TAG: u8 = 1;
+}
+
+impl WireEncode for Sigkey {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put(&self.x);
+        w.put(&self.y);
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        Ok(Self {
+            x: r.get()?,
+            y: r.message_seq()?,
+        })
+    }
+}
+
+impl WireMessage for Sigkey {
+    const TAG: u8 = 2;
+}
+
+impl WireEncode for Verkey {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put(&self.X_tilde);
+        w.put(&self.Y_tilde);
+        w.put(&self.params_label_hash);
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        Ok(Self {
+            X_tilde: r.get()?,
+            Y_tilde: r.message_seq()?,
+            params_label_hash: r.get()?,
+        })
+    }
+}
+
+impl WireMessage for Verkey {
+    const TAG: u8 = 3;
+}
+
+impl WireEncode for SKrss {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put(&self.n);
+        w.put(&self.x);
+        w.put(&self.y);
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        let n = r.usize()?;
+        r.limits().check_messages(n)?;
+        Ok(Self {
+            n,
+            x: r.get()?,
+            y: r.get()?,
+        })
+    }
+}
+
+impl WireMessage for SKrss {
+    const TAG: u8 = 5;
+}
+
+impl WireEncode for PKrss {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put(&self.n);
+        w.put(&self.g);
+        w.put(&self.g_tilde);
+        w.put(&self.Y_j_1_to_n);
+        w.put(&self.Y_k_nplus2_to_2n);
+        w.put(&self.X_tilde);
+        w.put(&self.Y_tilde_i);
+        w.put(&self.params_label_hash);
+    }
+
+    /// Fails unless the counts of elements agree with n, as deserializing with serde
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        let n = r.usize()?;
+        r.limits().check_messages(n)?;
+        PKrss::try_from(PKrssFields {
+            n,
+            g: r.get()?,
+            g_tilde: r.get()?,
+            Y_j_1_to_n: r.message_seq()?,
+            Y_k_nplus2_to_2n: r.message_seq()?,
+            X_tilde: r.get()?,
+            Y_tilde_i: r.message_seq()?,
+            params_label_hash: r.get()?,
+        })
+    }
+}
+
+impl WireMessage for PKrss {
+    const TAG: u8 = 6;
+}
+
+impl WireEncode for Credential {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put(&self.signature);
+        w.put(&self.messages);
+        w.put(&self.verkey);
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        Ok(Self {
+            signature: r.get()?,
+            messages: r.message_seq()?,
+            verkey: r.get()?,
+        })
+    }
+}
+
+impl WireMessage for Credential {
+    const TAG: u8 = 10;
+}
+
+impl WireEncode for BlindingKey {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put(&self.X);
+        w.put(&self.Y);
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        Ok(Self {
+            X: r.get()?,
+            Y: r.message_seq()?,
+        })
+    }
+}
+
+impl WireMessage for BlindingKey {
+    const TAG: u8 = 12;
+}
+
+impl WireEncode for SignedStatusList {
+    fn encode(&self, w: &mut WireWriter) {
+        w.put_str(&self.id);
+        w.put(&self.epoch);
+        w.put(&self.size);
+        w.put_bytes(&self.compressed);
+        w.put(&self.signature);
+    }
+
+    fn decode(r: &mut WireReader) -> Result<Self, PSError> {
+        Ok(Self {
+            id: r.string()?,
+            epoch: r.u64()?,
+            size: r.usize()?,
+            compressed: r.bytes()?,
+            signature: r.get()?,
+        })
+    }
+}
+
+impl WireMessage for SignedStatusList {
+    const TAG: u8 = 16;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, rsskeygen};
+    use crate::pok_sig::{PoKOfSignature, ProverCommittingSignatureGroup};
+    use crate::signature::fuzzed_bytes;
+    use crate::status_list::StatusList;
+    use crate::typed_message::TypedMessage;
+
+    /// Round trip of `value`, then rejection of the encoding truncated, with a trailing byte and in a message of
+    /// another tag or version, and no panic on random modifications
+    fn check_wire<T: WireMessage>(value: &T) {
+        let bytes = value.to_wire_bytes();
+        let decoded = T::from_wire_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_wire_bytes(), bytes);
+
+        for len in 0..bytes.len() {
+            assert!(T::from_wire_bytes(&bytes[..len]).is_err());
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(T::from_wire_bytes(&trailing).is_err());
+        // Trailing byte within the body
+        let mut in_body = trailing.clone();
+        in_body[2..HEADER_SIZE]
+            .copy_from_slice(&((bytes.len() - HEADER_SIZE + 1) as u64).to_be_bytes());
+        assert!(T::from_wire_bytes(&in_body).is_err());
+        let mut other = bytes.clone();
+        other[1] = other[1].wrapping_add(1);
+        assert!(T::from_wire_bytes(&other).is_err());
+        other = bytes.clone();
+        other[0] = WIRE_VERSION + 1;
+        assert!(T::from_wire_bytes(&other).is_err());
+        for fuzzed in fuzzed_bytes(&bytes, 50) {
+            // A modified encoding decodes only to a value encoded as it, as each value has one encoding
+            if let Ok(v) = T::from_wire_bytes(&fuzzed) {
+                assert_eq!(v.to_wire_bytes(), fuzzed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wire_format() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let (sk, vk) = keygen(count_msgs, &params).unwrap();
+        let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        check_wire(&params);
+        check_wire(&sk);
+        check_wire(&vk);
+        check_wire(&sig);
+
+        let (rss_sk, rss_pk) = rsskeygen(count_msgs, &params).unwrap();
+        let rss_sig = RSSignature::new(&msgs, &rss_sk, &params).unwrap();
+        let disclosed: HashSet<usize> = vec![0, 2].into_iter().collect();
+        let redacted = rss_sig.derive(&msgs, &disclosed, &rss_pk).unwrap();
+        check_wire(&rss_sk);
+        check_wire(&rss_pk);
+        check_wire(&rss_sig);
+        check_wire(&redacted);
+
+        let revealed: HashSet<usize> = vec![1].into_iter().collect();
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), None, revealed).unwrap();
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&challenge).unwrap();
+        check_wire(&proof);
+        check_wire(&proof.proof_vc);
+        check_wire(&ProofResponse {
+            proof: proof.clone(),
+            revealed_msgs: vec![(1, msgs[1].clone())].into_iter().collect(),
+        });
+        check_wire(&Credential {
+            signature: sig.clone(),
+            messages: msgs.clone(),
+            verkey: vk.clone(),
+        });
+
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(&params.g, None);
+        let vc = committing
+            .finish()
+            .gen_proof(&challenge, &[msgs[0].clone()])
+            .unwrap();
+        check_wire(&vc);
+        let blinding_key = BlindingKey::new(&sk, &params);
+        check_wire(&blinding_key);
+        let (request, _) = BlindIssuanceRequest::with_public_messages(
+            &msgs[..1],
+            &msgs[1..],
+            &blinding_key,
+            &params,
+            &[],
+        )
+        .unwrap();
+        check_wire(&request);
+        check_wire(&ProofOfPossession::new(&sk, &vk, &params).unwrap());
+
+        let mut text_msgs = msgs.clone();
+        text_msgs[0] = TypedMessage::Text(String::from("ACME")).encode();
+        let text_sig = Signature::new(&text_msgs, &sk, &params).unwrap();
+        check_wire(
+            &TextEqualityProof::new(&text_sig, &vk, &params, text_msgs, &[(0, "ACME")], &[])
+                .unwrap(),
+        );
+        let mut list = StatusList::new("list", 100);
+        list.revoke(3).unwrap();
+        let (list_sk, _) = keygen(1, &params).unwrap();
+        check_wire(&list.sign(1, &list_sk, &params).unwrap());
+
+        // Sets in another order, counts more than the remaining bytes and field elements not reduced
+        let limits = DeserializeLimits::default();
+        let mut w = WireWriter::default();
+        w.put_seq(2, &[2usize, 1]);
+        assert!(HashSet::<usize>::decode(&mut WireReader::new(&w.bytes, &limits)).is_err());
+        let mut w = WireWriter::default();
+        w.put_u64(u64::MAX);
+        w.put(&msgs[0]);
+        assert!(Vec::<FieldElement>::decode(&mut WireReader::new(&w.bytes, &limits)).is_err());
+        let not_reduced = [0xff; FieldElement_SIZE];
+        assert!(FieldElement::decode(&mut WireReader::new(&not_reduced, &limits)).is_err());
+
+        // Counts of messages over the limits
+        let limits = DeserializeLimits::new(count_msgs - 1, 1 << 16);
+        assert!(Verkey::from_wire_bytes_with_limits(&vk.to_wire_bytes(), &limits).is_err());
+        let credential = Credential {
+            signature: sig,
+            messages: msgs,
+            verkey: vk,
+        };
+        assert!(
+            Credential::from_wire_bytes_with_limits(&credential.to_wire_bytes(), &limits).is_err()
+        );
+        // A message of another type
+        assert!(Signature::from_wire_bytes(&rss_sig.to_wire_bytes()).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+// `#[derive(Credential)]` of a struct, run with the feature `derive`
+#![cfg(feature = "derive")]
+
+use ps_sig::credential_struct::{Credential, CredentialStruct, ToMessage};
+use ps_sig::errors::PSError;
+use ps_sig::integer_message::{encode_signed, encode_unsigned};
+use ps_sig::keys::{keygen, Params};
+use ps_sig::link_secret::LinkSecret;
+use ps_sig::presentation::FreshnessChallenge;
+use ps_sig::typed_message::TypedMessage;
+
+#[derive(Credential)]
+struct Passport {
+    #[link_secret]
+    link_secret: LinkSecret,
+    name: String,
+    #[non_redactable]
+    #[encoding(text)]
+    country: &'static str,
+    #[encoding(date)]
+    birthdate: i64,
+    #[encoding(unsigned)]
+    height_cm: u64,
+    #[encoding(signed)]
+    balance: i64,
+    verified: bool,
+}
+
+#[test]
+fn test_derive_credential() {
+    let params = Params::new("test".as_bytes());
+    let (sk, vk) = keygen(Passport::FIELDS.len(), &params).unwrap();
+    let passport = Passport {
+        link_secret: LinkSecret::new(),
+        name: String::from("Alice"),
+        country: "FR",
+        birthdate: 631_152_000,
+        height_cm: 170,
+        balance: -20,
+        verified: true,
+    };
+    let names: Vec<&str> = Passport::FIELDS.iter().map(|f| f.name).collect();
+    assert_eq!(
+        names,
+        vec![
+            "link_secret",
+            "name",
+            "country",
+            "birthdate",
+            "height_cm",
+            "balance",
+            "verified"
+        ]
+    );
+    assert!(Passport::FIELDS[0].link_secret && Passport::FIELDS[2].non_redactable);
+    let messages = passport.to_messages();
+    assert_eq!(messages[0], passport.link_secret.to_message());
+    assert_eq!(
+        messages[1],
+        TypedMessage::Text(String::from("Alice")).encode()
+    );
+    assert_eq!(messages[2], TypedMessage::Text(String::from("FR")).encode());
+    assert_eq!(messages[3], TypedMessage::Date(631_152_000).encode());
+    assert_eq!(messages[4], encode_unsigned(170));
+    assert_eq!(messages[5], encode_signed(-20));
+    assert_eq!(messages[6], TypedMessage::Bool(true).encode());
+
+    let sig = passport.sign(&sk, &params).unwrap();
+    assert!(passport.verify(&sig, &vk, &params).unwrap());
+    let now = 1_600_000_000;
+    let freshness = FreshnessChallenge::new(now);
+    let presentation = passport
+        .present(&sig, &vk, &params, &["height_cm"], freshness.clone())
+        .unwrap();
+    let verified =
+        Passport::verify_presentation(&presentation, &vk, &params, &freshness, now, 0).unwrap();
+    assert_eq!(verified.disclosed.len(), 2);
+    assert_eq!(
+        Passport::disclosed_field(&verified, "height_cm").unwrap(),
+        Some(&encode_unsigned(170))
+    );
+    match passport.present(&sig, &vk, &params, &["link_secret"], freshness) {
+        Err(PSError::LinkSecretRevealed { idx: 0 }) => (),
+        r => panic!("Expected LinkSecretRevealed but got {:?}", r.map(|_| ())),
+    }
+}
@@ -0,0 +1,140 @@
+// Coarse time budgets catching severe performance regressions, run with the feature `perf-tests`:
+// `cargo test --release --features perf-tests --test perf`. Budgets are for release builds on CI-class hardware
+// and are multiplied by the env variable `PS_SIG_PERF_SCALE` (e.g. 20 for debug builds or slow machines). Each
+// operation takes the median of a few runs. The scaling tests compare the times at n and 4n messages, a linear
+// operation taking about 4 times as long and a quadratic one 16 times, so they fail for an accidental O(n^2) loop
+// whatever the hardware.
+#![cfg(feature = "perf-tests")]
+
+use ps_sig::curve::{FieldElement, FieldElementVector};
+use ps_sig::keys::{keygen, rsskeygen, Params};
+use ps_sig::pok_sig::PoKOfSignature;
+use ps_sig::rss_signature::RSSignature;
+use ps_sig::signature::Signature;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 5;
+/// Largest ratio of the times at 4n and n messages of a linear operation
+const MAX_LINEAR_RATIO: f64 = 8.0;
+
+fn scale() -> f64 {
+    std::env::var("PS_SIG_PERF_SCALE")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(1.0)
+}
+
+fn median<F: FnMut()>(mut f: F) -> Duration {
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    times[RUNS / 2]
+}
+
+fn assert_within(name: &str, elapsed: Duration, budget_ms: u64) {
+    let budget = Duration::from_secs_f64(budget_ms as f64 * scale() / 1000.0);
+    assert!(
+        elapsed <= budget,
+        "{} took {:?}, over its budget of {:?}",
+        name,
+        elapsed,
+        budget
+    );
+}
+
+fn assert_linear(name: &str, at_n: Duration, at_4n: Duration) {
+    let ratio = at_4n.as_secs_f64() / at_n.as_secs_f64();
+    assert!(
+        ratio <= MAX_LINEAR_RATIO,
+        "{} took {:?} for n and {:?} for 4n messages, {:.1} times as long",
+        name,
+        at_n,
+        at_4n,
+        ratio
+    );
+}
+
+/// Time of creating and verifying a proof of knowledge of a signature on `count_msgs` messages, 1 revealed
+fn pok_time(count_msgs: usize, params: &Params) -> Duration {
+    let (sk, vk) = keygen(count_msgs, params).unwrap();
+    let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+    let sig = Signature::new(&msgs, &sk, params).unwrap();
+    let revealed: HashSet<usize> = vec![0].into_iter().collect();
+    let revealed_msgs: HashMap<usize, FieldElement> =
+        vec![(0, msgs[0].clone())].into_iter().collect();
+    median(|| {
+        let pok =
+            PoKOfSignature::init(&sig, &vk, params, msgs.clone(), None, revealed.clone()).unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        assert!(proof
+            .verify(&vk, params, revealed_msgs.clone(), &chal)
+            .unwrap());
+    })
+}
+
+#[test]
+fn perf_sign_verify() {
+    let params = Params::new("test".as_bytes());
+    let count_msgs = 10;
+    let (sk, vk) = keygen(count_msgs, &params).unwrap();
+    let msgs: Vec<FieldElement> = FieldElementVector::random(count_msgs).into();
+    let elapsed = median(|| {
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+    });
+    assert_within("Signing and verifying 10 messages", elapsed, 50);
+}
+
+#[test]
+fn perf_pok_sig() {
+    let params = Params::new("test".as_bytes());
+    assert_within(
+        "Proof of knowledge of signature on 10 messages",
+        pok_time(10, &params),
+        100,
+    );
+    assert_linear(
+        "Proof of knowledge of signature",
+        pok_time(25, &params),
+        pok_time(100, &params),
+    );
+}
+
+#[test]
+fn perf_rss() {
+    let params = Params::new("test".as_bytes());
+    let count_msgs = 10;
+    let (sk, pk) = rsskeygen(count_msgs, &params).unwrap();
+    let msgs = FieldElementVector::random(count_msgs);
+    let disclosed: HashSet<usize> = (0..count_msgs).filter(|i| i % 2 == 0).collect();
+    let disclosed_msgs: HashMap<usize, FieldElement> =
+        disclosed.iter().map(|i| (*i, msgs[*i].clone())).collect();
+    let elapsed = median(|| {
+        let sig = RSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let derived = sig.derive(msgs.as_slice(), &disclosed, &pk).unwrap();
+        assert!(derived.verify(&disclosed_msgs, &pk).unwrap());
+    });
+    assert_within(
+        "Signing, deriving and verifying a redactable signature on 10 messages",
+        elapsed,
+        250,
+    );
+
+    let keygen_time = |count_msgs: usize| {
+        median(|| {
+            rsskeygen(count_msgs, &params).unwrap();
+        })
+    };
+    assert_linear(
+        "Redactable signature key generation",
+        keygen_time(25),
+        keygen_time(100),
+    );
+}
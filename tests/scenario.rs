@@ -1,9 +1,9 @@
-use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
-use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
 use ps_sig::keys::{keygen, Params};
 use ps_sig::blind_signature::*;
 use ps_sig::pok_sig::*;
-use ps_sig::{VerkeyGroupVec, SignatureGroup};
+use ps_sig::SignatureGroup;
 use std::collections::{HashMap, HashSet};
 
 #[test]
@@ -14,7 +14,7 @@ fn test_scenario_1() {
     let count_msgs = 10;
     let count_blinded_msgs = 2;
     let params = Params::new("test".as_bytes());
-    let (sk, vk) = keygen(count_msgs, &params);
+    let (sk, vk) = keygen(count_msgs, &params).unwrap();
 
     let blinding_key = BlindingKey::new(&sk, &params);
     let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
@@ -22,18 +22,18 @@ fn test_scenario_1() {
 
     // User commits to some messages
     let mut comm = SignatureGroup::new();
-    for i in 0..count_blinded_msgs {
-        comm += (&blinding_key.Y[i] * &msgs[i]);
+    for (y, m) in blinding_key.Y.iter().zip(msgs.iter()).take(count_blinded_msgs) {
+        comm += y * m;
     }
-    comm += (&params.g * &blinding);
+    comm += &params.g * &blinding;
 
     {
         // User and signer engage in a proof of knowledge for the above commitment `comm`
         let mut bases = Vec::<SignatureGroup>::new();
         let mut hidden_msgs = Vec::<FieldElement>::new();
-        for i in 0..count_blinded_msgs {
-            bases.push(blinding_key.Y[i].clone());
-            hidden_msgs.push(msgs[i].clone());
+        for (y, m) in blinding_key.Y.iter().zip(msgs.iter()).take(count_blinded_msgs) {
+            bases.push(y.clone());
+            hidden_msgs.push(m.clone());
         }
         bases.push(params.g.clone());
         hidden_msgs.push(blinding.clone());
@@ -87,7 +87,7 @@ fn test_scenario_1() {
 
     let mut revealed_msgs = HashMap::new();
     for i in &revealed_msg_indices {
-        revealed_msgs.insert(i.clone(), msgs[*i].clone());
+        revealed_msgs.insert(*i, msgs[*i].clone());
     }
     assert!(proof.verify(&vk, &params, revealed_msgs.clone(), &chal).unwrap());
 }